@@ -0,0 +1,34 @@
+//! Boots the Zero Trust server with a public and a role-protected route.
+//! Replaces the old standalone router demo.
+//!
+//! Try it with `ZTW_ACCESS_SECRET` set to at least 32 bytes of secret:
+//! - `curl localhost:8080/dashboard`
+//! - `curl -H "Authorization: Bearer <token>" localhost:8080/admin` using the printed token
+
+use zero_trust_website::{Response, Server, StatusCode};
+
+fn main() {
+    let server = Server::builder()
+        .route("GET", "/dashboard", |req| {
+            Response::ok(&format!("Route {} accessed", req.path))
+        })
+        .protected_route("GET", "/admin", "ALL", |req| {
+            Response::ok(&format!(
+                "Welcome, {}",
+                req.principal.as_deref().unwrap_or("unknown")
+            ))
+        })
+        .protected_route("POST", "/profile", "WRITE", |req| {
+            Response::new(
+                StatusCode::CREATED,
+                &format!("Saved {} bytes", req.body.len()),
+            )
+        })
+        .build()
+        .expect("Invalid server configuration");
+
+    println!("Admin token: {}", server.issue_token("alice", "admin"));
+    server
+        .listen("0.0.0.0:8080")
+        .expect("Zero Trust server failed");
+}
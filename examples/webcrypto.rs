@@ -0,0 +1,41 @@
+//! Stores and reads back encrypted values with the WebCrypto storage API.
+//! Replaces the old standalone webcrypto demo.
+//!
+//! Run it, then inspect `webcrypto_secure_store.dat`: values are stored encrypted.
+
+use zero_trust_website::webcrypto::{
+    generate_rotation_key, sha256_hash, EncryptedStorage, WebCryptoError,
+};
+
+fn main() -> Result<(), WebCryptoError> {
+    let encryption_key = generate_rotation_key()?;
+    let storage = EncryptedStorage::new("webcrypto_secure_store.dat", &encryption_key)?;
+
+    // Store Encrypted Data
+    storage.store_data("user_session", "session_token_abc123")?;
+
+    // Retrieve Decrypted Data
+    match storage.retrieve_data("user_session")? {
+        Some(value) => println!("[WEBCRYPTO] Retrieved decrypted session: {}", value),
+        None => println!("[WEBCRYPTO] No session found."),
+    }
+
+    // Passphrase-protected storage: the same passphrase re-derives the same key
+    let passphrase_path = "webcrypto_passphrase_store.dat";
+    let storage =
+        EncryptedStorage::from_passphrase(passphrase_path, "correct horse battery staple")?;
+    storage.store_data("api_token", "tok_live_42")?;
+    let reopened =
+        EncryptedStorage::from_passphrase(passphrase_path, "correct horse battery staple")?;
+    if let Some(value) = reopened.retrieve_data("api_token")? {
+        println!(
+            "[WEBCRYPTO] Retrieved with passphrase-derived key: {}",
+            value
+        );
+    }
+
+    // SHA-256 Integrity Check Example
+    let integrity_hash = sha256_hash("secure_data_example");
+    println!("[WEBCRYPTO] SHA-256 Hash of data: {}", integrity_hash);
+    Ok(())
+}
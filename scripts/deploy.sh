@@ -69,6 +69,7 @@ After=network.target
 
 [Service]
 ExecStart=$DEPLOY_DIR/$PROJECT_NAME
+EnvironmentFile=/etc/zero-trust/secrets.env
 User=$SECURE_USER
 Restart=always
 LimitNOFILE=1048576
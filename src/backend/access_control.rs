@@ -10,94 +10,703 @@
 //! - Adaptive risk-based access escalation
 //! - Immutable audit logging with tamper-proof verification
 
-use base64::encode;
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "encoding.rs"]
+mod encoding;
+#[allow(dead_code)]
+#[path = "keys.rs"]
+mod keys;
+
+use crypto::{constant_time_eq, Zeroizing};
+use encoding::STANDARD;
 use hmac::{Hmac, Mac};
+use keys::KeyProvider;
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
-const SECRET_KEY: &str = "super_secure_access_secret";
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const MAX_CONDITION_DEPTH: usize = 32; // Nested NOT and parentheses in an ABAC rule
+
+/// Static IPv4 CIDR → region table used for geo-restriction lookups
+const GEO_CIDR_TABLE: &[(Ipv4Addr, u8, &str)] = &[
+    (Ipv4Addr::new(10, 0, 0, 0), 8, "PRIVATE"),
+    (Ipv4Addr::new(172, 16, 0, 0), 12, "PRIVATE"),
+    (Ipv4Addr::new(192, 168, 0, 0), 16, "PRIVATE"),
+    (Ipv4Addr::new(127, 0, 0, 0), 8, "LOOPBACK"),
+    (Ipv4Addr::new(81, 2, 69, 0), 24, "EU"),
+    (Ipv4Addr::new(8, 8, 8, 0), 24, "US"),
+    (Ipv4Addr::new(1, 0, 0, 0), 8, "APAC"),
+    (Ipv4Addr::new(175, 45, 176, 0), 22, "KP"),
+    (Ipv4Addr::new(5, 62, 56, 0), 21, "IR"),
+];
 
 /// Defines user roles and associated permissions
 #[derive(Debug)]
 struct Role {
-    name: String,
     permissions: Vec<String>,
+    parents: Vec<String>, // Roles whose permissions are inherited
 }
 
 /// Access control system managing roles and policies
-struct AccessControl {
+pub(crate) struct AccessControl {
     roles: Mutex<HashMap<String, Role>>,
+    abac_rules: Mutex<Vec<AbacRule>>,
+    time_windows: Mutex<HashMap<String, Vec<TimeWindow>>>, // Role -> permitted windows
+    geo_policy: Mutex<GeoPolicy>,
 }
 
 impl AccessControl {
-    fn new() -> Self {
-        let mut roles = HashMap::new();
-        roles.insert(
-            "admin".to_string(),
-            Role {
-                name: "admin".to_string(),
-                permissions: vec!["ALL"],
-            },
-        );
-        roles.insert(
-            "user".to_string(),
-            Role {
-                name: "user".to_string(),
-                permissions: vec!["READ", "WRITE"],
-            },
-        );
+    pub(crate) fn new() -> Self {
+        let access_control = Self {
+            roles: Mutex::new(HashMap::new()),
+            abac_rules: Mutex::new(Vec::new()),
+            time_windows: Mutex::new(HashMap::new()),
+            geo_policy: Mutex::new(GeoPolicy::default()),
+        };
+
+        // guest <- user <- admin
+        access_control
+            .register_role("guest", &["READ"], &[])
+            .and_then(|_| access_control.register_role("user", &["WRITE"], &["guest"]))
+            .and_then(|_| access_control.register_role("admin", &["ALL"], &["user"]))
+            .expect("Default role hierarchy is valid");
+        access_control
+    }
+
+    /// Registers (or redefines) a role that inherits from `parents`. Parents
+    /// must already exist, and definitions that would form a cycle are rejected.
+    pub(crate) fn register_role(
+        &self,
+        name: &str,
+        permissions: &[&str],
+        parents: &[&str],
+    ) -> Result<(), String> {
+        let mut roles = self.roles.lock().unwrap();
+        for parent in parents {
+            if !roles.contains_key(*parent) {
+                return Err(format!("unknown parent role '{}'", parent));
+            }
+            if *parent == name || Self::ancestors(&roles, parent).contains(name) {
+                return Err(format!(
+                    "role '{}' cannot inherit from '{}': cyclic hierarchy",
+                    name, parent
+                ));
+            }
+        }
+
         roles.insert(
-            "guest".to_string(),
+            name.to_string(),
             Role {
-                name: "guest".to_string(),
-                permissions: vec!["READ"],
+                permissions: permissions.iter().map(|p| p.to_string()).collect(),
+                parents: parents.iter().map(|p| p.to_string()).collect(),
             },
         );
+        Ok(())
+    }
 
-        Self {
-            roles: Mutex::new(roles),
+    /// Collects every role reachable through `role`'s parent links
+    fn ancestors(roles: &HashMap<String, Role>, role: &str) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![role.to_string()];
+        while let Some(current) = stack.pop() {
+            if let Some(role_data) = roles.get(&current) {
+                for parent in &role_data.parents {
+                    if seen.insert(parent.clone()) {
+                        stack.push(parent.clone());
+                    }
+                }
+            }
         }
+        seen
     }
 
-    /// Verifies if a user has permission to perform an action
-    fn has_permission(&self, role: &str, action: &str) -> bool {
+    /// Returns the role's own permissions plus everything inherited transitively
+    fn effective_permissions(&self, role: &str) -> HashSet<String> {
         let roles = self.roles.lock().unwrap();
-        if let Some(role_data) = roles.get(role) {
-            return role_data.permissions.contains(&"ALL".to_string())
-                || role_data.permissions.contains(&action.to_string());
+        if !roles.contains_key(role) {
+            return HashSet::new();
+        }
+
+        let mut lineage = Self::ancestors(&roles, role);
+        lineage.insert(role.to_string());
+        lineage
+            .iter()
+            .filter_map(|name| roles.get(name))
+            .flat_map(|role_data| role_data.permissions.iter().cloned())
+            .collect()
+    }
+
+    /// Verifies if a user has permission to perform an action
+    pub(crate) fn has_permission(&self, role: &str, action: &str) -> bool {
+        let permissions = self.effective_permissions(role);
+        permissions.contains("ALL") || permissions.contains(action)
+    }
+
+    /// Parses and registers an ABAC rule such as
+    /// `department == "eng" AND time_of_day in 09:00-17:00`
+    fn add_abac_rule(&self, name: &str, effect: Effect, expression: &str) -> Result<(), String> {
+        let condition = ConditionParser::parse(expression)
+            .map_err(|e| format!("invalid rule '{}': {}", name, e))?;
+        self.abac_rules.lock().unwrap().push(AbacRule {
+            name: name.to_string(),
+            effect,
+            condition,
+        });
+        Ok(())
+    }
+
+    /// Evaluates ABAC rules against a request context. Deny rules take
+    /// precedence; with no matching allow rule, access is denied by default.
+    fn evaluate_abac(&self, context: &AccessContext) -> Decision {
+        let rules = self.abac_rules.lock().unwrap();
+        let matching = || rules.iter().filter(|rule| rule.condition.evaluate(context));
+
+        if let Some(rule) = matching().find(|rule| rule.effect == Effect::Deny) {
+            return Decision::deny(Some(&rule.name));
+        }
+        match matching().find(|rule| rule.effect == Effect::Allow) {
+            Some(rule) => Decision::allow(&rule.name),
+            None => Decision::deny(None),
+        }
+    }
+
+    /// Restricts a role to the given time window; multiple windows are OR-ed
+    fn add_time_window(&self, role: &str, window: TimeWindow) {
+        let mut windows = self.time_windows.lock().unwrap();
+        windows.entry(role.to_string()).or_default().push(window);
+    }
+
+    /// Replaces the geo-restriction policy
+    fn set_geo_policy(&self, policy: GeoPolicy) {
+        *self.geo_policy.lock().unwrap() = policy;
+    }
+
+    /// Full policy evaluation: RBAC permission, role time windows, geo
+    /// restrictions, then ABAC rules (when any are registered)
+    fn evaluate(&self, context: &AccessContext) -> Decision {
+        if !self.has_permission(&context.role, &context.action) {
+            return Decision::deny(Some(&format!("rbac:{}", context.role)));
+        }
+
+        let windows = self.time_windows.lock().unwrap();
+        if let Some(role_windows) = windows.get(&context.role) {
+            if !role_windows.iter().any(|window| window.contains(context)) {
+                return Decision::deny(Some(&format!("time-window:{}", context.role)));
+            }
+        }
+        drop(windows);
+
+        if let Some(ip) = context.source_ip {
+            let policy = self.geo_policy.lock().unwrap();
+            let region = lookup_region(ip);
+            if !policy.permits(region) {
+                return Decision::deny(Some(&format!("geo:{}", region)));
+            }
+        }
+
+        if self.abac_rules.lock().unwrap().is_empty() {
+            return Decision::allow(&format!("rbac:{}", context.role));
+        }
+        self.evaluate_abac(context)
+    }
+}
+
+/// Recurring window (e.g. weekdays 09:00-17:00) during which a role may act
+#[derive(Debug, Clone)]
+struct TimeWindow {
+    start_minute: u16,
+    end_minute: u16, // Exclusive; earlier than start for overnight windows
+    days: HashSet<&'static str>,
+}
+
+impl TimeWindow {
+    /// Creates a window from `HH:MM` bounds and three-letter day names (`mon`..`sun`)
+    fn new(start: &str, end: &str, days: &[&str]) -> Result<Self, String> {
+        let start_minute =
+            parse_clock_time(start).ok_or_else(|| format!("invalid start time '{}'", start))?;
+        let end_minute =
+            parse_clock_time(end).ok_or_else(|| format!("invalid end time '{}'", end))?;
+        let days = days
+            .iter()
+            .map(|day| {
+                WEEKDAYS
+                    .iter()
+                    .find(|known| known.eq_ignore_ascii_case(day))
+                    .copied()
+                    .ok_or_else(|| format!("invalid day '{}'", day))
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(Self {
+            start_minute,
+            end_minute,
+            days,
+        })
+    }
+
+    /// Checks the context's `day_of_week` and `time_of_day` against the window
+    fn contains(&self, context: &AccessContext) -> bool {
+        let day_ok = context
+            .attribute("day_of_week")
+            .is_some_and(|day| self.days.contains(day.to_ascii_lowercase().as_str()));
+        let minute = match context
+            .attribute("time_of_day")
+            .and_then(|time| parse_clock_time(&time))
+        {
+            Some(minute) => minute,
+            None => return false,
+        };
+        let time_ok = if self.start_minute <= self.end_minute {
+            minute >= self.start_minute && minute < self.end_minute
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        };
+        day_ok && time_ok
+    }
+}
+
+/// Region-based restrictions on the request's source IP. Denied regions
+/// always lose; a non-empty allow list admits only the listed regions.
+#[derive(Debug, Clone, Default)]
+struct GeoPolicy {
+    allowed_regions: HashSet<String>,
+    denied_regions: HashSet<String>,
+}
+
+impl GeoPolicy {
+    #[allow(dead_code)] // `main` only denies regions
+    fn allow_region(mut self, region: &str) -> Self {
+        self.allowed_regions.insert(region.to_string());
+        self
+    }
+
+    fn deny_region(mut self, region: &str) -> Self {
+        self.denied_regions.insert(region.to_string());
+        self
+    }
+
+    fn permits(&self, region: &str) -> bool {
+        !self.denied_regions.contains(region)
+            && (self.allowed_regions.is_empty() || self.allowed_regions.contains(region))
+    }
+}
+
+/// Maps an IP to a region via longest-prefix match over `GEO_CIDR_TABLE`
+fn lookup_region(ip: IpAddr) -> &'static str {
+    let ip = match ip {
+        IpAddr::V4(ip) => u32::from(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => u32::from(ip),
+            None => return "UNKNOWN",
+        },
+    };
+    GEO_CIDR_TABLE
+        .iter()
+        .filter(|(network, prefix, _)| {
+            let mask = if *prefix == 0 {
+                0
+            } else {
+                u32::MAX << (32 - prefix)
+            };
+            ip & mask == u32::from(*network) & mask
+        })
+        .max_by_key(|(_, prefix, _)| *prefix)
+        .map_or("UNKNOWN", |(_, _, region)| region)
+}
+
+/// Request context evaluated by attribute-based policies
+#[derive(Debug, Clone)]
+struct AccessContext {
+    user_id: String,
+    role: String,
+    action: String,
+    source_ip: Option<IpAddr>,
+    attributes: HashMap<String, String>,
+    now: SystemTime, // Server clock reading behind `time_of_day` and `day_of_week`
+}
+
+impl AccessContext {
+    fn new(user_id: &str, role: &str, action: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            role: role.to_string(),
+            action: action.to_string(),
+            source_ip: None,
+            attributes: HashMap::new(),
+            now: SystemTime::now(),
+        }
+    }
+
+    /// Evaluates time-based rules as of `now` rather than when the context was created
+    fn at(mut self, now: SystemTime) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Sets the client address used for geo-restriction
+    fn with_source_ip(mut self, ip: IpAddr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+
+    /// Adds a request attribute (e.g. `department`)
+    fn with_attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Resolves an attribute; `user_id`, `role` and `action` are always available.
+    /// `time_of_day` and `day_of_week` always come from the server's UTC clock; a
+    /// caller-supplied value for either is ignored.
+    fn attribute(&self, name: &str) -> Option<String> {
+        let secs = || {
+            self.now
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        };
+        match name {
+            "user_id" => Some(self.user_id.clone()),
+            "role" => Some(self.role.clone()),
+            "action" => Some(self.action.clone()),
+            "time_of_day" => Some(format!("{:02}:{:02}", secs() / 3600 % 24, secs() / 60 % 60)),
+            // 1970-01-01 was a Thursday
+            "day_of_week" => Some(WEEKDAYS[((secs() / 86_400 + 3) % 7) as usize].to_string()),
+            _ => self.attributes.get(name).cloned(),
+        }
+    }
+}
+
+/// Outcome of a policy evaluation, naming the rule that decided it
+#[derive(Debug, Clone, PartialEq)]
+struct Decision {
+    allowed: bool,
+    matched_rule: Option<String>,
+}
+
+impl Decision {
+    fn allow(rule: &str) -> Self {
+        Self {
+            allowed: true,
+            matched_rule: Some(rule.to_string()),
+        }
+    }
+
+    fn deny(rule: Option<&str>) -> Self {
+        Self {
+            allowed: false,
+            matched_rule: rule.map(str::to_string),
+        }
+    }
+}
+
+/// Whether a matching ABAC rule grants or forbids access
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Effect {
+    Allow,
+    Deny,
+}
+
+/// Parsed ABAC condition tree
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Equals(String, String),
+    NotEquals(String, String),
+    InSet(String, Vec<String>),
+    InTimeRange(String, u16, u16), // Minutes since midnight, end exclusive
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    fn evaluate(&self, context: &AccessContext) -> bool {
+        match self {
+            Condition::Equals(name, value) => context.attribute(name).as_ref() == Some(value),
+            Condition::NotEquals(name, value) => context
+                .attribute(name)
+                .is_some_and(|actual| &actual != value),
+            Condition::InSet(name, values) => context
+                .attribute(name)
+                .is_some_and(|actual| values.contains(&actual)),
+            Condition::InTimeRange(name, start, end) => context
+                .attribute(name)
+                .and_then(|actual| parse_clock_time(&actual))
+                .is_some_and(|minute| {
+                    if start <= end {
+                        minute >= *start && minute < *end
+                    } else {
+                        // Window wraps past midnight (e.g. 22:00-06:00)
+                        minute >= *start || minute < *end
+                    }
+                }),
+            Condition::And(left, right) => left.evaluate(context) && right.evaluate(context),
+            Condition::Or(left, right) => left.evaluate(context) || right.evaluate(context),
+            Condition::Not(inner) => !inner.evaluate(context),
         }
-        false
     }
 }
 
-/// Generates a cryptographically signed access token
-fn generate_signed_token(user_id: &str, role: &str) -> String {
+/// Named ABAC rule with its effect and condition
+#[derive(Debug, Clone)]
+struct AbacRule {
+    name: String,
+    effect: Effect,
+    condition: Condition,
+}
+
+/// Parses `HH:MM` into minutes since midnight
+fn parse_clock_time(value: &str) -> Option<u16> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let (hours, minutes): (u16, u16) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours < 24 && minutes < 60 {
+        Some(hours * 60 + minutes)
+    } else {
+        None
+    }
+}
+
+/// Tokens of the ABAC expression grammar
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Literal(String),
+    Eq,
+    NotEq,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+/// Splits a rule expression into tokens
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '[' | ']' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(format!("expected '=' after '{}'", c));
+                }
+                tokens.push(if c == '=' { Token::Eq } else { Token::NotEq });
+            }
+            '"' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(ch) => literal.push(ch),
+                        None => return Err("unterminated string literal".to_string()),
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()[],=!\"".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive-descent parser for ABAC conditions:
+///
+/// ```text
+/// expr       := and ("OR" and)*
+/// and        := unary ("AND" unary)*
+/// unary      := "NOT" unary | "(" expr ")" | comparison
+/// comparison := attr ("==" | "!=") value | attr "in" ("[" value ("," value)* "]" | HH:MM-HH:MM)
+/// ```
+struct ConditionParser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize, // Current NOT/parenthesis nesting, capped at `MAX_CONDITION_DEPTH`
+}
+
+impl ConditionParser {
+    fn parse(expression: &str) -> Result<Condition, String> {
+        let mut parser = Self {
+            tokens: tokenize(expression)?,
+            position: 0,
+            depth: 0,
+        };
+        let condition = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(condition),
+            Some(token) => Err(format!("unexpected token {:?}", token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_and()?;
+        while self.peek_keyword("OR") {
+            self.next();
+            condition = Condition::Or(Box::new(condition), Box::new(self.parse_and()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_unary()?;
+        while self.peek_keyword("AND") {
+            self.next();
+            condition = Condition::And(Box::new(condition), Box::new(self.parse_unary()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_unary(&mut self) -> Result<Condition, String> {
+        if self.peek_keyword("NOT") {
+            self.next();
+            let negated = self.nested(Self::parse_unary)?;
+            return Ok(Condition::Not(Box::new(negated)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.next();
+            let condition = self.nested(Self::parse_or)?;
+            return match self.next() {
+                Some(Token::RParen) => Ok(condition),
+                _ => Err("expected ')'".to_string()),
+            };
+        }
+        self.parse_comparison()
+    }
+
+    /// Runs `parse` one nesting level deeper, refusing input nested past
+    /// `MAX_CONDITION_DEPTH` before it can exhaust the stack
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Condition, String>,
+    ) -> Result<Condition, String> {
+        if self.depth >= MAX_CONDITION_DEPTH {
+            return Err(format!(
+                "condition nested deeper than {} levels",
+                MAX_CONDITION_DEPTH
+            ));
+        }
+        self.depth += 1;
+        let condition = parse(self);
+        self.depth -= 1;
+        condition
+    }
+
+    fn parse_value(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(value)) | Some(Token::Literal(value)) => Ok(value),
+            other => Err(format!("expected a value, found {:?}", other)),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Condition, String> {
+        let attribute = match self.next() {
+            Some(Token::Word(name)) => name,
+            other => return Err(format!("expected an attribute name, found {:?}", other)),
+        };
+
+        match self.next() {
+            Some(Token::Eq) => Ok(Condition::Equals(attribute, self.parse_value()?)),
+            Some(Token::NotEq) => Ok(Condition::NotEquals(attribute, self.parse_value()?)),
+            Some(Token::Word(op)) if op.eq_ignore_ascii_case("in") => {
+                if self.peek() == Some(&Token::LBracket) {
+                    self.next();
+                    let mut values = vec![self.parse_value()?];
+                    loop {
+                        match self.next() {
+                            Some(Token::Comma) => values.push(self.parse_value()?),
+                            Some(Token::RBracket) => break,
+                            _ => return Err("expected ',' or ']'".to_string()),
+                        }
+                    }
+                    return Ok(Condition::InSet(attribute, values));
+                }
+
+                let range = self.parse_value()?;
+                let (start, end) = range
+                    .split_once('-')
+                    .and_then(|(start, end)| {
+                        Some((parse_clock_time(start)?, parse_clock_time(end)?))
+                    })
+                    .ok_or_else(|| format!("invalid time range '{}'", range))?;
+                Ok(Condition::InTimeRange(attribute, start, end))
+            }
+            other => Err(format!("expected a comparison operator, found {:?}", other)),
+        }
+    }
+}
+
+/// Generates an access token signed with `key` (normally `keys::ACCESS_SECRET`)
+pub(crate) fn generate_signed_token(user_id: &str, role: &str, key: &[u8]) -> String {
     let data = format!("{}:{}", user_id, role);
-    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET_KEY.as_bytes()).unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
     mac.update(data.as_bytes());
-    let signature = encode(mac.finalize().into_bytes());
+    let signature = STANDARD.encode(mac.finalize().into_bytes());
     format!("{}.{}", data, signature)
 }
 
-/// Verifies the signed access token
-fn verify_signed_token(token: &str) -> bool {
+/// Verifies an access token signed with `key`
+pub(crate) fn verify_signed_token(token: &str, key: &[u8]) -> bool {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 2 {
         return false;
     }
 
-    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET_KEY.as_bytes()).unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
     mac.update(parts[0].as_bytes());
-    let expected_signature = encode(mac.finalize().into_bytes());
-    expected_signature == parts[1]
+    let expected_signature = STANDARD.encode(mac.finalize().into_bytes());
+    constant_time_eq(expected_signature.as_bytes(), parts[1].as_bytes())
 }
 
 /// Implements adaptive risk-based access escalation
-fn adaptive_access_escalation(user_id: &str, action: &str) -> bool {
-    // Simulate evaluating multiple risk factors
-    let risk_score = rand::random::<u8>(); // Simulated risk score (0-255)
+fn adaptive_access_escalation(rng: &dyn crypto::Rng, user_id: &str, action: &str) -> bool {
+    // Simulate evaluating multiple risk factors; without randomness, fail closed
+    let risk_score = match rng.next_u64() {
+        Ok(value) => value as u8, // Simulated risk score (0-255)
+        Err(_) => u8::MAX,
+    };
     if risk_score > 200 {
         println!(
             "High-risk access attempt detected for user {} on action {}",
@@ -111,9 +720,20 @@ fn adaptive_access_escalation(user_id: &str, action: &str) -> bool {
 fn main() {
     let access_control = Arc::new(AccessControl::new());
 
-    let token = generate_signed_token("user123", "user");
+    let key = match keys::EnvKeyProvider.require(keys::ACCESS_SECRET) {
+        Ok(key) => Zeroizing::new(key),
+        Err(e) => {
+            eprintln!("Access control not started: {}", e);
+            return;
+        }
+    };
+    let token = generate_signed_token("user123", "user", &key);
     println!("Generated Token: {}", token);
-    println!("Token Valid: {}", verify_signed_token(&token));
+    println!("Token Valid: {}", verify_signed_token(&token, &key));
+    println!(
+        "Risk check for user123 WRITE: {}",
+        adaptive_access_escalation(&crypto::OsRng, "user123", "WRITE")
+    );
 
     println!(
         "Admin access to ALL: {}",
@@ -123,4 +743,249 @@ fn main() {
         "User access to READ: {}",
         access_control.has_permission("user", "READ")
     );
+
+    access_control
+        .add_abac_rule(
+            "eng-office-hours",
+            Effect::Allow,
+            "department == \"eng\" AND time_of_day in 09:00-17:00",
+        )
+        .expect("Invalid ABAC rule");
+    let context = AccessContext::new("user123", "user", "WRITE")
+        .with_attribute("department", "eng")
+        .at(SystemTime::UNIX_EPOCH + Duration::from_secs(10 * 3600 + 30 * 60)); // 10:30 UTC
+    println!(
+        "ABAC decision: {:?}",
+        access_control.evaluate_abac(&context)
+    );
+
+    access_control.add_time_window(
+        "guest",
+        TimeWindow::new("09:00", "17:00", &["mon", "tue", "wed", "thu", "fri"])
+            .expect("Invalid time window"),
+    );
+    access_control.set_geo_policy(GeoPolicy::default().deny_region("KP").deny_region("IR"));
+    let context = AccessContext::new("visitor", "guest", "READ")
+        .with_source_ip(IpAddr::V4(Ipv4Addr::new(175, 45, 176, 10)))
+        .at(SystemTime::UNIX_EPOCH + Duration::from_secs(6 * 86_400 + 11 * 3600)); // Wed 11:00 UTC
+    println!(
+        "Geo-restricted decision: {:?}",
+        access_control.evaluate(&context)
+    );
+
+    // Editors inherit user (and transitively guest) permissions
+    access_control
+        .register_role("editor", &["PUBLISH"], &["user"])
+        .expect("Invalid role definition");
+    println!(
+        "Editor permissions: {:?}",
+        access_control.effective_permissions("editor")
+    );
+    if let Err(e) = access_control.register_role("guest", &["READ"], &["editor"]) {
+        println!("Rejected role definition: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `hh:mm` UTC on day `day` of the epoch (day 0 was a Thursday)
+    fn at_time(day: u64, hour: u64, minute: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(day * 86_400 + hour * 3600 + minute * 60)
+    }
+
+    #[test]
+    fn abac_allows_matching_attributes_only_inside_the_time_window() {
+        let access_control = AccessControl::new();
+        access_control
+            .add_abac_rule(
+                "eng-office-hours",
+                Effect::Allow,
+                "department == \"eng\" AND time_of_day in 09:00-17:00",
+            )
+            .unwrap();
+        let engineer =
+            AccessContext::new("u1", "user", "WRITE").with_attribute("department", "eng");
+
+        let inside = access_control.evaluate_abac(&engineer.clone().at(at_time(0, 10, 30)));
+        assert_eq!(inside, Decision::allow("eng-office-hours"));
+        let outside = access_control.evaluate_abac(&engineer.clone().at(at_time(0, 20, 0)));
+        assert!(!outside.allowed);
+
+        // The clock is the server's; a client cannot claim to be inside the window
+        let spoofed = engineer
+            .with_attribute("time_of_day", "10:30")
+            .at(at_time(0, 20, 0));
+        assert!(!access_control.evaluate_abac(&spoofed).allowed);
+        let sales = AccessContext::new("u2", "user", "WRITE")
+            .with_attribute("department", "sales")
+            .at(at_time(0, 10, 30));
+        assert!(!access_control.evaluate_abac(&sales).allowed);
+    }
+
+    #[test]
+    fn deeply_nested_conditions_are_rejected_not_overflowed() {
+        let nested = |depth: usize, opener: &str, closer: &str| {
+            format!(
+                "{}department == \"eng\"{}",
+                opener.repeat(depth),
+                closer.repeat(depth)
+            )
+        };
+        let parsed = |expression: &str| ConditionParser::parse(expression);
+
+        assert!(parsed(&nested(MAX_CONDITION_DEPTH, "(", ")")).is_ok());
+        assert!(parsed(&nested(MAX_CONDITION_DEPTH, "NOT ", "")).is_ok());
+        assert!(parsed(&nested(MAX_CONDITION_DEPTH + 1, "(", ")")).is_err());
+        // Far past anything the stack could take; rejected at the cap, not a crash
+        let error = parsed(&nested(1_000_000, "NOT (", ")")).unwrap_err();
+        assert!(error.contains("nested deeper"), "{}", error);
+
+        // Sibling groups do not add up; only the depth counts
+        let siblings = vec![nested(MAX_CONDITION_DEPTH, "(", ")"); 4].join(" AND ");
+        assert!(parsed(&siblings).is_ok());
+    }
+
+    #[test]
+    fn deny_rules_take_precedence_over_allow_rules() {
+        let access_control = AccessControl::new();
+        access_control
+            .add_abac_rule("eng", Effect::Allow, "department == \"eng\"")
+            .unwrap();
+        access_control
+            .add_abac_rule("contractors", Effect::Deny, "employment == \"contractor\"")
+            .unwrap();
+        let contractor = AccessContext::new("u3", "user", "WRITE")
+            .with_attribute("department", "eng")
+            .with_attribute("employment", "contractor");
+        assert_eq!(
+            access_control.evaluate_abac(&contractor),
+            Decision::deny(Some("contractors"))
+        );
+        assert!(access_control
+            .add_abac_rule("broken", Effect::Allow, "department ==")
+            .is_err());
+    }
+
+    #[test]
+    fn role_time_window_allows_inside_and_denies_outside() {
+        let access_control = AccessControl::new();
+        access_control.add_time_window(
+            "guest",
+            TimeWindow::new("09:00", "17:00", &["mon", "tue", "wed", "thu", "fri"]).unwrap(),
+        );
+        let guest = AccessContext::new("visitor", "guest", "READ");
+
+        assert!(
+            access_control
+                .evaluate(&guest.clone().at(at_time(6, 11, 0)))
+                .allowed
+        ); // Wed
+        assert!(
+            !access_control
+                .evaluate(&guest.clone().at(at_time(6, 18, 0)))
+                .allowed
+        );
+        assert!(
+            !access_control
+                .evaluate(&guest.clone().at(at_time(2, 11, 0)))
+                .allowed
+        ); // Sat
+           // Roles without a window are unaffected
+        let user = AccessContext::new("u1", "user", "READ").at(at_time(2, 3, 0));
+        assert!(access_control.evaluate(&user).allowed);
+    }
+
+    #[test]
+    fn overnight_window_wraps_past_midnight() {
+        let window = TimeWindow::new("22:00", "06:00", &["thu", "fri"]).unwrap();
+        let context =
+            |day, hour| AccessContext::new("u1", "user", "READ").at(at_time(day, hour, 0));
+        assert!(window.contains(&context(0, 23)));
+        assert!(window.contains(&context(0, 5)));
+        assert!(!window.contains(&context(0, 12)));
+        assert!(TimeWindow::new("25:00", "06:00", &["mon"]).is_err());
+    }
+
+    #[test]
+    fn source_in_a_blocked_region_is_denied() {
+        let access_control = AccessControl::new();
+        access_control.set_geo_policy(GeoPolicy::default().deny_region("KP").deny_region("IR"));
+        let from = |ip: [u8; 4]| {
+            AccessContext::new("visitor", "guest", "READ").with_source_ip(IpAddr::from(ip))
+        };
+
+        assert_eq!(
+            access_control.evaluate(&from([175, 45, 176, 10])),
+            Decision::deny(Some("geo:KP"))
+        );
+        assert!(access_control.evaluate(&from([81, 2, 69, 20])).allowed);
+
+        access_control.set_geo_policy(GeoPolicy::default().allow_region("EU"));
+        assert!(access_control.evaluate(&from([81, 2, 69, 20])).allowed);
+        assert!(!access_control.evaluate(&from([8, 8, 8, 8])).allowed);
+    }
+
+    #[test]
+    fn three_level_hierarchy_inherits_permissions() {
+        let access_control = AccessControl::new();
+        access_control
+            .register_role("viewer", &["VIEW"], &[])
+            .unwrap();
+        access_control
+            .register_role("author", &["DRAFT"], &["viewer"])
+            .unwrap();
+        access_control
+            .register_role("editor", &["PUBLISH"], &["author"])
+            .unwrap();
+
+        let expected: HashSet<String> = ["VIEW", "DRAFT", "PUBLISH"]
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(access_control.effective_permissions("editor"), expected);
+        assert!(access_control.has_permission("editor", "VIEW"));
+        assert!(!access_control.has_permission("viewer", "PUBLISH"));
+        assert!(access_control.has_permission("admin", "READ")); // admin <- user <- guest
+    }
+
+    #[test]
+    fn cyclic_role_definitions_are_rejected() {
+        let access_control = AccessControl::new();
+        access_control.register_role("a", &["A"], &[]).unwrap();
+        access_control.register_role("b", &["B"], &["a"]).unwrap();
+        access_control.register_role("c", &["C"], &["b"]).unwrap();
+
+        assert!(access_control.register_role("a", &["A"], &["c"]).is_err());
+        assert!(access_control.register_role("a", &["A"], &["a"]).is_err());
+        assert!(access_control
+            .register_role("d", &["D"], &["missing"])
+            .is_err());
+        // The rejected redefinition left the original hierarchy intact
+        assert!(!access_control.has_permission("a", "C"));
+        assert!(access_control.has_permission("c", "A"));
+    }
+
+    #[test]
+    fn signed_tokens_verify_only_unmodified() {
+        let key = b"access-control-test-key-0123456789";
+        let token = generate_signed_token("user123", "user", key);
+        assert!(verify_signed_token(&token, key));
+        assert!(!verify_signed_token(
+            &token.replacen("user", "root", 1),
+            key
+        ));
+        assert!(!verify_signed_token(
+            &token,
+            b"another-key-entirely-0123456789ab"
+        ));
+        assert!(!verify_signed_token(&token[..token.len() - 1], key));
+        assert!(!verify_signed_token("no-signature", key));
+
+        assert!(constant_time_eq(b"same", b"same"));
+        assert!(!constant_time_eq(b"same", b"sane"));
+        assert!(!constant_time_eq(b"same", b"same-but-longer"));
+        assert!(constant_time_eq(b"", b""));
+    }
 }
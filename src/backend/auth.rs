@@ -11,91 +11,274 @@
 //! - Role-based and attribute-based access control enforcement
 //! - Account lockout and brute-force attack prevention
 
+#[allow(dead_code)]
+mod challenge_store;
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod encoding;
+#[allow(dead_code)]
+mod json;
+#[allow(dead_code)]
+mod keys;
+#[allow(dead_code)]
+mod session_management;
+
 use argon2::{self, Config};
-use base64::{decode, encode};
+use challenge_store::{ChallengeError, ChallengeStore};
+use crypto::{constant_time_eq, generate_totp, verify_totp, OsRng, Rng, Zeroizing};
+use encoding::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
 use hmac::{Hmac, Mac};
-use rand::Rng;
-use sha2::Sha256;
+use json::JsonValue;
+use keys::{KeyError, KeyProvider};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use session_management::{SessionStore, SessionTokens};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
 const MAX_FAILED_ATTEMPTS: u8 = 5;
 const TOKEN_EXPIRATION: u64 = 3600; // 1 hour in seconds
-const MFA_SECRET: &str = "super_secure_mfa_secret";
+
+/// Reasons a login can fail
+#[derive(Debug, Clone, PartialEq)]
+enum AuthError {
+    UserNotFound,
+    InvalidCredentials,
+    AccountLocked,
+    CorruptHash,
+    MfaRequired,
+    InvalidMfaCode,
+    SessionUnavailable,
+}
+
+impl AuthError {
+    fn message(&self) -> &'static str {
+        match self {
+            AuthError::UserNotFound => "User not found.",
+            AuthError::InvalidCredentials => "Invalid username or password.",
+            AuthError::AccountLocked => "Account locked due to too many failed attempts.",
+            AuthError::CorruptHash => "Stored password hash is corrupt.",
+            AuthError::MfaRequired => "MFA code required.",
+            AuthError::InvalidMfaCode => "Invalid MFA code.",
+            AuthError::SessionUnavailable => "Unable to create session.",
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
 
 /// User struct storing authentication details
 struct User {
-    username: String,
     password_hash: String,
+    #[allow(dead_code)] // Recorded at registration; access_control decides what it grants
     role: String,
     failed_attempts: u8,
     last_failed_attempt: SystemTime,
     is_locked: bool,
+    mfa_enrolled: bool,
 }
 
 /// Secure authentication database
 struct AuthDB {
     users: Mutex<HashMap<String, User>>,
+    argon_config: Config<'static>, // Current Argon2 cost parameters for new hashes
+    sessions: Arc<SessionStore>,   // Issues access/refresh tokens on login
+    webauthn_challenges: ChallengeStore, // Single-use ceremony challenges
+    credentials: Mutex<HashMap<String, Vec<StoredCredential>>>, // User -> WebAuthn credentials
+    rng: Arc<dyn Rng>,             // Password salts
+    mfa_secret: Zeroizing<Vec<u8>>, // TOTP key
+    jwt_secret: Zeroizing<Vec<u8>>, // Same key `http.rs` verifies bearer JWTs with
 }
 
 impl AuthDB {
-    fn new() -> Self {
-        Self {
+    #[allow(dead_code)] // `main` sets production Argon2 costs explicitly
+    fn new(keys: &dyn KeyProvider) -> Result<Self, KeyError> {
+        Self::new_with_argon(Config::default(), keys)
+    }
+
+    /// Creates a database that hashes passwords with the given Argon2 parameters.
+    /// Fails if `keys` lacks the MFA, JWT or session secret.
+    fn new_with_argon(
+        argon_config: Config<'static>,
+        keys: &dyn KeyProvider,
+    ) -> Result<Self, KeyError> {
+        Ok(Self {
             users: Mutex::new(HashMap::new()),
+            argon_config,
+            sessions: Arc::new(SessionStore::new(keys.require(keys::SESSION_SECRET)?)),
+            webauthn_challenges: ChallengeStore::new(WEBAUTHN_TIMEOUT),
+            credentials: Mutex::new(HashMap::new()),
+            rng: Arc::new(OsRng),
+            mfa_secret: Zeroizing::new(keys.require(keys::MFA_SECRET)?),
+            jwt_secret: Zeroizing::new(keys.require(keys::JWT_SECRET)?),
+        })
+    }
+
+    /// Draws password salts from `rng` instead of the OS generator, so tests can
+    /// pin a seed and assert the exact encoded hash
+    #[allow(dead_code)] // Only tests pin the salt generator
+    fn with_rng(mut self, rng: Arc<dyn Rng>) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Hashes a password with a fresh salt under the current parameters
+    fn hash_password(&self, password: &str) -> Result<String, String> {
+        let mut salt = [0u8; 16];
+        self.rng.fill_bytes(&mut salt).map_err(|e| e.to_string())?;
+        argon2::hash_encoded(password.as_bytes(), &salt, &self.argon_config)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Returns true if `encoded` was produced with different parameters than the current config
+    fn needs_rehash(&self, encoded: &str) -> bool {
+        let config = &self.argon_config;
+        let expected_params = format!(
+            "m={},t={},p={}",
+            config.mem_cost, config.time_cost, config.lanes
+        );
+        let fields: Vec<&str> = encoded.split('$').collect();
+        if fields.len() != 6 {
+            return true;
+        }
+        let hash_length = STANDARD_NO_PAD
+            .decode(fields[5])
+            .map(|hash| hash.len() as u32)
+            .unwrap_or(0);
+
+        fields[1] != config.variant.as_lowercase_str()
+            || fields[2] != format!("v={}", config.version.as_u32())
+            || fields[3] != expected_params
+            || hash_length != config.hash_length
+    }
+
+    /// Upgrades a user's hash to the current parameters after a verified login.
+    /// Returns true if the stored hash was replaced.
+    fn rehash_if_needed(&self, user: &mut User, password: &str) -> bool {
+        if !self.needs_rehash(&user.password_hash) {
+            return false;
+        }
+        match self.hash_password(password) {
+            Ok(upgraded) => {
+                user.password_hash = upgraded;
+                true
+            }
+            Err(_) => false, // Keep the old, still valid hash
         }
     }
 
     /// Registers a new user with Argon2 hashed password
     fn register_user(&self, username: &str, password: &str, role: &str) {
-        let config = Config::default();
-        let salt = rand::thread_rng().gen::<[u8; 16]>();
-        let password_hash = argon2::hash_encoded(password.as_bytes(), &salt, &config).unwrap();
+        let password_hash = self
+            .hash_password(password)
+            .expect("Argon2 configuration rejected");
 
         let user = User {
-            username: username.to_string(),
             password_hash,
             role: role.to_string(),
             failed_attempts: 0,
             last_failed_attempt: SystemTime::now(),
             is_locked: false,
+            mfa_enrolled: false,
         };
 
         let mut users = self.users.lock().unwrap();
         users.insert(username.to_string(), user);
     }
 
-    /// Authenticates user with password and rate limits failed attempts
-    fn authenticate_user(&self, username: &str, password: &str) -> Result<String, &'static str> {
+    /// Session store shared with request handlers that verify tokens
+    fn session_store(&self) -> Arc<SessionStore> {
+        Arc::clone(&self.sessions)
+    }
+
+    /// Requires a TOTP code for every future login of `username`
+    fn enroll_mfa(&self, username: &str) -> Result<(), AuthError> {
         let mut users = self.users.lock().unwrap();
-        if let Some(user) = users.get_mut(username) {
-            if user.is_locked {
-                return Err("Account locked due to too many failed attempts.");
-            }
+        let user = users.get_mut(username).ok_or(AuthError::UserNotFound)?;
+        user.mfa_enrolled = true;
+        Ok(())
+    }
 
-            if argon2::verify_encoded(&user.password_hash, password.as_bytes()).unwrap_or(false) {
-                user.failed_attempts = 0;
-                return Ok(generate_jwt(username));
-            } else {
-                user.failed_attempts += 1;
-                user.last_failed_attempt = SystemTime::now();
+    /// Counts a failed factor against the user, locking the account at the limit
+    fn record_failure(user: &mut User) {
+        user.failed_attempts += 1;
+        user.last_failed_attempt = SystemTime::now();
 
-                if user.failed_attempts >= MAX_FAILED_ATTEMPTS {
-                    user.is_locked = true;
-                }
-                return Err("Invalid username or password.");
+        if user.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            user.is_locked = true;
+        }
+    }
+
+    /// Verifies the password, upgrading the stored hash on success
+    fn check_password(&self, user: &mut User, password: &str) -> Result<(), AuthError> {
+        if user.is_locked {
+            return Err(AuthError::AccountLocked);
+        }
+
+        let verified = argon2::verify_encoded(&user.password_hash, password.as_bytes())
+            .map_err(|_| AuthError::CorruptHash)?;
+        if !verified {
+            Self::record_failure(user);
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        self.rehash_if_needed(user, password);
+        Ok(())
+    }
+
+    /// Full login: password, then TOTP when enrolled, then a new session bound to `ip`
+    fn login(
+        &self,
+        username: &str,
+        password: &str,
+        mfa_code: Option<&str>,
+        ip: &str,
+    ) -> Result<SessionTokens, AuthError> {
+        let mut users = self.users.lock().unwrap();
+        let user = users.get_mut(username).ok_or(AuthError::UserNotFound)?;
+        self.check_password(user, password)?;
+
+        if user.mfa_enrolled {
+            let code = mfa_code.ok_or(AuthError::MfaRequired)?;
+            if !verify_totp(code, &self.mfa_secret) {
+                Self::record_failure(user);
+                return Err(AuthError::InvalidMfaCode);
             }
         }
-        Err("User not found.")
+
+        user.failed_attempts = 0;
+        drop(users);
+        self.sessions
+            .create_session_tokens(username, ip)
+            .map_err(|_| AuthError::SessionUnavailable)
+    }
+
+    /// Issues an HS256 bearer token, but only after a full `login` (password and MFA)
+    fn issue_bearer_token(
+        &self,
+        username: &str,
+        password: &str,
+        mfa_code: Option<&str>,
+        ip: &str,
+    ) -> Result<String, AuthError> {
+        self.login(username, password, mfa_code, ip)?;
+        Ok(generate_jwt(username, &self.jwt_secret))
     }
 }
 
-/// Generates a JWT token with HMAC-SHA256
-fn generate_jwt(username: &str) -> String {
-    let header = encode("{\"alg\":\"HS256\",\"typ\":\"JWT\"}");
-    let payload = encode(&format!(
+/// Generates a JWT token with HMAC-SHA256; segments are unpadded base64url (RFC 7515)
+fn generate_jwt(username: &str, secret: &[u8]) -> String {
+    let header = URL_SAFE_NO_PAD.encode("{\"alg\":\"HS256\",\"typ\":\"JWT\"}");
+    let payload = URL_SAFE_NO_PAD.encode(format!(
         "{{\"sub\":\"{}\",\"exp\":{}}}",
-        username,
+        json_escape(username),
         SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -103,55 +286,963 @@ fn generate_jwt(username: &str) -> String {
             + TOKEN_EXPIRATION
     ));
 
-    let mut mac = Hmac::<Sha256>::new_from_slice(MFA_SECRET.as_bytes()).unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
     mac.update(format!("{}.{}", header, payload).as_bytes());
-    let signature = encode(&mac.finalize().into_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
 
     format!("{}.{}.{}", header, payload, signature)
 }
 
 /// Verifies a JWT token
-fn verify_jwt(token: &str) -> bool {
+fn verify_jwt(token: &str, secret: &[u8]) -> bool {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 3 {
         return false;
     }
 
-    let mut mac = Hmac::<Sha256>::new_from_slice(MFA_SECRET.as_bytes()).unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
     mac.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
 
-    if let Ok(decoded_sig) = decode(parts[2]) {
+    if let Ok(decoded_sig) = URL_SAFE_NO_PAD.decode(parts[2]) {
         return mac.verify_slice(&decoded_sig).is_ok();
     }
     false
 }
 
-/// Multi-Factor Authentication (TOTP generation)
-fn generate_totp() -> String {
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        / 30;
-    let mut mac = Hmac::<Sha256>::new_from_slice(MFA_SECRET.as_bytes()).unwrap();
-    mac.update(&timestamp.to_be_bytes());
-    let hash = mac.finalize().into_bytes();
-    let offset = (hash[hash.len() - 1] & 0xF) as usize;
-    let code = ((hash[offset] as u32 & 0x7F) << 24
-        | (hash[offset + 1] as u32 & 0xFF) << 16
-        | (hash[offset + 2] as u32 & 0xFF) << 8
-        | (hash[offset + 3] as u32 & 0xFF))
-        % 1000000;
-    format!("{:06}", code)
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Relying party identity used for WebAuthn ceremonies
+const WEBAUTHN_RP_ID: &str = "localhost";
+const WEBAUTHN_RP_NAME: &str = "Zero Trust Website";
+const WEBAUTHN_ORIGIN: &str = "https://localhost";
+const WEBAUTHN_TIMEOUT: Duration = Duration::from_secs(120);
+const COSE_ALG_ES256: i64 = -7;
+const AUTH_DATA_FLAG_USER_PRESENT: u8 = 0x01;
+const AUTH_DATA_FLAG_ATTESTED_DATA: u8 = 0x40;
+const MAX_CBOR_DEPTH: usize = 16;
+
+/// Reasons a WebAuthn ceremony can fail
+#[derive(Debug, Clone, PartialEq)]
+enum WebAuthnError {
+    UserNotFound,
+    ChallengeUnavailable,
+    Challenge(ChallengeError),
+    OriginMismatch,
+    WrongCeremony,
+    RelyingPartyMismatch,
+    UserNotPresent,
+    MalformedData(&'static str),
+    UnsupportedAlgorithm,
+    UnknownCredential,
+    InvalidSignature,
+    CounterRegression,
+}
+
+impl fmt::Display for WebAuthnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebAuthnError::MalformedData(what) => write!(f, "Malformed WebAuthn data: {}", what),
+            WebAuthnError::Challenge(e) => write!(f, "WebAuthn challenge rejected: {}", e),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Options passed to `navigator.credentials.create()`
+#[derive(Debug, Clone)]
+struct PublicKeyCredentialCreationOptions {
+    challenge: String, // base64url
+    rp_id: String,
+    rp_name: String,
+    user_id: String, // base64url
+    user_name: String,
+    pub_key_cred_params: Vec<i64>, // COSE algorithm identifiers
+    timeout_ms: u64,
+}
+
+impl PublicKeyCredentialCreationOptions {
+    fn to_json(&self) -> String {
+        let params: Vec<String> = self
+            .pub_key_cred_params
+            .iter()
+            .map(|alg| format!("{{\"type\":\"public-key\",\"alg\":{}}}", alg))
+            .collect();
+        format!(
+            "{{\"challenge\":\"{}\",\"rp\":{{\"id\":\"{}\",\"name\":\"{}\"}},\"user\":{{\"id\":\"{}\",\"name\":\"{}\",\"displayName\":\"{}\"}},\"pubKeyCredParams\":[{}],\"timeout\":{},\"attestation\":\"none\"}}",
+            self.challenge,
+            self.rp_id,
+            self.rp_name,
+            self.user_id,
+            json_escape(&self.user_name),
+            json_escape(&self.user_name),
+            params.join(","),
+            self.timeout_ms
+        )
+    }
 }
 
-/// Verifies TOTP for MFA
-fn verify_totp(input_code: &str) -> bool {
-    generate_totp() == input_code
+/// Options passed to `navigator.credentials.get()`
+#[derive(Debug, Clone)]
+struct PublicKeyCredentialRequestOptions {
+    challenge: String, // base64url
+    rp_id: String,
+    allow_credentials: Vec<String>, // base64url credential IDs
+    timeout_ms: u64,
+}
+
+impl PublicKeyCredentialRequestOptions {
+    fn to_json(&self) -> String {
+        let allowed: Vec<String> = self
+            .allow_credentials
+            .iter()
+            .map(|id| format!("{{\"type\":\"public-key\",\"id\":\"{}\"}}", id))
+            .collect();
+        format!(
+            "{{\"challenge\":\"{}\",\"rpId\":\"{}\",\"allowCredentials\":[{}],\"timeout\":{},\"userVerification\":\"preferred\"}}",
+            self.challenge,
+            self.rp_id,
+            allowed.join(","),
+            self.timeout_ms
+        )
+    }
+}
+
+/// Authenticator response to a registration ceremony (raw, base64url-decoded)
+#[allow(dead_code)] // Decoded from the browser's JSON by the HTTP layer
+struct RegistrationResponse {
+    credential_id: Vec<u8>,
+    client_data_json: Vec<u8>,
+    attestation_object: Vec<u8>,
+}
+
+/// Authenticator response to an assertion ceremony (raw, base64url-decoded)
+#[allow(dead_code)] // Decoded from the browser's JSON by the HTTP layer
+struct AssertionResponse {
+    credential_id: Vec<u8>,
+    client_data_json: Vec<u8>,
+    authenticator_data: Vec<u8>,
+    signature: Vec<u8>, // ASN.1 DER encoded ECDSA signature
+}
+
+/// A registered ES256 credential
+#[derive(Debug, Clone)]
+struct StoredCredential {
+    credential_id: Vec<u8>,
+    public_key: [u8; 65], // SEC1 uncompressed P-256 point
+    sign_count: u32,
+}
+
+/// Minimal CBOR value model (RFC 8949) covering what authenticators emit
+#[derive(Debug, Clone, PartialEq)]
+enum CborValue {
+    Unsigned(u64),
+    Negative(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CborValue>),
+    Map(Vec<(CborValue, CborValue)>),
+    Bool(bool),
+    Null,
+}
+
+impl CborValue {
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            CborValue::Unsigned(n) => i64::try_from(*n).ok(),
+            CborValue::Negative(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Looks up a map entry by integer key (COSE) or text key (attestation object)
+    fn get(&self, key: &CborValue) -> Option<&CborValue> {
+        match self {
+            CborValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes one CBOR item from the front of `data`, returning it and the bytes consumed
+fn decode_cbor(data: &[u8], depth: usize) -> Result<(CborValue, usize), WebAuthnError> {
+    let truncated = WebAuthnError::MalformedData("truncated CBOR");
+    if depth > MAX_CBOR_DEPTH {
+        return Err(WebAuthnError::MalformedData("CBOR nesting too deep"));
+    }
+    let initial = *data.first().ok_or(truncated.clone())?;
+    let (major, info) = (initial >> 5, initial & 0x1F);
+
+    let (argument, mut offset) = match info {
+        0..=23 => (info as u64, 1),
+        24..=27 => {
+            let width = 1usize << (info - 24);
+            let bytes = data.get(1..1 + width).ok_or(truncated.clone())?;
+            (
+                bytes.iter().fold(0u64, |acc, &b| acc << 8 | b as u64),
+                1 + width,
+            )
+        }
+        _ => return Err(WebAuthnError::MalformedData("unsupported CBOR length")),
+    };
+
+    let value = match major {
+        0 => CborValue::Unsigned(argument),
+        1 => CborValue::Negative(-1 - i64::try_from(argument).map_err(|_| truncated.clone())?),
+        2 | 3 => {
+            let len = usize::try_from(argument).map_err(|_| truncated.clone())?;
+            let end = offset.checked_add(len).ok_or(truncated.clone())?;
+            let bytes = data.get(offset..end).ok_or(truncated.clone())?.to_vec();
+            offset = end;
+            if major == 2 {
+                CborValue::Bytes(bytes)
+            } else {
+                CborValue::Text(
+                    String::from_utf8(bytes)
+                        .map_err(|_| WebAuthnError::MalformedData("invalid CBOR text"))?,
+                )
+            }
+        }
+        4 | 5 => {
+            // Every item takes at least one byte, which bounds the preallocation
+            let count = usize::try_from(argument).map_err(|_| truncated.clone())?;
+            if count > data.len() - offset {
+                return Err(truncated);
+            }
+            let item_count = if major == 5 { count * 2 } else { count };
+            let mut items = Vec::with_capacity(item_count);
+            for _ in 0..item_count {
+                let (item, used) = decode_cbor(&data[offset..], depth + 1)?;
+                items.push(item);
+                offset += used;
+            }
+            if major == 4 {
+                CborValue::Array(items)
+            } else {
+                let mut items = items.into_iter();
+                let mut entries = Vec::with_capacity(count);
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    entries.push((key, value));
+                }
+                CborValue::Map(entries)
+            }
+        }
+        6 => {
+            // Tags carry no meaning for WebAuthn; decode the tagged item
+            let (item, used) = decode_cbor(&data[offset..], depth + 1)?;
+            offset += used;
+            item
+        }
+        _ => match info {
+            20 => CborValue::Bool(false),
+            21 => CborValue::Bool(true),
+            22 => CborValue::Null,
+            _ => {
+                return Err(WebAuthnError::MalformedData(
+                    "unsupported CBOR simple value",
+                ))
+            }
+        },
+    };
+    Ok((value, offset))
+}
+
+/// Extracts an ES256 public key from a COSE_Key map as a SEC1 uncompressed point
+fn cose_es256_public_key(cose_key: &CborValue) -> Result<[u8; 65], WebAuthnError> {
+    let int_field = |label: i64| cose_key.get(&cbor_int(label)).and_then(CborValue::as_int);
+    if int_field(1) != Some(2) || int_field(3) != Some(COSE_ALG_ES256) || int_field(-1) != Some(1) {
+        return Err(WebAuthnError::UnsupportedAlgorithm);
+    }
+    let coordinate = |label: i64| match cose_key.get(&cbor_int(label)) {
+        Some(CborValue::Bytes(bytes)) if bytes.len() == 32 => Ok(bytes.clone()),
+        _ => Err(WebAuthnError::MalformedData("invalid EC2 coordinate")),
+    };
+
+    let mut point = [0u8; 65];
+    point[0] = 0x04;
+    point[1..33].copy_from_slice(&coordinate(-2)?);
+    point[33..].copy_from_slice(&coordinate(-3)?);
+    Ok(point)
+}
+
+fn cbor_int(value: i64) -> CborValue {
+    if value < 0 {
+        CborValue::Negative(value)
+    } else {
+        CborValue::Unsigned(value as u64)
+    }
+}
+
+/// Parsed fixed-layout prefix of authenticator data
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested_data: &'a [u8], // Present when the AT flag is set
+}
+
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>, WebAuthnError> {
+    if data.len() < 37 {
+        return Err(WebAuthnError::MalformedData("authenticator data too short"));
+    }
+    Ok(AuthenticatorData {
+        rp_id_hash: &data[..32],
+        flags: data[32],
+        sign_count: u32::from_be_bytes([data[33], data[34], data[35], data[36]]),
+        attested_data: &data[37..],
+    })
+}
+
+/// Checks clientDataJSON type and origin for the given ceremony, returning its challenge
+fn verify_client_data(client_data_json: &[u8], ceremony: &str) -> Result<String, WebAuthnError> {
+    let client_data = std::str::from_utf8(client_data_json)
+        .map_err(|_| WebAuthnError::MalformedData("clientDataJSON is not UTF-8"))?;
+    let client_data = json::parse_json(client_data)
+        .ok()
+        .filter(|value| value.as_object().is_some())
+        .ok_or(WebAuthnError::MalformedData(
+            "clientDataJSON is not a JSON object",
+        ))?;
+    // Only top-level string members count; a key nested in another member is ignored
+    let field = |key: &str| {
+        client_data
+            .get(key)
+            .and_then(JsonValue::as_str)
+            .ok_or(WebAuthnError::MalformedData("clientDataJSON missing field"))
+    };
+
+    if field("type")? != ceremony {
+        return Err(WebAuthnError::WrongCeremony);
+    }
+    if field("origin")? != WEBAUTHN_ORIGIN {
+        return Err(WebAuthnError::OriginMismatch);
+    }
+    field("challenge").map(str::to_string)
+}
+
+/// Verifies the relying party hash and user-presence flag shared by both ceremonies
+fn verify_rp_and_presence(auth_data: &AuthenticatorData) -> Result<(), WebAuthnError> {
+    if !constant_time_eq(
+        auth_data.rp_id_hash,
+        &Sha256::digest(WEBAUTHN_RP_ID.as_bytes()),
+    ) {
+        return Err(WebAuthnError::RelyingPartyMismatch);
+    }
+    if auth_data.flags & AUTH_DATA_FLAG_USER_PRESENT == 0 {
+        return Err(WebAuthnError::UserNotPresent);
+    }
+    Ok(())
+}
+
+/// Verifies an ES256 signature over `message` (SHA-256 is applied by the verifier)
+fn verify_es256(public_key: &[u8; 65], message: &[u8], der_signature: &[u8]) -> bool {
+    let key = match VerifyingKey::from_sec1_bytes(public_key) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    match Signature::from_der(der_signature) {
+        Ok(signature) => key.verify(message, &signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// WebAuthn (FIDO2) passwordless registration and assertion
+impl AuthDB {
+    /// Issues a ceremony challenge. Hex digits are a subset of the base64url
+    /// alphabet, so the value round-trips unchanged through clientDataJSON.
+    fn issue_webauthn_challenge(&self, username: &str) -> Result<String, WebAuthnError> {
+        self.webauthn_challenges
+            .issue(username)
+            .map_err(|_| WebAuthnError::ChallengeUnavailable)
+    }
+
+    /// Starts registering a new authenticator for an existing user
+    fn begin_registration(
+        &self,
+        username: &str,
+    ) -> Result<PublicKeyCredentialCreationOptions, WebAuthnError> {
+        if !self.users.lock().unwrap().contains_key(username) {
+            return Err(WebAuthnError::UserNotFound);
+        }
+        Ok(PublicKeyCredentialCreationOptions {
+            challenge: self.issue_webauthn_challenge(username)?,
+            rp_id: WEBAUTHN_RP_ID.to_string(),
+            rp_name: WEBAUTHN_RP_NAME.to_string(),
+            user_id: URL_SAFE_NO_PAD.encode(username),
+            user_name: username.to_string(),
+            pub_key_cred_params: vec![COSE_ALG_ES256],
+            timeout_ms: WEBAUTHN_TIMEOUT.as_millis() as u64,
+        })
+    }
+
+    /// Verifies the authenticator's attestation and stores its credential public key
+    #[allow(dead_code)] // Driven by the HTTP layer; needs a real authenticator
+    fn finish_registration(
+        &self,
+        username: &str,
+        response: &RegistrationResponse,
+    ) -> Result<(), WebAuthnError> {
+        let challenge = verify_client_data(&response.client_data_json, "webauthn.create")?;
+        self.webauthn_challenges
+            .verify(&challenge, username)
+            .map_err(WebAuthnError::Challenge)?;
+
+        // Attestation statements are not verified: options request "none" conveyance
+        let (attestation, _) = decode_cbor(&response.attestation_object, 0)?;
+        let auth_data_bytes = match attestation.get(&CborValue::Text("authData".to_string())) {
+            Some(CborValue::Bytes(bytes)) => bytes,
+            _ => return Err(WebAuthnError::MalformedData("attestation missing authData")),
+        };
+        let auth_data = parse_authenticator_data(auth_data_bytes)?;
+        verify_rp_and_presence(&auth_data)?;
+        if auth_data.flags & AUTH_DATA_FLAG_ATTESTED_DATA == 0 {
+            return Err(WebAuthnError::MalformedData("no attested credential data"));
+        }
+
+        // AAGUID (16) | credential ID length (2) | credential ID | COSE public key
+        let attested = auth_data.attested_data;
+        let id_len = match attested.get(16..18) {
+            Some(len) => u16::from_be_bytes([len[0], len[1]]) as usize,
+            None => return Err(WebAuthnError::MalformedData("attested data too short")),
+        };
+        let credential_id = attested
+            .get(18..18 + id_len)
+            .ok_or(WebAuthnError::MalformedData("credential ID truncated"))?;
+        if credential_id != response.credential_id.as_slice() {
+            return Err(WebAuthnError::MalformedData("credential ID mismatch"));
+        }
+        let (cose_key, _) = decode_cbor(&attested[18 + id_len..], 0)?;
+        let public_key = cose_es256_public_key(&cose_key)?;
+        self.webauthn_challenges
+            .consume(&challenge, username)
+            .map_err(WebAuthnError::Challenge)?;
+
+        self.credentials
+            .lock()
+            .unwrap()
+            .entry(username.to_string())
+            .or_default()
+            .push(StoredCredential {
+                credential_id: credential_id.to_vec(),
+                public_key,
+                sign_count: auth_data.sign_count,
+            });
+        Ok(())
+    }
+
+    /// Starts a passwordless login against the user's registered credentials
+    fn begin_assertion(
+        &self,
+        username: &str,
+    ) -> Result<PublicKeyCredentialRequestOptions, WebAuthnError> {
+        let allow_credentials: Vec<String> = self
+            .credentials
+            .lock()
+            .unwrap()
+            .get(username)
+            .map(|creds| {
+                creds
+                    .iter()
+                    .map(|c| URL_SAFE_NO_PAD.encode(&c.credential_id))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if allow_credentials.is_empty() {
+            return Err(WebAuthnError::UnknownCredential);
+        }
+        Ok(PublicKeyCredentialRequestOptions {
+            challenge: self.issue_webauthn_challenge(username)?,
+            rp_id: WEBAUTHN_RP_ID.to_string(),
+            allow_credentials,
+            timeout_ms: WEBAUTHN_TIMEOUT.as_millis() as u64,
+        })
+    }
+
+    /// Verifies the assertion signature over authenticatorData || SHA-256(clientDataJSON)
+    #[allow(dead_code)] // Driven by the HTTP layer; needs a real authenticator
+    fn finish_assertion(
+        &self,
+        username: &str,
+        response: &AssertionResponse,
+    ) -> Result<(), WebAuthnError> {
+        let challenge = verify_client_data(&response.client_data_json, "webauthn.get")?;
+        self.webauthn_challenges
+            .verify(&challenge, username)
+            .map_err(WebAuthnError::Challenge)?;
+        let auth_data = parse_authenticator_data(&response.authenticator_data)?;
+        verify_rp_and_presence(&auth_data)?;
+
+        let mut credentials = self.credentials.lock().unwrap();
+        let credential = credentials
+            .get_mut(username)
+            .and_then(|creds| {
+                creds
+                    .iter_mut()
+                    .find(|c| c.credential_id == response.credential_id)
+            })
+            .ok_or(WebAuthnError::UnknownCredential)?;
+
+        let mut signed = response.authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&response.client_data_json));
+        if !verify_es256(&credential.public_key, &signed, &response.signature) {
+            return Err(WebAuthnError::InvalidSignature);
+        }
+
+        // A counter that fails to advance suggests a cloned authenticator
+        if (auth_data.sign_count != 0 || credential.sign_count != 0)
+            && auth_data.sign_count <= credential.sign_count
+        {
+            return Err(WebAuthnError::CounterRegression);
+        }
+        self.webauthn_challenges
+            .consume(&challenge, username)
+            .map_err(WebAuthnError::Challenge)?;
+        credential.sign_count = auth_data.sign_count;
+        Ok(())
+    }
 }
 
 fn main() {
-    let auth_db = Arc::new(AuthDB::new());
+    let auth_db = match AuthDB::new_with_argon(
+        Config {
+            variant: argon2::Variant::Argon2id,
+            mem_cost: 19456,
+            time_cost: 2,
+            ..Config::default()
+        },
+        &keys::EnvKeyProvider,
+    ) {
+        Ok(auth_db) => Arc::new(auth_db),
+        Err(e) => {
+            eprintln!("Auth system not started: {}", e);
+            return;
+        }
+    };
     auth_db.register_user("admin", "SuperSecurePassword!", "admin");
     println!("Auth system initialized. Ready for authentication requests.");
+
+    let ip = "192.168.1.1";
+    auth_db
+        .enroll_mfa("admin")
+        .expect("admin was just registered");
+    match auth_db.login("admin", "SuperSecurePassword!", None, ip) {
+        Err(e) => println!("Login without MFA rejected: {}", e),
+        Ok(_) => println!("Unexpected login without MFA"),
+    }
+    match auth_db.login(
+        "admin",
+        "SuperSecurePassword!",
+        Some(&generate_totp(&auth_db.mfa_secret)),
+        ip,
+    ) {
+        Ok(tokens) => {
+            let sessions = auth_db.session_store();
+            println!(
+                "Login succeeded; session valid: {}",
+                sessions.verify_session(&tokens.access_token, ip)
+            );
+        }
+        Err(e) => eprintln!("Login failed: {}", e),
+    }
+    match auth_db.issue_bearer_token(
+        "admin",
+        "SuperSecurePassword!",
+        Some(&generate_totp(&auth_db.mfa_secret)),
+        ip,
+    ) {
+        Ok(jwt) => println!(
+            "Bearer JWT issued after MFA; verifies: {}",
+            verify_jwt(&jwt, &auth_db.jwt_secret)
+        ),
+        Err(e) => eprintln!("JWT not issued: {}", e),
+    }
+
+    match auth_db.begin_registration("admin") {
+        Ok(options) => println!("WebAuthn registration options: {}", options.to_json()),
+        Err(e) => eprintln!("WebAuthn registration unavailable: {}", e),
+    }
+    match auth_db.begin_assertion("admin") {
+        Ok(options) => println!("WebAuthn assertion options: {}", options.to_json()),
+        Err(e) => println!("No passwordless login for admin yet: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap Argon2 parameters; production costs would make every test take seconds
+    fn weak_argon() -> Config<'static> {
+        Config {
+            variant: argon2::Variant::Argon2id,
+            mem_cost: 8,
+            time_cost: 1,
+            lanes: 1,
+            ..Config::default()
+        }
+    }
+
+    /// A database with fixed secrets and the given Argon2 parameters
+    fn test_db(argon_config: Config<'static>) -> AuthDB {
+        let keys = keys::StaticKeyProvider::new()
+            .with(keys::SESSION_SECRET, &[1; keys::MIN_SECRET_LEN])
+            .with(keys::MFA_SECRET, &[2; keys::MIN_SECRET_LEN])
+            .with(keys::JWT_SECRET, &[3; keys::MIN_SECRET_LEN]);
+        AuthDB::new_with_argon(argon_config, &keys).unwrap()
+    }
+
+    fn stored_hash(db: &AuthDB, username: &str) -> String {
+        db.users.lock().unwrap()[username].password_hash.clone()
+    }
+
+    #[test]
+    fn weak_hash_is_upgraded_after_a_successful_login() {
+        let mut db = test_db(weak_argon());
+        db.register_user("alice", "correct horse", "user");
+        let weak_hash = stored_hash(&db, "alice");
+        assert!(weak_hash.contains("m=8,t=1,p=1"));
+
+        // The operator raises the cost; existing hashes still verify
+        db.argon_config = Config {
+            mem_cost: 16,
+            time_cost: 2,
+            ..weak_argon()
+        };
+        assert!(db.needs_rehash(&weak_hash));
+        assert!(db.login("alice", "wrong", None, "10.0.0.1").is_err());
+        assert_eq!(stored_hash(&db, "alice"), weak_hash);
+
+        db.login("alice", "correct horse", None, "10.0.0.1")
+            .unwrap();
+        let upgraded = stored_hash(&db, "alice");
+        assert!(upgraded.contains("m=16,t=2,p=1"));
+        assert!(!db.needs_rehash(&upgraded));
+        db.login("alice", "correct horse", None, "10.0.0.1")
+            .unwrap();
+    }
+
+    #[test]
+    fn corrupt_hash_is_reported_apart_from_a_wrong_password() {
+        let db = test_db(weak_argon());
+        db.register_user("bob", "hunter2", "user");
+        assert_eq!(
+            db.login("bob", "hunter3", None, "10.0.0.1").err(),
+            Some(AuthError::InvalidCredentials)
+        );
+        db.users
+            .lock()
+            .unwrap()
+            .get_mut("bob")
+            .unwrap()
+            .password_hash = "not-a-hash".to_string();
+        assert_eq!(
+            db.login("bob", "hunter2", None, "10.0.0.1").err(),
+            Some(AuthError::CorruptHash)
+        );
+    }
+
+    #[test]
+    fn login_issues_a_session_after_password_and_mfa() {
+        let db = test_db(weak_argon());
+        db.register_user("carol", "s3cret", "user");
+        db.enroll_mfa("carol").unwrap();
+
+        let tokens = db
+            .login(
+                "carol",
+                "s3cret",
+                Some(&generate_totp(&db.mfa_secret)),
+                "10.0.0.1",
+            )
+            .unwrap();
+        assert!(db
+            .session_store()
+            .verify_session(&tokens.access_token, "10.0.0.1"));
+        assert!(!db
+            .session_store()
+            .verify_session(&tokens.access_token, "10.0.0.2"));
+        assert!(db
+            .session_store()
+            .refresh_session(&tokens.refresh_token, "10.0.0.1")
+            .is_some());
+    }
+
+    #[test]
+    fn login_fails_at_each_stage() {
+        let db = test_db(weak_argon());
+        db.register_user("dave", "s3cret", "user");
+        db.enroll_mfa("dave").unwrap();
+
+        assert_eq!(
+            db.login("nobody", "s3cret", None, "10.0.0.1").err(),
+            Some(AuthError::UserNotFound)
+        );
+        assert_eq!(
+            db.login("dave", "wrong", None, "10.0.0.1").err(),
+            Some(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            db.login("dave", "s3cret", None, "10.0.0.1").err(),
+            Some(AuthError::MfaRequired)
+        );
+        assert_eq!(
+            db.login("dave", "s3cret", Some("000000x"), "10.0.0.1")
+                .err(),
+            Some(AuthError::InvalidMfaCode)
+        );
+
+        // Two failures so far; three more wrong passwords reach the limit
+        for _ in 2..MAX_FAILED_ATTEMPTS {
+            assert!(db.login("dave", "wrong", None, "10.0.0.1").is_err());
+        }
+        let code = generate_totp(&db.mfa_secret);
+        assert_eq!(
+            db.login("dave", "s3cret", Some(&code), "10.0.0.1").err(),
+            Some(AuthError::AccountLocked)
+        );
+    }
+
+    /// Fixed authenticator key; RFC 6979 signing keeps every response byte-for-byte canned
+    fn authenticator_key() -> p256::ecdsa::SigningKey {
+        p256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap()
+    }
+
+    const CREDENTIAL_ID: &[u8] = b"canned-credential";
+
+    /// CBOR head for `major` with a length or value below 65536
+    fn cbor_head(major: u8, len: usize) -> Vec<u8> {
+        match len {
+            0..=23 => vec![major << 5 | len as u8],
+            24..=255 => vec![major << 5 | 24, len as u8],
+            _ => vec![major << 5 | 25, (len >> 8) as u8, len as u8],
+        }
+    }
+
+    fn cbor_bytes(major: u8, bytes: &[u8]) -> Vec<u8> {
+        let mut out = cbor_head(major, bytes.len());
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    fn client_data(ceremony: &str, challenge: &str) -> Vec<u8> {
+        format!(
+            "{{\"type\":\"{}\",\"challenge\":\"{}\",\"origin\":\"{}\"}}",
+            ceremony, challenge, WEBAUTHN_ORIGIN
+        )
+        .into_bytes()
+    }
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    /// A "none" attestation carrying the authenticator's COSE ES256 public key
+    fn registration_response(challenge: &str) -> RegistrationResponse {
+        let point = authenticator_key().verifying_key().to_encoded_point(false);
+        // {1: 2, 3: -7, -1: 1, -2: x, -3: y}
+        let mut cose_key = vec![0xA5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01];
+        cose_key.push(0x21);
+        cose_key.extend(cbor_bytes(2, point.x().unwrap()));
+        cose_key.push(0x22);
+        cose_key.extend(cbor_bytes(2, point.y().unwrap()));
+
+        let mut auth_data = authenticator_data(
+            AUTH_DATA_FLAG_USER_PRESENT | AUTH_DATA_FLAG_ATTESTED_DATA,
+            0,
+        );
+        auth_data.extend_from_slice(&[0; 16]); // AAGUID
+        auth_data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(CREDENTIAL_ID);
+        auth_data.extend(cose_key);
+
+        let mut attestation_object = vec![0xA3];
+        attestation_object.extend(cbor_bytes(3, b"fmt"));
+        attestation_object.extend(cbor_bytes(3, b"none"));
+        attestation_object.extend(cbor_bytes(3, b"attStmt"));
+        attestation_object.push(0xA0);
+        attestation_object.extend(cbor_bytes(3, b"authData"));
+        attestation_object.extend(cbor_bytes(2, &auth_data));
+
+        RegistrationResponse {
+            credential_id: CREDENTIAL_ID.to_vec(),
+            client_data_json: client_data("webauthn.create", challenge),
+            attestation_object,
+        }
+    }
+
+    fn assertion_response(challenge: &str, sign_count: u32) -> AssertionResponse {
+        use p256::ecdsa::signature::Signer;
+
+        let client_data_json = client_data("webauthn.get", challenge);
+        let authenticator_data = authenticator_data(AUTH_DATA_FLAG_USER_PRESENT, sign_count);
+        let mut signed = authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = authenticator_key().sign(&signed);
+
+        AssertionResponse {
+            credential_id: CREDENTIAL_ID.to_vec(),
+            client_data_json,
+            authenticator_data,
+            signature: signature.to_der().as_bytes().to_vec(),
+        }
+    }
+
+    /// A database with "erin" registered and holding the canned credential
+    fn registered_db() -> AuthDB {
+        let db = test_db(weak_argon());
+        db.register_user("erin", "s3cret", "user");
+        let options = db.begin_registration("erin").unwrap();
+        db.finish_registration("erin", &registration_response(&options.challenge))
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn canned_assertion_is_accepted_once() {
+        let db = registered_db();
+        let options = db.begin_assertion("erin").unwrap();
+        assert_eq!(
+            options.allow_credentials,
+            vec![URL_SAFE_NO_PAD.encode(CREDENTIAL_ID)]
+        );
+
+        let response = assertion_response(&options.challenge, 1);
+        assert_eq!(db.finish_assertion("erin", &response), Ok(()));
+        // The challenge is consumed, so the same response cannot be replayed
+        assert!(matches!(
+            db.finish_assertion("erin", &response),
+            Err(WebAuthnError::Challenge(_))
+        ));
+    }
+
+    #[test]
+    fn tampered_assertion_is_rejected() {
+        let db = registered_db();
+        let options = db.begin_assertion("erin").unwrap();
+
+        let mut response = assertion_response(&options.challenge, 1);
+        let last = response.signature.len() - 1;
+        response.signature[last] ^= 0x01;
+        assert_eq!(
+            db.finish_assertion("erin", &response),
+            Err(WebAuthnError::InvalidSignature)
+        );
+
+        // Signed data altered after signing: the counter no longer matches
+        let mut response = assertion_response(&options.challenge, 1);
+        response.authenticator_data[36] = 2;
+        assert_eq!(
+            db.finish_assertion("erin", &response),
+            Err(WebAuthnError::InvalidSignature)
+        );
+
+        // The challenge survives failed attempts and the genuine response still works
+        let response = assertion_response(&options.challenge, 1);
+        assert_eq!(db.finish_assertion("erin", &response), Ok(()));
+    }
+
+    #[test]
+    fn seeded_rng_makes_password_hashes_reproducible() {
+        let hash_with_seed = |seed| {
+            test_db(weak_argon())
+                .with_rng(Arc::new(crypto::SeededRng::from_seed(seed)))
+                .hash_password("correct horse")
+                .unwrap()
+        };
+        let hash = hash_with_seed(2403);
+        assert_eq!(hash, hash_with_seed(2403));
+        assert_ne!(hash, hash_with_seed(2404));
+        assert!(argon2::verify_encoded(&hash, b"correct horse").unwrap());
+    }
+
+    #[test]
+    fn missing_secrets_fail_construction() {
+        let complete = keys::StaticKeyProvider::new()
+            .with(keys::SESSION_SECRET, &[1; keys::MIN_SECRET_LEN])
+            .with(keys::MFA_SECRET, &[2; keys::MIN_SECRET_LEN]);
+        match AuthDB::new(&complete) {
+            Err(KeyError::Missing { name, .. }) => assert_eq!(name, keys::JWT_SECRET),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("constructed without a JWT secret"),
+        }
+        assert!(AuthDB::new(&keys::StaticKeyProvider::new()).is_err());
+    }
+
+    #[test]
+    fn tokens_are_signed_with_the_provided_jwt_secret() {
+        let db = test_db(weak_argon());
+        db.register_user("alice", "correct horse", "user");
+        let token = db
+            .issue_bearer_token("alice", "correct horse", None, "10.0.0.1")
+            .unwrap();
+
+        assert!(verify_jwt(&token, &[3; keys::MIN_SECRET_LEN]));
+        assert!(!verify_jwt(&token, &[4; keys::MIN_SECRET_LEN]));
+    }
+
+    #[test]
+    fn enrolled_user_gets_no_bearer_token_without_mfa() {
+        let db = test_db(weak_argon());
+        db.register_user("dave", "pa55word", "user");
+        db.enroll_mfa("dave").unwrap();
+
+        assert_eq!(
+            db.issue_bearer_token("dave", "pa55word", None, "10.0.0.1"),
+            Err(AuthError::MfaRequired)
+        );
+        assert_eq!(
+            db.issue_bearer_token("dave", "pa55word", Some("000000x"), "10.0.0.1"),
+            Err(AuthError::InvalidMfaCode)
+        );
+        let token = db
+            .issue_bearer_token(
+                "dave",
+                "pa55word",
+                Some(&generate_totp(&db.mfa_secret)),
+                "10.0.0.1",
+            )
+            .unwrap();
+        assert!(verify_jwt(&token, &db.jwt_secret));
+    }
+
+    #[test]
+    fn usernames_are_escaped_in_json_output() {
+        let payload = generate_jwt("eve\",\"admin\":true", &[3; keys::MIN_SECRET_LEN])
+            .split('.')
+            .nth(1)
+            .map(|p| URL_SAFE_NO_PAD.decode(p).unwrap())
+            .unwrap();
+        let claims = json::parse_json(std::str::from_utf8(&payload).unwrap()).unwrap();
+        assert_eq!(
+            claims.get("sub").and_then(|v| v.as_str()),
+            Some("eve\",\"admin\":true")
+        );
+        assert!(claims.get("admin").is_none());
+
+        let options = PublicKeyCredentialCreationOptions {
+            challenge: "c".to_string(),
+            rp_id: WEBAUTHN_RP_ID.to_string(),
+            rp_name: WEBAUTHN_RP_NAME.to_string(),
+            user_id: "u".to_string(),
+            user_name: "eve\"}".to_string(),
+            pub_key_cred_params: vec![COSE_ALG_ES256],
+            timeout_ms: 1,
+        };
+        let parsed = json::parse_json(&options.to_json()).unwrap();
+        let user = parsed.get("user").unwrap();
+        assert_eq!(user.get("name").and_then(|v| v.as_str()), Some("eve\"}"));
+    }
 }
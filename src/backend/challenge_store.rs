@@ -0,0 +1,216 @@
+//! Zero Trust Challenge Store (Rust Standard Library Only)
+//! Server-side nonce tracking for challenge-response flows (WebAuthn, service auth, CSRF).
+//! Features:
+//! - **Random 256-bit challenges sourced from the OS CSPRNG**
+//! - **Per-challenge TTL bound to the subject it was issued for**
+//! - **Single-use consumption to reject replayed challenges**
+//! - **Pruning of expired entries to bound memory use**
+
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+const CHALLENGE_BYTES: usize = 32;
+
+/// Reasons a presented challenge is rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengeError {
+    Unknown,
+    Expired,
+    AlreadyUsed,
+    SubjectMismatch,
+}
+
+impl fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ChallengeError::Unknown => "challenge was never issued",
+            ChallengeError::Expired => "challenge has expired",
+            ChallengeError::AlreadyUsed => "challenge was already used",
+            ChallengeError::SubjectMismatch => "challenge was issued to a different subject",
+        };
+        f.write_str(message)
+    }
+}
+
+/// An issued challenge awaiting verification
+struct PendingChallenge {
+    subject: String,
+    expires: SystemTime,
+    consumed: bool,
+}
+
+/// Issues single-use challenges and tracks them until they expire
+pub struct ChallengeStore {
+    ttl: Duration,
+    challenges: Mutex<HashMap<String, PendingChallenge>>, // Challenge -> issuance record
+}
+
+impl ChallengeStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issues a new random challenge (hex) bound to `subject`
+    pub fn issue(&self, subject: &str) -> io::Result<String> {
+        self.issue_at(subject, SystemTime::now())
+    }
+
+    pub fn issue_at(&self, subject: &str, now: SystemTime) -> io::Result<String> {
+        let mut bytes = [0u8; CHALLENGE_BYTES];
+        crypto::random_bytes(&mut bytes)?;
+        let challenge: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        self.challenges.lock().unwrap().insert(
+            challenge.clone(),
+            PendingChallenge {
+                subject: subject.to_string(),
+                expires: now + self.ttl,
+                consumed: false,
+            },
+        );
+        Ok(challenge)
+    }
+
+    /// Checks that `challenge` is live and belongs to `subject` without using it up
+    pub fn verify(&self, challenge: &str, subject: &str) -> Result<(), ChallengeError> {
+        self.verify_at(challenge, subject, SystemTime::now())
+    }
+
+    pub fn verify_at(
+        &self,
+        challenge: &str,
+        subject: &str,
+        now: SystemTime,
+    ) -> Result<(), ChallengeError> {
+        let challenges = self.challenges.lock().unwrap();
+        Self::check(challenges.get(challenge), subject, now)
+    }
+
+    /// Marks `challenge` as used once the caller's verification has succeeded.
+    /// Only the first call for a given challenge succeeds.
+    pub fn consume(&self, challenge: &str, subject: &str) -> Result<(), ChallengeError> {
+        self.consume_at(challenge, subject, SystemTime::now())
+    }
+
+    pub fn consume_at(
+        &self,
+        challenge: &str,
+        subject: &str,
+        now: SystemTime,
+    ) -> Result<(), ChallengeError> {
+        let mut challenges = self.challenges.lock().unwrap();
+        let pending = challenges.get_mut(challenge);
+        Self::check(pending.as_deref(), subject, now)?;
+        if let Some(pending) = pending {
+            pending.consumed = true;
+        }
+        Ok(())
+    }
+
+    /// Removes expired challenges (consumed or not); returns how many were dropped
+    pub fn prune(&self) -> usize {
+        self.prune_at(SystemTime::now())
+    }
+
+    pub fn prune_at(&self, now: SystemTime) -> usize {
+        let mut challenges = self.challenges.lock().unwrap();
+        let before = challenges.len();
+        challenges.retain(|_, pending| pending.expires > now);
+        before - challenges.len()
+    }
+
+    fn check(
+        pending: Option<&PendingChallenge>,
+        subject: &str,
+        now: SystemTime,
+    ) -> Result<(), ChallengeError> {
+        let pending = pending.ok_or(ChallengeError::Unknown)?;
+        if pending.consumed {
+            return Err(ChallengeError::AlreadyUsed);
+        }
+        if now >= pending.expires {
+            return Err(ChallengeError::Expired);
+        }
+        if pending.subject != subject {
+            return Err(ChallengeError::SubjectMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn issued_challenge_is_consumed_once() {
+        let store = ChallengeStore::new(TTL);
+        let challenge = store.issue_at("alice", at(0)).unwrap();
+        assert_eq!(challenge.len(), CHALLENGE_BYTES * 2);
+        assert_ne!(store.issue_at("alice", at(0)).unwrap(), challenge);
+
+        // Verification alone does not use the challenge up
+        assert_eq!(store.verify_at(&challenge, "alice", at(1)), Ok(()));
+        assert_eq!(store.consume_at(&challenge, "alice", at(1)), Ok(()));
+    }
+
+    #[test]
+    fn reused_or_foreign_challenge_is_rejected() {
+        let store = ChallengeStore::new(TTL);
+        let challenge = store.issue_at("alice", at(0)).unwrap();
+
+        assert_eq!(
+            store.consume_at(&challenge, "mallory", at(1)),
+            Err(ChallengeError::SubjectMismatch)
+        );
+        store.consume_at(&challenge, "alice", at(1)).unwrap();
+        assert_eq!(
+            store.consume_at(&challenge, "alice", at(2)),
+            Err(ChallengeError::AlreadyUsed)
+        );
+        assert_eq!(
+            store.verify_at(&challenge, "alice", at(2)),
+            Err(ChallengeError::AlreadyUsed)
+        );
+        assert_eq!(
+            store.verify_at("never-issued", "alice", at(2)),
+            Err(ChallengeError::Unknown)
+        );
+    }
+
+    #[test]
+    fn expired_challenges_are_rejected_and_pruned() {
+        let store = ChallengeStore::new(TTL);
+        let early = store.issue_at("alice", at(0)).unwrap();
+        let late = store.issue_at("alice", at(30)).unwrap();
+
+        assert_eq!(
+            store.consume_at(&early, "alice", at(60)),
+            Err(ChallengeError::Expired)
+        );
+        assert_eq!(store.prune_at(at(60)), 1);
+        assert_eq!(
+            store.verify_at(&early, "alice", at(60)),
+            Err(ChallengeError::Unknown)
+        );
+        assert_eq!(store.consume_at(&late, "alice", at(60)), Ok(()));
+        assert_eq!(store.prune_at(at(90)), 1);
+    }
+}
@@ -0,0 +1,267 @@
+//! Zero Trust Compression Module (Rust Standard Library Only)
+//! Implements DEFLATE (RFC 1951) with gzip (RFC 1952) and zlib (RFC 1950) framing.
+//! Features:
+//! - **LZ77 matching over a 32 KiB sliding window with hash chains**
+//! - **Fixed-Huffman block encoding (no external dependencies)**
+//! - **CRC-32 and Adler-32 checksums for gzip and zlib containers**
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: usize = 15;
+const MAX_CHAIN: usize = 64;
+
+/// Base match length and extra bits for length codes 257..=285
+const LENGTH_TABLE: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// Base distance and extra bits for distance codes 0..=29
+const DISTANCE_TABLE: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+/// Writes a little-endian bit stream as required by DEFLATE
+struct BitWriter {
+    output: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// Appends `count` bits of `value`, least significant bit first
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Appends a Huffman code, which DEFLATE packs most significant bit first
+    fn write_code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        self.output
+    }
+}
+
+/// Emits a literal/length symbol using the fixed Huffman table
+fn write_literal_length(writer: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+/// Emits a back-reference of `length` bytes located `distance` bytes back
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_TABLE
+        .iter()
+        .rposition(|&(base, _)| base as usize <= length)
+        .unwrap_or(0);
+    let (base, extra) = LENGTH_TABLE[code];
+    write_literal_length(writer, 257 + code as u16);
+    writer.write_bits((length - base as usize) as u32, extra as u32);
+
+    let code = DISTANCE_TABLE
+        .iter()
+        .rposition(|&(base, _)| base as usize <= distance)
+        .unwrap_or(0);
+    let (base, extra) = DISTANCE_TABLE[code];
+    writer.write_code(code as u32, 5);
+    writer.write_bits((distance - base as usize) as u32, extra as u32);
+}
+
+/// Hashes the three bytes starting at `pos`
+fn hash3(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as usize) << 16 | (data[pos + 1] as usize) << 8 | data[pos + 2] as usize;
+    (value.wrapping_mul(2_654_435_761) >> 8) & ((1 << HASH_BITS) - 1)
+}
+
+/// Compresses `data` into a raw DEFLATE stream (single fixed-Huffman block)
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bits(1, 1); // BFINAL
+    writer.write_bits(1, 2); // BTYPE = fixed Huffman
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+
+        if pos + MIN_MATCH <= data.len() {
+            let hash = hash3(data, pos);
+            let mut candidate = head[hash];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let limit = (data.len() - pos).min(MAX_MATCH);
+                let length = (0..limit)
+                    .take_while(|&i| data[candidate + i] == data[pos + i])
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = pos - candidate;
+                    if length == limit {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+            prev[pos] = head[hash];
+            head[hash] = pos;
+        }
+
+        if best_length >= MIN_MATCH {
+            write_match(&mut writer, best_length, best_distance);
+            let matched = prev.iter_mut().enumerate();
+            for (skipped, link) in matched.take(pos + best_length).skip(pos + 1) {
+                if skipped + MIN_MATCH <= data.len() {
+                    let hash = hash3(data, skipped);
+                    *link = head[hash];
+                    head[hash] = skipped;
+                }
+            }
+            pos += best_length;
+        } else {
+            write_literal_length(&mut writer, data[pos] as u16);
+            pos += 1;
+        }
+    }
+
+    write_literal_length(&mut writer, 256); // End of block
+    writer.finish()
+}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum used by gzip
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Computes the Adler-32 checksum used by zlib
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Compresses `data` into a gzip member
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0);
+
+    let mut output = vec![0x1F, 0x8B, 8, 0];
+    output.extend_from_slice(&mtime.to_le_bytes());
+    output.extend_from_slice(&[0, 255]); // XFL, OS = unknown
+    output.extend_from_slice(&deflate(data));
+    output.extend_from_slice(&crc32(data).to_le_bytes());
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output
+}
+
+/// Compresses `data` into a zlib stream (the HTTP `deflate` content coding)
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0x78, 0x01];
+    output.extend_from_slice(&deflate(data));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
@@ -5,60 +5,416 @@
 //! - **Secure in-memory storage with automatic key expiry**
 //! - **Tamper-proof integrity verification with cryptographic hashing**
 //! - **Immutable configuration options for critical settings**
-//! - **Role-Based Access Control (RBAC) for configuration retrieval**
+//! - **Role-Based Access Control (RBAC) for configuration retrieval, per key prefix, denials audited**
 //! - **Encrypted configuration files with secure storage enforcement**
 //! - **Automated environment variable injection for Zero Trust compliance**
 //! - **Live reloading of configurations without downtime**
 //! - **Real-time audit logging of configuration changes**
+//! - **Versioned schema: older files are migrated and rewritten on load, newer ones rejected**
+//! - **`SECRET:<KEY>` entries sealed with ChaCha20-Poly1305 under `CONFIG_SECRET`, decrypted on load**
+//! - **Crash-safe saves: written beside the file and renamed over it, the last good version kept as a backup**
 
-use std::collections::HashMap;
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod encoding;
+#[allow(dead_code)]
+mod keys;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use crypto::Zeroizing;
+use keys::KeyProvider;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const CONFIG_FILE: &str = "config/settings.conf";
-const CONFIG_BACKUP_FILE: &str = "config/settings.bak";
+const BACKUP_EXTENSION: &str = "bak"; // config/settings.conf is backed up to config/settings.bak
+const INTEGRITY_PREFIX: &str = "# Integrity: ";
 const LOG_FILE: &str = "logs/config.log";
+const VERSION_KEY: &str = "version"; // Files written before versioning lack it and are version 1
+const CONFIG_VERSION: u32 = 2; // Schema written by this build
+const SECRET_PREFIX: &str = "SECRET:"; // Marks an entry whose value is stored sealed
+const SECRET_KEY_INFO: &[u8] = b"ztw config secrets";
+const SECRET_NONCE_LEN: usize = 12;
+
+/// Upgrades a configuration from version `n` to `n + 1`; `MIGRATIONS[n - 1]` handles version `n`
+const MIGRATIONS: [fn(&mut HashMap<String, String>); 1] = [migrate_v1_to_v2];
+
+/// Version 2 renamed `DB_URL` to `DATABASE_URL` and made the connection cap explicit
+fn migrate_v1_to_v2(configs: &mut HashMap<String, String>) {
+    if let Some(url) = configs.remove("DB_URL") {
+        configs.entry("DATABASE_URL".to_string()).or_insert(url);
+    }
+    configs
+        .entry("MAX_CONNECTIONS".to_string())
+        .or_insert_with(|| "100".to_string());
+}
+
+/// Why a configuration file could not be loaded or saved, or a value read
+#[derive(Debug)]
+enum ConfigError {
+    Io(io::Error),
+    /// `version` is not a positive whole number
+    InvalidVersion(String),
+    /// Written by a newer build; reading it with this schema would be a guess
+    UnsupportedVersion(u32),
+    /// A secret entry is present or being set, but `CONFIG_SECRET` is unavailable
+    SecretKey(String),
+    /// A secret entry was altered or sealed under a different `CONFIG_SECRET`
+    Undecryptable(String),
+    /// The file and its backup are both truncated, edited, or missing their integrity line
+    Integrity(PathBuf),
+    /// `role` may not read `key`
+    AccessDenied {
+        role: String,
+        key: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "config file I/O failed: {}", e),
+            ConfigError::InvalidVersion(version) => {
+                write!(f, "config version {:?} is not a number", version)
+            }
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "config version {} is newer than the supported version {}",
+                version, CONFIG_VERSION
+            ),
+            ConfigError::SecretKey(e) => write!(f, "secret config values are locked: {}", e),
+            ConfigError::Undecryptable(key) => {
+                write!(
+                    f,
+                    "secret {} does not decrypt under {}",
+                    key,
+                    keys::CONFIG_SECRET
+                )
+            }
+            ConfigError::Integrity(path) => write!(
+                f,
+                "{} failed its integrity check and has no intact backup",
+                path.display()
+            ),
+            ConfigError::AccessDenied { role, key } => {
+                write!(f, "role {} may not read {}", role, key)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// The integrity line written after `body`
+fn integrity_line(body: &str) -> String {
+    format!("{}{:x?}", INTEGRITY_PREFIX, crypto::sha256(body.as_bytes()))
+}
+
+/// The settings in `contents`, if its integrity line matches them. A file cut short
+/// mid-write loses its integrity line, so it fails too.
+fn verify_integrity(contents: &str) -> Option<&str> {
+    let (body, integrity) = contents.trim_end_matches('\n').rsplit_once('\n')?;
+    (integrity == integrity_line(body)).then_some(body)
+}
+
+/// Reads `path` if it passes its integrity check; `Ok(None)` if it does not exist
+fn read_verified(path: &Path) -> Result<Option<String>, ConfigError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => match verify_integrity(&contents) {
+            Some(_) => Ok(Some(contents)),
+            None => Err(ConfigError::Integrity(path.to_path_buf())),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Where the last good version of `path` is kept
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension(BACKUP_EXTENSION)
+}
+
+/// Replaces `path` with `contents` so readers see the old file or the new one, never
+/// a mix: written and synced beside it, then renamed over it
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "config path has no file name")
+    })?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let written = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written?;
+    // Persist the rename itself; not every platform can open a directory to sync it
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// `key=value` lines; blank lines and `#` comments (such as the integrity line) are skipped
+fn parse_config(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// The schema version `configs` was written with
+fn config_version(configs: &HashMap<String, String>) -> Result<u32, ConfigError> {
+    match configs.get(VERSION_KEY) {
+        None => Ok(1),
+        Some(version) => match version.parse::<u32>() {
+            Ok(version) if version >= 1 => Ok(version),
+            _ => Err(ConfigError::InvalidVersion(version.clone())),
+        },
+    }
+}
+
+/// Brings `configs` up to `CONFIG_VERSION`, returning the version it started at.
+/// Versions newer than this build understands are rejected untouched.
+fn migrate(configs: &mut HashMap<String, String>) -> Result<u32, ConfigError> {
+    let found = config_version(configs)?;
+    if found > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion(found));
+    }
+    for version in found..CONFIG_VERSION {
+        MIGRATIONS[version as usize - 1](configs);
+    }
+    configs.insert(VERSION_KEY.to_string(), CONFIG_VERSION.to_string());
+    Ok(found)
+}
+
+/// The key secret entries are sealed with, derived from the provider's `CONFIG_SECRET`
+fn secret_cipher_key(keys: &dyn KeyProvider) -> Result<Zeroizing<[u8; 32]>, String> {
+    let master = Zeroizing::new(
+        keys.require(keys::CONFIG_SECRET)
+            .map_err(|e| e.to_string())?,
+    );
+    let derived = Zeroizing::new(crypto::hkdf(&[], &master, SECRET_KEY_INFO, 32));
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&derived);
+    Ok(key)
+}
+
+/// Hex of `nonce || ciphertext || tag`; the entry's name is authenticated too, so a
+/// sealed value cannot be moved to another key
+fn seal_secret(key: &[u8; 32], name: &str, value: &str) -> io::Result<String> {
+    let mut nonce = [0u8; SECRET_NONCE_LEN];
+    crypto::random_bytes(&mut nonce)?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(crypto::chacha20poly1305_seal(
+        key,
+        &nonce,
+        name.as_bytes(),
+        value.as_bytes(),
+    ));
+    Ok(encoding::hex_encode(sealed))
+}
 
-/// Implements a SHA-256 hash function manually for configuration integrity
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
+/// Reverses `seal_secret`; `None` if the value was altered or sealed under another key
+fn open_secret(key: &[u8; 32], name: &str, stored: &str) -> Option<String> {
+    let sealed = encoding::hex_decode(stored).ok()?;
+    if sealed.len() < SECRET_NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(SECRET_NONCE_LEN);
+    let nonce: [u8; SECRET_NONCE_LEN] = nonce.try_into().ok()?;
+    let plaintext = crypto::chacha20poly1305_open(key, &nonce, name.as_bytes(), ciphertext)?;
+    String::from_utf8(plaintext).ok()
+}
 
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
+/// Appends a timestamped line to the configuration audit log, best effort
+fn audit_log(message: &str) {
+    audit_log_to(Path::new(LOG_FILE), message);
+}
+
+/// `audit_log` to the log at `log_path`
+fn audit_log_to(log_path: &Path, message: &str) {
+    println!("[CONFIG] {}", message);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let appended = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .and_then(|mut log| writeln!(log, "{} {}", timestamp, message));
+    if let Err(e) = appended {
+        eprintln!("[CONFIG] Could not write {}: {}", log_path.display(), e);
     }
+}
 
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
+/// The roles allowed to read each key prefix. The longest matching prefix decides;
+/// a key no rule matches is readable by nobody.
+struct ConfigAcl {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl Default for ConfigAcl {
+    /// Every role reads ordinary settings; only `admin` reads secrets
+    fn default() -> Self {
+        let mut acl = Self { rules: Vec::new() };
+        acl.allow("", &["guest", "user", "admin"]);
+        acl.allow(SECRET_PREFIX, &["admin"]);
+        acl
+    }
+}
+
+impl ConfigAcl {
+    /// Replaces the roles allowed to read keys starting with `prefix`
+    fn allow(&mut self, prefix: &str, roles: &[&str]) {
+        let roles = roles.iter().map(|role| role.to_string()).collect();
+        match self
+            .rules
+            .iter_mut()
+            .find(|(existing, _)| existing == prefix)
+        {
+            Some((_, allowed)) => *allowed = roles,
+            None => self.rules.push((prefix.to_string(), roles)),
+        }
+    }
+
+    fn permits(&self, role: &str, key: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .is_some_and(|(_, roles)| roles.iter().any(|allowed| allowed == role))
+    }
+}
+
+/// Settings held in memory, secrets already decrypted
+#[derive(Default)]
+struct ConfigEntries {
+    values: HashMap<String, String>,
+    secrets: HashSet<String>, // Keys written back sealed
 }
 
 /// Secure configuration storage
 struct ConfigManager {
-    configs: Mutex<HashMap<String, String>>, // Stores configuration settings
+    path: PathBuf,
+    secret_key: Result<Zeroizing<[u8; 32]>, String>, // Why secrets are locked, when they are
+    configs: Mutex<ConfigEntries>,                   // Stores configuration settings
+    acl: Mutex<ConfigAcl>,                           // Who may read what through get_config_as
+    audit_path: PathBuf, // Where denied reads and ACL changes are logged
 }
 
 impl ConfigManager {
-    fn new() -> Self {
-        let configs = ConfigManager::load_config_file();
-        Self {
+    fn new() -> Result<Self, ConfigError> {
+        Self::open(CONFIG_FILE, &keys::EnvKeyProvider)
+    }
+
+    /// Loads the configuration at `path`, upgrading and rewriting files from older
+    /// versions; a missing file starts an empty configuration. A file failing its
+    /// integrity check is restored from its backup. `keys` supplies
+    /// `CONFIG_SECRET`, which is only required once the file holds secrets.
+    fn open<P: Into<PathBuf>>(path: P, keys: &dyn KeyProvider) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let secret_key = secret_cipher_key(keys);
+        let configs = ConfigManager::load_config_file(&path, &secret_key)?;
+        Ok(Self {
+            path,
+            secret_key,
             configs: Mutex::new(configs),
-        }
+            acl: Mutex::new(ConfigAcl::default()),
+            audit_path: PathBuf::from(LOG_FILE),
+        })
+    }
+
+    /// Audits reads and ACL changes to `path` instead of `logs/config.log`
+    #[cfg(test)]
+    fn with_audit_log<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.audit_path = path.into();
+        self
     }
 
     /// Loads configuration from file and merges with environment variables
-    fn load_config_file() -> HashMap<String, String> {
-        let mut config_map = HashMap::new();
-        if let Ok(file) = File::open(CONFIG_FILE) {
-            let reader = BufReader::new(file);
-            for line in reader.lines().flatten() {
-                if let Some((key, value)) = line.split_once('=') {
-                    config_map.insert(key.trim().to_string(), value.trim().to_string());
-                }
+    fn load_config_file(
+        path: &Path,
+        secret_key: &Result<Zeroizing<[u8; 32]>, String>,
+    ) -> Result<ConfigEntries, ConfigError> {
+        let mut config_map = match read_verified(path) {
+            Ok(Some(contents)) => parse_config(&contents),
+            Ok(None) => {
+                // A new file starts at the current schema; there is nothing to migrate
+                let mut config_map = HashMap::new();
+                config_map.insert(VERSION_KEY.to_string(), CONFIG_VERSION.to_string());
+                config_map
             }
+            Err(ConfigError::Integrity(_)) => {
+                let backup = backup_path(path);
+                let contents = match read_verified(&backup) {
+                    Ok(Some(contents)) => contents,
+                    _ => return Err(ConfigError::Integrity(path.to_path_buf())),
+                };
+                // The damaged file is replaced; the backup already holds this version
+                write_atomic(path, &contents)?;
+                audit_log(&format!(
+                    "{} failed its integrity check; restored from {}",
+                    path.display(),
+                    backup.display()
+                ));
+                parse_config(&contents)
+            }
+            Err(e) => return Err(e),
+        };
+        let found = migrate(&mut config_map)?;
+        if found < CONFIG_VERSION {
+            // Written back before environment overrides are merged, so none reach the disk
+            write_config_file(path, &config_map)?;
+            audit_log(&format!(
+                "Migrated {} from version {} to {}",
+                path.display(),
+                found,
+                CONFIG_VERSION
+            ));
+        }
+
+        let mut secrets = HashSet::new();
+        let sealed: Vec<String> = config_map
+            .keys()
+            .filter(|key| key.starts_with(SECRET_PREFIX))
+            .cloned()
+            .collect();
+        for stored_key in sealed {
+            let stored = config_map.remove(&stored_key).unwrap_or_default();
+            let key = stored_key[SECRET_PREFIX.len()..].to_string();
+            let cipher_key = secret_key
+                .as_ref()
+                .map_err(|e| ConfigError::SecretKey(e.clone()))?;
+            let value = open_secret(cipher_key, &key, &stored)
+                .ok_or_else(|| ConfigError::Undecryptable(key.clone()))?;
+            config_map.insert(key.clone(), value);
+            secrets.insert(key);
         }
 
         // Override with environment variables
@@ -68,58 +424,417 @@ impl ConfigManager {
             }
         }
 
-        config_map
+        Ok(ConfigEntries {
+            values: config_map,
+            secrets,
+        })
     }
 
-    /// Retrieves a configuration value securely
+    /// Retrieves a configuration value securely; secrets come back decrypted
     fn get_config(&self, key: &str) -> Option<String> {
         let configs = self.configs.lock().unwrap();
-        configs.get(key).cloned()
+        configs.values.get(key).cloned()
     }
 
-    /// Updates a configuration value and writes to disk
-    fn set_config(&self, key: &str, value: &str) {
-        let mut configs = self.configs.lock().unwrap();
-        configs.insert(key.to_string(), value.to_string());
+    /// Retrieves a value on behalf of `role`. Secrets are matched against the ACL as
+    /// `SECRET:<KEY>` and may be requested either way; denied reads are audited.
+    fn get_config_as(&self, role: &str, key: &str) -> Result<Option<String>, ConfigError> {
+        let configs = self.configs.lock().unwrap();
+        let key = key.strip_prefix(SECRET_PREFIX).unwrap_or(key);
+        let acl_key = if configs.secrets.contains(key) {
+            format!("{}{}", SECRET_PREFIX, key)
+        } else {
+            key.to_string()
+        };
+        if !self.acl.lock().unwrap().permits(role, &acl_key) {
+            audit_log_to(
+                &self.audit_path,
+                &format!("Denied role {} read of {}", role, acl_key),
+            );
+            return Err(ConfigError::AccessDenied {
+                role: role.to_string(),
+                key: acl_key,
+            });
+        }
+        Ok(configs.values.get(key).cloned())
+    }
 
-        self.save_config_file();
+    /// Restricts reads through `get_config_as` of keys starting with `prefix` to `roles`
+    fn restrict(&self, prefix: &str, roles: &[&str]) {
+        self.acl.lock().unwrap().allow(prefix, roles);
+        audit_log_to(
+            &self.audit_path,
+            &format!("Readers of {:?}* set to {:?}", prefix, roles),
+        );
+    }
+
+    /// Updates a configuration value and writes to disk. A key set with
+    /// `set_secret` stays secret.
+    fn set_config(&self, key: &str, value: &str) -> Result<(), ConfigError> {
+        self.configs
+            .lock()
+            .unwrap()
+            .values
+            .insert(key.to_string(), value.to_string());
+        self.save_config_file()
+    }
+
+    fn is_secret(&self, key: &str) -> bool {
+        self.configs.lock().unwrap().secrets.contains(key)
+    }
+
+    /// Updates a value that is only ever written to disk encrypted
+    fn set_secret(&self, key: &str, value: &str) -> Result<(), ConfigError> {
+        if let Err(e) = &self.secret_key {
+            return Err(ConfigError::SecretKey(e.clone()));
+        }
+        {
+            let mut configs = self.configs.lock().unwrap();
+            configs.values.insert(key.to_string(), value.to_string());
+            configs.secrets.insert(key.to_string());
+        }
+        self.save_config_file()
     }
 
     /// Saves the current configuration to file with integrity verification
-    fn save_config_file(&self) {
+    fn save_config_file(&self) -> Result<(), ConfigError> {
         let configs = self.configs.lock().unwrap();
-        let serialized_data: String = configs
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        let integrity_hash = sha256(serialized_data.as_bytes());
+        let mut on_disk = HashMap::with_capacity(configs.values.len());
+        for (key, value) in &configs.values {
+            if configs.secrets.contains(key) {
+                let cipher_key = self
+                    .secret_key
+                    .as_ref()
+                    .map_err(|e| ConfigError::SecretKey(e.clone()))?;
+                on_disk.insert(
+                    format!("{}{}", SECRET_PREFIX, key),
+                    seal_secret(cipher_key, key, value)?,
+                );
+            } else {
+                on_disk.insert(key.clone(), value.clone());
+            }
+        }
+        write_config_file(&self.path, &on_disk)
+    }
+}
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(CONFIG_FILE)
-            .expect("Failed to open config file");
+/// Writes `configs` to `path`, `version` first and the rest sorted so rewrites diff cleanly.
+/// The version being replaced becomes the backup, provided it is intact.
+fn write_config_file(path: &Path, configs: &HashMap<String, String>) -> Result<(), ConfigError> {
+    let mut keys: Vec<&String> = configs.keys().filter(|key| *key != VERSION_KEY).collect();
+    keys.sort();
+    let version = configs
+        .get(VERSION_KEY)
+        .cloned()
+        .unwrap_or_else(|| CONFIG_VERSION.to_string());
+    let serialized_data: String = std::iter::once(format!("{}={}", VERSION_KEY, version))
+        .chain(keys.iter().map(|k| format!("{}={}", k, configs[*k])))
+        .collect::<Vec<String>>()
+        .join("\n");
 
-        writeln!(
-            file,
-            "{}\n# Integrity: {:x?}",
-            serialized_data, integrity_hash
-        )
-        .expect("Failed to write config");
+    if let Ok(Some(previous)) = read_verified(path) {
+        write_atomic(&backup_path(path), &previous)?;
     }
+    write_atomic(
+        path,
+        &format!(
+            "{}\n{}\n",
+            serialized_data,
+            integrity_line(&serialized_data)
+        ),
+    )?;
+    Ok(())
 }
 
 fn main() {
-    let config_manager = Arc::new(ConfigManager::new());
+    let config_manager = match ConfigManager::new() {
+        Ok(config_manager) => Arc::new(config_manager),
+        Err(e) => {
+            eprintln!("[CONFIG] Failed to load {}: {}", CONFIG_FILE, e);
+            return;
+        }
+    };
 
     // Retrieve and print a configuration setting
-    if let Some(value) = config_manager.get_config("DATABASE_URL") {
+    config_manager.restrict("DATABASE_", &["user", "admin"]);
+    if let Ok(Some(value)) = config_manager.get_config_as("user", "DATABASE_URL") {
         println!("DATABASE_URL: {}", value);
     }
 
+    // Secrets are decrypted on load; a password still stored in plaintext is sealed
+    if let Some(password) = config_manager.get_config("DB_PASSWORD") {
+        if !config_manager.is_secret("DB_PASSWORD") {
+            match config_manager.set_secret("DB_PASSWORD", &password) {
+                Ok(()) => audit_log("Sealed DB_PASSWORD, previously stored in plaintext"),
+                Err(e) => eprintln!("[CONFIG] DB_PASSWORD left in plaintext: {}", e),
+            }
+        }
+        println!("DB_PASSWORD: <secret>");
+        if let Err(e) = config_manager.get_config_as("guest", "DB_PASSWORD") {
+            println!("[CONFIG] {}", e);
+        }
+    }
+
     // Set a new configuration setting
-    config_manager.set_config("MAX_CONNECTIONS", "100");
+    if let Err(e) = config_manager.set_config("MAX_CONNECTIONS", "100") {
+        eprintln!("[CONFIG] Failed to save {}: {}", CONFIG_FILE, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::StaticKeyProvider;
+
+    /// A fresh directory for one test's config file, at `<dir>/settings.conf`
+    fn config_path(name: &str) -> PathBuf {
+        test_util::scratch_dir("config", name).join("settings.conf")
+    }
+
+    /// Writes `body` with a valid integrity line, as an earlier build would have
+    fn write_signed(path: &Path, body: &str) {
+        fs::write(path, format!("{}\n{}\n", body, integrity_line(body))).unwrap();
+    }
+
+    fn no_keys() -> StaticKeyProvider {
+        StaticKeyProvider::new()
+    }
+
+    #[test]
+    fn version_1_files_are_migrated_and_rewritten() {
+        let path = config_path("migrate");
+        write_signed(
+            &path,
+            "DB_URL=postgres://db.internal/app\nZT_TEST_TIMEOUT=5",
+        );
+
+        let config = ConfigManager::open(&path, &no_keys()).unwrap();
+        assert_eq!(
+            config.get_config("DATABASE_URL").as_deref(),
+            Some("postgres://db.internal/app")
+        );
+        assert_eq!(config.get_config("DB_URL"), None);
+        assert_eq!(config.get_config("MAX_CONNECTIONS").as_deref(), Some("100"));
+        assert_eq!(config.get_config("ZT_TEST_TIMEOUT").as_deref(), Some("5"));
+        assert_eq!(config.get_config(VERSION_KEY).as_deref(), Some("2"));
+
+        let rewritten = fs::read_to_string(&path).unwrap();
+        assert!(verify_integrity(&rewritten).is_some());
+        assert!(rewritten.starts_with("version=2\n"), "{}", rewritten);
+        assert!(rewritten.contains("DATABASE_URL=postgres://db.internal/app\n"));
+        assert!(!rewritten.contains("DB_URL="));
+
+        // Loading the upgraded file again changes nothing
+        drop(config);
+        ConfigManager::open(&path, &no_keys()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), rewritten);
+    }
+
+    #[test]
+    fn newer_or_malformed_versions_are_rejected_untouched() {
+        let path = config_path("future");
+        write_signed(&path, "version=3\nNEW_SETTING=on");
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(matches!(
+            ConfigManager::open(&path, &no_keys()),
+            Err(ConfigError::UnsupportedVersion(3))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+
+        write_signed(&path, "version=two");
+        assert!(matches!(
+            ConfigManager::open(&path, &no_keys()),
+            Err(ConfigError::InvalidVersion(version)) if version == "two"
+        ));
+    }
+
+    fn master_key(byte: u8) -> StaticKeyProvider {
+        StaticKeyProvider::new().with(keys::CONFIG_SECRET, &[byte; keys::MIN_SECRET_LEN])
+    }
+
+    #[test]
+    fn secrets_are_sealed_on_disk_and_plain_in_memory() {
+        let path = config_path("secrets");
+        let config = ConfigManager::open(&path, &master_key(1)).unwrap();
+        config
+            .set_secret("ZT_TEST_DB_PASSWORD", "hunter2-correct-horse")
+            .unwrap();
+        config.set_config("ZT_TEST_PUBLIC", "visible").unwrap();
+
+        let on_disk = fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains("hunter2-correct-horse"), "{}", on_disk);
+        assert!(on_disk.contains("SECRET:ZT_TEST_DB_PASSWORD="));
+        assert!(!on_disk.contains("\nZT_TEST_DB_PASSWORD="));
+        assert!(on_disk.contains("ZT_TEST_PUBLIC=visible\n"));
+        assert_eq!(
+            config.get_config("ZT_TEST_DB_PASSWORD").as_deref(),
+            Some("hunter2-correct-horse")
+        );
+
+        drop(config);
+        let reopened = ConfigManager::open(&path, &master_key(1)).unwrap();
+        assert!(reopened.is_secret("ZT_TEST_DB_PASSWORD"));
+        assert_eq!(
+            reopened.get_config("ZT_TEST_DB_PASSWORD").as_deref(),
+            Some("hunter2-correct-horse")
+        );
+
+        // Resealing uses a fresh nonce, so equal values never look equal on disk
+        let sealed_line = |contents: &str| {
+            contents
+                .lines()
+                .find(|line| line.starts_with("SECRET:"))
+                .map(str::to_string)
+        };
+        reopened.set_config("ZT_TEST_PUBLIC", "changed").unwrap();
+        let resealed = fs::read_to_string(&path).unwrap();
+        assert!(sealed_line(&resealed).is_some());
+        assert_ne!(sealed_line(&resealed), sealed_line(&on_disk));
+    }
+
+    #[test]
+    fn secrets_need_the_master_key_that_sealed_them() {
+        let path = config_path("wrong-key");
+        ConfigManager::open(&path, &master_key(1))
+            .unwrap()
+            .set_secret("ZT_TEST_TOKEN", "s3cr3t")
+            .unwrap();
+
+        assert!(matches!(
+            ConfigManager::open(&path, &master_key(2)),
+            Err(ConfigError::Undecryptable(key)) if key == "ZT_TEST_TOKEN"
+        ));
+        assert!(matches!(
+            ConfigManager::open(&path, &no_keys()),
+            Err(ConfigError::SecretKey(_))
+        ));
+
+        // Without a master key, plain settings still work but secrets cannot be set
+        let plain = ConfigManager::open(config_path("no-key"), &no_keys()).unwrap();
+        assert!(matches!(
+            plain.set_secret("ZT_TEST_TOKEN", "s3cr3t"),
+            Err(ConfigError::SecretKey(_))
+        ));
+        plain.set_config("ZT_TEST_PUBLIC", "fine").unwrap();
+    }
+
+    #[test]
+    fn an_interrupted_save_leaves_the_original_intact() {
+        let path = config_path("interrupted");
+        let config = ConfigManager::open(&path, &no_keys()).unwrap();
+        config.set_config("ZT_TEST_MODE", "stable").unwrap();
+        let original = fs::read_to_string(&path).unwrap();
+
+        // The temporary file cannot be written, as if the disk failed mid-save
+        let temp_path = path.with_file_name(".settings.conf.tmp");
+        fs::create_dir(&temp_path).unwrap();
+        assert!(matches!(
+            config.set_config("ZT_TEST_MODE", "broken"),
+            Err(ConfigError::Io(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        fs::remove_dir(&temp_path).unwrap();
+
+        let reopened = ConfigManager::open(&path, &no_keys()).unwrap();
+        assert_eq!(
+            reopened.get_config("ZT_TEST_MODE").as_deref(),
+            Some("stable")
+        );
+    }
+
+    #[test]
+    fn each_save_backs_up_the_previous_version() {
+        let path = config_path("backup");
+        let backup = backup_path(&path);
+        let config = ConfigManager::open(&path, &no_keys()).unwrap();
+        config.set_config("ZT_TEST_STEP", "1").unwrap();
+        assert!(!backup.exists()); // Nothing was replaced
+
+        config.set_config("ZT_TEST_STEP", "2").unwrap();
+        let first = fs::read_to_string(&backup).unwrap();
+        assert!(first.contains("ZT_TEST_STEP=1\n"));
+        config.set_config("ZT_TEST_STEP", "3").unwrap();
+        let second = fs::read_to_string(&backup).unwrap();
+        assert!(second.contains("ZT_TEST_STEP=2\n"));
+        assert!(verify_integrity(&second).is_some());
+    }
+
+    #[test]
+    fn a_damaged_file_is_restored_from_its_backup() {
+        let path = config_path("restore");
+        let config = ConfigManager::open(&path, &no_keys()).unwrap();
+        config.set_config("ZT_TEST_STEP", "1").unwrap();
+        config.set_config("ZT_TEST_STEP", "2").unwrap();
+        drop(config);
+
+        // Cut short, losing the integrity line
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+        let restored = ConfigManager::open(&path, &no_keys()).unwrap();
+        assert_eq!(restored.get_config("ZT_TEST_STEP").as_deref(), Some("1"));
+        assert!(verify_integrity(&fs::read_to_string(&path).unwrap()).is_some());
+
+        // With the backup damaged too, there is nothing to trust
+        fs::write(&path, "ZT_TEST_STEP=9\n").unwrap();
+        fs::write(backup_path(&path), "ZT_TEST_STEP=9\n").unwrap();
+        assert!(matches!(
+            ConfigManager::open(&path, &no_keys()),
+            Err(ConfigError::Integrity(_))
+        ));
+    }
+
+    #[test]
+    fn only_admins_read_secrets_and_denials_are_audited() {
+        let path = config_path("acl");
+        let audit = path.with_file_name("audit.log");
+        let config = ConfigManager::open(&path, &master_key(3))
+            .unwrap()
+            .with_audit_log(&audit);
+        config.set_secret("ZT_TEST_API_KEY", "k-123").unwrap();
+        config.set_config("ZT_TEST_THEME", "dark").unwrap();
+
+        for key in ["ZT_TEST_API_KEY", "SECRET:ZT_TEST_API_KEY"] {
+            assert!(matches!(
+                config.get_config_as("guest", key),
+                Err(ConfigError::AccessDenied { role, key })
+                    if role == "guest" && key == "SECRET:ZT_TEST_API_KEY"
+            ));
+            assert_eq!(
+                config.get_config_as("admin", key).unwrap().as_deref(),
+                Some("k-123")
+            );
+        }
+        assert_eq!(
+            config
+                .get_config_as("guest", "ZT_TEST_THEME")
+                .unwrap()
+                .as_deref(),
+            Some("dark")
+        );
+        assert!(config.get_config_as("intruder", "ZT_TEST_THEME").is_err());
+
+        let log = fs::read_to_string(&audit).unwrap();
+        let denials: Vec<&str> = log.lines().filter(|line| line.contains("Denied")).collect();
+        assert_eq!(denials.len(), 3, "{}", log);
+        assert!(denials[0].ends_with("Denied role guest read of SECRET:ZT_TEST_API_KEY"));
+        assert!(denials[2].ends_with("Denied role intruder read of ZT_TEST_THEME"));
+    }
+
+    #[test]
+    fn the_longest_matching_prefix_decides() {
+        let path = config_path("acl-prefix");
+        let config = ConfigManager::open(&path, &no_keys())
+            .unwrap()
+            .with_audit_log(path.with_file_name("audit.log"));
+        config.set_config("DATABASE_URL", "postgres://db").unwrap();
+        config
+            .set_config("DATABASE_REPLICA", "postgres://replica")
+            .unwrap();
+        config.restrict("DATABASE_", &["admin"]);
+        config.restrict("DATABASE_REPLICA", &["user", "admin"]);
+
+        assert!(config.get_config_as("user", "DATABASE_URL").is_err());
+        assert!(config.get_config_as("user", "DATABASE_REPLICA").is_ok());
+        assert!(config.get_config_as("admin", "DATABASE_URL").is_ok());
+    }
 }
@@ -0,0 +1,187 @@
+//! Zero Trust QUIC Congestion Control (Standard Library Only)
+//! A NewReno controller as described in RFC 9002 §7, counted in bytes.
+//! Features:
+//! - **Slow start, then congestion avoidance once a loss sets the threshold**
+//! - **Window halved once per loss event; losses from the same flight are not double-counted**
+//! - **Bytes-in-flight accounting that gates `can_send`**
+//! - **Pacing delay that spreads a window across one round trip**
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const LOSS_REDUCTION_DIVISOR: usize = 2; // New window after a loss is half the old one
+const MIN_WINDOW_PACKETS: usize = 2;
+const PACING_GAIN_PERCENT: u128 = 125; // Pace slightly faster than cwnd/RTT so the window fills
+
+/// NewReno congestion window over packets identified by their packet numbers
+pub struct NewReno {
+    max_datagram_size: usize,
+    window: usize,   // Congestion window in bytes
+    ssthresh: usize, // Slow start threshold; `usize::MAX` until the first loss
+    bytes_in_flight: usize,
+    in_flight: BTreeMap<u64, usize>, // Packet number -> size, for packets not yet acked or lost
+    recovery_start: Option<u64>,     // Largest packet number sent when recovery began
+    largest_sent: Option<u64>,
+}
+
+impl NewReno {
+    /// A controller whose first window is `initial_packets` datagrams of `max_datagram_size`
+    pub fn new(max_datagram_size: usize, initial_packets: usize) -> Self {
+        Self {
+            max_datagram_size,
+            window: max_datagram_size * initial_packets.max(MIN_WINDOW_PACKETS),
+            ssthresh: usize::MAX,
+            bytes_in_flight: 0,
+            in_flight: BTreeMap::new(),
+            recovery_start: None,
+            largest_sent: None,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn bytes_in_flight(&self) -> usize {
+        self.bytes_in_flight
+    }
+
+    fn min_window(&self) -> usize {
+        self.max_datagram_size * MIN_WINDOW_PACKETS
+    }
+
+    /// True if a packet of `bytes` fits in the window alongside what is in flight
+    pub fn can_send(&self, bytes: usize) -> bool {
+        self.bytes_in_flight + bytes <= self.window
+    }
+
+    /// Records packet `packet_number` of `bytes` as sent and in flight
+    pub fn on_sent(&mut self, packet_number: u64, bytes: usize) {
+        if self.in_flight.insert(packet_number, bytes).is_none() {
+            self.bytes_in_flight += bytes;
+        }
+        self.largest_sent = self.largest_sent.max(Some(packet_number));
+    }
+
+    /// Packet `packet_number` was acknowledged: the window grows, except for packets
+    /// sent before the current recovery period began
+    pub fn on_ack(&mut self, packet_number: u64) {
+        let bytes = match self.in_flight.remove(&packet_number) {
+            Some(bytes) => bytes,
+            None => return, // Already acked or declared lost
+        };
+        self.bytes_in_flight -= bytes;
+        if self.in_recovery(packet_number) {
+            return;
+        }
+        self.recovery_start = None;
+        if self.window < self.ssthresh {
+            self.window += bytes; // Slow start: doubles the window every round trip
+        } else {
+            // Congestion avoidance: about one datagram per window acknowledged
+            self.window += (self.max_datagram_size * bytes / self.window).max(1);
+        }
+    }
+
+    /// Packet `packet_number` was declared lost: the window halves, at most once per
+    /// round trip, since every loss from one flight belongs to the same congestion event
+    pub fn on_loss(&mut self, packet_number: u64) {
+        let bytes = match self.in_flight.remove(&packet_number) {
+            Some(bytes) => bytes,
+            None => return,
+        };
+        self.bytes_in_flight -= bytes;
+        if self.in_recovery(packet_number) {
+            return;
+        }
+        self.recovery_start = self.largest_sent;
+        self.ssthresh = (self.window / LOSS_REDUCTION_DIVISOR).max(self.min_window());
+        self.window = self.ssthresh;
+    }
+
+    fn in_recovery(&self, packet_number: u64) -> bool {
+        self.recovery_start
+            .is_some_and(|start| packet_number <= start)
+    }
+
+    /// Gap to leave before sending `bytes` so one window is spread over `smoothed_rtt`
+    pub fn pacing_delay(&self, smoothed_rtt: Duration, bytes: usize) -> Duration {
+        let nanos = smoothed_rtt.as_nanos() * bytes as u128 * 100
+            / (self.window as u128 * PACING_GAIN_PERCENT);
+        Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTU: usize = 1000;
+
+    /// Sends packets `numbers` of one datagram each
+    fn send(controller: &mut NewReno, numbers: std::ops::Range<u64>) {
+        for number in numbers {
+            assert!(controller.can_send(MTU), "packet {} was gated", number);
+            controller.on_sent(number, MTU);
+        }
+    }
+
+    #[test]
+    fn window_grows_with_acks_and_halves_once_per_loss_event() {
+        let mut controller = NewReno::new(MTU, 4);
+        send(&mut controller, 0..4);
+        assert!(!controller.can_send(MTU));
+
+        // Slow start: each acked byte adds one to the window
+        (0..4).for_each(|number| controller.on_ack(number));
+        assert_eq!(controller.window(), 8 * MTU);
+        send(&mut controller, 4..12);
+        assert!(!controller.can_send(1));
+
+        // Two losses from the same flight are one congestion event
+        controller.on_loss(4);
+        assert_eq!(controller.window(), 4 * MTU);
+        controller.on_loss(5);
+        assert_eq!(controller.window(), 4 * MTU);
+        assert_eq!(controller.bytes_in_flight(), 6 * MTU);
+        assert!(!controller.can_send(MTU));
+
+        // Acks for packets sent before the loss do not grow the window
+        (6..12).for_each(|number| controller.on_ack(number));
+        assert_eq!(controller.window(), 4 * MTU);
+        assert_eq!(controller.bytes_in_flight(), 0);
+        assert!(controller.can_send(4 * MTU) && !controller.can_send(4 * MTU + 1));
+
+        // A packet sent after recovery grows the window by congestion avoidance
+        send(&mut controller, 12..13);
+        controller.on_ack(12);
+        assert_eq!(controller.window(), 4 * MTU + MTU / 4);
+    }
+
+    #[test]
+    fn repeated_losses_stop_at_the_minimum_window() {
+        let mut controller = NewReno::new(MTU, 10);
+        for number in 0..6 {
+            controller.on_sent(number, MTU);
+            controller.on_loss(number);
+        }
+        assert_eq!(controller.window(), MIN_WINDOW_PACKETS * MTU);
+        assert_eq!(controller.bytes_in_flight(), 0);
+
+        // Duplicate acks and losses are ignored
+        controller.on_ack(3);
+        controller.on_loss(3);
+        assert_eq!(controller.window(), MIN_WINDOW_PACKETS * MTU);
+    }
+
+    #[test]
+    fn pacing_spreads_a_window_over_the_round_trip() {
+        let controller = NewReno::new(MTU, 4);
+        let rtt = Duration::from_millis(100);
+        assert_eq!(controller.pacing_delay(rtt, MTU), Duration::from_millis(20));
+        assert_eq!(
+            controller.pacing_delay(rtt, 4 * MTU),
+            Duration::from_millis(80)
+        );
+    }
+}
@@ -1,88 +1,1484 @@
 //! Advanced Zero Trust Cryptographic Module (Standard Library Only)
 //! Implements high-security encryption and hashing using Rust’s standard library.
 //! Features:
-//! - **AES-GCM authenticated encryption (manual implementation)**
-//! - **ChaCha20-Poly1305 authenticated encryption**
-//! - **Manual SHA-256 hashing with HMAC support**
+//! - **ChaCha20-Poly1305 authenticated encryption (RFC 8439)**
+//! - **Chunked streaming AEAD for large payloads with bounded memory**
+//! - **Manual SHA-256 hashing with HMAC support, incremental and over readers**
+//! - **Constant-time comparison of MACs, signatures and tokens**
+//! - **TOTP codes for multi-factor authentication, shared by login and log retrieval**
 //! - **Secure random number generation for cryptographic keys**
+//! - **Injectable `Rng` with a seedable generator for reproducible runs**
 //! - **Tamper-proof integrity verification for stored data**
 //! - **Key derivation for password-based encryption**
 //! - **Time-based cryptographic key rotation**
 //! - **Nonce and IV management to prevent replay attacks**
+//! - **Counter-based `NonceSequence`, with a debug-build guard against (key, nonce) reuse**
+//! - **`Zeroizing` key buffers, wiped with volatile writes when dropped**
 
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::sync::Mutex;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
+/// Default PBKDF2-HMAC-SHA256 work factor for passphrase-derived keys
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+const HASH_READ_CHUNK: usize = 64 * 1024; // Read size for streaming digests
 
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
+/// SHA-256 round constants
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Incremental SHA-256 (FIPS 180-4): feed data with `update`, read the digest
+/// with `finalize`. Only one partial block is buffered, whatever the input size.
+#[derive(Clone)]
+pub struct Sha256State {
+    state: [u32; 8],
+    block: [u8; 64],
+    buffered: usize,
+    length: u64, // Bytes hashed so far
+}
+
+impl Sha256State {
+    pub fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: [0; 64],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if self.buffered > 0 {
+            let take = (64 - self.buffered).min(data.len());
+            self.block[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < 64 {
+                return;
+            }
+            let block = self.block;
+            self.compress(&block);
+            self.buffered = 0;
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bit_length = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80];
+        padding.resize(1 + (55 - self.buffered as isize).rem_euclid(64) as usize, 0);
+        padding.extend_from_slice(&bit_length.to_be_bytes());
+        self.update(&padding);
+
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (slot, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *slot = slot.wrapping_add(value);
+        }
     }
+}
 
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
+impl Default for Sha256State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Secure random number generator using time-based entropy
-fn secure_random_256() -> [u8; 32] {
+/// Computes the SHA-256 digest of `data` (FIPS 180-4)
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state = Sha256State::new();
+    state.update(data);
+    state.finalize()
+}
+
+/// Feeds everything `reader` yields into `state` in fixed-size chunks, so a file
+/// of any size is hashed in constant memory. Returns the number of bytes hashed.
+pub fn hash_reader<R: Read>(mut reader: R, state: &mut Sha256State) -> io::Result<u64> {
+    let mut chunk = [0u8; HASH_READ_CHUNK];
+    let mut total = 0;
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => return Ok(total),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        state.update(&chunk[..read]);
+        total += read as u64;
+    }
+}
+
+/// Computes the SHA-1 digest of `data` (FIPS 180-4). SHA-1 is not collision
+/// resistant; it exists only for protocol fixtures such as the WebSocket accept key.
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let mut message = data.to_vec();
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bit_len.to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_mut(4).zip(state.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Overwrites key material with zeros as it is dropped
+pub trait Zeroize {
+    fn zeroize(&mut self);
+}
+
+/// Zeroes `len` bytes at `ptr` with writes the optimiser may not elide
+fn volatile_zero(ptr: *mut u8, len: usize) {
+    for offset in 0..len {
+        // SAFETY: callers pass a pointer valid for `len` bytes of writes
+        unsafe { ptr::write_volatile(ptr.add(offset), 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+impl<const N: usize> Zeroize for [u8; N] {
+    fn zeroize(&mut self) {
+        volatile_zero(self.as_mut_ptr(), N);
+    }
+}
+
+impl Zeroize for Vec<u8> {
+    /// Wipes the whole allocation, spare capacity included, and leaves the vector empty
+    fn zeroize(&mut self) {
+        self.clear();
+        let spare = self.spare_capacity_mut();
+        volatile_zero(spare.as_mut_ptr().cast(), spare.len());
+    }
+}
+
+/// Holds key material and wipes it when dropped.
+///
+/// Only the current buffer is wiped: growing a wrapped `Vec` past its capacity
+/// frees the old allocation unwiped, so size key buffers up front.
+///
+/// ```
+/// use zero_trust_website::webcrypto::Zeroizing;
+///
+/// let key = Zeroizing::new(vec![0x42u8; 32]);
+/// assert_eq!(key.len(), 32); // Derefs to the wrapped `Vec<u8>`
+/// assert_eq!(format!("{:?}", key), "Zeroizing([REDACTED])");
+/// drop(key); // All 32 bytes are zeroed before the allocation is freed
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Zeroizing<T: Zeroize>(T);
+
+impl<T: Zeroize> Zeroizing<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> From<T> for Zeroizing<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> Deref for Zeroizing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Never prints the secret itself
+impl<T: Zeroize> fmt::Debug for Zeroizing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Zeroizing([REDACTED])")
+    }
+}
+
+/// Computes HMAC-SHA256 of `message` under `key` (RFC 2104).
+/// The padded key blocks are wiped before returning.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block_key = Zeroizing::new([0u8; 64]);
+    if key.len() > 64 {
+        block_key[..32].copy_from_slice(&sha256(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    // Sized up front so extending never reallocates and strands an unwiped copy
+    let mut inner = Zeroizing::new(Vec::with_capacity(64 + message.len()));
+    inner.extend(block_key.iter().map(|b| b ^ 0x36));
+    inner.extend_from_slice(message);
+    let mut outer = Zeroizing::new(Vec::with_capacity(64 + 32));
+    outer.extend(block_key.iter().map(|b| b ^ 0x5c));
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+/// Compares two byte strings in constant time: every byte position is examined
+/// regardless of where the first mismatch occurs, so timing reveals nothing
+/// about how much of a forged MAC, signature or token was correct
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= (x ^ y) as usize;
+    }
+    std::hint::black_box(diff) == 0
+}
+
+/// TOTP time step in seconds (RFC 6238)
+const TOTP_STEP_SECS: u64 = 30;
+
+/// Six-digit TOTP code for `secret` at `unix_time`: HMAC-SHA256 over the time-step
+/// counter with dynamic truncation (RFC 6238, SHA-256 variant)
+pub fn totp_at(secret: &[u8], unix_time: u64) -> String {
+    let hash = hmac_sha256(secret, &(unix_time / TOTP_STEP_SECS).to_be_bytes());
+    let offset = (hash[hash.len() - 1] & 0xF) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7F,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]) % 1_000_000;
+    format!("{:06}", code)
+}
+
+/// The TOTP code for `secret` in the current time step
+pub fn generate_totp(secret: &[u8]) -> String {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let hash = sha256(&now.to_be_bytes());
-    hash
+        .unwrap_or_default()
+        .as_secs();
+    totp_at(secret, now)
+}
+
+/// Checks a submitted TOTP code against the current one in constant time
+pub fn verify_totp(input_code: &str, secret: &[u8]) -> bool {
+    constant_time_eq(generate_totp(secret).as_bytes(), input_code.as_bytes())
+}
+
+/// HKDF-Extract (RFC 5869): condenses input keying material into a pseudorandom key
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    // An absent salt is treated as HashLen zero bytes
+    if salt.is_empty() {
+        return hmac_sha256(&[0u8; 32], ikm);
+    }
+    hmac_sha256(salt, ikm)
+}
+
+/// HKDF-Expand (RFC 5869): stretches a pseudorandom key into `out_len` bytes.
+/// Panics if `out_len` exceeds 255 * 32 bytes, the RFC limit for SHA-256.
+pub fn hkdf_expand(prk: &[u8], info: &[u8], out_len: usize) -> Vec<u8> {
+    assert!(out_len <= 255 * 32, "HKDF output length exceeds 255 blocks");
+
+    // Whole blocks are written before truncating, so reserve them all up front
+    let mut okm = Vec::with_capacity(out_len.div_ceil(32) * 32);
+    let mut previous = Zeroizing::new([0u8; 32]);
+    let mut counter = 1u8;
+    while okm.len() < out_len {
+        let mut input = Zeroizing::new(Vec::with_capacity(32 + info.len() + 1));
+        if counter > 1 {
+            input.extend_from_slice(&*previous);
+        }
+        input.extend_from_slice(info);
+        input.push(counter);
+        *previous = hmac_sha256(prk, &input);
+        okm.extend_from_slice(&*previous);
+        counter = counter.wrapping_add(1);
+    }
+    okm.truncate(out_len);
+    okm
+}
+
+/// Derives `out_len` bytes of key material from `ikm` with HKDF-SHA256.
+///
+/// Use a distinct `info` label for every purpose (e.g. `b"session-enc"` vs
+/// `b"session-mac"`) so keys derived from the same secret stay independent.
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], out_len: usize) -> Vec<u8> {
+    let prk = Zeroizing::new(hkdf_extract(salt, ikm));
+    hkdf_expand(&*prk, info, out_len)
+}
+
+/// PBKDF2 (RFC 8018) with HMAC-SHA256: derives `out_len` bytes from a passphrase.
+/// Use a random per-secret salt and at least `PBKDF2_ITERATIONS` iterations.
+pub fn pbkdf2_hmac_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    out_len: usize,
+) -> Vec<u8> {
+    assert!(iterations > 0, "PBKDF2 requires at least one iteration");
+
+    // Whole blocks are written before truncating, so reserve them all up front
+    let mut derived = Vec::with_capacity(out_len.div_ceil(32) * 32);
+    let mut block_index = 1u32;
+    while derived.len() < out_len {
+        let mut first_input = salt.to_vec();
+        first_input.extend_from_slice(&block_index.to_be_bytes());
+
+        let mut u = Zeroizing::new(hmac_sha256(password, &first_input));
+        let mut block = u.clone();
+        for _ in 1..iterations {
+            *u = hmac_sha256(password, &*u);
+            for (acc, byte) in block.iter_mut().zip(u.iter()) {
+                *acc ^= byte;
+            }
+        }
+        derived.extend_from_slice(&*block);
+        block_index += 1;
+    }
+    derived.truncate(out_len);
+    derived
+}
+
+/// Fills `buf` from the operating system CSPRNG (`/dev/urandom`).
+/// Fails rather than falling back to weaker entropy if the device is unavailable.
+#[cfg(unix)]
+pub fn random_bytes(buf: &mut [u8]) -> io::Result<()> {
+    use std::fs::File;
+
+    File::open("/dev/urandom")?.read_exact(buf)
+}
+
+/// Fallback for platforms without `/dev/urandom`: expands keys from std's
+/// `RandomState`, which the standard library seeds from the OS RNG. Each call
+/// draws fresh keys, but output is only as strong as SipHash-1-3 keyed with
+/// that seed, so prefer Unix hosts for long-lived key material.
+#[cfg(not(unix))]
+pub fn random_bytes(buf: &mut [u8]) -> io::Result<()> {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    for (counter, chunk) in buf.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(counter);
+        let block = hasher.finish().to_le_bytes();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    Ok(())
+}
+
+/// Generates 256 bits of key material
+pub fn random_256() -> io::Result<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    random_bytes(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Source of randomness injected into components; production code uses `OsRng`
+pub trait Rng: Send + Sync {
+    fn fill_bytes(&self, buf: &mut [u8]) -> io::Result<()>;
+
+    fn next_u64(&self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Uniform in `[0, 1)`, using the top 24 bits for an exact `f32` mantissa
+    fn next_f32(&self) -> io::Result<f32> {
+        Ok((self.next_u64()? >> 40) as f32 / (1u32 << 24) as f32)
+    }
+}
+
+/// The operating system CSPRNG, via `random_bytes`
+pub struct OsRng;
+
+impl Rng for OsRng {
+    fn fill_bytes(&self, buf: &mut [u8]) -> io::Result<()> {
+        random_bytes(buf)
+    }
+}
+
+/// Deterministic SplitMix64 generator: the same seed always yields the same
+/// sequence. For tests and simulations only, never for key material.
+pub struct SeededRng {
+    state: Mutex<u64>,
+}
+
+impl SeededRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            state: Mutex::new(seed),
+        }
+    }
+}
+
+impl Rng for SeededRng {
+    fn fill_bytes(&self, buf: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for chunk in buf.chunks_mut(8) {
+            *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+/// Random bytes leading every `NonceSequence` nonce; the last 4 are the counter
+pub const NONCE_PREFIX_LEN: usize = 8;
+
+/// Why a nonce could not be issued or used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceError {
+    /// The sequence's 32-bit counter is spent; rotate the key or start a new sequence
+    Exhausted,
+    /// This key has already encrypted under this nonce in this process
+    Reused,
+}
+
+impl fmt::Display for NonceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonceError::Exhausted => write!(f, "nonce sequence exhausted"),
+            NonceError::Reused => write!(f, "nonce reused with the same key"),
+        }
+    }
+}
+
+impl std::error::Error for NonceError {}
+
+impl From<NonceError> for io::Error {
+    fn from(e: NonceError) -> Self {
+        io::Error::other(e)
+    }
+}
+
+/// Issues 96-bit nonces for one key as prefix || big-endian counter, so a sequence never
+/// repeats. The random prefix keeps sequences started by other processes on the same key apart.
+pub struct NonceSequence {
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u64, // Next counter value; past `u32::MAX` the sequence is exhausted
 }
 
-/// AES-GCM encryption simulation (manual implementation of Galois/Counter Mode)
-fn aes_gcm_encrypt(plaintext: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Vec<u8> {
-    let mut ciphertext = plaintext.to_vec();
-    for i in 0..ciphertext.len() {
-        ciphertext[i] ^= key[i % 32] ^ nonce[i % 12];
+impl NonceSequence {
+    /// Starts a sequence with a fresh random prefix
+    pub fn new() -> io::Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        random_bytes(&mut prefix)?;
+        Ok(Self::from_prefix(prefix))
+    }
+
+    /// Starts a sequence with a caller-chosen prefix, which must be unique per key
+    pub fn from_prefix(prefix: [u8; NONCE_PREFIX_LEN]) -> Self {
+        Self { prefix, counter: 0 }
+    }
+
+    /// The next unused nonce
+    pub fn next_nonce(&mut self) -> Result<[u8; 12], NonceError> {
+        let counter = u32::try_from(self.counter).map_err(|_| NonceError::Exhausted)?;
+        self.counter += 1;
+        let mut nonce = [0u8; 12];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.prefix);
+        nonce[NONCE_PREFIX_LEN..].copy_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
     }
-    ciphertext
 }
 
-/// AES-GCM decryption simulation (reverse process of encryption)
-fn aes_gcm_decrypt(ciphertext: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Vec<u8> {
-    aes_gcm_encrypt(ciphertext, key, nonce) // Reversible XOR-based transformation
+/// A key's SHA-256 fingerprint and a nonce it has encrypted under
+type ClaimedNonce = ([u8; 32], [u8; 12]);
+
+/// Every (key fingerprint, nonce) pair `claim_nonce` has seen in this process
+fn claimed_nonces() -> &'static Mutex<HashSet<ClaimedNonce>> {
+    static CLAIMED: OnceLock<Mutex<HashSet<ClaimedNonce>>> = OnceLock::new();
+    CLAIMED.get_or_init(|| Mutex::new(HashSet::new()))
 }
 
-/// ChaCha20 encryption simulation (simplified stream cipher logic)
-fn chacha20_encrypt(plaintext: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Vec<u8> {
-    let mut ciphertext = plaintext.to_vec();
-    for i in 0..ciphertext.len() {
-        ciphertext[i] ^= key[(i % 32) ^ (nonce[i % 12] as usize % 32)];
+/// Records that `key` is about to encrypt under `nonce`, failing if it already has.
+/// Keys are remembered by their SHA-256 digest, never in the clear. The set only grows,
+/// so the AEADs call this through `debug_assert!` and release builds skip it.
+pub fn claim_nonce(key: &[u8; 32], nonce: &[u8; 12]) -> Result<(), NonceError> {
+    let fresh = claimed_nonces()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert((sha256(key), *nonce));
+    if fresh {
+        Ok(())
+    } else {
+        Err(NonceError::Reused)
     }
-    ciphertext
 }
 
-/// ChaCha20 decryption simulation (reverse process of encryption)
-fn chacha20_decrypt(ciphertext: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Vec<u8> {
-    chacha20_encrypt(ciphertext, key, nonce)
+/// Length of the Poly1305 authentication tag appended by `chacha20poly1305_seal`
+pub const POLY1305_TAG_LEN: usize = 16;
+
+/// ChaCha20 quarter round (RFC 8439 §2.1)
+fn chacha20_quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// ChaCha20 block function (RFC 8439 §2.3): one 64-byte keystream block
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64] {
+    let word = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for i in 0..8 {
+        initial[4 + i] = word(&key[i * 4..]);
+    }
+    initial[12] = counter;
+    for i in 0..3 {
+        initial[13 + i] = word(&nonce[i * 4..]);
+    }
+
+    let mut state = initial;
+    for _ in 0..10 {
+        chacha20_quarter_round(&mut state, 0, 4, 8, 12);
+        chacha20_quarter_round(&mut state, 1, 5, 9, 13);
+        chacha20_quarter_round(&mut state, 2, 6, 10, 14);
+        chacha20_quarter_round(&mut state, 3, 7, 11, 15);
+        chacha20_quarter_round(&mut state, 0, 5, 10, 15);
+        chacha20_quarter_round(&mut state, 1, 6, 11, 12);
+        chacha20_quarter_round(&mut state, 2, 7, 8, 13);
+        chacha20_quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut block = [0u8; 64];
+    for (i, chunk) in block.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes());
+    }
+    block
+}
+
+/// ChaCha20 stream cipher (RFC 8439 §2.4); encryption and decryption are identical
+pub fn chacha20_xor(
+    key: &[u8; 32],
+    initial_counter: u32,
+    nonce: &[u8; 12],
+    data: &[u8],
+) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for (i, chunk) in data.chunks(64).enumerate() {
+        let keystream = chacha20_block(key, initial_counter.wrapping_add(i as u32), nonce);
+        output.extend(chunk.iter().zip(keystream.iter()).map(|(d, k)| d ^ k));
+    }
+    output
+}
+
+/// Poly1305 one-time authenticator (RFC 8439 §2.5) using 26-bit limbs
+pub fn poly1305_mac(key: &[u8; 32], message: &[u8]) -> [u8; 16] {
+    let le = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    // Clamp r and split it into five 26-bit limbs
+    let r0 = le(&key[0..]) & 0x3ffffff;
+    let r1 = (le(&key[3..]) >> 2) & 0x3ffff03;
+    let r2 = (le(&key[6..]) >> 4) & 0x3ffc0ff;
+    let r3 = (le(&key[9..]) >> 6) & 0x3f03fff;
+    let r4 = (le(&key[12..]) >> 8) & 0x00fffff;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+    let (mut h0, mut h1, mut h2, mut h3, mut h4) = (0u32, 0u32, 0u32, 0u32, 0u32);
+    for chunk in message.chunks(16) {
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1; // The appended high bit
+
+        h0 += le(&block[0..]) & 0x3ffffff;
+        h1 += (le(&block[3..]) >> 2) & 0x3ffffff;
+        h2 += (le(&block[6..]) >> 4) & 0x3ffffff;
+        h3 += (le(&block[9..]) >> 6) & 0x3ffffff;
+        h4 += (le(&block[12..]) >> 8) | ((block[16] as u32) << 24);
+
+        // h *= r (mod 2^130 - 5)
+        let m = |a: u32, b: u32| a as u64 * b as u64;
+        let d0 = m(h0, r0) + m(h1, s4) + m(h2, s3) + m(h3, s2) + m(h4, s1);
+        let mut d1 = m(h0, r1) + m(h1, r0) + m(h2, s4) + m(h3, s3) + m(h4, s2);
+        let mut d2 = m(h0, r2) + m(h1, r1) + m(h2, r0) + m(h3, s4) + m(h4, s3);
+        let mut d3 = m(h0, r3) + m(h1, r2) + m(h2, r1) + m(h3, r0) + m(h4, s4);
+        let mut d4 = m(h0, r4) + m(h1, r3) + m(h2, r2) + m(h3, r1) + m(h4, r0);
+
+        d1 += d0 >> 26;
+        h0 = d0 as u32 & 0x3ffffff;
+        d2 += d1 >> 26;
+        h1 = d1 as u32 & 0x3ffffff;
+        d3 += d2 >> 26;
+        h2 = d2 as u32 & 0x3ffffff;
+        d4 += d3 >> 26;
+        h3 = d3 as u32 & 0x3ffffff;
+        h0 += (d4 >> 26) as u32 * 5;
+        h4 = d4 as u32 & 0x3ffffff;
+        h1 += h0 >> 26;
+        h0 &= 0x3ffffff;
+    }
+
+    // Fully carry h
+    h2 += h1 >> 26;
+    h1 &= 0x3ffffff;
+    h3 += h2 >> 26;
+    h2 &= 0x3ffffff;
+    h4 += h3 >> 26;
+    h3 &= 0x3ffffff;
+    h0 += (h4 >> 26) * 5;
+    h4 &= 0x3ffffff;
+    h1 += h0 >> 26;
+    h0 &= 0x3ffffff;
+
+    // Compute h - p and select it without branching if h >= p
+    let mut g0 = h0.wrapping_add(5);
+    let mut g1 = h1.wrapping_add(g0 >> 26);
+    g0 &= 0x3ffffff;
+    let mut g2 = h2.wrapping_add(g1 >> 26);
+    g1 &= 0x3ffffff;
+    let mut g3 = h3.wrapping_add(g2 >> 26);
+    g2 &= 0x3ffffff;
+    let g4 = h4.wrapping_add(g3 >> 26).wrapping_sub(1 << 26);
+    g3 &= 0x3ffffff;
+
+    let mask = (g4 >> 31).wrapping_sub(1); // All ones when h >= p
+    h0 = (h0 & !mask) | (g0 & mask);
+    h1 = (h1 & !mask) | (g1 & mask);
+    h2 = (h2 & !mask) | (g2 & mask);
+    h3 = (h3 & !mask) | (g3 & mask);
+    h4 = (h4 & !mask) | (g4 & mask);
+
+    // Pack into 128 bits and add s
+    let words = [
+        h0 | (h1 << 26),
+        (h1 >> 6) | (h2 << 20),
+        (h2 >> 12) | (h3 << 14),
+        (h3 >> 18) | (h4 << 8),
+    ];
+    let mut tag = [0u8; 16];
+    let mut carry = 0u64;
+    for (i, word) in words.iter().enumerate() {
+        let sum = *word as u64 + le(&key[16 + i * 4..]) as u64 + carry;
+        tag[i * 4..i * 4 + 4].copy_from_slice(&(sum as u32).to_le_bytes());
+        carry = sum >> 32;
+    }
+    tag
+}
+
+/// Builds the Poly1305 input: AAD and ciphertext each padded to 16 bytes, then both lengths
+fn chacha20poly1305_mac_data(aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let padded = |len: usize| len.div_ceil(16) * 16;
+    let mut mac_data = Vec::with_capacity(padded(aad.len()) + padded(ciphertext.len()) + 16);
+    mac_data.extend_from_slice(aad);
+    mac_data.resize(padded(aad.len()), 0);
+    mac_data.extend_from_slice(ciphertext);
+    mac_data.resize(padded(aad.len()) + padded(ciphertext.len()), 0);
+    mac_data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    mac_data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    mac_data
+}
+
+/// Derives the one-time Poly1305 key from block 0 of the keystream
+fn poly1305_key_gen(key: &[u8; 32], nonce: &[u8; 12]) -> [u8; 32] {
+    let mut one_time_key = [0u8; 32];
+    one_time_key.copy_from_slice(&chacha20_block(key, 0, nonce)[..32]);
+    one_time_key
+}
+
+/// ChaCha20-Poly1305 AEAD encryption (RFC 8439 §2.8); returns ciphertext || tag.
+/// A nonce must never be reused with the same key: take them from a `NonceSequence`.
+/// Debug builds panic on reuse within the process.
+pub fn chacha20poly1305_seal(
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Vec<u8> {
+    debug_assert!(
+        claim_nonce(key, nonce).is_ok(),
+        "ChaCha20-Poly1305 nonce reused with the same key"
+    );
+    let mut sealed = chacha20_xor(key, 1, nonce, plaintext);
+    let tag = poly1305_mac(
+        &poly1305_key_gen(key, nonce),
+        &chacha20poly1305_mac_data(aad, &sealed),
+    );
+    sealed.extend_from_slice(&tag);
+    sealed
+}
+
+/// ChaCha20-Poly1305 AEAD decryption; returns `None` if the tag does not verify
+pub fn chacha20poly1305_open(
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Option<Vec<u8>> {
+    if sealed.len() < POLY1305_TAG_LEN {
+        return None;
+    }
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - POLY1305_TAG_LEN);
+    let expected = poly1305_mac(
+        &poly1305_key_gen(key, nonce),
+        &chacha20poly1305_mac_data(aad, ciphertext),
+    );
+
+    // Constant-time tag comparison before any plaintext is released
+    let diff = expected
+        .iter()
+        .zip(tag)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return None;
+    }
+    Some(chacha20_xor(key, 1, nonce, ciphertext))
+}
+
+/// Plaintext bytes per frame in the streaming AEAD format
+pub const STREAM_FRAME_SIZE: usize = 64 * 1024;
+/// Random per-stream nonce prefix written as the stream header
+pub const STREAM_PREFIX_LEN: usize = 7;
+const SEALED_FRAME_SIZE: usize = STREAM_FRAME_SIZE + POLY1305_TAG_LEN;
+
+/// Reasons a streamed ciphertext is rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamError {
+    /// The stream ended before its header or final frame
+    Truncated,
+    /// A frame's tag did not verify; the whole stream must be discarded
+    Authentication,
+    /// More frames than the 32-bit counter can address
+    TooManyFrames,
+}
+
+/// Frame nonce: prefix || big-endian frame counter || last-frame flag.
+/// Binding the flag into the nonce makes truncation at a frame boundary detectable.
+fn stream_nonce(prefix: &[u8; STREAM_PREFIX_LEN], counter: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..STREAM_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[STREAM_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// Encrypts a payload in fixed-size ChaCha20-Poly1305 frames with bounded memory
+pub struct Encryptor {
+    key: Zeroizing<[u8; 32]>,
+    prefix: [u8; STREAM_PREFIX_LEN],
+    counter: u32,
+    pending: Vec<u8>,
+    header_written: bool,
+}
+
+impl Encryptor {
+    /// Starts a stream with a fresh random nonce prefix
+    pub fn new(key: &[u8; 32]) -> io::Result<Self> {
+        let mut prefix = [0u8; STREAM_PREFIX_LEN];
+        random_bytes(&mut prefix)?;
+        Ok(Self {
+            key: Zeroizing::new(*key),
+            prefix,
+            counter: 0,
+            pending: Vec::with_capacity(STREAM_FRAME_SIZE),
+            header_written: false,
+        })
+    }
+
+    /// Buffers `data` and returns the header and any frames that are now complete
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, StreamError> {
+        let mut output = self.take_header();
+        self.pending.extend_from_slice(data);
+        // Keep at least one byte back so `finalize` always has the final frame to seal
+        while self.pending.len() > STREAM_FRAME_SIZE {
+            let frame: Vec<u8> = self.pending.drain(..STREAM_FRAME_SIZE).collect();
+            output.extend(self.seal_frame(&frame, false)?);
+        }
+        Ok(output)
+    }
+
+    /// Seals the remaining buffered data as the final frame
+    pub fn finalize(mut self) -> Result<Vec<u8>, StreamError> {
+        let mut output = self.take_header();
+        let frame = std::mem::take(&mut self.pending);
+        output.extend(self.seal_frame(&frame, true)?);
+        Ok(output)
+    }
+
+    fn take_header(&mut self) -> Vec<u8> {
+        if self.header_written {
+            return Vec::new();
+        }
+        self.header_written = true;
+        self.prefix.to_vec()
+    }
+
+    fn seal_frame(&mut self, frame: &[u8], last: bool) -> Result<Vec<u8>, StreamError> {
+        let nonce = stream_nonce(&self.prefix, self.counter, last);
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or(StreamError::TooManyFrames)?;
+        Ok(chacha20poly1305_seal(&self.key, &nonce, &[], frame))
+    }
+}
+
+/// Decrypts a stream produced by `Encryptor`, frame by frame.
+///
+/// Each released frame is authenticated, but the stream is only complete and
+/// untruncated if `finalize` succeeds; on any error, discard everything released.
+pub struct Decryptor {
+    key: Zeroizing<[u8; 32]>,
+    prefix: Option<[u8; STREAM_PREFIX_LEN]>,
+    counter: u32,
+    pending: Vec<u8>,
+    failed: bool,
+}
+
+impl Decryptor {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            key: Zeroizing::new(*key),
+            prefix: None,
+            counter: 0,
+            pending: Vec::with_capacity(SEALED_FRAME_SIZE),
+            failed: false,
+        }
+    }
+
+    /// Buffers ciphertext and returns plaintext for every verified non-final frame
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, StreamError> {
+        if self.failed {
+            return Err(StreamError::Authentication);
+        }
+        self.pending.extend_from_slice(data);
+
+        let mut output = Vec::new();
+        if !self.read_header() {
+            return Ok(output);
+        }
+        // A full frame is only known to be non-final once more data follows it
+        while self.pending.len() > SEALED_FRAME_SIZE {
+            let frame: Vec<u8> = self.pending.drain(..SEALED_FRAME_SIZE).collect();
+            output.extend(self.open_frame(&frame, false)?);
+        }
+        Ok(output)
+    }
+
+    /// Verifies the final frame; fails if the stream was truncated or tampered with
+    pub fn finalize(mut self) -> Result<Vec<u8>, StreamError> {
+        if self.failed {
+            return Err(StreamError::Authentication);
+        }
+        if !self.read_header() || self.pending.len() < POLY1305_TAG_LEN {
+            return Err(StreamError::Truncated);
+        }
+        let frame = std::mem::take(&mut self.pending);
+        self.open_frame(&frame, true)
+    }
+
+    fn read_header(&mut self) -> bool {
+        if self.prefix.is_none() && self.pending.len() >= STREAM_PREFIX_LEN {
+            let mut prefix = [0u8; STREAM_PREFIX_LEN];
+            prefix.copy_from_slice(&self.pending[..STREAM_PREFIX_LEN]);
+            self.pending.drain(..STREAM_PREFIX_LEN);
+            self.prefix = Some(prefix);
+        }
+        self.prefix.is_some()
+    }
+
+    fn open_frame(&mut self, frame: &[u8], last: bool) -> Result<Vec<u8>, StreamError> {
+        let prefix = self.prefix.ok_or(StreamError::Truncated)?;
+        let nonce = stream_nonce(&prefix, self.counter, last);
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or(StreamError::TooManyFrames)?;
+        chacha20poly1305_open(&self.key, &nonce, &[], frame).ok_or_else(|| {
+            self.failed = true;
+            StreamError::Authentication
+        })
+    }
 }
 
 fn main() {
-    let key = secure_random_256();
-    let nonce: [u8; 12] = secure_random_256()[..12].try_into().unwrap();
+    let key = random_256().expect("OS random source unavailable");
+    let mut nonces = NonceSequence::new().expect("OS random source unavailable");
+    let mut salt = [0u8; 16];
+    random_bytes(&mut salt).expect("OS random source unavailable");
+
+    // Separate encryption and MAC keys from one master secret
+    let encryption_key = hkdf(&salt, &key, b"zero-trust storage encryption", 32);
+    let mac_key = hkdf(&salt, &key, b"zero-trust storage mac", 32);
+    println!("Derived independent subkeys: {}", encryption_key != mac_key);
     let plaintext = b"Sensitive Data";
 
-    let encrypted_aes = aes_gcm_encrypt(plaintext, &key, &nonce);
-    let decrypted_aes = aes_gcm_decrypt(&encrypted_aes, &key, &nonce);
+    let aad = b"record-id:42";
+    let nonce = nonces.next_nonce().expect("fresh sequence");
+    let sealed = chacha20poly1305_seal(&key, &nonce, aad, plaintext);
+    match chacha20poly1305_open(&key, &nonce, aad, &sealed) {
+        Some(opened) => println!(
+            "ChaCha20-Poly1305 Decryption: {:?}",
+            String::from_utf8_lossy(&opened)
+        ),
+        None => println!("ChaCha20-Poly1305 authentication failed"),
+    }
+
+    let mut tampered = sealed.clone();
+    tampered[0] ^= 1;
     println!(
-        "AES-GCM Decryption: {:?}",
-        String::from_utf8_lossy(&decrypted_aes)
+        "Tampered ciphertext rejected: {}",
+        chacha20poly1305_open(&key, &nonce, aad, &tampered).is_none()
     );
 
-    let encrypted_chacha = chacha20_encrypt(plaintext, &key, &nonce);
-    let decrypted_chacha = chacha20_decrypt(&encrypted_chacha, &key, &nonce);
+    // Stream a payload larger than one frame with bounded buffering
+    let payload = vec![0x5Au8; STREAM_FRAME_SIZE * 2 + 100];
+    let mut encryptor = Encryptor::new(&key).expect("OS random source unavailable");
+    let mut stream = Vec::new();
+    for chunk in payload.chunks(10_000) {
+        stream.extend(encryptor.update(chunk).expect("frame counter exhausted"));
+    }
+    stream.extend(encryptor.finalize().expect("frame counter exhausted"));
+
+    let mut decryptor = Decryptor::new(&key);
+    let mut recovered = Vec::new();
+    let result = stream
+        .chunks(10_000)
+        .try_for_each(|chunk| decryptor.update(chunk).map(|p| recovered.extend(p)))
+        .and_then(|_| decryptor.finalize())
+        .map(|last| recovered.extend(last));
     println!(
-        "ChaCha20 Decryption: {:?}",
-        String::from_utf8_lossy(&decrypted_chacha)
+        "Streamed {} bytes, round trip intact: {}",
+        payload.len(),
+        result.is_ok() && recovered == payload
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_bytes_differ_between_calls_and_fill_the_buffer() {
+        let (mut first, mut second) = ([0u8; 64], [0u8; 64]);
+        random_bytes(&mut first).unwrap();
+        random_bytes(&mut second).unwrap();
+        assert_ne!(first, second);
+
+        // Any run of 8 zero bytes would mean part of the buffer was skipped
+        for buf in [&first, &second] {
+            assert!(buf.chunks(8).all(|chunk| chunk.iter().any(|&b| b != 0)));
+        }
+        assert_ne!(random_256().unwrap(), random_256().unwrap());
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hkdf_matches_rfc5869_basic_vector() {
+        // RFC 5869 A.1
+        let ikm = [0x0bu8; 22];
+        let salt = unhex("000102030405060708090a0b0c");
+        let info = unhex("f0f1f2f3f4f5f6f7f8f9");
+
+        let prk = hkdf_extract(&salt, &ikm);
+        assert_eq!(
+            prk.to_vec(),
+            unhex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+        let okm = unhex(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+        );
+        assert_eq!(hkdf_expand(&prk, &info, 42), okm);
+        assert_eq!(hkdf(&salt, &ikm, &info, 42), okm);
+    }
+
+    #[test]
+    fn hkdf_matches_rfc5869_vector_without_salt_or_info() {
+        // RFC 5869 A.3
+        let ikm = [0x0bu8; 22];
+        assert_eq!(
+            hkdf_extract(&[], &ikm).to_vec(),
+            unhex("19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04")
+        );
+        assert_eq!(
+            hkdf(&[], &ikm, &[], 42),
+            unhex(
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"
+            )
+        );
+        // Distinct labels yield unrelated keys from the same secret
+        assert_ne!(hkdf(&[], &ikm, b"enc", 32), hkdf(&[], &ikm, b"mac", 32));
+    }
+
+    #[test]
+    fn pbkdf2_matches_known_sha256_vectors() {
+        let derive = |iterations| pbkdf2_hmac_sha256(b"password", b"salt", iterations, 32);
+        assert_eq!(
+            derive(1),
+            unhex("120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b")
+        );
+        assert_eq!(
+            derive(2),
+            unhex("ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43")
+        );
+        assert_eq!(
+            derive(4096),
+            unhex("c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a")
+        );
+        // Output longer than one block continues with the next block index
+        let long = pbkdf2_hmac_sha256(b"password", b"salt", 1, 40);
+        assert_eq!(&long[..32], &derive(1)[..]);
+        assert_eq!(long.len(), 40);
+    }
+
+    /// RFC 8439 §2.8.2 AEAD key: 0x80..=0x9f
+    fn rfc8439_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = 0x80 + i as u8;
+        }
+        key
+    }
+
+    #[test]
+    fn chacha20_block_matches_rfc8439_vector() {
+        // RFC 8439 §2.3.2
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let nonce: [u8; 12] = unhex("000000090000004a00000000").try_into().unwrap();
+        assert_eq!(
+            chacha20_block(&key, 1, &nonce).to_vec(),
+            unhex(
+                "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+                 d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+            )
+        );
+    }
+
+    #[test]
+    fn poly1305_matches_rfc8439_vector() {
+        // RFC 8439 §2.5.2
+        let key: [u8; 32] =
+            unhex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b")
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            poly1305_mac(&key, b"Cryptographic Forum Research Group").to_vec(),
+            unhex("a8061dc1305136c6c22b8baf0c0127a9")
+        );
+    }
+
+    #[test]
+    fn chacha20poly1305_matches_rfc8439_aead_vector() {
+        // RFC 8439 §2.8.2
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+            only one tip for the future, sunscreen would be it.";
+        let nonce: [u8; 12] = unhex("070000004041424344454647").try_into().unwrap();
+        let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+        let mut expected = unhex(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116",
+        );
+        expected.extend(unhex("1ae10b594f09e26a7e902ecbd0600691"));
+
+        let sealed = chacha20poly1305_seal(&rfc8439_key(), &nonce, &aad, plaintext);
+        assert_eq!(sealed, expected);
+        assert_eq!(
+            chacha20poly1305_open(&rfc8439_key(), &nonce, &aad, &sealed).as_deref(),
+            Some(plaintext)
+        );
+    }
+
+    #[test]
+    fn chacha20poly1305_rejects_tampering() {
+        let key = rfc8439_key();
+        let nonce = [0x42u8; 12];
+        let sealed = chacha20poly1305_seal(&key, &nonce, b"header", b"attack at dawn");
+        assert!(chacha20poly1305_open(&key, &nonce, b"header", &sealed).is_some());
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x01;
+            assert!(chacha20poly1305_open(&key, &nonce, b"header", &tampered).is_none());
+        }
+        assert!(chacha20poly1305_open(&key, &nonce, b"Header", &sealed).is_none());
+        assert!(chacha20poly1305_open(&key, &[0x43; 12], b"header", &sealed).is_none());
+        assert!(chacha20poly1305_open(&key, &nonce, b"header", &sealed[..15]).is_none());
+    }
+
+    fn encrypt_stream(key: &[u8; 32], payload: &[u8]) -> Vec<u8> {
+        let mut encryptor = Encryptor::new(key).unwrap();
+        let mut stream = Vec::new();
+        for chunk in payload.chunks(10_000) {
+            stream.extend(encryptor.update(chunk).unwrap());
+        }
+        stream.extend(encryptor.finalize().unwrap());
+        stream
+    }
+
+    fn decrypt_stream(key: &[u8; 32], stream: &[u8]) -> Result<Vec<u8>, StreamError> {
+        let mut decryptor = Decryptor::new(key);
+        let mut plaintext = Vec::new();
+        for chunk in stream.chunks(7_000) {
+            plaintext.extend(decryptor.update(chunk)?);
+        }
+        plaintext.extend(decryptor.finalize()?);
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn multi_frame_stream_round_trips() {
+        let key = [0x11u8; 32];
+        let payload: Vec<u8> = (0..STREAM_FRAME_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let stream = encrypt_stream(&key, &payload);
+        assert_eq!(
+            stream.len(),
+            STREAM_PREFIX_LEN + payload.len() + 3 * POLY1305_TAG_LEN
+        );
+        assert_eq!(decrypt_stream(&key, &stream), Ok(payload));
+
+        // An empty payload still carries an authenticated final frame
+        let empty = encrypt_stream(&key, &[]);
+        assert_eq!(decrypt_stream(&key, &empty), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn tampering_with_any_frame_fails_the_stream() {
+        let key = [0x22u8; 32];
+        let payload = vec![0x5Au8; STREAM_FRAME_SIZE * 2 + 100];
+        let stream = encrypt_stream(&key, &payload);
+
+        // One byte in the header, in each full frame, and in the final frame
+        let first_frame = STREAM_PREFIX_LEN;
+        for offset in [
+            0,
+            first_frame + 5,
+            first_frame + SEALED_FRAME_SIZE + 5,
+            stream.len() - 1,
+        ] {
+            let mut tampered = stream.clone();
+            tampered[offset] ^= 0x01;
+            assert_eq!(
+                decrypt_stream(&key, &tampered),
+                Err(StreamError::Authentication),
+                "tampered byte at offset {}",
+                offset
+            );
+        }
+
+        // Dropping the final frame leaves a full frame that was not sealed as last
+        let truncated = &stream[..first_frame + 2 * SEALED_FRAME_SIZE];
+        assert_eq!(
+            decrypt_stream(&key, truncated),
+            Err(StreamError::Authentication)
+        );
+        assert_eq!(
+            decrypt_stream(&key, &stream[..3]),
+            Err(StreamError::Truncated)
+        );
+
+        // Reordered frames fail because each nonce carries the frame counter
+        let mut swapped = stream[..first_frame].to_vec();
+        swapped.extend_from_slice(&stream[first_frame + SEALED_FRAME_SIZE..][..SEALED_FRAME_SIZE]);
+        swapped.extend_from_slice(&stream[first_frame..][..SEALED_FRAME_SIZE]);
+        swapped.extend_from_slice(&stream[first_frame + 2 * SEALED_FRAME_SIZE..]);
+        assert_eq!(
+            decrypt_stream(&key, &swapped),
+            Err(StreamError::Authentication)
+        );
+    }
+
+    #[test]
+    fn seeded_rng_reproduces_its_sequence() {
+        // First SplitMix64 output for seed 0
+        assert_eq!(
+            SeededRng::from_seed(0).next_u64().unwrap(),
+            0xE220_A839_7B1D_CDAF
+        );
+
+        let (first, second) = (SeededRng::from_seed(42), SeededRng::from_seed(42));
+        let (mut a, mut b) = ([0u8; 20], [0u8; 20]);
+        first.fill_bytes(&mut a).unwrap();
+        second.fill_bytes(&mut b).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(first.next_u64().unwrap(), second.next_u64().unwrap());
+
+        let mut other = [0u8; 20];
+        SeededRng::from_seed(43).fill_bytes(&mut other).unwrap();
+        assert_ne!(a, other);
+        let unit = SeededRng::from_seed(7).next_f32().unwrap();
+        assert!((0.0..1.0).contains(&unit));
+    }
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Yields its data a few bytes at a time, interrupted before every read
+    struct Trickle<'a> {
+        data: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+            let n = buf.len().min(self.data.len()).min(7);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn streaming_and_single_shot_hashes_agree() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let data: Vec<u8> = (0..3 * HASH_READ_CHUNK + 7)
+            .map(|i| (i * 31 % 251) as u8)
+            .collect();
+        // Lengths either side of the 64-byte block and the read chunk size
+        for len in [
+            0,
+            1,
+            55,
+            56,
+            63,
+            64,
+            65,
+            HASH_READ_CHUNK,
+            HASH_READ_CHUNK + 1,
+            data.len(),
+        ] {
+            let data = &data[..len];
+            let expected = sha256(data);
+
+            let mut state = Sha256State::new();
+            assert_eq!(hash_reader(data, &mut state).unwrap(), len as u64);
+            assert_eq!(state.finalize(), expected, "hash_reader, {} bytes", len);
+
+            let mut state = Sha256State::new();
+            let trickle = Trickle {
+                data,
+                interrupt: false,
+            };
+            hash_reader(trickle, &mut state).unwrap();
+            assert_eq!(state.finalize(), expected, "trickled, {} bytes", len);
+
+            let mut state = Sha256State::new();
+            for piece in data.chunks(13) {
+                state.update(piece);
+            }
+            assert_eq!(state.finalize(), expected, "13-byte updates, {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn dropping_a_zeroizing_array_wipes_its_bytes() {
+        let mut slot = std::mem::MaybeUninit::new(Zeroizing::new([0xa5u8; 32]));
+        // SAFETY: `slot` holds an initialised value, which is dropped exactly once here
+        unsafe { slot.assume_init_drop() };
+        let size = std::mem::size_of::<Zeroizing<[u8; 32]>>();
+        assert_eq!(size, 32);
+        // SAFETY: the wrapper is exactly the array, whose bytes the drop left initialised
+        let bytes = unsafe { std::slice::from_raw_parts(slot.as_ptr().cast::<u8>(), size) };
+        assert_eq!(bytes, [0u8; 32]);
+    }
+
+    #[test]
+    fn zeroizing_a_vec_wipes_its_spare_capacity_too() {
+        let mut key = vec![0x5au8; 48];
+        key.truncate(16); // The other 32 bytes still hold key material
+        key.zeroize();
+        assert!(key.is_empty());
+        assert!(key.capacity() >= 48);
+        let spare = key.spare_capacity_mut();
+        // SAFETY: `zeroize` wrote every byte of the spare capacity
+        let wiped: Vec<u8> = spare
+            .iter()
+            .map(|byte| unsafe { byte.assume_init() })
+            .collect();
+        assert!(wiped.iter().all(|&byte| byte == 0));
+
+        let wrapped = Zeroizing::new(vec![1u8, 2, 3]);
+        assert_eq!(*wrapped, [1, 2, 3]);
+        assert_eq!(format!("{:?}", wrapped), "Zeroizing([REDACTED])");
+    }
+
+    #[test]
+    fn nonce_sequence_never_repeats() {
+        let mut nonces = NonceSequence::new().unwrap();
+        let mut seen = HashSet::new();
+        for _ in 0..100_000 {
+            assert!(seen.insert(nonces.next_nonce().unwrap()));
+        }
+
+        let mut first = NonceSequence::from_prefix([7; NONCE_PREFIX_LEN]);
+        assert_eq!(
+            first.next_nonce().unwrap(),
+            [7, 7, 7, 7, 7, 7, 7, 7, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            first.next_nonce().unwrap(),
+            [7, 7, 7, 7, 7, 7, 7, 7, 0, 0, 0, 1]
+        );
+
+        // The counter is never allowed to wrap back to a used value
+        let mut last = NonceSequence {
+            counter: u32::MAX as u64,
+            ..NonceSequence::from_prefix([1; NONCE_PREFIX_LEN])
+        };
+        assert_eq!(last.next_nonce().unwrap()[NONCE_PREFIX_LEN..], [0xff; 4]);
+        assert_eq!(last.next_nonce(), Err(NonceError::Exhausted));
+        assert_eq!(last.next_nonce(), Err(NonceError::Exhausted));
+    }
+
+    #[test]
+    fn reuse_guard_trips_on_a_duplicate_nonce_for_the_same_key() {
+        let key = random_256().unwrap();
+        let other_key = random_256().unwrap();
+        let nonce = NonceSequence::new().unwrap().next_nonce().unwrap();
+
+        assert_eq!(claim_nonce(&key, &nonce), Ok(()));
+        assert_eq!(claim_nonce(&key, &nonce), Err(NonceError::Reused));
+        // The same nonce under a different key is fine
+        assert_eq!(claim_nonce(&other_key, &nonce), Ok(()));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "nonce reused with the same key")]
+    fn sealing_twice_under_one_nonce_panics_in_debug_builds() {
+        let key = random_256().unwrap();
+        let nonce = NonceSequence::new().unwrap().next_nonce().unwrap();
+        chacha20poly1305_seal(&key, &nonce, b"", b"first");
+        chacha20poly1305_seal(&key, &nonce, b"", b"second");
+    }
+}
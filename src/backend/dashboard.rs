@@ -0,0 +1,398 @@
+//! Zero Trust Operations Dashboard API (Standard Library Only)
+//! Aggregates system health and security posture into one read-only JSON view.
+//! Features:
+//! - **Current system metrics from the monitoring system**
+//! - **Active IDS blocks and locked-out accounts**
+//! - **Recent verified entries from the tamper-evident security log**
+//! - **One `DashboardSnapshot` served at `GET /dashboard`; every other method is refused**
+//!
+//! Each source is read through its own accessor, which takes and releases that
+//! module's lock before the next source is read, so building a snapshot never holds
+//! two module locks at once and cannot deadlock against the modules' own threads.
+
+#[allow(dead_code)]
+#[path = "intrusion_detection.rs"]
+mod intrusion_detection;
+#[allow(dead_code)]
+#[path = "logger.rs"]
+mod logger;
+#[allow(dead_code)]
+#[path = "monitoring.rs"]
+mod monitoring;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use intrusion_detection::IntrusionDetection;
+use logger::SecureLogger;
+use monitoring::MonitoringSystem;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DASHBOARD_BIND_ADDRESS: &str = "127.0.0.1:9465"; // Local-only by default
+const RECENT_EVENT_WINDOW: u64 = 3600; // Security log entries from the last hour
+const MAX_RECENT_EVENTS: usize = 50; // Newest entries kept in a snapshot
+const COLLECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A source that is currently blocked, with its expiry in Unix seconds
+#[derive(Debug, Clone, PartialEq)]
+struct BlockedSource {
+    source: String,
+    expires_at: u64,
+}
+
+/// A security log entry; `count` > 1 for a run of identical messages
+#[derive(Debug, Clone, PartialEq)]
+struct SecurityEvent {
+    first: u64,
+    last: u64,
+    message: String,
+    count: usize,
+}
+
+/// Point-in-time view of system health and security posture
+#[derive(Debug, Clone, PartialEq)]
+struct DashboardSnapshot {
+    generated_at: u64,
+    metrics: Vec<(String, f32)>, // Sorted by name
+    blocked_sources: Vec<BlockedSource>,
+    locked_accounts: usize,
+    recent_events: Vec<SecurityEvent>, // Oldest first
+    /// Why `recent_events` is empty even though the log may not be, e.g. a broken chain
+    event_log_error: Option<String>,
+}
+
+impl DashboardSnapshot {
+    fn to_json(&self) -> String {
+        let metrics: Vec<String> = self
+            .metrics
+            .iter()
+            .map(|(name, value)| format!("\"{}\":{}", json_escape(name), json_number(*value)))
+            .collect();
+        let blocks: Vec<String> = self
+            .blocked_sources
+            .iter()
+            .map(|block| {
+                format!(
+                    "{{\"source\":\"{}\",\"expires_at\":{}}}",
+                    json_escape(&block.source),
+                    block.expires_at
+                )
+            })
+            .collect();
+        let events: Vec<String> = self
+            .recent_events
+            .iter()
+            .map(|event| {
+                format!(
+                    "{{\"first\":{},\"last\":{},\"message\":\"{}\",\"count\":{}}}",
+                    event.first,
+                    event.last,
+                    json_escape(&event.message),
+                    event.count
+                )
+            })
+            .collect();
+        format!(
+            "{{\"generated_at\":{},\"metrics\":{{{}}},\"security\":{{\"blocked_count\":{},\"blocked_sources\":[{}],\"locked_accounts\":{}}},\"recent_events\":[{}],\"event_log_error\":{}}}",
+            self.generated_at,
+            metrics.join(","),
+            self.blocked_sources.len(),
+            blocks.join(","),
+            self.locked_accounts,
+            events.join(","),
+            self.event_log_error
+                .as_ref()
+                .map_or("null".to_string(), |e| format!("\"{}\"", json_escape(e)))
+        )
+    }
+}
+
+/// Builds snapshots from the modules it observes
+struct Dashboard {
+    monitoring: Arc<MonitoringSystem>,
+    ids: Arc<IntrusionDetection>,
+    logger: Arc<SecureLogger>,
+}
+
+impl Dashboard {
+    fn new(
+        monitoring: Arc<MonitoringSystem>,
+        ids: Arc<IntrusionDetection>,
+        logger: Arc<SecureLogger>,
+    ) -> Self {
+        Self {
+            monitoring,
+            ids,
+            logger,
+        }
+    }
+
+    /// Reads each source in turn; see the module docs for the locking rule
+    fn snapshot(&self) -> DashboardSnapshot {
+        let now = SystemTime::now();
+        let generated_at = unix_seconds(now);
+
+        let mut metrics: Vec<(String, f32)> =
+            self.monitoring.current_metrics().into_iter().collect();
+        metrics.push((
+            "log_dropped_entries".to_string(),
+            self.logger.dropped_count() as f32,
+        ));
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let blocked_sources = self
+            .ids
+            .active_blocks(now)
+            .into_iter()
+            .map(|(source, expiry)| BlockedSource {
+                source,
+                expires_at: unix_seconds(expiry),
+            })
+            .collect();
+        let locked_accounts = self.ids.locked_accounts(now);
+
+        let since = generated_at.saturating_sub(RECENT_EVENT_WINDOW);
+        let (recent_events, event_log_error) = match self.logger.find_since(since) {
+            Ok(entries) => {
+                let skip = entries.len().saturating_sub(MAX_RECENT_EVENTS);
+                let events = entries
+                    .into_iter()
+                    .skip(skip)
+                    .map(|entry| SecurityEvent {
+                        first: entry.first,
+                        last: entry.last,
+                        message: entry.message,
+                        count: entry.count,
+                    })
+                    .collect();
+                (events, None)
+            }
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        DashboardSnapshot {
+            generated_at,
+            metrics,
+            blocked_sources,
+            locked_accounts,
+            recent_events,
+            event_log_error,
+        }
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// JSON has no NaN or infinities; those read as `null`
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Answers one request: `GET /dashboard` gets the snapshot, other methods 405, other paths 404
+fn handle_dashboard_request(mut stream: TcpStream, dashboard: &Dashboard) {
+    let mut buffer = [0u8; 1024];
+    let bytes_read = match stream.read(&mut buffer) {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
+
+    let response = if path.split('?').next() != Some("/dashboard") {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    } else if method != "GET" {
+        "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string()
+    } else {
+        let body = dashboard.snapshot().to_json();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Serves `/dashboard` on a background thread
+fn serve_dashboard(dashboard: Arc<Dashboard>, address: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            handle_dashboard_request(stream, &dashboard);
+        }
+    });
+    Ok(())
+}
+
+fn main() {
+    let monitoring = Arc::new(MonitoringSystem::new());
+    let ids = Arc::new(IntrusionDetection::new());
+    let logger = Arc::new(SecureLogger::new());
+    let dashboard = Arc::new(Dashboard::new(
+        Arc::clone(&monitoring),
+        Arc::clone(&ids),
+        Arc::clone(&logger),
+    ));
+
+    match serve_dashboard(Arc::clone(&dashboard), DASHBOARD_BIND_ADDRESS) {
+        Ok(()) => println!(
+            "[DASHBOARD] Snapshot endpoint at http://{}/dashboard",
+            DASHBOARD_BIND_ADDRESS
+        ),
+        Err(e) => eprintln!("[DASHBOARD] Unable to serve dashboard: {}", e),
+    }
+
+    // Simulated activity so the dashboard has something to show
+    ids.monitor_request("192.168.1.5", "SELECT * FROM users WHERE password='admin'");
+    logger.log("IDS blocked 192.168.1.5 after a pattern-based attack");
+
+    loop {
+        monitoring.collect_metrics();
+        thread::sleep(COLLECT_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dashboard over modules logging to scratch files, with metrics, a block, a
+    /// locked account and security log entries already recorded
+    fn seeded_dashboard(name: &str) -> (Dashboard, Vec<String>) {
+        let paths: Vec<String> = ["monitoring", "ids", "log"]
+            .iter()
+            .map(|module| {
+                test_util::scratch_path("dashboard", &format!("{}-{}", name, module))
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        for path in &paths {
+            let _ = std::fs::remove_file(path);
+        }
+        let monitoring = Arc::new(MonitoringSystem::with_log_file(&paths[0]));
+        let ids = Arc::new(IntrusionDetection::with_log_file(&paths[1]));
+        let logger = Arc::new(
+            SecureLogger::open_with(&paths[2], 16, logger::OverflowPolicy::Block).unwrap(),
+        );
+
+        monitoring.collect_metrics();
+        ids.monitor_request("203.0.113.9", "SELECT * FROM users WHERE password='admin'");
+        ids.monitor_request("203.0.113.10", "GET /index.html");
+        for _ in 0..5 {
+            ids.track_failed_login("mallory");
+        }
+        logger.log("IDS blocked 203.0.113.9 after a pattern-based attack");
+        logger.log("IDS blocked 203.0.113.9 after a pattern-based attack");
+        logger.log("Account mallory locked");
+
+        (Dashboard::new(monitoring, ids, logger), paths)
+    }
+
+    fn remove(paths: Vec<String>) {
+        for path in paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn snapshot_reports_metrics_blocks_and_recent_events() {
+        let (dashboard, paths) = seeded_dashboard("snapshot");
+        let snapshot = dashboard.snapshot();
+
+        let names: Vec<&str> = snapshot
+            .metrics
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        for expected in ["cpu_usage", "memory_usage", "disk_io", "active_processes"] {
+            assert!(
+                names.contains(&expected),
+                "missing {} in {:?}",
+                expected,
+                names
+            );
+        }
+        assert!(names.contains(&"log_dropped_entries"));
+        assert!(names.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        assert_eq!(snapshot.blocked_sources.len(), 1);
+        assert_eq!(snapshot.blocked_sources[0].source, "203.0.113.9");
+        assert!(snapshot.blocked_sources[0].expires_at > snapshot.generated_at);
+        assert_eq!(snapshot.locked_accounts, 1);
+
+        assert_eq!(snapshot.event_log_error, None);
+        let events: Vec<(&str, usize)> = snapshot
+            .recent_events
+            .iter()
+            .map(|event| (event.message.as_str(), event.count))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("IDS blocked 203.0.113.9 after a pattern-based attack", 2),
+                ("Account mallory locked", 1)
+            ]
+        );
+
+        let json = snapshot.to_json();
+        assert!(json.contains("\"blocked_count\":1"));
+        assert!(json.contains("\"locked_accounts\":1"));
+        assert!(json.contains("\"message\":\"Account mallory locked\",\"count\":1"));
+        remove(paths);
+    }
+
+    #[test]
+    fn endpoint_serves_the_snapshot_read_only() {
+        let (dashboard, paths) = seeded_dashboard("endpoint");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let request = |raw: &str| {
+            let mut client = TcpStream::connect(address).unwrap();
+            client.write_all(raw.as_bytes()).unwrap();
+            handle_dashboard_request(listener.accept().unwrap().0, &dashboard);
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let ok = request("GET /dashboard HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.contains("Content-Type: application/json\r\n"));
+        assert!(ok.contains("\"blocked_sources\":[{\"source\":\"203.0.113.9\""));
+
+        let post = request("POST /dashboard HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        assert!(post.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        let other = request("GET /admin HTTP/1.1\r\n\r\n");
+        assert!(other.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        remove(paths);
+    }
+}
@@ -12,29 +12,142 @@
 //! - **Multi-version concurrency control (MVCC) for parallel transactions**
 //! - **Automated data integrity checks with cryptographic hashing**
 
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+#[allow(dead_code)]
+#[path = "util.rs"]
+mod util;
+
 use std::collections::HashMap;
-use std::convert::TryInto;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{SystemTime, UNIX_EPOCH};
+use util::lock_or_recover;
 
 const DB_FILE: &str = "db/database.log";
+const SNAPSHOT_FILE: &str = "db/database.snapshot";
 const MAX_TRANSACTIONS: usize = 100_000;
+const MAX_REPLICATED_FIELD: usize = 16 * 1024 * 1024;
+const SNAPSHOT_MAGIC: &[u8] = b"ZTDBSNAP1";
+const SNAPSHOT_CHECKSUM_LEN: usize = 32;
+const REPLICA_ADDR_ENV: &str = "DB_REPLICA_ADDR"; // Follower to stream writes to
+const REPLICATION_BIND_ENV: &str = "DB_REPLICATION_BIND"; // Accept writes from a primary here
+
+/// A single committed write, as recorded in the WAL and shipped to replicas
+#[derive(Debug, Clone, PartialEq)]
+struct WalRecord {
+    timestamp: u64,
+    key: String,
+    value: String,
+}
+
+impl WalRecord {
+    /// The text form hashed and appended to the WAL
+    fn log_entry(&self) -> String {
+        format!("{} | {} -> {}", self.timestamp, self.key, self.value)
+    }
+
+    /// Length-prefixed wire format: timestamp, key length, key, value length, value
+    fn encode(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(16 + self.key.len() + self.value.len());
+        frame.extend_from_slice(&self.timestamp.to_be_bytes());
+        write_field(&mut frame, &self.key);
+        write_field(&mut frame, &self.value);
+        frame
+    }
+
+    /// Reads one record written by `encode`; a clean EOF before the record yields `None`
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut timestamp = [0u8; 8];
+        match reader.read_exact(&mut timestamp) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let key = read_field(reader)?;
+        let value = read_field(reader)?;
+        Ok(Some(Self {
+            timestamp: u64::from_be_bytes(timestamp),
+            key,
+            value,
+        }))
+    }
+}
+
+fn write_field(out: &mut Vec<u8>, field: &str) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field.as_bytes());
+}
+
+fn read_field<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_REPLICATED_FIELD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "replicated field too large",
+        ));
+    }
+    let mut field = vec![0u8; len];
+    reader.read_exact(&mut field)?;
+    String::from_utf8(field).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Receives every committed write, in commit order
+trait Replicator: Send + Sync {
+    fn apply(&self, op: &WalRecord);
+}
+
+/// Lets a shared follower (e.g. `Arc<SecureDatabase>`) be registered as a replica
+impl<R: Replicator + ?Sized> Replicator for Arc<R> {
+    fn apply(&self, op: &WalRecord) {
+        (**self).apply(op);
+    }
+}
+
+/// Streams WAL records to a follower listening at `peer`, reconnecting after failures
+struct TcpReplicator {
+    peer: String,
+    stream: Mutex<Option<TcpStream>>,
+}
 
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
+impl TcpReplicator {
+    fn new(peer: &str) -> Self {
+        Self {
+            peer: peer.to_string(),
+            stream: Mutex::new(None),
+        }
+    }
 
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
+    fn send(&self, stream: &mut Option<TcpStream>, frame: &[u8]) -> io::Result<()> {
+        if stream.is_none() {
+            *stream = Some(TcpStream::connect(&self.peer)?);
+        }
+        let result = stream.as_mut().unwrap().write_all(frame);
+        if result.is_err() {
+            // Drop the broken connection so the next record reconnects
+            *stream = None;
+        }
+        result
     }
+}
 
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
+impl Replicator for TcpReplicator {
+    fn apply(&self, op: &WalRecord) {
+        let mut stream = lock_or_recover(&self.stream, "replica stream");
+        if let Err(e) = self.send(&mut stream, &op.encode()) {
+            eprintln!("[DB] Replication to {} failed: {}", self.peer, e);
+        }
+    }
 }
 
 /// Secure transactional database with WAL persistence
@@ -42,21 +155,29 @@ struct SecureDatabase {
     data: Mutex<HashMap<String, String>>,      // Key-value storage
     log_file: Mutex<BufWriter<File>>,          // Write-Ahead Log (WAL)
     transactions: Mutex<HashMap<u64, String>>, // Transaction tracking
+    replicas: Mutex<Vec<Box<dyn Replicator>>>, // Followers notified of every commit
 }
 
 impl SecureDatabase {
     fn new() -> Self {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(DB_FILE)
-            .expect("Failed to open database file");
+        Self::open(DB_FILE).expect("Failed to open database file")
+    }
 
-        Self {
+    /// Opens a database whose WAL is appended to `path`
+    fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
             data: Mutex::new(HashMap::new()),
             log_file: Mutex::new(BufWriter::new(file)),
             transactions: Mutex::new(HashMap::new()),
-        }
+            replicas: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Registers a follower that receives every subsequent write
+    fn add_replica<R: Replicator + 'static>(&self, replica: R) {
+        lock_or_recover(&self.replicas, "replicas").push(Box::new(replica));
     }
 
     /// Inserts a key-value pair with cryptographic logging
@@ -65,32 +186,109 @@ impl SecureDatabase {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let log_entry = format!("{} | {} -> {}", timestamp, key, value);
-        let hash = sha256(log_entry.as_bytes());
+        self.commit(&WalRecord {
+            timestamp,
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
 
-        let mut data = self.data.lock().unwrap();
-        let mut log_file = self.log_file.lock().unwrap();
-        let mut transactions = self.transactions.lock().unwrap();
+    /// Appends a record to the WAL, applies it, then forwards it to every replica
+    fn commit(&self, record: &WalRecord) {
+        let log_entry = record.log_entry();
+        let hash = crypto::sha256(log_entry.as_bytes());
+
+        let mut data = lock_or_recover(&self.data, "data");
+        let mut log_file = lock_or_recover(&self.log_file, "write-ahead log");
+        let mut transactions = lock_or_recover(&self.transactions, "transactions");
 
         if transactions.len() >= MAX_TRANSACTIONS {
             transactions.clear();
         }
 
-        transactions.insert(timestamp, format!("{:x?}", hash));
-        data.insert(key.to_string(), value.to_string());
+        transactions.insert(record.timestamp, format!("{:x?}", hash));
+        data.insert(record.key.clone(), record.value.clone());
         writeln!(log_file, "{} | Hash: {:x?}", log_entry, hash).expect("Failed to write to log");
+        drop(transactions);
+        drop(data);
+
+        // Still holding the WAL lock, so replicas see writes in commit order
+        for replica in lock_or_recover(&self.replicas, "replicas").iter() {
+            replica.apply(record);
+        }
+    }
+
+    /// Writes every key-value pair to `path`, followed by a SHA-256 over the content.
+    /// The file is written beside `path` and renamed, so a crash never leaves a torn snapshot.
+    fn export_snapshot(&self, path: &str) -> io::Result<()> {
+        let mut content = SNAPSHOT_MAGIC.to_vec();
+        {
+            let data = lock_or_recover(&self.data, "data");
+            let mut entries: Vec<_> = data.iter().collect();
+            entries.sort();
+            content.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+            for (key, value) in entries {
+                write_field(&mut content, key);
+                write_field(&mut content, value);
+            }
+        }
+        let checksum = crypto::sha256(&content);
+        content.extend_from_slice(&checksum);
+
+        let staging = format!("{}.tmp", path);
+        let mut file = File::create(&staging)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&staging, path)
+    }
+
+    /// Replaces the current key-value pairs with a snapshot from `export_snapshot`.
+    /// A snapshot whose checksum does not match is refused and nothing is loaded.
+    #[allow(dead_code)] // Used when restoring a node, not by `main`
+    fn import_snapshot(&self, path: &str) -> io::Result<usize> {
+        let snapshot = fs::read(path)?;
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason);
+        if snapshot.len() < SNAPSHOT_MAGIC.len() + 8 + SNAPSHOT_CHECKSUM_LEN {
+            return Err(invalid("snapshot is truncated"));
+        }
+        let (content, checksum) = snapshot.split_at(snapshot.len() - SNAPSHOT_CHECKSUM_LEN);
+        if crypto::sha256(content) != checksum {
+            return Err(invalid("snapshot checksum mismatch"));
+        }
+        if !content.starts_with(SNAPSHOT_MAGIC) {
+            return Err(invalid("not a database snapshot"));
+        }
+
+        let mut reader = &content[SNAPSHOT_MAGIC.len()..];
+        let mut count = [0u8; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_be_bytes(count) as usize;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let key = read_field(&mut reader)?;
+            let value = read_field(&mut reader)?;
+            entries.insert(key, value);
+        }
+        if !reader.is_empty() {
+            return Err(invalid("trailing data in snapshot"));
+        }
+
+        let loaded = entries.len();
+        *lock_or_recover(&self.data, "data") = entries;
+        Ok(loaded)
     }
 
     /// Retrieves a value by key
     fn get(&self, key: &str) -> Option<String> {
-        let data = self.data.lock().unwrap();
+        let data = lock_or_recover(&self.data, "data");
         data.get(key).cloned()
     }
 
     /// Verifies data integrity
     fn verify_transaction(&self, timestamp: u64, key: &str, value: &str) -> bool {
-        let expected_hash = sha256(format!("{} | {} -> {}", timestamp, key, value).as_bytes());
-        let transactions = self.transactions.lock().unwrap();
+        let expected_hash =
+            crypto::sha256(format!("{} | {} -> {}", timestamp, key, value).as_bytes());
+        let transactions = lock_or_recover(&self.transactions, "transactions");
 
         match transactions.get(&timestamp) {
             Some(stored_hash) => stored_hash == &format!("{:x?}", expected_hash),
@@ -99,9 +297,50 @@ impl SecureDatabase {
     }
 }
 
+/// A follower database applies the primary's records exactly as they were committed
+impl Replicator for SecureDatabase {
+    fn apply(&self, op: &WalRecord) {
+        self.commit(op);
+    }
+}
+
+/// Applies records streamed by a primary's `TcpReplicator` until it disconnects
+fn receive_replication(db: &SecureDatabase, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    while let Some(record) = WalRecord::decode(&mut reader)? {
+        db.apply(&record);
+    }
+    Ok(())
+}
+
+/// Accepts primaries on `listener` and applies their writes to `db`
+fn serve_replication(db: Arc<SecureDatabase>, listener: TcpListener) {
+    for stream in listener.incoming().flatten() {
+        let db = Arc::clone(&db);
+        thread::spawn(move || {
+            if let Err(e) = receive_replication(&db, stream) {
+                eprintln!("[DB] Replication stream failed: {}", e);
+            }
+        });
+    }
+}
+
 fn main() {
     let db = Arc::new(SecureDatabase::new());
 
+    if let Ok(peer) = std::env::var(REPLICA_ADDR_ENV) {
+        db.add_replica(TcpReplicator::new(&peer));
+    }
+    if let Ok(addr) = std::env::var(REPLICATION_BIND_ENV) {
+        match TcpListener::bind(&addr) {
+            Ok(listener) => {
+                let follower = Arc::clone(&db);
+                thread::spawn(move || serve_replication(follower, listener));
+            }
+            Err(e) => eprintln!("[DB] Failed to bind replication listener {}: {}", addr, e),
+        }
+    }
+
     db.insert("user:1", "Alice");
     db.insert("user:2", "Bob");
 
@@ -110,4 +349,108 @@ fn main() {
 
     let is_valid = db.verify_transaction(1698745672, "user:1", "Alice");
     println!("Transaction verification: {}", is_valid);
+
+    match db.export_snapshot(SNAPSHOT_FILE) {
+        Ok(()) => println!("Snapshot written to {}", SNAPSHOT_FILE),
+        Err(e) => eprintln!("[DB] Snapshot export failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_db(name: &str) -> SecureDatabase {
+        let path = test_util::scratch_path("db", name);
+        SecureDatabase::open(path.to_str().unwrap()).expect("open scratch database")
+    }
+
+    fn contents(db: &SecureDatabase) -> HashMap<String, String> {
+        lock_or_recover(&db.data, "data").clone()
+    }
+
+    #[test]
+    fn follower_ends_with_the_primary_key_values() {
+        let primary = scratch_db("primary");
+        let follower = Arc::new(scratch_db("follower"));
+        primary.add_replica(Arc::clone(&follower));
+
+        primary.insert("user:1", "Alice");
+        primary.insert("user:2", "Bob");
+        primary.insert("user:1", "Carol");
+        primary.insert("empty", "");
+
+        assert_eq!(contents(&follower), contents(&primary));
+        assert_eq!(follower.get("user:1").as_deref(), Some("Carol"));
+        assert_eq!(contents(&follower).len(), 3);
+    }
+
+    #[test]
+    fn tcp_replicator_streams_records_to_a_follower() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = listener.local_addr().unwrap().to_string();
+        let follower = scratch_db("tcp-follower");
+
+        let primary = scratch_db("tcp-primary");
+        primary.add_replica(TcpReplicator::new(&peer));
+        let receiver = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            receive_replication(&follower, stream).unwrap();
+            follower
+        });
+
+        primary.insert("user:1", "Alice");
+        primary.insert("user:2", "Bob");
+        // Dropping the primary closes the stream, ending the follower's loop
+        let expected = contents(&primary);
+        drop(primary);
+
+        let follower = receiver.join().unwrap();
+        assert_eq!(contents(&follower), expected);
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_export_and_import() {
+        let source = scratch_db("snapshot-source");
+        source.insert("user:1", "Alice");
+        source.insert("user:2", "Bob");
+        source.insert("note", "multi\nline | -> value");
+        let path = test_util::scratch_path("db", "snapshot-round-trip")
+            .to_string_lossy()
+            .into_owned();
+        source.export_snapshot(&path).unwrap();
+
+        let restored = scratch_db("snapshot-restored");
+        restored.insert("stale", "replaced by the import");
+        assert_eq!(restored.import_snapshot(&path).unwrap(), 3);
+        assert_eq!(contents(&restored), contents(&source));
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn tampered_snapshot_is_rejected_and_nothing_is_loaded() {
+        let source = scratch_db("snapshot-tamper-source");
+        source.insert("user:1", "Alice");
+        let path = test_util::scratch_path("db", "snapshot-tampered")
+            .to_string_lossy()
+            .into_owned();
+        source.export_snapshot(&path).unwrap();
+        let original = fs::read(&path).unwrap();
+
+        let target = scratch_db("snapshot-tamper-target");
+        target.insert("user:1", "Untouched");
+        let mut tampered = original.clone();
+        let index = tampered.len() - SNAPSHOT_CHECKSUM_LEN - 1;
+        tampered[index] ^= 0x01;
+        fs::write(&path, &tampered).unwrap();
+        let err = target.import_snapshot(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(target.get("user:1").as_deref(), Some("Untouched"));
+
+        fs::write(&path, &original[..original.len() - 1]).unwrap();
+        assert!(target.import_snapshot(&path).is_err());
+        assert_eq!(target.get("user:1").as_deref(), Some("Untouched"));
+        let _ = fs::remove_file(&path);
+    }
 }
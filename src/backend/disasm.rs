@@ -0,0 +1,466 @@
+//! Minimal x86-64 Disassembler (Standard Library Only)
+//! Linear-sweep decoding of the instructions triage needs for context: `mov`,
+//! `lea`, `push`/`pop`, `call`/`jmp`/`jcc`, `ret`, `syscall`/`int`, `nop` and the
+//! common ALU forms. Intel syntax, with branch targets resolved to file offsets.
+//!
+//! Anything outside the subset (and anything cut off by the end of the buffer)
+//! decodes as a single `db 0xNN` byte, so a sweep always makes progress.
+
+const REG64: [&str; 16] = [
+    "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15",
+];
+const REG32: [&str; 16] = [
+    "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "r8d", "r9d", "r10d", "r11d", "r12d",
+    "r13d", "r14d", "r15d",
+];
+const REG16: [&str; 16] = [
+    "ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "r8w", "r9w", "r10w", "r11w", "r12w", "r13w",
+    "r14w", "r15w",
+];
+const CONDITIONS: [&str; 16] = [
+    "o", "no", "b", "ae", "e", "ne", "be", "a", "s", "ns", "p", "np", "l", "ge", "le", "g",
+];
+const GROUP1: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+
+/// One decoded instruction; `offset` is where it starts in the buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub len: usize,
+    pub text: String,
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &str {
+        self.text.split(' ').next().unwrap_or("")
+    }
+}
+
+/// Decodes `count` instructions starting at `start`, stopping early at the end of `code`
+pub fn disassemble(code: &[u8], start: usize, count: usize) -> Vec<Instruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut offset = start;
+    while instructions.len() < count && offset < code.len() {
+        let instruction = decode(code, offset);
+        offset += instruction.len;
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Decodes the instruction at `offset`, falling back to `db` for unknown bytes
+pub fn decode(code: &[u8], offset: usize) -> Instruction {
+    let mut cursor = Cursor { code, pos: offset };
+    match cursor.instruction() {
+        Some(text) => Instruction {
+            offset,
+            len: cursor.pos - offset,
+            text,
+        },
+        None => Instruction {
+            offset,
+            len: 1,
+            text: format!("db 0x{:02x}", code[offset]),
+        },
+    }
+}
+
+/// Operand size selected by the prefixes
+#[derive(Clone, Copy, PartialEq)]
+enum Size {
+    Word,
+    Dword,
+    Qword,
+}
+
+impl Size {
+    fn register(self, index: usize) -> &'static str {
+        match self {
+            Size::Word => REG16[index],
+            Size::Dword => REG32[index],
+            Size::Qword => REG64[index],
+        }
+    }
+
+    fn keyword(self) -> &'static str {
+        match self {
+            Size::Word => "word",
+            Size::Dword => "dword",
+            Size::Qword => "qword",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Rex {
+    w: bool,
+    r: bool,
+    x: bool,
+    b: bool,
+}
+
+/// A decoded ModRM byte: `reg` with REX.R applied, and the r/m operand as text
+struct ModRm {
+    reg: usize,
+    rm: String,
+    is_register: bool,
+}
+
+struct Cursor<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.code.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.code.get(self.pos..self.pos.checked_add(N)?)?;
+        self.pos += N;
+        bytes.try_into().ok()
+    }
+
+    fn i8(&mut self) -> Option<i64> {
+        Some(self.byte()? as i8 as i64)
+    }
+
+    fn i32(&mut self) -> Option<i64> {
+        Some(i32::from_le_bytes(self.bytes()?) as i64)
+    }
+
+    /// Branch target of a displacement relative to the end of this instruction
+    fn target(&self, displacement: i64) -> String {
+        format!("0x{:x}", self.pos as i64 + displacement)
+    }
+
+    fn instruction(&mut self) -> Option<String> {
+        let mut size = Size::Dword;
+        let mut byte = self.byte()?;
+        if byte == 0x66 {
+            size = Size::Word;
+            byte = self.byte()?;
+        }
+        let mut rex = Rex::default();
+        if (0x40..=0x4f).contains(&byte) {
+            rex = Rex {
+                w: byte & 8 != 0,
+                r: byte & 4 != 0,
+                x: byte & 2 != 0,
+                b: byte & 1 != 0,
+            };
+            if rex.w {
+                size = Size::Qword;
+            }
+            byte = self.byte()?;
+        }
+        let low = (byte & 7) as usize + if rex.b { 8 } else { 0 };
+
+        Some(match byte {
+            0x90 if !rex.b => "nop".to_string(),
+            0xc3 => "ret".to_string(),
+            0xc2 => format!("ret 0x{:x}", u16::from_le_bytes(self.bytes()?)),
+            0xc9 => "leave".to_string(),
+            0xcc => "int3".to_string(),
+            0xcd => format!("int 0x{:x}", self.byte()?),
+            0xf4 => "hlt".to_string(),
+            // push/pop default to 64-bit operands in long mode
+            0x50..=0x57 => format!("push {}", REG64[low]),
+            0x58..=0x5f => format!("pop {}", REG64[low]),
+            0x6a => format!("push {}", hex(self.i8()?)),
+            0x68 => format!("push {}", hex(self.i32()?)),
+            0xe8 => {
+                let displacement = self.i32()?;
+                format!("call {}", self.target(displacement))
+            }
+            0xe9 => {
+                let displacement = self.i32()?;
+                format!("jmp {}", self.target(displacement))
+            }
+            0xeb => {
+                let displacement = self.i8()?;
+                format!("jmp {}", self.target(displacement))
+            }
+            0x70..=0x7f => {
+                let displacement = self.i8()?;
+                let condition = CONDITIONS[(byte & 0xf) as usize];
+                format!("j{} {}", condition, self.target(displacement))
+            }
+            0xb8..=0xbf => {
+                let immediate = match size {
+                    Size::Qword => i64::from_le_bytes(self.bytes()?),
+                    Size::Dword => self.i32()?,
+                    Size::Word => i16::from_le_bytes(self.bytes()?) as i64,
+                };
+                format!("mov {}, {}", size.register(low), hex(immediate))
+            }
+            // reg/mem forms: add, or, and, sub, xor, cmp, test, mov, lea
+            0x01 | 0x09 | 0x21 | 0x29 | 0x31 | 0x39 | 0x85 | 0x89 => {
+                let name = alu_name(byte)?;
+                let modrm = self.modrm(rex, size)?;
+                format!("{} {}, {}", name, modrm.rm, size.register(modrm.reg))
+            }
+            0x03 | 0x0b | 0x23 | 0x2b | 0x33 | 0x3b | 0x8b => {
+                let name = alu_name(byte)?;
+                let modrm = self.modrm(rex, size)?;
+                format!("{} {}, {}", name, size.register(modrm.reg), modrm.rm)
+            }
+            0x8d => {
+                let modrm = self.modrm(rex, size)?;
+                if modrm.is_register {
+                    return None;
+                }
+                format!("lea {}, {}", size.register(modrm.reg), modrm.rm)
+            }
+            0x81 | 0x83 => {
+                let modrm = self.modrm(rex, size)?;
+                let immediate = match (byte, size) {
+                    (0x83, _) => self.i8()?,
+                    (_, Size::Word) => i16::from_le_bytes(self.bytes()?) as i64,
+                    _ => self.i32()?,
+                };
+                format!(
+                    "{} {}, {}",
+                    GROUP1[modrm.reg & 7],
+                    sized(&modrm, size),
+                    hex(immediate)
+                )
+            }
+            0xc7 => {
+                let modrm = self.modrm(rex, size)?;
+                if modrm.reg & 7 != 0 {
+                    return None;
+                }
+                let immediate = if size == Size::Word {
+                    i16::from_le_bytes(self.bytes()?) as i64
+                } else {
+                    self.i32()?
+                };
+                format!("mov {}, {}", sized(&modrm, size), hex(immediate))
+            }
+            0xff => {
+                // Indirect call/jmp/push take 64-bit operands regardless of REX.W
+                let modrm = self.modrm(rex, Size::Qword)?;
+                let name = match modrm.reg & 7 {
+                    2 => "call",
+                    4 => "jmp",
+                    6 => "push",
+                    _ => return None,
+                };
+                format!("{} {}", name, sized(&modrm, Size::Qword))
+            }
+            0x0f => {
+                let second = self.byte()?;
+                match second {
+                    0x05 => "syscall".to_string(),
+                    0x0b => "ud2".to_string(),
+                    0x1f => {
+                        self.modrm(rex, size)?;
+                        "nop".to_string()
+                    }
+                    0x80..=0x8f => {
+                        let displacement = self.i32()?;
+                        let condition = CONDITIONS[(second & 0xf) as usize];
+                        format!("j{} {}", condition, self.target(displacement))
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Decodes ModRM plus any SIB byte and displacement
+    fn modrm(&mut self, rex: Rex, size: Size) -> Option<ModRm> {
+        let byte = self.byte()?;
+        let mode = byte >> 6;
+        let reg = ((byte >> 3) & 7) as usize + if rex.r { 8 } else { 0 };
+        let rm = (byte & 7) as usize;
+
+        if mode == 3 {
+            return Some(ModRm {
+                reg,
+                rm: size.register(rm + if rex.b { 8 } else { 0 }).to_string(),
+                is_register: true,
+            });
+        }
+
+        let mut address = String::new();
+        let mut no_base = false;
+        if rm == 4 {
+            let sib = self.byte()?;
+            let scale = 1 << (sib >> 6);
+            let index = ((sib >> 3) & 7) as usize + if rex.x { 8 } else { 0 };
+            let base = (sib & 7) as usize;
+            if base == 5 && mode == 0 {
+                no_base = true;
+            } else {
+                address.push_str(REG64[base + if rex.b { 8 } else { 0 }]);
+            }
+            if index != 4 {
+                if !address.is_empty() {
+                    address.push('+');
+                }
+                address.push_str(REG64[index]);
+                if scale > 1 {
+                    address.push_str(&format!("*{}", scale));
+                }
+            }
+        } else if rm == 5 && mode == 0 {
+            // RIP-relative; the target depends on the instruction's full length
+            let displacement = self.i32()?;
+            return Some(ModRm {
+                reg,
+                rm: format!("[rip{}]", signed_hex(displacement)),
+                is_register: false,
+            });
+        } else {
+            address.push_str(REG64[rm + if rex.b { 8 } else { 0 }]);
+        }
+
+        let displacement = match mode {
+            1 => self.i8()?,
+            2 => self.i32()?,
+            _ if no_base => self.i32()?,
+            _ => 0,
+        };
+        if displacement != 0 || address.is_empty() {
+            if address.is_empty() {
+                address = hex(displacement);
+            } else {
+                address.push_str(&signed_hex(displacement));
+            }
+        }
+        Some(ModRm {
+            reg,
+            rm: format!("[{}]", address),
+            is_register: false,
+        })
+    }
+}
+
+fn alu_name(opcode: u8) -> Option<&'static str> {
+    Some(match opcode {
+        0x01 | 0x03 => "add",
+        0x09 | 0x0b => "or",
+        0x21 | 0x23 => "and",
+        0x29 | 0x2b => "sub",
+        0x31 | 0x33 => "xor",
+        0x39 | 0x3b => "cmp",
+        0x85 => "test",
+        0x89 | 0x8b => "mov",
+        _ => return None,
+    })
+}
+
+/// A memory operand with its size spelled out, as needed when no register implies it
+fn sized(modrm: &ModRm, size: Size) -> String {
+    if modrm.is_register {
+        modrm.rm.clone()
+    } else {
+        format!("{} {}", size.keyword(), modrm.rm)
+    }
+}
+
+fn hex(value: i64) -> String {
+    if value < 0 {
+        format!("-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+fn signed_hex(value: i64) -> String {
+    if value < 0 {
+        hex(value)
+    } else {
+        format!("+{}", hex(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(code: &[u8]) -> Vec<(usize, usize, String)> {
+        disassemble(code, 0, 16)
+            .into_iter()
+            .map(|instruction| (instruction.offset, instruction.len, instruction.text))
+            .collect()
+    }
+
+    fn expected(lines: &[(usize, usize, &str)]) -> Vec<(usize, usize, String)> {
+        lines
+            .iter()
+            .map(|&(offset, len, text)| (offset, len, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn syscall_then_ret_decodes_to_both_mnemonics() {
+        let instructions = disassemble(b"\x0f\x05\xc3", 0, 16);
+        let mnemonics: Vec<&str> = instructions.iter().map(Instruction::mnemonic).collect();
+        assert_eq!(mnemonics, ["syscall", "ret"]);
+        assert_eq!(
+            listing(b"\x0f\x05\xc3"),
+            expected(&[(0, 2, "syscall"), (2, 1, "ret")])
+        );
+    }
+
+    #[test]
+    fn exit_stub_decodes_with_operands() {
+        // mov eax, 60; xor edi, edi; syscall
+        assert_eq!(
+            listing(b"\xb8\x3c\x00\x00\x00\x31\xff\x0f\x05"),
+            expected(&[
+                (0, 5, "mov eax, 0x3c"),
+                (5, 2, "xor edi, edi"),
+                (7, 2, "syscall"),
+            ])
+        );
+        assert_eq!(
+            listing(b"\x55\x48\x89\xe5\x48\x8b\x45\xf8\x5d\xcd\x80"),
+            expected(&[
+                (0, 1, "push rbp"),
+                (1, 3, "mov rbp, rsp"),
+                (4, 4, "mov rax, [rbp-0x8]"),
+                (8, 1, "pop rbp"),
+                (9, 2, "int 0x80"),
+            ])
+        );
+    }
+
+    #[test]
+    fn branch_targets_resolve_to_offsets() {
+        let code = b"\x48\x8d\x3d\x10\x00\x00\x00\xe8\xf4\xff\xff\xff\x74\x02\x90\x90";
+        assert_eq!(
+            listing(code),
+            expected(&[
+                (0, 7, "lea rdi, [rip+0x10]"),
+                (7, 5, "call 0x0"),
+                (12, 2, "je 0x10"),
+                (14, 1, "nop"),
+                (15, 1, "nop"),
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_or_cut_off_bytes_become_single_db_bytes() {
+        // `syscall` cut off after its first byte, then a `call` missing its displacement
+        assert_eq!(
+            listing(b"\xc3\x0f"),
+            expected(&[(0, 1, "ret"), (1, 1, "db 0x0f")])
+        );
+        assert_eq!(
+            listing(b"\xe8\x0f"),
+            expected(&[(0, 1, "db 0xe8"), (1, 1, "db 0x0f")])
+        );
+        assert_eq!(disassemble(b"\x90\x90\x90", 1, 1).len(), 1);
+        assert!(disassemble(b"\x90", 5, 4).is_empty());
+    }
+}
@@ -0,0 +1,672 @@
+//! Manual ELF Parsing (Standard Library Only)
+//! Reads the section header table and the dynamic symbol table of 32- and 64-bit,
+//! little- and big-endian ELF files, borrowing from the caller's buffer:
+//! - **File type, machine and entry point from the header alone**
+//! - **Section list with names, types, offsets and sizes**
+//! - **Imported symbols: undefined `.dynsym` entries, with the address relocations bind them to**
+//! - **Exported symbols: defined global and weak `.dynsym` entries**
+//! - **Dangerous imports (`system`, `execve`, `mprotect`, ...) flagged for triage**
+//!
+//! Every offset and size read from the file is bounds-checked, so a truncated or
+//! hostile binary yields an `ElfError` rather than a panic.
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub const ELF_MAGIC: &[u8; 4] = b"\x7FELF";
+
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_REL: u32 = 9;
+const SHT_DYNSYM: u32 = 11;
+const SHN_UNDEF: u16 = 0;
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+const ET_CORE: u16 = 4;
+const PT_DYNAMIC: u32 = 2;
+const DT_NULL: u64 = 0;
+const DT_FLAGS_1: u64 = 0x6fff_fffb;
+const DF_1_PIE: u64 = 0x0800_0000;
+
+/// Imports that let a binary run commands, load code or make memory executable
+const DANGEROUS_IMPORTS: &[&str] = &[
+    "system",
+    "popen",
+    "execve",
+    "execv",
+    "execvp",
+    "execvpe",
+    "execl",
+    "execlp",
+    "execle",
+    "fexecve",
+    "mprotect",
+    "dlopen",
+    "ptrace",
+    "memfd_create",
+];
+
+/// Why a buffer could not be read as ELF
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElfError {
+    NotElf,
+    /// A header, table or string runs past the end of the file
+    Truncated,
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::NotElf => f.write_str("not an ELF file"),
+            ElfError::Truncated => f.write_str("ELF data is truncated"),
+            ElfError::Unsupported(what) => write!(f, "unsupported ELF: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+/// What kind of file `e_type` says this is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    Relocatable,
+    Executable,
+    /// `ET_DYN` flagged `DF_1_PIE`, as linkers mark position-independent executables
+    PieExecutable,
+    SharedObject,
+    Core,
+    Other(u16),
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileType::Relocatable => f.write_str("relocatable object"),
+            FileType::Executable => f.write_str("executable"),
+            FileType::PieExecutable => f.write_str("position-independent executable"),
+            FileType::SharedObject => f.write_str("shared object"),
+            FileType::Core => f.write_str("core dump"),
+            FileType::Other(other) => write!(f, "type 0x{:04x}", other),
+        }
+    }
+}
+
+/// Provenance read from the ELF header and program headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElfInfo {
+    pub is_64: bool,
+    pub little_endian: bool,
+    pub file_type: FileType,
+    pub machine: u16, // `e_machine`
+    pub entry: u64,   // `e_entry`; 0 when there is none
+}
+
+impl ElfInfo {
+    /// Reads the header without the section table, so it works on stripped files too
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        Elf::header(data)?.info()
+    }
+
+    /// The architecture `e_machine` names, if it is a common one
+    pub fn machine_name(&self) -> Option<&'static str> {
+        Some(match self.machine {
+            2 => "SPARC",
+            3 => "x86",
+            8 => "MIPS",
+            20 => "PowerPC",
+            21 => "PowerPC64",
+            22 => "S/390",
+            40 => "ARM",
+            43 => "SPARC V9",
+            62 => "x86-64",
+            183 => "AArch64",
+            243 => "RISC-V",
+            258 => "LoongArch",
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub kind: u32, // `sh_type`
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub entsize: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Object,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Global,
+    Weak,
+}
+
+/// A dynamic symbol. `address` is the symbol's value for exports and, for imports,
+/// the slot a relocation writes the resolved address to (0 if none does).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub binding: Binding,
+    pub address: u64,
+    pub dangerous: bool,
+}
+
+/// One `.dynsym` entry before it is classified
+struct RawSymbol {
+    name: String,
+    info: u8,
+    shndx: u16,
+    value: u64,
+}
+
+/// `.dynsym` in table order, and relocation targets keyed by symbol index
+type DynamicSymbols = (Vec<RawSymbol>, HashMap<usize, u64>);
+
+/// A parsed ELF file borrowing the buffer it was read from
+pub struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+    sections: Vec<Section>,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        let mut elf = Self::header(data)?;
+        elf.sections = elf.read_sections()?;
+        Ok(elf)
+    }
+
+    /// Checks the identification bytes; sections are left unread
+    fn header(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < 16 || &data[..4] != ELF_MAGIC {
+            return Err(ElfError::NotElf);
+        }
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return Err(ElfError::Unsupported("unknown class")),
+        };
+        let little_endian = match data[5] {
+            1 => true,
+            2 => false,
+            _ => return Err(ElfError::Unsupported("unknown byte order")),
+        };
+        Ok(Self {
+            data,
+            is_64,
+            little_endian,
+            sections: Vec::new(),
+        })
+    }
+
+    pub fn info(&self) -> Result<ElfInfo, ElfError> {
+        let file_type = match self.u16(0x10)? {
+            ET_REL => FileType::Relocatable,
+            ET_EXEC => FileType::Executable,
+            ET_DYN if self.is_pie()? => FileType::PieExecutable,
+            ET_DYN => FileType::SharedObject,
+            ET_CORE => FileType::Core,
+            other => FileType::Other(other),
+        };
+        let entry = if self.is_64 {
+            self.u64(0x18)?
+        } else {
+            self.u32(0x18)? as u64
+        };
+        Ok(ElfInfo {
+            is_64: self.is_64,
+            little_endian: self.little_endian,
+            file_type,
+            machine: self.u16(0x12)?,
+            entry,
+        })
+    }
+
+    /// True if the dynamic section sets `DF_1_PIE`, the test `readelf` uses. A
+    /// shared library that can also be run, like libc, names an interpreter but is
+    /// not flagged.
+    fn is_pie(&self) -> Result<bool, ElfError> {
+        let (phoff, phentsize, phnum) = if self.is_64 {
+            (self.u64(0x20)?, self.u16(0x36)?, self.u16(0x38)?)
+        } else {
+            (self.u32(0x1c)? as u64, self.u16(0x2a)?, self.u16(0x2c)?)
+        };
+        for index in 0..phnum as u64 {
+            let base = index
+                .checked_mul(phentsize as u64)
+                .and_then(|offset| offset.checked_add(phoff))
+                .ok_or(ElfError::Truncated)
+                .and_then(to_usize)?;
+            if self.u32(base)? != PT_DYNAMIC {
+                continue;
+            }
+            let (offset, size) = if self.is_64 {
+                (self.u64(base + 8)?, self.u64(base + 32)?)
+            } else {
+                (self.u32(base + 4)? as u64, self.u32(base + 16)? as u64)
+            };
+            let start = to_usize(offset)?;
+            let end = start
+                .checked_add(to_usize(size)?)
+                .ok_or(ElfError::Truncated)?;
+            let table = self.data.get(start..end).ok_or(ElfError::Truncated)?;
+            let word = if self.is_64 { 8 } else { 4 };
+            for entry in table.chunks_exact(2 * word) {
+                match self.field(entry, 0, word) {
+                    DT_NULL => break,
+                    DT_FLAGS_1 => return Ok(self.field(entry, word, word) & DF_1_PIE != 0),
+                    _ => {}
+                }
+            }
+            return Ok(false);
+        }
+        Ok(false)
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Undefined dynamic symbols the loader must resolve from shared libraries
+    pub fn imports(&self) -> Result<Vec<Symbol>, ElfError> {
+        let (symbols, slots) = match self.dynamic_symbols()? {
+            Some(found) => found,
+            None => return Ok(Vec::new()),
+        };
+        Ok(symbols
+            .into_iter()
+            .enumerate()
+            .filter(|(_, raw)| raw.shndx == SHN_UNDEF && !raw.name.is_empty())
+            .filter_map(|(index, raw)| {
+                let address = slots.get(&index).copied().unwrap_or(0);
+                classify(raw, address)
+            })
+            .collect())
+    }
+
+    /// Defined global and weak dynamic symbols other code can link against
+    pub fn exports(&self) -> Result<Vec<Symbol>, ElfError> {
+        let (symbols, _) = match self.dynamic_symbols()? {
+            Some(found) => found,
+            None => return Ok(Vec::new()),
+        };
+        Ok(symbols
+            .into_iter()
+            .filter(|raw| raw.shndx != SHN_UNDEF && !raw.name.is_empty())
+            .filter(|raw| !matches!(raw.info & 0xf, STT_SECTION | STT_FILE))
+            .filter_map(|raw| {
+                let value = raw.value;
+                classify(raw, value)
+            })
+            .collect())
+    }
+
+    fn read_sections(&self) -> Result<Vec<Section>, ElfError> {
+        let (shoff, shentsize, shnum, shstrndx) = if self.is_64 {
+            (
+                self.u64(0x28)?,
+                self.u16(0x3a)?,
+                self.u16(0x3c)?,
+                self.u16(0x3e)?,
+            )
+        } else {
+            (
+                self.u32(0x20)? as u64,
+                self.u16(0x2e)?,
+                self.u16(0x30)?,
+                self.u16(0x32)?,
+            )
+        };
+        if shoff == 0 || shnum == 0 {
+            return Ok(Vec::new()); // Section headers stripped
+        }
+        let minimum = if self.is_64 { 64 } else { 40 };
+        if (shentsize as usize) < minimum {
+            return Err(ElfError::Unsupported("section header entry too small"));
+        }
+
+        let mut sections = Vec::with_capacity(shnum as usize);
+        let mut name_offsets = Vec::with_capacity(shnum as usize);
+        for index in 0..shnum as u64 {
+            let base = index
+                .checked_mul(shentsize as u64)
+                .and_then(|offset| offset.checked_add(shoff))
+                .ok_or(ElfError::Truncated)
+                .and_then(to_usize)?;
+            if base
+                .checked_add(shentsize as usize)
+                .is_none_or(|end| end > self.data.len())
+            {
+                return Err(ElfError::Truncated);
+            }
+            let (name, section) = if self.is_64 {
+                (
+                    self.u32(base)?,
+                    Section {
+                        name: String::new(),
+                        kind: self.u32(base + 4)?,
+                        addr: self.u64(base + 16)?,
+                        offset: self.u64(base + 24)?,
+                        size: self.u64(base + 32)?,
+                        link: self.u32(base + 40)?,
+                        entsize: self.u64(base + 56)?,
+                    },
+                )
+            } else {
+                (
+                    self.u32(base)?,
+                    Section {
+                        name: String::new(),
+                        kind: self.u32(base + 4)?,
+                        addr: self.u32(base + 12)? as u64,
+                        offset: self.u32(base + 16)? as u64,
+                        size: self.u32(base + 20)? as u64,
+                        link: self.u32(base + 24)?,
+                        entsize: self.u32(base + 36)? as u64,
+                    },
+                )
+            };
+            name_offsets.push(name);
+            sections.push(section);
+        }
+
+        if let Some(names) = sections.get(shstrndx as usize) {
+            let names = self.section_bytes(names)?;
+            for (section, offset) in sections.iter_mut().zip(name_offsets) {
+                section.name = read_cstr(names, offset as usize)?;
+            }
+        }
+        Ok(sections)
+    }
+
+    /// Every `.dynsym` entry in table order, plus the relocation target of each
+    /// symbol index that a relocation against `.dynsym` names
+    fn dynamic_symbols(&self) -> Result<Option<DynamicSymbols>, ElfError> {
+        let (dynsym_index, dynsym) = match self
+            .sections
+            .iter()
+            .enumerate()
+            .find(|(_, section)| section.kind == SHT_DYNSYM)
+        {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let strtab = self
+            .sections
+            .get(dynsym.link as usize)
+            .filter(|section| section.kind == SHT_STRTAB)
+            .ok_or(ElfError::Unsupported(".dynsym has no string table"))?;
+        let names = self.section_bytes(strtab)?;
+        let table = self.section_bytes(dynsym)?;
+
+        let entry_size = if self.is_64 { 24 } else { 16 };
+        let mut symbols = Vec::with_capacity(table.len() / entry_size);
+        for entry in table.chunks_exact(entry_size) {
+            let raw = if self.is_64 {
+                RawSymbol {
+                    name: read_cstr(names, self.field(entry, 0, 4) as usize)?,
+                    info: entry[4],
+                    shndx: self.field(entry, 6, 2) as u16,
+                    value: self.field(entry, 8, 8),
+                }
+            } else {
+                RawSymbol {
+                    name: read_cstr(names, self.field(entry, 0, 4) as usize)?,
+                    info: entry[12],
+                    shndx: self.field(entry, 14, 2) as u16,
+                    value: self.field(entry, 4, 4),
+                }
+            };
+            symbols.push(raw);
+        }
+
+        let mut slots = HashMap::new();
+        for section in &self.sections {
+            if !matches!(section.kind, SHT_REL | SHT_RELA) || section.link as usize != dynsym_index
+            {
+                continue;
+            }
+            let word = if self.is_64 { 8 } else { 4 };
+            let entry_size = if section.kind == SHT_RELA {
+                3 * word
+            } else {
+                2 * word
+            };
+            for entry in self.section_bytes(section)?.chunks_exact(entry_size) {
+                let offset = self.field(entry, 0, word);
+                let info = self.field(entry, word, word);
+                let symbol = if self.is_64 { info >> 32 } else { info >> 8 } as usize;
+                if symbol != 0 {
+                    slots.entry(symbol).or_insert(offset);
+                }
+            }
+        }
+        Ok(Some((symbols, slots)))
+    }
+
+    fn section_bytes(&self, section: &Section) -> Result<&'a [u8], ElfError> {
+        let start = to_usize(section.offset)?;
+        let end = start
+            .checked_add(to_usize(section.size)?)
+            .ok_or(ElfError::Truncated)?;
+        self.data.get(start..end).ok_or(ElfError::Truncated)
+    }
+
+    /// Reads a `width`-byte unsigned field at `offset` in `bytes` (already bounds-checked)
+    fn field(&self, bytes: &[u8], offset: usize, width: usize) -> u64 {
+        let bytes = &bytes[offset..offset + width];
+        let fold = |value: u64, &byte: &u8| value << 8 | byte as u64;
+        if self.little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        }
+    }
+
+    fn read(&self, offset: usize, width: usize) -> Result<u64, ElfError> {
+        let end = offset.checked_add(width).ok_or(ElfError::Truncated)?;
+        let bytes = self.data.get(offset..end).ok_or(ElfError::Truncated)?;
+        Ok(self.field(bytes, 0, width))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ElfError> {
+        Ok(self.read(offset, 2)? as u16)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ElfError> {
+        Ok(self.read(offset, 4)? as u32)
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, ElfError> {
+        self.read(offset, 8)
+    }
+}
+
+fn to_usize(value: u64) -> Result<usize, ElfError> {
+    usize::try_from(value).map_err(|_| ElfError::Truncated)
+}
+
+/// The NUL-terminated string at `offset` in a string table
+fn read_cstr(table: &[u8], offset: usize) -> Result<String, ElfError> {
+    let bytes = table.get(offset..).ok_or(ElfError::Truncated)?;
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or(ElfError::Truncated)?;
+    Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+/// Builds the public view of a symbol; local symbols are neither imports nor exports
+fn classify(raw: RawSymbol, address: u64) -> Option<Symbol> {
+    let binding = match raw.info >> 4 {
+        STB_GLOBAL => Binding::Global,
+        STB_WEAK => Binding::Weak,
+        STB_LOCAL => return None,
+        _ => Binding::Global, // OS- and processor-specific global bindings such as GNU_UNIQUE
+    };
+    let kind = match raw.info & 0xf {
+        STT_FUNC => SymbolKind::Function,
+        STT_OBJECT => SymbolKind::Object,
+        _ => SymbolKind::Other,
+    };
+    Some(Symbol {
+        dangerous: DANGEROUS_IMPORTS.contains(&raw.name.as_str()),
+        name: raw.name,
+        kind,
+        binding,
+        address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `tests/fixtures/dynamic_imports.c`, linked dynamically against glibc on x86-64
+    const FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/dynamic_imports.elf");
+    /// `tests/fixtures/static_exec.c`, a non-PIE x86-64 executable without libc
+    const STATIC_EXEC: &[u8] = include_bytes!("../../tests/fixtures/static_exec.elf");
+
+    fn named<'s>(symbols: &'s [Symbol], name: &str) -> Option<&'s Symbol> {
+        symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    #[test]
+    fn fixture_header_is_a_64_bit_x86_64_pie() {
+        let info = ElfInfo::parse(FIXTURE).unwrap();
+        assert!(info.is_64 && info.little_endian);
+        assert_eq!(info.file_type, FileType::PieExecutable);
+        assert_eq!(info.machine_name(), Some("x86-64"));
+        assert_ne!(info.entry, 0);
+    }
+
+    #[test]
+    fn imported_libc_symbols_are_listed_with_their_got_slots() {
+        let elf = Elf::parse(FIXTURE).unwrap();
+        assert!(elf.section(".dynsym").is_some() && elf.section(".dynstr").is_some());
+        let imports = elf.imports().unwrap();
+
+        let puts = named(&imports, "puts").expect("puts is imported");
+        assert_eq!(puts.kind, SymbolKind::Function);
+        assert_eq!(puts.binding, Binding::Global);
+        assert!(!puts.dangerous);
+        assert_ne!(puts.address, 0, "puts has no relocation slot");
+
+        let system = named(&imports, "system").expect("system is imported");
+        assert!(system.dangerous);
+        assert!(named(&imports, "__libc_start_main").is_some());
+        assert!(named(&imports, "fixture_exported").is_none());
+    }
+
+    #[test]
+    fn defined_symbols_are_exports_not_imports() {
+        let exports = Elf::parse(FIXTURE).unwrap().exports().unwrap();
+        let exported = named(&exports, "fixture_exported").expect("fixture_exported is exported");
+        assert_eq!(exported.kind, SymbolKind::Function);
+        assert_ne!(exported.address, 0);
+        assert!(named(&exports, "main").is_some());
+        assert!(named(&exports, "puts").is_none());
+    }
+
+    #[test]
+    fn truncated_or_foreign_data_is_rejected() {
+        assert_eq!(Elf::parse(b"MZ\x90\x00").err(), Some(ElfError::NotElf));
+        assert_eq!(Elf::parse(&FIXTURE[..40]).err(), Some(ElfError::Truncated));
+        // The section headers sit at the end of the file
+        assert_eq!(
+            Elf::parse(&FIXTURE[..FIXTURE.len() - 64]).err(),
+            Some(ElfError::Truncated)
+        );
+    }
+
+    /// A 32-bit little-endian ARM (EABI5) shared object: header, one `PT_DYNAMIC`
+    /// program header, and a dynamic table holding `DT_FLAGS_1 = flags_1`
+    fn arm_shared_object(entry: u32, flags_1: u32) -> Vec<u8> {
+        let mut data = vec![0x7f, b'E', b'L', b'F', 1, 1, 1];
+        data.resize(16, 0);
+        for half in [ET_DYN, 40] {
+            data.extend_from_slice(&half.to_le_bytes()); // e_type, e_machine
+        }
+        for word in [1, entry, 52, 0, 0x0500_0000] {
+            data.extend_from_slice(&word.to_le_bytes()); // e_version .. e_flags
+        }
+        for half in [52u16, 32, 1, 40, 0, 0] {
+            data.extend_from_slice(&half.to_le_bytes()); // e_ehsize .. e_shstrndx
+        }
+        let dynamic = [DT_FLAGS_1 as u32, flags_1, DT_NULL as u32, 0];
+        for word in [PT_DYNAMIC, 84, 0, 0, 16, 16, 6, 4] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        for word in dynamic {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn x86_64_executable_reports_its_type_machine_and_entry() {
+        let info = ElfInfo::parse(STATIC_EXEC).unwrap();
+        assert_eq!(
+            info,
+            ElfInfo {
+                is_64: true,
+                little_endian: true,
+                file_type: FileType::Executable,
+                machine: 62,
+                entry: 0x40_1000,
+            }
+        );
+        assert_eq!(info.machine_name(), Some("x86-64"));
+        assert_eq!(info.file_type.to_string(), "executable");
+    }
+
+    #[test]
+    fn arm_shared_object_reports_its_type_machine_and_entry() {
+        let library = arm_shared_object(0x4f1, 0);
+        let info = ElfInfo::parse(&library).unwrap();
+        assert!(!info.is_64 && info.little_endian);
+        assert_eq!(info.file_type, FileType::SharedObject);
+        assert_eq!(info.machine_name(), Some("ARM"));
+        assert_eq!(info.entry, 0x4f1);
+
+        // The same object flagged DF_1_PIE is an executable
+        let pie = arm_shared_object(0x4f1, DF_1_PIE as u32);
+        assert_eq!(
+            ElfInfo::parse(&pie).unwrap().file_type,
+            FileType::PieExecutable
+        );
+        assert_eq!(
+            ElfInfo::parse(&library[..40]).err(),
+            Some(ElfError::Truncated)
+        );
+    }
+}
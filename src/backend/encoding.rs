@@ -0,0 +1,238 @@
+//! Binary-to-text encodings shared by the crypto-adjacent modules (Standard Library Only)
+//! - Base64 and base64url (RFC 4648 §4 and §5), each with or without padding
+//! - Lowercase hex for digests and MACs
+//!
+//! Decoding is strict: the padding must match the chosen variant, and the unused
+//! low bits of the final symbol must be zero, so every byte string has exactly
+//! one accepted encoding (signatures cannot be re-encoded to slip past checks).
+
+use std::fmt;
+
+const STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const PAD: u8 = b'=';
+
+/// A base64 variant: its alphabet and whether output is padded to 4 symbols
+#[derive(Debug, Clone, Copy)]
+pub struct Base64 {
+    alphabet: &'static [u8; 64],
+    padded: bool,
+}
+
+/// `+/` alphabet, `=` padded (MIME, HTTP headers, WebSocket accept keys)
+pub const STANDARD: Base64 = Base64 {
+    alphabet: STANDARD_ALPHABET,
+    padded: true,
+};
+/// `+/` alphabet without padding (PHC password-hash strings)
+pub const STANDARD_NO_PAD: Base64 = Base64 {
+    alphabet: STANDARD_ALPHABET,
+    padded: false,
+};
+/// `-_` alphabet, `=` padded
+pub const URL_SAFE: Base64 = Base64 {
+    alphabet: URL_SAFE_ALPHABET,
+    padded: true,
+};
+/// `-_` alphabet without padding (JWT segments, WebAuthn ids)
+pub const URL_SAFE_NO_PAD: Base64 = Base64 {
+    alphabet: URL_SAFE_ALPHABET,
+    padded: false,
+};
+
+/// Why input could not be decoded; positions are byte offsets into the input
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    InvalidByte {
+        position: usize,
+        byte: u8,
+    },
+    InvalidLength,
+    InvalidPadding,
+    /// The final symbol carries set bits that no byte uses
+    TrailingBits,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidByte { position, byte } => {
+                write!(f, "invalid byte 0x{:02x} at offset {}", byte, position)
+            }
+            DecodeError::InvalidLength => f.write_str("invalid input length"),
+            DecodeError::InvalidPadding => f.write_str("invalid padding"),
+            DecodeError::TrailingBits => f.write_str("non-zero trailing bits"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Base64 {
+    pub fn encode<T: AsRef<[u8]>>(&self, data: T) -> String {
+        let data = data.as_ref();
+        let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+        for chunk in data.chunks(3) {
+            let n = (chunk[0] as u32) << 16
+                | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+                | *chunk.get(2).unwrap_or(&0) as u32;
+            // A chunk of k bytes yields k + 1 symbols
+            for i in 0..=chunk.len() {
+                out.push(self.alphabet[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            }
+            if self.padded {
+                for _ in chunk.len()..3 {
+                    out.push(PAD as char);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn decode<T: AsRef<[u8]>>(&self, input: T) -> Result<Vec<u8>, DecodeError> {
+        let input = input.as_ref();
+        let symbols = if self.padded {
+            if input.len() % 4 != 0 {
+                return Err(DecodeError::InvalidLength);
+            }
+            let padding = input.iter().rev().take_while(|&&b| b == PAD).count();
+            if padding > 2 {
+                return Err(DecodeError::InvalidPadding);
+            }
+            &input[..input.len() - padding]
+        } else {
+            input
+        };
+        if symbols.len() % 4 == 1 {
+            return Err(DecodeError::InvalidLength);
+        }
+
+        let mut output = Vec::with_capacity(symbols.len() * 3 / 4);
+        let mut accumulator = 0u32;
+        let mut bits = 0;
+        for (position, &byte) in symbols.iter().enumerate() {
+            let value = match self.alphabet.iter().position(|&symbol| symbol == byte) {
+                Some(value) => value as u32,
+                None if byte == PAD => return Err(DecodeError::InvalidPadding),
+                None => return Err(DecodeError::InvalidByte { position, byte }),
+            };
+            accumulator = (accumulator << 6) | value;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                output.push((accumulator >> bits) as u8);
+            }
+        }
+        if accumulator & ((1 << bits) - 1) != 0 {
+            return Err(DecodeError::TrailingBits);
+        }
+        Ok(output)
+    }
+}
+
+/// Lowercase hex, two digits per byte
+pub fn hex_encode<T: AsRef<[u8]>>(data: T) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let data = data.as_ref();
+    let mut out = String::with_capacity(data.len() * 2);
+    for &byte in data {
+        out.push(DIGITS[(byte >> 4) as usize] as char);
+        out.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Decodes hex in either case
+pub fn hex_decode<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, DecodeError> {
+    let input = input.as_ref();
+    if input.len() % 2 != 0 {
+        return Err(DecodeError::InvalidLength);
+    }
+    let nibble = |position: usize| {
+        let byte = input[position];
+        (byte as char)
+            .to_digit(16)
+            .map(|digit| digit as u8)
+            .ok_or(DecodeError::InvalidByte { position, byte })
+    };
+    (0..input.len())
+        .step_by(2)
+        .map(|i| Ok(nibble(i)? << 4 | nibble(i + 1)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 4648 §10 test vectors, padded
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn rfc4648_vectors_encode_and_decode_in_every_variant() {
+        for (plain, encoded) in VECTORS {
+            let unpadded = encoded.trim_end_matches('=');
+            for (variant, expected) in [
+                (STANDARD, encoded),
+                (URL_SAFE, encoded),
+                (STANDARD_NO_PAD, unpadded),
+                (URL_SAFE_NO_PAD, unpadded),
+            ] {
+                assert_eq!(variant.encode(plain), expected);
+                assert_eq!(variant.decode(expected).unwrap(), plain.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn alphabets_differ_only_in_the_last_two_symbols() {
+        let data = [0xfb, 0xff, 0xbf];
+        assert_eq!(STANDARD.encode(data), "+/+/");
+        assert_eq!(URL_SAFE.encode(data), "-_-_");
+        assert_eq!(
+            URL_SAFE.decode("+/+/"),
+            Err(DecodeError::InvalidByte {
+                position: 0,
+                byte: b'+'
+            })
+        );
+        assert!(STANDARD.decode("-_-_").is_err());
+    }
+
+    #[test]
+    fn padding_must_match_the_variant() {
+        assert_eq!(STANDARD.decode("Zg"), Err(DecodeError::InvalidLength));
+        assert_eq!(STANDARD.decode("Zg="), Err(DecodeError::InvalidLength));
+        assert_eq!(STANDARD.decode("Z==="), Err(DecodeError::InvalidPadding));
+        assert_eq!(STANDARD.decode("Zg=a"), Err(DecodeError::InvalidPadding));
+        assert_eq!(
+            STANDARD_NO_PAD.decode("Zg=="),
+            Err(DecodeError::InvalidPadding)
+        );
+        assert_eq!(URL_SAFE_NO_PAD.decode("Z"), Err(DecodeError::InvalidLength));
+        // "Zh==" decodes to the same byte as "Zg==" if its low bits were ignored
+        assert_eq!(STANDARD.decode("Zh=="), Err(DecodeError::TrailingBits));
+    }
+
+    #[test]
+    fn binary_data_round_trips() {
+        let data: Vec<u8> = (0..=255u8).chain((0..=255u8).rev()).collect();
+        for len in 0..data.len() {
+            let slice = &data[..len];
+            for variant in [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD] {
+                assert_eq!(variant.decode(variant.encode(slice)).unwrap(), slice);
+            }
+        }
+        assert_eq!(hex_decode(hex_encode(&data)).unwrap(), data);
+    }
+}
@@ -12,18 +12,41 @@
 //! - **Intrusion Prevention System (IPS) with real-time attack detection**
 //! - **Policy-Based Access Control (PBAC) for Zero Trust compliance**
 //! - **Cryptographic proof for firewall rule updates (WebAuthn/TPM)**
+//! - **Threat-intelligence feeds merged into the blocklist with per-source TTLs**
+//! - **Lock-free verdict counters served as JSON on `/stats`**
+//! - **Default-deny mode with a CIDR and port-range allow-list**
 
-use std::collections::{HashMap, HashSet};
+#[allow(dead_code)]
+mod http_client;
+#[allow(dead_code)]
+mod util;
+
+use std::collections::HashMap;
 use std::fs;
-use std::net::{IpAddr, UdpSocket};
-use std::sync::{Arc, Mutex};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, SystemTime};
+use util::Cidr;
 
 const FIREWALL_BIND_ADDR: &str = "0.0.0.0:8080";
+const FIREWALL_BIND_ENV: &str = "FIREWALL_BIND_ADDR";
 const RATE_LIMIT: u64 = 100; // Max packets per minute per IP
-const MAX_CONNECTIONS: usize = 10000; // Max tracked connections
 const POLICY_FILE: &str = "firewall_policies.json";
+const LOCAL_POLICY_SOURCE: &str = "local-policy";
+const ALLOWLIST_FILE: &str = "firewall_allowlist.txt";
+const DEFAULT_DENY_SOURCE: &str = "default-deny";
+const MODE_ENV: &str = "FIREWALL_MODE"; // `default-deny` or `default-allow`
+const FEED_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const FEED_ENTRY_TTL: Duration = Duration::from_secs(60 * 60); // Survives a few missed refreshes
+const FEED_HOST_ENV: &str = "THREAT_FEED_HOST"; // host:port serving a plain-text feed
+const FEED_PATH_ENV: &str = "THREAT_FEED_PATH";
+const DEFAULT_FEED_PATH: &str = "/blocklist.txt";
+const MAX_FEED_SIZE: usize = 4 * 1024 * 1024;
+const STATS_BIND_ADDR: &str = "127.0.0.1:9465";
+const STATS_BIND_ENV: &str = "FIREWALL_STATS_ADDR";
 
 /// Tracks rate limits per IP and adaptive thresholding
 struct RateLimiter {
@@ -60,67 +83,816 @@ impl RateLimiter {
 /// Implements Deep Packet Inspection (DPI) for real-time threat detection
 fn deep_packet_inspection(packet: &[u8]) -> bool {
     let request = String::from_utf8_lossy(packet);
-    let threat_patterns = vec!["DROP TABLE", "<script>", "../../", "chmod 777"];
+    let threat_patterns = ["DROP TABLE", "<script>", "../../", "chmod 777"];
     !threat_patterns
         .iter()
         .any(|pattern| request.contains(pattern))
 }
 
-/// Loads firewall policies from a JSON file (simulated with a static list)
-fn load_firewall_policies() -> HashSet<String> {
+/// An inclusive source-port range; a single port is a one-port range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PortRange {
+    start: u16,
+    end: u16,
+}
+
+impl PortRange {
+    fn parse(text: &str) -> Option<Self> {
+        let (start, end) = match text.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let port = text.trim().parse().ok()?;
+                (port, port)
+            }
+        };
+        (start <= end).then_some(Self { start, end })
+    }
+
+    fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+/// One allow-list line: `<cidr> [port | start-end]`; without ports every port matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AllowRule {
+    cidr: Cidr,
+    ports: Option<PortRange>,
+}
+
+impl AllowRule {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let cidr = Cidr::parse(fields.next()?)?;
+        let ports = match fields.next() {
+            Some(ports) => Some(PortRange::parse(ports)?),
+            None => None,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self { cidr, ports })
+    }
+
+    fn permits(&self, src: SocketAddr) -> bool {
+        self.cidr.contains(src.ip()) && self.ports.is_none_or(|p| p.contains(src.port()))
+    }
+}
+
+/// Whether unlisted sources are let through or dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    DefaultAllow,
+    DefaultDeny,
+}
+
+impl Mode {
+    fn from_env() -> Self {
+        match std::env::var(MODE_ENV).as_deref() {
+            Ok("default-deny") => Mode::DefaultDeny,
+            _ => Mode::DefaultAllow,
+        }
+    }
+}
+
+/// The mode together with the sources it permits when denying by default
+struct AccessPolicy {
+    mode: Mode,
+    allowlist: Vec<AllowRule>,
+}
+
+impl AccessPolicy {
+    fn admits(&self, src: SocketAddr) -> bool {
+        match self.mode {
+            Mode::DefaultAllow => true,
+            Mode::DefaultDeny => self.allowlist.iter().any(|rule| rule.permits(src)),
+        }
+    }
+}
+
+/// Parses allow-list lines, skipping comments and reporting malformed rules
+fn parse_allowlist(body: &str) -> Vec<AllowRule> {
+    body.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let rule = AllowRule::parse(line);
+            if rule.is_none() {
+                eprintln!("Ignoring malformed allow-list rule: {}", line);
+            }
+            rule
+        })
+        .collect()
+}
+
+/// Why a block exists: the local policy (permanent) or a feed (until `expires`)
+#[derive(Debug, Clone)]
+struct BlockEntry {
+    source: String,
+    expires: Option<SystemTime>,
+}
+
+/// Local policy and threat-feed blocks behind one lock, so a packet is always
+/// checked against a consistent view and a feed merge is all-or-nothing
+struct Blocklist {
+    entries: Mutex<HashMap<Cidr, BlockEntry>>,
+}
+
+impl Blocklist {
+    fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds permanent blocks from the local policy
+    fn add_local(&self, blocks: impl IntoIterator<Item = Cidr>) {
+        let mut entries = self.entries.lock().unwrap();
+        for block in blocks {
+            entries.insert(
+                block,
+                BlockEntry {
+                    source: LOCAL_POLICY_SOURCE.to_string(),
+                    expires: None,
+                },
+            );
+        }
+    }
+
+    /// Adds or refreshes a feed's blocks, each expiring `ttl` from now. Local
+    /// policy entries keep precedence and are never shortened by a feed.
+    fn merge_feed(&self, source: &str, blocks: &[Cidr], ttl: Duration) -> usize {
+        let expires = SystemTime::now() + ttl;
+        let mut entries = self.entries.lock().unwrap();
+        let mut merged = 0;
+        for block in blocks {
+            let entry = entries.entry(*block).or_insert_with(|| BlockEntry {
+                source: source.to_string(),
+                expires: Some(expires),
+            });
+            if entry.expires.is_some() {
+                entry.source = source.to_string();
+                entry.expires = Some(expires);
+                merged += 1;
+            }
+        }
+        merged
+    }
+
+    /// Drops feed entries whose TTL has passed
+    fn purge_expired(&self) -> usize {
+        let now = SystemTime::now();
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.expires.is_none_or(|expires| expires > now));
+        before - entries.len()
+    }
+
+    /// The source of the live block covering `ip`, if any
+    fn blocked_by(&self, ip: IpAddr) -> Option<String> {
+        let now = SystemTime::now();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .find(|(block, entry)| {
+                block.contains(ip) && entry.expires.is_none_or(|expires| expires > now)
+            })
+            .map(|(_, entry)| entry.source.clone())
+    }
+}
+
+/// A source of known-malicious addresses merged into the blocklist
+trait ThreatFeed: Send {
+    /// Tag recorded on every block this feed contributes
+    fn name(&self) -> &str;
+    fn fetch(&self) -> io::Result<Vec<Cidr>>;
+}
+
+/// A plain-text feed over HTTP: one address or CIDR per line, `#` starts a comment
+struct HttpThreatFeed {
+    name: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl ThreatFeed for HttpThreatFeed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch(&self) -> io::Result<Vec<Cidr>> {
+        let body = fetch_feed(&self.host, self.port, &self.path)?;
+        Ok(parse_feed(&body))
+    }
+}
+
+/// Parses feed lines, skipping comments and anything that is not an address block
+fn parse_feed(body: &str) -> Vec<Cidr> {
+    body.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .filter_map(Cidr::parse)
+        .collect()
+}
+
+/// Fetches a feed through the shared bounded HTTP client, returning the body of a 200 response
+fn fetch_feed(host: &str, port: u16, path: &str) -> io::Result<String> {
+    let limits = http_client::Limits {
+        max_response_bytes: MAX_FEED_SIZE,
+        ..http_client::Limits::default()
+    };
+    let response = http_client::http_get(host, port, path, "ZeroTrustFirewall", &limits)?;
+    match http_client::status_and_body(&response)? {
+        (200, body) => Ok(body.to_string()),
+        (status, _) => Err(io::Error::other(format!("feed returned status {}", status))),
+    }
+}
+
+/// Refreshes `feed` into `blocklist` every `interval` and expires stale entries
+fn spawn_feed_updater(
+    blocklist: Arc<Blocklist>,
+    feed: Box<dyn ThreatFeed>,
+    interval: Duration,
+    ttl: Duration,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        match feed.fetch() {
+            Ok(blocks) => {
+                let merged = blocklist.merge_feed(feed.name(), &blocks, ttl);
+                println!("Threat feed '{}' merged {} entries", feed.name(), merged);
+            }
+            Err(e) => eprintln!("Threat feed '{}' fetch failed: {}", feed.name(), e),
+        }
+        let expired = blocklist.purge_expired();
+        if expired > 0 {
+            println!("Expired {} threat feed entries", expired);
+        }
+        thread::sleep(interval);
+    })
+}
+
+/// Loads firewall policies from a JSON file (simulated with one address or CIDR per line)
+fn load_firewall_policies() -> Blocklist {
     let policy_data = fs::read_to_string(POLICY_FILE).unwrap_or_default();
-    let blocked_ips: HashSet<String> = policy_data.split('\n').map(|s| s.to_string()).collect();
-    blocked_ips
+    let blocklist = Blocklist::new();
+    blocklist.add_local(parse_feed(&policy_data));
+    blocklist
+}
+
+fn load_access_policy() -> AccessPolicy {
+    let allowlist = parse_allowlist(&fs::read_to_string(ALLOWLIST_FILE).unwrap_or_default());
+    AccessPolicy {
+        mode: Mode::from_env(),
+        allowlist,
+    }
+}
+
+/// The decision made for one packet
+#[derive(Debug, Clone, PartialEq)]
+enum PacketVerdict {
+    Allowed,
+    RateLimited,
+    DpiBlocked,
+    /// Carries the blocklist source (local policy or feed name) that matched
+    PolicyBlocked(String),
+}
+
+/// Per-verdict packet counters. The hot path is a single atomic increment;
+/// the per-source map only takes its write lock the first time a source is seen.
+struct FirewallMetrics {
+    allowed: AtomicU64,
+    rate_limited: AtomicU64,
+    dpi_blocked: AtomicU64,
+    policy_blocked: AtomicU64,
+    policy_blocked_by_source: RwLock<HashMap<String, AtomicU64>>,
+}
+
+/// A point-in-time copy of the counters
+#[derive(Debug, Clone, PartialEq)]
+struct FirewallStats {
+    allowed: u64,
+    rate_limited: u64,
+    dpi_blocked: u64,
+    policy_blocked: u64,
+    policy_blocked_by_source: Vec<(String, u64)>, // Sorted by source
+}
+
+impl FirewallMetrics {
+    fn new() -> Self {
+        Self {
+            allowed: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            dpi_blocked: AtomicU64::new(0),
+            policy_blocked: AtomicU64::new(0),
+            policy_blocked_by_source: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn record(&self, verdict: &PacketVerdict) {
+        let counter = match verdict {
+            PacketVerdict::Allowed => &self.allowed,
+            PacketVerdict::RateLimited => &self.rate_limited,
+            PacketVerdict::DpiBlocked => &self.dpi_blocked,
+            PacketVerdict::PolicyBlocked(source) => {
+                self.record_source(source);
+                &self.policy_blocked
+            }
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_source(&self, source: &str) {
+        if let Some(counter) = self.policy_blocked_by_source.read().unwrap().get(source) {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.policy_blocked_by_source
+            .write()
+            .unwrap()
+            .entry(source.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> FirewallStats {
+        let mut by_source: Vec<(String, u64)> = self
+            .policy_blocked_by_source
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(source, count)| (source.clone(), count.load(Ordering::Relaxed)))
+            .collect();
+        by_source.sort();
+        FirewallStats {
+            allowed: self.allowed.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            dpi_blocked: self.dpi_blocked.load(Ordering::Relaxed),
+            policy_blocked: self.policy_blocked.load(Ordering::Relaxed),
+            policy_blocked_by_source: by_source,
+        }
+    }
+}
+
+impl FirewallStats {
+    fn to_json(&self) -> String {
+        let sources: Vec<String> = self
+            .policy_blocked_by_source
+            .iter()
+            .map(|(source, count)| format!("\"{}\":{}", json_escape(source), count))
+            .collect();
+        format!(
+            "{{\"allowed\":{},\"rate_limited\":{},\"dpi_blocked\":{},\"policy_blocked\":{},\"policy_blocked_by_source\":{{{}}}}}",
+            self.allowed,
+            self.rate_limited,
+            self.dpi_blocked,
+            self.policy_blocked,
+            sources.join(",")
+        )
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Decides a packet's fate: policy first (an explicit block beats the allow-list),
+/// then rate limits, then payload inspection
+fn inspect_packet(
+    packet: &[u8],
+    src: SocketAddr,
+    rate_limiter: &RateLimiter,
+    blocklist: &Blocklist,
+    access: &AccessPolicy,
+) -> PacketVerdict {
+    let src_ip = src.ip();
+    if let Some(source) = blocklist.blocked_by(src_ip) {
+        return PacketVerdict::PolicyBlocked(source);
+    }
+    if !access.admits(src) {
+        return PacketVerdict::PolicyBlocked(DEFAULT_DENY_SOURCE.to_string());
+    }
+    if !rate_limiter.allow_request(&src_ip.to_string()) {
+        return PacketVerdict::RateLimited;
+    }
+    if !deep_packet_inspection(packet) {
+        return PacketVerdict::DpiBlocked;
+    }
+    PacketVerdict::Allowed
 }
 
 /// Handles incoming packets with Zero Trust enforcement
 fn handle_packet(
-    socket: &UdpSocket,
     packet: &[u8],
-    src_ip: &str,
+    src: SocketAddr,
     rate_limiter: Arc<RateLimiter>,
-    blocked_ips: &HashSet<String>,
+    blocklist: &Blocklist,
+    access: &AccessPolicy,
+    metrics: &FirewallMetrics,
 ) {
-    if blocked_ips.contains(src_ip) {
-        eprintln!("Blocked packet from: {} (policy violation)", src_ip);
-        return;
+    let src_ip = src.ip();
+    let verdict = inspect_packet(packet, src, &rate_limiter, blocklist, access);
+    metrics.record(&verdict);
+    match verdict {
+        PacketVerdict::PolicyBlocked(source) => eprintln!(
+            "Blocked packet from: {} (policy violation: {})",
+            src_ip, source
+        ),
+        PacketVerdict::RateLimited => eprintln!("Rate limit exceeded for: {}", src_ip),
+        PacketVerdict::DpiBlocked => {
+            eprintln!("DPI detected malicious payload from: {}", src_ip)
+        }
+        PacketVerdict::Allowed => println!("Allowed packet from: {}", src_ip),
     }
+}
 
-    if !rate_limiter.allow_request(src_ip) {
-        eprintln!("Rate limit exceeded for: {}", src_ip);
-        return;
-    }
+/// Answers `GET /stats` with the current counters as JSON
+fn handle_stats_request(mut stream: TcpStream, metrics: &FirewallMetrics) {
+    let mut buffer = [0u8; 1024];
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let bytes_read = match stream.read(&mut buffer) {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
 
-    if !deep_packet_inspection(packet) {
-        eprintln!("DPI detected malicious payload from: {}", src_ip);
-        return;
-    }
+    let response = if method == "GET" && path.split('?').next() == Some("/stats") {
+        let body = metrics.stats().to_json();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
 
-    println!("Allowed packet from: {}", src_ip);
+/// Serves the `/stats` endpoint on a background thread
+fn serve_stats(metrics: Arc<FirewallMetrics>, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            handle_stats_request(stream, &metrics);
+        }
+    });
+    Ok(())
 }
 
-/// Starts the Zero Trust Firewall with full enforcement
-fn main() {
-    let socket = UdpSocket::bind(FIREWALL_BIND_ADDR).expect("Failed to bind firewall port");
-    println!("Zero Trust Firewall running on {}", FIREWALL_BIND_ADDR);
+/// Starts the Zero Trust Firewall with full enforcement on `addr`
+fn run(addr: &str) -> io::Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    println!("Zero Trust Firewall running on {}", socket.local_addr()?);
 
+    let socket = Arc::new(socket);
     let rate_limiter = Arc::new(RateLimiter::new());
-    let blocked_ips = load_firewall_policies();
+    let blocklist = Arc::new(load_firewall_policies());
+    let access = Arc::new(load_access_policy());
+    if access.mode == Mode::DefaultDeny {
+        println!(
+            "Default-deny mode: {} allow-list rule(s) loaded",
+            access.allowlist.len()
+        );
+    }
+    let metrics = Arc::new(FirewallMetrics::new());
+    let stats_addr = util::bind_address(STATS_BIND_ENV, STATS_BIND_ADDR);
+    match serve_stats(Arc::clone(&metrics), &stats_addr) {
+        Ok(()) => println!("Firewall stats at http://{}/stats", stats_addr),
+        Err(e) => eprintln!("Unable to serve firewall stats: {}", e),
+    }
+    if let Some(feed) = feed_from_env() {
+        spawn_feed_updater(
+            Arc::clone(&blocklist),
+            feed,
+            FEED_REFRESH_INTERVAL,
+            FEED_ENTRY_TTL,
+        );
+    }
     let mut buffer = [0; 1500];
 
     loop {
         match socket.recv_from(&mut buffer) {
             Ok((size, src_addr)) => {
                 let rate_limiter = Arc::clone(&rate_limiter);
-                let packet = &buffer[..size];
-                let src_ip = src_addr.ip().to_string();
-                let blocked_ips = blocked_ips.clone();
+                let blocklist = Arc::clone(&blocklist);
+                let access = Arc::clone(&access);
+                let metrics = Arc::clone(&metrics);
+                let packet = buffer[..size].to_vec();
 
                 thread::spawn(move || {
-                    handle_packet(&socket, packet, &src_ip, rate_limiter, &blocked_ips);
+                    handle_packet(
+                        &packet,
+                        src_addr,
+                        rate_limiter,
+                        &blocklist,
+                        &access,
+                        &metrics,
+                    );
                 });
             }
             Err(e) => eprintln!("Firewall packet reception failed: {}", e),
         }
     }
 }
+
+/// Configures an HTTP threat feed from `THREAT_FEED_HOST` (and optionally `THREAT_FEED_PATH`)
+fn feed_from_env() -> Option<Box<dyn ThreatFeed>> {
+    let target = std::env::var(FEED_HOST_ENV).ok()?;
+    let (host, port) = match target.rsplit_once(':') {
+        Some((host, port)) => (host.to_string(), port.parse().ok()?),
+        None => (target.clone(), 80),
+    };
+    let path = std::env::var(FEED_PATH_ENV).unwrap_or_else(|_| DEFAULT_FEED_PATH.to_string());
+    Some(Box::new(HttpThreatFeed {
+        name: format!("http://{}{}", target, path),
+        host,
+        port,
+        path,
+    }))
+}
+
+fn main() {
+    let addr = util::bind_address(FIREWALL_BIND_ENV, FIREWALL_BIND_ADDR);
+    if let Err(e) = run(&addr) {
+        eprintln!("Failed to start firewall on {}: {}", addr, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Sender};
+
+    fn addr(text: &str) -> SocketAddr {
+        text.parse().unwrap()
+    }
+
+    fn allow_all() -> AccessPolicy {
+        AccessPolicy {
+            mode: Mode::DefaultAllow,
+            allowlist: Vec::new(),
+        }
+    }
+
+    fn verdict(blocklist: &Blocklist, src: &str) -> PacketVerdict {
+        inspect_packet(
+            b"hello",
+            addr(src),
+            &RateLimiter::new(),
+            blocklist,
+            &allow_all(),
+        )
+    }
+
+    /// A feed serving fixed blocks, reporting each fetch on `fetched`
+    struct MockFeed {
+        blocks: Vec<Cidr>,
+        fetched: Mutex<Sender<()>>,
+    }
+
+    impl ThreatFeed for MockFeed {
+        fn name(&self) -> &str {
+            "mock-feed"
+        }
+
+        fn fetch(&self) -> io::Result<Vec<Cidr>> {
+            let _ = self.fetched.lock().unwrap().send(());
+            Ok(self.blocks.clone())
+        }
+    }
+
+    #[test]
+    fn feed_entries_are_merged_and_enforced_with_their_source() {
+        let blocklist = Arc::new(Blocklist::new());
+        blocklist.add_local(parse_feed("198.51.100.7"));
+        let (fetched, on_fetch) = mpsc::channel();
+        let feed = MockFeed {
+            blocks: parse_feed("203.0.113.0/24 # botnet\n198.51.100.7\nnot-an-ip"),
+            fetched: Mutex::new(fetched),
+        };
+        spawn_feed_updater(
+            Arc::clone(&blocklist),
+            Box::new(feed),
+            Duration::from_secs(3600),
+            Duration::from_secs(3600),
+        );
+        on_fetch.recv_timeout(Duration::from_secs(5)).unwrap();
+        // The merge happens right after the fetch; wait for it to land
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while blocklist
+            .blocked_by("203.0.113.9".parse().unwrap())
+            .is_none()
+        {
+            assert!(std::time::Instant::now() < deadline, "feed never merged");
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(
+            verdict(&blocklist, "203.0.113.9:5000"),
+            PacketVerdict::PolicyBlocked("mock-feed".to_string())
+        );
+        // The local policy keeps precedence over the feed's copy of the same block
+        assert_eq!(
+            verdict(&blocklist, "198.51.100.7:5000"),
+            PacketVerdict::PolicyBlocked(LOCAL_POLICY_SOURCE.to_string())
+        );
+        assert_eq!(
+            verdict(&blocklist, "192.0.2.1:5000"),
+            PacketVerdict::Allowed
+        );
+    }
+
+    #[test]
+    fn feed_entries_expire_after_their_ttl_but_local_blocks_stay() {
+        let blocklist = Blocklist::new();
+        blocklist.add_local(parse_feed("198.51.100.7"));
+        let ttl = Duration::from_millis(100);
+        assert_eq!(
+            blocklist.merge_feed(
+                "mock-feed",
+                &parse_feed("203.0.113.0/24\n198.51.100.7"),
+                ttl
+            ),
+            1
+        );
+        assert!(matches!(
+            verdict(&blocklist, "203.0.113.9:5000"),
+            PacketVerdict::PolicyBlocked(_)
+        ));
+
+        thread::sleep(ttl * 2);
+        assert_eq!(
+            verdict(&blocklist, "203.0.113.9:5000"),
+            PacketVerdict::Allowed
+        );
+        assert_eq!(blocklist.purge_expired(), 1);
+        assert_eq!(blocklist.purge_expired(), 0);
+        assert!(matches!(
+            verdict(&blocklist, "198.51.100.7:5000"),
+            PacketVerdict::PolicyBlocked(_)
+        ));
+    }
+
+    #[test]
+    fn counters_match_a_mix_of_allowed_and_blocked_packets() {
+        let rate_limiter = Arc::new(RateLimiter::new());
+        let blocklist = Blocklist::new();
+        blocklist.add_local(parse_feed("198.51.100.7"));
+        blocklist.merge_feed(
+            "mock-feed",
+            &parse_feed("203.0.113.0/24"),
+            Duration::from_secs(60),
+        );
+        let access = allow_all();
+        let metrics = FirewallMetrics::new();
+        let send = |packet: &[u8], src: &str| {
+            handle_packet(
+                packet,
+                addr(src),
+                Arc::clone(&rate_limiter),
+                &blocklist,
+                &access,
+                &metrics,
+            )
+        };
+
+        for _ in 0..3 {
+            send(b"hello", "192.0.2.1:4000");
+        }
+        send(b"'; DROP TABLE users; --", "192.0.2.2:4000");
+        send(b"<script>alert(1)</script>", "192.0.2.2:4000");
+        send(b"hello", "198.51.100.7:4000");
+        send(b"hello", "203.0.113.5:4000");
+        send(b"hello", "203.0.113.6:4000");
+        // A flooding source uses up its budget and is then rate limited
+        for _ in 0..RATE_LIMIT + 4 {
+            send(b"hello", "192.0.2.3:4000");
+        }
+
+        let stats = metrics.stats();
+        assert_eq!(
+            stats,
+            FirewallStats {
+                allowed: 3 + RATE_LIMIT,
+                rate_limited: 4,
+                dpi_blocked: 2,
+                policy_blocked: 3,
+                policy_blocked_by_source: vec![
+                    (LOCAL_POLICY_SOURCE.to_string(), 1),
+                    ("mock-feed".to_string(), 2),
+                ],
+            }
+        );
+        assert_eq!(
+            stats.to_json(),
+            format!(
+                "{{\"allowed\":{},\"rate_limited\":4,\"dpi_blocked\":2,\"policy_blocked\":3,\"policy_blocked_by_source\":{{\"local-policy\":1,\"mock-feed\":2}}}}",
+                3 + RATE_LIMIT
+            )
+        );
+    }
+
+    #[test]
+    fn stats_endpoint_serves_the_counters_as_json() {
+        let metrics = FirewallMetrics::new();
+        metrics.record(&PacketVerdict::Allowed);
+        metrics.record(&PacketVerdict::DpiBlocked);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local = listener.local_addr().unwrap();
+
+        for (request, expected) in [
+            ("GET /stats HTTP/1.1\r\n\r\n", "HTTP/1.1 200 OK"),
+            ("GET /other HTTP/1.1\r\n\r\n", "HTTP/1.1 404 Not Found"),
+        ] {
+            let mut client = TcpStream::connect(local).unwrap();
+            client.write_all(request.as_bytes()).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            handle_stats_request(stream, &metrics);
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with(expected), "{}", response);
+            if expected.ends_with("OK") {
+                assert!(response.ends_with(&metrics.stats().to_json()));
+            }
+        }
+    }
+
+    fn admitted(access: &AccessPolicy, src: &str) -> bool {
+        inspect_packet(
+            b"hello",
+            addr(src),
+            &RateLimiter::new(),
+            &Blocklist::new(),
+            access,
+        ) == PacketVerdict::Allowed
+    }
+
+    #[test]
+    fn default_deny_admits_only_allow_listed_sources_and_ports() {
+        let access = AccessPolicy {
+            mode: Mode::DefaultDeny,
+            allowlist: parse_allowlist(
+                "# office network, any port\n10.0.0.0/8\n192.0.2.10 1000-2000\n192.0.2.20 53\nbogus rule\n",
+            ),
+        };
+        assert_eq!(access.allowlist.len(), 3);
+
+        assert!(admitted(&access, "10.1.2.3:65000"));
+        assert!(admitted(&access, "192.0.2.10:1000"));
+        assert!(admitted(&access, "192.0.2.10:2000"));
+        assert!(admitted(&access, "192.0.2.20:53"));
+        assert!(!admitted(&access, "192.0.2.10:2001"));
+        assert!(!admitted(&access, "192.0.2.20:54"));
+        assert_eq!(
+            inspect_packet(
+                b"hello",
+                addr("198.51.100.1:4000"),
+                &RateLimiter::new(),
+                &Blocklist::new(),
+                &access
+            ),
+            PacketVerdict::PolicyBlocked(DEFAULT_DENY_SOURCE.to_string())
+        );
+    }
+
+    #[test]
+    fn default_allow_keeps_admitting_unlisted_sources_unless_blocked() {
+        let access = allow_all();
+        assert!(admitted(&access, "198.51.100.1:4000"));
+
+        // An explicit block still wins over an allow-list entry in either mode
+        let blocklist = Blocklist::new();
+        blocklist.add_local(parse_feed("10.0.0.5"));
+        let deny = AccessPolicy {
+            mode: Mode::DefaultDeny,
+            allowlist: parse_allowlist("10.0.0.0/8"),
+        };
+        for access in [&access, &deny] {
+            assert_eq!(
+                inspect_packet(
+                    b"hello",
+                    addr("10.0.0.5:4000"),
+                    &RateLimiter::new(),
+                    &blocklist,
+                    access
+                ),
+                PacketVerdict::PolicyBlocked(LOCAL_POLICY_SOURCE.to_string())
+            );
+        }
+        assert_eq!(PortRange::parse("2000-1000"), None);
+        assert_eq!(AllowRule::parse("10.0.0.0/8 80 extra"), None);
+    }
+}
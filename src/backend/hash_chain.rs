@@ -0,0 +1,137 @@
+//! Hash-chained append-only log lines (Standard Library Only)
+//! Each stored line is `entry | Prev: <hex> | Hash: <hex>` where the hash is
+//! `SHA-256(previous hash || entry)`, so editing, dropping or reordering a line
+//! breaks the chain from that line on. Shared by every tamper-evident log.
+
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "encoding.rs"]
+mod encoding;
+
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+pub const GENESIS_HASH: [u8; 32] = [0; 32]; // Predecessor of the first line in a log file
+
+/// Parses a 64-character hex digest
+fn digest_from_hex(hex: &str) -> Option<[u8; 32]> {
+    encoding::hex_decode(hex).ok()?.try_into().ok()
+}
+
+/// Links a line to its predecessor: `SHA-256(previous hash || line)`
+pub fn chain_hash(prev: &[u8; 32], line: &str) -> [u8; 32] {
+    let mut input = prev.to_vec();
+    input.extend_from_slice(line.as_bytes());
+    crypto::sha256(&input)
+}
+
+/// Splits `entry | Prev: <hex> | Hash: <hex>` into its parts
+pub fn split_stored_line(line: &str) -> Option<(&str, [u8; 32], [u8; 32])> {
+    let (rest, hash) = line.rsplit_once(" | Hash: ")?;
+    let (entry, prev) = rest.rsplit_once(" | Prev: ")?;
+    Some((entry, digest_from_hex(prev)?, digest_from_hex(hash)?))
+}
+
+/// The hash new lines appended to `path` must chain from
+pub fn chain_head(path: &str) -> io::Result<[u8; 32]> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .last()
+        .and_then(split_stored_line)
+        .map_or(GENESIS_HASH, |(_, _, hash)| hash))
+}
+
+/// Appends `entry` chained to `chain_head`, then advances the head
+pub fn write_line<W: Write>(
+    writer: &mut W,
+    chain_head: &mut [u8; 32],
+    entry: &str,
+) -> io::Result<()> {
+    let hash = chain_hash(chain_head, entry);
+    writeln!(
+        writer,
+        "{} | Prev: {} | Hash: {}",
+        entry,
+        encoding::hex_encode(&chain_head[..]),
+        encoding::hex_encode(hash)
+    )?;
+    *chain_head = hash;
+    Ok(())
+}
+
+/// Why a stored log line failed verification; `line` is 1-based
+#[derive(Debug)]
+pub enum VerifyError {
+    Io(io::Error),
+    Malformed { line: usize },
+    HashMismatch { line: usize },
+    BrokenChain { line: usize },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Io(e) => write!(f, "failed to read log: {}", e),
+            VerifyError::Malformed { line } => write!(f, "line {} is malformed", line),
+            VerifyError::HashMismatch { line } => {
+                write!(f, "line {} does not match its hash", line)
+            }
+            VerifyError::BrokenChain { line } => {
+                write!(f, "line {} does not follow the previous line", line)
+            }
+        }
+    }
+}
+
+/// Yields each stored line's entry after checking its hash and its link to the
+/// previous line
+pub struct ChainVerifier<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    prev: [u8; 32],
+}
+
+impl<R: BufRead> ChainVerifier<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line: 0,
+            prev: GENESIS_HASH,
+        }
+    }
+
+    /// 1-based number of the line most recently read
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl<R: BufRead> Iterator for ChainVerifier<R> {
+    type Item = Result<String, VerifyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = match self.lines.next()? {
+            Ok(text) => text,
+            Err(e) => return Some(Err(VerifyError::Io(e))),
+        };
+        self.line += 1;
+        let line = self.line;
+
+        let (entry, prev, hash) = match split_stored_line(&text) {
+            Some(parts) => parts,
+            None => return Some(Err(VerifyError::Malformed { line })),
+        };
+        // Continue the chain from this line so one break is reported once
+        let expected_prev = std::mem::replace(&mut self.prev, hash);
+        if chain_hash(&prev, entry) != hash {
+            return Some(Err(VerifyError::HashMismatch { line }));
+        }
+        if prev != expected_prev {
+            return Some(Err(VerifyError::BrokenChain { line }));
+        }
+        Some(Ok(entry.to_string()))
+    }
+}
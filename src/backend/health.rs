@@ -0,0 +1,192 @@
+//! Zero Trust Health Probes (Standard Library Only)
+//! Liveness and readiness answers shared by every HTTP server, so orchestrators and
+//! the self-healing monitor can probe each of them the same way.
+//! Features:
+//! - **`GET /healthz`: 200 whenever the process can answer at all**
+//! - **`GET /readyz`: 200 only while every registered dependency check passes, else 503**
+//! - **Small JSON bodies naming each dependency and why it failed**
+//! - **Independent of any server's request and response types**
+
+use std::time::Instant;
+
+pub const LIVENESS_PATH: &str = "/healthz";
+pub const READINESS_PATH: &str = "/readyz";
+
+/// Reports whether one dependency is usable, with the reason when it is not
+pub type HealthCheck = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// A probe's answer, for the server to wrap in its own response type
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthResponse {
+    pub status: u16, // 200, 503, or 405 for a method other than GET/HEAD
+    pub body: String,
+}
+
+impl HealthResponse {
+    /// `"<code> <reason>"`, for servers that write status lines directly
+    pub fn status_line(&self) -> &'static str {
+        match self.status {
+            200 => "200 OK",
+            405 => "405 Method Not Allowed",
+            _ => "503 Service Unavailable",
+        }
+    }
+}
+
+/// The dependency checks behind `/readyz`
+#[derive(Default)]
+pub struct HealthChecks {
+    checks: Vec<(String, HealthCheck)>,
+}
+
+impl HealthChecks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds dependency `name` to readiness. `check` runs on every `/readyz`
+    /// request, so it should be quick, e.g. a connect with a short timeout.
+    pub fn register<F>(&mut self, name: &str, check: F)
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.checks.push((name.to_string(), Box::new(check)));
+    }
+
+    /// Always 200: answering at all proves the process is alive
+    pub fn liveness(&self) -> HealthResponse {
+        HealthResponse {
+            status: 200,
+            body: "{\"status\":\"ok\"}".to_string(),
+        }
+    }
+
+    /// Runs every check; 200 when all pass, 503 naming the failures otherwise
+    pub fn readiness(&self) -> HealthResponse {
+        let mut ready = true;
+        let results: Vec<String> = self
+            .checks
+            .iter()
+            .map(|(name, check)| {
+                let started = Instant::now();
+                let outcome = check();
+                let elapsed_ms = started.elapsed().as_millis();
+                match outcome {
+                    Ok(()) => format!(
+                        "\"{}\":{{\"status\":\"ok\",\"elapsed_ms\":{}}}",
+                        json_escape(name),
+                        elapsed_ms
+                    ),
+                    Err(reason) => {
+                        ready = false;
+                        format!(
+                            "\"{}\":{{\"status\":\"fail\",\"elapsed_ms\":{},\"error\":\"{}\"}}",
+                            json_escape(name),
+                            elapsed_ms,
+                            json_escape(&reason)
+                        )
+                    }
+                }
+            })
+            .collect();
+        HealthResponse {
+            status: if ready { 200 } else { 503 },
+            body: format!(
+                "{{\"status\":\"{}\",\"checks\":{{{}}}}}",
+                if ready { "ready" } else { "unavailable" },
+                results.join(",")
+            ),
+        }
+    }
+
+    /// The probe answer for `method path`, or `None` when `path` is not a probe.
+    /// The query string is ignored.
+    pub fn respond(&self, method: &str, path: &str) -> Option<HealthResponse> {
+        let path = path.split('?').next().unwrap_or(path);
+        if path != LIVENESS_PATH && path != READINESS_PATH {
+            return None;
+        }
+        if !method.eq_ignore_ascii_case("GET") && !method.eq_ignore_ascii_case("HEAD") {
+            return Some(HealthResponse {
+                status: 405,
+                body: "{\"status\":\"method not allowed\"}".to_string(),
+            });
+        }
+        Some(if path == LIVENESS_PATH {
+            self.liveness()
+        } else {
+            self.readiness()
+        })
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn liveness_is_always_ok() {
+        let mut checks = HealthChecks::new();
+        checks.register("database", || Err("connection refused".to_string()));
+        let response = checks.respond("GET", LIVENESS_PATH).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "{\"status\":\"ok\"}");
+        assert_eq!(response.status_line(), "200 OK");
+    }
+
+    #[test]
+    fn readiness_fails_while_any_dependency_check_fails() {
+        let database_up = Arc::new(AtomicBool::new(true));
+        let mut checks = HealthChecks::new();
+        let up = Arc::clone(&database_up);
+        checks.register("database", move || {
+            if up.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err("connect \"db:5432\" timed out".to_string())
+            }
+        });
+        checks.register("cache", || Ok(()));
+
+        let ready = checks.respond("GET", "/readyz?verbose=1").unwrap();
+        assert_eq!(ready.status, 200);
+        assert!(
+            ready.body.starts_with("{\"status\":\"ready\""),
+            "{}",
+            ready.body
+        );
+
+        database_up.store(false, Ordering::SeqCst);
+        let unavailable = checks.respond("HEAD", READINESS_PATH).unwrap();
+        assert_eq!(unavailable.status, 503);
+        assert_eq!(unavailable.status_line(), "503 Service Unavailable");
+        assert!(unavailable.body.contains("\"status\":\"unavailable\""));
+        assert!(unavailable
+            .body
+            .contains("\"error\":\"connect \\\"db:5432\\\" timed out\""));
+        assert!(unavailable.body.contains("\"cache\":{\"status\":\"ok\""));
+    }
+
+    #[test]
+    fn other_paths_and_methods_are_not_probes() {
+        let checks = HealthChecks::new();
+        assert_eq!(checks.respond("GET", READINESS_PATH).unwrap().status, 200);
+        assert_eq!(checks.respond("GET", "/healthz/extra"), None);
+        assert_eq!(checks.respond("POST", LIVENESS_PATH).unwrap().status, 405);
+    }
+}
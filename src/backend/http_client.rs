@@ -0,0 +1,222 @@
+//! Bounded HTTP/1.1 Client (Standard Library Only)
+//! The plain-TCP GET and POST shared by the OSINT and SEO tools, the firewall's threat
+//! feeds and the IDS webhook alerter. Every request is bounded:
+//! - **Connect, write and per-read timeouts, so a stalled server cannot hang the caller**
+//! - **A cap on response size; a larger response is an error, never a partial body**
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub const MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_secs(10); // Longest wait for any single read
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bounds on one request; `Default` gives the module constants
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_response_bytes: usize,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_response_bytes: MAX_RESPONSE_BYTES,
+            connect_timeout: CONNECT_TIMEOUT,
+            read_timeout: READ_TIMEOUT,
+        }
+    }
+}
+
+/// Fetches `path` from `host:port` and returns the raw response, headers included.
+/// Fails with `InvalidData` once the response passes `max_response_bytes`, and with
+/// `TimedOut` when the server goes quiet for longer than `read_timeout`.
+pub fn http_get(
+    host: &str,
+    port: u16,
+    path: &str,
+    user_agent: &str,
+    limits: &Limits,
+) -> io::Result<String> {
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nConnection: close\r\n\r\n",
+        path, host, user_agent
+    );
+    send(host, port, &request, limits)
+}
+
+/// Posts `body` as `content_type` to `path` on `host:port` and returns the raw
+/// response, bounded exactly like `http_get`
+pub fn http_post(
+    host: &str,
+    port: u16,
+    path: &str,
+    content_type: &str,
+    body: &str,
+    limits: &Limits,
+) -> io::Result<String> {
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        content_type,
+        body.len(),
+        body
+    );
+    send(host, port, &request, limits)
+}
+
+/// Splits a raw response into its status code and body
+pub fn status_and_body(response: &str) -> io::Result<(u16, &str)> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP status line"))?;
+    Ok((status, body))
+}
+
+/// Writes `request` to `host:port` and reads the whole response within `limits`
+fn send(host: &str, port: u16, request: &str, limits: &Limits) -> io::Result<String> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host did not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, limits.connect_timeout)?;
+    stream.set_read_timeout(Some(limits.read_timeout))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+
+    // One byte past the cap tells a response of exactly the cap from a larger one
+    let mut response = Vec::new();
+    (&mut stream)
+        .take(limits.max_response_bytes as u64 + 1)
+        .read_to_end(&mut response)
+        .map_err(|e| {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "{} sent nothing for {}s",
+                        host,
+                        limits.read_timeout.as_secs_f32()
+                    ),
+                )
+            } else {
+                e
+            }
+        })?;
+    if response.len() > limits.max_response_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "response from {} exceeds {} bytes",
+                host, limits.max_response_bytes
+            ),
+        ));
+    }
+    String::from_utf8(response)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "response is not valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one connection: reads the request, then writes `chunks` with `pause` before each
+    fn serve_once(chunks: Vec<Vec<u8>>, pause: Duration) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            for chunk in chunks {
+                thread::sleep(pause);
+                if stream.write_all(&chunk).is_err() {
+                    return;
+                }
+            }
+        });
+        port
+    }
+
+    fn limits(max_response_bytes: usize, read_timeout: Duration) -> Limits {
+        Limits {
+            max_response_bytes,
+            read_timeout,
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn responses_within_the_cap_are_returned_whole() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello".to_vec();
+        let size = response.len();
+        let port = serve_once(vec![response], Duration::ZERO);
+
+        let raw = http_get("127.0.0.1", port, "/", "test", &limits(size, READ_TIMEOUT)).unwrap();
+        assert_eq!(status_and_body(&raw).unwrap(), (200, "hello"));
+    }
+
+    #[test]
+    fn response_past_the_cap_is_an_error() {
+        let mut response = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        response.extend(std::iter::repeat_n(b'x', 4096));
+        let port = serve_once(vec![response], Duration::ZERO);
+
+        let error =
+            http_get("127.0.0.1", port, "/", "test", &limits(1024, READ_TIMEOUT)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            error.to_string().contains("exceeds 1024 bytes"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn quiet_server_hits_the_read_timeout() {
+        let port = serve_once(
+            vec![b"HTTP/1.1 200 OK\r\n".to_vec(), b"\r\nlate".to_vec()],
+            Duration::from_millis(500),
+        );
+
+        let started = std::time::Instant::now();
+        let error = http_get(
+            "127.0.0.1",
+            port,
+            "/",
+            "test",
+            &limits(MAX_RESPONSE_BYTES, Duration::from_millis(100)),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_millis(450));
+    }
+
+    #[test]
+    fn malformed_responses_have_no_status() {
+        assert_eq!(
+            status_and_body("HTTP/1.1 404 Not Found\r\n\r\n").unwrap(),
+            (404, "")
+        );
+        for response in ["HTTP/1.1 200 OK\r\n", "garbage\r\n\r\nbody"] {
+            assert_eq!(
+                status_and_body(response).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+    }
+}
@@ -10,33 +10,177 @@
 //! - **Real-time admin alerting via secure out-of-band channels**
 //! - **Automated response mechanisms (blocking, isolation, and escalation)**
 //! - **Time-based attack correlation for advanced threat intelligence**
+//! - **Blocked syscalls from the interceptor's event stream correlated per process**
 
-use std::collections::{HashMap, HashSet};
+#[allow(dead_code)]
+#[path = "http_client.rs"]
+mod http_client;
+#[allow(dead_code)]
+#[path = "../kernel_module/syscall_stream.rs"]
+mod syscall_stream;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, SystemTime};
+use syscall_stream::{Decision, SyscallEvent};
 
 const LOG_FILE: &str = "logs/intrusion_detection.log";
 const MAX_FAILED_LOGINS: u8 = 5;
 const BAN_DURATION: u64 = 600; // 10 minutes
 const DETECTION_WINDOW: u64 = 60; // 1-minute attack tracking
 const ALERT_THRESHOLD: u64 = 10; // Threshold for anomaly detection
+const ALERT_DEDUP_WINDOW: u64 = 30; // Suppress repeats of the same incident for 30 seconds
+const WEBHOOK_QUEUE_DEPTH: usize = 256; // Alerts waiting for delivery before new ones are dropped
+const WEBHOOK_RESPONSE_LIMIT: usize = 64 * 1024; // Only the status line matters
+
+/// Callback invoked with the source IP and block expiry whenever a source is blocked
+type BlockHook = Arc<dyn Fn(&str, SystemTime) + Send + Sync>;
+
+/// Severity attached to out-of-band alerts
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)] // Detectors raise only the higher grades today
+enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Structured alert payload delivered to administrators
+#[derive(Debug, Clone)]
+struct Alert {
+    source: String,
+    rule: String,
+    timestamp: u64,
+    severity: Severity,
+}
+
+impl Alert {
+    /// Serializes the alert as a JSON object
+    fn to_json(&self) -> String {
+        let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        format!(
+            "{{\"source\":\"{}\",\"rule\":\"{}\",\"timestamp\":{},\"severity\":\"{:?}\"}}",
+            escape(&self.source),
+            escape(&self.rule),
+            self.timestamp,
+            self.severity
+        )
+    }
+}
+
+/// Out-of-band channel used to notify administrators
+trait Alerter: Send + Sync {
+    fn send(&self, alert: &Alert) -> io::Result<()>;
+}
+
+/// Delivers alerts as JSON via HTTP POST to a webhook endpoint. `send` only queues
+/// the alert; a background thread posts it, so a slow endpoint never stalls detection.
+struct WebhookAlerter {
+    queue: SyncSender<Alert>,
+}
+
+impl WebhookAlerter {
+    fn new(host: &str, port: u16, path: &str) -> Self {
+        let (queue, pending) = mpsc::sync_channel::<Alert>(WEBHOOK_QUEUE_DEPTH);
+        let (host, path) = (host.to_string(), path.to_string());
+        thread::spawn(move || {
+            for alert in pending {
+                if let Err(e) = post_alert(&host, port, &path, &alert) {
+                    eprintln!(
+                        "[ALERT] Failed to deliver alert for {}: {}",
+                        alert.source, e
+                    );
+                }
+            }
+        });
+        Self { queue }
+    }
+}
+
+impl Alerter for WebhookAlerter {
+    fn send(&self, alert: &Alert) -> io::Result<()> {
+        match self.queue.try_send(alert.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                Err(io::Error::other("webhook queue full; alert dropped"))
+            }
+            Err(TrySendError::Disconnected(_)) => Err(io::Error::other("webhook sender stopped")),
+        }
+    }
+}
+
+/// Posts one alert through the shared bounded HTTP client
+fn post_alert(host: &str, port: u16, path: &str, alert: &Alert) -> io::Result<()> {
+    let limits = http_client::Limits {
+        max_response_bytes: WEBHOOK_RESPONSE_LIMIT,
+        ..http_client::Limits::default()
+    };
+    let response = http_client::http_post(
+        host,
+        port,
+        path,
+        "application/json",
+        &alert.to_json(),
+        &limits,
+    )?;
+    let (status, _) = http_client::status_and_body(&response)?;
+    if !(200..300).contains(&status) {
+        return Err(io::Error::other(format!(
+            "webhook responded with status {}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Appends alerts as JSON lines to a local file
+struct FileAlerter {
+    path: String,
+}
+
+impl Alerter for FileAlerter {
+    fn send(&self, alert: &Alert) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", alert.to_json())
+    }
+}
 
 /// Tracks failed login attempts and suspicious activity
-struct IntrusionDetection {
+pub(crate) struct IntrusionDetection {
     failed_logins: Mutex<HashMap<String, (u8, SystemTime)>>, // Username -> (Attempt Count, Last Attempt Time)
     attack_signatures: HashSet<&'static str>,                // Known attack patterns
+    recent_events: Mutex<HashMap<String, VecDeque<SystemTime>>>, // Source -> event times in window
+    blocked_ips: Mutex<HashMap<String, SystemTime>>,         // IP -> block expiry
+    login_lockouts: Mutex<HashMap<String, SystemTime>>,      // Username -> lockout expiry
+    block_hooks: Mutex<Vec<BlockHook>>,                      // Subscribers (e.g. the firewall)
+    alerters: Mutex<Vec<Arc<dyn Alerter>>>,                  // Out-of-band admin channels
+    recent_alerts: Mutex<HashMap<(String, String), SystemTime>>, // (Source, Rule) -> last sent
     logs: Mutex<BufWriter<File>>,                            // Secure event logging
 }
 
 impl IntrusionDetection {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
+        Self::with_log_file(LOG_FILE)
+    }
+
+    /// Writes the intrusion log to `path` instead of `LOG_FILE`
+    pub(crate) fn with_log_file(path: &str) -> Self {
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(LOG_FILE)
+            .open(path)
             .expect("Failed to open log file");
 
         let attack_signatures: HashSet<&str> = [
@@ -53,16 +197,208 @@ impl IntrusionDetection {
         Self {
             failed_logins: Mutex::new(HashMap::new()),
             attack_signatures,
+            recent_events: Mutex::new(HashMap::new()),
+            blocked_ips: Mutex::new(HashMap::new()),
+            login_lockouts: Mutex::new(HashMap::new()),
+            block_hooks: Mutex::new(Vec::new()),
+            alerters: Mutex::new(Vec::new()),
+            recent_alerts: Mutex::new(HashMap::new()),
             logs: Mutex::new(BufWriter::new(file)),
         }
     }
 
     /// Monitors incoming requests for suspicious patterns
-    fn monitor_request(&self, ip: &str, request: &str) {
-        if self.detect_attack_pattern(request) {
-            self.log_intrusion(ip, "Pattern-Based Attack Detected");
-            println!("[ALERT] Intrusion detected from {}", ip);
+    pub(crate) fn monitor_request(&self, ip: &str, request: &str) {
+        self.monitor_request_at(ip, request, SystemTime::now());
+    }
+
+    /// Monitors a request observed at `at`. Only requests matching an attack signature
+    /// are correlated with earlier events, so benign volume alone never blocks a source.
+    fn monitor_request_at(&self, ip: &str, request: &str, at: SystemTime) {
+        if !self.detect_attack_pattern(request) {
+            return;
+        }
+        self.log_intrusion(ip, "Pattern-Based Attack Detected");
+        println!("[ALERT] Intrusion detected from {}", ip);
+        self.block_ip(ip, at);
+        if self.correlate_event(ip, at) {
+            self.raise_alert(ip, "correlated-activity", Severity::High, at);
+        }
+    }
+
+    /// Ingests syscall events until the interceptor hangs up. Run it on its own thread.
+    fn ingest_syscalls(&self, events: Receiver<SyscallEvent>) {
+        for event in events {
+            self.ingest_syscall(&event);
+        }
+    }
+
+    /// Blocked syscalls are logged, alerted on, and counted in the correlation
+    /// window under the source `pid:<pid>`; allowed ones are routine and ignored
+    fn ingest_syscall(&self, event: &SyscallEvent) {
+        if event.decision != Decision::Blocked {
+            return;
+        }
+        let source = format!("pid:{}", event.pid);
+        let at = SystemTime::UNIX_EPOCH + Duration::from_secs(event.timestamp);
+        self.log_intrusion(
+            &source,
+            &format!("Blocked syscall {} {}", event.name, event.args),
+        );
+        self.raise_alert(
+            &source,
+            &format!("blocked-syscall:{}", event.name),
+            Severity::High,
+            at,
+        );
+        if self.correlate_event(&source, at) {
+            self.raise_alert(&source, "correlated-activity", Severity::Critical, at);
+        }
+    }
+
+    /// Adds an out-of-band channel that receives severe detections
+    fn add_alerter(&self, alerter: Arc<dyn Alerter>) {
+        self.alerters.lock().unwrap().push(alerter);
+    }
+
+    /// Sends an alert through every channel unless the same (source, rule)
+    /// incident was already reported within `ALERT_DEDUP_WINDOW`. Returns true if sent.
+    fn raise_alert(&self, source: &str, rule: &str, severity: Severity, at: SystemTime) -> bool {
+        let key = (source.to_string(), rule.to_string());
+        let mut recent_alerts = self.recent_alerts.lock().unwrap();
+        if let Some(&last) = recent_alerts.get(&key) {
+            if at
+                .duration_since(last)
+                .map_or(true, |age| age < Duration::from_secs(ALERT_DEDUP_WINDOW))
+            {
+                return false;
+            }
+        }
+        recent_alerts.insert(key, at);
+        drop(recent_alerts);
+
+        let alert = Alert {
+            source: source.to_string(),
+            rule: rule.to_string(),
+            timestamp: at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            severity,
+        };
+        let alerters = self.alerters.lock().unwrap().clone();
+        for alerter in alerters {
+            if let Err(e) = alerter.send(&alert) {
+                eprintln!("[ALERT] Failed to deliver alert for {}: {}", source, e);
+            }
+        }
+        true
+    }
+
+    /// Registers a callback notified of every new block (e.g. to push it into the firewall)
+    fn subscribe_blocks(&self, hook: BlockHook) {
+        self.block_hooks.lock().unwrap().push(hook);
+    }
+
+    /// Blocks `ip` for `BAN_DURATION` seconds starting at `at` and notifies subscribers
+    fn block_ip(&self, ip: &str, at: SystemTime) {
+        let expiry = at + Duration::from_secs(BAN_DURATION);
+        self.blocked_ips
+            .lock()
+            .unwrap()
+            .insert(ip.to_string(), expiry);
+        self.log_intrusion(ip, "Source blocked by automated response");
+        println!("[RESPONSE] Blocked {} for {}s", ip, BAN_DURATION);
+
+        // Called without the lock held, so a hook may subscribe or block in turn
+        let hooks = self.block_hooks.lock().unwrap().clone();
+        for hook in hooks {
+            hook(ip, expiry);
+        }
+    }
+
+    /// Returns true while `ip` is serving an automated block
+    fn is_blocked(&self, ip: &str) -> bool {
+        self.is_blocked_at(ip, SystemTime::now())
+    }
+
+    /// Checks a block against the given time, lifting it once expired
+    fn is_blocked_at(&self, ip: &str, now: SystemTime) -> bool {
+        let mut blocked_ips = self.blocked_ips.lock().unwrap();
+        match blocked_ips.get(ip) {
+            Some(&expiry) if now < expiry => true,
+            Some(_) => {
+                blocked_ips.remove(ip);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Sources still blocked at `now` with their expiry, soonest to expire first.
+    /// Read-only: expired blocks are left for `is_blocked_at` to lift.
+    pub(crate) fn active_blocks(&self, now: SystemTime) -> Vec<(String, SystemTime)> {
+        let mut blocks: Vec<_> = self
+            .blocked_ips
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, &expiry)| now < expiry)
+            .map(|(ip, &expiry)| (ip.clone(), expiry))
+            .collect();
+        blocks.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        blocks
+    }
+
+    /// Number of accounts still locked out at `now`
+    pub(crate) fn locked_accounts(&self, now: SystemTime) -> usize {
+        self.login_lockouts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|&&expiry| now < expiry)
+            .count()
+    }
+
+    /// Records a suspicious event for `source` and raises a correlated alert once more than
+    /// `ALERT_THRESHOLD` events fall within `DETECTION_WINDOW` seconds. Returns true
+    /// when this event pushed the source over the threshold.
+    fn correlate_event(&self, source: &str, at: SystemTime) -> bool {
+        let window = Duration::from_secs(DETECTION_WINDOW);
+        let mut recent_events = self.recent_events.lock().unwrap();
+        let events = recent_events.entry(source.to_string()).or_default();
+
+        while let Some(&oldest) = events.front() {
+            match at.duration_since(oldest) {
+                Ok(age) if age >= window => {
+                    events.pop_front();
+                }
+                _ => break,
+            }
         }
+        events.push_back(at);
+
+        // Alert only when crossing the threshold, not for every event above it
+        if events.len() as u64 == ALERT_THRESHOLD + 1 {
+            drop(recent_events);
+            println!(
+                "[ALERT] Correlated activity from {}: more than {} events within {}s",
+                source, ALERT_THRESHOLD, DETECTION_WINDOW
+            );
+            self.log_intrusion(source, "Correlated attack pattern within detection window");
+            return true;
+        }
+        false
+    }
+
+    /// Drops sources whose events have all aged out of the detection window
+    fn expire_events(&self) {
+        let window = Duration::from_secs(DETECTION_WINDOW);
+        let mut recent_events = self.recent_events.lock().unwrap();
+        recent_events.retain(|_, events| {
+            events.retain(|event| event.elapsed().map_or(true, |age| age < window));
+            !events.is_empty()
+        });
     }
 
     /// Detects known attack patterns in the request
@@ -73,21 +409,59 @@ impl IntrusionDetection {
     }
 
     /// Tracks failed login attempts and applies account lockout policies
-    fn track_failed_login(&self, username: &str) {
+    pub(crate) fn track_failed_login(&self, username: &str) {
+        self.track_failed_login_at(username, SystemTime::now());
+    }
+
+    /// Records a failed login at `at`. Failures older than `BAN_DURATION` are
+    /// forgotten; reaching `MAX_FAILED_LOGINS` locks the account for `BAN_DURATION`.
+    fn track_failed_login_at(&self, username: &str, at: SystemTime) {
+        let ban = Duration::from_secs(BAN_DURATION);
         let mut failed_logins = self.failed_logins.lock().unwrap();
-        let entry = failed_logins
-            .entry(username.to_string())
-            .or_insert((0, SystemTime::now()));
+        let entry = failed_logins.entry(username.to_string()).or_insert((0, at));
 
+        if at.duration_since(entry.1).is_ok_and(|age| age >= ban) {
+            entry.0 = 0;
+        }
         entry.0 += 1;
-        entry.1 = SystemTime::now();
+        entry.1 = at;
 
         if entry.0 >= MAX_FAILED_LOGINS {
+            failed_logins.remove(username);
+            drop(failed_logins);
+            self.login_lockouts
+                .lock()
+                .unwrap()
+                .insert(username.to_string(), at + ban);
             println!(
                 "[SECURITY] Account {} temporarily locked due to excessive failed login attempts.",
                 username
             );
             self.log_intrusion(username, "Brute-force login attempt detected");
+            self.raise_alert(username, "brute-force-login", Severity::Critical, at);
+        }
+    }
+
+    /// Clears the failure counter after a successful login
+    fn record_successful_login(&self, username: &str) {
+        self.failed_logins.lock().unwrap().remove(username);
+    }
+
+    /// Returns true while the account is locked out; the auth path must refuse logins
+    fn is_login_locked(&self, username: &str) -> bool {
+        self.is_login_locked_at(username, SystemTime::now())
+    }
+
+    /// Checks the lockout against the given time, unlocking once it has expired
+    fn is_login_locked_at(&self, username: &str, now: SystemTime) -> bool {
+        let mut lockouts = self.login_lockouts.lock().unwrap();
+        match lockouts.get(username) {
+            Some(&expiry) if now < expiry => true,
+            Some(_) => {
+                lockouts.remove(username);
+                false
+            }
+            None => false,
         }
     }
 
@@ -107,7 +481,252 @@ impl IntrusionDetection {
 fn main() {
     let ids = Arc::new(IntrusionDetection::new());
 
+    // Periodically purge correlation state for quiet sources
+    let janitor = Arc::clone(&ids);
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(DETECTION_WINDOW));
+        janitor.expire_events();
+    });
+
+    ids.add_alerter(Arc::new(FileAlerter {
+        path: "logs/ids_alerts.jsonl".to_string(),
+    }));
+    ids.add_alerter(Arc::new(WebhookAlerter::new(
+        "localhost",
+        9000,
+        "/hooks/security",
+    )));
+
+    // Forward automated blocks to the firewall
+    ids.subscribe_blocks(Arc::new(|ip, expiry| {
+        println!("[FIREWALL] Dropping traffic from {} until {:?}", ip, expiry);
+    }));
+
     // Simulate suspicious activity
     ids.monitor_request("192.168.1.5", "SELECT * FROM users WHERE password='admin'");
-    ids.track_failed_login("admin");
+    println!(
+        "[IDS] 192.168.1.5 blocked: {}",
+        ids.is_blocked("192.168.1.5")
+    );
+    for _ in 0..MAX_FAILED_LOGINS {
+        ids.track_failed_login("admin");
+    }
+    println!("[IDS] admin locked: {}", ids.is_login_locked("admin"));
+    let now = SystemTime::now();
+    println!(
+        "[IDS] {} active block(s), {} locked account(s)",
+        ids.active_blocks(now).len(),
+        ids.locked_accounts(now)
+    );
+
+    // A successful login resets the failure count
+    ids.track_failed_login("operator");
+    ids.record_successful_login("operator");
+
+    // Syscall decisions arrive from the interceptor's event stream
+    let syscalls = syscall_stream::SyscallBroadcaster::new();
+    let events = syscalls.subscribe();
+    let ingest = Arc::clone(&ids);
+    let consumer = thread::spawn(move || ingest.ingest_syscalls(events));
+    syscalls.publish(SyscallEvent {
+        seq: 0,
+        pid: 4242,
+        name: "setuid",
+        args: "args=[0x0, 0x0, 0x0]".to_string(),
+        timestamp: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        decision: Decision::Blocked,
+    });
+    drop(syscalls);
+    let _ = consumer.join();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A detector logging to a scratch file unique to this test
+    fn detector(name: &str) -> IntrusionDetection {
+        let path = test_util::scratch_path("ids", name);
+        let ids = IntrusionDetection::with_log_file(path.to_str().unwrap());
+        let _ = std::fs::remove_file(path); // The open handle keeps logging working
+        ids
+    }
+
+    /// Seconds after an arbitrary fixed start time
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn burst_within_the_window_is_correlated() {
+        let ids = detector("burst");
+        let crossings: Vec<bool> = (0..=ALERT_THRESHOLD)
+            .map(|i| ids.correlate_event("10.0.0.1", at(i)))
+            .collect();
+        assert_eq!(crossings.iter().filter(|&&crossed| crossed).count(), 1);
+        assert!(crossings.last().unwrap());
+        // Crossing is reported once, not for every later event
+        assert!(!ids.correlate_event("10.0.0.1", at(ALERT_THRESHOLD + 1)));
+    }
+
+    #[test]
+    fn same_count_spread_beyond_the_window_is_not_correlated() {
+        let ids = detector("spread");
+        let spacing = DETECTION_WINDOW / ALERT_THRESHOLD + 1;
+        assert!(
+            (0..=ALERT_THRESHOLD * 2).all(|i| !ids.correlate_event("10.0.0.2", at(i * spacing)))
+        );
+        // Sources are tracked separately
+        assert!(!ids.correlate_event("10.0.0.3", at(0)));
+    }
+
+    #[test]
+    fn detected_attack_blocks_the_source_until_the_block_expires() {
+        let ids = detector("block");
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let hook_log = Arc::clone(&notified);
+        ids.subscribe_blocks(Arc::new(move |ip: &str, expiry| {
+            hook_log.lock().unwrap().push((ip.to_string(), expiry));
+        }));
+
+        ids.monitor_request_at("10.0.0.66", "GET /?q=1; DROP TABLE users", at(0));
+        ids.monitor_request_at("10.0.0.7", "GET /index.html", at(0));
+
+        assert!(ids.is_blocked_at("10.0.0.66", at(1)));
+        assert!(!ids.is_blocked_at("10.0.0.7", at(1)));
+        assert_eq!(
+            *notified.lock().unwrap(),
+            vec![("10.0.0.66".to_string(), at(BAN_DURATION))]
+        );
+        assert!(ids.is_blocked_at("10.0.0.66", at(BAN_DURATION - 1)));
+        assert!(!ids.is_blocked_at("10.0.0.66", at(BAN_DURATION)));
+    }
+
+    #[test]
+    fn benign_volume_never_blocks_a_source() {
+        let ids = detector("benign");
+        for i in 0..ALERT_THRESHOLD * 3 {
+            ids.monitor_request_at("10.0.0.8", "GET /api/status", at(i));
+        }
+        assert!(!ids.is_blocked_at("10.0.0.8", at(ALERT_THRESHOLD * 3)));
+        assert!(ids.active_blocks(at(0)).is_empty());
+    }
+
+    #[test]
+    fn repeated_failures_lock_the_account_until_the_lockout_ends() {
+        let ids = detector("lockout");
+        for i in 0..MAX_FAILED_LOGINS as u64 - 1 {
+            ids.track_failed_login_at("admin", at(i));
+        }
+        assert!(!ids.is_login_locked_at("admin", at(10)));
+        ids.track_failed_login_at("admin", at(10));
+        assert!(ids.is_login_locked_at("admin", at(11)));
+        assert_eq!(ids.locked_accounts(at(11)), 1);
+
+        assert!(!ids.is_login_locked_at("admin", at(10 + BAN_DURATION)));
+        assert_eq!(ids.locked_accounts(at(10 + BAN_DURATION)), 0);
+    }
+
+    #[test]
+    fn successful_login_resets_the_failure_count() {
+        let ids = detector("reset");
+        for i in 0..MAX_FAILED_LOGINS as u64 - 1 {
+            ids.track_failed_login_at("operator", at(i));
+        }
+        ids.record_successful_login("operator");
+        ids.track_failed_login_at("operator", at(20));
+        assert!(!ids.is_login_locked_at("operator", at(21)));
+
+        // Failures older than the ban duration are forgotten as well
+        for i in 0..MAX_FAILED_LOGINS as u64 - 1 {
+            ids.track_failed_login_at("auditor", at(i));
+        }
+        ids.track_failed_login_at("auditor", at(BAN_DURATION * 2));
+        assert!(!ids.is_login_locked_at("auditor", at(BAN_DURATION * 2)));
+    }
+
+    /// Keeps every alert it is sent
+    #[derive(Default)]
+    struct RecordingAlerter {
+        alerts: Mutex<Vec<Alert>>,
+    }
+
+    impl Alerter for RecordingAlerter {
+        fn send(&self, alert: &Alert) -> io::Result<()> {
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn one_alert_per_incident_with_rapid_duplicates_suppressed() {
+        let ids = detector("alerts");
+        let alerter = Arc::new(RecordingAlerter::default());
+        ids.add_alerter(alerter.clone());
+
+        assert!(ids.raise_alert("10.0.0.9", "sqli", Severity::High, at(0)));
+        assert!(!ids.raise_alert("10.0.0.9", "sqli", Severity::High, at(1)));
+        assert!(ids.raise_alert("10.0.0.9", "xss", Severity::Medium, at(2)));
+        assert!(ids.raise_alert("10.0.0.10", "sqli", Severity::High, at(3)));
+        assert!(!ids.raise_alert(
+            "10.0.0.9",
+            "sqli",
+            Severity::High,
+            at(ALERT_DEDUP_WINDOW - 1)
+        ));
+        assert!(ids.raise_alert("10.0.0.9", "sqli", Severity::High, at(ALERT_DEDUP_WINDOW)));
+
+        let alerts = alerter.alerts.lock().unwrap();
+        let incidents: Vec<(&str, &str)> = alerts
+            .iter()
+            .map(|alert| (alert.source.as_str(), alert.rule.as_str()))
+            .collect();
+        assert_eq!(
+            incidents,
+            vec![
+                ("10.0.0.9", "sqli"),
+                ("10.0.0.9", "xss"),
+                ("10.0.0.10", "sqli"),
+                ("10.0.0.9", "sqli"),
+            ]
+        );
+    }
+
+    #[test]
+    fn webhook_alerter_posts_the_alert_as_json() {
+        use std::io::Read;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let alerter = WebhookAlerter::new("127.0.0.1", port, "/hooks/security");
+        let alert = Alert {
+            source: "10.0.0.9".to_string(),
+            rule: "sqli".to_string(),
+            timestamp: 42,
+            severity: Severity::Critical,
+        };
+        alerter.send(&alert).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut request = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !String::from_utf8_lossy(&request).ends_with(&alert.to_json()) {
+            let read = stream.read(&mut chunk).unwrap();
+            assert!(read > 0, "connection closed before the alert arrived");
+            request.extend_from_slice(&chunk[..read]);
+        }
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST /hooks/security HTTP/1.1\r\n"));
+        assert!(request.contains("\"severity\":\"Critical\""));
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    }
 }
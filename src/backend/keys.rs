@@ -0,0 +1,254 @@
+//! Secret Key Provisioning (Standard Library Only)
+//! Loads signing and encryption secrets at startup instead of compiling them in.
+//! Features:
+//! - **`KeyProvider` trait, so each module takes its secrets from whatever store is configured**
+//! - **`EnvKeyProvider`: `ZTW_<NAME>` directly, or a file named by `ZTW_<NAME>_FILE`**
+//! - **`StaticKeyProvider` for embedding and for pinning keys in tests**
+//! - **A missing or short secret is an error at construction, never a built-in default**
+
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Signs TOTP codes for multi-factor authentication
+pub const MFA_SECRET: &str = "MFA_SECRET";
+/// Signs and verifies HS256 JWTs
+pub const JWT_SECRET: &str = "JWT_SECRET";
+/// Root of the session store's event and persistence keys
+pub const SESSION_SECRET: &str = "SESSION_SECRET";
+/// Signs `user:role` access tokens
+pub const ACCESS_SECRET: &str = "ACCESS_SECRET";
+/// Signs rate limit counters gossiped between server instances
+pub const RATE_LIMIT_SECRET: &str = "RATE_LIMIT_SECRET";
+/// Encrypts values marked secret in the configuration file
+pub const CONFIG_SECRET: &str = "CONFIG_SECRET";
+/// Signs merged audit trail exports
+pub const AUDIT_EXPORT_SECRET: &str = "AUDIT_EXPORT_SECRET";
+/// Signs the attestations of nodes the cluster auto-scaler launches
+pub const ATTESTATION_SECRET: &str = "ATTESTATION_SECRET";
+
+pub const MIN_SECRET_LEN: usize = 32; // One full HMAC-SHA256 block of key material
+const ENV_PREFIX: &str = "ZTW_";
+const FILE_SUFFIX: &str = "_FILE";
+
+/// Why a required secret could not be provided
+#[derive(Debug)]
+pub enum KeyError {
+    /// Not configured; `location` says where the provider looked
+    Missing {
+        name: String,
+        location: String,
+    },
+    TooShort {
+        name: String,
+        len: usize,
+    },
+    Unreadable {
+        name: String,
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Missing { name, location } => {
+                write!(
+                    f,
+                    "required secret {} is not configured (set {})",
+                    name, location
+                )
+            }
+            KeyError::TooShort { name, len } => write!(
+                f,
+                "secret {} is {} bytes; at least {} are required",
+                name, len, MIN_SECRET_LEN
+            ),
+            KeyError::Unreadable { name, path, source } => write!(
+                f,
+                "secret {} could not be read from {}: {}",
+                name,
+                path.display(),
+                source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<KeyError> for io::Error {
+    fn from(e: KeyError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+/// A source of named secrets
+pub trait KeyProvider: Send + Sync {
+    /// The raw secret, or `None` when `name` is not configured
+    fn lookup(&self, name: &str) -> Result<Option<Vec<u8>>, KeyError>;
+
+    /// Where `lookup` looks for `name`, for error messages
+    fn location(&self, name: &str) -> String;
+
+    /// A secret the caller cannot run without: absent or short secrets are errors
+    fn require(&self, name: &str) -> Result<Vec<u8>, KeyError> {
+        let secret = self.lookup(name)?.ok_or_else(|| KeyError::Missing {
+            name: name.to_string(),
+            location: self.location(name),
+        })?;
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(KeyError::TooShort {
+                name: name.to_string(),
+                len: secret.len(),
+            });
+        }
+        Ok(secret)
+    }
+}
+
+/// Reads `ZTW_<NAME>`, falling back to the contents of the file at `ZTW_<NAME>_FILE`.
+/// One trailing newline is stripped from file contents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvKeyProvider;
+
+impl KeyProvider for EnvKeyProvider {
+    fn lookup(&self, name: &str) -> Result<Option<Vec<u8>>, KeyError> {
+        let variable = format!("{}{}", ENV_PREFIX, name);
+        if let Some(value) = env::var_os(&variable) {
+            return Ok(Some(value.into_encoded_bytes()));
+        }
+        let path = match env::var_os(format!("{}{}", variable, FILE_SUFFIX)) {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(None),
+        };
+        let mut secret = fs::read(&path).map_err(|source| KeyError::Unreadable {
+            name: name.to_string(),
+            path,
+            source,
+        })?;
+        if secret.ends_with(b"\n") {
+            secret.pop();
+            if secret.ends_with(b"\r") {
+                secret.pop();
+            }
+        }
+        Ok(Some(secret))
+    }
+
+    fn location(&self, name: &str) -> String {
+        format!(
+            "{}{} or {}{}{}",
+            ENV_PREFIX, name, ENV_PREFIX, name, FILE_SUFFIX
+        )
+    }
+}
+
+/// A fixed set of secrets held in memory
+#[derive(Debug, Clone, Default)]
+pub struct StaticKeyProvider {
+    secrets: HashMap<String, Vec<u8>>,
+}
+
+impl StaticKeyProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, secret: &[u8]) -> Self {
+        self.secrets.insert(name.to_string(), secret.to_vec());
+        self
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn lookup(&self, name: &str) -> Result<Option<Vec<u8>>, KeyError> {
+        Ok(self.secrets.get(name).cloned())
+    }
+
+    fn location(&self, name: &str) -> String {
+        format!("{} in the static key set", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A secret name no other test or deployment uses. Binaries can include this
+    /// module more than once, so the module path keeps the copies apart.
+    fn unique_name(test: &str) -> String {
+        format!(
+            "KEYS_TEST_{}_{}_{}",
+            test,
+            module_path!().replace("::", "_").to_ascii_uppercase(),
+            std::process::id()
+        )
+    }
+
+    #[test]
+    fn missing_or_short_secrets_are_errors() {
+        let keys = StaticKeyProvider::new().with(JWT_SECRET, b"too short");
+        match keys.require(MFA_SECRET) {
+            Err(KeyError::Missing { name, location }) => {
+                assert_eq!(name, MFA_SECRET);
+                assert_eq!(location, "MFA_SECRET in the static key set");
+            }
+            other => panic!("expected Missing, got {:?}", other),
+        }
+        assert!(matches!(
+            keys.require(JWT_SECRET),
+            Err(KeyError::TooShort { len: 9, .. })
+        ));
+
+        let name = unique_name("MISSING");
+        let error = EnvKeyProvider.require(&name).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&format!("ZTW_{} or ZTW_{}_FILE", name, name)));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn provided_secrets_are_returned_as_configured() {
+        let secret = [7u8; MIN_SECRET_LEN];
+        let keys = StaticKeyProvider::new().with(SESSION_SECRET, &secret);
+        assert_eq!(keys.require(SESSION_SECRET).unwrap(), secret);
+
+        let name = unique_name("DIRECT");
+        env::set_var(format!("ZTW_{}", name), "d".repeat(MIN_SECRET_LEN));
+        assert_eq!(
+            EnvKeyProvider.require(&name).unwrap(),
+            "d".repeat(MIN_SECRET_LEN).into_bytes()
+        );
+        env::remove_var(format!("ZTW_{}", name));
+    }
+
+    #[test]
+    fn secret_files_are_read_without_their_trailing_newline() {
+        let name = unique_name("FILE");
+        let path = test_util::scratch_path("keys", &name);
+        fs::write(&path, format!("{}\r\n", "f".repeat(MIN_SECRET_LEN))).unwrap();
+        env::set_var(format!("ZTW_{}_FILE", name), &path);
+        assert_eq!(
+            EnvKeyProvider.require(&name).unwrap(),
+            "f".repeat(MIN_SECRET_LEN).into_bytes()
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            EnvKeyProvider.require(&name),
+            Err(KeyError::Unreadable { .. })
+        ));
+        env::remove_var(format!("ZTW_{}_FILE", name));
+    }
+}
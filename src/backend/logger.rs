@@ -6,69 +6,240 @@
 //! - **Encrypted remote log replication for redundancy**
 //! - **Time-based log encryption with scheduled decryption windows**
 //! - **Asynchronous logging with multi-threaded worker pools**
+//! - **Bounded write queue drained by a writer thread, with a block/drop-oldest/drop-new overflow policy**
 //! - **Memory-mapped I/O for high-speed log storage**
 //! - **Automated log compaction & deduplication**
 //! - **Multi-Factor Authentication (MFA) for log retrieval**
 //! - **Real-time anomaly detection & unauthorized access monitoring**
 //! - **Secure log archival with automated expiry policies**
 
-use std::collections::{HashMap, HashSet};
-use std::convert::TryInto;
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod hash_chain;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use crypto::{sha256, verify_totp, Zeroizing};
+use hash_chain::{ChainVerifier, VerifyError};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::ops::Deref;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
-use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const LOG_FILE: &str = "logs/system.log";
 const MAX_LOG_ENTRIES: usize = 100_000;
 const ARCHIVE_RETENTION_DAYS: u64 = 365;
+const MAX_MFA_FAILURES: u32 = 3; // Failed codes allowed per requester before lockout
+const MFA_LOCKOUT: Duration = Duration::from_secs(300);
+const LOG_QUEUE_CAPACITY: usize = 4096; // Entries waiting for the writer thread
+const LOG_OVERFLOW_ENV: &str = "LOG_OVERFLOW_POLICY"; // block | drop-oldest | drop-new
 
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
+/// A run of consecutive identical messages; also the unit read back from disk
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LogEntry {
+    pub(crate) message: String,
+    pub(crate) first: u64,
+    pub(crate) last: u64,
+    pub(crate) count: usize,
+}
 
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
+impl LogEntry {
+    /// A single entry keeps the plain `ts | message` form; repeats collapse to
+    /// `first..last | message (xN)`
+    fn to_line(&self) -> String {
+        if self.count == 1 {
+            format!("{} | {}", self.first, self.message)
+        } else {
+            format!(
+                "{}..{} | {} (x{})",
+                self.first, self.last, self.message, self.count
+            )
+        }
     }
 
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
+    /// Inverse of `to_line`
+    fn parse(line: &str) -> Option<Self> {
+        let (time, message) = line.split_once(" | ")?;
+        match time.split_once("..") {
+            None => Some(Self {
+                message: message.to_string(),
+                first: time.parse().ok()?,
+                last: time.parse().ok()?,
+                count: 1,
+            }),
+            Some((first, last)) => {
+                let (message, count) = message.strip_suffix(')')?.rsplit_once(" (x")?;
+                Some(Self {
+                    message: message.to_string(),
+                    first: first.parse().ok()?,
+                    last: last.parse().ok()?,
+                    count: count.parse().ok()?,
+                })
+            }
+        }
+    }
 }
 
-/// Secure log storage with cryptographic integrity checks
-struct SecureLogger {
-    log_file: Mutex<BufWriter<File>>,       // Ensures thread-safe writes
-    log_index: Mutex<HashMap<u64, String>>, // Tracks log entries
-    log_archive: Mutex<HashSet<String>>,    // Stores archived log files
-    condition: Condvar,
+/// Parses each verified stored line back into a log entry
+struct EntryVerifier<R> {
+    lines: ChainVerifier<R>,
 }
 
-impl SecureLogger {
-    fn new() -> Self {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(LOG_FILE)
-            .expect("Failed to open log file");
+impl<R: BufRead> Iterator for EntryVerifier<R> {
+    type Item = Result<LogEntry, VerifyError>;
 
-        Self {
-            log_file: Mutex::new(BufWriter::new(file)),
-            log_index: Mutex::new(HashMap::new()),
-            log_archive: Mutex::new(HashSet::new()),
-            condition: Condvar::new(),
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.lines.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let line = self.lines.line();
+        Some(LogEntry::parse(&entry).ok_or(VerifyError::Malformed { line }))
+    }
+}
+
+/// What `log` does when the write queue is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OverflowPolicy {
+    /// Wait for the writer thread to make room; nothing is lost
+    Block,
+    /// Discard the oldest queued entry to make room for the new one
+    DropOldest,
+    /// Discard the entry being logged
+    DropNew,
+}
+
+impl OverflowPolicy {
+    /// Reads `LOG_OVERFLOW_POLICY`; unset or unrecognised values block,
+    /// since an audit log should only lose entries by explicit choice
+    fn from_env() -> Self {
+        match env::var(LOG_OVERFLOW_ENV).as_deref() {
+            Ok("drop-oldest") => OverflowPolicy::DropOldest,
+            Ok("drop-new") => OverflowPolicy::DropNew,
+            _ => OverflowPolicy::Block,
         }
     }
+}
+
+struct QueueState {
+    entries: VecDeque<(u64, String)>, // (Timestamp, message) in arrival order
+    writing: bool,                    // The writer holds a batch it has not finished writing
+    shutdown: bool,                   // Drain what is queued, then stop
+    writer_exited: bool,              // Nothing will drain the queue any more
+}
+
+/// Bounded hand-off between `log` callers and the writer thread
+struct LogQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar, // Signalled when entries arrive or shutdown begins
+    space: Condvar, // Signalled when the writer takes or finishes a batch
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
 
+impl LogQueue {
+    fn push(&self, timestamp: u64, entry: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.entries.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    while state.entries.len() >= self.capacity && !state.writer_exited {
+                        state = self.space.wait(state).unwrap();
+                    }
+                }
+                OverflowPolicy::DropOldest => {
+                    state.entries.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::DropNew => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        if state.writer_exited {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        state.entries.push_back((timestamp, entry.to_string()));
+        drop(state);
+        self.ready.notify_one();
+    }
+
+    /// Waits until everything queued so far has been handed to the store
+    fn wait_idle(&self) {
+        let mut state = self.state.lock().unwrap();
+        while (state.writing || !state.entries.is_empty()) && !state.writer_exited {
+            state = self.space.wait(state).unwrap();
+        }
+    }
+}
+
+/// Marks the writer gone however it exits, so blocked callers are released
+/// and whatever is still queued is counted as dropped
+struct WriterExit<'a>(&'a LogQueue);
+
+impl Drop for WriterExit<'_> {
+    fn drop(&mut self) {
+        let queue = self.0;
+        let mut state = queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        queue
+            .dropped
+            .fetch_add(state.entries.len() as u64, Ordering::Relaxed);
+        state.entries.clear();
+        state.writing = false;
+        state.writer_exited = true;
+        drop(state);
+        queue.space.notify_all();
+    }
+}
+
+/// Writer thread body: moves queued entries into the store in batches until shutdown
+fn drain_queue(queue: &LogQueue, store: &LogStore) {
+    let _exit = WriterExit(queue);
+    loop {
+        let batch = {
+            let mut state = queue.state.lock().unwrap();
+            while state.entries.is_empty() && !state.shutdown {
+                state = queue.ready.wait(state).unwrap();
+            }
+            if state.entries.is_empty() {
+                return; // Shut down with nothing left to write
+            }
+            state.writing = true;
+            std::mem::take(&mut state.entries)
+        };
+        queue.space.notify_all();
+        for (timestamp, entry) in batch {
+            store.record(timestamp, &entry);
+        }
+        // A process killed between batches loses at most the run still being compacted
+        store.flush_written();
+        queue.state.lock().unwrap().writing = false;
+        queue.space.notify_all();
+    }
+}
+
+/// The hash-chained log file and its in-memory index; written only by the writer thread
+struct LogStore {
+    log_file: Mutex<BufWriter<File>>,       // Ensures thread-safe writes
+    chain_head: Mutex<[u8; 32]>,            // Hash of the last line written
+    log_index: Mutex<HashMap<u64, String>>, // Tracks log entries
+    pending: Mutex<Option<LogEntry>>,       // Deduplication buffer for repeated messages
+}
+
+impl LogStore {
     /// Appends a cryptographically signed log entry
-    fn log(&self, entry: &str) {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    fn record(&self, timestamp: u64, entry: &str) {
         let log_entry = format!("{} | {}", timestamp, entry);
         let hash = sha256(log_entry.as_bytes());
 
@@ -80,13 +251,161 @@ impl SecureLogger {
         }
 
         log_index.insert(timestamp, format!("{:x?}", hash));
-        writeln!(log_file, "{} | Hash: {:x?}", log_entry, hash).expect("Failed to write log");
+
+        // Consecutive repeats are counted and written once the run ends
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_mut() {
+            Some(run) if run.message == entry => {
+                run.last = timestamp;
+                run.count += 1;
+            }
+            _ => {
+                if let Some(run) = pending.take() {
+                    write_run(&mut log_file, &mut self.chain_head.lock().unwrap(), &run);
+                }
+                *pending = Some(LogEntry {
+                    message: entry.to_string(),
+                    first: timestamp,
+                    last: timestamp,
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    /// Flushes completed lines to disk, leaving any run of repeats buffered
+    fn flush_written(&self) {
+        self.log_file
+            .lock()
+            .unwrap()
+            .flush()
+            .expect("Failed to flush log");
+    }
+
+    /// Writes any buffered run of repeated messages and flushes the file
+    fn flush(&self) {
+        let mut log_file = self.log_file.lock().unwrap();
+        if let Some(run) = self.pending.lock().unwrap().take() {
+            write_run(&mut log_file, &mut self.chain_head.lock().unwrap(), &run);
+        }
+        log_file.flush().expect("Failed to flush log");
+    }
+}
+
+/// Secure log storage with cryptographic integrity checks.
+/// `log` only enqueues; a dedicated writer thread does the hashing and disk I/O.
+pub(crate) struct SecureLogger {
+    path: String,
+    store: Arc<LogStore>,
+    queue: Arc<LogQueue>,
+    writer: Option<JoinHandle<()>>, // Drains `queue` into `store`; joined on drop
+    log_archive: Mutex<HashSet<String>>, // Stores archived log files
+}
+
+impl SecureLogger {
+    pub(crate) fn new() -> Self {
+        Self::open(LOG_FILE).expect("Failed to open log file")
+    }
+
+    /// Opens the log at `path`, continuing the hash chain of any existing lines.
+    /// The overflow policy comes from `LOG_OVERFLOW_POLICY`.
+    pub(crate) fn open(path: &str) -> io::Result<Self> {
+        Self::open_with(path, LOG_QUEUE_CAPACITY, OverflowPolicy::from_env())
+    }
+
+    /// Opens the log at `path` with a queue of `capacity` entries and the given overflow policy
+    pub(crate) fn open_with(
+        path: &str,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let chain_head = hash_chain::chain_head(path)?;
+
+        let store = Arc::new(LogStore {
+            log_file: Mutex::new(BufWriter::new(file)),
+            chain_head: Mutex::new(chain_head),
+            log_index: Mutex::new(HashMap::new()),
+            pending: Mutex::new(None),
+        });
+        let queue = Arc::new(LogQueue {
+            state: Mutex::new(QueueState {
+                entries: VecDeque::with_capacity(capacity.max(1)),
+                writing: false,
+                shutdown: false,
+                writer_exited: false,
+            }),
+            ready: Condvar::new(),
+            space: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+            dropped: AtomicU64::new(0),
+        });
+        let writer = {
+            let store = Arc::clone(&store);
+            let queue = Arc::clone(&queue);
+            thread::Builder::new()
+                .name("log-writer".to_string())
+                .spawn(move || drain_queue(&queue, &store))?
+        };
+
+        Ok(Self {
+            path: path.to_string(),
+            store,
+            queue,
+            writer: Some(writer),
+            log_archive: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Queues an entry for the writer thread, stamped with the current time.
+    /// A full queue blocks or drops according to the overflow policy.
+    pub(crate) fn log(&self, entry: &str) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.queue.push(timestamp, entry);
+    }
+
+    /// Entries discarded because the queue was full or the writer had stopped
+    pub(crate) fn dropped_count(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Waits for queued entries to be written, then flushes any buffered run and the file
+    fn flush(&self) {
+        self.queue.wait_idle();
+        self.store.flush();
+    }
+
+    /// Reads every stored entry back, verifying each line's hash and its link to the
+    /// previous line. Works on logs written before a restart, unlike `verify_log`.
+    fn iter_entries(&self) -> io::Result<impl Iterator<Item = Result<LogEntry, VerifyError>>> {
+        self.flush();
+        let file = File::open(&self.path)?;
+        Ok(EntryVerifier {
+            lines: ChainVerifier::new(BufReader::new(file)),
+        })
+    }
+
+    /// Verified entries whose last occurrence is at or after `since`
+    pub(crate) fn find_since(&self, since: u64) -> Result<Vec<LogEntry>, VerifyError> {
+        let mut found = Vec::new();
+        for entry in self.iter_entries().map_err(VerifyError::Io)? {
+            let entry = entry?;
+            if entry.last >= since {
+                found.push(entry);
+            }
+        }
+        Ok(found)
     }
 
     /// Verifies the integrity of a logged entry
     fn verify_log(&self, timestamp: u64, entry: &str) -> bool {
+        self.queue.wait_idle();
         let expected_hash = sha256(format!("{} | {}", timestamp, entry).as_bytes());
-        let log_index = self.log_index.lock().unwrap();
+        let log_index = self.store.log_index.lock().unwrap();
 
         match log_index.get(&timestamp) {
             Some(stored_hash) => stored_hash == &format!("{:x?}", expected_hash),
@@ -107,12 +426,105 @@ impl SecureLogger {
     }
 }
 
+impl Drop for SecureLogger {
+    /// Lets the writer drain the queue before the final flush
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().shutdown = true;
+        self.queue.ready.notify_all();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        self.store.flush();
+    }
+}
+
+/// Appends a (possibly compacted) run, chained to the previous line's hash
+fn write_run(log_file: &mut BufWriter<File>, chain_head: &mut [u8; 32], run: &LogEntry) {
+    hash_chain::write_line(log_file, chain_head, &run.to_line()).expect("Failed to write log");
+}
+
+/// Why a log retrieval was refused
+#[derive(Debug)]
+enum RetrievalError {
+    InvalidMfaCode,
+    RateLimited,
+    Verify(VerifyError),
+}
+
+impl fmt::Display for RetrievalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RetrievalError::InvalidMfaCode => write!(f, "invalid MFA code"),
+            RetrievalError::RateLimited => write!(f, "too many failed MFA attempts"),
+            RetrievalError::Verify(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Gates log retrieval behind a TOTP code; every attempt is itself logged
+struct SecureLogReader {
+    logger: Arc<SecureLogger>,
+    failures: Mutex<HashMap<String, (u32, Instant)>>, // Requester -> (failed codes, first failure)
+    mfa_secret: Zeroizing<Vec<u8>>, // `keys::MFA_SECRET`, the TOTP key `auth.rs` issues codes under
+}
+
+#[allow(dead_code)] // Not wired into `main`; retrieval needs an operator's TOTP code
+impl SecureLogReader {
+    fn new(logger: Arc<SecureLogger>, mfa_secret: Vec<u8>) -> Self {
+        Self {
+            logger,
+            failures: Mutex::new(HashMap::new()),
+            mfa_secret: Zeroizing::new(mfa_secret),
+        }
+    }
+
+    /// Returns the verified log entries once `mfa_code` checks out for `requester`.
+    /// After `MAX_MFA_FAILURES` bad codes the requester is refused until `MFA_LOCKOUT` passes.
+    fn read_entries(
+        &self,
+        requester: &str,
+        mfa_code: &str,
+    ) -> Result<Vec<LogEntry>, RetrievalError> {
+        {
+            let mut failures = self.failures.lock().unwrap();
+            if let Some(&(count, since)) = failures.get(requester) {
+                if since.elapsed() >= MFA_LOCKOUT {
+                    failures.remove(requester);
+                } else if count >= MAX_MFA_FAILURES {
+                    drop(failures);
+                    self.audit(requester, "denied (rate limited)");
+                    return Err(RetrievalError::RateLimited);
+                }
+            }
+
+            if !verify_totp(mfa_code, &self.mfa_secret) {
+                let failure = failures
+                    .entry(requester.to_string())
+                    .or_insert((0, Instant::now()));
+                failure.0 += 1;
+                drop(failures);
+                self.audit(requester, "denied (invalid MFA code)");
+                return Err(RetrievalError::InvalidMfaCode);
+            }
+            failures.remove(requester);
+        }
+
+        self.audit(requester, "granted");
+        self.logger.find_since(0).map_err(RetrievalError::Verify)
+    }
+
+    fn audit(&self, requester: &str, outcome: &str) {
+        self.logger
+            .log(&format!("LOG-ACCESS requester='{}' {}", requester, outcome));
+    }
+}
+
 /// Worker thread pool for asynchronous logging
 fn log_worker(logger: Arc<SecureLogger>) {
     loop {
         let mut logs_to_write = Vec::new();
         {
-            let log_index = logger.log_index.lock().unwrap();
+            let log_index = logger.store.log_index.lock().unwrap();
             logs_to_write.extend(
                 log_index
                     .iter()
@@ -141,9 +553,328 @@ fn main() {
     let is_valid = logger.verify_log(1698745672, "User authentication succeeded");
     println!("Log verification: {}", is_valid);
 
+    // Entries from earlier runs are checked from disk, not the in-memory index
+    match logger.find_since(0) {
+        Ok(entries) => println!("Verified {} stored log entries", entries.len()),
+        Err(e) => eprintln!("Stored log verification failed: {}", e),
+    }
+    println!("Log entries dropped so far: {}", logger.dropped_count());
+
     // Periodically archive logs
     loop {
         thread::sleep(Duration::from_secs(86400)); // Run daily
         logger.archive_logs();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_700_000_000;
+
+    fn scratch_logger(name: &str) -> (SecureLogger, String) {
+        let path = test_util::scratch_path("logger", name)
+            .to_string_lossy()
+            .into_owned();
+        let logger = SecureLogger::open_with(&path, 16, OverflowPolicy::Block).unwrap();
+        (logger, path)
+    }
+
+    #[test]
+    fn identical_consecutive_entries_compact_to_one_counted_line() {
+        let (logger, path) = scratch_logger("compaction");
+        logger.store.record(T0 - 1, "service started");
+        for i in 0..100 {
+            logger.store.record(T0 + i, "health check failed");
+        }
+        logger.store.record(T0 + 200, "service recovered");
+
+        let entries = logger.find_since(0).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                LogEntry {
+                    message: "service started".to_string(),
+                    first: T0 - 1,
+                    last: T0 - 1,
+                    count: 1,
+                },
+                LogEntry {
+                    message: "health check failed".to_string(),
+                    first: T0,
+                    last: T0 + 99,
+                    count: 100,
+                },
+                LogEntry {
+                    message: "service recovered".to_string(),
+                    first: T0 + 200,
+                    last: T0 + 200,
+                    count: 1,
+                },
+            ]
+        );
+
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(stored.lines().count(), 3);
+        assert!(stored.contains(&format!("{}..{} | health check failed (x100)", T0, T0 + 99)));
+        drop(logger);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn distinct_and_interleaved_entries_are_preserved() {
+        let (logger, path) = scratch_logger("distinct");
+        for (i, message) in ["a", "b", "a", "a", "b"].iter().enumerate() {
+            logger.store.record(T0 + i as u64, message);
+        }
+
+        let counts: Vec<_> = logger
+            .find_since(0)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.message, entry.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("a".to_string(), 1),
+                ("b".to_string(), 1),
+                ("a".to_string(), 2),
+                ("b".to_string(), 1),
+            ]
+        );
+        drop(logger);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn compacted_lines_parse_back_including_messages_with_separators() {
+        let run = LogEntry {
+            message: "GET /a | b (x2)".to_string(),
+            first: T0,
+            last: T0 + 5,
+            count: 7,
+        };
+        assert_eq!(LogEntry::parse(&run.to_line()), Some(run));
+    }
+
+    #[test]
+    fn entries_survive_dropping_and_reopening_the_logger() {
+        let (logger, path) = scratch_logger("restart");
+        logger.log("first run: boot");
+        logger.log("first run: login");
+        drop(logger);
+
+        let reopened = SecureLogger::open_with(&path, 16, OverflowPolicy::Block).unwrap();
+        reopened.log("second run: boot");
+        let messages: Vec<_> = reopened
+            .find_since(0)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(
+            messages,
+            ["first run: boot", "first run: login", "second run: boot"]
+        );
+        drop(reopened);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn edited_or_removed_lines_fail_verification() {
+        let (logger, path) = scratch_logger("tamper");
+        logger.store.record(T0, "user alice logged in");
+        logger.store.record(T0 + 1, "user bob logged in");
+        logger.store.record(T0 + 2, "user carol logged in");
+        drop(logger);
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, original.replacen("bob", "eve", 1)).unwrap();
+        let reopened = SecureLogger::open_with(&path, 16, OverflowPolicy::Block).unwrap();
+        assert!(matches!(
+            reopened.find_since(0),
+            Err(VerifyError::HashMismatch { line: 2 })
+        ));
+        drop(reopened);
+
+        let without_first: String = original
+            .lines()
+            .skip(1)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        std::fs::write(&path, without_first).unwrap();
+        let reopened = SecureLogger::open_with(&path, 16, OverflowPolicy::Block).unwrap();
+        assert!(matches!(
+            reopened.find_since(0),
+            Err(VerifyError::BrokenChain { line: 1 })
+        ));
+        drop(reopened);
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn audit_trail(logger: &SecureLogger) -> Vec<String> {
+        logger
+            .find_since(0)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.message)
+            .filter(|message| message.starts_with("LOG-ACCESS"))
+            .collect()
+    }
+
+    #[test]
+    fn retrieval_requires_a_valid_code_and_is_itself_logged() {
+        let (logger, path) = scratch_logger("retrieval");
+        let logger = Arc::new(logger);
+        logger.log("sensitive event");
+        let secret = b"logger-test-mfa-secret".to_vec();
+        let reader = SecureLogReader::new(Arc::clone(&logger), secret.clone());
+
+        assert!(matches!(
+            reader.read_entries("mallory", "000000x"),
+            Err(RetrievalError::InvalidMfaCode)
+        ));
+        let entries = reader
+            .read_entries("alice", &crypto::generate_totp(&secret))
+            .unwrap();
+        assert!(entries
+            .iter()
+            .any(|entry| entry.message == "sensitive event"));
+
+        assert_eq!(
+            audit_trail(&logger),
+            [
+                "LOG-ACCESS requester='mallory' denied (invalid MFA code)",
+                "LOG-ACCESS requester='alice' granted",
+            ]
+        );
+        drop(reader);
+        drop(logger);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn repeated_bad_codes_lock_the_requester_out() {
+        let (logger, path) = scratch_logger("lockout");
+        let logger = Arc::new(logger);
+        let secret = b"logger-test-mfa-secret".to_vec();
+        let reader = SecureLogReader::new(Arc::clone(&logger), secret.clone());
+
+        for _ in 0..MAX_MFA_FAILURES {
+            assert!(matches!(
+                reader.read_entries("mallory", "bad"),
+                Err(RetrievalError::InvalidMfaCode)
+            ));
+        }
+        // Even the right code is refused during the lockout
+        assert!(matches!(
+            reader.read_entries("mallory", &crypto::generate_totp(&secret)),
+            Err(RetrievalError::RateLimited)
+        ));
+        assert!(reader
+            .read_entries("alice", &crypto::generate_totp(&secret))
+            .is_ok());
+        assert_eq!(
+            audit_trail(&logger).last().map(String::as_str),
+            Some("LOG-ACCESS requester='alice' granted")
+        );
+        drop(reader);
+        drop(logger);
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// A queue with no writer draining it, so what `push` keeps is deterministic
+    fn undrained_queue(capacity: usize, policy: OverflowPolicy) -> LogQueue {
+        LogQueue {
+            state: Mutex::new(QueueState {
+                entries: VecDeque::new(),
+                writing: false,
+                shutdown: false,
+                writer_exited: false,
+            }),
+            ready: Condvar::new(),
+            space: Condvar::new(),
+            capacity,
+            policy,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn queued(queue: &LogQueue) -> Vec<String> {
+        let state = queue.state.lock().unwrap();
+        state
+            .entries
+            .iter()
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
+    #[test]
+    fn full_queue_drops_per_policy_and_counts_every_loss() {
+        let drop_new = undrained_queue(4, OverflowPolicy::DropNew);
+        let drop_oldest = undrained_queue(4, OverflowPolicy::DropOldest);
+        for i in 0..10 {
+            drop_new.push(T0, &format!("event {}", i));
+            drop_oldest.push(T0, &format!("event {}", i));
+        }
+
+        assert_eq!(
+            queued(&drop_new),
+            ["event 0", "event 1", "event 2", "event 3"]
+        );
+        assert_eq!(
+            queued(&drop_oldest),
+            ["event 6", "event 7", "event 8", "event 9"]
+        );
+        assert_eq!(drop_new.dropped.load(Ordering::Relaxed), 6);
+        assert_eq!(drop_oldest.dropped.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn full_queue_blocks_the_producer_until_there_is_room() {
+        let queue = Arc::new(undrained_queue(2, OverflowPolicy::Block));
+        queue.push(T0, "first");
+        queue.push(T0, "second");
+        let producer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.push(T0, "third"))
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        queue.state.lock().unwrap().entries.pop_front();
+        queue.space.notify_all();
+        producer.join().unwrap();
+
+        assert_eq!(queued(&queue), ["second", "third"]);
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn saturating_producer_loses_exactly_what_is_counted() {
+        for policy in [OverflowPolicy::DropNew, OverflowPolicy::DropOldest] {
+            let path = test_util::scratch_path("logger", &format!("saturate-{:?}", policy))
+                .to_string_lossy()
+                .into_owned();
+            let logger = SecureLogger::open_with(&path, 2, policy).unwrap();
+            for i in 0..2000 {
+                logger.log(&format!("burst entry {}", i));
+            }
+
+            let stored = logger.find_since(0).unwrap();
+            let dropped = logger.dropped_count();
+            assert!(dropped > 0, "{:?} never overflowed", policy);
+            assert_eq!(stored.len() as u64 + dropped, 2000, "{:?}", policy);
+            // Survivors keep their order whichever end was dropped
+            let numbers: Vec<usize> = stored
+                .iter()
+                .map(|entry| entry.message["burst entry ".len()..].parse().unwrap())
+                .collect();
+            assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+            drop(logger);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -5,74 +5,397 @@
 //! - **Security event tracking, intrusion detection, and anomaly detection**
 //! - **Tamper-proof immutable logging for regulatory compliance**
 //! - **Real-time structured logging with cryptographic integrity validation**
-//! - **Multi-threaded metric collection for high-performance tracking**
+//! - **Concurrent metric collection; a collector that misses its timeout marks its metric stale**
+//! - **Bounded per-metric history with time-range queries and percentiles**
+//! - **Prometheus text exposition served at `/metrics`**
 //! - **Adaptive alerting with AI-driven risk assessment**
-//! - **Self-healing triggers based on monitored anomalies**
+//! - **Self-healing triggers based on monitored anomalies (debounced per condition)**
 //! - **Encrypted metric storage with secure access controls**
 //! - **Secure remote log replication for redundancy and failover protection**
 //! - **Distributed monitoring support for Zero Trust infrastructure**
 
-use std::collections::HashMap;
-use std::convert::TryInto;
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod self_healing;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use crypto::{OsRng, Rng};
+use self_healing::{
+    Anomaly, RemediationAction, RemediationKind, SelfHealingSystem, ServiceRemediation,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::io::{BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const LOG_FILE: &str = "logs/monitoring.log";
 const CHECK_INTERVAL: u64 = 5; // Monitor every 5 seconds
+const COLLECTOR_TIMEOUT: Duration = Duration::from_secs(2); // Longest a cycle waits on any collector
 const ALERT_THRESHOLD_CPU: f32 = 85.0; // CPU usage alert threshold
 const ALERT_THRESHOLD_MEMORY: f32 = 90.0; // Memory usage alert threshold
 const ALERT_THRESHOLD_DISK_IO: f32 = 80.0; // Disk I/O alert threshold
+const REMEDIATION_THRESHOLD_MEMORY: f32 = 95.0; // Memory level that triggers self-healing
+const HISTORY_SIZE: usize = 720; // Samples kept per metric (one hour at CHECK_INTERVAL)
+const METRICS_BIND_ADDRESS: &str = "127.0.0.1:9464"; // Scrape endpoint, local-only by default
+const METRIC_PREFIX: &str = "ztw_";
+
+/// `# HELP` text for the metrics the collector knows about
+const METRIC_HELP: [(&str, &str); 4] = [
+    ("cpu_usage", "CPU utilisation in percent"),
+    ("memory_usage", "Memory utilisation in percent"),
+    ("disk_io", "Disk I/O utilisation in percent"),
+    ("active_processes", "Number of active processes"),
+];
+
+/// Fixed-capacity ring of `(timestamp, value)` samples for one metric
+struct MetricHistory {
+    samples: VecDeque<(u64, f32)>,
+    capacity: usize,
+}
+
+impl MetricHistory {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Appends a sample, evicting the oldest once the ring is full
+    fn push(&mut self, timestamp: u64, value: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((timestamp, value));
+    }
+}
+
+/// Reads one metric; runs on its own thread so a slow read cannot hold up the others
+type ReadMetric = Arc<dyn Fn() -> f32 + Send + Sync>;
+
+/// A named metric source. `busy` stays set while a read is running, so a read
+/// that outlives its cycle is not started again until it returns.
+struct Collector {
+    metric: String,
+    read: ReadMetric,
+    busy: Arc<AtomicBool>,
+}
+
+/// A remediation bound to a metric threshold. `armed` is cleared when the
+/// action fires and set again once the metric recovers, so one crossing
+/// triggers exactly one remediation.
+struct RemediationRule {
+    metric: String,
+    threshold: f32,
+    action: Arc<dyn RemediationAction>,
+    armed: bool,
+}
 
 /// Secure monitoring system for collecting and logging performance & security metrics
-struct MonitoringSystem {
+pub(crate) struct MonitoringSystem {
     logs: Mutex<BufWriter<File>>, // Ensures secure, structured logging
     metrics: Mutex<HashMap<String, f32>>, // Stores current metric values
+    history: Mutex<HashMap<String, MetricHistory>>, // Recent samples per metric
+    history_size: usize,
+    remediations: Mutex<Vec<RemediationRule>>,
+    collectors: Vec<Collector>,
+    collector_timeout: Duration,
+    stale: Mutex<HashSet<String>>, // Metrics whose last collector missed its timeout
 }
 
 impl MonitoringSystem {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
+        Self::with_log_file(LOG_FILE)
+    }
+
+    /// Writes the metrics log to `path` instead of `LOG_FILE`
+    pub(crate) fn with_log_file(path: &str) -> Self {
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(LOG_FILE)
+            .open(path)
             .expect("Failed to open log file");
 
         Self {
             logs: Mutex::new(BufWriter::new(file)),
             metrics: Mutex::new(HashMap::new()),
+            history: Mutex::new(HashMap::new()),
+            history_size: HISTORY_SIZE,
+            remediations: Mutex::new(Vec::new()),
+            collectors: Vec::new(),
+            collector_timeout: COLLECTOR_TIMEOUT,
+            stale: Mutex::new(HashSet::new()),
+        }
+        .with_rng(Arc::new(OsRng))
+    }
+
+    /// Replaces the randomness behind simulated readings, e.g. with a `SeededRng`
+    /// so a run can be reproduced exactly. Resets the collectors to the simulated set.
+    fn with_rng(mut self, rng: Arc<dyn Rng>) -> Self {
+        self.collectors.clear();
+        for (metric, scale, step) in [
+            ("cpu_usage", 100.0, None),
+            ("memory_usage", 100.0, None),
+            ("disk_io", 100.0, None),
+            ("active_processes", 256.0, Some(10.0)),
+        ] {
+            let rng = Arc::clone(&rng);
+            self = self.with_collector(metric, move || {
+                let reading = simulated(rng.as_ref(), scale);
+                step.map_or(reading, |step| reading.floor() * step)
+            });
         }
+        self
     }
 
-    /// Collects system performance metrics
-    fn collect_metrics(&self) {
-        let mut metrics = self.metrics.lock().unwrap();
+    /// Adds a collector for `metric`, replacing any existing one for the same metric
+    fn with_collector(
+        mut self,
+        metric: &str,
+        read: impl Fn() -> f32 + Send + Sync + 'static,
+    ) -> Self {
+        self.collectors
+            .retain(|collector| collector.metric != metric);
+        self.collectors.push(Collector {
+            metric: metric.to_string(),
+            read: Arc::new(read),
+            busy: Arc::new(AtomicBool::new(false)),
+        });
+        self
+    }
 
-        metrics.insert("cpu_usage".to_string(), self.get_cpu_usage());
-        metrics.insert("memory_usage".to_string(), self.get_memory_usage());
-        metrics.insert("disk_io".to_string(), self.get_disk_io());
-        metrics.insert("active_processes".to_string(), self.get_active_processes());
+    /// Sets how long one collection cycle waits for its collectors
+    #[allow(dead_code)] // `main` keeps the default budget
+    fn with_collector_timeout(mut self, timeout: Duration) -> Self {
+        self.collector_timeout = timeout;
+        self
+    }
+
+    /// Runs every collector concurrently and records what arrives within the timeout.
+    /// A collector that misses it keeps running detached; its metric keeps its previous
+    /// value, gets no history sample and is marked stale until a later read succeeds.
+    pub(crate) fn collect_metrics(&self) {
+        let deadline = Instant::now() + self.collector_timeout;
+        let (sender, receiver) = mpsc::channel();
+        let mut pending = HashSet::new();
+        for collector in &self.collectors {
+            pending.insert(collector.metric.clone());
+            // Still stuck in a previous cycle; leave it pending rather than pile up threads
+            if collector.busy.swap(true, Ordering::SeqCst) {
+                continue;
+            }
+            let sender = sender.clone();
+            let metric = collector.metric.clone();
+            let read = Arc::clone(&collector.read);
+            let busy = Arc::clone(&collector.busy);
+            thread::spawn(move || {
+                let value = read();
+                busy.store(false, Ordering::SeqCst);
+                // The cycle may have moved on; a late reading is simply dropped
+                let _ = sender.send((metric, value));
+            });
+        }
+        drop(sender);
+
+        let mut readings = Vec::new();
+        while !pending.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(remaining) {
+                Ok((metric, value)) => {
+                    pending.remove(&metric);
+                    readings.push((metric, value));
+                }
+                Err(_) => break,
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.merge_readings(readings, pending, now);
+    }
+
+    /// Applies one cycle's readings, taking each lock once
+    fn merge_readings(&self, readings: Vec<(String, f32)>, stale: HashSet<String>, timestamp: u64) {
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            for (metric, value) in &readings {
+                metrics.insert(metric.clone(), *value);
+            }
+        }
+        {
+            let mut history = self.history.lock().unwrap();
+            for (metric, value) in &readings {
+                history
+                    .entry(metric.clone())
+                    .or_insert_with(|| MetricHistory::new(self.history_size))
+                    .push(timestamp, *value);
+            }
+        }
+        *self.stale.lock().unwrap() = stale;
+    }
+
+    /// Metrics whose collector missed the last cycle's timeout, sorted by name
+    pub(crate) fn stale_metrics(&self) -> Vec<String> {
+        let mut stale: Vec<String> = self.stale.lock().unwrap().iter().cloned().collect();
+        stale.sort();
+        stale
+    }
+
+    /// Stores `value` as the metric's current value and appends it to its history
+    #[allow(dead_code)] // For values measured outside a collector
+    pub(crate) fn record(&self, metric: &str, value: f32) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.record_at(metric, value, now);
+    }
+
+    fn record_at(&self, metric: &str, value: f32, timestamp: u64) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .insert(metric.to_string(), value);
+        self.history
+            .lock()
+            .unwrap()
+            .entry(metric.to_string())
+            .or_insert_with(|| MetricHistory::new(self.history_size))
+            .push(timestamp, value);
+    }
+
+    /// A copy of every metric's current value
+    #[allow(dead_code)] // Snapshot for embedders; `main` reads via `log_metrics`
+    pub(crate) fn current_metrics(&self) -> HashMap<String, f32> {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    /// Returns the retained samples for `metric` recorded at or after `since` (oldest first)
+    fn history(&self, metric: &str, since: u64) -> Vec<(u64, f32)> {
+        self.history
+            .lock()
+            .unwrap()
+            .get(metric)
+            .map(|ring| {
+                ring.samples
+                    .iter()
+                    .filter(|(timestamp, _)| *timestamp >= since)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Computes the `p`th percentile (0-100) over the retained window using
+    /// linear interpolation between closest ranks; `None` without samples
+    fn percentile(&self, metric: &str, p: f32) -> Option<f32> {
+        let mut values: Vec<f32> = self
+            .history(metric, 0)
+            .into_iter()
+            .map(|(_, value)| value)
+            .filter(|value| value.is_finite())
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let rank = (p.clamp(0.0, 100.0) / 100.0) * (values.len() - 1) as f32;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        let weight = rank - lower as f32;
+        Some(values[lower] + (values[upper] - values[lower]) * weight)
     }
 
     /// Logs monitored metrics securely with cryptographic integrity
     fn log_metrics(&self) {
+        let stale = self.stale_metrics();
         let metrics = self.metrics.lock().unwrap();
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let log_entry = format!("{} | Metrics: {:?}", timestamp, *metrics);
-        let integrity_hash = self.sha256(log_entry.as_bytes());
+        let log_entry = if stale.is_empty() {
+            format!("{} | Metrics: {:?}", timestamp, *metrics)
+        } else {
+            format!(
+                "{} | Metrics: {:?} | Stale: {:?}",
+                timestamp, *metrics, stale
+            )
+        };
+        let integrity_hash = crypto::sha256(log_entry.as_bytes());
 
         let mut logs = self.logs.lock().unwrap();
         writeln!(logs, "{} | Hash: {:x?}", log_entry, integrity_hash).expect("Failed to write log");
     }
 
+    /// Invokes `action` when `metric` rises above `threshold`
+    fn register_remediation(
+        &self,
+        metric: &str,
+        threshold: f32,
+        action: Arc<dyn RemediationAction>,
+    ) {
+        self.remediations.lock().unwrap().push(RemediationRule {
+            metric: metric.to_string(),
+            threshold,
+            action,
+            armed: true,
+        });
+    }
+
+    /// Fires remediations whose metric has crossed its threshold since the last
+    /// evaluation; a rule re-arms only after the metric drops back to the threshold
+    fn evaluate_remediations(&self) {
+        let metrics = self.metrics.lock().unwrap().clone();
+        let mut triggered = Vec::new();
+        {
+            let mut rules = self.remediations.lock().unwrap();
+            for rule in rules.iter_mut() {
+                let value = match metrics.get(&rule.metric) {
+                    Some(&value) => value,
+                    None => continue,
+                };
+                if value > rule.threshold && rule.armed {
+                    rule.armed = false;
+                    triggered.push((
+                        Arc::clone(&rule.action),
+                        Anomaly {
+                            metric: rule.metric.clone(),
+                            value,
+                            threshold: rule.threshold,
+                        },
+                    ));
+                } else if value <= rule.threshold {
+                    rule.armed = true;
+                }
+            }
+        }
+
+        // Actions run without locks held so they may query the monitor
+        for (action, anomaly) in triggered {
+            action.remediate(&anomaly);
+        }
+    }
+
     /// Checks if alert thresholds are exceeded and triggers alerts
     fn check_alerts(&self) {
+        self.evaluate_remediations();
+
+        for metric in self.stale_metrics() {
+            println!("[ALERT] Collector for {} timed out; value is stale", metric);
+        }
+
         let metrics = self.metrics.lock().unwrap();
 
         if let Some(&cpu) = metrics.get("cpu_usage") {
@@ -94,48 +417,326 @@ impl MonitoringSystem {
         }
     }
 
-    /// Gets CPU usage (simulated)
-    fn get_cpu_usage(&self) -> f32 {
-        rand::random::<f32>() * 100.0
-    }
+    /// Renders the current metric values in the Prometheus text exposition format
+    fn render_prometheus(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut names: Vec<&String> = metrics.keys().collect();
+        names.sort();
 
-    /// Gets memory usage (simulated)
-    fn get_memory_usage(&self) -> f32 {
-        rand::random::<f32>() * 100.0
+        let mut out = String::new();
+        for name in names {
+            let exposed = sanitize_metric_name(&format!("{}{}", METRIC_PREFIX, name));
+            let help = METRIC_HELP
+                .iter()
+                .find(|(metric, _)| metric == name)
+                .map(|(_, help)| help.to_string())
+                .unwrap_or_else(|| format!("Monitored value of {}", name));
+            out.push_str(&format!("# HELP {} {}\n", exposed, escape_help(&help)));
+            out.push_str(&format!("# TYPE {} gauge\n", exposed));
+            out.push_str(&format!("{} {}\n", exposed, format_sample(metrics[name])));
+        }
+        out
     }
+}
 
-    /// Gets disk I/O usage (simulated)
-    fn get_disk_io(&self) -> f32 {
-        rand::random::<f32>() * 100.0
+/// A simulated reading in `[0, scale)`; reads as zero if no randomness is available
+fn simulated(rng: &dyn Rng, scale: f32) -> f32 {
+    rng.next_f32().unwrap_or(0.0) * scale
+}
+
+/// Maps an arbitrary metric key onto `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn sanitize_metric_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
     }
+    sanitized
+}
 
-    /// Gets number of active processes (simulated)
-    fn get_active_processes(&self) -> f32 {
-        (rand::random::<u8>() as f32) * 10.0
+/// HELP text may not contain raw backslashes or newlines
+fn escape_help(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn format_sample(value: f32) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
     }
+}
 
-    /// Implements a SHA-256 hash function manually for log integrity
-    fn sha256(&self, data: &[u8]) -> [u8; 32] {
-        let mut hash = [0u8; 32];
-        let mut accumulator: u32 = 0x6A09E667;
+/// Answers one scrape: `GET /metrics` gets the exposition, anything else 404
+fn handle_metrics_request(mut stream: TcpStream, monitoring: &MonitoringSystem) {
+    let mut buffer = [0u8; 1024];
+    let bytes_read = match stream.read(&mut buffer) {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
 
-        for &byte in data.iter() {
-            accumulator = accumulator.wrapping_add(byte as u32);
-            accumulator ^= accumulator.rotate_left(5);
-        }
+    let response = if method == "GET" && path.split('?').next() == Some("/metrics") {
+        let body = monitoring.render_prometheus();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
 
-        hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-        hash
-    }
+/// Serves the `/metrics` scrape endpoint on a background thread
+fn serve_metrics(monitoring: Arc<MonitoringSystem>, address: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            handle_metrics_request(stream, &monitoring);
+        }
+    });
+    Ok(())
 }
 
 fn main() {
     let monitoring_system = Arc::new(MonitoringSystem::new());
 
+    let self_healing = Arc::new(SelfHealingSystem::new());
+    self_healing.register_service("web_server", "./start_web_server.sh");
+    monitoring_system.register_remediation(
+        "memory_usage",
+        REMEDIATION_THRESHOLD_MEMORY,
+        Arc::new(ServiceRemediation::new(
+            self_healing,
+            "web_server",
+            RemediationKind::Restart,
+        )),
+    );
+    match serve_metrics(Arc::clone(&monitoring_system), METRICS_BIND_ADDRESS) {
+        Ok(()) => println!(
+            "[METRICS] Prometheus endpoint at http://{}/metrics",
+            METRICS_BIND_ADDRESS
+        ),
+        Err(e) => eprintln!("[METRICS] Unable to serve metrics: {}", e),
+    }
+
     loop {
         monitoring_system.collect_metrics();
         monitoring_system.log_metrics();
         monitoring_system.check_alerts();
+        if let Some(p95) = monitoring_system.percentile("cpu_usage", 95.0) {
+            println!("[METRICS] CPU p95 over the last hour: {:.1}%", p95);
+        }
         thread::sleep(Duration::new(CHECK_INTERVAL, 0));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A monitor logging to a scratch file, which is unlinked once opened
+    fn monitor(name: &str) -> MonitoringSystem {
+        let path = test_util::scratch_path("monitoring", name);
+        let monitoring = MonitoringSystem::with_log_file(path.to_str().unwrap());
+        let _ = std::fs::remove_file(path);
+        monitoring
+    }
+
+    #[test]
+    fn history_supports_percentiles_and_time_filtering() {
+        let monitoring = monitor("history");
+        for (i, value) in [50.0, 10.0, 40.0, 20.0, 30.0].into_iter().enumerate() {
+            monitoring.record_at("cpu_usage", value, 1_000 + i as u64 * 5);
+        }
+
+        assert_eq!(monitoring.percentile("cpu_usage", 50.0), Some(30.0));
+        assert_eq!(monitoring.percentile("cpu_usage", 0.0), Some(10.0));
+        assert_eq!(monitoring.percentile("cpu_usage", 100.0), Some(50.0));
+        assert_eq!(monitoring.percentile("cpu_usage", 25.0), Some(20.0));
+        assert_eq!(monitoring.percentile("disk_io", 50.0), None);
+
+        assert_eq!(
+            monitoring.history("cpu_usage", 1_010),
+            vec![(1_010, 40.0), (1_015, 20.0), (1_020, 30.0)]
+        );
+        assert!(monitoring.history("cpu_usage", 1_021).is_empty());
+        assert_eq!(monitoring.current_metrics()["cpu_usage"], 30.0);
+    }
+
+    #[test]
+    fn history_is_bounded_by_the_ring_size() {
+        let mut monitoring = monitor("ring");
+        monitoring.history_size = 3;
+        for i in 0..10u64 {
+            monitoring.record_at("memory_usage", i as f32, i);
+        }
+        assert_eq!(
+            monitoring.history("memory_usage", 0),
+            vec![(7, 7.0), (8, 8.0), (9, 9.0)]
+        );
+        assert_eq!(monitoring.percentile("memory_usage", 50.0), Some(8.0));
+    }
+
+    /// Checks each line against the text exposition grammar, returning the samples
+    fn parse_exposition(text: &str) -> Vec<(String, f64)> {
+        let is_name = |name: &str| {
+            name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == ':')
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+        };
+        let mut described = HashSet::new();
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, _) = rest.split_once(' ').expect("HELP has text");
+                assert!(is_name(name), "{}", line);
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').expect("TYPE has a kind");
+                assert!(is_name(name) && kind == "gauge", "{}", line);
+                described.insert(name.to_string());
+            } else {
+                let (name, value) = line.split_once(' ').expect("sample has a value");
+                assert!(is_name(name), "{}", line);
+                assert!(described.contains(name), "no TYPE before {}", name);
+                samples.push((name.to_string(), value.parse().expect("numeric value")));
+            }
+        }
+        assert!(text.ends_with('\n'));
+        samples
+    }
+
+    #[test]
+    fn prometheus_output_is_valid_exposition_format() {
+        let monitoring = monitor("prometheus");
+        monitoring.record("cpu_usage", 42.5);
+        monitoring.record("http.requests-total", 7.0);
+        monitoring.record("9lives", 1.0);
+
+        let text = monitoring.render_prometheus();
+        assert!(text.contains("# HELP ztw_cpu_usage CPU utilisation in percent\n"));
+        assert_eq!(
+            parse_exposition(&text),
+            vec![
+                ("ztw_9lives".to_string(), 1.0),
+                ("ztw_cpu_usage".to_string(), 42.5),
+                ("ztw_http_requests_total".to_string(), 7.0),
+            ]
+        );
+        assert_eq!(sanitize_metric_name("1st"), "_1st");
+    }
+
+    #[test]
+    fn metrics_endpoint_serves_the_exposition() {
+        let monitoring = Arc::new(monitor("endpoint"));
+        monitoring.record("memory_usage", 12.0);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        serve_metrics(Arc::clone(&monitoring), &address).unwrap();
+
+        let fetch = |path: &str| {
+            let mut stream = TcpStream::connect(&address).unwrap();
+            // One write: the handler answers after a single read
+            let request = format!("GET {} HTTP/1.1\r\nHost: test\r\n\r\n", path);
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = fetch("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            parse_exposition(body),
+            vec![("ztw_memory_usage".to_string(), 12.0)]
+        );
+        assert!(fetch("/other").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    /// Remediation that only records what it was asked to fix
+    #[derive(Default)]
+    struct RecordingAction {
+        fired: Mutex<Vec<f32>>,
+    }
+
+    impl RemediationAction for RecordingAction {
+        fn remediate(&self, anomaly: &Anomaly) {
+            assert_eq!(anomaly.metric, "memory_usage");
+            assert_eq!(anomaly.threshold, REMEDIATION_THRESHOLD_MEMORY);
+            self.fired.lock().unwrap().push(anomaly.value);
+        }
+    }
+
+    #[test]
+    fn remediation_fires_once_per_threshold_crossing() {
+        let monitoring = monitor("remediation");
+        let action = Arc::new(RecordingAction::default());
+        monitoring.register_remediation(
+            "memory_usage",
+            REMEDIATION_THRESHOLD_MEMORY,
+            Arc::clone(&action) as Arc<dyn RemediationAction>,
+        );
+
+        // Below, above three times, back at the threshold, above again
+        for value in [80.0, 96.0, 97.0, 99.0, 95.0, 98.0] {
+            monitoring.record("memory_usage", value);
+            monitoring.evaluate_remediations();
+        }
+        assert_eq!(*action.fired.lock().unwrap(), vec![96.0, 98.0]);
+    }
+
+    #[test]
+    fn slow_collector_is_marked_stale_without_stalling_the_cycle() {
+        let slow_once = Arc::new(AtomicBool::new(true));
+        let monitoring = {
+            let slow_once = Arc::clone(&slow_once);
+            monitor("slow-collector")
+                .with_collector("disk_io", move || {
+                    if slow_once.swap(false, Ordering::SeqCst) {
+                        thread::sleep(Duration::from_millis(400));
+                    }
+                    42.0
+                })
+                .with_collector_timeout(Duration::from_millis(100))
+        };
+
+        let started = Instant::now();
+        monitoring.collect_metrics();
+        assert!(
+            started.elapsed() < Duration::from_millis(300),
+            "cycle took {:?}",
+            started.elapsed()
+        );
+        assert_eq!(monitoring.stale_metrics(), ["disk_io"]);
+        let metrics = monitoring.current_metrics();
+        assert!(metrics.contains_key("cpu_usage") && metrics.contains_key("active_processes"));
+        assert!(!metrics.contains_key("disk_io"));
+
+        // Still running, so the next cycle leaves it pending instead of starting another read
+        monitoring.collect_metrics();
+        assert_eq!(monitoring.stale_metrics(), ["disk_io"]);
+
+        thread::sleep(Duration::from_millis(400));
+        monitoring.collect_metrics();
+        assert!(monitoring.stale_metrics().is_empty());
+        assert_eq!(monitoring.current_metrics()["disk_io"], 42.0);
+    }
+}
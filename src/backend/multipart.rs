@@ -0,0 +1,489 @@
+//! Streaming `multipart/form-data` parser for uploads (Standard Library Only)
+//! Parts are read one at a time from any `Read` source, so only the current part
+//! is held in memory. Every part, the headers of each part, the number of parts
+//! and the whole body are size-limited before any bytes are buffered.
+
+use std::fmt;
+use std::io::{self, Read};
+
+const MAX_BOUNDARY_LEN: usize = 70; // RFC 2046
+const MAX_PART_HEADER_SIZE: usize = 8 * 1024;
+const DEFAULT_MAX_PART_SIZE: usize = 8 * 1024 * 1024;
+const DEFAULT_MAX_TOTAL_SIZE: usize = 32 * 1024 * 1024;
+const DEFAULT_MAX_PARTS: usize = 256;
+const READ_CHUNK: usize = 8 * 1024;
+
+/// One field or file of a form submission
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub headers: Vec<(String, String)>,
+    pub name: String,
+    /// File name as sent, reduced to its last path component
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl Part {
+    /// Looks up a part header by name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The data as text, if it is valid UTF-8
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+}
+
+/// Why a multipart body was rejected
+#[derive(Debug)]
+pub enum MultipartError {
+    /// The boundary is empty, too long, or absent from `Content-Type`
+    InvalidBoundary,
+    /// The body never contains the boundary delimiter
+    BoundaryNotFound,
+    /// A part's headers or `Content-Disposition` are unusable
+    MalformedPart(&'static str),
+    /// The body ended before the closing delimiter
+    Truncated,
+    PartTooLarge,
+    BodyTooLarge,
+    TooManyParts,
+    Io(io::Error),
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultipartError::InvalidBoundary => f.write_str("invalid multipart boundary"),
+            MultipartError::BoundaryNotFound => f.write_str("boundary not found in body"),
+            MultipartError::MalformedPart(reason) => write!(f, "malformed part: {}", reason),
+            MultipartError::Truncated => f.write_str("body ended before the closing boundary"),
+            MultipartError::PartTooLarge => f.write_str("part exceeds the size limit"),
+            MultipartError::BodyTooLarge => f.write_str("body exceeds the size limit"),
+            MultipartError::TooManyParts => f.write_str("too many parts"),
+            MultipartError::Io(e) => write!(f, "failed to read body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+/// Where the reader is within the body
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Preamble,
+    PartStart, // Just past a delimiter
+    Done,
+}
+
+/// Yields the parts of a multipart body as they are read from `source`
+pub struct MultipartReader<R> {
+    source: R,
+    delimiter: Vec<u8>, // `\r\n--boundary`
+    buffer: Vec<u8>,
+    eof: bool,
+    state: State,
+    consumed: usize,
+    parts: usize,
+    max_part_size: usize,
+    max_total_size: usize,
+    max_parts: usize,
+}
+
+impl<R: Read> MultipartReader<R> {
+    pub fn new(source: R, boundary: &str) -> Result<Self, MultipartError> {
+        if boundary.is_empty()
+            || boundary.len() > MAX_BOUNDARY_LEN
+            || boundary.bytes().any(|b| b.is_ascii_control())
+        {
+            return Err(MultipartError::InvalidBoundary);
+        }
+        Ok(Self {
+            source,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // The first delimiter may open the body, so read as if a line break preceded it
+            buffer: b"\r\n".to_vec(),
+            eof: false,
+            state: State::Preamble,
+            consumed: 0,
+            parts: 0,
+            max_part_size: DEFAULT_MAX_PART_SIZE,
+            max_total_size: DEFAULT_MAX_TOTAL_SIZE,
+            max_parts: DEFAULT_MAX_PARTS,
+        })
+    }
+
+    /// Overrides the per-part, whole-body and part-count limits
+    pub fn with_limits(
+        mut self,
+        max_part_size: usize,
+        max_total_size: usize,
+        max_parts: usize,
+    ) -> Self {
+        self.max_part_size = max_part_size;
+        self.max_total_size = max_total_size;
+        self.max_parts = max_parts;
+        self
+    }
+
+    /// Reads another chunk into the buffer; false at end of input
+    fn fill(&mut self) -> Result<bool, MultipartError> {
+        if self.eof {
+            return Ok(false);
+        }
+        let mut chunk = [0u8; READ_CHUNK];
+        let n = loop {
+            match self.source.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(MultipartError::Io(e)),
+            }
+        };
+        self.consumed += n;
+        if self.consumed > self.max_total_size {
+            return Err(MultipartError::BodyTooLarge);
+        }
+        self.eof = n == 0;
+        self.buffer.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    /// Ensures at least `len` bytes are buffered unless the input ends first
+    fn fill_to(&mut self, len: usize) -> Result<bool, MultipartError> {
+        while self.buffer.len() < len {
+            if !self.fill()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Moves everything before the next delimiter into `sink` (`None` discards it),
+    /// then consumes the delimiter. Fails if more than `limit` bytes precede it.
+    fn read_until_delimiter(
+        &mut self,
+        mut sink: Option<&mut Vec<u8>>,
+        limit: usize,
+        missing: fn() -> MultipartError,
+    ) -> Result<(), MultipartError> {
+        let mut taken = 0;
+        loop {
+            if let Some(pos) = find(&self.buffer, &self.delimiter) {
+                if taken + pos > limit {
+                    return Err(MultipartError::PartTooLarge);
+                }
+                if let Some(sink) = sink.as_deref_mut() {
+                    sink.extend_from_slice(&self.buffer[..pos]);
+                }
+                self.buffer.drain(..pos + self.delimiter.len());
+                return Ok(());
+            }
+            // Keep a tail that could be the start of a delimiter split across reads
+            let safe = self.buffer.len().saturating_sub(self.delimiter.len() - 1);
+            taken += safe;
+            if taken > limit {
+                return Err(MultipartError::PartTooLarge);
+            }
+            if let Some(sink) = sink.as_deref_mut() {
+                sink.extend_from_slice(&self.buffer[..safe]);
+            }
+            self.buffer.drain(..safe);
+            if !self.fill()? {
+                return Err(missing());
+            }
+        }
+    }
+
+    /// After a delimiter: `--` closes the body, otherwise the line must end
+    /// (after optional whitespace) before the next part's headers
+    fn after_delimiter(&mut self) -> Result<bool, MultipartError> {
+        self.fill_to(2)?;
+        if self.buffer.starts_with(b"--") {
+            self.state = State::Done;
+            return Ok(false);
+        }
+        loop {
+            match self.buffer.iter().position(|b| *b != b' ' && *b != b'\t') {
+                Some(pos) => {
+                    self.buffer.drain(..pos);
+                    break;
+                }
+                None => {
+                    self.buffer.clear();
+                    if !self.fill()? {
+                        return Err(MultipartError::Truncated);
+                    }
+                }
+            }
+        }
+        if !self.fill_to(2)? || !self.buffer.starts_with(b"\r\n") {
+            return Err(MultipartError::MalformedPart(
+                "boundary line not terminated",
+            ));
+        }
+        self.buffer.drain(..2);
+        Ok(true)
+    }
+
+    /// Reads a part's header block, consuming the blank line that ends it
+    fn read_headers(&mut self) -> Result<Vec<(String, String)>, MultipartError> {
+        let end = loop {
+            if self.buffer.starts_with(b"\r\n") {
+                self.buffer.drain(..2);
+                return Ok(Vec::new()); // No headers at all
+            }
+            if let Some(pos) = find(&self.buffer, b"\r\n\r\n") {
+                break pos;
+            }
+            if self.buffer.len() > MAX_PART_HEADER_SIZE {
+                return Err(MultipartError::MalformedPart("headers too large"));
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Truncated);
+            }
+        };
+        if end > MAX_PART_HEADER_SIZE {
+            return Err(MultipartError::MalformedPart("headers too large"));
+        }
+        let block = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+        self.buffer.drain(..end + 4);
+
+        block
+            .split("\r\n")
+            .map(|line| {
+                let (name, value) = line
+                    .split_once(':')
+                    .ok_or(MultipartError::MalformedPart("header without ':'"))?;
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    fn next_part(&mut self) -> Result<Option<Part>, MultipartError> {
+        if self.state == State::Preamble {
+            self.read_until_delimiter(None, self.max_total_size, || {
+                MultipartError::BoundaryNotFound
+            })?;
+            self.state = State::PartStart;
+        }
+        if self.state == State::Done || !self.after_delimiter()? {
+            return Ok(None);
+        }
+        if self.parts == self.max_parts {
+            return Err(MultipartError::TooManyParts);
+        }
+        self.parts += 1;
+
+        let headers = self.read_headers()?;
+        let disposition = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Disposition"))
+            .map(|(_, value)| value.as_str())
+            .ok_or(MultipartError::MalformedPart("missing Content-Disposition"))?;
+        let (kind, params) = parse_disposition(disposition);
+        if !kind.eq_ignore_ascii_case("form-data") {
+            return Err(MultipartError::MalformedPart(
+                "disposition is not form-data",
+            ));
+        }
+        let name = param(&params, "name")
+            .ok_or(MultipartError::MalformedPart("missing field name"))?
+            .to_string();
+        let filename = param(&params, "filename").map(base_name);
+        let content_type = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .map(|(_, value)| value.clone());
+
+        let mut data = Vec::new();
+        self.read_until_delimiter(Some(&mut data), self.max_part_size, || {
+            MultipartError::Truncated
+        })?;
+        Ok(Some(Part {
+            headers,
+            name,
+            filename,
+            content_type,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for MultipartReader<R> {
+    type Item = Result<Part, MultipartError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_part() {
+            Ok(part) => part.map(Ok),
+            Err(e) => {
+                self.state = State::Done; // Stop after the first error
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Parses a complete in-memory body with the default limits
+pub fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Part>, MultipartError> {
+    MultipartReader::new(body, boundary)?.collect()
+}
+
+/// The `boundary` parameter of a `multipart/form-data` Content-Type
+pub fn boundary_from_content_type(content_type: &str) -> Option<String> {
+    let (media_type, params) = parse_disposition(content_type);
+    if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    param(&params, "boundary").map(str::to_string)
+}
+
+/// Splits `value; key=v; key2="quoted"` into the leading token and its parameters
+fn parse_disposition(value: &str) -> (&str, Vec<(String, String)>) {
+    let (kind, mut rest) = value.split_once(';').unwrap_or((value, ""));
+    let mut params = Vec::new();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key
+            .trim()
+            .trim_start_matches(';')
+            .trim()
+            .to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        params.push((key, value));
+        rest = remaining.trim_start().trim_start_matches(';');
+    }
+    (kind.trim(), params)
+}
+
+/// Reads a quoted-string body (after the opening quote), undoing `\` escapes
+fn unquote(input: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &input[i + 1..]),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Drops any client-side directories so a filename cannot name a path
+fn base_name(filename: &str) -> String {
+    filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "----zt-form-7MA4YWxk";
+
+    /// A text field `title` and a two-line file `notes.txt`
+    fn two_part_form() -> Vec<u8> {
+        format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\
+             \r\n\
+             Quarterly report\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"upload\"; filename=\"C:\\\\docs\\\\notes.txt\"\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             line one\r\nline two\r\n\
+             --{b}--\r\n",
+            b = BOUNDARY
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn text_field_and_file_are_parsed() {
+        let content_type = format!("multipart/form-data; boundary=\"{}\"", BOUNDARY);
+        let boundary = boundary_from_content_type(&content_type).unwrap();
+        assert_eq!(boundary, BOUNDARY);
+
+        let parts = parse_multipart(&two_part_form(), &boundary).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "title");
+        assert_eq!(parts[0].text(), Some("Quarterly report"));
+        assert!(!parts[0].is_file());
+
+        assert_eq!(parts[1].name, "upload");
+        assert_eq!(parts[1].filename.as_deref(), Some("notes.txt"));
+        assert_eq!(parts[1].content_type.as_deref(), Some("text/plain"));
+        assert_eq!(parts[1].header("content-type"), Some("text/plain"));
+        assert_eq!(parts[1].data, b"line one\r\nline two");
+        assert!(parts[1].is_file());
+    }
+
+    #[test]
+    fn mismatched_or_invalid_boundaries_are_rejected() {
+        let result = parse_multipart(&two_part_form(), "some-other-boundary");
+        assert!(matches!(result, Err(MultipartError::BoundaryNotFound)));
+        assert!(matches!(
+            parse_multipart(&two_part_form(), ""),
+            Err(MultipartError::InvalidBoundary)
+        ));
+        assert_eq!(boundary_from_content_type("text/plain; boundary=x"), None);
+
+        let mut truncated = two_part_form();
+        truncated.truncate(truncated.len() - 8);
+        assert!(matches!(
+            parse_multipart(&truncated, BOUNDARY),
+            Err(MultipartError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn limits_stop_oversized_forms() {
+        let body = two_part_form();
+        let reader = MultipartReader::new(&body[..], BOUNDARY).unwrap();
+        let results: Vec<_> = reader.with_limits(16, 1024, 4).collect();
+        // The 16-byte title fits; the 18-byte file does not, and parsing stops there
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(MultipartError::PartTooLarge)));
+
+        let reader = MultipartReader::new(&body[..], BOUNDARY).unwrap();
+        let result: Result<Vec<_>, _> = reader.with_limits(1024, 1024, 1).collect();
+        assert!(matches!(result, Err(MultipartError::TooManyParts)));
+    }
+}
@@ -2,29 +2,223 @@
 //! Advanced OSINT Aggregator aligned with Zero Trust principles
 //! Rust standard library only – No external dependencies
 
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::net::{TcpStream, ToSocketAddrs};
-use std::thread::sleep;
-use std::time::{Duration, SystemTime};
+#[allow(dead_code)]
+#[path = "http_client.rs"]
+mod http_client;
 
-// Manual HTTP GET request implementation via TCP
-fn manual_http_get(host: &str, path: &str) -> std::io::Result<String> {
-    let addr = (host, 80).to_socket_addrs()?.next().unwrap();
-    let mut stream = TcpStream::connect_timeout(&addr, Duration::new(5, 0))?;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant, SystemTime};
+
+const DEFAULT_WORKERS: usize = 4; // Global cap on fetches in flight
+const HOST_INTERVAL: Duration = Duration::from_secs(10); // Minimum gap between requests to one host
+const ROBOTS_AGENT: &str = "osint-aggregator"; // Product token matched against robots.txt groups
+const IGNORE_ROBOTS_ENV: &str = "OSINT_IGNORE_ROBOTS"; // Set to 1 for scans the site owner authorized
 
-    // Zero Trust: Manually construct and sanitize HTTP request
-    let request = format!(
-        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Mozilla/5.0 (OSINT Aggregator)\r\nConnection: close\r\n\r\n",
-        path, host
-    );
+// What one target revealed; `error` is set when the fetch failed
+#[derive(Debug, Clone, PartialEq)]
+struct OsintProfile {
+    host: String,
+    path: String,
+    title: Option<String>,
+    error: Option<String>,
+    disallowed: bool, // Skipped because robots.txt disallows the path
+}
+
+// Concurrency and politeness settings for `fetch_profiles`
+#[derive(Debug, Clone, Copy)]
+struct PoolConfig {
+    workers: usize,
+    host_interval: Duration,
+    ignore_robots: bool, // Fetch every path regardless of robots.txt
+}
 
-    stream.write_all(request.as_bytes())?;
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: DEFAULT_WORKERS,
+            host_interval: HOST_INTERVAL,
+            ignore_robots: false,
+        }
+    }
+}
 
-    let mut response = String::new();
-    stream.read_to_string(&mut response)?;
+// The robots.txt rules that apply to us on one host
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RobotsRules {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+    crawl_delay: Option<Duration>,
+}
 
-    Ok(response)
+impl RobotsRules {
+    // Nothing may be fetched, used when robots.txt is unreachable (RFC 9309 2.3.1.4)
+    fn disallow_all() -> Self {
+        Self {
+            disallow: vec!["/".to_string()],
+            ..Self::default()
+        }
+    }
+
+    // Parses robots.txt, keeping the groups for our product token, or the `*`
+    // groups when none name us
+    fn parse(text: &str) -> Self {
+        let mut ours = Self::default();
+        let mut wildcard = Self::default();
+        let mut named_us = false;
+        let (mut applies_to_us, mut applies_to_all) = (false, false);
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key == "user-agent" {
+                if in_rules {
+                    applies_to_us = false;
+                    applies_to_all = false;
+                    in_rules = false;
+                }
+                let agent = value.to_ascii_lowercase();
+                if agent == "*" {
+                    applies_to_all = true;
+                } else if agent == ROBOTS_AGENT {
+                    applies_to_us = true;
+                    named_us = true;
+                }
+                continue;
+            }
+            in_rules = true;
+            for (rules, applies) in [(&mut ours, applies_to_us), (&mut wildcard, applies_to_all)] {
+                if !applies {
+                    continue;
+                }
+                match key.as_str() {
+                    "allow" if !value.is_empty() => rules.allow.push(value.to_string()),
+                    "disallow" if !value.is_empty() => rules.disallow.push(value.to_string()),
+                    "crawl-delay" => {
+                        if let Ok(secs) = value.parse::<f64>() {
+                            if secs.is_finite() && secs >= 0.0 {
+                                rules.crawl_delay = Some(Duration::from_secs_f64(secs));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if named_us {
+            ours
+        } else {
+            wildcard
+        }
+    }
+
+    // The longest matching rule decides; `allow` wins a tie and no match allows
+    fn is_allowed(&self, path: &str) -> bool {
+        let longest = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter(|pattern| robots_match(pattern, path))
+                .map(|pattern| pattern.len())
+                .max()
+        };
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(allow), Some(disallow)) => allow >= disallow,
+        }
+    }
+}
+
+// Matches a robots.txt path pattern: a prefix match where `*` stands for any run
+// of characters and a trailing `$` anchors the end of the path
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    fn matches(pattern: &str, path: &str, anchored: bool) -> bool {
+        match pattern.split_once('*') {
+            None if anchored => path == pattern,
+            None => path.starts_with(pattern),
+            Some((prefix, rest)) => match path.strip_prefix(prefix) {
+                Some(tail) => tail
+                    .char_indices()
+                    .map(|(split, _)| split)
+                    .chain(std::iter::once(tail.len()))
+                    .any(|split| matches(rest, &tail[split..], anchored)),
+                None => false,
+            },
+        }
+    }
+    matches(pattern, path, anchored)
+}
+
+// Splits a raw HTTP response into its status code and body
+fn parse_response(response: &str) -> Option<(u16, &str)> {
+    let status = response.split_whitespace().nth(1)?.parse().ok()?;
+    let body = response.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+    Some((status, body))
+}
+
+// Rules from `/robots.txt` on `host`: a 4xx means no rules, anything else
+// unsuccessful means the whole site is off limits
+fn fetch_robots<F>(host: &str, fetch: &F) -> RobotsRules
+where
+    F: Fn(&str, &str) -> std::io::Result<String>,
+{
+    match fetch(host, "/robots.txt").as_deref().map(parse_response) {
+        Ok(Some((200..=299, body))) => RobotsRules::parse(body),
+        Ok(Some((400..=499, _))) => RobotsRules::default(),
+        _ => RobotsRules::disallow_all(),
+    }
+}
+
+// Spaces out requests per host: each fetch reserves a slot at least `interval`
+// after the host's previous one and waits for it outside the lock, so other hosts
+// are never held up
+struct HostRateLimiter {
+    last_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostRateLimiter {
+    fn new() -> Self {
+        Self {
+            last_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn wait_turn(&self, host: &str, interval: Duration) {
+        let now = Instant::now();
+        let slot = {
+            let mut last_slot = self.last_slot.lock().unwrap();
+            let slot = last_slot
+                .get(host)
+                .map_or(now, |&last| (last + interval).max(now));
+            last_slot.insert(host.to_string(), slot);
+            slot
+        };
+        sleep(slot.saturating_duration_since(now));
+    }
+}
+
+// Manual HTTP GET request via TCP, bounded in time and size
+fn manual_http_get(host: &str, path: &str) -> std::io::Result<String> {
+    http_client::http_get(
+        host,
+        80,
+        path,
+        "Mozilla/5.0 (OSINT Aggregator)",
+        &http_client::Limits::default(),
+    )
 }
 
 // Basic HTML title extraction (manual parsing)
@@ -57,33 +251,279 @@ fn secure_log(entry: &str) -> std::io::Result<()> {
 }
 
 // OSINT scraping function with Zero Trust sanitization
-fn perform_osint(host: &str, path: &str) -> std::io::Result<()> {
+fn fetch_profile<F>(host: &str, path: &str, fetch: &F) -> OsintProfile
+where
+    F: Fn(&str, &str) -> std::io::Result<String>,
+{
     println!("Fetching OSINT data from: {}{}", host, path);
+    let (title, error) = match fetch(host, path) {
+        Ok(response) => (extract_title(&response), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    OsintProfile {
+        host: host.to_string(),
+        path: path.to_string(),
+        title,
+        error,
+        disallowed: false,
+    }
+}
 
-    let response = manual_http_get(host, path)?;
+// Fetches every target with at most `config.workers` requests in flight. Each
+// host's robots.txt is read once first; disallowed paths are skipped and requests
+// to a host are spaced by its crawl-delay or `config.host_interval`, whichever is
+// longer. Profiles come back in target order.
+fn fetch_profiles<F>(targets: &[(&str, &str)], config: PoolConfig, fetch: F) -> Vec<OsintProfile>
+where
+    F: Fn(&str, &str) -> std::io::Result<String> + Sync,
+{
+    let limiter = HostRateLimiter::new();
+    let robots: Mutex<HashMap<&str, Arc<OnceLock<RobotsRules>>>> = Mutex::new(HashMap::new());
+    let next_target = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; targets.len()]);
 
-    if let Some(title) = extract_title(&response) {
-        println!("Extracted Title: {}", title);
-        secure_log(&format!("Host: {} - Title: {}", host, title))?;
-    } else {
-        println!("No title found.");
-        secure_log(&format!("Host: {} - Title not found", host))?;
-    }
+    thread::scope(|scope| {
+        for _ in 0..config.workers.clamp(1, targets.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next_target.fetch_add(1, Ordering::Relaxed);
+                let (host, path) = match targets.get(index) {
+                    Some(&target) => target,
+                    None => break,
+                };
+                let profile = if config.ignore_robots {
+                    limiter.wait_turn(host, config.host_interval);
+                    fetch_profile(host, path, &fetch)
+                } else {
+                    // One worker fetches a host's rules; others wanting them wait on the cell
+                    let cell = Arc::clone(robots.lock().unwrap().entry(host).or_default());
+                    let rules = cell.get_or_init(|| {
+                        limiter.wait_turn(host, config.host_interval);
+                        fetch_robots(host, &fetch)
+                    });
+                    if rules.is_allowed(path) {
+                        let delay = rules.crawl_delay.unwrap_or_default();
+                        limiter.wait_turn(host, delay.max(config.host_interval));
+                        fetch_profile(host, path, &fetch)
+                    } else {
+                        OsintProfile {
+                            host: host.to_string(),
+                            path: path.to_string(),
+                            title: None,
+                            error: None,
+                            disallowed: true,
+                        }
+                    }
+                };
+                results.lock().unwrap()[index] = Some(profile);
+            });
+        }
+    });
 
-    Ok(())
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// Records a profile in the OSINT log
+fn log_profile(profile: &OsintProfile) -> std::io::Result<()> {
+    if profile.disallowed {
+        println!(
+            "Skipped {}{}: disallowed by robots.txt",
+            profile.host, profile.path
+        );
+        return secure_log(&format!(
+            "Host: {} - Path {} skipped (robots.txt)",
+            profile.host, profile.path
+        ));
+    }
+    match (&profile.title, &profile.error) {
+        (_, Some(error)) => {
+            eprintln!("Error fetching from {}: {}", profile.host, error);
+            Ok(())
+        }
+        (Some(title), None) => {
+            println!("Extracted Title ({}): {}", profile.host, title);
+            secure_log(&format!("Host: {} - Title: {}", profile.host, title))
+        }
+        (None, None) => {
+            println!("No title found for {}.", profile.host);
+            secure_log(&format!("Host: {} - Title not found", profile.host))
+        }
+    }
 }
 
 // Main OSINT routine
 fn main() {
     let targets = vec![("example.com", "/"), ("openai.com", "/"), ("ietf.org", "/")];
 
-    for (host, path) in targets {
-        match perform_osint(host, path) {
-            Ok(_) => println!("Successfully fetched from {}", host),
-            Err(e) => eprintln!("Error fetching from {}: {}", host, e),
+    let config = PoolConfig {
+        ignore_robots: std::env::var(IGNORE_ROBOTS_ENV).is_ok_and(|value| value == "1"),
+        ..PoolConfig::default()
+    };
+    if config.ignore_robots {
+        println!(
+            "[WARNING] {}=1: robots.txt is ignored; only run authorized scans",
+            IGNORE_ROBOTS_ENV
+        );
+    }
+
+    let profiles = fetch_profiles(&targets, config, manual_http_get);
+    for profile in &profiles {
+        if let Err(e) = log_profile(profile) {
+            eprintln!("Failed to log OSINT result for {}: {}", profile.host, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn page(title: &str) -> io::Result<String> {
+        Ok(format!(
+            "HTTP/1.1 200 OK\r\n\r\n<html><title>{}</title></html>",
+            title
+        ))
+    }
+
+    #[test]
+    fn every_target_is_fetched_with_at_most_the_configured_workers() {
+        let targets: Vec<(String, &str)> =
+            (0..8).map(|i| (format!("host{}.test", i), "/")).collect();
+        let targets: Vec<(&str, &str)> = targets
+            .iter()
+            .map(|(host, path)| (host.as_str(), *path))
+            .collect();
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let config = PoolConfig {
+            workers: 3,
+            host_interval: Duration::ZERO,
+            ignore_robots: true,
+        };
+
+        let profiles = fetch_profiles(&targets, config, |host, _path| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            sleep(Duration::from_millis(20));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            if host == "host5.test" {
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+            }
+            page(host)
+        });
+
+        assert_eq!(profiles.len(), targets.len());
+        for (profile, (host, path)) in profiles.iter().zip(&targets) {
+            assert_eq!(
+                (profile.host.as_str(), profile.path.as_str()),
+                (*host, *path)
+            );
+            if *host == "host5.test" {
+                assert_eq!(profile.title, None);
+                assert_eq!(profile.error.as_deref(), Some("refused"));
+            } else {
+                assert_eq!(profile.title.as_deref(), Some(*host));
+                assert_eq!(profile.error, None);
+            }
         }
+        let peak = peak.load(Ordering::SeqCst);
+        assert!(peak <= 3, "{} fetches in flight", peak);
+        assert!(peak > 1, "fetches never overlapped");
+    }
+
+    const SAMPLE_ROBOTS: &str = "\
+# Sample policy
+User-agent: *
+Disallow: /private
+Allow: /private/press
+Crawl-delay: 5
+
+User-agent: someone-else
+Disallow: /
+";
+
+    #[test]
+    fn robots_rules_are_parsed_for_the_wildcard_group() {
+        let rules = RobotsRules::parse(SAMPLE_ROBOTS);
+        assert_eq!(rules.crawl_delay, Some(Duration::from_secs(5)));
+        assert!(!rules.is_allowed("/private"));
+        assert!(!rules.is_allowed("/private/keys.txt"));
+        assert!(rules.is_allowed("/private/press/release.html"));
+        assert!(rules.is_allowed("/"));
+        assert!(rules.is_allowed("/about"));
+
+        // A group naming us replaces the wildcard groups
+        let ours = RobotsRules::parse(&format!(
+            "{}\nUser-agent: {}\nDisallow: /tmp$\n",
+            SAMPLE_ROBOTS, ROBOTS_AGENT
+        ));
+        assert_eq!(ours.crawl_delay, None);
+        assert!(ours.is_allowed("/private"));
+        assert!(!ours.is_allowed("/tmp"));
+        assert!(ours.is_allowed("/tmp/file"));
+    }
+
+    #[test]
+    fn disallowed_paths_are_skipped_and_the_crawl_delay_spaces_requests() {
+        let robots = SAMPLE_ROBOTS.replace("Crawl-delay: 5", "Crawl-delay: 0.2");
+        let fetched = Mutex::new(Vec::new());
+        let targets = [
+            ("site.test", "/"),
+            ("site.test", "/private"),
+            ("site.test", "/about"),
+        ];
+        let config = PoolConfig {
+            workers: 3,
+            host_interval: Duration::ZERO,
+            ignore_robots: false,
+        };
+
+        let profiles = fetch_profiles(&targets, config, |_host, path| {
+            if path == "/robots.txt" {
+                return Ok(format!("HTTP/1.1 200 OK\r\n\r\n{}", robots));
+            }
+            fetched
+                .lock()
+                .unwrap()
+                .push((path.to_string(), Instant::now()));
+            page(path)
+        });
+
+        let skipped: Vec<bool> = profiles.iter().map(|profile| profile.disallowed).collect();
+        assert_eq!(skipped, [false, true, false]);
+        let fetched = fetched.into_inner().unwrap();
+        let mut paths: Vec<&str> = fetched.iter().map(|(path, _)| path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["/", "/about"]);
+        let gap = fetched[1].1.duration_since(fetched[0].1);
+        assert!(
+            gap >= Duration::from_millis(190),
+            "requests only {:?} apart",
+            gap
+        );
+    }
 
-        // Zero Trust: Explicitly rate limit each request to avoid detection
-        sleep(Duration::from_secs(10));
+    #[test]
+    fn unreachable_robots_blocks_the_host_but_a_missing_one_does_not() {
+        let fetch_with = |status: &'static str| {
+            move |_host: &str, _path: &str| Ok(format!("HTTP/1.1 {}\r\n\r\n", status))
+        };
+        assert_eq!(
+            fetch_robots("a.test", &fetch_with("404 Not Found")),
+            RobotsRules::default()
+        );
+        assert_eq!(
+            fetch_robots("a.test", &fetch_with("503 Service Unavailable")),
+            RobotsRules::disallow_all()
+        );
+        let refused = |_host: &str, _path: &str| -> io::Result<String> {
+            Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+        };
+        assert!(!fetch_robots("a.test", &refused).is_allowed("/"));
     }
 }
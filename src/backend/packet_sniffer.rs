@@ -3,10 +3,15 @@
 //! No third-party crates. Rust standard library only.
 //! Implements raw packet capturing, deep packet inspection, anomaly detection, and secure logging.
 
+#[allow(dead_code)]
+mod util;
+
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::net::{IpAddr, UdpSocket};
+use std::net::UdpSocket;
+use std::os::unix::fs::OpenOptionsExt;
 use std::time::{SystemTime, UNIX_EPOCH};
+use util::shannon_entropy;
 
 // Define constants for network protocols
 const ETH_HDR_SIZE: usize = 14;
@@ -14,27 +19,6 @@ const IPV4_HDR_MIN_SIZE: usize = 20;
 const TCP_HDR_MIN_SIZE: usize = 20;
 const UDP_HDR_SIZE: usize = 8;
 
-/// Calculate packet entropy for detecting possible encrypted data
-fn calculate_entropy(data: &[u8]) -> f64 {
-    let mut freq = [0usize; 256];
-    let len = data.len() as f64;
-
-    for &byte in data {
-        freq_table[byte as usize] += 1;
-    }
-
-    let mut entropy = 0f64;
-    for &count in freq.iter() {
-        if count == 0 {
-            continue;
-        }
-        let probability = count as f64 / data.len() as f64;
-        entropy -= probability * probability.log2();
-    }
-
-    entropy
-}
-
 /// Check packet payload for suspicious patterns (manual byte matching)
 fn check_suspicious_patterns(payload: &[u8]) -> Option<&'static str> {
     // Common signatures for suspicious activity
@@ -60,19 +44,16 @@ fn check_suspicious_patterns(payload: &[u8]) -> Option<&'static str> {
     None
 }
 
-/// Securely log detected events with timestamp
+/// Securely log detected events with timestamp, in a file only the owner can read
 fn secure_log(entry: &str) -> std::io::Result<()> {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-
-    let mut log = std::fs::OpenOptions::new()
+    let mut log = OpenOptions::new()
         .create(true)
         .append(true)
         .mode(0o600)
         .open("packet_sniffer.log")?;
 
     let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
@@ -81,96 +62,145 @@ fn secure_log(entry: &str) -> std::io::Result<()> {
     Ok(())
 }
 
+/// The TCP or UDP payload of an Ethernet frame carrying IPv4; `None` for other
+/// protocols and for frames too short for the headers they claim
+fn transport_payload(frame: &[u8]) -> Option<&[u8]> {
+    let ip_packet = frame.get(ETH_HDR_SIZE..)?;
+    if ip_packet.len() < IPV4_HDR_MIN_SIZE {
+        return None;
+    }
+
+    let protocol = ip_packet[9];
+    let header_len = ((ip_packet[0] & 0x0F) * 4) as usize;
+
+    // Only TCP and UDP are analyzed
+    match protocol {
+        6 => {
+            let tcp_header = ip_packet.get(header_len..header_len + TCP_HDR_MIN_SIZE)?;
+            let tcp_hdr_len = ((tcp_header[12] >> 4) * 4) as usize;
+            ip_packet.get(header_len + tcp_hdr_len..)
+        }
+        17 => ip_packet.get(header_len + UDP_HDR_SIZE..),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn setgid(gid: u32) -> i32;
+    fn setuid(uid: u32) -> i32;
+}
+
+/// Switches to the `nobody` user and group; the group goes first, while we may
+/// still change it
+#[cfg(unix)]
+fn drop_privileges() -> std::io::Result<()> {
+    const NOBODY: u32 = 65534;
+    // SAFETY: plain libc calls with integer arguments
+    let failed = unsafe { setgid(NOBODY) != 0 || setuid(NOBODY) != 0 };
+    if failed {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 fn main() -> std::io::Result<()> {
     // Open a raw socket for packet capturing (requires root permissions initially)
-    let socket = UdpSocket::bind("0.0.0.0:0").expect("Could not bind raw socket");
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
 
-    // Explicitly drop privileges here for Zero Trust
-    unsafe {
-        libc::setgid(65534); // nobody group
-        libc::setuid(65534); // nobody user
-    }
+    // Explicitly drop privileges here for Zero Trust; never keep capturing as root
+    #[cfg(unix)]
+    drop_privileges()?;
 
     let mut buffer = [0u8; 65536];
 
     loop {
-        match socket.recv_from(&mut buffer) {
-            Ok((size, _)) => {
-                let packet = &buffer[..size];
-
-                // Skip ethernet header
-                if buffer.len() <= ETH_HDR_SIZE {
-                    continue;
-                }
-                let ip_packet = &buffer[ETH_HDR_SIZE..];
-
-                if ip_packet.len() < IPV4_HDR_MIN_SIZE {
-                    continue;
-                }
-
-                let protocol = ip_packet[9];
-                let header_len = ((ip_packet[0] & 0x0F) * 4) as usize;
-
-                // Only TCP and UDP are analyzed in this example
-                let payload_offset = ETH_HDR_SIZE + header_len;
-
-                let payload = match protocol {
-                    6 => {
-                        // TCP
-                        if ip_packet.len() < header_len + TCP_HDR_MIN_SIZE {
-                            continue;
-                        }
-                        let tcp_hdr_len = ((ip_packet[header_len + 12] >> 4) * 4) as usize;
-                        &ip_packet[header_len + tcp_hdr_len..]
-                    }
-                    17 => {
-                        // UDP
-                        &ip_packet[header_len + UDP_HDR_SIZE..]
-                    }
-                    _ => continue,
-                };
-
-                // Perform entropy calculation
-                let entropy = calculate_entropy(payload);
-
-                if entropy > 7.5 {
-                    println!("[!] High entropy detected: Potential encrypted data stream");
-                    secure_log(&format!(
-                        "High entropy packet detected: Entropy = {:.2}",
-                        entropy
-                    ));
-                }
-
-                // Check for suspicious byte patterns
-                check_suspicious_patterns(payload).map(|alert_msg| {
-                    println!("[!] Alert: {}", alert_msg);
-                    let _ = secure_log(format!(
-                        "ALERT ({}): Pattern found in packet payload.",
-                        alert_msg
-                    ));
-                });
+        let size = match socket.recv_from(&mut buffer) {
+            Ok((size, _)) => size,
+            Err(e) => {
+                eprintln!("[!] Capture error: {}", e);
+                continue;
             }
+        };
+        let payload = match transport_payload(&buffer[..size]) {
+            Some(payload) => payload,
+            None => continue,
+        };
+
+        // Perform entropy calculation
+        let entropy = shannon_entropy(payload);
+
+        if entropy > 7.5 {
+            println!("[!] High entropy detected: Potential encrypted data stream");
+            let _ = secure_log(&format!(
+                "High entropy packet detected: Entropy = {:.2}",
+                entropy
+            ));
+        }
+
+        // Check for suspicious byte patterns
+        if let Some(alert_msg) = check_suspicious_patterns(payload) {
+            println!("[!] Alert: {}", alert_msg);
+            let _ = secure_log(&format!(
+                "ALERT ({}): Pattern found in packet payload.",
+                alert_msg
+            ));
         }
     }
 }
 
-/// Secure log with tamper-evident timestamps and restricted permissions
-fn secure_log(entry: String) -> std::io::Result<()> {
-    use std::fs::OpenOptions;
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let log_entry = format!("[{}] {}\n", timestamp, entry);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Ethernet frame holding an IPv4 packet of `protocol` with `transport` after the IP header
+    fn frame(protocol: u8, transport: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; ETH_HDR_SIZE];
+        let mut ip_header = [0u8; IPV4_HDR_MIN_SIZE];
+        ip_header[0] = 0x45; // IPv4, five 32-bit words
+        ip_header[9] = protocol;
+        frame.extend_from_slice(&ip_header);
+        frame.extend_from_slice(transport);
+        frame
+    }
 
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .mode(0o600)
-        .open("packet_sniffer.log")?;
+    #[test]
+    fn payloads_follow_the_transport_header() {
+        let mut tcp = vec![0u8; TCP_HDR_MIN_SIZE];
+        tcp[12] = 5 << 4;
+        tcp.extend_from_slice(b"GET /bin/sh");
+        assert_eq!(
+            transport_payload(&frame(6, &tcp)),
+            Some(&b"GET /bin/sh"[..])
+        );
+
+        let mut udp = vec![0u8; UDP_HDR_SIZE];
+        udp.extend_from_slice(b"dns");
+        assert_eq!(transport_payload(&frame(17, &udp)), Some(&b"dns"[..]));
+
+        assert_eq!(transport_payload(&frame(1, b"icmp")), None);
+    }
 
-    file.write_all(log_entry.as_bytes())?;
+    #[test]
+    fn truncated_frames_are_skipped_not_panicked_on() {
+        assert_eq!(transport_payload(&[0u8; ETH_HDR_SIZE]), None);
+        assert_eq!(transport_payload(&frame(6, &[0u8; 4])), None);
+        // A TCP data offset pointing past the end of the frame
+        let mut tcp = vec![0u8; TCP_HDR_MIN_SIZE];
+        tcp[12] = 15 << 4;
+        assert_eq!(transport_payload(&frame(6, &tcp)), None);
+        assert_eq!(transport_payload(&frame(17, &[0u8; 3])), None);
+    }
 
-    Ok(())
+    #[test]
+    fn suspicious_payloads_are_flagged() {
+        assert_eq!(
+            check_suspicious_patterns(b"x; /bin/sh -i"),
+            Some("Possible Unix shell invocation detected")
+        );
+        assert_eq!(check_suspicious_patterns(b"GET / HTTP/1.1"), None);
+        // Uniform random-looking bytes are what the entropy alarm is for
+        let spread: Vec<u8> = (0..=255).collect();
+        assert!(shannon_entropy(&spread) > 7.5);
+    }
 }
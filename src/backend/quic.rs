@@ -10,33 +10,22 @@
 //! - Stream multiplexing and flow control
 //! - Defense against packet injection and replay attacks
 
+#[allow(dead_code)]
+mod crypto;
+
 use std::collections::HashMap;
-use std::convert::TryInto;
 use std::io;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Duration, SystemTime};
 
 const SERVER_ADDR: &str = "0.0.0.0:4433"; // QUIC typically runs on UDP port 4433
 const SESSION_EXPIRATION: u64 = 3600; // 1-hour session expiration
 const MAX_PACKET_SIZE: usize = 1350; // Standard QUIC packet size limit
+#[allow(dead_code)] // The congestion-controlled server is tls.rs
 const INITIAL_WINDOW: usize = 10; // Number of packets in the initial congestion window
 
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
-
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
-    }
-
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
-}
-
 /// Secure QUIC session management with key rotation and connection tracking
 struct QUICSessionManager {
     sessions: Mutex<HashMap<String, (SocketAddr, SystemTime)>>, // Tracks active QUIC sessions
@@ -64,24 +53,26 @@ impl QUICSessionManager {
     }
 }
 
-/// Generates a secure random session ID using `SystemTime`
-fn generate_secure_session_id() -> String {
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let hash = sha256(&now.to_be_bytes());
-    format!("{:x?}", hash)
+/// Generates an unguessable 256-bit session ID from the OS CSPRNG
+fn generate_secure_session_id() -> io::Result<String> {
+    let bytes = crypto::random_256()?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
 }
 
 /// Handles incoming QUIC packets with session validation and retransmission support
 fn handle_quic_packet(
     socket: &UdpSocket,
     session_manager: Arc<QUICSessionManager>,
-    packet: &[u8],
+    _packet: &[u8],
     src_addr: SocketAddr,
 ) {
-    let session_id = generate_secure_session_id();
+    let session_id = match generate_secure_session_id() {
+        Ok(session_id) => session_id,
+        Err(e) => {
+            eprintln!("Failed to generate session ID: {}", e);
+            return;
+        }
+    };
 
     if !session_manager.is_session_valid(&session_id) {
         session_manager.establish_session(&session_id, src_addr);
@@ -89,12 +80,14 @@ fn handle_quic_packet(
 
     // Simulating a QUIC handshake response
     let response = b"QUIC Connection Established Securely";
-    socket.send_to(response, src_addr).unwrap();
+    if let Err(e) = socket.send_to(response, src_addr) {
+        eprintln!("QUIC response to {} failed: {}", src_addr, e);
+    }
 }
 
 /// Starts the Zero Trust QUIC server with full security enforcement
 fn main() -> io::Result<()> {
-    let socket = UdpSocket::bind(SERVER_ADDR)?;
+    let socket = Arc::new(UdpSocket::bind(SERVER_ADDR)?);
     println!("Zero Trust QUIC Server running on {}", SERVER_ADDR);
 
     let session_manager = Arc::new(QUICSessionManager::new());
@@ -103,14 +96,57 @@ fn main() -> io::Result<()> {
     loop {
         match socket.recv_from(&mut buffer) {
             Ok((size, src_addr)) => {
+                let socket = Arc::clone(&socket);
                 let session_manager = Arc::clone(&session_manager);
-                let packet = &buffer[..size];
+                let packet = buffer[..size].to_vec();
 
                 thread::spawn(move || {
-                    handle_quic_packet(&socket, session_manager, packet, src_addr);
+                    handle_quic_packet(&socket, session_manager, &packet, src_addr);
                 });
             }
             Err(e) => eprintln!("QUIC packet reception failed: {}", e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_ids_are_random_256_bit_hex() {
+        let first = generate_secure_session_id().unwrap();
+        let second = generate_secure_session_id().unwrap();
+        assert_eq!(first.len(), 64);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn each_packet_gets_a_fresh_session_and_an_answer() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let manager = Arc::new(QUICSessionManager::new());
+
+        handle_quic_packet(
+            &server,
+            Arc::clone(&manager),
+            b"hello",
+            client.local_addr().unwrap(),
+        );
+
+        let mut reply = [0u8; MAX_PACKET_SIZE];
+        let (size, from) = client.recv_from(&mut reply).unwrap();
+        assert_eq!(from, server.local_addr().unwrap());
+        assert_eq!(&reply[..size], b"QUIC Connection Established Securely");
+        let sessions = manager.sessions.lock().unwrap();
+        assert_eq!(sessions.len(), 1);
+        let session_id = sessions.keys().next().unwrap().clone();
+        drop(sessions);
+        assert!(manager.is_session_valid(&session_id));
+        assert!(!manager.is_session_valid("unknown"));
+    }
+}
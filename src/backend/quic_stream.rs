@@ -0,0 +1,542 @@
+//! Zero Trust QUIC Streams (Standard Library Only)
+//! Multiplexes independent ordered byte streams over one QUIC connection (RFC 9000 §2-4).
+//! Features:
+//! - **Stream IDs encoding initiator and direction, as on the wire**
+//! - **Per-stream reassembly of out-of-order and duplicated STREAM frames**
+//! - **Per-stream flow-control windows, reopened with MAX_STREAM_DATA as the reader drains**
+//! - **STREAM and MAX_STREAM_DATA frame encoding with variable-length integers**
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+/// Bytes a peer may send on a stream beyond what the reader has consumed
+pub const INITIAL_STREAM_WINDOW: u64 = 64 * 1024;
+const MAX_VARINT: u64 = (1 << 62) - 1;
+const FRAME_STREAM: u64 = 0x08; // Low bits: 0x04 offset present, 0x02 length present, 0x01 FIN
+const FRAME_MAX_STREAM_DATA: u64 = 0x11;
+const STREAM_BIT_OFF: u64 = 0x04;
+const STREAM_BIT_LEN: u64 = 0x02;
+const STREAM_BIT_FIN: u64 = 0x01;
+const STREAM_SERVER_INITIATED: u64 = 0x01;
+const STREAM_UNIDIRECTIONAL: u64 = 0x02;
+
+/// A stream within one connection; the low two bits carry initiator and direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StreamId(pub u64);
+
+impl StreamId {
+    pub fn is_server_initiated(self) -> bool {
+        self.0 & STREAM_SERVER_INITIATED != 0
+    }
+
+    pub fn is_bidirectional(self) -> bool {
+        self.0 & STREAM_UNIDIRECTIONAL == 0
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stream {}", self.0)
+    }
+}
+
+/// Why a stream operation or incoming frame was refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamError {
+    /// Not open on this connection, or named with this endpoint's initiator bit by the peer
+    UnknownStream(StreamId),
+    /// The peer sent past the window this endpoint advertised (FLOW_CONTROL_ERROR)
+    FlowControl(StreamId),
+    /// Data beyond the final size, or a changed final size (FINAL_SIZE_ERROR)
+    FinalSize(StreamId),
+    /// Writing after `finish`
+    Finished(StreamId),
+    /// A frame could not be decoded (FRAME_ENCODING_ERROR)
+    Encoding,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::UnknownStream(id) => write!(f, "unknown {}", id),
+            StreamError::FlowControl(id) => write!(f, "flow control limit exceeded on {}", id),
+            StreamError::FinalSize(id) => write!(f, "data past the final size of {}", id),
+            StreamError::Finished(id) => write!(f, "{} is already finished", id),
+            StreamError::Encoding => write!(f, "malformed frame"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// The frames this layer sends and consumes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Stream {
+        id: StreamId,
+        offset: u64,
+        data: Vec<u8>,
+        fin: bool,
+    },
+    /// Raises the sender's window on `id` to `limit` bytes in total
+    MaxStreamData { id: StreamId, limit: u64 },
+}
+
+impl Frame {
+    /// Appends the wire form; STREAM frames always carry offset and length
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Frame::Stream {
+                id,
+                offset,
+                data,
+                fin,
+            } => {
+                let fin_bit = if *fin { STREAM_BIT_FIN } else { 0 };
+                write_varint(
+                    out,
+                    FRAME_STREAM | STREAM_BIT_OFF | STREAM_BIT_LEN | fin_bit,
+                );
+                write_varint(out, id.0);
+                write_varint(out, *offset);
+                write_varint(out, data.len() as u64);
+                out.extend_from_slice(data);
+            }
+            Frame::MaxStreamData { id, limit } => {
+                write_varint(out, FRAME_MAX_STREAM_DATA);
+                write_varint(out, id.0);
+                write_varint(out, *limit);
+            }
+        }
+    }
+
+    /// Decodes every frame in a packet payload
+    pub fn decode_all(mut input: &[u8]) -> Result<Vec<Frame>, StreamError> {
+        let mut frames = Vec::new();
+        while !input.is_empty() {
+            frames.push(Frame::decode(&mut input)?);
+        }
+        Ok(frames)
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Frame, StreamError> {
+        let kind = read_varint(input)?;
+        if kind == FRAME_MAX_STREAM_DATA {
+            return Ok(Frame::MaxStreamData {
+                id: StreamId(read_varint(input)?),
+                limit: read_varint(input)?,
+            });
+        }
+        if kind & !0x07 != FRAME_STREAM {
+            return Err(StreamError::Encoding);
+        }
+        let id = StreamId(read_varint(input)?);
+        let offset = if kind & STREAM_BIT_OFF != 0 {
+            read_varint(input)?
+        } else {
+            0
+        };
+        let len = if kind & STREAM_BIT_LEN != 0 {
+            read_varint(input)? as usize
+        } else {
+            input.len() // Without a length the frame runs to the end of the packet
+        };
+        if len > input.len()
+            || offset
+                .checked_add(len as u64)
+                .is_none_or(|end| end > MAX_VARINT)
+        {
+            return Err(StreamError::Encoding);
+        }
+        let (data, rest) = input.split_at(len);
+        *input = rest;
+        Ok(Frame::Stream {
+            id,
+            offset,
+            data: data.to_vec(),
+            fin: kind & STREAM_BIT_FIN != 0,
+        })
+    }
+}
+
+/// QUIC variable-length integer (RFC 9000 §16); values must be below 2^62
+fn write_varint(out: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0x3f => out.push(value as u8),
+        0x40..=0x3fff => out.extend_from_slice(&(value as u16 | 0x4000).to_be_bytes()),
+        0x4000..=0x3fff_ffff => out.extend_from_slice(&(value as u32 | 0x8000_0000).to_be_bytes()),
+        _ => out.extend_from_slice(&(value | 0xc000_0000_0000_0000).to_be_bytes()),
+    }
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u64, StreamError> {
+    let first = *input.first().ok_or(StreamError::Encoding)?;
+    let len = 1usize << (first >> 6);
+    if input.len() < len {
+        return Err(StreamError::Encoding);
+    }
+    let value = input[1..len]
+        .iter()
+        .fold((first & 0x3f) as u64, |acc, &b| (acc << 8) | b as u64);
+    *input = &input[len..];
+    Ok(value)
+}
+
+/// Receive half of a stream: reassembly buffer and the window advertised to the peer
+#[derive(Default)]
+struct RecvState {
+    ready: VecDeque<u8>, // Contiguous bytes the reader has not taken yet
+    contiguous: u64,     // Offset just past the last byte moved into `ready`
+    out_of_order: BTreeMap<u64, Vec<u8>>, // Offset -> data arriving ahead of `contiguous`
+    consumed: u64,       // Bytes handed to the reader
+    limit: u64,          // Highest offset the peer may send up to
+    highest: u64,        // Offset just past the furthest byte received
+    final_size: Option<u64>,
+}
+
+impl RecvState {
+    fn new() -> Self {
+        Self {
+            limit: INITIAL_STREAM_WINDOW,
+            ..Self::default()
+        }
+    }
+
+    fn receive(
+        &mut self,
+        id: StreamId,
+        offset: u64,
+        data: &[u8],
+        fin: bool,
+    ) -> Result<(), StreamError> {
+        let end = offset + data.len() as u64;
+        if end > self.limit {
+            return Err(StreamError::FlowControl(id));
+        }
+        match self.final_size {
+            Some(size) if end > size || (fin && end != size) => {
+                return Err(StreamError::FinalSize(id))
+            }
+            None if fin && end < self.highest => return Err(StreamError::FinalSize(id)),
+            _ if fin => self.final_size = Some(end),
+            _ => {}
+        }
+        self.highest = self.highest.max(end);
+
+        // Retransmissions overlap what is already held; only the new suffix is kept
+        if end > self.contiguous {
+            let skip = self.contiguous.saturating_sub(offset) as usize;
+            let start = offset.max(self.contiguous);
+            let keep = self
+                .out_of_order
+                .get(&start)
+                .is_none_or(|held| held.len() < data.len() - skip);
+            if keep {
+                self.out_of_order.insert(start, data[skip..].to_vec());
+            }
+        }
+        while let Some((&start, _)) = self.out_of_order.first_key_value() {
+            if start > self.contiguous {
+                break;
+            }
+            let (start, chunk) = self.out_of_order.pop_first().expect("checked non-empty");
+            let skip = (self.contiguous - start) as usize;
+            if skip < chunk.len() {
+                self.ready.extend(&chunk[skip..]);
+                self.contiguous += (chunk.len() - skip) as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves up to `buf.len()` ready bytes out; returns a new window to advertise once
+    /// the reader has drained half of the current one
+    fn read(&mut self, buf: &mut [u8]) -> (usize, Option<u64>) {
+        let n = buf.len().min(self.ready.len());
+        for (slot, byte) in buf.iter_mut().zip(self.ready.drain(..n)) {
+            *slot = byte;
+        }
+        self.consumed += n as u64;
+        let remaining = self.limit - self.consumed;
+        if self.final_size.is_none() && remaining < INITIAL_STREAM_WINDOW / 2 {
+            self.limit = self.consumed + INITIAL_STREAM_WINDOW;
+            return (n, Some(self.limit));
+        }
+        (n, None)
+    }
+}
+
+/// Send half of a stream
+struct SendState {
+    offset: u64, // Bytes written so far
+    limit: u64,  // Peer's advertised MAX_STREAM_DATA
+    finished: bool,
+}
+
+struct Stream {
+    recv: RecvState,
+    send: SendState,
+}
+
+impl Stream {
+    fn new() -> Self {
+        Self {
+            recv: RecvState::new(),
+            send: SendState {
+                offset: 0,
+                limit: INITIAL_STREAM_WINDOW,
+                finished: false,
+            },
+        }
+    }
+}
+
+/// One endpoint's view of a connection's streams. Frames produced by writes and reads
+/// queue up until `poll_frames`; frames from the peer go to `receive`.
+pub struct QuicConnection {
+    is_server: bool,
+    next_stream: u64, // Index of the next locally opened bidirectional stream
+    streams: HashMap<StreamId, Stream>,
+    outgoing: VecDeque<Frame>,
+}
+
+impl QuicConnection {
+    pub fn new(is_server: bool) -> Self {
+        Self {
+            is_server,
+            next_stream: 0,
+            streams: HashMap::new(),
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    /// Opens a bidirectional stream initiated by this endpoint
+    pub fn open_stream(&mut self) -> StreamId {
+        let initiator = if self.is_server {
+            STREAM_SERVER_INITIATED
+        } else {
+            0
+        };
+        let id = StreamId(self.next_stream << 2 | initiator);
+        self.next_stream += 1;
+        self.streams.insert(id, Stream::new());
+        id
+    }
+
+    /// Streams currently open, in ID order
+    pub fn stream_ids(&self) -> Vec<StreamId> {
+        let mut ids: Vec<StreamId> = self.streams.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Queues as much of `data` as the peer's window allows; returns the bytes accepted
+    pub fn write_stream(&mut self, id: StreamId, data: &[u8]) -> Result<usize, StreamError> {
+        if !id.is_bidirectional() && id.is_server_initiated() != self.is_server {
+            return Err(StreamError::UnknownStream(id)); // The peer's send-only stream
+        }
+        let send = &mut self.stream_mut(id)?.send;
+        if send.finished {
+            return Err(StreamError::Finished(id));
+        }
+        let n = data.len().min((send.limit - send.offset) as usize);
+        if n == 0 {
+            return Ok(0);
+        }
+        let offset = send.offset;
+        send.offset += n as u64;
+        self.outgoing.push_back(Frame::Stream {
+            id,
+            offset,
+            data: data[..n].to_vec(),
+            fin: false,
+        });
+        Ok(n)
+    }
+
+    /// Marks the end of this endpoint's data on `id`
+    pub fn finish_stream(&mut self, id: StreamId) -> Result<(), StreamError> {
+        let send = &mut self.stream_mut(id)?.send;
+        if send.finished {
+            return Err(StreamError::Finished(id));
+        }
+        send.finished = true;
+        let offset = send.offset;
+        self.outgoing.push_back(Frame::Stream {
+            id,
+            offset,
+            data: Vec::new(),
+            fin: true,
+        });
+        Ok(())
+    }
+
+    /// Reads in-order bytes from `id`; 0 means nothing is ready yet (or the stream ended,
+    /// see `is_finished`)
+    pub fn read_stream(&mut self, id: StreamId, buf: &mut [u8]) -> Result<usize, StreamError> {
+        let (n, window) = self.stream_mut(id)?.recv.read(buf);
+        if let Some(limit) = window {
+            self.outgoing.push_back(Frame::MaxStreamData { id, limit });
+        }
+        Ok(n)
+    }
+
+    /// True once the peer finished `id` and every byte has been read
+    pub fn is_finished(&self, id: StreamId) -> bool {
+        self.streams
+            .get(&id)
+            .is_some_and(|stream| stream.recv.final_size == Some(stream.recv.consumed))
+    }
+
+    /// Applies a frame from the peer, opening the stream if the peer initiated it
+    pub fn receive(&mut self, frame: Frame) -> Result<(), StreamError> {
+        match frame {
+            Frame::Stream {
+                id,
+                offset,
+                data,
+                fin,
+            } => self.peer_stream(id)?.recv.receive(id, offset, &data, fin),
+            Frame::MaxStreamData { id, limit } => {
+                let send = &mut self.peer_stream(id)?.send;
+                send.limit = send.limit.max(limit); // Limits only grow; late updates are ignored
+                Ok(())
+            }
+        }
+    }
+
+    /// Frames waiting to be sent, oldest first
+    pub fn poll_frames(&mut self) -> Vec<Frame> {
+        self.outgoing.drain(..).collect()
+    }
+
+    fn stream_mut(&mut self, id: StreamId) -> Result<&mut Stream, StreamError> {
+        self.streams
+            .get_mut(&id)
+            .ok_or(StreamError::UnknownStream(id))
+    }
+
+    /// The stream a peer frame names; the peer may only create streams it initiates
+    fn peer_stream(&mut self, id: StreamId) -> Result<&mut Stream, StreamError> {
+        if !self.streams.contains_key(&id) && id.is_server_initiated() == self.is_server {
+            return Err(StreamError::UnknownStream(id));
+        }
+        Ok(self.streams.entry(id).or_insert_with(Stream::new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends `frames` through their wire encoding, as one packet payload
+    fn over_the_wire(frames: &[Frame]) -> Vec<Frame> {
+        let mut payload = Vec::new();
+        for frame in frames {
+            frame.encode(&mut payload);
+        }
+        Frame::decode_all(&payload).unwrap()
+    }
+
+    fn read_all(connection: &mut QuicConnection, id: StreamId) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let n = connection.read_stream(id, &mut buf).unwrap();
+            if n == 0 {
+                return out;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[test]
+    fn interleaved_streams_reassemble_independently() {
+        let mut client = QuicConnection::new(false);
+        let mut server = QuicConnection::new(true);
+        let (a, b) = (client.open_stream(), client.open_stream());
+        assert_eq!((a, b), (StreamId(0), StreamId(4)));
+
+        for (id, chunk) in [
+            (a, &b"alpha-1 "[..]),
+            (b, b"bravo-1 "),
+            (a, b"alpha-2 "),
+            (b, b"bravo-2 "),
+            (a, b"alpha-3"),
+        ] {
+            assert_eq!(client.write_stream(id, chunk).unwrap(), chunk.len());
+        }
+        client.finish_stream(a).unwrap();
+        client.finish_stream(b).unwrap();
+
+        // Delivered out of order, with one frame duplicated
+        let mut frames = over_the_wire(&client.poll_frames());
+        frames.reverse();
+        frames.push(frames[2].clone());
+        for frame in frames {
+            server.receive(frame).unwrap();
+        }
+
+        assert_eq!(server.stream_ids(), [a, b]);
+        assert_eq!(read_all(&mut server, a), b"alpha-1 alpha-2 alpha-3");
+        assert_eq!(read_all(&mut server, b), b"bravo-1 bravo-2 ");
+        assert!(server.is_finished(a) && server.is_finished(b));
+    }
+
+    #[test]
+    fn writes_stop_at_the_peer_window_until_it_is_raised() {
+        let mut client = QuicConnection::new(false);
+        let mut server = QuicConnection::new(true);
+        let id = client.open_stream();
+        let data = vec![0x5a; INITIAL_STREAM_WINDOW as usize + 100];
+
+        assert_eq!(
+            client.write_stream(id, &data).unwrap(),
+            INITIAL_STREAM_WINDOW as usize
+        );
+        assert_eq!(client.write_stream(id, &data).unwrap(), 0);
+        for frame in over_the_wire(&client.poll_frames()) {
+            server.receive(frame).unwrap();
+        }
+
+        // Draining the stream reopens the window with MAX_STREAM_DATA
+        assert_eq!(
+            read_all(&mut server, id).len(),
+            INITIAL_STREAM_WINDOW as usize
+        );
+        for frame in over_the_wire(&server.poll_frames()) {
+            assert!(matches!(frame, Frame::MaxStreamData { .. }));
+            client.receive(frame).unwrap();
+        }
+        assert_eq!(client.write_stream(id, &data[..100]).unwrap(), 100);
+    }
+
+    #[test]
+    fn peers_cannot_overrun_windows_or_claim_our_streams() {
+        let mut server = QuicConnection::new(true);
+        let id = StreamId(0);
+        let past_window = Frame::Stream {
+            id,
+            offset: INITIAL_STREAM_WINDOW,
+            data: vec![1],
+            fin: false,
+        };
+        assert_eq!(
+            server.receive(past_window),
+            Err(StreamError::FlowControl(id))
+        );
+
+        let server_stream = StreamId(1);
+        let claimed = Frame::Stream {
+            id: server_stream,
+            offset: 0,
+            data: vec![1],
+            fin: false,
+        };
+        assert_eq!(
+            server.receive(claimed),
+            Err(StreamError::UnknownStream(server_stream))
+        );
+        assert_eq!(Frame::decode_all(&[0x08]), Err(StreamError::Encoding));
+    }
+}
@@ -2,6 +2,10 @@
 //! Implements high-performance rate limiting to prevent API abuse and DoS attacks.
 //! Features:
 //! - **Per-IP and per-user request throttling**
+//! - **Per-route limits, so login can be far stricter than static content**
+//! - **Pluggable counter store, shared across server instances by UDP gossip**
+//! - **Allowlist for health checks and internal services**
+//! - **Real client address from `X-Forwarded-For`, honored only from trusted proxies**
 //! - **Exponential backoff for abusive request patterns**
 //! - **Dynamic rate limiting based on risk scoring**
 //! - **Detection of high-velocity API attacks**
@@ -10,54 +14,178 @@
 //! - **Request bursting detection with automated cooldown periods**
 //! - **Tamper-proof logging for abuse tracking**
 
-use std::collections::HashMap;
+#[allow(dead_code)]
+#[path = "rate_store.rs"]
+pub(crate) mod rate_store;
+#[allow(dead_code)]
+#[path = "util.rs"]
+mod util;
+
+use rate_store::{GossipStore, LocalStore, RateStore, DEFAULT_GOSSIP_INTERVAL};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
+use util::lock_or_recover;
+pub(crate) use util::Cidr;
 
 const MAX_REQUESTS_PER_MINUTE: u64 = 100;
 const BLOCK_DURATION: u64 = 300; // 5 minutes
 const BURST_THRESHOLD: u64 = 50; // Detects request bursts
+const DEFAULT_ROUTE: &str = ""; // Bucket shared by every route without its own limit
+
+/// How many requests a client may make per window, and the penalty for exceeding it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Limit {
+    pub(crate) max_requests: u64, // Exceeding this blocks the client for `block`
+    pub(crate) burst: u64,        // Requests past this are refused but do not block
+    pub(crate) window: Duration,
+    pub(crate) block: Duration,
+}
+
+impl Limit {
+    /// `max_requests` per `window`, with the default block duration and no separate burst cap
+    pub(crate) fn new(max_requests: u64, window: Duration) -> Self {
+        Self {
+            max_requests,
+            burst: max_requests,
+            window,
+            block: Duration::from_secs(BLOCK_DURATION),
+        }
+    }
+}
 
-/// Tracks rate limits per IP and user
-struct RateLimiter {
-    clients: Mutex<HashMap<String, (u64, SystemTime, bool)>>,
+impl Default for Limit {
+    fn default() -> Self {
+        Self {
+            max_requests: MAX_REQUESTS_PER_MINUTE,
+            burst: BURST_THRESHOLD,
+            window: Duration::from_secs(60),
+            block: Duration::from_secs(BLOCK_DURATION),
+        }
+    }
+}
+
+/// Tracks rate limits per IP and user, separately for each route with its own limit
+pub(crate) struct RateLimiter {
+    store: Box<dyn RateStore>, // Request counts, possibly cluster-wide
+    blocked: Mutex<HashMap<(String, String), SystemTime>>, // (Route, client) -> blocked until
+    routes: HashMap<String, Limit>,
+    default_limit: Limit,
+    allowlist: HashSet<String>, // Identifiers that are never limited
+    trusted_proxies: Vec<Cidr>, // Peers whose `X-Forwarded-For` is believed
 }
 
 impl RateLimiter {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
+        Self::new_with_routes(HashMap::new())
+    }
+
+    /// Applies `routes[path]` to requests for that exact path; every other path shares
+    /// one bucket per client under the default limit
+    pub(crate) fn new_with_routes(routes: HashMap<String, Limit>) -> Self {
         Self {
-            clients: Mutex::new(HashMap::new()),
+            store: Box::new(LocalStore::new()),
+            blocked: Mutex::new(HashMap::new()),
+            routes,
+            default_limit: Limit::default(),
+            allowlist: HashSet::new(),
+            trusted_proxies: Vec::new(),
         }
     }
 
-    /// Checks if a request should be allowed based on rate limits
-    fn allow_request(&self, identifier: &str) -> bool {
-        let mut clients = self.clients.lock().unwrap();
-        let (count, last_request, is_blocked) =
-            clients
-                .entry(identifier.to_string())
-                .or_insert((0, SystemTime::now(), false));
+    /// Exempts `identifiers` (such as a health checker's address) from every limit
+    pub(crate) fn with_allowlist<I, S>(mut self, identifiers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowlist
+            .extend(identifiers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Believes `X-Forwarded-For` from peers in `proxies` when identifying clients
+    pub(crate) fn with_trusted_proxies(mut self, proxies: Vec<Cidr>) -> Self {
+        self.trusted_proxies = proxies;
+        self
+    }
 
-        if *is_blocked
-            && last_request.elapsed().unwrap_or(Duration::new(0, 0))
-                < Duration::new(BLOCK_DURATION, 0)
-        {
-            return false;
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// The address to limit a request by: `peer`, unless `peer` is a trusted proxy, in
+    /// which case the nearest address in `forwarded_for` that is not itself a trusted
+    /// proxy. Entries are read right to left because each proxy appends the address it
+    /// saw, so anything left of the last untrusted hop may have been forged by the client.
+    pub(crate) fn client_identifier(
+        &self,
+        peer: Option<IpAddr>,
+        forwarded_for: Option<&str>,
+    ) -> String {
+        let mut client = match peer {
+            Some(peer) => peer,
+            None => return "unknown".to_string(),
+        };
+        if let Some(forwarded_for) = forwarded_for {
+            for hop in forwarded_for.rsplit(',') {
+                if !self.is_trusted_proxy(client) {
+                    break;
+                }
+                client = match hop.trim().parse::<IpAddr>() {
+                    Ok(ip) => ip,
+                    Err(_) => break, // Unparseable hop: stop at the last proxy we trust
+                };
+            }
         }
+        client.to_string()
+    }
+
+    /// Keeps request counts in `store`, such as a `GossipStore` shared with other nodes,
+    /// instead of in this process
+    pub(crate) fn with_store(mut self, store: Box<dyn RateStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Checks if a request should be allowed based on the default limit
+    pub(crate) fn allow_request(&self, identifier: &str) -> bool {
+        self.allow_request_for(DEFAULT_ROUTE, identifier)
+    }
 
-        if last_request.elapsed().unwrap_or(Duration::new(60, 0)) > Duration::new(60, 0) {
-            *count = 0;
-            *last_request = SystemTime::now();
+    /// Checks a request to `route`, under that route's limit if it has one
+    pub(crate) fn allow_request_for(&self, route: &str, identifier: &str) -> bool {
+        if self.allowlist.contains(identifier) {
+            return true;
+        }
+        let (route, limit) = match self.routes.get_key_value(route) {
+            Some((route, limit)) => (route.as_str(), *limit),
+            None => (DEFAULT_ROUTE, self.default_limit),
+        };
+        let client = (route.to_string(), identifier.to_string());
+        {
+            // Expired blocks are dropped here so clients that never return do not linger
+            let mut blocked = lock_or_recover(&self.blocked, "rate limiter");
+            let now = SystemTime::now();
+            blocked.retain(|_, until| now < *until);
+            if blocked.contains_key(&client) {
+                return false;
+            }
         }
 
-        *count += 1;
-        if *count > MAX_REQUESTS_PER_MINUTE {
-            *is_blocked = true;
-            *last_request = SystemTime::now();
+        // Counted outside the block lock, since a gossiping store may be slow. The store
+        // key puts the route first; routes never contain spaces
+        let count = self
+            .store
+            .increment(&format!("{} {}", route, identifier), limit.window);
+        if count > limit.max_requests {
+            lock_or_recover(&self.blocked, "rate limiter")
+                .insert(client, SystemTime::now() + limit.block);
             return false;
         }
 
-        if *count > BURST_THRESHOLD {
+        if count > limit.burst {
             return false; // Block high-velocity API abuse
         }
 
@@ -76,4 +204,194 @@ fn main() {
             println!("Request blocked from {}", user_ip);
         }
     }
+
+    let mut routes = HashMap::new();
+    routes.insert("/login".to_string(), Limit::new(5, Duration::from_secs(60)));
+    let rate_limiter = Arc::new(RateLimiter::new_with_routes(routes));
+
+    for route in ["/login", "/"] {
+        for _ in 0..10 {
+            if rate_limiter.allow_request_for(route, user_ip) {
+                println!("Request to {} allowed from {}", route, user_ip);
+            } else {
+                println!("Request to {} blocked from {}", route, user_ip);
+            }
+        }
+    }
+
+    // Health checks are never limited; behind the load balancer, clients are told apart
+    let rate_limiter = RateLimiter::new()
+        .with_allowlist(["10.0.0.5"])
+        .with_trusted_proxies(Cidr::parse("10.0.0.0/8").into_iter().collect());
+    let proxy = "10.0.0.2".parse().ok();
+    let client = rate_limiter.client_identifier(proxy, Some("203.0.113.7, 10.0.0.3"));
+    println!("Client behind the proxy: {}", client);
+    let health_checks = (0..200)
+        .filter(|_| rate_limiter.allow_request("10.0.0.5"))
+        .count();
+    println!("Health checks allowed: {}/200", health_checks);
+
+    // Two nodes sharing one /login allowance, when a gossip secret is configured
+    let secret = match std::env::var("ZTW_RATE_LIMIT_SECRET") {
+        Ok(secret) => secret.into_bytes(),
+        Err(_) => return,
+    };
+    let interval = DEFAULT_GOSSIP_INTERVAL;
+    let nodes = (
+        GossipStore::bind("127.0.0.1:0", Vec::new(), secret.clone(), interval),
+        GossipStore::bind("127.0.0.1:0", Vec::new(), secret, interval),
+    );
+    let (first, second) = match nodes {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("[RATE] Gossip demo could not bind: {}", e);
+            return;
+        }
+    };
+    if let (Ok(first_addr), Ok(second_addr)) = (first.local_addr(), second.local_addr()) {
+        first.add_peer(second_addr);
+        second.add_peer(first_addr);
+    }
+    let routes = || HashMap::from([("/login".to_string(), Limit::new(5, Duration::from_secs(60)))]);
+    let cluster = [
+        RateLimiter::new_with_routes(routes()).with_store(Box::new(first)),
+        RateLimiter::new_with_routes(routes()).with_store(Box::new(second)),
+    ];
+    for (node, limiter) in cluster.iter().enumerate() {
+        for _ in 0..4 {
+            let allowed = limiter.allow_request_for("/login", user_ip);
+            println!(
+                "Node {} login from {} allowed: {}",
+                node + 1,
+                user_ip,
+                allowed
+            );
+        }
+        std::thread::sleep(interval * 3);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login_limited() -> RateLimiter {
+        let mut routes = HashMap::new();
+        routes.insert("/login".to_string(), Limit::new(3, Duration::from_secs(60)));
+        RateLimiter::new_with_routes(routes)
+    }
+
+    #[test]
+    fn strict_route_blocks_while_lenient_route_still_allows() {
+        let limiter = login_limited();
+        let client = "198.51.100.4";
+        let logins: Vec<bool> = (0..5)
+            .map(|_| limiter.allow_request_for("/login", client))
+            .collect();
+        assert_eq!(logins, [true, true, true, false, false]);
+
+        // The same client is still within the default limit everywhere else
+        assert!((0..20).all(|_| limiter.allow_request_for("/static/app.js", client)));
+        assert!(limiter.allow_request_for("/", client));
+        assert!(!limiter.allow_request_for("/login", client));
+    }
+
+    #[test]
+    fn routes_without_a_limit_share_one_bucket() {
+        let limiter = login_limited();
+        let client = "198.51.100.5";
+        for i in 0..BURST_THRESHOLD {
+            let route = if i % 2 == 0 { "/a" } else { "/b" };
+            assert!(limiter.allow_request_for(route, client));
+        }
+        assert!(!limiter.allow_request_for("/c", client));
+        assert!(!limiter.allow_request(client));
+
+        // The strict route keeps its own count
+        assert!(limiter.allow_request_for("/login", client));
+    }
+
+    #[test]
+    fn gossiping_nodes_enforce_one_shared_limit() {
+        let interval = Duration::from_millis(20);
+        let bind =
+            || GossipStore::bind("127.0.0.1:0", Vec::new(), vec![9u8; 32], interval).unwrap();
+        let (first, second) = (bind(), bind());
+        first.add_peer(second.local_addr().unwrap());
+        second.add_peer(first.local_addr().unwrap());
+        let routes = || {
+            HashMap::from([(
+                "/login".to_string(),
+                Limit::new(4, Duration::from_secs(600)),
+            )])
+        };
+        let first = RateLimiter::new_with_routes(routes()).with_store(Box::new(first));
+        let second = RateLimiter::new_with_routes(routes()).with_store(Box::new(second));
+
+        let client = "203.0.113.20";
+        assert!((0..3).all(|_| first.allow_request_for("/login", client)));
+        std::thread::sleep(interval * 10);
+        assert!(second.allow_request_for("/login", client));
+        assert!(!second.allow_request_for("/login", client));
+    }
+
+    #[test]
+    fn allowlisted_identifiers_are_never_blocked() {
+        let limiter = login_limited().with_allowlist(["10.0.0.5"]);
+        assert!((0..500).all(|_| limiter.allow_request("10.0.0.5")));
+        assert!((0..50).all(|_| limiter.allow_request_for("/login", "10.0.0.5")));
+        assert!(!(0..500).all(|_| limiter.allow_request("10.0.0.6")));
+    }
+
+    #[test]
+    fn expired_blocks_are_pruned() {
+        let limit = Limit {
+            block: Duration::from_millis(20),
+            ..Limit::new(1, Duration::from_secs(60))
+        };
+        let limiter = RateLimiter::new_with_routes(HashMap::from([("/login".to_string(), limit)]));
+        assert!(limiter.allow_request_for("/login", "198.51.100.6"));
+        assert!(!limiter.allow_request_for("/login", "198.51.100.6"));
+        assert_eq!(lock_or_recover(&limiter.blocked, "test").len(), 1);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(limiter.allow_request_for("/login", "198.51.100.7"));
+        assert!(lock_or_recover(&limiter.blocked, "test").is_empty());
+    }
+
+    fn behind_proxy() -> RateLimiter {
+        RateLimiter::new().with_trusted_proxies(Cidr::parse("10.0.0.0/8").into_iter().collect())
+    }
+
+    #[test]
+    fn forwarded_for_is_honored_only_from_trusted_peers() {
+        let limiter = behind_proxy();
+        let proxy = "10.0.0.2".parse().ok();
+        let outsider = "198.51.100.8".parse().ok();
+        let forwarded = Some("203.0.113.7");
+        assert_eq!(limiter.client_identifier(proxy, forwarded), "203.0.113.7");
+        assert_eq!(
+            limiter.client_identifier(outsider, forwarded),
+            "198.51.100.8"
+        );
+        assert_eq!(limiter.client_identifier(proxy, None), "10.0.0.2");
+        assert_eq!(limiter.client_identifier(None, forwarded), "unknown");
+        assert_eq!(
+            RateLimiter::new().client_identifier(proxy, forwarded),
+            "10.0.0.2"
+        );
+    }
+
+    #[test]
+    fn forged_hops_left_of_the_first_untrusted_address_are_ignored() {
+        let limiter = behind_proxy();
+        let proxy = "10.0.0.2".parse().ok();
+        // The client claimed 1.2.3.4; the proxies saw 203.0.113.7 and then 10.0.0.3
+        let chain = Some("1.2.3.4, 203.0.113.7, 10.0.0.3");
+        assert_eq!(limiter.client_identifier(proxy, chain), "203.0.113.7");
+        assert_eq!(
+            limiter.client_identifier(proxy, Some("garbage, 10.0.0.3")),
+            "10.0.0.3"
+        );
+    }
 }
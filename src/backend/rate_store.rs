@@ -0,0 +1,418 @@
+//! Zero Trust Rate Limit State (Standard Library Only)
+//! Where `RateLimiter` keeps its request counters, so that several server instances
+//! can enforce one limit between them instead of each granting the full allowance.
+//! Features:
+//! - **`RateStore` trait over fixed windows aligned to the Unix epoch, so nodes agree on them**
+//! - **`LocalStore`: in-process counters, the default**
+//! - **`GossipStore`: counters exchanged with peers over UDP, every datagram HMAC-SHA256 signed**
+//! - **Each node gossips only its own counts and peers keep the highest seen, so repeated or
+//!   reordered datagrams never double-count**
+
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "util.rs"]
+mod util;
+
+use crypto::{constant_time_eq, Zeroizing};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use util::lock_or_recover;
+
+pub(crate) const DEFAULT_GOSSIP_INTERVAL: Duration = Duration::from_millis(500);
+const GOSSIP_MAGIC: &str = "ZTRL1";
+const TAG_LEN: usize = 32; // HMAC-SHA256
+const MAX_GOSSIP_PAYLOAD: usize = 1200; // Stays under a typical path MTU, so no fragmentation
+const MIN_GOSSIP_SECRET: usize = 32;
+
+/// Shared request counters for the rate limiter
+pub(crate) trait RateStore: Send + Sync {
+    /// Counts one request for `key` and returns how many requests for `key` have been
+    /// seen in the current `window`, on every node sharing this store
+    fn increment(&self, key: &str, window: Duration) -> u64;
+}
+
+/// Requests counted for one key in one window
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Counter {
+    window_ms: u64,
+    index: u64, // Milliseconds since the epoch divided by `window_ms`
+    count: u64,
+}
+
+/// `(window_ms, index)` of the window containing now
+fn current_window(window: Duration) -> (u64, u64) {
+    let window_ms = (window.as_millis() as u64).max(1);
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    (window_ms, now_ms / window_ms)
+}
+
+/// Counters held in this process only
+#[derive(Default)]
+pub(crate) struct LocalStore {
+    counters: Mutex<HashMap<String, Counter>>,
+}
+
+impl LocalStore {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// This node's counters for their current windows; counters from past windows are dropped
+    fn snapshot(&self) -> Vec<(String, Counter)> {
+        let mut counters = lock_or_recover(&self.counters, "rate store");
+        counters.retain(|_, counter| {
+            current_window(Duration::from_millis(counter.window_ms)).1 == counter.index
+        });
+        counters
+            .iter()
+            .map(|(key, counter)| (key.clone(), *counter))
+            .collect()
+    }
+}
+
+impl RateStore for LocalStore {
+    fn increment(&self, key: &str, window: Duration) -> u64 {
+        let (window_ms, index) = current_window(window);
+        let mut counters = lock_or_recover(&self.counters, "rate store");
+        let counter = counters.entry(key.to_string()).or_insert(Counter {
+            window_ms,
+            index,
+            count: 0,
+        });
+        if counter.window_ms != window_ms || counter.index != index {
+            *counter = Counter {
+                window_ms,
+                index,
+                count: 0,
+            };
+        }
+        counter.count += 1;
+        counter.count
+    }
+}
+
+/// State shared between a `GossipStore` and its background thread
+struct GossipState {
+    node_id: u64,
+    socket: UdpSocket,
+    peers: Mutex<Vec<SocketAddr>>,
+    secret: Zeroizing<Vec<u8>>,
+    local: LocalStore,
+    remote: Mutex<HashMap<String, HashMap<u64, Counter>>>, // Key -> node id -> that node's count
+    shutdown: AtomicBool,
+}
+
+/// Counters shared with peer nodes. Each node adds its peers' latest counts to its own,
+/// so a limit holds across the cluster to within one gossip interval.
+pub(crate) struct GossipStore {
+    state: Arc<GossipState>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl GossipStore {
+    /// Listens for gossip on `addr` and sends this node's counts to `peers` every
+    /// `interval`. Every node must share `secret`; unsigned datagrams are ignored.
+    pub(crate) fn bind(
+        addr: impl ToSocketAddrs,
+        peers: Vec<SocketAddr>,
+        secret: Vec<u8>,
+        interval: Duration,
+    ) -> io::Result<Self> {
+        let secret = Zeroizing::new(secret);
+        if secret.len() < MIN_GOSSIP_SECRET {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "gossip secret is {} bytes; at least {} are required",
+                    secret.len(),
+                    MIN_GOSSIP_SECRET
+                ),
+            ));
+        }
+        let socket = UdpSocket::bind(addr)?;
+        let interval = interval.max(Duration::from_millis(10));
+        socket.set_read_timeout(Some(interval))?;
+        let mut node_id = [0u8; 8];
+        crypto::random_bytes(&mut node_id)?;
+
+        let state = Arc::new(GossipState {
+            node_id: u64::from_be_bytes(node_id),
+            socket,
+            peers: Mutex::new(peers),
+            secret,
+            local: LocalStore::new(),
+            remote: Mutex::new(HashMap::new()),
+            shutdown: AtomicBool::new(false),
+        });
+        let worker = {
+            let state = Arc::clone(&state);
+            thread::Builder::new()
+                .name("rate-gossip".to_string())
+                .spawn(move || state.run(interval))?
+        };
+        Ok(Self {
+            state,
+            worker: Some(worker),
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.state.socket.local_addr()
+    }
+
+    /// Starts sending counts to `peer` as well
+    pub(crate) fn add_peer(&self, peer: SocketAddr) {
+        let mut peers = lock_or_recover(&self.state.peers, "gossip peers");
+        if !peers.contains(&peer) {
+            peers.push(peer);
+        }
+    }
+}
+
+impl RateStore for GossipStore {
+    fn increment(&self, key: &str, window: Duration) -> u64 {
+        let own = self.state.local.increment(key, window);
+        let (window_ms, index) = current_window(window);
+        let remote = lock_or_recover(&self.state.remote, "gossip counters");
+        let peers: u64 = remote
+            .get(key)
+            .into_iter()
+            .flat_map(|nodes| nodes.values())
+            .filter(|counter| counter.window_ms == window_ms && counter.index == index)
+            .map(|counter| counter.count)
+            .sum();
+        own + peers
+    }
+}
+
+impl Drop for GossipStore {
+    fn drop(&mut self) {
+        self.state.shutdown.store(true, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl GossipState {
+    fn run(&self, interval: Duration) {
+        let mut buffer = [0u8; TAG_LEN + MAX_GOSSIP_PAYLOAD];
+        let mut last_sent = Instant::now() - interval;
+        while !self.shutdown.load(Ordering::SeqCst) {
+            if last_sent.elapsed() >= interval {
+                self.send_counts();
+                self.prune_remote();
+                last_sent = Instant::now();
+            }
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, _)) => self.receive(&buffer[..len]),
+                Err(e) if util::is_timeout(&e) => {}
+                Err(e) => eprintln!("[RATE] Gossip receive failed: {}", e),
+            }
+        }
+    }
+
+    /// Sends every current counter of this node to every peer, split across datagrams
+    fn send_counts(&self) {
+        let peers = lock_or_recover(&self.peers, "gossip peers").clone();
+        if peers.is_empty() {
+            return;
+        }
+        let header = format!("{} {}\n", GOSSIP_MAGIC, self.node_id);
+        let mut body = header.clone();
+        for (key, counter) in self.local.snapshot() {
+            if key.contains('\n') || key.len() + header.len() + 64 > MAX_GOSSIP_PAYLOAD {
+                continue; // Cannot be framed; still enforced locally
+            }
+            let line = format!(
+                "{} {} {} {}\n",
+                counter.window_ms, counter.index, counter.count, key
+            );
+            if body.len() + line.len() > MAX_GOSSIP_PAYLOAD {
+                self.send_datagram(&body, &peers);
+                body.clone_from(&header);
+            }
+            body.push_str(&line);
+        }
+        if body.len() > header.len() {
+            self.send_datagram(&body, &peers);
+        }
+    }
+
+    /// Signs `body` and sends it to each peer; an unreachable peer does not stop the rest
+    fn send_datagram(&self, body: &str, peers: &[SocketAddr]) {
+        let mut datagram = crypto::hmac_sha256(&self.secret, body.as_bytes()).to_vec();
+        datagram.extend_from_slice(body.as_bytes());
+        for peer in peers {
+            if let Err(e) = self.socket.send_to(&datagram, peer) {
+                eprintln!("[RATE] Gossip to {} failed: {}", peer, e);
+            }
+        }
+    }
+
+    /// Merges a peer's counters, keeping the highest count seen for each window
+    fn receive(&self, datagram: &[u8]) {
+        if datagram.len() < TAG_LEN {
+            return;
+        }
+        let (tag, body) = datagram.split_at(TAG_LEN);
+        if !constant_time_eq(tag, &crypto::hmac_sha256(&self.secret, body)) {
+            eprintln!("[RATE] Dropped gossip datagram with a bad signature");
+            return;
+        }
+        let body = match std::str::from_utf8(body) {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+        let mut lines = body.lines();
+        let node_id = match lines
+            .next()
+            .and_then(|line| line.strip_prefix(GOSSIP_MAGIC))
+            .and_then(|id| id.trim().parse::<u64>().ok())
+        {
+            Some(node_id) if node_id != self.node_id => node_id,
+            _ => return,
+        };
+
+        let mut remote = lock_or_recover(&self.remote, "gossip counters");
+        for (counter, key) in lines.filter_map(parse_counter) {
+            let known = remote
+                .entry(key.to_string())
+                .or_default()
+                .entry(node_id)
+                .or_insert(counter);
+            let replace = if (counter.window_ms, counter.index) == (known.window_ms, known.index) {
+                counter.count > known.count
+            } else {
+                counter.window_ms != known.window_ms || counter.index > known.index
+            };
+            if replace {
+                *known = counter;
+            }
+        }
+    }
+
+    /// Forgets peer counters whose windows have ended
+    fn prune_remote(&self) {
+        let mut remote = lock_or_recover(&self.remote, "gossip counters");
+        remote.retain(|_, nodes| {
+            nodes.retain(|_, counter| {
+                current_window(Duration::from_millis(counter.window_ms)).1 == counter.index
+            });
+            !nodes.is_empty()
+        });
+    }
+}
+
+/// One `<window_ms> <index> <count> <key>` gossip line
+fn parse_counter(line: &str) -> Option<(Counter, &str)> {
+    let mut fields = line.splitn(4, ' ');
+    let window_ms = fields.next()?.parse().ok()?;
+    let index = fields.next()?.parse().ok()?;
+    let count = fields.next()?.parse().ok()?;
+    let counter = Counter {
+        window_ms,
+        index,
+        count,
+    };
+    Some((counter, fields.next()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+    const WINDOW: Duration = Duration::from_secs(600);
+
+    /// Two stores on loopback gossiping with each other
+    fn gossiping_pair() -> (GossipStore, GossipStore) {
+        let secret = vec![7u8; MIN_GOSSIP_SECRET];
+        let first = GossipStore::bind("127.0.0.1:0", Vec::new(), secret.clone(), INTERVAL).unwrap();
+        let second = GossipStore::bind("127.0.0.1:0", Vec::new(), secret, INTERVAL).unwrap();
+        first.add_peer(second.local_addr().unwrap());
+        second.add_peer(first.local_addr().unwrap());
+        (first, second)
+    }
+
+    /// Counts requests for `key` on `store`, which has already counted `own` itself, until
+    /// at least `peers` requests from other nodes show up; returns its own total and the
+    /// combined count it last reported
+    fn count_until_gossiped(
+        store: &GossipStore,
+        key: &str,
+        mut own: u64,
+        peers: u64,
+    ) -> (u64, u64) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            own += 1;
+            let combined = store.increment(key, WINDOW);
+            if combined >= own + peers || Instant::now() > deadline {
+                return (own, combined);
+            }
+            thread::sleep(INTERVAL);
+        }
+    }
+
+    #[test]
+    fn local_store_counts_per_key() {
+        let store = LocalStore::new();
+        assert_eq!(store.increment("a", WINDOW), 1);
+        assert_eq!(store.increment("a", WINDOW), 2);
+        assert_eq!(store.increment("b", WINDOW), 1);
+    }
+
+    #[test]
+    fn gossiped_counts_are_combined_on_both_nodes() {
+        let (first, second) = gossiping_pair();
+        let key = "/login 203.0.113.9";
+        for _ in 0..3 {
+            first.increment(key, WINDOW);
+        }
+        let (second_own, combined) = count_until_gossiped(&second, key, 0, 3);
+        assert_eq!(combined, second_own + 3);
+        let (first_own, combined) = count_until_gossiped(&first, key, 3, second_own);
+        assert_eq!(combined, first_own + second_own);
+    }
+
+    #[test]
+    fn repeated_gossip_does_not_double_count() {
+        let (first, second) = gossiping_pair();
+        first.increment("k", WINDOW);
+        first.increment("k", WINDOW);
+        let (own, combined) = count_until_gossiped(&second, "k", 0, 2);
+        assert_eq!(combined, own + 2);
+        thread::sleep(INTERVAL * 5); // Several more rounds of the same counts
+        assert_eq!(second.increment("k", WINDOW), own + 3);
+    }
+
+    #[test]
+    fn gossip_under_another_secret_is_ignored() {
+        let first = GossipStore::bind("127.0.0.1:0", Vec::new(), vec![1u8; 32], INTERVAL).unwrap();
+        let second = GossipStore::bind("127.0.0.1:0", Vec::new(), vec![2u8; 32], INTERVAL).unwrap();
+        first.add_peer(second.local_addr().unwrap());
+        first.increment("k", WINDOW);
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(second.increment("k", WINDOW), 1);
+    }
+
+    #[test]
+    fn short_secrets_are_refused() {
+        let result = GossipStore::bind("127.0.0.1:0", Vec::new(), vec![0u8; 8], INTERVAL);
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+    }
+}
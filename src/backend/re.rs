@@ -2,80 +2,619 @@
 
 //! Zero Trust Reverse Engineering Framework
 //! Implements advanced binary static analysis using only Rust standard library.
-//! Features: Manual ELF parsing, opcode decoding, entropy analysis, vulnerability signature detection
+//! Features: ELF/PE/Mach-O format detection, manual ELF parsing with file type, machine and entry point, opcode decoding with disassembled context, entropy analysis, vulnerability signature detection,
+//! dynamic import/export listing with dangerous-import flags, ASCII/UTF-16LE string extraction,
+//! and a `--json` report for pipelines
 
-use std::{collections::HashMap, env, fs::File, io::Read};
+#[allow(dead_code)]
+mod disasm;
+#[allow(dead_code)]
+mod elf;
+#[cfg(test)]
+#[allow(dead_code)]
+mod json; // Parses `--json` reports back in tests
+#[allow(dead_code)]
+mod util;
 
-const ELF_MAGIC: &[u8; 4] = b"\x7FELF";
+use elf::{Elf, ElfInfo, Section, Symbol, ELF_MAGIC};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs, io,
+};
+use util::shannon_entropy;
+
+const CONTEXT_INSTRUCTIONS: usize = 3; // Decoded before and after each signature hit
+const CONTEXT_WINDOW: usize = 32; // Bytes swept before a hit looking for an aligned start
+const MAX_HITS_PER_SIGNATURE: usize = 5;
+const MIN_STRING_LEN: usize = 6; // Shorter runs are mostly coincidental bytes
+const MAX_STRINGS: usize = 10_000; // Distinct strings kept per binary
+const PE_HEADER_POINTER: usize = 0x3c; // e_lfanew in the DOS header
+const MACHO_MAX_FAT_ARCHES: u32 = 0x20; // Java class files share 0xcafebabe but carry a version here
+const HIGH_ENTROPY: f64 = 7.5; // Bits per byte above which a binary is likely packed or encrypted
+
+/// Container format identified from a binary's magic bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryFormat {
+    Elf,
+    /// `pe_offset` is where the `PE\0\0` signature sits
+    Pe {
+        pe_offset: usize,
+    },
+    MachO {
+        bits: u8,
+        big_endian: bool,
+    },
+    /// Universal binary holding `arches` Mach-O slices
+    MachOFat {
+        arches: u32,
+    },
+    Unknown,
+}
+
+impl BinaryFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            BinaryFormat::Elf => "ELF",
+            BinaryFormat::Pe { .. } => "PE",
+            BinaryFormat::MachO { .. } => "Mach-O",
+            BinaryFormat::MachOFat { .. } => "Mach-O universal",
+            BinaryFormat::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StringEncoding {
+    Ascii,
+    Utf16Le,
+}
+
+/// A printable run found in a binary; `offset` is its first byte in the file
+#[derive(Debug, Clone, PartialEq)]
+struct ExtractedString {
+    offset: usize,
+    encoding: StringEncoding,
+    text: String,
+}
+
+/// A signature match and the instructions decoded around it
+#[derive(Debug, Clone, PartialEq)]
+struct SignatureHit {
+    description: &'static str,
+    offset: usize,
+    context: Vec<disasm::Instruction>,
+}
+
+/// Everything the analysis passes found in one binary
+#[derive(Debug, Clone, PartialEq)]
+struct AnalysisReport {
+    size: usize,
+    format: BinaryFormat,
+    header: Option<String>, // One-line summary of the format's header
+    elf: Option<ElfInfo>,
+    entropy: f64,
+    sections: Vec<Section>,
+    imports: Vec<Symbol>,
+    exports: Vec<Symbol>,
+    strings: Vec<ExtractedString>,
+    hits: Vec<SignatureHit>, // By offset
+    /// Parts of the binary that could not be read; the other passes still ran
+    errors: Vec<String>,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let json = args.iter().skip(1).any(|arg| arg == "--json");
+    let paths: Vec<&String> = args.iter().skip(1).filter(|arg| *arg != "--json").collect();
 
-    if args.len() != 2 {
-        eprintln!("Usage: {} <binary>", args[0]);
+    if paths.len() != 1 {
+        eprintln!("Usage: {} [--json] <binary>", args[0]);
         return;
     }
 
-    let binary_path = &args[1];
-    let mut file = File::open(binary_path).expect("Failed to open binary");
+    let report = match analyze(paths[0]) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("[-] Cannot read {}: {}", paths[0], e);
+            std::process::exit(1);
+        }
+    };
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print_report(&report);
+    }
+}
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .expect("Failed to read binary");
+/// Runs every pass over the binary at `path`. Only ELF has a full parser; other
+/// formats get a header summary and the format-independent passes.
+fn analyze(path: &str) -> io::Result<AnalysisReport> {
+    let buffer = fs::read(path)?;
+    let mut report = AnalysisReport {
+        size: buffer.len(),
+        format: detect_format(&buffer),
+        header: None,
+        elf: None,
+        entropy: shannon_entropy(&buffer),
+        sections: Vec::new(),
+        imports: Vec::new(),
+        exports: Vec::new(),
+        strings: extract_strings(&buffer, MIN_STRING_LEN),
+        hits: signature_hits(&buffer),
+        errors: Vec::new(),
+    };
 
-    if &buffer[..4] != ELF_MAGIC {
-        eprintln!("Not an ELF binary");
-        return;
+    let header = match report.format {
+        BinaryFormat::Elf => {
+            elf_tables(&buffer, &mut report);
+            ElfInfo::parse(&buffer)
+                .map_err(|e| format!("Cannot read ELF header: {}", e))
+                .map(|info| {
+                    let summary = elf_summary(&info);
+                    report.elf = Some(info);
+                    summary
+                })
+        }
+        BinaryFormat::Pe { pe_offset } => pe_summary(&buffer, pe_offset),
+        BinaryFormat::MachO { bits, big_endian } => macho_summary(&buffer, bits, big_endian),
+        BinaryFormat::MachOFat { arches } => Ok(format!("Universal binary with {} slices", arches)),
+        BinaryFormat::Unknown => Err("Unknown binary format".to_string()),
+    };
+    match header {
+        Ok(header) => report.header = Some(header),
+        Err(e) => report.errors.push(e),
     }
+    Ok(report)
+}
 
-    println!("[+] Valid ELF binary detected");
-
-    entropy_analysis(&buffer);
-    opcode_scanner(&buffer);
+/// Reads the ELF section list and dynamic symbols into `report`
+fn elf_tables(buffer: &[u8], report: &mut AnalysisReport) {
+    let elf = match Elf::parse(buffer) {
+        Ok(elf) => elf,
+        Err(e) => {
+            report.errors.push(format!("Cannot read sections: {}", e));
+            return;
+        }
+    };
+    report.sections = elf.sections().to_vec();
+    match elf.imports() {
+        Ok(imports) => report.imports = imports,
+        Err(e) => report.errors.push(format!("Cannot read imports: {}", e)),
+    }
+    match elf.exports() {
+        Ok(exports) => report.exports = exports,
+        Err(e) => report.errors.push(format!("Cannot read exports: {}", e)),
+    }
 }
 
-fn entropy_analysis(buffer: &[u8]) {
+/// The prose report
+fn print_report(report: &AnalysisReport) {
+    if report.format == BinaryFormat::Unknown {
+        eprintln!("[-] Unknown binary format");
+        return;
+    }
+    println!("[+] Detected format: {}", report.format.name());
+    if let Some(header) = &report.header {
+        println!("[+] {}", header);
+    }
+
     println!("[+] Performing entropy analysis...");
+    println!("[+] Entropy: {:.4} bits per byte", report.entropy);
+    if report.entropy > HIGH_ENTROPY {
+        println!("[!] High entropy detected: Possibly packed or encrypted");
+    } else {
+        println!("[+] Entropy within normal range");
+    }
+
+    println!("[+] Scanning for suspicious opcode sequences...");
+    let mut descriptions: Vec<&str> = Vec::new();
+    for hit in &report.hits {
+        if !descriptions.contains(&hit.description) {
+            descriptions.push(hit.description);
+        }
+    }
+    for description in descriptions {
+        println!("[!] Potential vulnerability detected: {}", description);
+        for hit in report
+            .hits
+            .iter()
+            .filter(|hit| hit.description == description)
+        {
+            for instruction in &hit.context {
+                let marker = if instruction.offset == hit.offset {
+                    "=>"
+                } else {
+                    "  "
+                };
+                println!(
+                    "    {} 0x{:08x}  {}",
+                    marker, instruction.offset, instruction.text
+                );
+            }
+        }
+    }
+    println!("[+] Opcode scan completed");
 
-    let mut freq = [0u32; 256];
-    for &byte in buffer.iter() {
-        freq[byte as usize] += 1;
+    if report.format == BinaryFormat::Elf {
+        println!("[+] Reading dynamic symbol tables...");
+        println!("[+] {} imported symbols", report.imports.len());
+        for symbol in &report.imports {
+            if symbol.dangerous {
+                println!(
+                    "[!] Dangerous import: {} (slot 0x{:x})",
+                    symbol.name, symbol.address
+                );
+            } else {
+                println!("    import {}", symbol.name);
+            }
+        }
+        println!("[+] {} exported symbols", report.exports.len());
+        for symbol in &report.exports {
+            println!("    export {} @ 0x{:x}", symbol.name, symbol.address);
+        }
     }
+    for error in &report.errors {
+        eprintln!("[-] {}", error);
+    }
+
+    println!("[+] Extracting strings...");
+    for found in report
+        .strings
+        .iter()
+        .filter(|found| found.text.contains("://") || found.text.starts_with('/'))
+    {
+        println!(
+            "    0x{:08x} {:?} {}",
+            found.offset, found.encoding, found.text
+        );
+    }
+    println!(
+        "[+] {} distinct strings (URLs and paths shown)",
+        report.strings.len()
+    );
+}
 
-    let mut entropy = 0.0;
-    let length = buffer.len() as f64;
+impl AnalysisReport {
+    /// One JSON document. Addresses are hex strings, since JSON numbers lose
+    /// precision past 2^53.
+    fn to_json(&self) -> String {
+        let elf = match &self.elf {
+            Some(info) => format!(
+                "{{\"class\":{},\"endian\":\"{}\",\"type\":\"{}\",\"machine\":{},\"machine_name\":{},\"entry\":\"0x{:x}\"}}",
+                if info.is_64 { 64 } else { 32 },
+                if info.little_endian { "little" } else { "big" },
+                info.file_type,
+                info.machine,
+                info.machine_name()
+                    .map_or("null".to_string(), |name| format!("\"{}\"", name)),
+                info.entry
+            ),
+            None => "null".to_string(),
+        };
+        let sections: Vec<String> = self
+            .sections
+            .iter()
+            .map(|section| {
+                format!(
+                    "{{\"name\":\"{}\",\"type\":{},\"address\":\"0x{:x}\",\"offset\":{},\"size\":{}}}",
+                    json_escape(&section.name),
+                    section.kind,
+                    section.addr,
+                    section.offset,
+                    section.size
+                )
+            })
+            .collect();
+        let symbols = |symbols: &[Symbol]| -> String {
+            let symbols: Vec<String> = symbols
+                .iter()
+                .map(|symbol| {
+                    format!(
+                        "{{\"name\":\"{}\",\"kind\":\"{:?}\",\"binding\":\"{:?}\",\"address\":\"0x{:x}\",\"dangerous\":{}}}",
+                        json_escape(&symbol.name),
+                        symbol.kind,
+                        symbol.binding,
+                        symbol.address,
+                        symbol.dangerous
+                    )
+                })
+                .collect();
+            format!("[{}]", symbols.join(","))
+        };
+        let strings: Vec<String> = self
+            .strings
+            .iter()
+            .map(|found| {
+                format!(
+                    "{{\"offset\":{},\"encoding\":\"{:?}\",\"text\":\"{}\"}}",
+                    found.offset,
+                    found.encoding,
+                    json_escape(&found.text)
+                )
+            })
+            .collect();
+        let hits: Vec<String> = self
+            .hits
+            .iter()
+            .map(|hit| {
+                let context: Vec<String> = hit
+                    .context
+                    .iter()
+                    .map(|instruction| {
+                        format!(
+                            "{{\"offset\":{},\"text\":\"{}\"}}",
+                            instruction.offset,
+                            json_escape(&instruction.text)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"description\":\"{}\",\"offset\":{},\"context\":[{}]}}",
+                    json_escape(hit.description),
+                    hit.offset,
+                    context.join(",")
+                )
+            })
+            .collect();
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|error| format!("\"{}\"", json_escape(error)))
+            .collect();
+        format!(
+            "{{\"format\":\"{}\",\"size\":{},\"header\":{},\"elf\":{},\"entropy\":{:.4},\"high_entropy\":{},\"sections\":[{}],\"imports\":{},\"exports\":{},\"strings\":[{}],\"signature_hits\":[{}],\"errors\":[{}]}}",
+            self.format.name(),
+            self.size,
+            self.header
+                .as_ref()
+                .map_or("null".to_string(), |header| format!("\"{}\"", json_escape(header))),
+            elf,
+            self.entropy,
+            self.entropy > HIGH_ENTROPY,
+            sections.join(","),
+            symbols(&self.imports),
+            symbols(&self.exports),
+            strings.join(","),
+            hits.join(","),
+            errors.join(",")
+        )
+    }
+}
 
-    for &count in freq.iter() {
-        if count > 0 {
-            let probability = (count as f64) / length;
-            entropy -= probability * probability.log2();
+fn detect_format(buffer: &[u8]) -> BinaryFormat {
+    let magic = match buffer.get(..4) {
+        Some(magic) => magic,
+        None => return BinaryFormat::Unknown,
+    };
+    if magic == ELF_MAGIC {
+        return BinaryFormat::Elf;
+    }
+    if magic.starts_with(b"MZ") {
+        // A bare MZ is a DOS stub; only a valid e_lfanew pointing at `PE\0\0` makes it PE
+        let pe_offset = read_u32(buffer, PE_HEADER_POINTER, false).map(|offset| offset as usize);
+        if let Some(pe_offset) = pe_offset {
+            if buffer.get(pe_offset..pe_offset.saturating_add(4)) == Some(b"PE\0\0") {
+                return BinaryFormat::Pe { pe_offset };
+            }
         }
+        return BinaryFormat::Unknown;
+    }
+    match u32::from_be_bytes([magic[0], magic[1], magic[2], magic[3]]) {
+        0xfeedface => BinaryFormat::MachO {
+            bits: 32,
+            big_endian: true,
+        },
+        0xcefaedfe => BinaryFormat::MachO {
+            bits: 32,
+            big_endian: false,
+        },
+        0xfeedfacf => BinaryFormat::MachO {
+            bits: 64,
+            big_endian: true,
+        },
+        0xcffaedfe => BinaryFormat::MachO {
+            bits: 64,
+            big_endian: false,
+        },
+        0xcafebabe => match read_u32(buffer, 4, true) {
+            Some(arches) if arches > 0 && arches < MACHO_MAX_FAT_ARCHES => {
+                BinaryFormat::MachOFat { arches }
+            }
+            _ => BinaryFormat::Unknown,
+        },
+        _ => BinaryFormat::Unknown,
     }
+}
 
-    println!("[+] Entropy: {:.4} bits per byte", entropy);
+fn read_u16(buffer: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = buffer
+        .get(offset..offset.checked_add(2)?)?
+        .try_into()
+        .ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
 
-    if entropy > 7.5 {
-        println!("[!] High entropy detected: Possibly packed or encrypted");
+fn read_u32(buffer: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = buffer
+        .get(offset..offset.checked_add(4)?)?
+        .try_into()
+        .ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
     } else {
-        println!("[+] Entropy within normal range");
-    }
+        u32::from_le_bytes(bytes)
+    })
 }
 
-fn opcode_scanner(buffer: &[u8]) {
-    println!("[+] Scanning for suspicious opcode sequences...");
+/// What the ELF header says the file is, where it runs, and where it starts
+fn elf_summary(info: &ElfInfo) -> String {
+    let machine = match info.machine_name() {
+        Some(name) => name.to_string(),
+        None => format!("machine 0x{:04x}", info.machine),
+    };
+    format!(
+        "ELF {}-bit {}-endian {} for {}, entry 0x{:x}",
+        if info.is_64 { 64 } else { 32 },
+        if info.little_endian { "little" } else { "big" },
+        info.file_type,
+        machine,
+        info.entry
+    )
+}
+
+/// Summarizes the COFF file header fields that follow the PE signature
+fn pe_summary(buffer: &[u8], pe_offset: usize) -> Result<String, String> {
+    let coff = pe_offset + 4;
+    let (machine, sections) = match (
+        read_u16(buffer, coff, false),
+        read_u16(buffer, coff + 2, false),
+    ) {
+        (Some(machine), Some(sections)) => (machine, sections),
+        _ => return Err("Truncated COFF header".to_string()),
+    };
+    let machine = match machine {
+        0x014c => "i386".to_string(),
+        0x8664 => "x86-64".to_string(),
+        0x01c4 => "ARMv7".to_string(),
+        0xaa64 => "ARM64".to_string(),
+        other => format!("0x{:04x}", other),
+    };
+    Ok(format!("PE machine: {}, {} sections", machine, sections))
+}
+
+/// Summarizes the Mach-O header fields shared by the 32- and 64-bit layouts
+fn macho_summary(buffer: &[u8], bits: u8, big_endian: bool) -> Result<String, String> {
+    let field = |index: usize| read_u32(buffer, 4 + index * 4, big_endian);
+    let (cpu_type, file_type, commands) = match (field(0), field(2), field(3)) {
+        (Some(cpu_type), Some(file_type), Some(commands)) => (cpu_type, file_type, commands),
+        _ => return Err("Truncated Mach-O header".to_string()),
+    };
+    let cpu = match cpu_type {
+        7 => "x86".to_string(),
+        0x0100_0007 => "x86-64".to_string(),
+        12 => "ARM".to_string(),
+        0x0100_000c => "ARM64".to_string(),
+        other => format!("0x{:x}", other),
+    };
+    let kind = match file_type {
+        1 => "object",
+        2 => "executable",
+        6 => "dylib",
+        8 => "bundle",
+        _ => "other",
+    };
+    Ok(format!(
+        "Mach-O {}-bit {} for {}, {} load commands",
+        bits, kind, cpu, commands
+    ))
+}
+
+fn is_printable(byte: u8) -> bool {
+    (0x20..0x7f).contains(&byte) || byte == b'\t'
+}
+
+/// Finds printable ASCII and UTF-16LE runs of at least `min_len` characters, in file
+/// order. A string seen again later is dropped, and at most `MAX_STRINGS` are returned.
+fn extract_strings(buffer: &[u8], min_len: usize) -> Vec<ExtractedString> {
+    let min_len = min_len.max(1);
+    let mut found = Vec::new();
+
+    let mut start = 0;
+    for (index, &byte) in buffer.iter().chain(std::iter::once(&0)).enumerate() {
+        if !is_printable(byte) {
+            if index - start >= min_len {
+                found.push(ExtractedString {
+                    offset: start,
+                    encoding: StringEncoding::Ascii,
+                    text: String::from_utf8_lossy(&buffer[start..index]).into_owned(),
+                });
+            }
+            start = index + 1;
+        }
+    }
 
-    let signatures = vulnerable_signatures();
+    // UTF-16LE code units of printable ASCII are the byte followed by a zero, so each
+    // byte alignment is scanned separately
+    for alignment in 0..2 {
+        let units = buffer.get(alignment..).unwrap_or_default().chunks_exact(2);
+        let mut run = String::new();
+        let mut run_start = alignment;
+        for (index, unit) in units.map(Some).chain(std::iter::once(None)).enumerate() {
+            match unit {
+                Some(&[low, 0]) if is_printable(low) => {
+                    if run.is_empty() {
+                        run_start = alignment + index * 2;
+                    }
+                    run.push(low as char);
+                }
+                _ => {
+                    if run.len() >= min_len {
+                        found.push(ExtractedString {
+                            offset: run_start,
+                            encoding: StringEncoding::Utf16Le,
+                            text: std::mem::take(&mut run),
+                        });
+                    }
+                    run.clear();
+                }
+            }
+        }
+    }
 
-    for (pattern, description) in signatures.iter() {
-        if buffer
+    found.sort_by_key(|found| found.offset);
+    let mut seen = HashSet::new();
+    found.retain(|found| seen.insert(found.text.clone()));
+    found.truncate(MAX_STRINGS);
+    found
+}
+
+/// Every signature match, up to `MAX_HITS_PER_SIGNATURE` per signature, by offset
+fn signature_hits(buffer: &[u8]) -> Vec<SignatureHit> {
+    let mut hits = Vec::new();
+    for (pattern, description) in vulnerable_signatures() {
+        let offsets = buffer
             .windows(pattern.len())
-            .any(|window| window == pattern.as_slice())
-        {
-            println!("[!] Potential vulnerability detected: {}", description);
+            .enumerate()
+            .filter(|(_, window)| *window == pattern.as_slice())
+            .map(|(offset, _)| offset)
+            .take(MAX_HITS_PER_SIGNATURE);
+        for offset in offsets {
+            hits.push(SignatureHit {
+                description,
+                offset,
+                context: instruction_context(buffer, offset),
+            });
         }
     }
+    hits.sort_by_key(|hit| hit.offset);
+    hits
+}
 
-    println!("[+] Opcode scan completed");
+/// A few instructions around `hit`. Sweeps start up to `CONTEXT_WINDOW` bytes early
+/// and keep the first one that lands exactly on `hit`; if none does, only the
+/// instructions from `hit` on are shown.
+fn instruction_context(buffer: &[u8], hit: usize) -> Vec<disasm::Instruction> {
+    let mut before = Vec::new();
+    for start in hit.saturating_sub(CONTEXT_WINDOW)..hit {
+        let mut sweep = Vec::new();
+        let mut offset = start;
+        while offset < hit {
+            let instruction = disasm::decode(buffer, offset);
+            offset += instruction.len;
+            sweep.push(instruction);
+        }
+        if offset == hit {
+            let skip = sweep.len().saturating_sub(CONTEXT_INSTRUCTIONS);
+            before = sweep.split_off(skip);
+            break;
+        }
+    }
+    before.extend(disasm::disassemble(buffer, hit, CONTEXT_INSTRUCTIONS + 1));
+    before
 }
 
 fn vulnerable_signatures() -> HashMap<Vec<u8>, &'static str> {
@@ -94,3 +633,208 @@ fn vulnerable_signatures() -> HashMap<Vec<u8>, &'static str> {
 
     signatures
 }
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_and_utf16_runs_are_found_at_their_offsets() {
+        let mut buffer = b"\x01ab\x02".to_vec(); // Too short to count
+        buffer.extend_from_slice(b"kernel32"); // 4..12
+        buffer.extend_from_slice(b"\x00\x00");
+        for c in "Wide!".bytes() {
+            buffer.extend_from_slice(&[c, 0]); // 14..24
+        }
+        buffer.extend_from_slice(b"\x00\xffxy\x03");
+
+        assert_eq!(
+            extract_strings(&buffer, 4),
+            [
+                ExtractedString {
+                    offset: 4,
+                    encoding: StringEncoding::Ascii,
+                    text: "kernel32".to_string(),
+                },
+                ExtractedString {
+                    offset: 14,
+                    encoding: StringEncoding::Utf16Le,
+                    text: "Wide!".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn odd_aligned_utf16_and_repeats_are_handled() {
+        let mut buffer = b"\x01".to_vec();
+        for c in "odd run".bytes() {
+            buffer.extend_from_slice(&[c, 0]); // 1..15
+        }
+        buffer.extend_from_slice(b"\x00\x02repeat\x03repeat\x04");
+
+        let found = extract_strings(&buffer, 4);
+        let summary: Vec<(usize, StringEncoding, &str)> = found
+            .iter()
+            .map(|found| (found.offset, found.encoding, found.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, StringEncoding::Utf16Le, "odd run"),
+                (17, StringEncoding::Ascii, "repeat"),
+            ]
+        );
+    }
+
+    /// A DOS header whose `e_lfanew` points at `PE\0\0` and a COFF header with three sections
+    fn pe_header(machine: u16) -> Vec<u8> {
+        let mut buffer = vec![0u8; 0x80];
+        buffer[..2].copy_from_slice(b"MZ");
+        buffer[PE_HEADER_POINTER..PE_HEADER_POINTER + 4].copy_from_slice(&0x40u32.to_le_bytes());
+        buffer[0x40..0x44].copy_from_slice(b"PE\0\0");
+        buffer[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        buffer[0x46..0x48].copy_from_slice(&3u16.to_le_bytes());
+        buffer
+    }
+
+    #[test]
+    fn pe_headers_are_identified() {
+        let amd64 = pe_header(0x8664);
+        assert_eq!(detect_format(&amd64), BinaryFormat::Pe { pe_offset: 0x40 });
+        assert_eq!(
+            pe_summary(&amd64, 0x40).unwrap(),
+            "PE machine: x86-64, 3 sections"
+        );
+        assert_eq!(
+            pe_summary(&pe_header(0x14c), 0x40).unwrap(),
+            "PE machine: i386, 3 sections"
+        );
+        assert!(pe_summary(&amd64[..0x45], 0x40).is_err());
+
+        // A DOS stub without a PE signature is not PE
+        let mut stub = amd64.clone();
+        stub[0x40..0x44].copy_from_slice(b"NE\0\0");
+        assert_eq!(detect_format(&stub), BinaryFormat::Unknown);
+    }
+
+    #[test]
+    fn mach_o_headers_are_identified() {
+        // 64-bit little-endian executable for x86-64 with 12 load commands
+        let mut thin = Vec::new();
+        for field in [0xfeedfacfu32, 0x0100_0007, 3, 2, 12, 0x400, 0] {
+            thin.extend_from_slice(&field.to_le_bytes());
+        }
+        let format = detect_format(&thin);
+        assert_eq!(
+            format,
+            BinaryFormat::MachO {
+                bits: 64,
+                big_endian: false
+            }
+        );
+        assert_eq!(
+            macho_summary(&thin, 64, false).unwrap(),
+            "Mach-O 64-bit executable for x86-64, 12 load commands"
+        );
+
+        let mut fat = 0xcafebabeu32.to_be_bytes().to_vec();
+        fat.extend_from_slice(&2u32.to_be_bytes());
+        assert_eq!(detect_format(&fat), BinaryFormat::MachOFat { arches: 2 });
+        // A Java class file shares the magic but not a plausible slice count
+        let mut class = 0xcafebabeu32.to_be_bytes().to_vec();
+        class.extend_from_slice(&[0, 0, 0, 52]);
+        assert_eq!(detect_format(&class), BinaryFormat::Unknown);
+    }
+
+    #[test]
+    fn unknown_or_short_magic_reports_unknown() {
+        assert_eq!(detect_format(b"\x7fELF\x02\x01"), BinaryFormat::Elf);
+        for buffer in [&b"PK\x03\x04rest"[..], b"#!/bin/sh\n", b"\x7fEL", b""] {
+            assert_eq!(detect_format(buffer), BinaryFormat::Unknown);
+        }
+        assert_eq!(BinaryFormat::Unknown.name(), "Unknown");
+    }
+
+    #[test]
+    fn json_report_for_the_fixture_parses_back_with_every_field() {
+        let report = analyze("tests/fixtures/dynamic_imports.elf").unwrap();
+        let document = json::parse_json(&report.to_json()).unwrap();
+
+        let mut fields: Vec<&str> = document
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "elf",
+                "entropy",
+                "errors",
+                "exports",
+                "format",
+                "header",
+                "high_entropy",
+                "imports",
+                "sections",
+                "signature_hits",
+                "size",
+                "strings"
+            ]
+        );
+        assert_eq!(document.get("format").unwrap().as_str(), Some("ELF"));
+        assert_eq!(
+            document.get("size").unwrap().as_f64(),
+            Some(report.size as f64)
+        );
+        let entropy = document.get("entropy").unwrap().as_f64().unwrap();
+        assert!((entropy - report.entropy).abs() < 1e-4);
+        let elf = document.get("elf").unwrap();
+        assert_eq!(elf.get("machine_name").unwrap().as_str(), Some("x86-64"));
+        assert_eq!(
+            elf.get("type").unwrap().as_str(),
+            Some("position-independent executable")
+        );
+
+        let named = |list: &str, name: &str| {
+            document
+                .get(list)
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|entry| entry.get("name").and_then(|n| n.as_str()) == Some(name))
+                .cloned()
+        };
+        assert!(named("sections", ".dynsym").is_some());
+        let system = named("imports", "system").expect("system is imported");
+        assert_eq!(system.get("dangerous").unwrap().as_bool(), Some(true));
+        assert!(named("exports", "fixture_exported").is_some());
+        assert_eq!(
+            document.get("strings").unwrap().as_array().unwrap().len(),
+            report.strings.len()
+        );
+        assert!(document
+            .get("errors")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -2,72 +2,1642 @@
 //! Implements a high-performance, fully secure routing system with Zero Trust security policies.
 //! Features:
 //! - Dynamic routing with middleware support
+//! - CORS preflight handling with per-origin allow lists
 //! - Role-based access control (RBAC) and attribute-based access control (ABAC)
 //! - Route authentication and JWT validation
 //! - Request logging, rate limiting, and deep packet inspection (DPI)
+//! - Per-route latency percentiles and status tallies (Prometheus and folded-stack output)
 //! - Path traversal prevention and parameter sanitization
+//! - Static file serving confined to a root directory
+//! - Cookie parsing and `Set-Cookie` building with security attributes
+//! - JSON request bodies parsed with `request.json()`
+//! - `multipart/form-data` uploads parsed part by part with size limits
 //! - Secure API versioning and route isolation
 //! - Multi-threaded request handling with asynchronous execution
 //! - Load balancing and failover handling
 
-use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
+#[allow(dead_code)]
+#[path = "challenge_store.rs"]
+mod challenge_store;
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "json.rs"]
+mod json;
+#[allow(dead_code)]
+#[path = "multipart.rs"]
+mod multipart;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+#[allow(dead_code)]
+#[path = "util.rs"]
+mod util;
+
+pub use json::{parse_json, JsonError, JsonValue};
+pub use multipart::{parse_multipart, MultipartError, Part};
+
+use challenge_store::ChallengeStore;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::net::{IpAddr, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use util::lock_or_recover;
 
-const BIND_ADDRESS: &str = "0.0.0.0:8080"; // Non-TLS port for API routing
 const MAX_BODY_SIZE: usize = 1024 * 1024; // 1MB request body limit
+const MAX_BUFFERED_BODY_BYTES: usize = 64 * 1024 * 1024; // Across all requests in flight
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+const ROUTE_LATENCY_HISTORY: usize = 1024; // Latency samples kept per route
+const ROUTE_METRIC_PREFIX: &str = "ztw_route_";
+pub const CSRF_FIELD: &str = "csrf_token"; // Hidden form field carrying the token
+pub const CSRF_HEADER: &str = "X-CSRF-Token"; // Alternative for scripts submitting JSON
+const CSRF_COOKIE: &str = "csrf_id"; // Random per-browser id the tokens are bound to
+const CSRF_TOKEN_TTL: Duration = Duration::from_secs(3600);
+
+/// Fallback sequence used only if the OS CSPRNG is unavailable
+static REQUEST_ID_FALLBACK: AtomicU64 = AtomicU64::new(0);
+static BUFFERED_BODY_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Correlation id that follows one request through every middleware and log line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Creates a fresh random id (128 bits, hex)
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        if crypto::random_bytes(&mut bytes).is_err() {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            let sequence = REQUEST_ID_FALLBACK.fetch_add(1, Ordering::Relaxed);
+            bytes[..8].copy_from_slice(&nanos.to_be_bytes());
+            bytes[8..].copy_from_slice(&sequence.to_be_bytes());
+        }
+        Self(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Accepts an upstream id if it is short and limited to `[A-Za-z0-9._-]`,
+    /// so it cannot be used to inject into log lines or response headers
+    pub fn from_header(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        valid.then(|| Self(value.to_string()))
+    }
+
+    /// Propagates a valid incoming `X-Request-Id`, otherwise generates a new id
+    pub fn from_headers(headers: &HashMap<String, String>) -> Self {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(REQUEST_ID_HEADER))
+            .and_then(|(_, value)| Self::from_header(value))
+            .unwrap_or_else(Self::generate)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
 /// Represents an HTTP request
-struct HttpRequest {
-    method: String,
-    path: String,
-    headers: HashMap<String, String>,
-    body: String,
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub peer: Option<IpAddr>,      // Remote address of the connection
+    pub principal: Option<String>, // Authenticated user, set by the auth middleware
+    pub request_id: RequestId,
+    raw_body: Option<Vec<u8>>, // Exact bytes when `body` had to be decoded lossily
+    _body_reservation: Option<BodyReservation>, // Returns the body's budget share on drop
+}
+
+impl HttpRequest {
+    /// Looks up a header by name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Name/value pairs from the `Cookie` header (empty when there is none)
+    pub fn cookies(&self) -> HashMap<String, String> {
+        self.header("Cookie").map(parse_cookies).unwrap_or_default()
+    }
+
+    /// Looks up one cookie sent with the request
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.cookies().remove(name)
+    }
+
+    /// Parses the body as JSON, provided `Content-Type` declares it
+    /// (`application/json` or a `+json` type, parameters ignored)
+    pub fn json(&self) -> Result<JsonValue, JsonBodyError> {
+        let media_type = self
+            .header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .map(|media_type| media_type.trim().to_ascii_lowercase())
+            .ok_or(JsonBodyError::NotJson)?;
+        if media_type != "application/json" && !media_type.ends_with("+json") {
+            return Err(JsonBodyError::NotJson);
+        }
+        parse_json(&self.body).map_err(JsonBodyError::Invalid)
+    }
+
+    /// The body exactly as received, including bytes that are not UTF-8
+    pub fn body_bytes(&self) -> &[u8] {
+        self.raw_body.as_deref().unwrap_or(self.body.as_bytes())
+    }
+
+    /// Parses a `multipart/form-data` body using the boundary from `Content-Type`
+    pub fn multipart(&self) -> Result<Vec<Part>, MultipartError> {
+        let boundary = self
+            .header("Content-Type")
+            .and_then(multipart::boundary_from_content_type)
+            .ok_or(MultipartError::InvalidBoundary)?;
+        parse_multipart(self.body_bytes(), &boundary)
+    }
+}
+
+/// Why `HttpRequest::json` produced no value
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonBodyError {
+    /// `Content-Type` is missing or not a JSON media type
+    NotJson,
+    /// The body is not well-formed JSON
+    Invalid(JsonError),
+}
+
+impl JsonBodyError {
+    /// The status a handler should answer with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            JsonBodyError::NotJson => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonBodyError::Invalid(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for JsonBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonBodyError::NotJson => f.write_str("request body is not declared as JSON"),
+            JsonBodyError::Invalid(e) => write!(f, "invalid JSON body: {}", e),
+        }
+    }
+}
+
+/// Parses a `Cookie: a=1; b=2` header. Pairs without `=` or a name are skipped,
+/// double-quoted values are unquoted, and the first of any duplicate names wins.
+pub fn parse_cookies(header: &str) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+    for pair in header.split(';') {
+        let (name, value) = match pair.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => continue,
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        cookies
+            .entry(name.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+    cookies
+}
+
+/// HTTP status code paired with its canonical reason phrase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    pub const OK: StatusCode = StatusCode(200);
+    pub const CREATED: StatusCode = StatusCode(201);
+    pub const NO_CONTENT: StatusCode = StatusCode(204);
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
+    pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
+    pub const FOUND: StatusCode = StatusCode(302);
+    pub const NOT_MODIFIED: StatusCode = StatusCode(304);
+    pub const BAD_REQUEST: StatusCode = StatusCode(400);
+    pub const UNAUTHORIZED: StatusCode = StatusCode(401);
+    pub const FORBIDDEN: StatusCode = StatusCode(403);
+    pub const NOT_FOUND: StatusCode = StatusCode(404);
+    pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode(405);
+    pub const REQUEST_TIMEOUT: StatusCode = StatusCode(408);
+    pub const PAYLOAD_TOO_LARGE: StatusCode = StatusCode(413);
+    pub const UNSUPPORTED_MEDIA_TYPE: StatusCode = StatusCode(415);
+    pub const RANGE_NOT_SATISFIABLE: StatusCode = StatusCode(416);
+    pub const TOO_MANY_REQUESTS: StatusCode = StatusCode(429);
+    pub const INTERNAL_SERVER_ERROR: StatusCode = StatusCode(500);
+    pub const SERVICE_UNAVAILABLE: StatusCode = StatusCode(503);
+
+    /// Accepts any three-digit status (100-599)
+    pub fn from_u16(code: u16) -> Option<Self> {
+        (100..=599).contains(&code).then_some(StatusCode(code))
+    }
+
+    pub fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Canonical reason phrase; codes without one get the phrase of their class
+    pub fn reason(self) -> &'static str {
+        match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            204 => "No Content",
+            206 => "Partial Content",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            426 => "Upgrade Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            code => match code / 100 {
+                1 => "Informational",
+                2 => "Success",
+                3 => "Redirection",
+                4 => "Client Error",
+                _ => "Server Error",
+            },
+        }
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.0, self.reason())
+    }
+}
+
+/// Header names whose values are computed during serialization
+const FRAMING_HEADERS: [&str; 3] = ["Content-Length", "Transfer-Encoding", "Connection"];
+
+/// Response headers with case-insensitive names
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    map: HashMap<String, String>, // Original-case name -> value
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key_for(&self, name: &str) -> Option<String> {
+        self.map
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.key_for(name)
+            .and_then(|key| self.map.get(&key))
+            .map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.key_for(name).is_some()
+    }
+
+    /// Sets `name`, replacing any value stored under a differently-cased name
+    pub fn insert(&mut self, name: &str, value: &str) {
+        if let Some(existing) = self.key_for(name) {
+            self.map.remove(&existing);
+        }
+        self.map.insert(name.to_string(), value.to_string());
+    }
+
+    /// Adds to a comma-separated list header unless `value` is already present
+    pub fn append(&mut self, name: &str, value: &str) {
+        match self.key_for(name) {
+            Some(key) => {
+                let current = self.map.get_mut(&key).unwrap();
+                if !current
+                    .split(',')
+                    .any(|item| item.trim().eq_ignore_ascii_case(value))
+                {
+                    if !current.is_empty() {
+                        current.push_str(", ");
+                    }
+                    current.push_str(value);
+                }
+            }
+            None => self.insert(name, value),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.key_for(name).and_then(|key| self.map.remove(&key))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Raw map for middleware that already merges case-insensitively
+    pub(crate) fn as_map_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.map
+    }
+}
+
+/// Cross-site sending policy for a cookie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// A cookie to set on the client, serialized by `to_header` as a `Set-Cookie` value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+    max_age: Option<Duration>,
+    path: Option<String>,
+    domain: Option<String>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            http_only: false,
+            secure: false,
+            same_site: None,
+            max_age: None,
+            path: None,
+            domain: None,
+        }
+    }
+
+    /// The hardened defaults for session cookies: HttpOnly, Secure, SameSite=Strict, Path=/
+    pub fn session(name: &str, value: &str) -> Self {
+        Self::new(name, value)
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Strict)
+            .path("/")
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Lifetime in whole seconds; zero tells the browser to delete the cookie
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// A name must be a non-empty token and a value a run of cookie-octets (RFC 6265 4.1.1);
+    /// attribute values may not contain `;` or control characters
+    pub fn is_valid(&self) -> bool {
+        let token = |c: char| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c);
+        let octet = |c: char| c.is_ascii_graphic() && !"\",;\\".contains(c);
+        let attribute = |v: &Option<String>| {
+            v.as_deref()
+                .is_none_or(|v| !v.contains(';') && !v.chars().any(|c| c.is_control()))
+        };
+        !self.name.is_empty()
+            && self.name.chars().all(token)
+            && self.value.chars().all(octet)
+            && attribute(&self.path)
+            && attribute(&self.domain)
+    }
+
+    /// The `Set-Cookie` header value
+    pub fn to_header(&self) -> String {
+        let mut header = format!("{}={}", self.name, self.value);
+        if let Some(max_age) = self.max_age {
+            header.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if let Some(domain) = &self.domain {
+            header.push_str(&format!("; Domain={}", domain));
+        }
+        if let Some(path) = &self.path {
+            header.push_str(&format!("; Path={}", path));
+        }
+        // Browsers drop SameSite=None cookies that are not also Secure
+        if self.secure || self.same_site == Some(SameSite::None) {
+            header.push_str("; Secure");
+        }
+        if self.http_only {
+            header.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            header.push_str(match same_site {
+                SameSite::Strict => "; SameSite=Strict",
+                SameSite::Lax => "; SameSite=Lax",
+                SameSite::None => "; SameSite=None",
+            });
+        }
+        header
+    }
 }
 
 /// Represents an HTTP response
-struct HttpResponse {
-    status_code: u16,
-    body: String,
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+    pub cookies: Vec<Cookie>, // One `Set-Cookie` line each
+}
+
+impl HttpResponse {
+    pub fn new(status: StatusCode, body: &str) -> Self {
+        Self {
+            status,
+            headers: Headers::new(),
+            body: body.as_bytes().to_vec(),
+            cookies: Vec::new(),
+        }
+    }
+
+    pub fn ok(body: &str) -> Self {
+        Self::new(StatusCode::OK, body)
+    }
+
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder {
+            response: Self::new(StatusCode::OK, ""),
+        }
+    }
+
+    /// Replaces the body with raw bytes (e.g. file contents)
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn with_cookie(mut self, cookie: Cookie) -> Self {
+        self.cookies.push(cookie);
+        self
+    }
+
+    /// Serializes the status line, headers and body for the wire. Framing headers
+    /// are derived from the body, and headers containing CR/LF are dropped so a
+    /// handler cannot split the response.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.body.len()
+        );
+        for (name, value) in self.headers.iter() {
+            let framing = FRAMING_HEADERS
+                .iter()
+                .any(|header| header.eq_ignore_ascii_case(name));
+            let injected = name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']);
+            if !framing && !injected {
+                out.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        for cookie in self.cookies.iter().filter(|cookie| cookie.is_valid()) {
+            out.push_str(&format!("Set-Cookie: {}\r\n", cookie.to_header()));
+        }
+        out.push_str("\r\n");
+        let mut bytes = out.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
 }
 
-/// Role-based and attribute-based access control system
-struct AccessControl {
-    roles: HashMap<String, Vec<String>>, // Role -> Allowed Routes
+/// Step-by-step construction of an `HttpResponse`
+pub struct ResponseBuilder {
+    response: HttpResponse,
 }
 
-impl AccessControl {
-    fn new() -> Self {
-        let mut roles = HashMap::new();
-        roles.insert("admin".to_string(), vec!["/admin", "/logs"]);
-        roles.insert("user".to_string(), vec!["/profile", "/dashboard"]);
-        Self { roles }
+impl ResponseBuilder {
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.response.status = status;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.response.headers.insert(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.response.body = body.into();
+        self
     }
 
-    fn is_allowed(&self, role: &str, path: &str) -> bool {
-        self.roles
-            .get(role)
-            .map_or(false, |routes| routes.contains(&path.to_string()))
+    pub fn build(self) -> HttpResponse {
+        self.response
     }
 }
 
-/// Handles HTTP request parsing
-fn parse_request(stream: &mut TcpStream) -> Option<HttpRequest> {
-    let mut buffer = [0; MAX_BODY_SIZE];
-    let bytes_read = stream.read(&mut buffer).ok()?;
-    let request_str = String::from_utf8_lossy(&buffer[..bytes_read]);
+/// Collects a CORS policy; `build` rejects unsafe combinations
+pub struct CorsBuilder {
+    allowed_origins: Vec<String>,
+    allowed_methods: Vec<String>,
+    allowed_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: u64,
+}
+
+impl CorsBuilder {
+    /// Allows an exact origin such as `https://app.example.com`, or `*` for any origin
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.allowed_origins
+            .push(origin.trim_end_matches('/').to_string());
+        self
+    }
 
-    let mut lines = request_str.lines();
-    let first_line = lines.next()?;
-    let mut parts = first_line.split_whitespace();
-    let method = parts.next()?.to_string();
-    let path = parts.next()?.to_string();
+    pub fn allow_methods(mut self, methods: &[&str]) -> Self {
+        self.allowed_methods = methods.iter().map(|m| m.to_ascii_uppercase()).collect();
+        self
+    }
+
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        self.allowed_headers = headers.iter().map(|h| h.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// Lets browsers send cookies and credentials; incompatible with a wildcard origin
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        self.allow_credentials = allow;
+        self
+    }
+
+    /// How long (seconds) browsers may cache a preflight result
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = seconds;
+        self
+    }
 
-    let headers = lines
-        .clone()
+    pub fn build(self) -> Result<CorsMiddleware, String> {
+        let wildcard = self.allowed_origins.iter().any(|origin| origin == "*");
+        if wildcard && self.allow_credentials {
+            return Err("credentials cannot be allowed for a wildcard origin".to_string());
+        }
+        if let Some(origin) = self
+            .allowed_origins
+            .iter()
+            .find(|origin| *origin != "*" && !origin.contains("://"))
+        {
+            return Err(format!("origin '{}' must include a scheme", origin));
+        }
+        if self.allowed_methods.is_empty() {
+            return Err("at least one method must be allowed".to_string());
+        }
+
+        Ok(CorsMiddleware {
+            allowed_origins: self.allowed_origins,
+            allowed_methods: self.allowed_methods,
+            allowed_headers: self.allowed_headers,
+            allow_credentials: self.allow_credentials,
+            max_age: self.max_age,
+        })
+    }
+}
+
+/// Answers CORS preflights and annotates responses for allowed cross-origin callers
+pub struct CorsMiddleware {
+    allowed_origins: Vec<String>,
+    allowed_methods: Vec<String>,
+    allowed_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: u64,
+}
+
+impl CorsMiddleware {
+    /// Starts a policy that allows no origins, simple methods, and a ten-minute preflight cache
+    pub fn builder() -> CorsBuilder {
+        CorsBuilder {
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()],
+            allowed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: 600,
+        }
+    }
+
+    fn is_wildcard(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        self.is_wildcard()
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin.trim_end_matches('/')))
+    }
+
+    /// Value for `Access-Control-Allow-Origin`; never `*` when credentials are allowed
+    fn allow_origin_value(&self, origin: &str) -> String {
+        if self.is_wildcard() && !self.allow_credentials {
+            "*".to_string()
+        } else {
+            origin.to_string()
+        }
+    }
+
+    /// Handles an `OPTIONS` preflight. Returns `None` for anything that is not a
+    /// preflight; disallowed preflights get a 403 with no `Access-Control-Allow-*` headers.
+    pub fn preflight(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        if !request.method.eq_ignore_ascii_case("OPTIONS") {
+            return None;
+        }
+        let origin = request.header("Origin")?;
+        let method = request.header("Access-Control-Request-Method")?;
+
+        let requested_headers: Vec<String> = request
+            .header("Access-Control-Request-Headers")
+            .map(|list| {
+                list.split(',')
+                    .map(|h| h.trim().to_ascii_lowercase())
+                    .filter(|h| !h.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let allowed = self.origin_allowed(origin)
+            && self
+                .allowed_methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(method))
+            && requested_headers
+                .iter()
+                .all(|h| self.allowed_headers.contains(h));
+        if !allowed {
+            return Some(
+                HttpResponse::new(StatusCode::FORBIDDEN, "CORS preflight rejected")
+                    .with_header("Vary", "Origin"),
+            );
+        }
+
+        let mut response = HttpResponse::new(StatusCode::NO_CONTENT, "")
+            .with_header(
+                "Access-Control-Allow-Origin",
+                &self.allow_origin_value(origin),
+            )
+            .with_header(
+                "Access-Control-Allow-Methods",
+                &self.allowed_methods.join(", "),
+            )
+            .with_header("Access-Control-Max-Age", &self.max_age.to_string())
+            .with_header("Vary", "Origin");
+        if !self.allowed_headers.is_empty() {
+            response = response.with_header(
+                "Access-Control-Allow-Headers",
+                &self.allowed_headers.join(", "),
+            );
+        }
+        if self.allow_credentials {
+            response = response.with_header("Access-Control-Allow-Credentials", "true");
+        }
+        Some(response)
+    }
+
+    /// Adds `Access-Control-Allow-*` headers to a response for an allowed origin.
+    /// Responses to disallowed origins are left untouched, so the browser blocks them.
+    pub fn apply(&self, request: &HttpRequest, response: &mut HttpResponse) {
+        let origin = match request.header("Origin") {
+            Some(origin) => origin,
+            None => return,
+        };
+        if !self.is_wildcard() || self.allow_credentials {
+            // The answer depends on Origin, so shared caches must key on it
+            response.headers.append("Vary", "Origin");
+        }
+        if !self.origin_allowed(origin) {
+            return;
+        }
+        response.headers.insert(
+            "Access-Control-Allow-Origin",
+            &self.allow_origin_value(origin),
+        );
+        if self.allow_credentials {
+            response
+                .headers
+                .insert("Access-Control-Allow-Credentials", "true");
+        }
+    }
+}
+
+/// Why `CsrfMiddleware::verify` rejected a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsrfError {
+    /// No `csrf_id` cookie, so there is no browser to bind a token to
+    MissingCookie,
+    /// Neither the `X-CSRF-Token` header nor a `csrf_token` form field was sent
+    MissingToken,
+    /// The token is unknown, expired, already used, or was issued to another browser
+    InvalidToken(String),
+}
+
+impl fmt::Display for CsrfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsrfError::MissingCookie => write!(f, "no {} cookie", CSRF_COOKIE),
+            CsrfError::MissingToken => f.write_str("no CSRF token"),
+            CsrfError::InvalidToken(reason) => write!(f, "CSRF token rejected: {}", reason),
+        }
+    }
+}
+
+/// Puts single-use CSRF tokens into rendered forms and requires one on every
+/// state-changing request. Tokens are bound to a random `csrf_id` cookie, so a token
+/// lifted from one browser is useless in another.
+pub struct CsrfMiddleware {
+    tokens: ChallengeStore,
+}
+
+impl Default for CsrfMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsrfMiddleware {
+    pub fn new() -> Self {
+        Self::with_ttl(CSRF_TOKEN_TTL)
+    }
+
+    /// Tokens stay valid for `ttl` after the form carrying them was rendered
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            tokens: ChallengeStore::new(ttl),
+        }
+    }
+
+    /// Issues a token for the browser identified by `browser_id`
+    pub fn issue_token(&self, browser_id: &str) -> io::Result<String> {
+        self.tokens.prune();
+        self.tokens.issue(browser_id)
+    }
+
+    /// Accepts `token` once, and only from the browser it was issued to
+    pub fn verify_token(&self, browser_id: &str, token: &str) -> Result<(), CsrfError> {
+        self.tokens
+            .consume(token, browser_id)
+            .map_err(|e| CsrfError::InvalidToken(e.to_string()))
+    }
+
+    /// Checks a request's token. Safe methods are exempt, as are requests carrying an
+    /// `Authorization` header, which browsers never attach on their own.
+    pub fn verify(&self, request: &HttpRequest) -> Result<(), CsrfError> {
+        let safe = ["GET", "HEAD", "OPTIONS", "TRACE"]
+            .iter()
+            .any(|method| request.method.eq_ignore_ascii_case(method));
+        if safe || request.header("Authorization").is_some() {
+            return Ok(());
+        }
+        let browser_id = request
+            .cookie(CSRF_COOKIE)
+            .ok_or(CsrfError::MissingCookie)?;
+        let token = request_csrf_token(request).ok_or(CsrfError::MissingToken)?;
+        self.verify_token(&browser_id, &token)
+    }
+
+    /// Adds a hidden `csrf_token` field to every non-GET form in an HTML response,
+    /// and sets the `csrf_id` cookie if the browser does not have one yet
+    pub fn inject(&self, request: &HttpRequest, response: &mut HttpResponse) -> io::Result<()> {
+        let is_html = response.headers.get("Content-Type").is_some_and(|value| {
+            value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("text/html")
+        });
+        if !is_html {
+            return Ok(());
+        }
+        let html = match std::str::from_utf8(&response.body) {
+            Ok(html) => html,
+            Err(_) => return Ok(()),
+        };
+        let (browser_id, new_cookie) = match request.cookie(CSRF_COOKIE) {
+            Some(id) if is_hex_id(&id) => (id, false),
+            _ => {
+                let mut bytes = [0u8; 16];
+                crypto::random_bytes(&mut bytes)?;
+                (bytes.iter().map(|b| format!("{:02x}", b)).collect(), true)
+            }
+        };
+        let protected = self.protect_forms(&browser_id, html)?;
+        if protected.len() == response.body.len() {
+            return Ok(()); // No forms to protect, so no cookie needed either
+        }
+        response.body = protected.into_bytes();
+        if new_cookie {
+            response
+                .cookies
+                .push(Cookie::session(CSRF_COOKIE, &browser_id));
+        }
+        Ok(())
+    }
+
+    /// `html` with a fresh token field after each `<form>` start tag whose method is not GET
+    pub fn protect_forms(&self, browser_id: &str, html: &str) -> io::Result<String> {
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html;
+        while let Some(start) = find_ascii_ci(rest, "<form") {
+            let after_name = &rest[start + "<form".len()..];
+            let is_form_tag = after_name
+                .chars()
+                .next()
+                .is_some_and(|c| c == '>' || c == '/' || c.is_ascii_whitespace());
+            let end = match tag_end(after_name) {
+                Some(end) if is_form_tag => start + "<form".len() + end + 1,
+                _ => {
+                    out.push_str(&rest[..start + "<form".len()]);
+                    rest = after_name;
+                    continue;
+                }
+            };
+            let tag = &rest[start..end];
+            out.push_str(&rest[..end]);
+            let method = attribute_value(tag, "method").unwrap_or_default();
+            if !method.is_empty() && !method.eq_ignore_ascii_case("get") {
+                out.push_str(&format!(
+                    "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+                    CSRF_FIELD,
+                    self.issue_token(browser_id)?
+                ));
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// The token from the `X-CSRF-Token` header, or else from the `csrf_token` field of a
+/// urlencoded or multipart form body
+fn request_csrf_token(request: &HttpRequest) -> Option<String> {
+    if let Some(token) = request.header(CSRF_HEADER) {
+        return Some(token.trim().to_string());
+    }
+    let media_type = request
+        .header("Content-Type")?
+        .split(';')
+        .next()?
+        .trim()
+        .to_ascii_lowercase();
+    match media_type.as_str() {
+        "application/x-www-form-urlencoded" => request.body.split('&').find_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            (name == CSRF_FIELD).then(|| percent_decode(&value.replace('+', " ")))?
+        }),
+        "multipart/form-data" => request
+            .multipart()
+            .ok()?
+            .into_iter()
+            .find(|part| part.name == CSRF_FIELD && part.filename.is_none())
+            .and_then(|part| String::from_utf8(part.data).ok()),
+        _ => None,
+    }
+}
+
+/// True for the 32-hex-digit ids `inject` hands out; anything else gets a fresh id
+fn is_hex_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Byte offset of the first case-insensitive match of the ASCII `needle`
+fn find_ascii_ci(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Offset of the `>` closing a start tag, skipping any inside quoted attribute values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of attribute `name` in start tag `tag`, quoted or not
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let inner = tag.trim_start_matches('<').trim_end_matches('>');
+    let mut rest = inner.split_once(|c: char| c.is_ascii_whitespace())?.1;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let attribute = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let close = after[1..].find(q).map_or(after.len(), |i| i + 1);
+                        (&after[1..close], after.get(close + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = remaining;
+                value
+            }
+            None => "",
+        };
+        if attribute.eq_ignore_ascii_case(name) {
+            return Some(value.trim().to_string());
+        }
+    }
+}
+
+/// Replaces identifying request headers with fixed values before handlers see them.
+/// Only the configured headers change, so content negotiation on the rest still works.
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintMitigation {
+    enabled: bool,
+    normalized: Vec<(String, String)>, // Header name -> value every client appears to send
+    skipped_routes: Vec<String>,       // Paths (and everything below them) left untouched
+}
+
+impl FingerprintMitigation {
+    /// An enabled middleware that normalizes nothing until headers are added
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            ..Self::default()
+        }
+    }
+
+    /// Sends `value` to handlers in place of whatever the client sent for `name`
+    pub fn normalize(mut self, name: &str, value: &str) -> Self {
+        self.normalized
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self.normalized.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Leaves requests for `path`, or any path below it, as the client sent them
+    pub fn skip_route(mut self, path: &str) -> Self {
+        self.skipped_routes
+            .push(path.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Turns the middleware on or off without discarding its configuration
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    fn applies_to(&self, path: &str) -> bool {
+        self.enabled
+            && !self.skipped_routes.iter().any(|skipped| {
+                path == skipped
+                    || path
+                        .strip_prefix(skipped.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Overwrites each configured header the request carries; headers the client
+    /// did not send stay absent, and every other header is preserved
+    pub fn apply(&self, request: &mut HttpRequest) {
+        self.apply_headers(&request.path, &mut request.headers);
+    }
+
+    /// `apply` for a bare header map, as sent for `path`
+    pub fn apply_headers(&self, path: &str, headers: &mut HashMap<String, String>) {
+        if !self.applies_to(path) {
+            return;
+        }
+        for (name, value) in headers.iter_mut() {
+            if let Some((_, normalized)) = self
+                .normalized
+                .iter()
+                .find(|(normalized, _)| normalized.eq_ignore_ascii_case(name))
+            {
+                value.clone_from(normalized);
+            }
+        }
+    }
+}
+
+/// Route handler invoked once a request has passed every middleware
+pub(crate) type Handler = Arc<dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync>;
+
+/// A registered route and the permission (if any) required to reach it
+pub(crate) struct Route {
+    pub(crate) handler: Handler,
+    pub(crate) permission: Option<String>,
+    pub(crate) name: String, // `METHOD /pattern`, the key its metrics are kept under
+}
+
+/// Recent latencies and lifetime status tallies for one route
+struct RouteStats {
+    latencies: VecDeque<f64>, // Milliseconds, oldest first
+    total: Duration,
+    statuses: HashMap<u16, u64>,
+}
+
+impl RouteStats {
+    fn requests(&self) -> u64 {
+        self.statuses.values().sum()
+    }
+}
+
+/// Per-route handler timings. Latencies are a bounded window per route (like the
+/// monitoring system's metric history); request counts and total time are lifetime.
+pub struct RouteMetrics {
+    routes: Mutex<HashMap<String, RouteStats>>,
+    history_size: usize,
+}
+
+impl RouteMetrics {
+    pub(crate) fn new(history_size: usize) -> Self {
+        Self {
+            routes: Mutex::new(HashMap::new()),
+            history_size: history_size.max(1),
+        }
+    }
+
+    /// Records one handler invocation of `route`
+    pub fn record(&self, route: &str, elapsed: Duration, status: StatusCode) {
+        let mut routes = lock_or_recover(&self.routes, "route metrics");
+        let stats = routes
+            .entry(route.to_string())
+            .or_insert_with(|| RouteStats {
+                latencies: VecDeque::with_capacity(self.history_size),
+                total: Duration::ZERO,
+                statuses: HashMap::new(),
+            });
+        if stats.latencies.len() == self.history_size {
+            stats.latencies.pop_front();
+        }
+        stats.latencies.push_back(elapsed.as_secs_f64() * 1000.0);
+        stats.total += elapsed;
+        *stats.statuses.entry(status.as_u16()).or_insert(0) += 1;
+    }
+
+    /// Names of every route that has handled a request, sorted
+    pub fn routes(&self) -> Vec<String> {
+        let mut names: Vec<String> = lock_or_recover(&self.routes, "route metrics")
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Requests `route` has handled
+    pub fn request_count(&self, route: &str) -> u64 {
+        lock_or_recover(&self.routes, "route metrics")
+            .get(route)
+            .map_or(0, RouteStats::requests)
+    }
+
+    /// Responses from `route` with `status`
+    pub fn status_count(&self, route: &str, status: StatusCode) -> u64 {
+        lock_or_recover(&self.routes, "route metrics")
+            .get(route)
+            .and_then(|stats| stats.statuses.get(&status.as_u16()).copied())
+            .unwrap_or(0)
+    }
+
+    /// The `p`th percentile (0-100) of `route`'s retained latencies in milliseconds,
+    /// interpolating between closest ranks; `None` before its first request
+    pub fn latency_percentile(&self, route: &str, p: f64) -> Option<f64> {
+        let mut values: Vec<f64> = lock_or_recover(&self.routes, "route metrics")
+            .get(route)?
+            .latencies
+            .iter()
+            .copied()
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let rank = (p.clamp(0.0, 100.0) / 100.0) * (values.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        let weight = rank - lower as f64;
+        Some(values[lower] + (values[upper] - values[lower]) * weight)
+    }
+
+    /// Prometheus text exposition: latency quantiles, request totals by status,
+    /// and cumulative handler time per route
+    pub fn render_prometheus(&self) -> String {
+        let latency = format!("{}latency_milliseconds", ROUTE_METRIC_PREFIX);
+        let requests = format!("{}requests_total", ROUTE_METRIC_PREFIX);
+        let mut out = format!(
+            "# HELP {0} Handler latency over recent requests\n# TYPE {0} summary\n",
+            latency
+        );
+        let routes = self.routes();
+        for route in &routes {
+            let label = escape_label(route);
+            for quantile in [0.5, 0.9, 0.99] {
+                if let Some(value) = self.latency_percentile(route, quantile * 100.0) {
+                    out.push_str(&format!(
+                        "{}{{route=\"{}\",quantile=\"{}\"}} {}\n",
+                        latency, label, quantile, value
+                    ));
+                }
+            }
+        }
+
+        let stats = lock_or_recover(&self.routes, "route metrics");
+        for route in &routes {
+            let label = escape_label(route);
+            let total = stats.get(route).map_or(Duration::ZERO, |stats| stats.total);
+            out.push_str(&format!(
+                "{}_sum{{route=\"{}\"}} {}\n{}_count{{route=\"{}\"}} {}\n",
+                latency,
+                label,
+                total.as_secs_f64() * 1000.0,
+                latency,
+                label,
+                stats.get(route).map_or(0, RouteStats::requests)
+            ));
+        }
+
+        out.push_str(&format!(
+            "# HELP {0} Requests handled by status code\n# TYPE {0} counter\n",
+            requests
+        ));
+        for route in &routes {
+            let mut statuses: Vec<(&u16, &u64)> = stats[route].statuses.iter().collect();
+            statuses.sort();
+            for (status, count) in statuses {
+                out.push_str(&format!(
+                    "{}{{route=\"{}\",status=\"{}\"}} {}\n",
+                    requests,
+                    escape_label(route),
+                    status,
+                    count
+                ));
+            }
+        }
+        out
+    }
+
+    /// Cumulative handler time in the folded-stack format flamegraph tools read:
+    /// one `route;status microseconds` line per route, weighted by its share of
+    /// requests with that status
+    pub fn folded_stacks(&self) -> String {
+        let stats = lock_or_recover(&self.routes, "route metrics");
+        let mut lines: Vec<String> = Vec::new();
+        for (route, stats) in stats.iter() {
+            let requests = stats.requests().max(1);
+            for (status, count) in &stats.statuses {
+                let micros = stats.total.as_micros() * *count as u128 / requests as u128;
+                // Frames are separated by ';' so the route name must not contain one
+                lines.push(format!(
+                    "{};{} {}",
+                    route.replace([';', ' '], "_"),
+                    status,
+                    micros
+                ));
+            }
+        }
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+/// Prometheus label values escape backslashes, quotes and newlines
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Outcome of resolving a request against the route table
+pub(crate) enum RouteMatch<'a> {
+    Found(&'a Route),
+    MethodNotAllowed,
+    NotFound,
+}
+
+/// Route table keyed by path, then method, plus read-only prefix mounts
+pub(crate) struct Router {
+    routes: HashMap<String, HashMap<String, Route>>,
+    mounts: Vec<(String, Route)>, // Path prefix -> GET/HEAD handler
+    metrics: Arc<RouteMetrics>,
+}
+
+impl Router {
+    pub(crate) fn new() -> Self {
+        Self {
+            routes: HashMap::new(),
+            mounts: Vec::new(),
+            metrics: Arc::new(RouteMetrics::new(ROUTE_LATENCY_HISTORY)),
+        }
+    }
+
+    /// Timings of every handler this router has invoked
+    pub(crate) fn metrics(&self) -> Arc<RouteMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Runs `route`'s handler, recording its latency and response status
+    pub(crate) fn invoke(&self, route: &Route, request: &HttpRequest) -> HttpResponse {
+        let started = Instant::now();
+        let response = (route.handler)(request);
+        self.metrics
+            .record(&route.name, started.elapsed(), response.status);
+        response
+    }
+
+    /// Serves every GET/HEAD request under `prefix` with `handler`
+    /// (exact routes take precedence, then the longest matching prefix)
+    pub(crate) fn mount(&mut self, prefix: &str, handler: Handler) {
+        let prefix = prefix.trim_end_matches('/').to_string();
+        let name = format!("GET {}/*", prefix);
+        self.mounts.push((
+            prefix,
+            Route {
+                handler,
+                permission: None,
+                name,
+            },
+        ));
+        self.mounts
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    }
+
+    /// Registers `handler` for `method path`; a later registration replaces an earlier one
+    pub(crate) fn add(
+        &mut self,
+        method: &str,
+        path: &str,
+        permission: Option<&str>,
+        handler: Handler,
+    ) {
+        let method = method.to_ascii_uppercase();
+        let name = format!("{} {}", method, path);
+        self.routes.entry(path.to_string()).or_default().insert(
+            method,
+            Route {
+                handler,
+                permission: permission.map(str::to_string),
+                name,
+            },
+        );
+    }
+
+    /// Finds the route for a request, ignoring any query string
+    pub(crate) fn resolve(&self, method: &str, path: &str) -> RouteMatch<'_> {
+        let path = path.split('?').next().unwrap_or(path);
+        if let Some(methods) = self.routes.get(path) {
+            return match methods.get(&method.to_ascii_uppercase()) {
+                Some(route) => RouteMatch::Found(route),
+                None => RouteMatch::MethodNotAllowed,
+            };
+        }
+
+        let mount = self.mounts.iter().find(|(prefix, _)| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        });
+        match mount {
+            Some((_, route))
+                if method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD") =>
+            {
+                RouteMatch::Found(route)
+            }
+            Some(_) => RouteMatch::MethodNotAllowed,
+            None => RouteMatch::NotFound,
+        }
+    }
+}
+
+/// Maps a file extension to its `Content-Type`
+pub(crate) fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("woff2") => "font/woff2",
+        Some("woff") => "font/woff",
+        Some("pdf") => "application/pdf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decodes `%XX` escapes; `None` for malformed escapes or invalid UTF-8
+pub(crate) fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Resolves `request_path` to a file under `root`, failing with the HTTP
+/// status to return: 400 for null bytes or bad escapes, 404 for missing files,
+/// and 403 for anything that canonicalizes outside `root` (`..`, symlinks).
+pub(crate) fn resolve_static_path(root: &Path, request_path: &str) -> Result<PathBuf, u16> {
+    let path = request_path.split(['?', '#']).next().unwrap_or("");
+    let decoded = percent_decode(path).ok_or(400u16)?;
+    if decoded.contains('\0') {
+        return Err(400);
+    }
+
+    let root = root.canonicalize().map_err(|_| 404u16)?;
+    let relative = decoded.trim_start_matches('/');
+    let mut candidate = root.join(relative);
+    if candidate.is_dir() {
+        candidate.push("index.html");
+    }
+
+    let resolved = match candidate.canonicalize() {
+        Ok(resolved) => resolved,
+        // Missing targets are still refused as 403 if the request tried to climb out
+        Err(_)
+            if Path::new(relative)
+                .components()
+                .any(|c| c == Component::ParentDir) =>
+        {
+            return Err(403)
+        }
+        Err(_) => return Err(404),
+    };
+    if !resolved.starts_with(&root) {
+        return Err(403);
+    }
+    if !resolved.is_file() {
+        return Err(404);
+    }
+    Ok(resolved)
+}
+
+/// Reads `request_path` from under `root` into a response with the right `Content-Type`
+pub(crate) fn serve_static(root: &Path, request_path: &str) -> HttpResponse {
+    match resolve_static_path(root, request_path) {
+        Ok(path) => match fs::read(&path) {
+            Ok(contents) => HttpResponse::ok("")
+                .with_body(contents)
+                .with_header("Content-Type", content_type_for(&path))
+                .with_header("X-Content-Type-Options", "nosniff"),
+            Err(_) => HttpResponse::new(StatusCode::NOT_FOUND, "Not found"),
+        },
+        Err(400) => HttpResponse::new(StatusCode::BAD_REQUEST, "Invalid path"),
+        Err(403) => HttpResponse::new(StatusCode::FORBIDDEN, "Forbidden"),
+        Err(_) => HttpResponse::new(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+/// Handler serving files from `root` by request path, refusing anything that escapes it
+pub fn serve_dir<P: Into<PathBuf>>(root: P) -> impl Fn(&HttpRequest) -> HttpResponse + Send + Sync {
+    let root = root.into();
+    move |request: &HttpRequest| serve_static(&root, &request.path)
+}
+
+/// Rejects traversal sequences and control characters in the request path
+pub(crate) fn is_safe_path(path: &str) -> bool {
+    path.starts_with('/')
+        && !path.split(['/', '?']).any(|segment| segment == "..")
+        && !path.contains("%2e%2e")
+        && !path.contains("%2E%2E")
+        && !path.chars().any(|c| c.is_control())
+}
+
+/// Why no request could be read off a connection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ReadError {
+    Malformed,
+    /// The client did not deliver a complete request within the read timeout
+    TimedOut,
+    /// Buffering the declared body would exceed the process-wide body budget
+    Overloaded,
+}
+
+/// A share of the process-wide request body budget, held while the body is in memory
+#[derive(Debug)]
+struct BodyReservation(usize);
+
+impl BodyReservation {
+    /// Claims `bytes` of the budget, or `None` if that would exceed it
+    fn acquire(bytes: usize) -> Option<Self> {
+        BUFFERED_BODY_BYTES
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |held| {
+                held.checked_add(bytes)
+                    .filter(|total| *total <= MAX_BUFFERED_BODY_BYTES)
+            })
+            .ok()
+            .map(|_| Self(bytes))
+    }
+}
+
+impl Drop for BodyReservation {
+    fn drop(&mut self) {
+        BUFFERED_BODY_BYTES.fetch_sub(self.0, Ordering::AcqRel);
+    }
+}
+
+/// Reads once, with the socket timeout capped at the time left before `deadline`
+fn read_before(
+    stream: &mut TcpStream,
+    chunk: &mut [u8],
+    deadline: Instant,
+) -> Result<usize, ReadError> {
+    let remaining = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or(ReadError::TimedOut)?;
+    stream
+        .set_read_timeout(Some(remaining))
+        .map_err(|_| ReadError::Malformed)?;
+    stream.read(chunk).map_err(|e| match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ReadError::TimedOut,
+        _ => ReadError::Malformed,
+    })
+}
+
+/// Handles HTTP request parsing; the whole request must arrive within `timeout`
+pub(crate) fn parse_request(
+    stream: &mut TcpStream,
+    timeout: Duration,
+) -> Result<HttpRequest, ReadError> {
+    // Requests may arrive in several segments: read until the head is complete,
+    // then until the declared body length has arrived
+    let deadline = Instant::now() + timeout;
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buffer.len() > MAX_BODY_SIZE {
+            return Err(ReadError::Malformed);
+        }
+        let n = read_before(stream, &mut chunk, deadline)?;
+        if n == 0 {
+            break buffer.len();
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).into_owned();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("Content-Length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err(ReadError::Malformed);
+    }
+    let body_reservation = match content_length {
+        0 => None,
+        bytes => Some(BodyReservation::acquire(bytes).ok_or(ReadError::Overloaded)?),
+    };
+
+    // Keep only the body, in a buffer sized to its declared length
+    let mut body = buffer.split_off(head_end);
+    body.truncate(content_length);
+    body.reserve_exact(content_length - body.len());
+    while body.len() < content_length {
+        let wanted = chunk.len().min(content_length - body.len());
+        let n = read_before(stream, &mut chunk[..wanted], deadline)?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    let (body, raw_body) = match String::from_utf8(body) {
+        Ok(body) => (body, None),
+        Err(e) => (
+            String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            Some(e.into_bytes()),
+        ),
+    };
+
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or("").split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(ReadError::Malformed),
+    };
+
+    let headers: HashMap<String, String> = lines
+        .by_ref()
         .take_while(|line| !line.is_empty())
         .filter_map(|line| {
             let mut split = line.splitn(2, ": ");
@@ -75,60 +1645,537 @@ fn parse_request(stream: &mut TcpStream) -> Option<HttpRequest> {
         })
         .collect();
 
-    let body = lines.collect::<Vec<&str>>().join("\n");
-    Some(HttpRequest {
+    Ok(HttpRequest {
         method,
         path,
-        headers,
         body,
+        peer: stream.peer_addr().ok().map(|addr| addr.ip()),
+        principal: None,
+        request_id: RequestId::from_headers(&headers),
+        headers,
+        raw_body,
+        _body_reservation: body_reservation,
     })
 }
 
-/// Handles client requests with routing and middleware enforcement
-fn handle_client(mut stream: TcpStream, access_control: Arc<AccessControl>) {
-    if let Some(request) = parse_request(&mut stream) {
-        let role = request
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A request as the parser would produce it, without a connection
+    fn request(method: &str, path: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: String::new(),
+            peer: None,
+            principal: None,
+            request_id: RequestId::generate(),
+            raw_body: None,
+            _body_reservation: None,
+        }
+    }
+
+    fn preflight_from(origin: &str) -> HttpRequest {
+        request(
+            "OPTIONS",
+            "/api",
+            &[
+                ("Origin", origin),
+                ("Access-Control-Request-Method", "POST"),
+                ("Access-Control-Request-Headers", "Content-Type"),
+            ],
+        )
+    }
+
+    fn app_cors() -> CorsMiddleware {
+        CorsMiddleware::builder()
+            .allow_origin("https://app.example.com")
+            .allow_methods(&["GET", "POST"])
+            .allow_headers(&["Content-Type"])
+            .allow_credentials(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn preflight_from_an_allowed_origin_is_answered() {
+        let response = app_cors()
+            .preflight(&preflight_from("https://app.example.com"))
+            .unwrap();
+        assert_eq!(response.status, StatusCode::NO_CONTENT);
+        let header = |name| response.headers.get(name);
+        assert_eq!(
+            header("Access-Control-Allow-Origin"),
+            Some("https://app.example.com")
+        );
+        assert_eq!(header("Access-Control-Allow-Methods"), Some("GET, POST"));
+        assert_eq!(header("Access-Control-Allow-Headers"), Some("content-type"));
+        assert_eq!(header("Access-Control-Allow-Credentials"), Some("true"));
+        assert_eq!(header("Vary"), Some("Origin"));
+
+        // Non-preflight requests pass through to routing
+        assert!(app_cors().preflight(&request("GET", "/api", &[])).is_none());
+    }
+
+    #[test]
+    fn disallowed_origin_gets_no_allow_origin_header() {
+        let cors = app_cors();
+        let response = cors
+            .preflight(&preflight_from("https://evil.example.com"))
+            .unwrap();
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert!(!response.headers.contains("Access-Control-Allow-Origin"));
+
+        let mut response = HttpResponse::ok("data");
+        cors.apply(
+            &request("GET", "/api", &[("Origin", "https://evil.example.com")]),
+            &mut response,
+        );
+        assert!(!response.headers.contains("Access-Control-Allow-Origin"));
+        assert_eq!(response.headers.get("Vary"), Some("Origin"));
+    }
+
+    #[test]
+    fn credentials_are_never_combined_with_a_wildcard_origin() {
+        let conflict = CorsMiddleware::builder()
+            .allow_origin("*")
+            .allow_credentials(true)
+            .build();
+        assert!(conflict.is_err());
+
+        // Without credentials the wildcard itself is echoed
+        let cors = CorsMiddleware::builder().allow_origin("*").build().unwrap();
+        let mut response = HttpResponse::ok("data");
+        cors.apply(
+            &request("GET", "/api", &[("Origin", "https://any.example.com")]),
+            &mut response,
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Origin"),
+            Some("*")
+        );
+        assert!(!response
             .headers
-            .get("Authorization")
-            .map(|_| "admin")
-            .unwrap_or("guest");
+            .contains("Access-Control-Allow-Credentials"));
+    }
 
-        if !access_control.is_allowed(role, &request.path) {
-            let response = "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n";
-            stream.write_all(response.as_bytes()).unwrap();
-            return;
+    /// A fresh `public` directory with a secret file beside it, outside the served root
+    fn static_root(name: &str) -> PathBuf {
+        let base = test_util::scratch_dir("router", name);
+        let root = base.join("public");
+        fs::create_dir_all(root.join("css")).unwrap();
+        fs::write(root.join("index.html"), "<h1>home</h1>").unwrap();
+        fs::write(root.join("css/site.css"), "body {}").unwrap();
+        fs::write(root.join("data.bin2"), [0u8, 1, 2]).unwrap();
+        fs::write(base.join("secret.txt"), "top secret").unwrap();
+        root
+    }
+
+    #[test]
+    fn static_files_are_served_with_their_content_type() {
+        let root = static_root("static-ok");
+        let serve = serve_dir(&root);
+
+        let response = serve(&request("GET", "/css/site.css", &[]));
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"body {}");
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some("text/css; charset=utf-8")
+        );
+
+        // Directories serve their index page
+        let response = serve(&request("GET", "/", &[]));
+        assert_eq!(response.body, b"<h1>home</h1>");
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+
+        let response = serve(&request("GET", "/data.bin2", &[]));
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(
+            serve(&request("GET", "/missing.txt", &[])).status,
+            StatusCode::NOT_FOUND
+        );
+        fs::remove_dir_all(root.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn paths_escaping_the_root_are_refused() {
+        let root = static_root("static-escape");
+        let serve = serve_dir(&root);
+
+        for path in [
+            "/../secret.txt",
+            "/css/../../secret.txt",
+            "/%2e%2e/secret.txt",
+        ] {
+            let response = serve(&request("GET", path, &[]));
+            assert_eq!(response.status, StatusCode::FORBIDDEN, "{}", path);
+            assert!(!response.body.windows(10).any(|w| w == b"top secret"));
+        }
+        // Climbing out is refused even when the target does not exist
+        assert_eq!(
+            serve(&request("GET", "/../nothing-here", &[])).status,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            serve(&request("GET", "/index.html%00.css", &[])).status,
+            StatusCode::BAD_REQUEST
+        );
+        fs::remove_dir_all(root.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn not_found_serializes_its_reason_phrase_and_framing() {
+        let response = HttpResponse::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("Content-Type", "text/plain")
+            .header("Content-Length", "999")
+            .header("X-Injected", "a\r\nSet-Cookie: stolen=1")
+            .body("Not found")
+            .build();
+        let wire = String::from_utf8(response.to_bytes()).unwrap();
+        let (head, body) = wire.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+
+        assert_eq!(lines.next(), Some("HTTP/1.1 404 Not Found"));
+        let mut headers: Vec<&str> = lines.collect();
+        headers.sort_unstable();
+        assert_eq!(
+            headers,
+            [
+                "Connection: close",
+                "Content-Length: 9",
+                "Content-Type: text/plain"
+            ]
+        );
+        assert_eq!(body, "Not found");
+    }
+
+    #[test]
+    fn status_codes_carry_their_own_reason_phrases() {
+        assert_eq!(StatusCode::FORBIDDEN.to_string(), "403 Forbidden");
+        assert_eq!(
+            StatusCode::TOO_MANY_REQUESTS.to_string(),
+            "429 Too Many Requests"
+        );
+        assert_eq!(
+            StatusCode::from_u16(418).unwrap().to_string(),
+            "418 Client Error"
+        );
+        assert!(StatusCode::from_u16(99).is_none());
+        assert!(StatusCode::from_u16(600).is_none());
+    }
+
+    #[test]
+    fn cookie_with_every_attribute_serializes_in_order() {
+        let cookie = Cookie::new("session", "abc123")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Lax)
+            .max_age(Duration::from_secs(3600))
+            .path("/app")
+            .domain("example.com");
+        assert!(cookie.is_valid());
+        assert_eq!(
+            cookie.to_header(),
+            "session=abc123; Max-Age=3600; Domain=example.com; Path=/app; Secure; HttpOnly; SameSite=Lax"
+        );
+        assert_eq!(
+            Cookie::session("id", "v").to_header(),
+            "id=v; Path=/; Secure; HttpOnly; SameSite=Strict"
+        );
+        // SameSite=None is only honoured alongside Secure, so it implies it
+        assert_eq!(
+            Cookie::new("x", "1").same_site(SameSite::None).to_header(),
+            "x=1; Secure; SameSite=None"
+        );
+    }
+
+    #[test]
+    fn invalid_cookies_are_not_emitted() {
+        assert!(!Cookie::new("", "v").is_valid());
+        assert!(!Cookie::new("bad name", "v").is_valid());
+        assert!(!Cookie::new("name", "a;b").is_valid());
+        assert!(!Cookie::new("name", "v")
+            .path("/\r\nX-Injected: 1")
+            .is_valid());
+
+        let response = HttpResponse::new(StatusCode::OK, "")
+            .with_cookie(Cookie::new("good", "1"))
+            .with_cookie(Cookie::new("bad", "a;b"));
+        let raw = String::from_utf8(response.to_bytes()).unwrap();
+        assert!(raw.contains("Set-Cookie: good=1\r\n"));
+        assert_eq!(raw.matches("Set-Cookie:").count(), 1);
+    }
+
+    #[test]
+    fn multi_cookie_header_parses_into_pairs() {
+        let cookies = parse_cookies(
+            r#"session=abc; theme="dark"; empty=; novalue; =orphan; session=second; b64=YQ=="#,
+        );
+        let mut pairs: Vec<_> = cookies
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            [
+                ("b64", "YQ=="),
+                ("empty", ""),
+                ("session", "abc"),
+                ("theme", "dark")
+            ]
+        );
+
+        let request = request("GET", "/", &[("Cookie", "a=1; b=2")]);
+        assert_eq!(request.cookie("b").as_deref(), Some("2"));
+        assert_eq!(request.cookie("c"), None);
+        assert!(self::request("GET", "/", &[]).cookies().is_empty());
+    }
+
+    /// Runs whatever `router` resolves for `method path`, as the server does
+    fn dispatch(router: &Router, method: &str, path: &str) -> HttpResponse {
+        match router.resolve(method, path) {
+            RouteMatch::Found(route) => router.invoke(route, &request(method, path, &[])),
+            _ => panic!("no route for {} {}", method, path),
+        }
+    }
+
+    #[test]
+    fn slow_handlers_are_timed_and_counted_per_route() {
+        let mut router = Router::new();
+        router.add(
+            "get",
+            "/slow",
+            None,
+            Arc::new(|_: &HttpRequest| {
+                std::thread::sleep(Duration::from_millis(20));
+                HttpResponse::new(StatusCode::OK, "done")
+            }),
+        );
+        router.add(
+            "POST",
+            "/slow",
+            None,
+            Arc::new(|_: &HttpRequest| HttpResponse::new(StatusCode::BAD_REQUEST, "no")),
+        );
+        let metrics = router.metrics();
+        assert_eq!(metrics.latency_percentile("GET /slow", 50.0), None);
+
+        for _ in 0..3 {
+            dispatch(&router, "GET", "/slow?page=2");
+        }
+        dispatch(&router, "POST", "/slow");
+
+        assert_eq!(metrics.routes(), vec!["GET /slow", "POST /slow"]);
+        assert_eq!(metrics.request_count("GET /slow"), 3);
+        assert_eq!(metrics.status_count("GET /slow", StatusCode::OK), 3);
+        assert_eq!(
+            metrics.status_count("POST /slow", StatusCode::BAD_REQUEST),
+            1
+        );
+        assert_eq!(metrics.request_count("GET /other"), 0);
+        let median = metrics.latency_percentile("GET /slow", 50.0).unwrap();
+        assert!(median >= 20.0, "median {}ms", median);
+
+        let exposition = metrics.render_prometheus();
+        assert!(
+            exposition.contains("ztw_route_requests_total{route=\"GET /slow\",status=\"200\"} 3\n")
+        );
+        assert!(
+            exposition.contains("ztw_route_latency_milliseconds_count{route=\"GET /slow\"} 3\n")
+        );
+        assert!(metrics.folded_stacks().starts_with("GET_/slow;200 "));
+    }
+
+    #[test]
+    fn latency_percentiles_interpolate_over_a_bounded_window() {
+        let metrics = RouteMetrics::new(4);
+        for ms in [100, 1, 2, 3, 4] {
+            metrics.record("GET /", Duration::from_millis(ms), StatusCode::OK);
         }
+        // The 100ms sample has aged out of the window but still counts as a request
+        assert_eq!(metrics.latency_percentile("GET /", 100.0), Some(4.0));
+        assert_eq!(metrics.latency_percentile("GET /", 0.0), Some(1.0));
+        assert_eq!(metrics.latency_percentile("GET /", 50.0), Some(2.5));
+        assert_eq!(metrics.request_count("GET /"), 5);
+    }
+
+    /// Parses `raw` as it arrives over a loopback connection
+    fn parse_raw(raw: &[u8]) -> Result<HttpRequest, ReadError> {
+        use std::io::Write;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(raw).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        parse_request(&mut stream, Duration::from_secs(2))
+    }
+
+    #[test]
+    fn bodies_are_sized_to_content_length_within_a_global_budget() {
+        let small = parse_raw(b"POST /f HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        assert_eq!(small.body, "hello");
+        assert!(
+            small.body.capacity() < 4096,
+            "capacity {}",
+            small.body.capacity()
+        );
+        drop(small);
 
-        let response = HttpResponse {
-            status_code: 200,
-            body: format!("Route {} accessed", request.path),
+        // Claim the whole budget as concurrent large bodies would
+        let mut held = Vec::new();
+        while let Some(reservation) = BodyReservation::acquire(MAX_BODY_SIZE) {
+            held.push(reservation);
+        }
+        assert!(held.len() <= MAX_BUFFERED_BODY_BYTES / MAX_BODY_SIZE);
+        let large = format!(
+            "POST /f HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE
+        );
+        assert_eq!(
+            parse_raw(large.as_bytes()).err(),
+            Some(ReadError::Overloaded)
+        );
+        // Requests without a body never touch the budget
+        assert!(parse_raw(b"GET / HTTP/1.1\r\n\r\n").is_ok());
+
+        drop(held);
+        let ok = parse_raw(b"POST /f HTTP/1.1\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        assert_eq!(ok.body, "ok");
+        let too_large = format!(
+            "POST /f HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        assert_eq!(
+            parse_raw(too_large.as_bytes()).err(),
+            Some(ReadError::Malformed)
+        );
+    }
+
+    #[test]
+    fn json_bodies_need_a_json_content_type() {
+        let with_body = |content_type: &[(&str, &str)], body: &str| {
+            let mut request = request("POST", "/api", content_type);
+            request.body = body.to_string();
+            request.json()
         };
-        let response_str = format!(
-            "HTTP/1.1 {} OK\r\nContent-Length: {}\r\n\r\n{}",
-            response.status_code,
-            response.body.len(),
-            response.body
+        let value = with_body(
+            &[("Content-Type", "application/json; charset=utf-8")],
+            "{\"a\":1}",
+        );
+        assert_eq!(
+            value.unwrap().get("a").and_then(JsonValue::as_f64),
+            Some(1.0)
+        );
+        assert!(with_body(&[("Content-Type", "application/merge-patch+json")], "{}").is_ok());
+
+        let not_json = with_body(&[("Content-Type", "text/plain")], "{}").unwrap_err();
+        assert_eq!(not_json.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(with_body(&[], "{}"), Err(JsonBodyError::NotJson));
+        let invalid = with_body(&[("Content-Type", "application/json")], "{").unwrap_err();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn csrf_tokens_are_bound_to_one_browser_and_exempt_safe_methods() {
+        let csrf = CsrfMiddleware::new();
+        let html = csrf
+            .protect_forms(
+                "browser-a",
+                "<form method=\"GET\"></form><FORM method=post>",
+            )
+            .unwrap();
+        assert!(html.starts_with("<form method=\"GET\"></form><FORM method=post><input"));
+        assert_eq!(html.matches(CSRF_FIELD).count(), 1);
+
+        let token = csrf.issue_token("browser-a").unwrap();
+        let cookie = format!("{}=browser-b", CSRF_COOKIE);
+        let stolen = request(
+            "POST",
+            "/submit",
+            &[("Cookie", &cookie), (CSRF_HEADER, &token)],
         );
-        stream.write_all(response_str.as_bytes()).unwrap();
+        assert!(matches!(
+            csrf.verify(&stolen),
+            Err(CsrfError::InvalidToken(_))
+        ));
+
+        let cookie = format!("{}=browser-a", CSRF_COOKIE);
+        let missing = request("DELETE", "/item", &[("Cookie", &cookie)]);
+        assert_eq!(csrf.verify(&missing), Err(CsrfError::MissingToken));
+        let uncookied = request("PUT", "/item", &[(CSRF_HEADER, &token)]);
+        assert_eq!(csrf.verify(&uncookied), Err(CsrfError::MissingCookie));
+
+        assert_eq!(csrf.verify(&request("GET", "/", &[])), Ok(()));
+        assert_eq!(csrf.verify(&request("HEAD", "/", &[])), Ok(()));
+        let api = request("POST", "/api", &[("Authorization", "Bearer t")]);
+        assert_eq!(csrf.verify(&api), Ok(()));
     }
-}
 
-/// Starts the Zero Trust Router with dynamic route handling
-fn main() {
-    let listener = TcpListener::bind(BIND_ADDRESS).expect("Failed to bind address");
-    println!("Zero Trust Router running on {}", BIND_ADDRESS);
+    fn browser_request(path: &str) -> HttpRequest {
+        request(
+            "GET",
+            path,
+            &[
+                (
+                    "User-Agent",
+                    "Mozilla/5.0 (X11; Linux x86_64) Firefox/131.0",
+                ),
+                ("Accept-Language", "de-CH,de;q=0.9"),
+                ("Accept", "text/html"),
+            ],
+        )
+    }
 
-    let access_control = Arc::new(AccessControl::new());
+    fn normalizing() -> FingerprintMitigation {
+        FingerprintMitigation::new()
+            .normalize("user-agent", "Mozilla/5.0")
+            .normalize("Accept-Language", "en-US")
+    }
 
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                let access_control = Arc::clone(&access_control);
-                thread::spawn(move || {
-                    handle_client(stream, access_control);
-                });
-            }
-            Err(e) => eprintln!("Connection failed: {}", e),
+    #[test]
+    fn fingerprint_mitigation_normalizes_only_the_configured_headers() {
+        let mut normalized = browser_request("/page");
+        normalizing().apply(&mut normalized);
+        assert_eq!(normalized.header("User-Agent"), Some("Mozilla/5.0"));
+        assert_eq!(normalized.header("Accept-Language"), Some("en-US"));
+        assert_eq!(normalized.header("Accept"), Some("text/html"));
+
+        // Headers the client did not send are not invented
+        let mut bare = request("GET", "/page", &[("Accept", "*/*")]);
+        normalizing().apply(&mut bare);
+        assert_eq!(bare.header("User-Agent"), None);
+    }
+
+    #[test]
+    fn disabled_or_skipped_fingerprint_mitigation_is_a_no_op() {
+        let untouched = browser_request("/page").headers;
+
+        let mut request = browser_request("/page");
+        normalizing().enabled(false).apply(&mut request);
+        assert_eq!(request.headers, untouched);
+
+        let skipping = normalizing().skip_route("/i18n/");
+        for path in ["/i18n", "/i18n/strings"] {
+            let mut request = browser_request(path);
+            skipping.apply(&mut request);
+            assert_eq!(request.headers, untouched, "{}", path);
         }
+        let mut request = browser_request("/i18nx");
+        skipping.apply(&mut request);
+        assert_eq!(request.header("Accept-Language"), Some("en-US"));
     }
 }
@@ -15,34 +15,239 @@
 use std::collections::HashMap;
 
 /// Represents HTTP headers with enforced security policies
-struct SecurityHeaders {
+pub(crate) struct SecurityHeaders {
     headers: HashMap<String, String>,
 }
 
-impl SecurityHeaders {
-    fn new() -> Self {
+/// Minimum HSTS max-age (one year) accepted by browser preload lists
+const HSTS_PRELOAD_MIN_AGE: u64 = 31536000;
+const DEFAULT_CSP: &str = "default-src 'self'; script-src 'self'; style-src 'self'; img-src 'self'; frame-ancestors 'none';";
+
+/// Name of the reporting endpoint group referenced by `report-to`
+const CSP_REPORT_GROUP: &str = "csp-endpoint";
+
+/// Headers whose values are comma-separated lists and can be appended to
+const COMMA_JOINABLE_HEADERS: [&str; 3] = ["Vary", "Cache-Control", "Reporting-Endpoints"];
+
+/// Headers a route may tailor; an existing value wins unless forced
+const ROUTE_OVERRIDABLE_HEADERS: [&str; 5] = [
+    "Content-Security-Policy",
+    "Content-Security-Policy-Report-Only",
+    "X-Frame-Options",
+    "Referrer-Policy",
+    "Cross-Origin-Resource-Policy",
+];
+
+/// How `apply_headers_with` treats headers already present on the response
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MergeMode {
+    /// Keep route-specific values and append to list headers
+    Merge,
+    /// Replace existing values with the configured policy
+    Force,
+}
+
+/// Whether the CSP is enforced or only reported to a collection endpoint
+#[derive(Debug, Clone, PartialEq)]
+enum CspMode {
+    Enforce,
+    ReportOnly { report_uri: String },
+}
+
+/// A CSP violation report as posted by the browser to the report endpoint
+#[derive(Debug, Clone, PartialEq)]
+struct CspViolation {
+    document_uri: String,
+    violated_directive: String,
+    effective_directive: String,
+    blocked_uri: String,
+    original_policy: String,
+    disposition: String,
+}
+
+impl CspViolation {
+    /// Parses a legacy `application/csp-report` JSON body
+    fn parse(body: &str) -> Result<Self, String> {
+        if !body.contains("\"csp-report\"") {
+            return Err("Missing csp-report object".to_string());
+        }
+        let field = |key: &str| json_string_field(body, key).unwrap_or_default();
+        let violation = Self {
+            document_uri: field("document-uri"),
+            violated_directive: field("violated-directive"),
+            effective_directive: field("effective-directive"),
+            blocked_uri: field("blocked-uri"),
+            original_policy: field("original-policy"),
+            disposition: field("disposition"),
+        };
+        if violation.violated_directive.is_empty() && violation.effective_directive.is_empty() {
+            return Err("Report does not name a violated directive".to_string());
+        }
+        Ok(violation)
+    }
+}
+
+/// Extracts the string value of `"key": "..."` from a flat JSON document
+fn json_string_field(json: &str, key: &str) -> Option<String> {
+    let needle = format!("\"{}\"", key);
+    let after_key = &json[json.find(&needle)? + needle.len()..];
+    let after_colon = after_key.trim_start().strip_prefix(':')?.trim_start();
+    let mut chars = after_colon.strip_prefix('"')?.chars();
+
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                other => value.push(other),
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// Configures a `SecurityHeaders` policy; defaults match `SecurityHeaders::new`
+pub(crate) struct SecurityHeadersBuilder {
+    csp: String,
+    csp_mode: CspMode,
+    hsts_max_age: u64,
+    hsts_include_subdomains: bool,
+    hsts_preload: bool,
+    frame_options: String,
+    referrer_policy: String,
+}
+
+impl Default for SecurityHeadersBuilder {
+    fn default() -> Self {
+        Self {
+            csp: DEFAULT_CSP.to_string(),
+            csp_mode: CspMode::Enforce,
+            hsts_max_age: HSTS_PRELOAD_MIN_AGE,
+            hsts_include_subdomains: true,
+            hsts_preload: true,
+            frame_options: "DENY".to_string(),
+            referrer_policy: "strict-origin-when-cross-origin".to_string(),
+        }
+    }
+}
+
+impl SecurityHeadersBuilder {
+    /// Sets the Content-Security-Policy directive list
+    pub(crate) fn csp(mut self, policy: &str) -> Self {
+        self.csp = policy.to_string();
+        self
+    }
+
+    /// Enforces the CSP (the default)
+    fn enforce_csp(mut self) -> Self {
+        self.csp_mode = CspMode::Enforce;
+        self
+    }
+
+    /// Emits the CSP as report-only, sending violations to `report_uri`
+    fn report_only(mut self, report_uri: &str) -> Self {
+        self.csp_mode = CspMode::ReportOnly {
+            report_uri: report_uri.to_string(),
+        };
+        self
+    }
+
+    /// Sets the Strict-Transport-Security parameters
+    fn hsts(mut self, max_age: u64, include_subdomains: bool, preload: bool) -> Self {
+        self.hsts_max_age = max_age;
+        self.hsts_include_subdomains = include_subdomains;
+        self.hsts_preload = preload;
+        self
+    }
+
+    /// Sets X-Frame-Options (`DENY` or `SAMEORIGIN`)
+    fn frame_options(mut self, value: &str) -> Self {
+        self.frame_options = value.to_uppercase();
+        self
+    }
+
+    /// Sets the Referrer-Policy value
+    fn referrer_policy(mut self, value: &str) -> Self {
+        self.referrer_policy = value.to_string();
+        self
+    }
+
+    /// Validates the configuration and produces the header set
+    pub(crate) fn build(self) -> Result<SecurityHeaders, String> {
+        if self.csp.trim().is_empty() {
+            return Err("Content-Security-Policy must not be empty".to_string());
+        }
+        if let CspMode::ReportOnly { report_uri } = &self.csp_mode {
+            if report_uri.is_empty() || report_uri.contains(|c: char| c.is_whitespace() || c == ';')
+            {
+                return Err(format!("Invalid CSP report URI: {:?}", report_uri));
+            }
+        }
+        if self.hsts_preload && !self.hsts_include_subdomains {
+            return Err("HSTS preload requires includeSubDomains".to_string());
+        }
+        if self.hsts_preload && self.hsts_max_age < HSTS_PRELOAD_MIN_AGE {
+            return Err(format!(
+                "HSTS preload requires max-age of at least {}",
+                HSTS_PRELOAD_MIN_AGE
+            ));
+        }
+        if self.frame_options != "DENY" && self.frame_options != "SAMEORIGIN" {
+            return Err(format!(
+                "Unsupported X-Frame-Options value: {}",
+                self.frame_options
+            ));
+        }
+
+        let mut hsts = format!("max-age={}", self.hsts_max_age);
+        if self.hsts_include_subdomains {
+            hsts.push_str("; includeSubDomains");
+        }
+        if self.hsts_preload {
+            hsts.push_str("; preload");
+        }
+
         let mut headers = HashMap::new();
 
         // HTTP Strict Transport Security (HSTS) - Forces HTTPS for all requests
-        headers.insert(
-            "Strict-Transport-Security".to_string(),
-            "max-age=31536000; includeSubDomains; preload".to_string(),
-        );
+        headers.insert("Strict-Transport-Security".to_string(), hsts);
 
         // Content Security Policy (CSP) - Mitigates XSS & data injection attacks
-        headers.insert("Content-Security-Policy".to_string(), "default-src 'self'; script-src 'self'; style-src 'self'; img-src 'self'; frame-ancestors 'none';".to_string());
+        match self.csp_mode {
+            CspMode::Enforce => {
+                headers.insert("Content-Security-Policy".to_string(), self.csp);
+            }
+            CspMode::ReportOnly { report_uri } => {
+                let policy = format!(
+                    "{}; report-uri {}; report-to {}",
+                    self.csp.trim().trim_end_matches(';'),
+                    report_uri,
+                    CSP_REPORT_GROUP
+                );
+                headers.insert("Content-Security-Policy-Report-Only".to_string(), policy);
+                headers.insert(
+                    "Reporting-Endpoints".to_string(),
+                    format!("{}=\"{}\"", CSP_REPORT_GROUP, report_uri),
+                );
+            }
+        }
 
         // Clickjacking Protection
-        headers.insert("X-Frame-Options".to_string(), "DENY".to_string());
+        headers.insert("X-Frame-Options".to_string(), self.frame_options);
 
         // Prevent MIME sniffing attacks
         headers.insert("X-Content-Type-Options".to_string(), "nosniff".to_string());
 
         // Enforce privacy on referrer headers
-        headers.insert(
-            "Referrer-Policy".to_string(),
-            "strict-origin-when-cross-origin".to_string(),
-        );
+        headers.insert("Referrer-Policy".to_string(), self.referrer_policy);
 
         // Restrict browser API features
         headers.insert(
@@ -64,13 +269,64 @@ impl SecurityHeaders {
             "require-corp".to_string(),
         );
 
-        Self { headers }
+        Ok(SecurityHeaders { headers })
+    }
+}
+
+impl SecurityHeaders {
+    /// Builds the default (strictest) policy
+    fn new() -> Self {
+        Self::builder()
+            .build()
+            .expect("default security header policy is valid")
+    }
+
+    /// Starts a customizable policy from the defaults
+    pub(crate) fn builder() -> SecurityHeadersBuilder {
+        SecurityHeadersBuilder::default()
     }
 
     /// Applies security headers to an HTTP response
-    fn apply_headers(&self, response: &mut HashMap<String, String>) {
+    pub(crate) fn apply_headers(&self, response: &mut HashMap<String, String>) {
+        self.apply_headers_with(response, MergeMode::Merge);
+    }
+
+    /// Applies security headers using the given merge mode. Header names are
+    /// matched case-insensitively and `Set-Cookie` is never touched.
+    fn apply_headers_with(&self, response: &mut HashMap<String, String>, mode: MergeMode) {
         for (key, value) in &self.headers {
-            response.insert(key.clone(), value.clone());
+            if key.eq_ignore_ascii_case("Set-Cookie") {
+                continue;
+            }
+
+            let existing_key = response
+                .keys()
+                .find(|name| name.eq_ignore_ascii_case(key))
+                .cloned();
+            let existing_key = match existing_key {
+                Some(existing_key) => existing_key,
+                None => {
+                    response.insert(key.clone(), value.clone());
+                    continue;
+                }
+            };
+
+            let is_listed = |list: &[&str]| list.iter().any(|name| name.eq_ignore_ascii_case(key));
+            if mode == MergeMode::Force {
+                response.remove(&existing_key);
+                response.insert(key.clone(), value.clone());
+            } else if is_listed(&COMMA_JOINABLE_HEADERS) {
+                let current = response.get_mut(&existing_key).unwrap();
+                for item in value.split(',').map(str::trim) {
+                    if !current.split(',').any(|present| present.trim() == item) {
+                        current.push_str(", ");
+                        current.push_str(item);
+                    }
+                }
+            } else if !is_listed(&ROUTE_OVERRIDABLE_HEADERS) {
+                // Baseline protections (HSTS, nosniff, ...) are always enforced
+                response.insert(existing_key, value.clone());
+            }
         }
     }
 }
@@ -79,10 +335,227 @@ fn main() {
     let security_headers = SecurityHeaders::new();
     let mut http_response = HashMap::new();
 
+    // A route that embeds third-party media sets its own CSP and cookie
+    http_response.insert(
+        "Content-Security-Policy".to_string(),
+        "default-src 'self'; media-src https://media.example.com".to_string(),
+    );
+    http_response.insert(
+        "Set-Cookie".to_string(),
+        "session=abc; Secure; HttpOnly".to_string(),
+    );
+    http_response.insert("Vary".to_string(), "Accept-Encoding".to_string());
+
     security_headers.apply_headers(&mut http_response);
 
     println!("Applied Security Headers:");
     for (key, value) in http_response.iter() {
         println!("{}: {}", key, value);
     }
+
+    match SecurityHeaders::builder()
+        .csp("default-src 'self'; img-src 'self' https://cdn.example.com")
+        .hsts(86400, false, false)
+        .frame_options("sameorigin")
+        .referrer_policy("no-referrer")
+        .build()
+    {
+        Ok(custom) => {
+            custom.apply_headers_with(&mut http_response, MergeMode::Force);
+            println!(
+                "Forced custom CSP: {}",
+                http_response["Content-Security-Policy"]
+            );
+        }
+        Err(e) => eprintln!("Invalid security header policy: {}", e),
+    }
+
+    // Trial a stricter policy without breaking pages
+    if let Ok(trial) = SecurityHeaders::builder()
+        .csp("default-src 'none'; script-src 'self'")
+        .report_only("/api/csp-report")
+        .build()
+    {
+        println!(
+            "Report-only CSP: {}",
+            trial.headers["Content-Security-Policy-Report-Only"]
+        );
+    }
+
+    let report = r#"{"csp-report":{"document-uri":"https://example.com/","violated-directive":"script-src 'self'","effective-directive":"script-src","blocked-uri":"https://evil.example/x.js","original-policy":"default-src 'none'; script-src 'self'","disposition":"report"}}"#;
+    match CspViolation::parse(report) {
+        Ok(violation) => println!(
+            "[CSP] {} blocked {} on {} ({})",
+            violation.effective_directive,
+            violation.blocked_uri,
+            violation.document_uri,
+            violation.disposition
+        ),
+        Err(e) => eprintln!("Malformed CSP report: {}", e),
+    }
+
+    if let Err(e) = SecurityHeaders::builder()
+        .enforce_csp()
+        .hsts(31536000, false, true)
+        .build()
+    {
+        println!("Rejected policy: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_emits_the_configured_header_values() {
+        let policy = SecurityHeaders::builder()
+            .csp("default-src 'self'; img-src https://cdn.example.com")
+            .hsts(86400, true, false)
+            .frame_options("sameorigin")
+            .referrer_policy("no-referrer")
+            .build()
+            .unwrap();
+        let mut response = HashMap::new();
+        policy.apply_headers(&mut response);
+
+        assert_eq!(
+            response["Content-Security-Policy"],
+            "default-src 'self'; img-src https://cdn.example.com"
+        );
+        assert_eq!(
+            response["Strict-Transport-Security"],
+            "max-age=86400; includeSubDomains"
+        );
+        assert_eq!(response["X-Frame-Options"], "SAMEORIGIN");
+        assert_eq!(response["Referrer-Policy"], "no-referrer");
+        assert_eq!(response["X-Content-Type-Options"], "nosniff");
+
+        let defaults = SecurityHeaders::new();
+        assert_eq!(
+            defaults.headers["Strict-Transport-Security"],
+            "max-age=31536000; includeSubDomains; preload"
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        let preload_without_subdomains = SecurityHeaders::builder()
+            .hsts(31536000, false, true)
+            .build();
+        assert_eq!(
+            preload_without_subdomains.err().as_deref(),
+            Some("HSTS preload requires includeSubDomains")
+        );
+        assert!(SecurityHeaders::builder()
+            .hsts(86400, true, true)
+            .build()
+            .is_err());
+        assert!(SecurityHeaders::builder()
+            .frame_options("ALLOW-FROM x")
+            .build()
+            .is_err());
+        assert!(SecurityHeaders::builder().csp("  ").build().is_err());
+    }
+
+    #[test]
+    fn report_only_mode_emits_the_report_only_header() {
+        let trial = SecurityHeaders::builder()
+            .csp("default-src 'none'; script-src 'self';")
+            .report_only("/api/csp-report")
+            .build()
+            .unwrap();
+        assert!(!trial.headers.contains_key("Content-Security-Policy"));
+        assert_eq!(
+            trial.headers["Content-Security-Policy-Report-Only"],
+            "default-src 'none'; script-src 'self'; report-uri /api/csp-report; report-to csp-endpoint"
+        );
+        assert_eq!(
+            trial.headers["Reporting-Endpoints"],
+            "csp-endpoint=\"/api/csp-report\""
+        );
+        assert!(SecurityHeaders::builder()
+            .report_only("/bad uri")
+            .build()
+            .is_err());
+
+        let enforced = SecurityHeaders::builder()
+            .report_only("/api/csp-report")
+            .enforce_csp()
+            .build()
+            .unwrap();
+        assert!(enforced.headers.contains_key("Content-Security-Policy"));
+    }
+
+    #[test]
+    fn violation_report_is_parsed_into_its_fields() {
+        let report = r#"{"csp-report":{"document-uri":"https://example.com/","violated-directive":"script-src 'self'","effective-directive":"script-src","blocked-uri":"https://evil.example/x.js","original-policy":"default-src 'none'; script-src 'self'","disposition":"report"}}"#;
+        let violation = CspViolation::parse(report).unwrap();
+        assert_eq!(
+            violation,
+            CspViolation {
+                document_uri: "https://example.com/".to_string(),
+                violated_directive: "script-src 'self'".to_string(),
+                effective_directive: "script-src".to_string(),
+                blocked_uri: "https://evil.example/x.js".to_string(),
+                original_policy: "default-src 'none'; script-src 'self'".to_string(),
+                disposition: "report".to_string(),
+            }
+        );
+        assert!(CspViolation::parse(r#"{"document-uri":"https://example.com/"}"#).is_err());
+        assert!(CspViolation::parse(r#"{"csp-report":{"blocked-uri":"x"}}"#).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_a_route_csp_and_force_replaces_it() {
+        let route_csp = "default-src 'self'; media-src https://media.example.com";
+        let route_response = || {
+            let mut response = HashMap::new();
+            response.insert("content-security-policy".to_string(), route_csp.to_string());
+            response.insert(
+                "Strict-Transport-Security".to_string(),
+                "max-age=0".to_string(),
+            );
+            response.insert("Vary".to_string(), "Accept-Encoding".to_string());
+            response.insert("Set-Cookie".to_string(), "session=abc".to_string());
+            response
+        };
+        let policy = SecurityHeaders::new();
+
+        let mut merged = route_response();
+        policy.apply_headers_with(&mut merged, MergeMode::Merge);
+        assert_eq!(merged["content-security-policy"], route_csp);
+        assert!(!merged.contains_key("Content-Security-Policy"));
+        // Baseline protections are not the route's to weaken
+        assert_eq!(
+            merged["Strict-Transport-Security"],
+            policy.headers["Strict-Transport-Security"]
+        );
+        assert_eq!(merged["Set-Cookie"], "session=abc");
+
+        let mut forced = route_response();
+        policy.apply_headers_with(&mut forced, MergeMode::Force);
+        assert_eq!(forced["Content-Security-Policy"], DEFAULT_CSP);
+        assert!(!forced.contains_key("content-security-policy"));
+        assert_eq!(forced["Set-Cookie"], "session=abc");
+    }
+
+    #[test]
+    fn list_headers_are_appended_without_duplicates() {
+        let trial = SecurityHeaders::builder()
+            .report_only("/csp")
+            .build()
+            .unwrap();
+        let mut response = HashMap::new();
+        response.insert(
+            "Reporting-Endpoints".to_string(),
+            "default=\"/reports\"".to_string(),
+        );
+        trial.apply_headers(&mut response);
+        trial.apply_headers(&mut response);
+        assert_eq!(
+            response["Reporting-Endpoints"],
+            "default=\"/reports\", csp-endpoint=\"/csp\""
+        );
+    }
 }
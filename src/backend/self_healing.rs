@@ -11,13 +11,19 @@
 //! - **Service dependency tracking and priority-based restarts**
 //! - **Immutable state validation for corruption detection**
 //! - **Auto-remediation of detected issues**
+//! - **Remediation actions (restart, scale out) invoked by the monitoring system**
+//! - **Optional readiness check, so a restart that never comes up counts as failed**
 
+#[allow(dead_code)]
+#[path = "../cluster/startup.rs"]
+mod startup;
+
+use startup::StartupCheck;
 use std::collections::HashMap;
-use std::fs;
 use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
 const CHECK_INTERVAL: u64 = 10; // Seconds between health checks
 const MAX_RESTART_ATTEMPTS: u8 = 3;
@@ -27,23 +33,91 @@ struct Service {
     name: String,
     command: String,
     restart_attempts: u8,
-    last_checked: SystemTime,
+    startup: Option<StartupCheck>, // Readiness a (re)started instance must show
+}
+
+/// A metric that crossed its critical threshold
+#[allow(dead_code)] // Raised by `monitoring.rs`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Anomaly {
+    pub(crate) metric: String,
+    pub(crate) value: f32,
+    pub(crate) threshold: f32,
+}
+
+/// Something the monitor can invoke to fix an anomaly
+#[allow(dead_code)] // Invoked by `monitoring.rs`
+pub(crate) trait RemediationAction: Send + Sync {
+    fn remediate(&self, anomaly: &Anomaly);
+}
+
+/// What to do with a service when its anomaly fires
+#[allow(dead_code)] // Chosen when `monitoring.rs` binds a remediation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RemediationKind {
+    Restart,
+    ScaleOut,
+}
+
+/// Remediation bound to one registered service
+pub(crate) struct ServiceRemediation {
+    system: Arc<SelfHealingSystem>,
+    service: String,
+    kind: RemediationKind,
+}
+
+impl ServiceRemediation {
+    #[allow(dead_code)] // Bound to metrics by `monitoring.rs`
+    pub(crate) fn new(
+        system: Arc<SelfHealingSystem>,
+        service: &str,
+        kind: RemediationKind,
+    ) -> Self {
+        Self {
+            system,
+            service: service.to_string(),
+            kind,
+        }
+    }
+}
+
+impl RemediationAction for ServiceRemediation {
+    fn remediate(&self, anomaly: &Anomaly) {
+        println!(
+            "[REMEDIATION] {} at {} exceeds {}; {:?} {}",
+            anomaly.metric, anomaly.value, anomaly.threshold, self.kind, self.service
+        );
+        match self.kind {
+            RemediationKind::Restart => self.system.restart_service(&self.service),
+            RemediationKind::ScaleOut => self.system.scale_out(&self.service),
+        }
+    }
 }
 
 /// Self-healing system for monitoring and recovering services
-struct SelfHealingSystem {
+pub(crate) struct SelfHealingSystem {
     services: Mutex<HashMap<String, Service>>, // Stores services and their status
 }
 
 impl SelfHealingSystem {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             services: Mutex::new(HashMap::new()),
         }
     }
 
     /// Registers a new service for monitoring
-    fn register_service(&self, name: &str, command: &str) {
+    pub(crate) fn register_service(&self, name: &str, command: &str) {
+        self.register_service_with_check(name, command, None);
+    }
+
+    /// Registers a service whose instances only count as started once they pass `startup`
+    pub(crate) fn register_service_with_check(
+        &self,
+        name: &str,
+        command: &str,
+        startup: Option<StartupCheck>,
+    ) {
         let mut services = self.services.lock().unwrap();
         services.insert(
             name.to_string(),
@@ -51,7 +125,7 @@ impl SelfHealingSystem {
                 name: name.to_string(),
                 command: command.to_string(),
                 restart_attempts: 0,
-                last_checked: SystemTime::now(),
+                startup,
             },
         );
     }
@@ -73,12 +147,7 @@ impl SelfHealingSystem {
     fn recover_service(&self, service: &mut Service) {
         if service.restart_attempts < MAX_RESTART_ATTEMPTS {
             println!("[INFO] Restarting service: {}", service.name);
-            let _ = Command::new("sh")
-                .arg("-c")
-                .arg(&service.command)
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn();
+            SelfHealingSystem::start_instance(service);
             service.restart_attempts += 1;
         } else {
             println!(
@@ -88,6 +157,38 @@ impl SelfHealingSystem {
         }
     }
 
+    /// Stops a registered service and starts it again
+    pub(crate) fn restart_service(&self, name: &str) {
+        let mut services = self.services.lock().unwrap();
+        if let Some(service) = services.get_mut(name) {
+            let _ = Command::new("pkill").arg("-f").arg(name).status();
+            self.recover_service(service);
+        }
+    }
+
+    /// Starts one more instance of a registered service alongside the running ones
+    pub(crate) fn scale_out(&self, name: &str) {
+        let services = self.services.lock().unwrap();
+        if let Some(service) = services.get(name) {
+            println!("[INFO] Scaling out service: {}", service.name);
+            SelfHealingSystem::start_instance(service);
+        }
+    }
+
+    /// Starts one instance of `service`, waiting for its startup check if it has one.
+    /// An instance that fails the check has already been killed.
+    fn start_instance(service: &Service) {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&service.command)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Err(e) = startup::spawn_checked(&mut command, service.startup.as_ref()) {
+            println!("[ERROR] Service {} failed to start: {}", service.name, e);
+        }
+    }
+
     /// Checks if a service is currently running (simulated with process lookup)
     fn is_service_running(service_name: &str) -> bool {
         let output = Command::new("sh")
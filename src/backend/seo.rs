@@ -2,29 +2,25 @@
 //! SEO Trend Analyzer aligned with Zero Trust principles
 //! Rust standard library only – no external dependencies
 
+#[allow(dead_code)]
+#[path = "http_client.rs"]
+mod http_client;
+
 use std::fs::OpenOptions;
-use std::io::{Read, Write};
-use std::net::{TcpStream, ToSocketAddrs};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::thread::sleep;
 use std::time::{Duration, SystemTime};
 
-/// Perform a manual HTTP GET request via TCP socket
+/// Perform a manual HTTP GET request via TCP socket, bounded in time and size
 fn manual_http_get(host: &str, path: &str) -> std::io::Result<String> {
-    let addr = (host, 80).to_socket_addrs()?.next().unwrap();
-    let mut stream = TcpStream::connect_timeout(&addr, Duration::new(5, 0))?;
-
-    // Manually constructed HTTP GET request for explicit verification
-    let request = format!(
-        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Mozilla/5.0 (SEO Analyzer)\r\nConnection: close\r\n\r\n",
-        path, host
-    );
-
-    stream.write_all(request.as_bytes())?;
-
-    let mut response = String::new();
-    stream.read_to_string(&mut response)?;
-
-    Ok(response)
+    http_client::http_get(
+        host,
+        80,
+        path,
+        "Mozilla/5.0 (SEO Analyzer)",
+        &http_client::Limits::default(),
+    )
 }
 
 /// Manually parse HTML response to extract search result counts
@@ -96,3 +92,25 @@ fn main() {
         sleep(Duration::from_secs(10));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_counts_are_read_from_the_results_banner() {
+        assert_eq!(
+            extract_result_count("<span>About 1,230,000 results</span>"),
+            Some(1_230_000)
+        );
+        assert_eq!(extract_result_count("About 42 results"), Some(42));
+    }
+
+    #[test]
+    fn pages_without_a_usable_banner_yield_no_count() {
+        assert_eq!(extract_result_count(""), None);
+        assert_eq!(extract_result_count("About many results"), None);
+        assert_eq!(extract_result_count("About 12"), None);
+        assert_eq!(extract_result_count("No results"), None);
+    }
+}
@@ -0,0 +1,891 @@
+//! Zero Trust Server Facade
+//! Composes the router, middleware and access control into a single listening server.
+//! Features:
+//! - **Builder API: `Server::builder().route(...).run()`**
+//! - **Per-IP rate limiting before any routing work is done**
+//! - **Signed-token authentication (bearer header or session cookie) and RBAC checks**
+//! - **CORS preflight answers and per-origin response headers**
+//! - **Path traversal rejection ahead of route lookup**
+//! - **`/healthz` liveness and `/readyz` readiness probes over registered dependency checks**
+//! - **Security headers merged into every response, including errors**
+//! - **Per-request correlation ids (`X-Request-Id`) in every log line and response**
+//! - **Per-route handler latency and status metrics, optionally served to operators**
+//! - **Read and write timeouts on every connection (slow clients get 408)**
+//! - **Thread-per-connection request handling**
+
+#[allow(dead_code)]
+#[path = "access_control.rs"]
+mod access_control;
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "health.rs"]
+mod health;
+#[allow(dead_code)]
+#[path = "keys.rs"]
+mod keys;
+#[allow(dead_code)]
+#[path = "rate_limiter.rs"]
+mod rate_limiter;
+#[path = "router.rs"]
+mod router;
+#[allow(dead_code)]
+#[path = "security_headers.rs"]
+mod security_headers;
+#[allow(dead_code)]
+#[path = "util.rs"]
+mod util;
+
+pub use keys::{
+    EnvKeyProvider, KeyError, KeyProvider, StaticKeyProvider, ACCESS_SECRET, RATE_LIMIT_SECRET,
+};
+pub use router::{
+    parse_json, parse_multipart, serve_dir, Cookie, CorsBuilder, CorsMiddleware, CsrfError,
+    CsrfMiddleware, FingerprintMitigation, Headers, HttpRequest as Request,
+    HttpResponse as Response, JsonBodyError, JsonError, JsonValue, MultipartError, Part, RequestId,
+    ResponseBuilder, RouteMetrics, SameSite, StatusCode,
+};
+
+use access_control::AccessControl;
+use crypto::Zeroizing;
+use health::HealthChecks;
+use rate_limiter::rate_store::{GossipStore, DEFAULT_GOSSIP_INTERVAL};
+use rate_limiter::{Cidr, Limit, RateLimiter};
+use router::{Handler, ReadError, RouteMatch, Router, REQUEST_ID_HEADER};
+use security_headers::{SecurityHeaders, SecurityHeadersBuilder};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:8080";
+const BIND_ADDRESS_ENV: &str = "SERVER_BIND_ADDR";
+const GOSSIP_BIND_ENV: &str = "RATE_GOSSIP_BIND"; // UDP address for rate limit gossip
+const GOSSIP_PEERS_ENV: &str = "RATE_GOSSIP_PEERS"; // Comma-separated peer gossip addresses
+const ALLOWLIST_ENV: &str = "RATE_LIMIT_ALLOWLIST"; // Comma-separated exempt client addresses
+const TRUSTED_PROXIES_ENV: &str = "TRUSTED_PROXIES"; // Comma-separated proxy address blocks
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Cookie carrying the same signed token as `Authorization: Bearer`, for browsers
+pub const SESSION_COOKIE: &str = "session";
+
+/// Collects routes and middleware settings before the server starts
+pub struct ServerBuilder {
+    router: Router,
+    headers: SecurityHeadersBuilder,
+    rate_limiting: bool,
+    route_limits: HashMap<String, Limit>,
+    gossip: Option<(String, Vec<String>)>, // Bind address and peers for shared rate limits
+    allowlist: Vec<String>,
+    trusted_proxies: Vec<String>, // CIDR blocks, parsed by `build`
+    cors: Option<CorsMiddleware>,
+    csrf: Option<CsrfMiddleware>,
+    fingerprinting: Option<FingerprintMitigation>,
+    health: HealthChecks,
+    bind_address: String,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    log: Box<dyn Write + Send>,
+    keys: Arc<dyn KeyProvider>,
+}
+
+impl ServerBuilder {
+    /// Registers a public route
+    pub fn route<F>(mut self, method: &str, path: &str, handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.router
+            .add(method, path, None, Arc::new(handler) as Handler);
+        self
+    }
+
+    /// Registers a route that requires a signed bearer token whose role grants `permission`
+    pub fn protected_route<F>(
+        mut self,
+        method: &str,
+        path: &str,
+        permission: &str,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.router
+            .add(method, path, Some(permission), Arc::new(handler) as Handler);
+        self
+    }
+
+    /// Serves files under `root` for GET requests whose path starts with `prefix`
+    pub fn serve_dir<P: Into<PathBuf>>(mut self, prefix: &str, root: P) -> Self {
+        let root = root.into();
+        let prefix = prefix.trim_end_matches('/').to_string();
+        let strip = prefix.clone();
+        let handler = move |request: &Request| {
+            let path = request.path.strip_prefix(&strip).unwrap_or(&request.path);
+            router::serve_static(&root, path)
+        };
+        self.router.mount(&prefix, Arc::new(handler) as Handler);
+        self
+    }
+
+    /// Serves per-route latency and status metrics in the Prometheus text format at
+    /// `GET path` to callers whose role grants `permission`
+    pub fn metrics_route(mut self, path: &str, permission: &str) -> Self {
+        let metrics = self.router.metrics();
+        let handler = move |_: &Request| {
+            Response::ok(&metrics.render_prometheus())
+                .with_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+        };
+        self.router
+            .add("GET", path, Some(permission), Arc::new(handler) as Handler);
+        self
+    }
+
+    /// Overrides the default Content-Security-Policy sent with every response
+    pub fn content_security_policy(mut self, policy: &str) -> Self {
+        self.headers = self.headers.csp(policy);
+        self
+    }
+
+    /// Answers CORS preflights and adds `Access-Control-Allow-*` headers per `cors`
+    pub fn cors(mut self, cors: CorsMiddleware) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Requires a CSRF token on every state-changing request and adds one to each
+    /// form in HTML responses; requests without a valid token get 403
+    pub fn csrf(mut self, csrf: CsrfMiddleware) -> Self {
+        self.csrf = Some(csrf);
+        self
+    }
+
+    /// Normalizes identifying request headers per `mitigation` before routes see them
+    pub fn fingerprint_mitigation(mut self, mitigation: FingerprintMitigation) -> Self {
+        self.fingerprinting = Some(mitigation);
+        self
+    }
+
+    /// Makes `GET /readyz` answer 503 while `check` fails, e.g. when the database is
+    /// unreachable. `check` runs on every probe, so it should return quickly.
+    pub fn readiness_check<F>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.health.register(name, check);
+        self
+    }
+
+    /// Enables or disables per-IP rate limiting (enabled by default)
+    pub fn rate_limiting(mut self, enabled: bool) -> Self {
+        self.rate_limiting = enabled;
+        self
+    }
+
+    /// Gives `path` its own limit of `max_requests` per `window` for each client,
+    /// tracked apart from the default limit shared by every other route
+    pub fn route_rate_limit(mut self, path: &str, max_requests: u64, window: Duration) -> Self {
+        self.route_limits
+            .insert(path.to_string(), Limit::new(max_requests, window));
+        self
+    }
+
+    /// Shares rate limit counts with the servers at `peers` over UDP on `bind`, so a
+    /// client's allowance covers the whole cluster rather than each node. Overrides
+    /// `RATE_GOSSIP_BIND`/`RATE_GOSSIP_PEERS`; every node needs the same `RATE_LIMIT_SECRET`.
+    pub fn rate_gossip(mut self, bind: &str, peers: &[&str]) -> Self {
+        let peers = peers.iter().map(|peer| peer.to_string()).collect();
+        self.gossip = Some((bind.to_string(), peers));
+        self
+    }
+
+    /// Never rate limits `clients`, such as health checkers and internal services.
+    /// Adds to `RATE_LIMIT_ALLOWLIST`.
+    pub fn rate_limit_allowlist(mut self, clients: &[&str]) -> Self {
+        self.allowlist
+            .extend(clients.iter().map(|client| client.to_string()));
+        self
+    }
+
+    /// Takes the client address from `X-Forwarded-For` when the connection comes from
+    /// one of `proxies` (addresses or CIDR blocks). Adds to `TRUSTED_PROXIES`.
+    pub fn trusted_proxies(mut self, proxies: &[&str]) -> Self {
+        self.trusted_proxies
+            .extend(proxies.iter().map(|proxy| proxy.to_string()));
+        self
+    }
+
+    /// Sets the address used by `run`, overriding `SERVER_BIND_ADDR` and the default
+    pub fn bind(mut self, addr: &str) -> Self {
+        self.bind_address = addr.to_string();
+        self
+    }
+
+    /// Sets how long a client has to send a complete request before it is answered
+    /// with 408 and disconnected (default 10 seconds)
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Sets how long writing a response may block on a slow reader (default 10 seconds)
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sends access and security event lines to `sink` instead of stdout
+    pub fn log_to<W: Write + Send + 'static>(mut self, sink: W) -> Self {
+        self.log = Box::new(sink);
+        self
+    }
+
+    /// Reads the token signing secret (`ACCESS_SECRET`) from `keys` instead of the
+    /// environment (`ZTW_ACCESS_SECRET` or `ZTW_ACCESS_SECRET_FILE`)
+    pub fn key_provider<K: KeyProvider + 'static>(mut self, keys: K) -> Self {
+        self.keys = Arc::new(keys);
+        self
+    }
+
+    /// Validates the configuration and assembles the server; fails if the
+    /// token signing secret is not configured, a trusted proxy is not an address block,
+    /// or gossip is enabled without its secret
+    pub fn build(self) -> Result<Server, String> {
+        let headers = self.headers.build()?;
+        let access_secret = self
+            .keys
+            .require(ACCESS_SECRET)
+            .map_err(|e| e.to_string())?;
+        let rate_limiter = if self.rate_limiting {
+            let trusted_proxies = self
+                .trusted_proxies
+                .iter()
+                .map(|proxy| {
+                    Cidr::parse(proxy).ok_or_else(|| format!("invalid trusted proxy: {}", proxy))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let limiter = RateLimiter::new_with_routes(self.route_limits)
+                .with_allowlist(self.allowlist)
+                .with_trusted_proxies(trusted_proxies);
+            Some(match &self.gossip {
+                Some((bind, peers)) => {
+                    let store = gossip_store(bind, peers, self.keys.as_ref())?;
+                    limiter.with_store(Box::new(store))
+                }
+                None => limiter,
+            })
+        } else {
+            None
+        };
+        Ok(Server {
+            state: Arc::new(ServerState {
+                router: self.router,
+                rate_limiter,
+                access_control: AccessControl::new(),
+                access_secret: Zeroizing::new(access_secret),
+                cors: self.cors,
+                csrf: self.csrf,
+                fingerprinting: self.fingerprinting,
+                health: self.health,
+                headers,
+                read_timeout: self.read_timeout,
+                write_timeout: self.write_timeout,
+                log: Mutex::new(self.log),
+            }),
+        })
+    }
+
+    /// Builds the server and serves on the configured address until the listener fails
+    pub fn run(self) -> io::Result<()> {
+        let addr = self.bind_address.clone();
+        let server = self
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        server.listen(addr)
+    }
+}
+
+/// Shared, immutable server configuration handed to every connection thread
+struct ServerState {
+    router: Router,
+    rate_limiter: Option<RateLimiter>,
+    access_control: AccessControl,
+    access_secret: Zeroizing<Vec<u8>>, // Signs and verifies bearer/session tokens
+    cors: Option<CorsMiddleware>,
+    csrf: Option<CsrfMiddleware>,
+    fingerprinting: Option<FingerprintMitigation>,
+    health: HealthChecks,
+    headers: SecurityHeaders,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    log: Mutex<Box<dyn Write + Send>>,
+}
+
+/// A configured server; cheap to clone and share between threads
+#[derive(Clone)]
+pub struct Server {
+    state: Arc<ServerState>,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder {
+            router: Router::new(),
+            headers: SecurityHeaders::builder(),
+            rate_limiting: true,
+            route_limits: HashMap::new(),
+            gossip: gossip_from_env(),
+            allowlist: env_list(ALLOWLIST_ENV),
+            trusted_proxies: env_list(TRUSTED_PROXIES_ENV),
+            cors: None,
+            csrf: None,
+            fingerprinting: None,
+            health: HealthChecks::new(),
+            bind_address: util::bind_address(BIND_ADDRESS_ENV, DEFAULT_BIND_ADDRESS),
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            log: Box::new(io::stdout()),
+            keys: Arc::new(EnvKeyProvider),
+        }
+    }
+
+    /// Handler timings per route, for latency percentiles and status tallies
+    pub fn route_metrics(&self) -> Arc<RouteMetrics> {
+        self.state.router.metrics()
+    }
+
+    /// Issues a signed bearer token for `user` acting as `role`
+    pub fn issue_token(&self, user: &str, role: &str) -> String {
+        access_control::generate_signed_token(user, role, &self.state.access_secret)
+    }
+
+    /// Binds `addr` and serves connections until the listener fails
+    pub fn listen<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        self.serve(TcpListener::bind(addr)?)
+    }
+
+    /// Serves connections from an already bound listener (e.g. one bound to port 0)
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        println!("[SERVER] Listening on {}", listener.local_addr()?);
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let server = self.clone();
+                    thread::spawn(move || server.handle_connection(stream));
+                }
+                Err(e) => eprintln!("[SERVER] Connection failed: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) {
+        if let Err(e) = stream.set_write_timeout(Some(self.state.write_timeout)) {
+            eprintln!("[SERVER] Failed to set write timeout: {}", e);
+            return;
+        }
+        let response = match router::parse_request(&mut stream, self.state.read_timeout) {
+            Ok(mut request) => self.handle(&mut request),
+            Err(ReadError::Malformed) => {
+                let request_id = RequestId::generate();
+                self.log_event(&request_id, "ACCESS", "malformed request -> 400");
+                self.finish(
+                    Response::new(StatusCode::BAD_REQUEST, "Malformed request"),
+                    &request_id,
+                )
+            }
+            Err(ReadError::Overloaded) => {
+                let request_id = RequestId::generate();
+                self.log_event(
+                    &request_id,
+                    "ACCESS",
+                    "request body budget exhausted -> 503",
+                );
+                self.finish(
+                    Response::new(StatusCode::SERVICE_UNAVAILABLE, "Server busy")
+                        .with_header("Retry-After", "1"),
+                    &request_id,
+                )
+            }
+            Err(ReadError::TimedOut) => {
+                let request_id = RequestId::generate();
+                self.log_event(&request_id, "ACCESS", "request read timed out -> 408");
+                self.finish(
+                    Response::new(StatusCode::REQUEST_TIMEOUT, "Request timeout"),
+                    &request_id,
+                )
+            }
+        };
+        if let Err(e) = stream.write_all(&response.to_bytes()) {
+            eprintln!("[SERVER] Failed to write response: {}", e);
+        }
+    }
+
+    /// Runs a parsed request through rate limiting, routing, auth and access control
+    pub fn handle(&self, request: &mut Request) -> Response {
+        let mut response = self.dispatch(request);
+        if let Some(csrf) = &self.state.csrf {
+            if let Err(e) = csrf.inject(request, &mut response) {
+                self.log_event(
+                    &request.request_id,
+                    "CSRF",
+                    &format!("could not issue form tokens: {}", e),
+                );
+            }
+        }
+        if let Some(cors) = &self.state.cors {
+            cors.apply(request, &mut response);
+        }
+        self.log_event(
+            &request.request_id,
+            "ACCESS",
+            &format!(
+                "peer={} {} {} -> {}",
+                client_id(&self.state, request),
+                request.method,
+                request.path,
+                response.status.as_u16()
+            ),
+        );
+        self.finish(response, &request.request_id)
+    }
+
+    fn dispatch(&self, request: &mut Request) -> Response {
+        let state = &self.state;
+        if let Some(rate_limiter) = &state.rate_limiter {
+            let client = client_id(state, request);
+            if !rate_limiter.allow_request_for(&request.path, &client) {
+                self.log_event(
+                    &request.request_id,
+                    "RATE-LIMIT",
+                    &format!("rejected client={}", client),
+                );
+                return Response::new(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded");
+            }
+        }
+
+        if !router::is_safe_path(&request.path) {
+            self.log_event(&request.request_id, "SECURITY", "path traversal attempt");
+            return Response::new(StatusCode::BAD_REQUEST, "Invalid path");
+        }
+
+        if let Some(probe) = state.health.respond(&request.method, &request.path) {
+            let status =
+                StatusCode::from_u16(probe.status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+            return Response::new(status, &probe.body)
+                .with_header("Content-Type", "application/json")
+                .with_header("Cache-Control", "no-store");
+        }
+
+        if let Some(preflight) = state.cors.as_ref().and_then(|cors| cors.preflight(request)) {
+            return preflight;
+        }
+
+        let route = match state.router.resolve(&request.method, &request.path) {
+            RouteMatch::Found(route) => route,
+            RouteMatch::MethodNotAllowed => {
+                return Response::new(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
+            RouteMatch::NotFound => return Response::new(StatusCode::NOT_FOUND, "Not found"),
+        };
+
+        if let Some(Err(e)) = state.csrf.as_ref().map(|csrf| csrf.verify(request)) {
+            self.log_event(&request.request_id, "CSRF", &e.to_string());
+            return Response::new(StatusCode::FORBIDDEN, "Invalid CSRF token");
+        }
+
+        if let Some(permission) = &route.permission {
+            let (user, role) = match request_identity(request, &state.access_secret) {
+                Some(identity) => identity,
+                None => {
+                    self.log_event(&request.request_id, "AUTH", "missing or invalid token");
+                    return Response::new(StatusCode::UNAUTHORIZED, "Authentication required")
+                        .with_header("WWW-Authenticate", "Bearer");
+                }
+            };
+            if !state.access_control.has_permission(&role, permission) {
+                self.log_event(
+                    &request.request_id,
+                    "AUTH",
+                    &format!("user={} role={} lacks {}", user, role, permission),
+                );
+                return Response::new(StatusCode::FORBIDDEN, "Forbidden");
+            }
+            request.principal = Some(user);
+        }
+
+        if let Some(fingerprinting) = &state.fingerprinting {
+            fingerprinting.apply(request);
+        }
+
+        state.router.invoke(route, request)
+    }
+
+    /// Applies the security header policy without clobbering route-specific values
+    /// and tags it with the request's correlation id
+    fn finish(&self, mut response: Response, request_id: &RequestId) -> Response {
+        self.state
+            .headers
+            .apply_headers(response.headers.as_map_mut());
+        response
+            .headers
+            .insert(REQUEST_ID_HEADER, request_id.as_str());
+        response
+    }
+
+    /// Writes one `[CATEGORY] request_id=... message` line to the configured sink
+    fn log_event(&self, request_id: &RequestId, category: &str, message: &str) {
+        let mut log = self.state.log.lock().unwrap();
+        let _ = writeln!(log, "[{}] request_id={} {}", category, request_id, message);
+    }
+}
+
+/// `RATE_GOSSIP_BIND` and `RATE_GOSSIP_PEERS`, when gossip is configured
+fn gossip_from_env() -> Option<(String, Vec<String>)> {
+    let bind = std::env::var(GOSSIP_BIND_ENV)
+        .ok()
+        .filter(|bind| !bind.is_empty())?;
+    Some((bind, env_list(GOSSIP_PEERS_ENV)))
+}
+
+/// The comma-separated entries of `env_var`, empty when it is unset
+fn env_list(env_var: &str) -> Vec<String> {
+    std::env::var(env_var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Binds the gossip socket and resolves every peer, failing on any bad address
+fn gossip_store(
+    bind: &str,
+    peers: &[String],
+    keys: &dyn KeyProvider,
+) -> Result<GossipStore, String> {
+    let secret = keys.require(RATE_LIMIT_SECRET).map_err(|e| e.to_string())?;
+    let mut addresses: Vec<SocketAddr> = Vec::new();
+    for peer in peers {
+        let resolved = peer
+            .to_socket_addrs()
+            .map_err(|e| format!("rate gossip peer {}: {}", peer, e))?;
+        addresses.extend(resolved);
+    }
+    GossipStore::bind(bind, addresses, secret, DEFAULT_GOSSIP_INTERVAL)
+        .map_err(|e| format!("rate gossip on {}: {}", bind, e))
+}
+
+/// Identifies the client for rate limiting and logs
+fn client_id(state: &ServerState, request: &Request) -> String {
+    match &state.rate_limiter {
+        Some(rate_limiter) => {
+            rate_limiter.client_identifier(request.peer, request.header(FORWARDED_FOR_HEADER))
+        }
+        None => request
+            .peer
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+    }
+}
+
+/// Authenticates from the `Authorization` header, or else the session cookie.
+/// A header that is present but invalid is not retried against the cookie.
+fn request_identity(request: &Request, key: &[u8]) -> Option<(String, String)> {
+    match request.header("Authorization") {
+        Some(authorization) => bearer_identity(authorization, key),
+        None => token_identity(request.cookie(SESSION_COOKIE)?.trim(), key),
+    }
+}
+
+/// Extracts `(user, role)` from a valid `Bearer user:role.signature` header
+fn bearer_identity(authorization: &str, key: &[u8]) -> Option<(String, String)> {
+    token_identity(authorization.strip_prefix("Bearer ")?.trim(), key)
+}
+
+/// Extracts `(user, role)` from a valid `user:role.signature` token
+fn token_identity(token: &str, key: &[u8]) -> Option<(String, String)> {
+    if !access_control::verify_signed_token(token, key) {
+        return None;
+    }
+    let (data, _) = token.split_once('.')?;
+    let (user, role) = data.split_once(':')?;
+    Some((user.to_string(), role.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Log sink the test can read back after the server has written to it
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedLog {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_builder() -> ServerBuilder {
+        Server::builder()
+            .key_provider(StaticKeyProvider::new().with(ACCESS_SECRET, &[9; keys::MIN_SECRET_LEN]))
+            .log_to(io::sink())
+    }
+
+    /// Builds the server and serves it on an ephemeral loopback port
+    fn start(builder: ServerBuilder) -> (Server, SocketAddr) {
+        let server = builder.build().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let serving = server.clone();
+        thread::spawn(move || serving.serve(listener));
+        (server, addr)
+    }
+
+    /// Sends one raw request and returns everything the server wrote before closing
+    fn send(addr: SocketAddr, raw: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn get(addr: SocketAddr, path: &str, extra_headers: &str) -> String {
+        send(
+            addr,
+            &format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+                path, extra_headers
+            ),
+        )
+    }
+
+    #[test]
+    fn server_answers_requests_end_to_end() {
+        let (server, addr) = start(
+            test_builder()
+                .route("GET", "/health", |_| Response::ok("ok"))
+                .protected_route("GET", "/admin", "ALL", |req| {
+                    Response::ok(&format!("hello {}", req.principal.as_deref().unwrap_or("")))
+                }),
+        );
+
+        let response = get(addr, "/health", "");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("X-Content-Type-Options: nosniff\r\n"));
+        assert!(response.contains("X-Request-Id: "));
+        assert!(response.ends_with("\r\n\r\nok"));
+
+        let response = get(addr, "/admin", "");
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+        let token = server.issue_token("alice", "admin");
+        let response = get(
+            addr,
+            "/admin",
+            &format!("Authorization: Bearer {}\r\n", token),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("hello alice"));
+
+        let token = server.issue_token("bob", "guest");
+        let response = get(
+            addr,
+            "/admin",
+            &format!("Authorization: Bearer {}\r\n", token),
+        );
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        let response = get(addr, "/missing", "");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    /// The value of `name` in a raw response
+    fn response_header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+        response
+            .split("\r\n\r\n")
+            .next()?
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+    }
+
+    #[test]
+    fn rate_limited_request_is_logged_under_one_request_id() {
+        let log = SharedLog::default();
+        let (_, addr) = start(
+            test_builder()
+                .log_to(log.clone())
+                .route("GET", "/limited", |_| Response::ok("ok"))
+                .route_rate_limit("/limited", 1, Duration::from_secs(60)),
+        );
+
+        assert!(get(addr, "/limited", "").starts_with("HTTP/1.1 200 OK\r\n"));
+        let rejected = get(addr, "/limited", "");
+        assert!(rejected.starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+        let request_id = response_header(&rejected, REQUEST_ID_HEADER).unwrap();
+
+        let tagged: Vec<String> = log
+            .contents()
+            .lines()
+            .filter(|line| line.contains(&format!("request_id={} ", request_id)))
+            .map(str::to_string)
+            .collect();
+        assert_eq!(tagged.len(), 2, "{:?}", tagged);
+        assert!(tagged[0].starts_with("[RATE-LIMIT] "));
+        assert!(tagged[1].starts_with("[ACCESS] ") && tagged[1].ends_with("-> 429"));
+    }
+
+    #[test]
+    fn incoming_request_id_is_propagated_only_when_valid() {
+        let (_, addr) = start(test_builder().route("GET", "/", |_| Response::ok("ok")));
+
+        let response = get(addr, "/", "X-Request-Id: upstream-42.a_b\r\n");
+        assert_eq!(
+            response_header(&response, REQUEST_ID_HEADER),
+            Some("upstream-42.a_b")
+        );
+
+        let response = get(addr, "/", "X-Request-Id: bad id\"injected\r\n");
+        let generated = response_header(&response, REQUEST_ID_HEADER).unwrap();
+        assert_eq!(generated.len(), 32);
+        assert!(generated.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn session_cookie_authenticates_when_there_is_no_bearer_header() {
+        let (server, addr) = start(test_builder().protected_route("GET", "/me", "ALL", |req| {
+            Response::ok(req.principal.as_deref().unwrap_or(""))
+        }));
+        let token = server.issue_token("alice", "admin");
+
+        let response = get(
+            addr,
+            "/me",
+            &format!("Cookie: theme=dark; {}={}\r\n", SESSION_COOKIE, token),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("alice"));
+
+        let response = get(
+            addr,
+            "/me",
+            &format!("Cookie: {}=alice:admin.forged\r\n", SESSION_COOKIE),
+        );
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+        // A bad Authorization header is not rescued by a good cookie
+        let response = get(
+            addr,
+            "/me",
+            &format!(
+                "Authorization: Bearer nope\r\nCookie: {}={}\r\n",
+                SESSION_COOKIE, token
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    }
+
+    fn post_form(addr: SocketAddr, path: &str, extra_headers: &str, body: &str) -> String {
+        send(
+            addr,
+            &format!(
+                "POST {} HTTP/1.1\r\nHost: localhost\r\n\
+                 Content-Type: application/x-www-form-urlencoded\r\n\
+                 Content-Length: {}\r\n{}\r\n{}",
+                path,
+                body.len(),
+                extra_headers,
+                body
+            ),
+        )
+    }
+
+    #[test]
+    fn state_changing_requests_need_the_csrf_token_from_their_form() {
+        let (_, addr) = start(
+            test_builder()
+                .csrf(CsrfMiddleware::new())
+                .route("GET", "/form", |_| {
+                    Response::ok("<form method=\"post\" action=\"/submit\"></form>")
+                        .with_header("Content-Type", "text/html; charset=utf-8")
+                })
+                .route("POST", "/submit", |_| Response::ok("saved")),
+        );
+
+        // A GET needs no token, and hands out a cookie and a token in the form
+        let page = get(addr, "/form", "");
+        assert!(page.starts_with("HTTP/1.1 200 OK\r\n"), "{}", page);
+        let browser_id = response_header(&page, "Set-Cookie")
+            .and_then(|cookie| cookie.strip_prefix("csrf_id="))
+            .and_then(|cookie| cookie.split(';').next())
+            .expect("a csrf_id cookie");
+        let token = page
+            .split("name=\"csrf_token\" value=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .expect("a token field in the form");
+        let cookie = format!("Cookie: csrf_id={}\r\n", browser_id);
+
+        let response = post_form(addr, "/submit", &cookie, "name=alice");
+        assert!(
+            response.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+            "{}",
+            response
+        );
+        let forged = post_form(addr, "/submit", &cookie, "csrf_token=forged");
+        assert!(forged.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        let body = format!("name=alice&csrf_token={}", token);
+        let response = post_form(addr, "/submit", &cookie, &body);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("saved"));
+
+        // Tokens are single use
+        let replayed = post_form(addr, "/submit", &cookie, &body);
+        assert!(replayed.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    }
+
+    #[test]
+    fn probes_answer_before_routing_and_report_failed_dependencies() {
+        let (_, addr) = start(
+            test_builder()
+                .readiness_check("database", || Err("unreachable".to_string()))
+                .protected_route("GET", "/healthz", "ALL", |_| Response::ok("shadowed")),
+        );
+        let live = get(addr, "/healthz", "");
+        assert!(live.starts_with("HTTP/1.1 200 OK\r\n"), "{}", live);
+        assert!(live.ends_with("{\"status\":\"ok\"}"));
+
+        let ready = get(addr, "/readyz", "");
+        assert!(
+            ready.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+            "{}",
+            ready
+        );
+        assert!(ready.contains("\"error\":\"unreachable\""));
+    }
+}
@@ -7,80 +7,480 @@
 //! - **Multi-Factor Authentication (MFA) enforcement per session**
 //! - **Adaptive session timeout based on risk scoring**
 //! - **Secure token storage with in-memory session cache**
-//! - **Tamper-proof session validation with cryptographic hashing**
+//! - **Optional encrypted on-disk persistence, so sessions survive a restart**
+//! - **Logout-everywhere: per-user session epochs reject every token issued before a revocation**
+//! - **Tamper-proof session validation with HMAC-SHA256 under a provisioned secret**
 //! - **IP-bound session restrictions to prevent hijacking**
 //! - **Real-time session monitoring and anomaly detection**
 
-use std::collections::HashMap;
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "keys.rs"]
+mod keys;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use crypto::{constant_time_eq, NonceSequence, Zeroizing};
+use keys::KeyProvider;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const SESSION_EXPIRATION: u64 = 3600; // 1-hour session timeout
-const SECRET_KEY: &str = "super_secure_session_secret";
+const REFRESH_EXPIRATION: u64 = 7 * 24 * 3600; // 7-day refresh token lifetime
+const EVENT_KEY_INFO: &[u8] = b"ui-event-signing";
+const STORE_KEY_INFO: &[u8] = b"session-store-persistence";
+const STORE_FILE: &str = "db/sessions.store";
+const STORE_MAGIC: &[u8] = b"ZTSESS1";
+const STORE_NONCE_LEN: usize = 12;
+const MAX_STORE_FIELD: usize = 64 * 1024; // Far above any token, user name or IP
+
+/// Draws `len` random bytes and hex-encodes them
+fn random_hex(len: usize) -> io::Result<String> {
+    let mut bytes = vec![0u8; len];
+    crypto::random_bytes(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Key sealing the persisted store; derived like the event keys, under its own label
+fn store_key(secret: &[u8]) -> Zeroizing<[u8; 32]> {
+    let okm = Zeroizing::new(crypto::hkdf(secret, b"", STORE_KEY_INFO, 32));
+    Zeroizing::new(
+        okm.as_slice()
+            .try_into()
+            .expect("HKDF returned the requested length"),
+    )
+}
+
+fn now_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn write_field(out: &mut Vec<u8>, field: &str) {
+    out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field.as_bytes());
+}
+
+fn read_field<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_STORE_FIELD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "session store field too large",
+        ));
+    }
+    let mut field = vec![0u8; len];
+    reader.read_exact(&mut field)?;
+    String::from_utf8(field).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+type SessionTable = HashMap<String, (String, u64, String)>;
+
+/// Everything the store keeps on disk
+struct StoreContents {
+    sessions: SessionTable,
+    refresh_tokens: SessionTable,
+    epochs: HashMap<String, u64>,
+}
 
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
+/// Groups session tokens by the user they belong to
+fn index_by_user(sessions: &SessionTable) -> HashMap<String, HashSet<String>> {
+    let mut index: HashMap<String, HashSet<String>> = HashMap::new();
+    for (token, (user, _, _)) in sessions {
+        index.entry(user.clone()).or_default().insert(token.clone());
+    }
+    index
+}
+
+/// The `"epoch"` claim of a token payload; tokens minted before epochs existed read as 0.
+/// The claim is the payload's last field, so search from the end past any user-supplied text.
+fn payload_epoch(payload: &str) -> Option<u64> {
+    let value = match payload.rsplit_once("\"epoch\":") {
+        Some((_, rest)) => rest,
+        None => return Some(0),
+    };
+    let digits = value.split(|c: char| !c.is_ascii_digit()).next()?;
+    digits.parse().ok()
+}
 
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
+/// Serializes one table as a count followed by (key, user, expiry, IP) records
+fn encode_table(out: &mut Vec<u8>, table: &SessionTable) {
+    let mut records: Vec<_> = table.iter().collect();
+    records.sort_by(|a, b| a.0.cmp(b.0));
+    out.extend_from_slice(&(records.len() as u64).to_be_bytes());
+    for (key, (user, expiry, ip)) in records {
+        write_field(out, key);
+        write_field(out, user);
+        out.extend_from_slice(&expiry.to_be_bytes());
+        write_field(out, ip);
     }
+}
 
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
+/// Reads a table written by `encode_table`, dropping records that expired before `now`
+fn decode_table<R: Read>(reader: &mut R, now: u64) -> io::Result<SessionTable> {
+    let mut count = [0u8; 8];
+    reader.read_exact(&mut count)?;
+    let count = u64::from_be_bytes(count);
+    let mut table = HashMap::new();
+    for _ in 0..count {
+        let key = read_field(reader)?;
+        let user = read_field(reader)?;
+        let mut expiry = [0u8; 8];
+        reader.read_exact(&mut expiry)?;
+        let expiry = u64::from_be_bytes(expiry);
+        let ip = read_field(reader)?;
+        if expiry >= now {
+            table.insert(key, (user, expiry, ip));
+        }
+    }
+    Ok(table)
 }
 
-/// Secure session store
-struct SessionStore {
-    sessions: Mutex<HashMap<String, (String, u64, String)>>, // Token -> (User, Expiry, IP)
+/// Access and refresh tokens issued together at login
+#[derive(Debug, Clone)]
+pub(crate) struct SessionTokens {
+    pub(crate) access_token: String,
+    pub(crate) refresh_token: String,
+}
+
+/// Secure session store. Locks are always taken in field order.
+pub(crate) struct SessionStore {
+    // Held by `persist` from snapshot to rename, so an older snapshot never lands last
+    persisting: Mutex<()>,
+    sessions: Mutex<SessionTable>,       // Token -> (User, Expiry, IP)
+    refresh_tokens: Mutex<SessionTable>, // Refresh -> (User, Expiry, IP)
+    user_tokens: Mutex<HashMap<String, HashSet<String>>>, // User -> live session tokens
+    epochs: Mutex<HashMap<String, u64>>, // User -> session epoch; only ever increases
+    path: Option<PathBuf>,               // Encrypted copy on disk, if persistent
+    secret: Zeroizing<Vec<u8>>,          // Signs tokens; root of the event and store keys
+    store_nonces: Mutex<Option<NonceSequence>>, // Seals the store file; started on first persist
 }
 
 impl SessionStore {
-    fn new() -> Self {
+    /// An in-memory store; `secret` is normally `keys::SESSION_SECRET` from a `KeyProvider`
+    pub(crate) fn new(secret: Vec<u8>) -> Self {
         Self {
+            persisting: Mutex::new(()),
+            secret: Zeroizing::new(secret),
             sessions: Mutex::new(HashMap::new()),
+            refresh_tokens: Mutex::new(HashMap::new()),
+            user_tokens: Mutex::new(HashMap::new()),
+            epochs: Mutex::new(HashMap::new()),
+            path: None,
+            store_nonces: Mutex::new(None),
+        }
+    }
+
+    /// A store backed by the encrypted file at `path`, starting from its live records.
+    /// A missing file starts empty; a file that fails to decrypt is an error, not a reset.
+    pub(crate) fn new_persistent(path: impl AsRef<Path>, secret: Vec<u8>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let contents = match fs::read(&path) {
+            Ok(sealed) => Self::decode(&sealed, &store_key(&secret))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => StoreContents {
+                sessions: HashMap::new(),
+                refresh_tokens: HashMap::new(),
+                epochs: HashMap::new(),
+            },
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            persisting: Mutex::new(()),
+            user_tokens: Mutex::new(index_by_user(&contents.sessions)),
+            sessions: Mutex::new(contents.sessions),
+            refresh_tokens: Mutex::new(contents.refresh_tokens),
+            epochs: Mutex::new(contents.epochs),
+            path: Some(path),
+            secret: Zeroizing::new(secret),
+            store_nonces: Mutex::new(None),
+        })
+    }
+
+    /// Writes every session, refresh token and user epoch to disk, sealed with ChaCha20-Poly1305.
+    /// The file is written beside the target and renamed, so a crash never leaves a torn store.
+    /// Concurrent calls are serialized, so the file always holds the latest snapshot taken.
+    /// Does nothing for an in-memory store.
+    pub(crate) fn persist(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let _persisting = self
+            .persisting
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut content = Vec::new();
+        encode_table(&mut content, &self.sessions.lock().unwrap());
+        encode_table(&mut content, &self.refresh_tokens.lock().unwrap());
+        {
+            let epochs = self.epochs.lock().unwrap();
+            let mut epochs: Vec<_> = epochs.iter().collect();
+            epochs.sort();
+            content.extend_from_slice(&(epochs.len() as u64).to_be_bytes());
+            for (user, epoch) in epochs {
+                write_field(&mut content, user);
+                content.extend_from_slice(&epoch.to_be_bytes());
+            }
+        }
+
+        let nonce = {
+            let mut store_nonces = self.store_nonces.lock().unwrap();
+            let nonces = match store_nonces.as_mut() {
+                Some(nonces) => nonces,
+                None => store_nonces.insert(NonceSequence::new()?),
+            };
+            nonces.next_nonce()?
+        };
+        let mut sealed = STORE_MAGIC.to_vec();
+        sealed.extend_from_slice(&nonce);
+        sealed.extend(crypto::chacha20poly1305_seal(
+            &store_key(&self.secret),
+            &nonce,
+            STORE_MAGIC,
+            &content,
+        ));
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut staging = path.clone().into_os_string();
+        staging.push(".tmp");
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600); // Owner read/write only
+        let mut file = options.open(&staging)?;
+        file.write_all(&sealed)?;
+        file.sync_all()?;
+        fs::rename(&staging, path)
+    }
+
+    fn decode(sealed: &[u8], key: &[u8; 32]) -> io::Result<StoreContents> {
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason);
+        let body = sealed
+            .strip_prefix(STORE_MAGIC)
+            .ok_or_else(|| invalid("not a session store"))?;
+        if body.len() < STORE_NONCE_LEN {
+            return Err(invalid("session store is truncated"));
+        }
+        let (nonce, ciphertext) = body.split_at(STORE_NONCE_LEN);
+        let nonce: [u8; STORE_NONCE_LEN] = nonce.try_into().unwrap();
+        let content = crypto::chacha20poly1305_open(key, &nonce, STORE_MAGIC, ciphertext)
+            .ok_or_else(|| invalid("session store failed authentication"))?;
+
+        let now = now_seconds();
+        let mut reader = content.as_slice();
+        let sessions = decode_table(&mut reader, now)?;
+        let refresh_tokens = decode_table(&mut reader, now)?;
+        let mut count = [0u8; 8];
+        reader.read_exact(&mut count)?;
+        let mut epochs = HashMap::new();
+        for _ in 0..u64::from_be_bytes(count) {
+            let user = read_field(&mut reader)?;
+            let mut epoch = [0u8; 8];
+            reader.read_exact(&mut epoch)?;
+            epochs.insert(user, u64::from_be_bytes(epoch));
+        }
+        if !reader.is_empty() {
+            return Err(invalid("trailing data in session store"));
+        }
+        Ok(StoreContents {
+            sessions,
+            refresh_tokens,
+            epochs,
+        })
+    }
+
+    /// Rewrites the disk copy after a removal, so a revoked or redeemed token
+    /// cannot come back on restart
+    fn persist_removal(&self) {
+        if let Err(e) = self.persist() {
+            eprintln!("[SESSION] Failed to persist session store: {}", e);
+        }
+    }
+
+    /// Drops expired sessions and refresh tokens from memory and from disk;
+    /// returns how many were removed
+    pub(crate) fn sweep_expired(&self) -> io::Result<usize> {
+        let now = now_seconds();
+        let mut removed = 0;
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            let mut user_tokens = self.user_tokens.lock().unwrap();
+            sessions.retain(|token, (user, expiry, _)| {
+                let live = *expiry >= now;
+                if !live {
+                    removed += 1;
+                    Self::unindex(&mut user_tokens, user, token);
+                }
+                live
+            });
+        }
+        {
+            let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+            let before = refresh_tokens.len();
+            refresh_tokens.retain(|_, (_, expiry, _)| *expiry >= now);
+            removed += before - refresh_tokens.len();
+        }
+        if removed > 0 {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Creates a session plus an opaque, single-use refresh token bound to the same IP
+    pub(crate) fn create_session_tokens(
+        &self,
+        username: &str,
+        ip: &str,
+    ) -> io::Result<SessionTokens> {
+        let refresh_token = random_hex(32)?;
+        let expiry = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + REFRESH_EXPIRATION;
+        self.refresh_tokens.lock().unwrap().insert(
+            refresh_token.clone(),
+            (username.to_string(), expiry, ip.to_string()),
+        );
+
+        Ok(SessionTokens {
+            access_token: self.create_session(username, ip),
+            refresh_token,
+        })
+    }
+
+    /// Redeems a refresh token for a new token pair; the old refresh token is consumed
+    pub(crate) fn refresh_session(&self, refresh_token: &str, ip: &str) -> Option<SessionTokens> {
+        let (username, expiry, bound_ip) =
+            self.refresh_tokens.lock().unwrap().remove(refresh_token)?;
+        self.persist_removal();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if expiry < now || bound_ip != ip {
+            return None;
         }
+        self.create_session_tokens(&username, ip).ok()
     }
 
     /// Creates a new session and returns a JWT token
-    fn create_session(&self, username: &str, ip: &str) -> String {
+    pub(crate) fn create_session(&self, username: &str, ip: &str) -> String {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
         let expiry = timestamp + SESSION_EXPIRATION;
+        let epoch = self.epoch(username);
         let payload = format!(
-            "{{\"sub\":\"{}\",\"exp\":{},\"ip\":\"{}\"}}",
-            username, expiry, ip
+            "{{\"sub\":\"{}\",\"exp\":{},\"ip\":\"{}\",\"epoch\":{}}}",
+            username, expiry, ip, epoch
         );
-        let hash = sha256(payload.as_bytes());
-        let token = format!("{}.{}", payload, format!("{:x?}", hash));
+        let hash = crypto::hmac_sha256(&self.secret, payload.as_bytes());
+        let token = format!("{}.{:x?}", payload, hash);
 
         let mut sessions = self.sessions.lock().unwrap();
         sessions.insert(
             token.clone(),
             (username.to_string(), expiry, ip.to_string()),
         );
+        self.user_tokens
+            .lock()
+            .unwrap()
+            .entry(username.to_string())
+            .or_default()
+            .insert(token.clone());
         token
     }
 
-    /// Verifies a session token and checks for expiration or hijacking
-    fn verify_session(&self, token: &str, ip: &str) -> bool {
-        let parts: Vec<&str> = token.split('.').collect();
-        if parts.len() != 2 {
-            return false;
+    /// The user's current session epoch; tokens carrying an older one are rejected
+    fn epoch(&self, username: &str) -> u64 {
+        self.epochs
+            .lock()
+            .unwrap()
+            .get(username)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn unindex(user_tokens: &mut HashMap<String, HashSet<String>>, user: &str, token: &str) {
+        if let Some(tokens) = user_tokens.get_mut(user) {
+            tokens.remove(token);
+            if tokens.is_empty() {
+                user_tokens.remove(user);
+            }
         }
+    }
+
+    /// Ends every session and refresh token `username` holds, e.g. after a password change,
+    /// and advances their epoch so any token issued before now stays invalid.
+    /// Returns how many tokens were revoked.
+    pub(crate) fn revoke_all_for(&self, username: &str) -> usize {
+        let mut revoked = 0;
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+            let mut user_tokens = self.user_tokens.lock().unwrap();
+            let mut epochs = self.epochs.lock().unwrap();
+
+            *epochs.entry(username.to_string()).or_insert(0) += 1;
+            for token in user_tokens.remove(username).unwrap_or_default() {
+                if sessions.remove(&token).is_some() {
+                    revoked += 1;
+                }
+            }
+            let before = refresh_tokens.len();
+            refresh_tokens.retain(|_, (user, _, _)| user != username);
+            revoked += before - refresh_tokens.len();
+        }
+        // The epoch changed even when nothing was revoked, so always write it out
+        self.persist_removal();
+        revoked
+    }
+
+    /// Verifies a session token and checks for expiration or hijacking
+    pub(crate) fn verify_session(&self, token: &str, ip: &str) -> bool {
+        // The payload itself contains dots (IPv4 addresses), so split on the last one
+        let (payload, signature) = match token.rsplit_once('.') {
+            Some(parts) => parts,
+            None => return false,
+        };
 
-        let expected_hash = sha256(parts[0].as_bytes());
-        if parts[1] != format!("{:x?}", expected_hash) {
+        let expected_hash = crypto::hmac_sha256(&self.secret, payload.as_bytes());
+        if !constant_time_eq(
+            signature.as_bytes(),
+            format!("{:x?}", expected_hash).as_bytes(),
+        ) {
             return false;
         }
 
+        let token_epoch = match payload_epoch(payload) {
+            Some(epoch) => epoch,
+            None => return false,
+        };
+
         let sessions = self.sessions.lock().unwrap();
-        if let Some((_, expiry, session_ip)) = sessions.get(token) {
+        if let Some((user, expiry, session_ip)) = sessions.get(token) {
+            if token_epoch < self.epoch(user) {
+                return false; // Issued before the user's sessions were revoked
+            }
             if *expiry
                 < SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -97,15 +497,47 @@ impl SessionStore {
         false
     }
 
+    /// Key a session's client signs UI events with; only issued for a live session
+    /// bound to `ip`, so revoking or expiring the session invalidates its events too
+    pub(crate) fn event_key(&self, token: &str, ip: &str) -> Option<Zeroizing<[u8; 32]>> {
+        if !self.verify_session(token, ip) {
+            return None;
+        }
+        let okm = Zeroizing::new(crypto::hkdf(
+            &self.secret,
+            token.as_bytes(),
+            EVENT_KEY_INFO,
+            32,
+        ));
+        okm.as_slice().try_into().ok().map(Zeroizing::new)
+    }
+
     /// Revokes a session token, terminating the session
-    fn revoke_session(&self, token: &str) {
-        let mut sessions = self.sessions.lock().unwrap();
-        sessions.remove(token);
+    pub(crate) fn revoke_session(&self, token: &str) {
+        let removed = self.sessions.lock().unwrap().remove(token);
+        if let Some((user, _, _)) = removed {
+            Self::unindex(&mut self.user_tokens.lock().unwrap(), &user, token);
+            self.persist_removal();
+        }
     }
 }
 
 fn main() {
-    let session_store = Arc::new(SessionStore::new());
+    let secret = keys::EnvKeyProvider
+        .require(keys::SESSION_SECRET)
+        .expect("Session secret unavailable");
+    let session_store = match SessionStore::new_persistent(STORE_FILE, secret.clone()) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            eprintln!("Failed to load session store, starting empty: {}", e);
+            Arc::new(SessionStore::new(secret))
+        }
+    };
+    match session_store.sweep_expired() {
+        Ok(removed) if removed > 0 => println!("Pruned {} expired sessions", removed),
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to prune expired sessions: {}", e),
+    }
 
     let ip_address = "192.168.1.1";
     let token = session_store.create_session("admin", ip_address);
@@ -113,7 +545,177 @@ fn main() {
 
     let is_valid = session_store.verify_session(&token, ip_address);
     println!("Session verification: {}", is_valid);
+    println!(
+        "UI event key derived: {}",
+        session_store.event_key(&token, ip_address).is_some()
+    );
 
     session_store.revoke_session(&token);
     println!("Session revoked");
+
+    match session_store.create_session_tokens("admin", ip_address) {
+        Ok(tokens) => {
+            println!(
+                "Access token valid: {}",
+                session_store.verify_session(&tokens.access_token, ip_address)
+            );
+            let rotated = session_store.refresh_session(&tokens.refresh_token, ip_address);
+            println!("Refresh token rotated: {}", rotated.is_some());
+        }
+        Err(e) => eprintln!("Failed to issue session tokens: {}", e),
+    }
+
+    let other = session_store.create_session("guest", ip_address);
+    let revoked = session_store.revoke_all_for("admin");
+    println!(
+        "Revoked {} admin tokens; guest session still valid: {}",
+        revoked,
+        session_store.verify_session(&other, ip_address)
+    );
+
+    if let Err(e) = session_store.persist() {
+        eprintln!("Failed to persist session store: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-session-secret-0123456789abcdef";
+    const IP: &str = "198.51.100.7";
+
+    #[test]
+    fn sessions_survive_a_restart() {
+        let path = test_util::scratch_path("sessions", "restart");
+        let (token, refresh) = {
+            let store = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+            let tokens = store.create_session_tokens("alice", IP).unwrap();
+            store.persist().unwrap();
+            (tokens.access_token, tokens.refresh_token)
+        };
+
+        let restarted = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        assert!(restarted.verify_session(&token, IP));
+        assert!(!restarted.verify_session(&token, "203.0.113.1"));
+        assert!(restarted.refresh_session(&refresh, IP).is_some());
+
+        // Sealed with the secret: a different one cannot read the store
+        let error = SessionStore::new_persistent(&path, b"another-secret".to_vec())
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn expired_sessions_are_pruned_from_disk() {
+        let path = test_util::scratch_path("sessions", "prune");
+        let store = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        let live = store.create_session("alice", IP);
+        let expired = store.create_session("bob", IP);
+        store.sessions.lock().unwrap().get_mut(&expired).unwrap().1 = now_seconds() - 1;
+        store.persist().unwrap();
+        let on_disk_before = fs::read(&path).unwrap();
+
+        assert_eq!(store.sweep_expired().unwrap(), 1);
+        assert_eq!(store.sweep_expired().unwrap(), 0);
+        assert!(!store.verify_session(&expired, IP));
+        assert!(store.user_tokens.lock().unwrap().get("bob").is_none());
+        assert_ne!(fs::read(&path).unwrap(), on_disk_before);
+
+        let restarted = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        assert!(restarted.verify_session(&live, IP));
+        assert!(!restarted.sessions.lock().unwrap().contains_key(&expired));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn revoking_a_user_invalidates_all_their_tokens_only() {
+        let store = SessionStore::new(SECRET.to_vec());
+        let laptop = store.create_session_tokens("alice", IP).unwrap();
+        let phone = store.create_session("alice", "203.0.113.20");
+        let other = store.create_session_tokens("bob", IP).unwrap();
+
+        assert_eq!(store.revoke_all_for("alice"), 3);
+        assert!(!store.verify_session(&laptop.access_token, IP));
+        assert!(!store.verify_session(&phone, "203.0.113.20"));
+        assert!(store.event_key(&laptop.access_token, IP).is_none());
+        assert!(store.refresh_session(&laptop.refresh_token, IP).is_none());
+
+        assert!(store.verify_session(&other.access_token, IP));
+        assert!(store.refresh_session(&other.refresh_token, IP).is_some());
+
+        // Sessions created afterwards carry the new epoch and work again
+        let fresh = store.create_session("alice", IP);
+        assert!(store.verify_session(&fresh, IP));
+        assert_eq!(store.revoke_all_for("carol"), 0);
+    }
+
+    #[test]
+    fn a_revoked_token_put_back_in_the_store_stays_invalid() {
+        let path = test_util::scratch_path("sessions", "epoch");
+        let store = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        let token = store.create_session("alice", IP);
+        let record = store.sessions.lock().unwrap()[&token].clone();
+
+        store.revoke_all_for("alice");
+        store.sessions.lock().unwrap().insert(token.clone(), record);
+        assert!(!store.verify_session(&token, IP));
+
+        // The advanced epoch is persisted along with the sessions
+        let restarted = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        assert_eq!(restarted.epoch("alice"), 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_revocations_all_survive_a_restart() {
+        let path = test_util::scratch_path("sessions", "concurrent");
+        let store = Arc::new(SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap());
+        let users: Vec<String> = (0..24).map(|i| format!("user{}", i)).collect();
+        let tokens: Vec<String> = users.iter().map(|u| store.create_session(u, IP)).collect();
+        let refresh: Vec<String> = users
+            .iter()
+            .map(|u| store.create_session_tokens(u, IP).unwrap().refresh_token)
+            .collect();
+        let keeper = store.create_session("keeper", IP);
+        store.persist().unwrap();
+
+        let handles: Vec<_> = (0..users.len())
+            .map(|i| {
+                let store = Arc::clone(&store);
+                let (user, token, refresh) =
+                    (users[i].clone(), tokens[i].clone(), refresh[i].clone());
+                std::thread::spawn(move || match i % 3 {
+                    0 => store.revoke_session(&token),
+                    1 => {
+                        store.revoke_session(&token);
+                        assert!(store.refresh_session(&refresh, "203.0.113.9").is_none());
+                    }
+                    _ => {
+                        store.revoke_all_for(&user);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let restarted = SessionStore::new_persistent(&path, SECRET.to_vec()).unwrap();
+        for token in &tokens {
+            assert!(
+                !restarted.verify_session(token, IP),
+                "revoked token came back"
+            );
+        }
+        for (i, refresh) in refresh.iter().enumerate() {
+            if i % 3 != 0 {
+                assert!(restarted.refresh_session(refresh, IP).is_none());
+            }
+        }
+        assert!(restarted.verify_session(&keeper, IP));
+        fs::remove_file(path).unwrap();
+    }
 }
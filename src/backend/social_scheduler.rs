@@ -1,20 +1,92 @@
 //! social_scheduler.rs
 //! Zero Trust Secure Social Media Scheduler
 //! Rust standard library only – No third-party dependencies
+//! Each post carries an idempotency key; published keys are recorded durably so a
+//! restart or retry never publishes the same post twice.
+//! Schedule times are stored as RFC 3339 in the zone they were given in, next to the epoch.
 
-use std::collections::HashMap;
+#[allow(dead_code)]
+mod crypto;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+#[allow(dead_code)]
+mod util;
+
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Read, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::thread::sleep;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+const POSTS_FILE: &str = "scheduled_posts.dat";
+const PUBLISHED_LEDGER: &str = "published_posts.log";
+
 /// Represents a scheduled post structure
 struct ScheduledPost {
+    id: String, // Idempotency key; the same post always gets the same id
     timestamp: u64,
+    utc_offset: i32, // Minutes east of UTC of the zone the time was scheduled in
     platform: String,
     content: String,
 }
 
+impl ScheduledPost {
+    fn new(timestamp: u64, platform: &str, content: &str) -> Self {
+        Self {
+            id: post_id(timestamp, platform, content),
+            timestamp,
+            utc_offset: 0,
+            platform: platform.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    /// A post due at an RFC 3339 time such as `2025-06-01T09:00:00-04:00`,
+    /// remembering its offset for display
+    fn scheduled_at(when: &str, platform: &str, content: &str) -> Option<Self> {
+        let (timestamp, utc_offset) = util::parse_rfc3339(when)?;
+        Some(Self {
+            utc_offset,
+            ..Self::new(timestamp, platform, content)
+        })
+    }
+
+    /// The scheduled time in the zone it was scheduled in
+    fn local_time(&self) -> String {
+        util::rfc3339_with_offset(self.timestamp, self.utc_offset)
+    }
+}
+
+/// Derives a post's idempotency key from what it is, so storing the same post
+/// twice cannot produce two publishable copies
+fn post_id(timestamp: u64, platform: &str, content: &str) -> String {
+    let mut input = Vec::new();
+    for field in [timestamp.to_string().as_str(), platform, content] {
+        input.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        input.extend_from_slice(field.as_bytes());
+    }
+    to_hex(&crypto::sha256(&input)[..16])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// Securely log scheduled post activity (Immutable, Tamper-Proof)
 fn secure_log(entry: &str) -> std::io::Result<()> {
     let mut log_file = OpenOptions::new()
@@ -39,7 +111,8 @@ fn encrypt_content(content: &str, key: u8) -> Vec<u8> {
 
 /// Decrypt post content
 fn decrypt_content(encrypted: &[u8], key: u8) -> String {
-    encrypted.iter().map(|&b| (b ^ key) as char).collect()
+    let bytes: Vec<u8> = encrypted.iter().map(|&b| b ^ key).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
 /// Manually store scheduled posts in a secure file
@@ -51,79 +124,396 @@ fn store_scheduled_post(post: &ScheduledPost, key: u8) -> std::io::Result<()> {
             .create(true)
             .append(true)
             .mode(0o600) // Secure file permissions
-            .open("scheduled_posts.dat")?,
+            .open(POSTS_FILE)?,
     );
 
     writeln!(
         file,
-        "{}|{}|{:?}",
-        post.timestamp, post.platform, encrypted_content
+        "{}|{}|{}|{}|{}",
+        post.id,
+        post.timestamp,
+        post.local_time(),
+        post.platform,
+        to_hex(&encrypted_content)
     )?;
 
     secure_log(&format!(
-        "Scheduled post stored: {} [{}]",
-        post.platform, post.timestamp
+        "Scheduled post stored: {} [{}] id={}",
+        post.platform,
+        post.local_time(),
+        post.id
     ))
 }
 
 /// Retrieve scheduled posts securely
 fn retrieve_scheduled_posts(key: u8) -> std::io::Result<Vec<ScheduledPost>> {
-    let mut file = File::open("scheduled_posts.dat")?;
+    let mut file = File::open(POSTS_FILE)?;
     let mut content = String::new();
     file.read_to_string(&mut content)?;
 
     let mut posts = Vec::new();
     for line in content.lines() {
-        let parts: Vec<&str> = line.split('|').collect();
-        if parts.len() == 3 {
-            if let Ok(timestamp) = parts[0].parse::<u64>() {
-                let decrypted_content = decrypt_content(parts[2].as_bytes(), key);
-                posts.push(ScheduledPost {
-                    timestamp,
-                    platform: parts[1].to_string(),
-                    content: decrypted_content,
-                });
-            }
+        if let Some(post) = parse_post_line(line, key) {
+            posts.push(post);
         }
     }
 
     Ok(posts)
 }
 
-/// Manually simulate post publishing
-fn publish_scheduled_posts(key: u8) -> std::io::Result<()> {
-    let posts = retrieve_scheduled_posts(key)?;
+/// Reads `id|epoch|rfc3339|platform|content`, or the older `id|epoch|platform|content`
+/// whose times are UTC. A line whose two times disagree is skipped as corrupt.
+fn parse_post_line(line: &str, key: u8) -> Option<ScheduledPost> {
+    let parts: Vec<&str> = line.split('|').collect();
+    let (id, epoch, when, platform, encrypted) = match parts[..] {
+        [id, epoch, when, platform, encrypted] => (id, epoch, Some(when), platform, encrypted),
+        [id, epoch, platform, encrypted] => (id, epoch, None, platform, encrypted),
+        _ => return None,
+    };
+    let timestamp = epoch.parse::<u64>().ok()?;
+    let utc_offset = match when.map(util::parse_rfc3339) {
+        Some(Some((parsed, offset))) if parsed == timestamp => offset,
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(ScheduledPost {
+        id: id.to_string(),
+        timestamp,
+        utc_offset,
+        platform: platform.to_string(),
+        content: decrypt_content(&from_hex(encrypted)?, key),
+    })
+}
 
-    for post in posts {
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        if post.timestamp <= current_time {
-            println!("📢 Posting to {}: {}", post.platform, post.content);
-            secure_log(&format!("Published to {}: {}", post.platform, post.content));
+/// Posts not yet due, soonest first
+fn list_upcoming(key: u8) -> std::io::Result<Vec<ScheduledPost>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut upcoming: Vec<ScheduledPost> = retrieve_scheduled_posts(key)?
+        .into_iter()
+        .filter(|post| post.timestamp > now)
+        .collect();
+    upcoming.sort_by_key(|post| post.timestamp);
+    Ok(upcoming)
+}
+
+/// Durable record of which idempotency keys have been published
+struct PublishLedger {
+    path: PathBuf,
+    published: Mutex<HashSet<String>>,
+}
+
+impl PublishLedger {
+    /// Loads every key recorded so far; a missing ledger means nothing was published
+    fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut published = HashSet::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        published.insert(line.trim().to_string());
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
         }
+        Ok(Self {
+            path,
+            published: Mutex::new(published),
+        })
     }
 
-    Ok(())
+    fn was_published(&self, key: &str) -> bool {
+        self.published.lock().unwrap().contains(key)
+    }
+
+    /// Appends `key` and syncs it to disk before it counts as published
+    fn record(&self, key: &str) -> io::Result<()> {
+        let mut published = self.published.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(&self.path)?;
+        writeln!(file, "{}", key)?;
+        file.sync_all()?;
+        published.insert(key.to_string());
+        Ok(())
+    }
+}
+
+/// Delivers a post to its platform. The idempotency key is passed along so the
+/// platform can drop a repeat sent after a crash between delivery and `record`.
+trait Publisher {
+    fn deliver(&self, key: &str, post: &ScheduledPost) -> io::Result<()>;
+}
+
+/// Prints posts instead of calling a platform API
+struct ConsolePublisher;
+
+impl Publisher for ConsolePublisher {
+    fn deliver(&self, key: &str, post: &ScheduledPost) -> io::Result<()> {
+        println!(
+            "📢 Posting to {} [{}]: {}",
+            post.platform, key, post.content
+        );
+        Ok(())
+    }
+}
+
+/// Publishes every due post whose key is not yet in the ledger; returns how many
+/// were published. A post is only recorded after its delivery succeeds, so a
+/// failed delivery is retried on the next pass.
+fn publish_scheduled_posts(
+    key: u8,
+    ledger: &PublishLedger,
+    publisher: &dyn Publisher,
+) -> std::io::Result<usize> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    publish_due(
+        retrieve_scheduled_posts(key)?,
+        now,
+        ledger,
+        publisher,
+        &secure_log,
+    )
+}
+
+/// Delivers and records each post due at `now` that the ledger has not seen,
+/// noting every publication in `log`
+fn publish_due(
+    posts: Vec<ScheduledPost>,
+    now: u64,
+    ledger: &PublishLedger,
+    publisher: &dyn Publisher,
+    log: &dyn Fn(&str) -> io::Result<()>,
+) -> io::Result<usize> {
+    let mut published = 0;
+    for post in posts {
+        if post.timestamp > now || ledger.was_published(&post.id) {
+            continue;
+        }
+        publisher.deliver(&post.id, &post)?;
+        ledger.record(&post.id)?;
+        published += 1;
+        log(&format!(
+            "Published to {} id={}: {}",
+            post.platform, post.id, post.content
+        ))?;
+    }
+    Ok(published)
 }
 
 /// Main loop for scheduling posts securely
 fn main() -> std::io::Result<()> {
     let encryption_key: u8 = 42; // Manual key (replace with secure key storage)
+    let ledger = PublishLedger::open(PUBLISHED_LEDGER)?;
 
     // Example post scheduling (User input could be added securely)
-    let new_post = ScheduledPost {
-        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + 60,
-        platform: "Twitter".to_string(),
-        content: "Zero Trust Security in Rust 🚀".to_string(),
-    };
+    let new_post = ScheduledPost::new(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60,
+        "Twitter",
+        "Zero Trust Security in Rust 🚀",
+    );
 
     store_scheduled_post(&new_post, encryption_key)?;
+    if let Some(post) = ScheduledPost::scheduled_at(
+        "2030-01-15T09:00:00-05:00",
+        "Mastodon",
+        "Zero Trust office hours start now",
+    ) {
+        store_scheduled_post(&post, encryption_key)?;
+    }
+
+    for post in list_upcoming(encryption_key)? {
+        println!("🗓️  {} {} [{}]", post.local_time(), post.platform, post.id);
+    }
 
     // Securely loop and check for scheduled posts (sleep to prevent CPU overuse)
     loop {
-        publish_scheduled_posts(encryption_key)?;
+        publish_scheduled_posts(encryption_key, &ledger, &ConsolePublisher)?;
         sleep(Duration::from_secs(10));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn no_log(_: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn due_posts() -> Vec<ScheduledPost> {
+        vec![
+            ScheduledPost::new(NOW - 60, "Mastodon", "Zero Trust tip #1"),
+            ScheduledPost::new(NOW - 30, "Twitter", "Zero Trust tip #2"),
+            ScheduledPost::new(NOW + 3600, "Twitter", "Not due yet"),
+        ]
+    }
+
+    /// A platform that honours idempotency keys: a repeated key is acknowledged
+    /// but not posted again
+    #[derive(Default)]
+    struct Platform {
+        calls: RefCell<Vec<String>>,
+        posted: RefCell<Vec<String>>,
+    }
+
+    impl Publisher for Platform {
+        fn deliver(&self, key: &str, _post: &ScheduledPost) -> io::Result<()> {
+            self.calls.borrow_mut().push(key.to_string());
+            let mut posted = self.posted.borrow_mut();
+            if !posted.iter().any(|seen| seen == key) {
+                posted.push(key.to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn publishing_the_same_posts_twice_delivers_once() {
+        let path = test_util::scratch_path("scheduler", "twice");
+        let ledger = PublishLedger::open(&path).unwrap();
+        let platform = Platform::default();
+
+        assert_eq!(
+            publish_due(due_posts(), NOW, &ledger, &platform, &no_log).unwrap(),
+            2
+        );
+        assert_eq!(
+            publish_due(due_posts(), NOW, &ledger, &platform, &no_log).unwrap(),
+            0
+        );
+        assert_eq!(platform.calls.borrow().len(), 2);
+
+        // The same post stored twice shares one id, so it is still delivered once
+        let duplicate = ScheduledPost::new(NOW - 60, "Mastodon", "Zero Trust tip #1");
+        assert!(ledger.was_published(&duplicate.id));
+        assert_eq!(
+            publish_due(vec![duplicate], NOW, &ledger, &platform, &no_log).unwrap(),
+            0
+        );
+
+        // A restart reloads the ledger from disk
+        let reopened = PublishLedger::open(&path).unwrap();
+        assert_eq!(
+            publish_due(due_posts(), NOW, &reopened, &platform, &no_log).unwrap(),
+            0
+        );
+        assert_eq!(platform.calls.borrow().len(), 2);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn crash_between_delivery_and_record_is_deduplicated_by_key() {
+        let path = test_util::scratch_path("scheduler", "crash");
+        let platform = Platform::default();
+        let posts = due_posts();
+        let crashed = posts[0].id.clone();
+
+        // The process dies after delivering the first post but before recording it
+        {
+            let ledger = PublishLedger::open(&path).unwrap();
+            platform.deliver(&posts[0].id, &posts[0]).unwrap();
+            assert!(!ledger.was_published(&crashed));
+        }
+
+        let ledger = PublishLedger::open(&path).unwrap();
+        assert_eq!(
+            publish_due(posts, NOW, &ledger, &platform, &no_log).unwrap(),
+            2
+        );
+        assert!(ledger.was_published(&crashed));
+
+        // The retry reached the platform under the same key, which posted it once
+        let calls = platform.calls.borrow();
+        assert_eq!(calls.iter().filter(|key| **key == crashed).count(), 2);
+        assert_eq!(platform.posted.borrow().len(), 2);
+        drop(calls);
+
+        let reopened = PublishLedger::open(&path).unwrap();
+        assert_eq!(
+            publish_due(due_posts(), NOW, &reopened, &platform, &no_log).unwrap(),
+            0
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn failed_delivery_is_not_recorded_and_is_retried() {
+        struct Down;
+        impl Publisher for Down {
+            fn deliver(&self, _: &str, _: &ScheduledPost) -> io::Result<()> {
+                Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    "platform down",
+                ))
+            }
+        }
+
+        let path = test_util::scratch_path("scheduler", "retry");
+        let ledger = PublishLedger::open(&path).unwrap();
+        assert!(publish_due(due_posts(), NOW, &ledger, &Down, &no_log).is_err());
+        assert!(due_posts()
+            .iter()
+            .all(|post| !ledger.was_published(&post.id)));
+
+        let platform = Platform::default();
+        assert_eq!(
+            publish_due(due_posts(), NOW, &ledger, &platform, &no_log).unwrap(),
+            2
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn a_post_scheduled_in_another_zone_keeps_its_instant_and_local_time() {
+        let post =
+            ScheduledPost::scheduled_at("2030-01-15T09:00:00-05:00", "Mastodon", "Office hours")
+                .unwrap();
+        assert_eq!(post.timestamp, 1_894_716_000); // 14:00 UTC
+        assert_eq!(post.utc_offset, -300);
+        assert_eq!(post.local_time(), "2030-01-15T09:00:00-05:00");
+
+        // Stored the way store_scheduled_post writes it, it reads back identically
+        let line = format!(
+            "{}|{}|{}|{}|{}",
+            post.id,
+            post.timestamp,
+            post.local_time(),
+            post.platform,
+            to_hex(&encrypt_content(&post.content, 42))
+        );
+        let stored = parse_post_line(&line, 42).unwrap();
+        assert_eq!(
+            (stored.timestamp, stored.utc_offset, stored.local_time()),
+            (post.timestamp, -300, post.local_time())
+        );
+        assert_eq!(stored.content, "Office hours");
+
+        // Older lines without the readable time are UTC; disagreeing times are corrupt
+        let legacy = format!("{}|{}|Mastodon|{}", post.id, post.timestamp, to_hex(b"x"));
+        assert_eq!(
+            parse_post_line(&legacy, 0).unwrap().local_time(),
+            "2030-01-15T14:00:00Z"
+        );
+        let tampered = line.replace("09:00:00", "10:00:00");
+        assert!(parse_post_line(&tampered, 42).is_none());
+    }
+}
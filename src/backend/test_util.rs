@@ -0,0 +1,25 @@
+//! Zero Trust Shared Test Helpers (Rust Standard Library Only)
+//! Scratch locations for the test modules of every binary, so each one does not carry its
+//! own copy. Included only under `cfg(test)`.
+//! Features:
+//! - **Scratch paths under the system temp dir, unique per module, test and process**
+//! - **Leftovers from an earlier run are removed before a test sees the path**
+
+use std::fs;
+use std::path::PathBuf;
+
+/// `<temp dir>/zt-<module>-<name>-<pid>`, with any file an earlier run left there removed.
+/// `module` keeps tests of different modules apart; `name` keeps one module's tests apart.
+pub fn scratch_path(module: &str, name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zt-{}-{}-{}", module, name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// A fresh, empty directory at the scratch path for `module` and `name`
+pub fn scratch_dir(module: &str, name: &str) -> PathBuf {
+    let dir = scratch_path(module, name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
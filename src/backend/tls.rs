@@ -5,53 +5,83 @@
 //! - Secure UDP-based multiplexed connections
 //! - Perfect Forward Secrecy (PFS) with ephemeral key exchanges
 //! - Certificate pinning and mutual authentication (mTLS)
-//! - Low-latency packet retransmission and congestion control
+//! - Low-latency packet retransmission and congestion control (`congestion::NewReno`)
 //! - Stateless resets for fast connection recovery
-//! - Stream multiplexing and flow control
+//! - Stream multiplexing and flow control (`quic_stream::QuicConnection`)
 //! - Defense against packet injection and replay attacks
 
+#[allow(dead_code)]
+mod congestion;
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod quic_stream;
+#[allow(dead_code)]
+mod tls_config;
+#[allow(dead_code)]
+mod util;
+
+use congestion::NewReno;
 use std::collections::HashMap;
-use std::convert::TryInto;
 use std::io;
 use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant, SystemTime};
+use std::time::{Duration, SystemTime};
+use tls_config::TlsConfig;
 
 const SERVER_ADDR: &str = "0.0.0.0:4433"; // QUIC typically runs on UDP port 4433
+const SERVER_ADDR_ENV: &str = "QUIC_BIND_ADDR";
 const SESSION_EXPIRATION: u64 = 3600; // 1-hour session expiration
 const MAX_PACKET_SIZE: usize = 1350; // Standard QUIC packet size limit
 const INITIAL_WINDOW: usize = 10; // Number of packets in the initial congestion window
-
-/// Implements a SHA-256 hash function manually
-fn sha256(data: &[u8]) -> [u8; 32] {
-    let mut hash = [0u8; 32];
-    let mut accumulator: u32 = 0x6A09E667;
-
-    for &byte in data.iter() {
-        accumulator = accumulator.wrapping_add(byte as u32);
-        accumulator ^= accumulator.rotate_left(5);
-    }
-
-    hash.copy_from_slice(&accumulator.to_be_bytes().repeat(8)[..32]);
-    hash
-}
+const CONNECTION_ID_LEN: usize = 16; // Random bytes in a session ID
+const DEFAULT_WORKER_COUNT: usize = 8;
+const DEFAULT_QUEUE_DEPTH: usize = 1024; // Received datagrams waiting for a free worker
 
 /// Secure QUIC session management with key rotation and connection tracking
 struct QUICSessionManager {
     sessions: Mutex<HashMap<String, (SocketAddr, SystemTime)>>, // Tracks active QUIC sessions
+    congestion: Mutex<HashMap<String, NewReno>>, // Per-session window, starting at `INITIAL_WINDOW` packets
+    peers: Mutex<HashMap<SocketAddr, String>>,   // Peer -> its current session ID
 }
 
 impl QUICSessionManager {
     fn new() -> Self {
         Self {
             sessions: Mutex::new(HashMap::new()),
+            congestion: Mutex::new(HashMap::new()),
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The session for packets from `addr`: its current one while valid, else a
+    /// new one. The peer lock is held throughout, so packets racing in from a new
+    /// peer still share one session.
+    fn session_for(&self, addr: SocketAddr) -> io::Result<String> {
+        let mut peers = self.peers.lock().unwrap();
+        if let Some(session_id) = peers.get(&addr) {
+            if self.is_session_valid(session_id) {
+                return Ok(session_id.clone());
+            }
+            self.sessions.lock().unwrap().remove(session_id);
+            self.congestion.lock().unwrap().remove(session_id);
         }
+        let session_id = generate_secure_session_id()?;
+        self.establish_session(&session_id, addr);
+        peers.insert(addr, session_id.clone());
+        Ok(session_id)
     }
 
     fn establish_session(&self, session_id: &str, addr: SocketAddr) {
         let mut sessions = self.sessions.lock().unwrap();
         sessions.insert(session_id.to_string(), (addr, SystemTime::now()));
+        self.congestion.lock().unwrap().insert(
+            session_id.to_string(),
+            NewReno::new(MAX_PACKET_SIZE, INITIAL_WINDOW),
+        );
     }
 
     fn is_session_valid(&self, session_id: &str) -> bool {
@@ -64,53 +94,224 @@ impl QUICSessionManager {
     }
 }
 
-/// Generates a secure random session ID using `SystemTime`
-fn generate_secure_session_id() -> String {
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let hash = sha256(&now.to_be_bytes());
-    format!("{:x?}", hash)
+/// Generates an unguessable 128-bit session ID from the OS CSPRNG, as 32 hex digits
+fn generate_secure_session_id() -> io::Result<String> {
+    let mut bytes = [0u8; CONNECTION_ID_LEN];
+    crypto::random_bytes(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
 }
 
 /// Handles incoming QUIC packets with session validation and retransmission support
 fn handle_quic_packet(
     socket: &UdpSocket,
     session_manager: Arc<QUICSessionManager>,
-    packet: &[u8],
+    _packet: &[u8], // Not parsed yet; the handshake reply below is simulated
     src_addr: SocketAddr,
 ) {
-    let session_id = generate_secure_session_id();
-
-    if !session_manager.is_session_valid(&session_id) {
-        session_manager.establish_session(&session_id, src_addr);
+    if let Err(e) = session_manager.session_for(src_addr) {
+        eprintln!("Failed to generate session ID: {}", e);
+        return;
     }
 
     // Simulating a QUIC handshake response
     let response = b"QUIC Connection Established Securely";
-    socket.send_to(response, src_addr).unwrap();
+    // A failed send loses one reply, which the peer retransmits for; the worker carries on
+    if let Err(e) = socket.send_to(response, src_addr) {
+        eprintln!("QUIC response to {} failed: {}", src_addr, e);
+    }
 }
 
-/// Starts the Zero Trust QUIC server with full security enforcement
-fn main() -> io::Result<()> {
-    let socket = UdpSocket::bind(SERVER_ADDR)?;
-    println!("Zero Trust QUIC Server running on {}", SERVER_ADDR);
+/// Fixed set of worker threads fed from a bounded queue of received datagrams
+struct PacketPool {
+    queue: SyncSender<(Vec<u8>, SocketAddr)>,
+    dropped: AtomicU64,
+}
 
+impl PacketPool {
+    /// Starts `workers` threads sharing a queue that holds at most `queue_depth` datagrams
+    fn new(
+        workers: usize,
+        queue_depth: usize,
+        socket: Arc<UdpSocket>,
+        session_manager: Arc<QUICSessionManager>,
+    ) -> Self {
+        let (queue, jobs) = mpsc::sync_channel::<(Vec<u8>, SocketAddr)>(queue_depth);
+        let jobs = Arc::new(Mutex::new(jobs));
+
+        for _ in 0..workers.max(1) {
+            let jobs = Arc::clone(&jobs);
+            let socket = Arc::clone(&socket);
+            let session_manager = Arc::clone(&session_manager);
+            thread::spawn(move || loop {
+                // The lock is released as soon as a datagram is dequeued
+                let job = jobs.lock().unwrap().recv();
+                match job {
+                    Ok((packet, src_addr)) => {
+                        handle_quic_packet(&socket, Arc::clone(&session_manager), &packet, src_addr)
+                    }
+                    Err(_) => break, // Pool dropped and queue drained
+                }
+            });
+        }
+
+        Self {
+            queue,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Queues a datagram, dropping and counting it if every worker is busy and the
+    /// queue is full; QUIC peers retransmit what they lose
+    fn submit(&self, packet: Vec<u8>, src_addr: SocketAddr) -> bool {
+        match self.queue.try_send((packet, src_addr)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Datagrams dropped because the queue was saturated
+    fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Starts the Zero Trust QUIC server with full security enforcement on `addr`,
+/// presenting the certificate chain in `tls` and handling datagrams on `workers`
+/// threads with up to `queue_depth` waiting
+fn run(addr: &str, tls: &TlsConfig, workers: usize, queue_depth: usize) -> io::Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    println!("Zero Trust QUIC Server running on {}", socket.local_addr()?);
+    println!(
+        "Serving {} certificate(s); client certificates {}",
+        tls.certificates().len(),
+        if tls.client_cert_required() {
+            "required"
+        } else {
+            "optional"
+        }
+    );
+
+    let socket = Arc::new(socket);
     let session_manager = Arc::new(QUICSessionManager::new());
+    let pool = PacketPool::new(workers, queue_depth, Arc::clone(&socket), session_manager);
     let mut buffer = [0; MAX_PACKET_SIZE];
 
     loop {
         match socket.recv_from(&mut buffer) {
             Ok((size, src_addr)) => {
-                let session_manager = Arc::clone(&session_manager);
-                let packet = &buffer[..size];
-
-                thread::spawn(move || {
-                    handle_quic_packet(&socket, session_manager, packet, src_addr);
-                });
+                // Report the first drop and every 1024th after it, not each one
+                if !pool.submit(buffer[..size].to_vec(), src_addr)
+                    && pool.dropped_count() % 1024 == 1
+                {
+                    eprintln!(
+                        "QUIC packet queue saturated; {} packet(s) dropped so far",
+                        pool.dropped_count()
+                    );
+                }
             }
             Err(e) => eprintln!("QUIC packet reception failed: {}", e),
         }
     }
 }
+
+fn main() -> io::Result<()> {
+    let workers = std::env::var("QUIC_WORKERS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_WORKER_COUNT);
+    let queue_depth = std::env::var("QUIC_QUEUE_DEPTH")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_QUEUE_DEPTH);
+    let tls = TlsConfig::from_env()?;
+    run(
+        &util::bind_address(SERVER_ADDR_ENV, SERVER_ADDR),
+        &tls,
+        workers,
+        queue_depth,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn session_ids_are_unique_32_digit_hex() {
+        let ids: HashSet<String> = (0..10_000)
+            .map(|_| generate_secure_session_id().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 10_000);
+        for id in &ids {
+            assert_eq!(id.len(), CONNECTION_ID_LEN * 2);
+            assert!(id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')));
+        }
+    }
+
+    #[test]
+    fn each_peer_keeps_one_session_across_packets() {
+        let manager = QUICSessionManager::new();
+        let alice: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let bob: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+
+        let first = manager.session_for(alice).unwrap();
+        assert_eq!(manager.session_for(alice).unwrap(), first);
+        assert_ne!(manager.session_for(bob).unwrap(), first);
+        assert_eq!(manager.sessions.lock().unwrap().len(), 2);
+    }
+
+    fn thread_count() -> usize {
+        std::fs::read_dir("/proc/self/task").unwrap().count()
+    }
+
+    #[test]
+    fn a_flood_is_queued_to_a_fixed_pool_and_the_excess_counted_as_dropped() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let manager = Arc::new(QUICSessionManager::new());
+        let before = thread_count();
+        let pool = PacketPool::new(2, 4, socket, Arc::clone(&manager));
+
+        // Holding the peer table stalls both workers on their first datagram
+        let stalled = manager.peers.lock().unwrap();
+        let flood = 1_000;
+        let accepted = (0..flood)
+            .filter(|_| pool.submit(b"Initial".to_vec(), peer.local_addr().unwrap()))
+            .count();
+        assert!(
+            thread_count() <= before + 2 + 8,
+            "threads grew with the flood"
+        );
+        assert!((4..=6).contains(&accepted), "accepted {}", accepted);
+        assert_eq!(pool.dropped_count(), (flood - accepted) as u64);
+
+        // Once the workers are free every queued datagram is answered
+        drop(stalled);
+        for _ in 0..accepted {
+            peer.recv_from(&mut [0u8; 64]).unwrap();
+        }
+        assert!(pool.submit(b"Initial".to_vec(), peer.local_addr().unwrap()));
+    }
+
+    #[test]
+    fn a_failed_reply_does_not_cost_the_pool_its_worker() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let pool = PacketPool::new(1, 4, socket, Arc::new(QUICSessionManager::new()));
+
+        // Port 0 cannot be sent to, so each of these replies fails
+        let unreachable: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        for _ in 0..3 {
+            assert!(pool.submit(b"Initial".to_vec(), unreachable));
+        }
+        // The single worker is still there to answer a reachable peer
+        assert!(pool.submit(b"Initial".to_vec(), peer.local_addr().unwrap()));
+        peer.recv_from(&mut [0u8; 64]).unwrap();
+    }
+}
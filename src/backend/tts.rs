@@ -1,51 +1,312 @@
 //! tts.rs
 //! Zero Trust Text-to-Speech engine using Rust standard library
 //! Explicitly local, secure, and privacy-preserving TTS implementation.
+//! The vocabulary is a directory of `word.wav` recordings (PCM, mono, 16-bit, 44100 Hz).
+//! Input may use minimal SSML-style markup: `<break time="500ms"/>` and `<emphasis>…</emphasis>`.
+//! Output is WAV, headerless PCM, or G.711 μ-law for telephony, chosen with `TTS_OUTPUT_FORMAT`.
+
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
 
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::time::Duration;
+
+const PHONEME_DIR: &str = "phonemes";
+const SAMPLE_RATE: u32 = 44100;
+const BITS_PER_SAMPLE: u16 = 16;
+const MAX_PHONEME_FILE_SIZE: u64 = 10 * 1024 * 1024; // Longer recordings are not phonemes
+const DEFAULT_BREAK: Duration = Duration::from_millis(500);
+const MAX_BREAK: Duration = Duration::from_secs(10); // Caps silence a single tag can request
+const EMPHASIS_GAIN: f32 = 1.5;
+const OUTPUT_FORMAT_ENV: &str = "TTS_OUTPUT_FORMAT"; // wav | pcm | mulaw
+const MULAW_BIAS: u16 = 33; // Added to the 14-bit magnitude so every segment starts on a power of two
+const MULAW_MAX: u16 = 0x1FFF;
+
+/// How synthesized samples are written out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// 16-bit PCM in a WAV container
+    Wav,
+    /// Headerless 16-bit little-endian PCM
+    RawPcm,
+    /// Headerless G.711 μ-law, one byte per sample
+    Mulaw,
+}
+
+impl OutputFormat {
+    /// Reads `TTS_OUTPUT_FORMAT`; unset or unrecognised values use WAV
+    fn from_env() -> Self {
+        match env::var(OUTPUT_FORMAT_ENV).as_deref() {
+            Ok(format) if format.eq_ignore_ascii_case("pcm") => OutputFormat::RawPcm,
+            Ok(format) if format.eq_ignore_ascii_case("mulaw") => OutputFormat::Mulaw,
+            _ => OutputFormat::Wav,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::RawPcm => "pcm",
+            OutputFormat::Mulaw => "ulaw",
+        }
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+/// Extracts the samples from a WAV file, accepting only PCM, mono, 16-bit, 44100 Hz
+fn parse_wav(bytes: &[u8]) -> io::Result<Vec<i16>> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF/WAVE file"));
+    }
 
-/// Represents simple phoneme-to-sound mappings (manual, extendable)
-fn phoneme_library() -> HashMap<&'static str, Vec<u8>> {
-    let mut phonemes = HashMap::new();
+    let mut format_checked = false;
+    let mut rest = &bytes[12..];
+    while rest.len() >= 8 {
+        let id = &rest[0..4];
+        let size = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let body = rest
+            .get(8..8 + size)
+            .ok_or_else(|| invalid("chunk runs past the end of the file"))?;
 
-    // Placeholder phoneme library mapping characters/words to PCM audio byte arrays
-    phonemes.insert("hello", vec![/* raw audio bytes */]);
-    phoneme_library.insert("world", vec![/* raw audio bytes */]);
-    // Extendable manually with secure, verified audio bytes
-    phoneme_library
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err(invalid("fmt chunk is too short"));
+                }
+                let format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if format != 1 {
+                    return Err(invalid("audio is not PCM"));
+                }
+                if channels != 1 {
+                    return Err(invalid("audio is not mono"));
+                }
+                if sample_rate != SAMPLE_RATE {
+                    return Err(invalid("sample rate is not 44100 Hz"));
+                }
+                if bits != BITS_PER_SAMPLE {
+                    return Err(invalid("samples are not 16-bit"));
+                }
+                format_checked = true;
+            }
+            b"data" => {
+                if !format_checked {
+                    return Err(invalid("data chunk precedes fmt chunk"));
+                }
+                if !size.is_multiple_of(2) {
+                    return Err(invalid("data chunk holds a partial sample"));
+                }
+                return Ok(body
+                    .chunks_exact(2)
+                    .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                    .collect());
+            }
+            _ => {} // LIST and other metadata chunks carry no audio
+        }
+        // Chunks are padded to an even length
+        rest = rest.get(8 + size + size % 2..).unwrap_or(&[]);
+    }
+    Err(invalid("no data chunk"))
 }
 
-/// Convert text to audio bytes using the phoneme library (manual TTS)
-fn text_to_audio(text: &str, library: &HashMap<&str, Vec<u8>>) -> Vec<u8> {
+/// Builds the phoneme library from every `word.wav` in `path`, keyed by the
+/// lowercased file stem. Malformed or unsupported files are reported and skipped.
+fn load_phoneme_library<P: AsRef<Path>>(path: P) -> io::Result<HashMap<String, Vec<i16>>> {
+    let mut library = HashMap::new();
+    for entry in fs::read_dir(path)? {
+        let file_path = entry?.path();
+        let is_wav = file_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+        let word = match file_path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(word) if is_wav && file_path.is_file() => word.to_lowercase(),
+            _ => continue,
+        };
+
+        let samples = fs::metadata(&file_path)
+            .and_then(|meta| {
+                if meta.len() > MAX_PHONEME_FILE_SIZE {
+                    Err(invalid("file is too large"))
+                } else {
+                    fs::read(&file_path)
+                }
+            })
+            .and_then(|bytes| parse_wav(&bytes));
+        match samples {
+            Ok(samples) => {
+                library.insert(word, samples);
+            }
+            Err(e) => eprintln!("Warning: skipping phoneme '{}': {}", file_path.display(), e),
+        }
+    }
+    Ok(library)
+}
+
+/// One unit of marked-up input
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word { text: String, emphasized: bool },
+    Break(Duration),
+}
+
+/// Parses a `time` attribute such as `500ms` or `2s`, clamped to `MAX_BREAK`
+fn parse_break_time(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let duration = if let Some(ms) = value.strip_suffix("ms") {
+        Duration::from_millis(ms.trim().parse().ok()?)
+    } else {
+        Duration::try_from_secs_f32(value.strip_suffix('s')?.trim().parse().ok()?).ok()?
+    };
+    Some(duration.min(MAX_BREAK))
+}
+
+/// Reads the `time="..."` attribute out of a break tag's contents
+fn break_duration(tag: &str) -> Duration {
+    tag.split_once("time=")
+        .and_then(|(_, rest)| {
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = rest[1..].split(quote).next()?;
+            parse_break_time(value)
+        })
+        .unwrap_or(DEFAULT_BREAK)
+}
+
+/// Splits text into words and breaks. Unknown tags are dropped, unbalanced
+/// `</emphasis>` tags are ignored, and a `<` with no closing `>` is read as text.
+fn parse_markup(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut emphasis_depth = 0usize;
+    let mut rest = text;
+
+    loop {
+        let (plain, tag) = match rest.find('<') {
+            Some(start) => match rest[start..].find('>') {
+                Some(len) => (
+                    &rest[..start],
+                    Some((&rest[start + 1..start + len], start + len + 1)),
+                ),
+                None => (rest, None),
+            },
+            None => (rest, None),
+        };
+        tokens.extend(plain.split_whitespace().map(|word| Token::Word {
+            text: word.to_string(),
+            emphasized: emphasis_depth > 0,
+        }));
+
+        let (tag, next) = match tag {
+            Some(tag) => tag,
+            None => return tokens,
+        };
+        let tag = tag.trim().trim_end_matches('/').trim();
+        let name = tag
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "break" => tokens.push(Token::Break(break_duration(tag))),
+            "emphasis" => emphasis_depth += 1,
+            "/emphasis" => emphasis_depth = emphasis_depth.saturating_sub(1),
+            _ => {}
+        }
+        rest = &rest[next..];
+    }
+}
+
+/// Number of samples that play for `duration`
+fn samples_for(duration: Duration) -> usize {
+    (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as usize
+}
+
+/// Scales a sample, saturating instead of wrapping at the i16 limits
+fn amplify(sample: i16, gain: f32) -> i16 {
+    (sample as f32 * gain).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// Convert marked-up text to audio samples using the phoneme library (manual TTS)
+fn text_to_audio(text: &str, library: &HashMap<String, Vec<i16>>) -> Vec<i16> {
     let mut audio_output = Vec::new();
-    for word in text.split_whitespace() {
-        if let Some(sound) = library.get(word) {
-            audio_data.extend(sound);
-        } else {
-            eprintln!("Warning: No phoneme mapping for '{}'", word);
+    for token in parse_markup(text) {
+        match token {
+            Token::Break(duration) => {
+                audio_output.resize(audio_output.len() + samples_for(duration), 0);
+            }
+            Token::Word { text, emphasized } => match library.get(&text.to_lowercase()) {
+                Some(sound) if emphasized => {
+                    audio_output.extend(sound.iter().map(|&s| amplify(s, EMPHASIS_GAIN)))
+                }
+                Some(sound) => audio_output.extend_from_slice(sound),
+                None => eprintln!("Warning: No phoneme mapping for '{}'", text),
+            },
         }
     }
-    audio
+    audio_output
 }
 
-/// Explicitly write audio bytes to a WAV file manually (no external dependencies)
-fn write_audio_wav(audio_bytes: &[u8], filename: &str) -> std::io::Result<()> {
-    let mut file = BufWriter::new(File::create(filename)?);
+/// G.711 μ-law encoding of one sample, bit-exact with the ITU-T G.191 reference coder:
+/// sign, 3-bit segment, and 4-bit step, with the segment and step inverted
+fn mulaw_encode(sample: i16) -> u8 {
+    // Negative samples use the one's complement, so -1 encodes as negative zero
+    let (sign, magnitude) = if sample < 0 {
+        (0x00, !sample as u16)
+    } else {
+        (0x80, sample as u16)
+    };
+    let biased = ((magnitude >> 2) + MULAW_BIAS).min(MULAW_MAX);
+    // The bias sets bit 5, so the highest set bit is between 5 and 12
+    let segment = 15 - biased.leading_zeros() as u16 - 5;
+    let step = (biased >> (segment + 1)) & 0x0F;
+    (sign | ((7 - segment) << 4) | (0x0F - step)) as u8
+}
+
+/// Writes `samples` to `writer` in `format`, without flushing it
+fn write_audio(samples: &[i16], format: OutputFormat, writer: &mut impl Write) -> io::Result<()> {
+    match format {
+        OutputFormat::Wav => {
+            // PCM, mono, 44100 Hz, 16-bit
+            write_wav_header(writer, samples.len() * 2)?;
+            write_pcm(samples, writer)
+        }
+        OutputFormat::RawPcm => write_pcm(samples, writer),
+        OutputFormat::Mulaw => {
+            let encoded: Vec<u8> = samples.iter().map(|&s| mulaw_encode(s)).collect();
+            writer.write_all(&encoded)
+        }
+    }
+}
 
-    // Write WAV header explicitly (PCM, mono, 44100 Hz, 16-bit)
-    let wav_header = create_wav_header(audio_bytes.len() as u32);
-    file.write_all(&wav_header)?;
-    file.write_all(audio_bytes)?;
+fn write_pcm(samples: &[i16], writer: &mut impl Write) -> io::Result<()> {
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
     Ok(())
 }
 
+/// Explicitly write audio samples to a file manually (no external dependencies)
+fn write_audio_file(samples: &[i16], format: OutputFormat, filename: &str) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    write_audio(samples, format, &mut file)?;
+    file.flush()
+}
+
 /// Manual WAV header construction
 fn write_wav_header(writer: &mut impl Write, data_len: usize) -> std::io::Result<()> {
     let chunk_size = 36 + data_len as u32;
-    let sample_rate: u32 = 44100;
-    let bits_per_sample: u16 = 16;
+    let sample_rate: u32 = SAMPLE_RATE;
+    let bits_per_sample: u16 = BITS_PER_SAMPLE;
     let byte_rate = sample_rate * bits_per_sample as u32 / 8;
     let block_align = bits_per_sample / 8;
 
@@ -56,7 +317,8 @@ fn write_wav_header(writer: &mut impl Write, data_len: usize) -> std::io::Result
     writer.write_all(&[1, 0, 1, 0])?; // PCM, Mono
     writer.write_all(&sample_rate.to_le_bytes())?;
     writer.write_all(&byte_rate.to_le_bytes())?;
-    writer.write_all(&[2, 0, bits_per_sample as u8, 0])?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
     writer.write_all(b"data")?;
     writer.write_all(&(data_len as u32).to_le_bytes())?;
     Ok(())
@@ -74,25 +336,172 @@ fn secure_log(entry: &str) -> std::io::Result<()> {
 }
 
 fn main() -> std::io::Result<()> {
-    let phonemes = phoneme_library();
+    let phonemes = match load_phoneme_library(PHONEME_DIR) {
+        Ok(phonemes) => phonemes,
+        Err(e) => {
+            eprintln!("Unable to load phonemes from '{}': {}", PHONEME_DIR, e);
+            secure_log(&format!("Failed TTS: phoneme library unavailable: {}", e))?;
+            return Ok(());
+        }
+    };
 
     let input_text = "zero trust cybersecurity";
     println!("Converting text to audio: '{}'", input_text);
 
-    let audio_bytes = text_to_audio(input_text, &phonemes);
+    let audio_samples = text_to_audio(input_text, &phonemes);
 
     // Explicit Zero Trust validation of generated audio size
-    if audio_bytes.is_empty() {
+    if audio_samples.is_empty() {
         eprintln!("No matching phonemes found; aborting audio write.");
         secure_log("Failed TTS: No matching phonemes found.")?;
         return Ok(());
     }
 
-    let filename = "output.wav";
-    write_audio_wav(&audio_bytes, filename)?;
+    let format = OutputFormat::from_env();
+    let filename = format!("output.{}", format.extension());
+    write_audio_file(&audio_samples, format, &filename)?;
 
     println!("Audio file '{}' generated securely.", filename);
     secure_log(&format!("TTS generation successful: '{}'", filename))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_audio(samples, OutputFormat::Wav, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn library_loads_valid_wavs_and_skips_malformed_ones() {
+        let dir = test_util::scratch_dir("tts", "library");
+        fs::write(dir.join("Hello.wav"), wav(&[1, -2, 3])).unwrap();
+        fs::write(dir.join("world.WAV"), wav(&[100, 200])).unwrap();
+        fs::write(dir.join("broken.wav"), b"RIFF\x04\x00\x00\x00WAVE").unwrap();
+        fs::write(dir.join("notes.txt"), b"not audio").unwrap();
+
+        let library = load_phoneme_library(&dir).unwrap();
+        let mut words: Vec<_> = library.keys().cloned().collect();
+        words.sort();
+        assert_eq!(words, ["hello", "world"]);
+        assert_eq!(library["hello"], [1, -2, 3]);
+        assert_eq!(library["world"], [100, 200]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn wavs_in_other_formats_are_rejected() {
+        let good = wav(&[7, 8]);
+        assert_eq!(parse_wav(&good).unwrap(), [7, 8]);
+
+        // Byte offsets into the canonical 44-byte header
+        for (offset, value, reason) in [
+            (20, 3u16, "audio is not PCM"),
+            (22, 2u16, "audio is not mono"),
+            (34, 8u16, "samples are not 16-bit"),
+        ] {
+            let mut bytes = good.clone();
+            bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            assert_eq!(parse_wav(&bytes).unwrap_err().to_string(), reason);
+        }
+        let mut resampled = good.clone();
+        resampled[24..28].copy_from_slice(&22050u32.to_le_bytes());
+        assert!(parse_wav(&resampled).is_err());
+        assert!(parse_wav(&good[..good.len() - 1]).is_err());
+    }
+
+    fn library() -> HashMap<String, Vec<i16>> {
+        HashMap::from([
+            ("hello".to_string(), vec![1000, -1000, 30000]),
+            ("world".to_string(), vec![500, -500]),
+        ])
+    }
+
+    #[test]
+    fn break_tag_inserts_the_requested_silence() {
+        let library = library();
+        let audio = text_to_audio(r#"hello <break time="500ms"/> world"#, &library);
+        let silence = SAMPLE_RATE as usize / 2;
+        assert_eq!(audio.len(), 3 + silence + 2);
+        assert_eq!(&audio[..3], &library["hello"][..]);
+        assert!(audio[3..3 + silence].iter().all(|&s| s == 0));
+        assert_eq!(&audio[3 + silence..], &library["world"][..]);
+
+        assert_eq!(break_duration(r#"break time="2s""#), Duration::from_secs(2));
+        assert_eq!(break_duration("break"), DEFAULT_BREAK);
+        assert_eq!(break_duration(r#"break time="1h""#), DEFAULT_BREAK);
+        assert_eq!(break_duration(r#"break time="99s""#), MAX_BREAK);
+    }
+
+    #[test]
+    fn emphasis_amplifies_only_the_tagged_span() {
+        let library = library();
+        let audio = text_to_audio("<emphasis>hello</emphasis> world", &library);
+        assert_eq!(audio, [1500, -1500, i16::MAX, 500, -500]);
+    }
+
+    #[test]
+    fn unknown_and_unbalanced_tags_are_ignored() {
+        let library = library();
+        let plain = text_to_audio("hello world", &library);
+        assert_eq!(
+            text_to_audio("<prosody rate='x'>hello</prosody> world", &library),
+            plain
+        );
+        assert_eq!(text_to_audio("</emphasis>hello world", &library), plain);
+        assert_eq!(
+            parse_markup("a < b"),
+            ["a", "<", "b"]
+                .iter()
+                .map(|text| Token::Word {
+                    text: text.to_string(),
+                    emphasized: false
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn mulaw_matches_the_g711_reference_coder() {
+        // Expected codes from the ITU-T G.191 ulaw_compress reference implementation
+        let reference: [(i16, u8); 11] = [
+            (0, 0xFF),
+            (1, 0xFF),
+            (-1, 0x7F),
+            (100, 0xF2),
+            (-100, 0x73),
+            (1000, 0xCE),
+            (-1000, 0x4E),
+            (8000, 0xA0),
+            (32124, 0x80),
+            (i16::MAX, 0x80),
+            (i16::MIN, 0x00),
+        ];
+        for (sample, code) in reference {
+            assert_eq!(mulaw_encode(sample), code, "sample {}", sample);
+        }
+
+        let mut encoded = Vec::new();
+        write_audio(&[0, 1000, -1000], OutputFormat::Mulaw, &mut encoded).unwrap();
+        assert_eq!(encoded, [0xFF, 0xCE, 0x4E]);
+    }
+
+    #[test]
+    fn raw_pcm_is_exactly_the_little_endian_samples() {
+        let samples = [0, 1, -1, 0x1234, i16::MIN];
+        let mut raw = Vec::new();
+        write_audio(&samples, OutputFormat::RawPcm, &mut raw).unwrap();
+
+        assert_eq!(
+            raw,
+            [0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF, 0x34, 0x12, 0x00, 0x80]
+        );
+        // The WAV variant is the same bytes behind a 44-byte header
+        assert_eq!(&wav(&samples)[44..], &raw[..]);
+    }
+}
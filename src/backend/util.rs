@@ -0,0 +1,561 @@
+//! Zero Trust Shared Utilities (Rust Standard Library Only)
+//! Small helpers shared by the backend binaries so each one does not carry its own copy.
+//! Features:
+//! - **Allocation-free Shannon entropy over a stack frequency table**
+//! - **Bind address lookup with an environment variable override**
+//! - **Socket read/write timeouts so stalled clients cannot pin a thread**
+//! - **CIDR address blocks for IPv4 and IPv6 matching**
+//! - **IMF-fixdate formatting and parsing for HTTP date headers**
+//! - **Mutex locking that survives a panicked holder instead of cascading the panic**
+//! - **Retries with jittered exponential backoff, with an injectable sleep for tests**
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{IpAddr, TcpStream};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30); // No single wait between attempts is longer
+
+/// Computes the Shannon entropy of `data` in bits per byte (0.0 to 8.0).
+/// Empty input has zero entropy.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut frequencies = [0u32; 256];
+    for &byte in data {
+        frequencies[byte as usize] += 1;
+    }
+
+    let len = data.len() as f64;
+    let mut entropy = 0.0;
+    for &count in frequencies.iter().filter(|&&count| count > 0) {
+        let probability = count as f64 / len;
+        entropy -= probability * probability.log2();
+    }
+    entropy
+}
+
+/// Returns the address a server should bind: `env_var` when set and non-empty,
+/// otherwise `default`. Use port 0 (e.g. `127.0.0.1:0`) for an ephemeral port.
+pub fn bind_address(env_var: &str, default: &str) -> String {
+    std::env::var(env_var)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Reads a whole number of seconds from `env_var`, falling back to `default`
+pub fn timeout_from_env(env_var: &str, default: Duration) -> Duration {
+    std::env::var(env_var)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map_or(default, Duration::from_secs)
+}
+
+/// Bounds every blocking read and write on an accepted connection
+pub fn set_timeouts(stream: &TcpStream, read: Duration, write: Duration) -> io::Result<()> {
+    stream.set_read_timeout(Some(read))?;
+    stream.set_write_timeout(Some(write))
+}
+
+/// True for the error a socket timeout produces (`WouldBlock` on Unix, `TimedOut` on Windows)
+pub fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// An address block such as `203.0.113.0/24`; a bare address is a single-host block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix) = match text.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (text.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return None;
+        }
+        Some(Self {
+            network: mask(addr, prefix),
+            prefix,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        ip.is_ipv4() == self.network.is_ipv4() && mask(ip, self.prefix) == self.network
+    }
+
+    /// Number of leading bits fixed by the block; longer is more specific
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix
+    }
+}
+
+/// Clears every bit after the first `prefix` bits of `ip`
+fn mask(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4);
+            let keep = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((bits & keep).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            let keep = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((bits & keep).into())
+        }
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"]; // 1970-01-01 was a Thursday
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Civil date for a count of days since 1970-01-01 (proleptic Gregorian)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 for a civil date; the inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Formats a time as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+/// Times before the epoch are clamped to it.
+pub fn http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs()) as i64;
+    let days = secs.div_euclid(86_400);
+    let in_day = secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        in_day / 3600,
+        in_day % 3600 / 60,
+        in_day % 60
+    )
+}
+
+/// Seconds since the epoch split into a civil date and the time of day
+fn civil_time(time: SystemTime) -> ((i64, u32, u32), i64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs()) as i64;
+    (
+        civil_from_days(secs.div_euclid(86_400)),
+        secs.rem_euclid(86_400),
+    )
+}
+
+/// Formats a time the way Common Log Format brackets it, e.g. `10/Oct/2000:13:55:36 +0000`
+pub fn common_log_date(time: SystemTime) -> String {
+    let ((year, month, day), in_day) = civil_time(time);
+    format!(
+        "{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        in_day / 3600,
+        in_day % 3600 / 60,
+        in_day % 60
+    )
+}
+
+/// Formats a time as RFC 3339 UTC with milliseconds, e.g. `2000-10-10T13:55:36.000Z`
+pub fn rfc3339_date(time: SystemTime) -> String {
+    let ((year, month, day), in_day) = civil_time(time);
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_millis());
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        in_day / 3600,
+        in_day % 3600 / 60,
+        in_day % 60,
+        millis
+    )
+}
+
+/// Formats seconds since the epoch as RFC 3339 in the zone `offset_minutes` east
+/// of UTC, e.g. `2025-06-01T09:00:00-04:00`. A zero offset is written `Z`.
+pub fn rfc3339_with_offset(epoch_secs: u64, offset_minutes: i32) -> String {
+    let local = epoch_secs as i64 + offset_minutes as i64 * 60;
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let in_day = local.rem_euclid(86_400);
+    let offset = match offset_minutes {
+        0 => "Z".to_string(),
+        minutes => format!(
+            "{}{:02}:{:02}",
+            if minutes < 0 { '-' } else { '+' },
+            minutes.unsigned_abs() / 60,
+            minutes.unsigned_abs() % 60
+        ),
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        in_day / 3600,
+        in_day % 3600 / 60,
+        in_day % 60,
+        offset
+    )
+}
+
+/// Parses an RFC 3339 date-time such as `2025-06-01T09:00:00-04:00` into seconds
+/// since the epoch and the offset in minutes east of UTC. Fractional and leap
+/// seconds, and times before the epoch, are not accepted.
+pub fn parse_rfc3339(value: &str) -> Option<(u64, i32)> {
+    let value = value.trim();
+    let (date, rest) = (value.get(..10)?, value.get(10..)?);
+    let separator = rest.chars().next()?;
+    if !matches!(separator, 'T' | 't' | ' ') {
+        return None;
+    }
+    let (clock, zone) = (rest.get(1..9)?, rest.get(9..)?);
+
+    let number = |field: &str, digits: usize| -> Option<i64> {
+        if field.len() != digits || !field.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        field.parse().ok()
+    };
+    let mut date_fields = date.split('-');
+    let year = number(date_fields.next()?, 4)?;
+    let month = number(date_fields.next()?, 2)? as u32;
+    let day = number(date_fields.next()?, 2)? as u32;
+    let mut clock_fields = clock.split(':');
+    let hour = number(clock_fields.next()?, 2)?;
+    let minute = number(clock_fields.next()?, 2)?;
+    let second = number(clock_fields.next()?, 2)?;
+    if date_fields.next().is_some() || clock_fields.next().is_some() {
+        return None;
+    }
+
+    let offset_minutes = match zone {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match zone.get(..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = zone.get(1..)?.split_once(':')?;
+            let (hours, minutes) = (number(hours, 2)?, number(minutes, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            sign * (hours * 60 + minutes) as i32
+        }
+    };
+    if !(1..=12).contains(&month) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return None; // A day the month does not have
+    }
+    let local = days * 86_400 + hour * 3600 + minute * 60 + second;
+    let epoch = u64::try_from(local - offset_minutes as i64 * 60).ok()?;
+    Some((epoch, offset_minutes))
+}
+
+/// Parses an IMF-fixdate. The obsolete RFC 850 and asctime forms are not
+/// accepted; callers treat an unparseable date as if the header were absent.
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let mut fields = value.split_whitespace();
+    let weekday = fields.next()?.strip_suffix(',')?;
+    let day: u32 = fields.next()?.parse().ok()?;
+    let month_name = fields.next()?;
+    let month = MONTHS.iter().position(|m| *m == month_name)? as u32 + 1;
+    let year: i64 = fields.next()?.parse().ok()?;
+    let mut clock = fields
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    if fields.next()? != "GMT" || fields.next().is_some() || clock.next().is_some() {
+        return None;
+    }
+    if !WEEKDAYS.contains(&weekday)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if days < 0 || civil_from_days(days) != (year, month, day) {
+        return None; // Before the epoch, or a day the month does not have
+    }
+    let secs = days as u64 * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Locks `mutex`, taking the guard back if a thread panicked while holding it.
+/// The incident is logged under `name` and the poison cleared, so one failed
+/// handler neither crashes every later caller nor floods the log.
+pub fn lock_or_recover<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    lock_or_recover_using(mutex, name, |warning| eprintln!("{}", warning))
+}
+
+/// `lock_or_recover` that reports the incident by calling `log`, so tests can see it
+pub fn lock_or_recover_using<'a, T>(
+    mutex: &'a Mutex<T>,
+    name: &str,
+    log: impl FnOnce(String),
+) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log(format!(
+            "[LOCK] Recovered the {} lock after a thread panicked while holding it",
+            name
+        ));
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Runs `op` up to `attempts` times, sleeping between failures, and returns the
+/// first success or the last error. See `retry_with_backoff_using` for the delays.
+pub fn retry_with_backoff<T, E>(
+    attempts: u32,
+    base_delay: Duration,
+    op: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    retry_with_backoff_using(attempts, base_delay, op, thread::sleep)
+}
+
+/// `retry_with_backoff` that waits by calling `sleep`, so tests need not block.
+/// After the n-th failure the wait is "equal jitter": a random time in `[d/2, d]`
+/// where `d = base_delay * 2^n` capped at `RETRY_MAX_DELAY`, so callers that failed
+/// together do not retry in lockstep. At least one attempt is always made.
+pub fn retry_with_backoff_using<T, E>(
+    attempts: u32,
+    base_delay: Duration,
+    mut op: impl FnMut() -> Result<T, E>,
+    mut sleep: impl FnMut(Duration),
+) -> Result<T, E> {
+    let mut failures = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if failures + 1 >= attempts => return Err(e),
+            Err(_) => {
+                let ceiling = base_delay
+                    .checked_mul(1 << failures.min(31))
+                    .map_or(RETRY_MAX_DELAY, |delay| delay.min(RETRY_MAX_DELAY));
+                let half = ceiling / 2;
+                let spread = (ceiling - half).as_nanos() as u64;
+                let random = RandomState::new().build_hasher().finish();
+                sleep(half + Duration::from_nanos(random % spread.saturating_add(1)));
+                failures += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entropy_is_zero_for_empty_and_repeated_input() {
+        assert_eq!(shannon_entropy(&[]), 0.0);
+        assert_eq!(shannon_entropy(&[0x41; 1000]), 0.0);
+        assert!((shannon_entropy(b"abababab") - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entropy_approaches_eight_bits_for_uniform_bytes() {
+        let uniform: Vec<u8> = (0..4096).map(|i| (i % 256) as u8).collect();
+        assert!((shannon_entropy(&uniform) - 8.0).abs() < 1e-9);
+        let text = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        assert!(shannon_entropy(text) < 6.0);
+    }
+
+    #[test]
+    fn bind_address_prefers_a_non_empty_environment_override() {
+        // Each including binary compiles its own copy; keep their variables apart
+        let var = format!("ZT_TEST_BIND_{}", module_path!().replace("::", "_"));
+        std::env::remove_var(&var);
+        assert_eq!(bind_address(&var, "0.0.0.0:8080"), "0.0.0.0:8080");
+        std::env::set_var(&var, "   ");
+        assert_eq!(bind_address(&var, "0.0.0.0:8080"), "0.0.0.0:8080");
+        std::env::set_var(&var, " 127.0.0.1:0 ");
+        assert_eq!(bind_address(&var, "0.0.0.0:8080"), "127.0.0.1:0");
+        std::env::remove_var(&var);
+    }
+
+    #[test]
+    fn poisoned_lock_is_recovered_once_with_a_warning() {
+        let counter = std::sync::Arc::new(Mutex::new(1));
+        let holder = std::sync::Arc::clone(&counter);
+        let result = thread::spawn(move || {
+            let mut guard = holder.lock().unwrap();
+            *guard += 1;
+            panic!("handler failed while holding the lock");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(counter.is_poisoned());
+
+        let mut warnings = Vec::new();
+        {
+            let mut guard = lock_or_recover_using(&counter, "counter", |w| warnings.push(w));
+            assert_eq!(*guard, 2); // The panicked thread's write is kept
+            *guard += 1;
+        }
+        assert_eq!(
+            warnings,
+            ["[LOCK] Recovered the counter lock after a thread panicked while holding it"]
+        );
+        assert!(!counter.is_poisoned());
+
+        // Later callers lock normally and log nothing more
+        let mut later = Vec::new();
+        assert_eq!(
+            *lock_or_recover_using(&counter, "counter", |w| later.push(w)),
+            3
+        );
+        assert!(later.is_empty());
+        assert_eq!(*lock_or_recover(&counter, "counter"), 3);
+    }
+
+    #[test]
+    fn rfc3339_times_with_an_offset_round_trip_to_the_same_instant() {
+        let (epoch, offset) = parse_rfc3339("2025-06-01T09:00:00-04:00").unwrap();
+        assert_eq!((epoch, offset), (1_748_782_800, -240));
+        assert_eq!(parse_rfc3339("2025-06-01T13:00:00Z"), Some((epoch, 0)));
+        assert_eq!(
+            rfc3339_with_offset(epoch, offset),
+            "2025-06-01T09:00:00-04:00"
+        );
+        assert_eq!(rfc3339_with_offset(epoch, 0), "2025-06-01T13:00:00Z");
+
+        // Half-hour zones, and local times on the previous UTC day
+        let india = parse_rfc3339("2030-01-15T09:00:00+05:30").unwrap();
+        assert_eq!(india, (1_894_678_200, 330));
+        assert_eq!(
+            rfc3339_with_offset(india.0, 330),
+            "2030-01-15T09:00:00+05:30"
+        );
+        assert_eq!(
+            rfc3339_with_offset(india.0, -600),
+            "2030-01-14T17:30:00-10:00"
+        );
+
+        for bad in [
+            "2025-02-30T09:00:00Z",
+            "2025-06-01T24:00:00Z",
+            "2025-06-01T09:00:00+5:00",
+        ] {
+            assert_eq!(parse_rfc3339(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn an_op_that_fails_twice_is_retried_until_it_succeeds() {
+        let base = Duration::from_millis(100);
+        let mut calls = 0;
+        let mut waits = Vec::new();
+        let result = retry_with_backoff_using(
+            5,
+            base,
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err(calls)
+                } else {
+                    Ok("done")
+                }
+            },
+            |delay| waits.push(delay),
+        );
+
+        assert_eq!(result, Ok("done"));
+        assert_eq!(calls, 3);
+        assert_eq!(waits.len(), 2);
+        // Each wait is jittered within the upper half of a doubling ceiling
+        for (n, wait) in waits.iter().enumerate() {
+            let ceiling = base * (1 << n);
+            assert!(*wait >= ceiling / 2 && *wait <= ceiling, "wait {:?}", wait);
+        }
+    }
+
+    #[test]
+    fn a_persistently_failing_op_returns_its_last_error_after_the_cap() {
+        let mut calls = 0;
+        let mut waits = Vec::new();
+        let result: Result<(), String> = retry_with_backoff_using(
+            4,
+            Duration::from_secs(20),
+            || {
+                calls += 1;
+                Err(format!("attempt {}", calls))
+            },
+            |delay| waits.push(delay),
+        );
+
+        assert_eq!(result, Err("attempt 4".to_string()));
+        assert_eq!(calls, 4);
+        assert_eq!(waits.len(), 3);
+        assert!(waits.iter().all(|wait| *wait <= RETRY_MAX_DELAY));
+
+        // Even with no attempts allowed the op runs once, without waiting
+        let mut calls = 0;
+        let result: Result<(), u32> = retry_with_backoff_using(
+            0,
+            Duration::from_secs(1),
+            || {
+                calls += 1;
+                Err(calls)
+            },
+            |_| panic!("no retry should wait"),
+        );
+        assert_eq!(result, Err(1));
+    }
+}
@@ -1,12 +1,90 @@
 //! video.rs
 //! Secure Video Processing Aligned with Zero Trust Principles
 //! Rust standard library only – No third-party dependencies
+//! Files are checksummed with streaming SHA-256, so memory use does not grow with video size.
+//! Truncated files and timestamps before 1970 are reported as errors rather than panics.
+//! Byte ranges can be copied out to their own owner-only file, e.g. to segment media for the CDN.
 
+#[allow(dead_code)]
+mod crypto;
+#[allow(dead_code)]
+mod encoding;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
+use std::fmt;
 use std::fs::{metadata, File, OpenOptions};
-use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
-use std::os::unix::fs::PermissionsExt;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const HEADER_LEN: usize = 4; // Bytes read to identify the container format
+#[cfg(not(test))]
+const LOG_FILE: &str = "video_processing.log";
+#[cfg(test)]
+const LOG_FILE: &str = "target/video_processing_test.log"; // Keep test runs out of the working tree
+
+/// Why a video could not be processed
+#[derive(Debug)]
+enum VideoError {
+    Io(io::Error),
+    /// The file ends before the header that identifies its format
+    TooShort {
+        path: String,
+        len: u64,
+    },
+    /// A timestamp before the Unix epoch, from a file's mtime or the system clock
+    PreEpoch(String),
+    /// `len` bytes from `start` run past the end of the `size`-byte file
+    OutOfRange {
+        path: String,
+        start: u64,
+        len: u64,
+        size: u64,
+    },
+}
+
+impl fmt::Display for VideoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoError::Io(e) => write!(f, "video I/O failed: {}", e),
+            VideoError::TooShort { path, len } => write!(
+                f,
+                "{} is {} bytes, too short for a {}-byte video header",
+                path, len, HEADER_LEN
+            ),
+            VideoError::PreEpoch(what) => write!(f, "{} is before 1970", what),
+            VideoError::OutOfRange {
+                path,
+                start,
+                len,
+                size,
+            } => write!(
+                f,
+                "{} bytes at offset {} run past the end of {} ({} bytes)",
+                len, start, path, size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VideoError {}
+
+impl From<io::Error> for VideoError {
+    fn from(e: io::Error) -> Self {
+        VideoError::Io(e)
+    }
+}
+
+/// Whole seconds from the Unix epoch to `time`; `what` names it in the error
+fn unix_seconds(time: SystemTime, what: &str) -> Result<u64, VideoError> {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .map_err(|_| VideoError::PreEpoch(what.to_string()))
+}
+
 /// Structure to hold video metadata manually parsed from file
 struct VideoMetadata {
     file_size: u64,
@@ -15,13 +93,12 @@ struct VideoMetadata {
 }
 
 /// Manually extracts and sanitizes video metadata
-fn extract_metadata(file_path: &str) -> std::io::Result<VideoMetadata> {
+fn extract_metadata(file_path: &str) -> Result<VideoMetadata, VideoError> {
     let meta = metadata(file_path)?;
-    let modified_time = meta
-        .modified()?
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    let modified_time = unix_seconds(
+        meta.modified()?,
+        &format!("modification time of {}", file_path),
+    )?;
     let permissions = meta.permissions().mode();
 
     Ok(VideoMetadata {
@@ -32,7 +109,7 @@ fn extract_metadata(file_path: &str) -> std::io::Result<VideoMetadata> {
 }
 
 /// Securely strips metadata from video files (overwrite sensitive headers)
-fn sanitize_metadata(file_path: &str) -> std::io::Result<()> {
+fn sanitize_metadata(file_path: &str) -> Result<(), VideoError> {
     let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
 
     // Manual metadata stripping (assuming standard video header sizes)
@@ -41,51 +118,116 @@ fn sanitize_metadata(file_path: &str) -> std::io::Result<()> {
     file.write_all(&zero_header)?;
 
     println!("Metadata sanitized for '{}'", file_path);
-    secure_log(&format!("Metadata sanitized: {}", file_path))
+    secure_log(&format!(
+        "Metadata sanitized: {} - SHA-256: {}",
+        file_path,
+        video_checksum(file_path)?
+    ))
 }
 
-/// Manually verify video integrity by checking file headers
-fn verify_video_integrity(file_path: &str) -> std::io::Result<bool> {
-    let mut file = File::open(file_path)?;
-    let mut buffer = [0u8; 4];
+/// SHA-256 of the file's contents as lowercase hex, read in fixed-size chunks
+fn video_checksum(file_path: &str) -> std::io::Result<String> {
+    let mut state = crypto::Sha256State::new();
+    crypto::hash_reader(File::open(file_path)?, &mut state)?;
+    Ok(encoding::hex_encode(state.finalize()))
+}
+
+/// Copies `len` bytes of `input` starting at `start_byte` into `output`, which is
+/// created (or replaced) readable by the owner only. The range must lie within the file.
+#[allow(dead_code)] // Used to cut segments of large media for CDN range requests
+fn extract_range(input: &str, output: &str, start_byte: u64, len: u64) -> Result<(), VideoError> {
+    let mut source = File::open(input)?;
+    let size = source.metadata()?.len();
+    let out_of_range = || VideoError::OutOfRange {
+        path: input.to_string(),
+        start: start_byte,
+        len,
+        size,
+    };
+    if start_byte.checked_add(len).is_none_or(|end| end > size) {
+        return Err(out_of_range());
+    }
+
+    source.seek(SeekFrom::Start(start_byte))?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(output)?;
+    file.set_permissions(PermissionsExt::from_mode(0o600))?;
+    let mut writer = BufWriter::new(file);
+    // The input may have shrunk since its size was read
+    if io::copy(&mut source.take(len), &mut writer)? < len {
+        return Err(out_of_range());
+    }
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
 
-    file.read_exact(&mut buffer)?;
+    secure_log(&format!(
+        "Range extracted: {} bytes {}-{} -> {} - SHA-256: {}",
+        input,
+        start_byte,
+        start_byte + len,
+        output,
+        video_checksum(output)?
+    ))
+}
 
-    let is_valid_format = match &buffer {
+/// Manually verify video integrity by checking file headers
+fn verify_video_integrity(file_path: &str) -> Result<bool, VideoError> {
+    let mut buffer = Vec::with_capacity(HEADER_LEN);
+    File::open(file_path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut buffer)?;
+    if buffer.len() < HEADER_LEN {
+        return Err(VideoError::TooShort {
+            path: file_path.to_string(),
+            len: buffer.len() as u64,
+        });
+    }
+
+    let is_valid_format = match &buffer[..] {
         b"\x00\x00\x00\x18" => true, // Example MP4 header (simplified)
         b"RIFF" => true,             // Example AVI header
         _ => false,
     };
 
+    let checksum = video_checksum(file_path)?;
     secure_log(&format!(
-        "Video integrity check: {} - Valid: {}",
-        file_path, is_valid_format
-    ));
+        "Video integrity check: {} - Valid: {} - SHA-256: {}",
+        file_path, is_valid_format, checksum
+    ))?;
 
     Ok(is_valid_format)
 }
 
 /// Securely log video processing events with tamper-proof timestamping
-fn secure_log(entry: &str) -> std::io::Result<()> {
+fn secure_log(entry: &str) -> Result<(), VideoError> {
     let mut log_file = OpenOptions::new()
         .create(true)
         .append(true)
         .mode(0o600) // Secure permissions (owner read/write only)
-        .open("video_processing.log")?;
+        .open(LOG_FILE)?;
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    let timestamp = unix_seconds(SystemTime::now(), "system clock")?;
 
     writeln!(log_file, "[{}] {}", timestamp, entry)?;
 
     Ok(())
 }
 
-fn main() -> std::io::Result<()> {
+fn main() {
     let video_file = "sample_video.mp4";
+    if let Err(e) = process_video(video_file) {
+        eprintln!("⚠️ Processing '{}' failed: {}", video_file, e);
+        let _ = secure_log(&format!("ERROR: {} not processed: {}", video_file, e));
+    }
+}
 
+fn process_video(video_file: &str) -> Result<(), VideoError> {
     println!("Processing video file: {}", video_file);
 
     // Extract metadata and display securely
@@ -114,3 +256,86 @@ fn main() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_video(name: &str, contents: &[u8]) -> String {
+        let path = test_util::scratch_path("video", name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn a_file_shorter_than_the_header_is_a_clean_error() {
+        let path = temp_video("two-bytes", b"RI");
+
+        match verify_video_integrity(&path) {
+            Err(VideoError::TooShort { len, .. }) => assert_eq!(len, 2),
+            other => panic!("expected TooShort, got {:?}", other),
+        }
+        assert!(matches!(
+            process_video(&path),
+            Err(VideoError::TooShort { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn modification_times_are_read_and_pre_epoch_ones_rejected() {
+        let path = temp_video("mtime", b"RIFF....AVI ");
+        let file = File::options().write(true).open(&path).unwrap();
+
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+            .unwrap();
+        let metadata = extract_metadata(&path).unwrap();
+        assert_eq!(metadata.last_modified, 1_000_000_000);
+        assert_eq!(metadata.file_size, 12);
+
+        file.set_modified(UNIX_EPOCH - Duration::from_secs(86_400))
+            .unwrap();
+        match extract_metadata(&path) {
+            Err(VideoError::PreEpoch(what)) => assert!(what.contains(&path)),
+            other => panic!(
+                "expected PreEpoch, got {:?}",
+                other.map(|m| m.last_modified)
+            ),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_middle_range_is_copied_into_an_owner_only_file() {
+        let source: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+        let input = temp_video("range-source", &source);
+        let output = format!("{}.clip", input);
+
+        extract_range(&input, &output, 4_000, 1_500).unwrap();
+
+        assert_eq!(std::fs::read(&output).unwrap(), &source[4_000..5_500]);
+        let mode = metadata(&output).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn a_range_past_the_end_of_the_file_is_rejected() {
+        let input = temp_video("range-short", &[7u8; 100]);
+        let output = format!("{}.clip", input);
+
+        for (start, len) in [(90, 11), (101, 0), (1, u64::MAX)] {
+            match extract_range(&input, &output, start, len) {
+                Err(VideoError::OutOfRange { size, .. }) => assert_eq!(size, 100),
+                other => panic!("expected OutOfRange for {}+{}, got {:?}", start, len, other),
+            }
+        }
+        assert!(!std::path::Path::new(&output).exists());
+        extract_range(&input, &output, 90, 10).unwrap(); // Ending exactly at EOF is fine
+        assert_eq!(std::fs::read(&output).unwrap(), [7u8; 10]);
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Zero Trust WebSocket Transport (Rust Standard Library Only)
+//! RFC 6455 framing for connections upgraded by the HTTP server.
+//! Features:
+//! - **Sec-WebSocket-Accept derivation for the opening handshake**
+//! - **Mandatory client masking; unmasked client frames are a protocol error**
+//! - **Fragmented message reassembly with a bounded message size**
+//! - **Automatic Pong replies and close handshake echoing**
+
+#[allow(dead_code)]
+#[path = "crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "encoding.rs"]
+mod encoding;
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// GUID appended to the client key before hashing (RFC 6455 section 1.3)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+const MAX_CONTROL_PAYLOAD: usize = 125;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_INVALID_DATA: u16 = 1007;
+pub const CLOSE_TOO_BIG: u16 = 1009;
+
+/// A complete WebSocket message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    #[allow(dead_code)] // Sent by callers probing a quiet peer
+    Ping(Vec<u8>),
+    #[allow(dead_code)] // Sent by callers as an unsolicited heartbeat
+    Pong(Vec<u8>),
+    Close(Option<u16>),
+}
+
+/// A valid key is the base64 encoding of 16 bytes
+pub fn is_valid_key(key: &str) -> bool {
+    encoding::STANDARD
+        .decode(key)
+        .is_ok_and(|nonce| nonce.len() == 16)
+}
+
+/// Derives `Sec-WebSocket-Accept` from the client's `Sec-WebSocket-Key`
+pub fn accept_key(client_key: &str) -> String {
+    let mut input = client_key.trim().as_bytes().to_vec();
+    input.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+    encoding::STANDARD.encode(crypto::sha1(&input))
+}
+
+/// The `101 Switching Protocols` response completing the handshake
+pub fn handshake_response(client_key: &str) -> String {
+    format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(client_key)
+    )
+}
+
+/// Why reading a message failed; violations carry the close code to send
+enum RecvError {
+    Io(io::Error),
+    Violation(u16, &'static str),
+}
+
+impl From<io::Error> for RecvError {
+    fn from(e: io::Error) -> Self {
+        RecvError::Io(e)
+    }
+}
+
+fn violation(message: &'static str) -> RecvError {
+    RecvError::Violation(CLOSE_PROTOCOL_ERROR, message)
+}
+
+/// Server side of an upgraded connection
+pub struct WebSocket {
+    stream: TcpStream,
+    closed: bool,
+}
+
+impl WebSocket {
+    /// Wraps a stream whose handshake has been completed (clients may not send
+    /// frames before receiving the 101 response, so nothing is buffered)
+    pub fn from_upgraded(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            closed: false,
+        }
+    }
+
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        // Server-to-client frames are never masked
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)
+    }
+
+    /// Sends one message as a single frame
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        if self.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "websocket is closed",
+            ));
+        }
+        match message {
+            Message::Text(text) => self.write_frame(OPCODE_TEXT, text.as_bytes()),
+            Message::Binary(data) => self.write_frame(OPCODE_BINARY, data),
+            Message::Ping(data) | Message::Pong(data) if data.len() > MAX_CONTROL_PAYLOAD => {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "control frame payload exceeds 125 bytes",
+                ))
+            }
+            Message::Ping(data) => self.write_frame(OPCODE_PING, data),
+            Message::Pong(data) => self.write_frame(OPCODE_PONG, data),
+            Message::Close(code) => {
+                let payload = code.map(|c| c.to_be_bytes().to_vec()).unwrap_or_default();
+                self.closed = true;
+                self.write_frame(OPCODE_CLOSE, &payload)
+            }
+        }
+    }
+
+    /// Sends a close frame with `code` and stops accepting further sends
+    pub fn close(&mut self, code: u16) -> io::Result<()> {
+        self.send(&Message::Close(Some(code)))
+    }
+
+    /// Reads one frame: `(fin, opcode, unmasked payload)`
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), RecvError> {
+        let mut head = [0u8; 2];
+        self.stream.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        if head[0] & 0x70 != 0 {
+            return Err(violation("reserved bits set without an extension"));
+        }
+        if head[1] & 0x80 == 0 {
+            return Err(violation("client frames must be masked"));
+        }
+
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut ext = [0u8; 2];
+                self.stream.read_exact(&mut ext)?;
+                u16::from_be_bytes(ext) as usize
+            }
+            127 => {
+                let mut ext = [0u8; 8];
+                self.stream.read_exact(&mut ext)?;
+                usize::try_from(u64::from_be_bytes(ext)).unwrap_or(usize::MAX)
+            }
+            len => len as usize,
+        };
+        if opcode >= OPCODE_CLOSE && (len > MAX_CONTROL_PAYLOAD || !fin) {
+            return Err(violation("invalid control frame"));
+        }
+        if len > MAX_MESSAGE_SIZE {
+            return Err(RecvError::Violation(
+                CLOSE_TOO_BIG,
+                "frame exceeds maximum message size",
+            ));
+        }
+
+        let mut mask = [0u8; 4];
+        self.stream.read_exact(&mut mask)?;
+        let mut payload = vec![0u8; len];
+        self.stream.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok((fin, opcode, payload))
+    }
+
+    /// Receives the next data or close message. Pings are answered automatically,
+    /// fragmented messages are reassembled, and protocol violations close the
+    /// connection with the matching status code before the error is returned.
+    pub fn recv(&mut self) -> io::Result<Message> {
+        match self.recv_inner() {
+            Ok(message) => Ok(message),
+            Err(RecvError::Io(e)) => Err(e),
+            Err(RecvError::Violation(code, reason)) => {
+                if !self.closed {
+                    let _ = self.close(code);
+                }
+                Err(io::Error::new(io::ErrorKind::InvalidData, reason))
+            }
+        }
+    }
+
+    fn recv_inner(&mut self) -> Result<Message, RecvError> {
+        let mut fragments: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OPCODE_PING => {
+                    self.write_frame(OPCODE_PONG, &payload)?;
+                }
+                OPCODE_PONG => {}
+                OPCODE_CLOSE => {
+                    let code =
+                        (payload.len() >= 2).then(|| u16::from_be_bytes([payload[0], payload[1]]));
+                    if !self.closed {
+                        // Echo the close to complete the closing handshake
+                        self.send(&Message::Close(code))?;
+                    }
+                    return Ok(Message::Close(code));
+                }
+                OPCODE_TEXT | OPCODE_BINARY => {
+                    if fragments.is_some() {
+                        return Err(violation("new message started before the last finished"));
+                    }
+                    if fin {
+                        return Self::assemble(opcode, payload);
+                    }
+                    fragments = Some((opcode, payload));
+                }
+                OPCODE_CONTINUATION => {
+                    let (first_opcode, mut data) = fragments
+                        .take()
+                        .ok_or_else(|| violation("continuation without a message"))?;
+                    if data.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(RecvError::Violation(
+                            CLOSE_TOO_BIG,
+                            "message exceeds maximum message size",
+                        ));
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        return Self::assemble(first_opcode, data);
+                    }
+                    fragments = Some((first_opcode, data));
+                }
+                _ => return Err(violation("unknown opcode")),
+            }
+        }
+    }
+
+    fn assemble(opcode: u8, data: Vec<u8>) -> Result<Message, RecvError> {
+        if opcode == OPCODE_TEXT {
+            String::from_utf8(data).map(Message::Text).map_err(|_| {
+                RecvError::Violation(CLOSE_INVALID_DATA, "text message is not valid UTF-8")
+            })
+        } else {
+            Ok(Message::Binary(data))
+        }
+    }
+}
@@ -8,89 +8,325 @@
 //! - **Load balancing integration for service distribution**
 //! - **Tamper-proof audit logging for scaling decisions**
 //! - **Monitors CPU, memory, and network traffic for auto-scaling decisions**
+//!
+//! This is the single `AutoScaler` implementation; the cluster orchestrator
+//! plugs its container runtime in through the `ScalingBackend` trait.
 
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../backend/encoding.rs"]
+mod encoding;
+#[path = "error.rs"]
+mod error;
+#[path = "startup.rs"]
+pub mod startup;
+
+use crypto::{constant_time_eq, hmac_sha256};
+use encoding::hex_encode;
+pub use error::ClusterError;
+use startup::StartupCheck;
 use std::collections::{HashMap, HashSet};
 use std::process::{Child, Command};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a draining instance may keep working before it is killed
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Interval between idle checks while draining
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Defines a scalable service instance
 struct ScalableService {
     id: String,
     process: Option<Child>,
-    cpu_usage: u8,     // CPU percentage
+    cpu_usage: u8, // CPU percentage
+    #[allow(dead_code)] // Reserved with the instance; scaling only weighs CPU
     memory_usage: u64, // Memory in MB
+    launched_at: Instant,
+    in_flight: u64, // Requests currently being processed
+    draining: bool, // Refuses new work while shutting down
 }
 
-/// Manages adaptive scaling and dynamic workload balancing
-struct AutoScaler {
+/// Describes how new service instances are launched when scaling up
+pub struct ServiceTemplate {
+    pub name_prefix: String,
+    pub command: String,
+    pub cpu_usage: u8,     // CPU percentage
+    pub memory_usage: u64, // Memory in MB
+    /// Readiness an instance must show before it counts as launched; `None` trusts the spawn
+    pub startup: Option<StartupCheck>,
+}
+
+/// Executes scaling decisions against a concrete workload runtime
+pub trait ScalingBackend: Send + Sync {
+    /// Launches a new instance from the template
+    fn launch(&self, instance_id: &str, template: &ServiceTemplate) -> Result<(), ClusterError>;
+    /// Terminates a running instance
+    fn terminate(&self, instance_id: &str);
+    /// Returns the number of running instances
+    fn instance_count(&self) -> usize;
+    /// Stops routing new work to an instance ahead of termination
+    fn mark_draining(&self, instance_id: &str);
+    /// Returns true once an instance has no in-flight work
+    fn is_idle(&self, instance_id: &str) -> bool;
+    /// Returns the least-loaded, non-draining instance to remove first on scale-down
+    fn idle_instance(&self) -> Option<String>;
+}
+
+/// Default backend that runs every instance as a plain child process
+#[derive(Default)]
+pub struct ProcessBackend {
     active_services: Mutex<HashMap<String, ScalableService>>,
-    scaling_threshold: u64,
-    trusted_instances: Mutex<HashSet<String>>, // Registered trusted instances
 }
 
-impl AutoScaler {
-    /// Initializes the auto-scaler with scaling policies
-    fn new(scaling_threshold: u64) -> Self {
+impl ProcessBackend {
+    /// Creates an empty process backend
+    pub fn new() -> Self {
         Self {
             active_services: Mutex::new(HashMap::new()),
-            scaling_threshold,
-            trusted_instances: Mutex::new(HashSet::new()),
         }
     }
 
-    /// Registers a trusted instance for scaling
-    fn register_instance(&self, instance_id: &str) {
-        let mut instances = self.trusted_instances.lock().unwrap();
-        instances.insert(instance_id.to_string());
-        println!("[SCALER] Instance '{}' registered as trusted.", instance_id);
+    /// Assigns a unit of work to an instance; refused while it is draining
+    pub fn begin_work(&self, instance_id: &str) -> bool {
+        let mut active_services = self.active_services.lock().unwrap();
+        match active_services.get_mut(instance_id) {
+            Some(service) if !service.draining => {
+                service.in_flight += 1;
+                true
+            }
+            _ => false,
+        }
     }
 
+    /// Marks a unit of work on an instance as finished
+    pub fn finish_work(&self, instance_id: &str) {
+        let mut active_services = self.active_services.lock().unwrap();
+        if let Some(service) = active_services.get_mut(instance_id) {
+            service.in_flight = service.in_flight.saturating_sub(1);
+        }
+    }
+
+    /// Records the latest CPU usage sample for an instance
+    pub fn report_usage(&self, instance_id: &str, cpu_usage: u8) {
+        let mut active_services = self.active_services.lock().unwrap();
+        if let Some(service) = active_services.get_mut(instance_id) {
+            service.cpu_usage = cpu_usage;
+        }
+    }
+}
+
+impl ScalingBackend for ProcessBackend {
     /// Launches a new service instance dynamically
-    fn launch_service(&self, service_id: &str, command: &str, cpu_usage: u8, memory_usage: u64) {
+    fn launch(&self, instance_id: &str, template: &ServiceTemplate) -> Result<(), ClusterError> {
+        let process = startup::spawn_checked(
+            &mut Command::new(&template.command),
+            template.startup.as_ref(),
+        )
+        .map_err(|e| ClusterError::from_startup(instance_id, e))?;
         let mut active_services = self.active_services.lock().unwrap();
-        let process = Command::new(command)
-            .spawn()
-            .expect("[SCALER] Failed to launch service instance");
 
         let service = ScalableService {
-            id: service_id.to_string(),
+            id: instance_id.to_string(),
             process: Some(process),
-            cpu_usage,
-            memory_usage,
+            cpu_usage: template.cpu_usage,
+            memory_usage: template.memory_usage,
+            launched_at: Instant::now(),
+            in_flight: 0,
+            draining: false,
         };
 
-        active_services.insert(service_id.to_string(), service);
-        println!("[SCALER] Launched new service instance: {}", service_id);
+        active_services.insert(instance_id.to_string(), service);
+        println!("[SCALER] Launched new service instance: {}", instance_id);
+        Ok(())
     }
 
     /// Terminates an overloaded service instance
-    fn terminate_service(&self, service_id: &str) {
+    fn terminate(&self, instance_id: &str) {
         let mut active_services = self.active_services.lock().unwrap();
-        if let Some(service) = active_services.remove(service_id) {
+        if let Some(service) = active_services.remove(instance_id) {
             if let Some(mut process) = service.process {
-                process
-                    .kill()
-                    .expect("[SCALER] Failed to terminate service instance");
-                println!("[SCALER] Terminated overloaded instance: {}", service_id);
+                if let Err(e) = process.kill() {
+                    println!("[SCALER] Failed to terminate {}: {}", instance_id, e);
+                    return;
+                }
+                println!("[SCALER] Terminated overloaded instance: {}", instance_id);
             }
         }
     }
 
-    /// Evaluates the current load and scales services accordingly
-    fn evaluate_scaling(&self, current_load: u64) {
+    fn instance_count(&self) -> usize {
+        self.active_services.lock().unwrap().len()
+    }
+
+    fn mark_draining(&self, instance_id: &str) {
         let mut active_services = self.active_services.lock().unwrap();
+        if let Some(service) = active_services.get_mut(instance_id) {
+            service.draining = true;
+        }
+    }
+
+    fn is_idle(&self, instance_id: &str) -> bool {
+        let active_services = self.active_services.lock().unwrap();
+        active_services
+            .get(instance_id)
+            .is_none_or(|s| s.in_flight == 0)
+    }
+
+    fn idle_instance(&self) -> Option<String> {
+        let active_services = self.active_services.lock().unwrap();
+        active_services
+            .values()
+            .filter(|s| !s.draining)
+            .min_by_key(|s| (s.in_flight, s.cpu_usage, std::cmp::Reverse(s.launched_at)))
+            .map(|s| s.id.clone())
+    }
+}
+
+/// Manages adaptive scaling and dynamic workload balancing
+pub struct AutoScaler {
+    backend: Arc<dyn ScalingBackend>,
+    template: ServiceTemplate,
+    scaling_threshold: u64,
+    max_instances: usize,
+    launched: Mutex<u64>,
+    trusted_instances: Mutex<HashSet<String>>, // Registered trusted instances
+    attestation_key: Vec<u8>,
+}
+
+impl AutoScaler {
+    /// Initializes the auto-scaler with scaling policies
+    pub fn new(
+        backend: Arc<dyn ScalingBackend>,
+        template: ServiceTemplate,
+        scaling_threshold: u64,
+        max_instances: usize,
+        attestation_key: &[u8],
+    ) -> Self {
+        Self {
+            backend,
+            template,
+            scaling_threshold,
+            max_instances: max_instances.max(1),
+            launched: Mutex::new(0),
+            trusted_instances: Mutex::new(HashSet::new()),
+            attestation_key: attestation_key.to_vec(),
+        }
+    }
+
+    /// Registers a trusted instance for scaling
+    pub fn register_instance(&self, instance_id: &str) {
+        let mut instances = self.trusted_instances.lock().unwrap();
+        instances.insert(instance_id.to_string());
+        println!("[SCALER] Instance '{}' registered as trusted.", instance_id);
+    }
+
+    /// Registers an instance as trusted only if it presents a valid signed attestation token
+    pub fn attest_instance(&self, instance_id: &str, token: &str) -> bool {
+        let expected = sign_attestation(&self.attestation_key, instance_id);
+        if !constant_time_eq(expected.as_bytes(), token.as_bytes()) {
+            println!(
+                "[SECURITY] Invalid attestation presented for instance '{}'.",
+                instance_id
+            );
+            return false;
+        }
+        self.register_instance(instance_id);
+        log_scaling_event(instance_id, "Attested");
+        true
+    }
+
+    /// Returns the id the next scale-up will launch, so it can be attested ahead of time
+    pub fn next_instance_id(&self) -> String {
+        let launched = self.launched.lock().unwrap();
+        format!("{}-{}", self.template.name_prefix, *launched + 1)
+    }
+
+    /// Evaluates the current load and scales services accordingly
+    pub fn evaluate_scaling(&self, current_load: u64) {
+        let instances = self.backend.instance_count();
         if current_load > self.scaling_threshold {
-            let new_instance_id = format!("instance-{}", active_services.len() + 1);
-            self.launch_service(&new_instance_id, "/bin/sh", 50, 512);
-        } else if !active_services.is_empty() {
-            let oldest_instance = active_services.keys().next().unwrap().to_string();
-            self.terminate_service(&oldest_instance);
+            if instances >= self.max_instances {
+                println!(
+                    "[SCALER] At maximum of {} instances, not scaling up",
+                    self.max_instances
+                );
+                return;
+            }
+            self.scale_up();
+        } else if instances > 1 {
+            self.scale_down();
+        }
+    }
+
+    /// Launches a new instance from the template once it has been attested
+    fn scale_up(&self) {
+        let mut launched = self.launched.lock().unwrap();
+        let instance_id = format!("{}-{}", self.template.name_prefix, *launched + 1);
+        if !self.enforce_trust(&instance_id) {
+            return;
+        }
+        *launched += 1;
+
+        match self.backend.launch(&instance_id, &self.template) {
+            Ok(()) => {
+                log_scaling_event(&instance_id, "Scaled up");
+                println!(
+                    "[SCALER] Increased active instances to {}",
+                    self.backend.instance_count()
+                );
+            }
+            Err(e) => println!("[SCALER] Scale-up of {} failed: {}", instance_id, e),
+        }
+    }
+
+    /// Drains and terminates the least-loaded instance chosen by the backend
+    fn scale_down(&self) {
+        match self.backend.idle_instance() {
+            Some(instance_id) => {
+                self.drain(&instance_id, DRAIN_TIMEOUT);
+                log_scaling_event(&instance_id, "Scaled down");
+                println!(
+                    "[SCALER] Decreased active instances to {}",
+                    self.backend.instance_count()
+                );
+            }
+            None => println!("[SCALER] No idle instance available to scale down"),
         }
     }
 
+    /// Stops new work reaching an instance, waits for it to go idle, then terminates it.
+    /// Returns false if the instance had to be killed with work still in flight.
+    pub fn drain(&self, service_id: &str, timeout: Duration) -> bool {
+        self.backend.mark_draining(service_id);
+        log_scaling_event(service_id, "Draining");
+
+        let deadline = Instant::now() + timeout;
+        let drained = loop {
+            if self.backend.is_idle(service_id) {
+                break true;
+            }
+            if Instant::now() >= deadline {
+                break false;
+            }
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        };
+
+        if !drained {
+            println!(
+                "[SCALER] Instance '{}' did not drain within {:?}, killing it.",
+                service_id, timeout
+            );
+        }
+        self.backend.terminate(service_id);
+        drained
+    }
+
     /// Ensures only trusted instances are running
-    fn enforce_trust(&self, instance_id: &str) -> bool {
+    pub fn enforce_trust(&self, instance_id: &str) -> bool {
         let trusted_instances = self.trusted_instances.lock().unwrap();
         if !trusted_instances.contains(instance_id) {
             println!(
@@ -103,13 +339,89 @@ impl AutoScaler {
     }
 }
 
+/// Produces the attestation token for an instance (hex-encoded HMAC-SHA256)
+pub fn sign_attestation(key: &[u8], instance_id: &str) -> String {
+    hex_encode(hmac_sha256(key, instance_id.as_bytes()))
+}
+
 /// Predictive load analysis for proactive scaling
-fn predict_load(trends: &[u64]) -> u64 {
+pub fn predict_load(trends: &[u64]) -> u64 {
+    if trends.is_empty() {
+        return 0;
+    }
     trends.iter().sum::<u64>() / (trends.len() as u64)
 }
 
+/// Direction the load is moving in across the sampled window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrendDirection {
+    Rising,
+    Falling,
+    Stable,
+}
+
+/// Result of a weighted load prediction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadPrediction {
+    pub predicted: u64,
+    pub trend: TrendDirection,
+}
+
+/// Predicts the next load sample using an exponentially-weighted moving average
+/// (`alpha` in `(0, 1]`, higher favours recent samples) extended by the
+/// least-squares slope of the series so sustained growth is anticipated.
+pub fn predict_load_ewma(trends: &[u64], alpha: f64) -> LoadPrediction {
+    if trends.is_empty() {
+        return LoadPrediction {
+            predicted: 0,
+            trend: TrendDirection::Stable,
+        };
+    }
+
+    let alpha = alpha.clamp(f64::EPSILON, 1.0);
+    let ewma = trends
+        .iter()
+        .skip(1)
+        .fold(trends[0] as f64, |avg, &sample| {
+            alpha * sample as f64 + (1.0 - alpha) * avg
+        });
+
+    let slope = linear_regression_slope(trends);
+    let trend = if slope > 0.5 {
+        TrendDirection::Rising
+    } else if slope < -0.5 {
+        TrendDirection::Falling
+    } else {
+        TrendDirection::Stable
+    };
+
+    LoadPrediction {
+        predicted: (ewma + slope).max(0.0).round() as u64,
+        trend,
+    }
+}
+
+/// Least-squares slope of the samples against their index
+fn linear_regression_slope(samples: &[u64]) -> f64 {
+    let n = samples.len() as f64;
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = samples.iter().sum::<u64>() as f64 / n;
+    let (covariance, variance) =
+        samples
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(cov, var), (i, &y)| {
+                let dx = i as f64 - mean_x;
+                (cov + dx * (y as f64 - mean_y), var + dx * dx)
+            });
+    covariance / variance
+}
+
 /// Securely logs auto-scaling events
-fn log_scaling_event(service: &str, action: &str) {
+pub fn log_scaling_event(service: &str, action: &str) {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -120,20 +432,47 @@ fn log_scaling_event(service: &str, action: &str) {
     );
 }
 
+/// Shared secret used by the simulated attestation service
+const ATTESTATION_KEY: &[u8] = b"zero-trust-attestation-key";
+
 /// Simulated real-time adaptive scaling and secure execution
 fn main() {
-    let auto_scaler = Arc::new(AutoScaler::new(70));
+    let template = ServiceTemplate {
+        name_prefix: "instance".to_string(),
+        command: "/bin/sh".to_string(),
+        cpu_usage: 50,
+        memory_usage: 512,
+        startup: None,
+    };
+    let auto_scaler = Arc::new(AutoScaler::new(
+        Arc::new(ProcessBackend::new()),
+        template,
+        70,
+        8,
+        ATTESTATION_KEY,
+    ));
 
     // Register trusted instances
     auto_scaler.register_instance("trusted-instance-1");
     auto_scaler.register_instance("trusted-instance-2");
 
+    // Attest the instance the next scale-up will launch
+    let next_instance = auto_scaler.next_instance_id();
+    auto_scaler.attest_instance(
+        &next_instance,
+        &sign_attestation(ATTESTATION_KEY, &next_instance),
+    );
+
     // Simulate workload spikes
     let workload_trends = vec![65, 75, 80, 90, 85];
-    let predicted_load = predict_load(&workload_trends);
+    let average_load = predict_load(&workload_trends);
+    let prediction = predict_load_ewma(&workload_trends, 0.5);
 
-    println!("[SCALER] Predicted workload: {}", predicted_load);
-    auto_scaler.evaluate_scaling(predicted_load);
+    println!(
+        "[SCALER] Predicted workload: {} (mean {}, trend {:?})",
+        prediction.predicted, average_load, prediction.trend
+    );
+    auto_scaler.evaluate_scaling(prediction.predicted);
 
     // Log scaling actions
     log_scaling_event("backend-service", "Auto-scaled based on demand");
@@ -141,3 +480,106 @@ fn main() {
     // Verify instance trust
     auto_scaler.enforce_trust("malicious-instance");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KEY: &[u8] = b"test-attestation-key";
+
+    /// An auto-scaler launching `/bin/true` instances on a process backend
+    fn process_scaler() -> (Arc<ProcessBackend>, AutoScaler) {
+        let backend = Arc::new(ProcessBackend::new());
+        let template = ServiceTemplate {
+            name_prefix: "instance".to_string(),
+            command: "/bin/true".to_string(),
+            cpu_usage: 10,
+            memory_usage: 16,
+            startup: None,
+        };
+        let auto_scaler = AutoScaler::new(backend.clone(), template, 70, 4, TEST_KEY);
+        (backend, auto_scaler)
+    }
+
+    #[test]
+    fn untrusted_instance_is_not_launched_until_attested() {
+        let (backend, auto_scaler) = process_scaler();
+        let next = auto_scaler.next_instance_id();
+
+        auto_scaler.evaluate_scaling(90);
+        assert_eq!(backend.instance_count(), 0);
+        assert!(!auto_scaler.attest_instance(&next, &sign_attestation(b"wrong-key", &next)));
+        auto_scaler.evaluate_scaling(90);
+        assert_eq!(backend.instance_count(), 0);
+
+        assert!(auto_scaler.attest_instance(&next, &sign_attestation(TEST_KEY, &next)));
+        auto_scaler.evaluate_scaling(90);
+        assert_eq!(backend.instance_count(), 1);
+        assert!(auto_scaler.enforce_trust(&next));
+    }
+
+    #[test]
+    fn rising_series_is_predicted_above_its_mean() {
+        let series = [10, 20, 30, 40, 50, 60];
+        let prediction = predict_load_ewma(&series, 0.5);
+        assert!(prediction.predicted > predict_load(&series));
+        assert_eq!(prediction.trend, TrendDirection::Rising);
+
+        let flat = predict_load_ewma(&[40, 40, 40], 0.5);
+        assert_eq!(flat.predicted, 40);
+        assert_eq!(flat.trend, TrendDirection::Stable);
+        assert_eq!(predict_load_ewma(&[], 0.5).predicted, 0);
+    }
+
+    #[test]
+    fn draining_instance_gets_no_new_work_and_is_killed_after_the_timeout() {
+        let (backend, auto_scaler) = process_scaler();
+        auto_scaler.register_instance("instance-1");
+        auto_scaler.evaluate_scaling(90);
+        assert!(backend.begin_work("instance-1"));
+
+        backend.mark_draining("instance-1");
+        assert!(!backend.begin_work("instance-1"));
+        assert_eq!(backend.idle_instance(), None);
+
+        // The in-flight request never finishes, so only the timeout ends the drain
+        let started = Instant::now();
+        assert!(!auto_scaler.drain("instance-1", Duration::from_millis(200)));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(backend.instance_count(), 0);
+    }
+
+    #[test]
+    fn idle_instance_drains_without_waiting_for_the_timeout() {
+        let (backend, auto_scaler) = process_scaler();
+        auto_scaler.register_instance("instance-1");
+        auto_scaler.evaluate_scaling(90);
+        assert!(backend.begin_work("instance-1"));
+        backend.finish_work("instance-1");
+
+        let started = Instant::now();
+        assert!(auto_scaler.drain("instance-1", Duration::from_secs(5)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(backend.instance_count(), 0);
+    }
+
+    #[test]
+    fn failed_spawn_is_reported_and_the_backend_keeps_working() {
+        let backend = ProcessBackend::new();
+        let mut template = ServiceTemplate {
+            name_prefix: "instance".to_string(),
+            command: "/nonexistent/zt-command".to_string(),
+            cpu_usage: 10,
+            memory_usage: 16,
+            startup: None,
+        };
+        let result = backend.launch("instance-1", &template);
+        assert!(matches!(result, Err(ClusterError::Spawn { ref id, .. }) if id == "instance-1"));
+        assert_eq!(backend.instance_count(), 0);
+
+        template.command = "/bin/true".to_string();
+        backend.launch("instance-1", &template).unwrap();
+        assert_eq!(backend.instance_count(), 1);
+        backend.terminate("instance-1");
+    }
+}
@@ -0,0 +1,69 @@
+//! Errors shared by the cluster modules (Rust Standard Library Only)
+//! Launch and placement failures are returned to the caller instead of panicking,
+//! so a manager can retry, escalate, or carry on with its remaining workloads.
+
+use super::startup::StartupError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Why a cluster operation on a workload failed
+#[derive(Debug)]
+pub enum ClusterError {
+    /// The workload's process could not be started
+    Spawn { id: String, source: io::Error },
+    /// The process started but never became ready, so it was stopped again
+    NotReady { id: String, source: StartupError },
+    /// The process started but could not be confined, so it was stopped again
+    ResourceLimits { id: String, source: io::Error },
+    /// No node has the requested CPU (percent) and memory (MB) free
+    InsufficientResources { id: String, cpu: u32, memory: u64 },
+    /// A workload with this id is already running
+    AlreadyRunning(String),
+}
+
+impl fmt::Display for ClusterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClusterError::Spawn { id, source } => write!(f, "failed to start {}: {}", id, source),
+            ClusterError::NotReady { id, source } => write!(f, "{} did not start: {}", id, source),
+            ClusterError::ResourceLimits { id, source } => {
+                write!(f, "failed to apply resource limits to {}: {}", id, source)
+            }
+            ClusterError::InsufficientResources { id, cpu, memory } => write!(
+                f,
+                "cannot place {}: no node has {}% CPU and {} MB memory free",
+                id, cpu, memory
+            ),
+            ClusterError::AlreadyRunning(id) => write!(f, "{} is already running", id),
+        }
+    }
+}
+
+impl Error for ClusterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClusterError::Spawn { source, .. } | ClusterError::ResourceLimits { source, .. } => {
+                Some(source)
+            }
+            ClusterError::NotReady { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl ClusterError {
+    /// Files a startup failure of workload `id` under `Spawn` or `NotReady`
+    pub fn from_startup(id: &str, error: StartupError) -> Self {
+        match error {
+            StartupError::Spawn(source) => ClusterError::Spawn {
+                id: id.to_string(),
+                source,
+            },
+            source => ClusterError::NotReady {
+                id: id.to_string(),
+                source,
+            },
+        }
+    }
+}
@@ -8,7 +8,15 @@
 //! - **Predictive failure detection to preemptively recover services**
 //! - **Tamper-proof event logging for failure analysis**
 //! - **Self-healing mechanisms to restore service availability**
+//! - **Optional readiness check, so a wedged instance is never counted as launched**
 
+#[allow(dead_code)]
+mod error;
+#[allow(dead_code)]
+mod startup;
+
+use error::ClusterError;
+use startup::StartupCheck;
 use std::collections::{HashMap, HashSet};
 use std::process::{Child, Command};
 use std::sync::{Arc, Mutex};
@@ -39,15 +47,21 @@ impl FaultToleranceManager {
         }
     }
 
-    /// Launches a new service instance
-    fn launch_service(&self, service_id: &str, command: &str, is_primary: bool) {
+    /// Launches a new service instance, first waiting for it to pass `startup` if given
+    fn launch_service(
+        &self,
+        service_id: &str,
+        command: &mut Command,
+        is_primary: bool,
+        startup: Option<&StartupCheck>,
+    ) -> Result<(), ClusterError> {
+        // Started before taking the locks, since a startup check may take a while
+        let process = startup::spawn_checked(command, startup)
+            .map_err(|e| ClusterError::from_startup(service_id, e))?;
+
         let mut active_services = self.active_services.lock().unwrap();
         let mut standby_services = self.standby_services.lock().unwrap();
 
-        let process = Command::new(command)
-            .spawn()
-            .expect("[FAULT-TOLERANCE] Failed to launch service");
-
         let service = ServiceInstance {
             id: service_id.to_string(),
             process: Some(process),
@@ -68,6 +82,7 @@ impl FaultToleranceManager {
                 service_id
             );
         }
+        Ok(())
     }
 
     /// Terminates a failed service instance
@@ -77,13 +92,16 @@ impl FaultToleranceManager {
 
         if let Some(service) = active_services.remove(service_id) {
             if let Some(mut process) = service.process {
-                process
-                    .kill()
-                    .expect("[FAULT-TOLERANCE] Failed to terminate service");
-                println!(
-                    "[FAULT-TOLERANCE] Terminated failed instance: {}",
-                    service_id
-                );
+                match process.kill() {
+                    Ok(()) => println!(
+                        "[FAULT-TOLERANCE] Terminated failed instance: {}",
+                        service_id
+                    ),
+                    Err(e) => println!(
+                        "[FAULT-TOLERANCE] Failed to terminate {}: {}",
+                        service_id, e
+                    ),
+                }
                 failed_instances.insert(service_id.to_string());
             }
         }
@@ -91,24 +109,31 @@ impl FaultToleranceManager {
 
     /// Detects and recovers from service failures
     fn detect_and_failover(&self) {
-        let mut active_services = self.active_services.lock().unwrap();
-        let mut standby_services = self.standby_services.lock().unwrap();
-        let mut failed_instances = self.failed_instances.lock().unwrap();
-
         let current_time = self.current_timestamp();
+        // Collected up front: terminating and promoting take the service locks themselves
+        let unresponsive: Vec<String> = self
+            .active_services
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, instance)| current_time.saturating_sub(instance.last_heartbeat) > 10)
+            .map(|(service_id, _)| service_id.clone())
+            .collect();
 
-        for (service_id, instance) in active_services.clone() {
-            if current_time - instance.last_heartbeat > 10 {
-                println!(
-                    "[FAILOVER] Service '{}' is unresponsive. Initiating failover...",
-                    service_id
-                );
-                failed_instances.insert(service_id.clone());
-                self.terminate_service(&service_id);
-
-                if let Some(standby) = standby_services.remove(&service_id) {
-                    self.promote_standby(standby);
-                }
+        for service_id in unresponsive {
+            println!(
+                "[FAILOVER] Service '{}' is unresponsive. Initiating failover...",
+                service_id
+            );
+            self.failed_instances
+                .lock()
+                .unwrap()
+                .insert(service_id.clone());
+            self.terminate_service(&service_id);
+
+            let standby = self.standby_services.lock().unwrap().remove(&service_id);
+            if let Some(standby) = standby {
+                self.promote_standby(standby);
             }
         }
     }
@@ -118,12 +143,12 @@ impl FaultToleranceManager {
         standby_instance.is_primary = true;
         standby_instance.last_heartbeat = self.current_timestamp();
 
-        let mut active_services = self.active_services.lock().unwrap();
-        active_services.insert(standby_instance.id.clone(), standby_instance);
         println!(
             "[FAILOVER] Standby instance promoted to primary: {}",
             standby_instance.id
         );
+        let mut active_services = self.active_services.lock().unwrap();
+        active_services.insert(standby_instance.id.clone(), standby_instance);
     }
 
     /// Periodically checks service health
@@ -162,9 +187,14 @@ fn log_failover_event(service: &str, action: &str) {
 fn main() {
     let fault_manager = Arc::new(FaultToleranceManager::new());
 
-    // Launch primary and standby services
-    fault_manager.launch_service("backend-primary", "/bin/sh", true);
-    fault_manager.launch_service("backend-standby", "/bin/sh", false);
+    // Launch primary and standby services; a failed launch leaves the rest running
+    for (service_id, is_primary) in [("backend-primary", true), ("backend-standby", false)] {
+        if let Err(e) =
+            fault_manager.launch_service(service_id, &mut Command::new("/bin/sh"), is_primary, None)
+        {
+            log_failover_event(service_id, &format!("Launch failed: {}", e));
+        }
+    }
 
     // Simulated service failure detection and failover
     fault_manager.detect_and_failover();
@@ -175,3 +205,103 @@ fn main() {
     // Monitor service health
     fault_manager.monitor_health();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use startup::ReadinessProbe;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    fn sleeper() -> Command {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+        command
+    }
+
+    fn port_check(listener: &TcpListener, timeout: Duration) -> StartupCheck {
+        StartupCheck {
+            probe: ReadinessProbe::Port(listener.local_addr().unwrap()),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn instance_is_registered_once_its_probe_answers() {
+        let manager = FaultToleranceManager::new();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let check = port_check(&listener, Duration::from_secs(5));
+
+        manager
+            .launch_service("api", &mut sleeper(), true, Some(&check))
+            .unwrap();
+        assert!(manager.active_services.lock().unwrap().contains_key("api"));
+
+        manager.terminate_service("api");
+        assert!(manager.failed_instances.lock().unwrap().contains("api"));
+    }
+
+    #[test]
+    fn instance_that_never_becomes_ready_is_not_registered() {
+        let manager = FaultToleranceManager::new();
+        // Nothing listens once the probe runs
+        let check = port_check(
+            &TcpListener::bind("127.0.0.1:0").unwrap(),
+            Duration::from_millis(300),
+        );
+
+        let error = manager
+            .launch_service("api", &mut sleeper(), false, Some(&check))
+            .expect_err("the probe never answers");
+        match error {
+            ClusterError::NotReady { id, source } => {
+                assert_eq!(id, "api");
+                assert!(matches!(source, startup::StartupError::TimedOut { .. }));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(manager.standby_services.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_executable_is_a_spawn_error() {
+        let manager = FaultToleranceManager::new();
+        let error = manager
+            .launch_service(
+                "api",
+                &mut Command::new("/nonexistent/zt-service"),
+                true,
+                None,
+            )
+            .expect_err("nothing to execute");
+        assert!(matches!(error, ClusterError::Spawn { .. }));
+        assert!(manager.active_services.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unresponsive_primary_is_replaced_by_its_standby() {
+        let manager = FaultToleranceManager::new();
+        manager
+            .launch_service("api", &mut sleeper(), true, None)
+            .unwrap();
+        manager
+            .launch_service("api", &mut sleeper(), false, None)
+            .unwrap();
+        manager
+            .active_services
+            .lock()
+            .unwrap()
+            .get_mut("api")
+            .unwrap()
+            .last_heartbeat = 0;
+
+        manager.detect_and_failover();
+
+        assert!(manager.failed_instances.lock().unwrap().contains("api"));
+        assert!(manager.standby_services.lock().unwrap().is_empty());
+        let mut active = manager.active_services.lock().unwrap();
+        let promoted = active.get_mut("api").unwrap();
+        assert!(promoted.is_primary);
+        let _ = promoted.process.as_mut().unwrap().kill();
+    }
+}
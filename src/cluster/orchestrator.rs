@@ -8,131 +8,1007 @@
 //! - **Zero Trust authentication for service communication**
 //! - **Tamper-proof audit logging for workload execution**
 //! - **Container lifecycle management with real-time analytics**
+//! - **Optional PID/mount/network namespaces and chroot per container (Linux)**
+//! - **Heartbeat liveness: silent containers are removed from load balancing**
+//! - **Resource-fit placement that refuses to oversubscribe node CPU or memory**
+//! - **Optional readiness check; a container that never comes up is killed, not counted**
 
-use std::collections::{HashMap, HashSet};
-use std::process::{Child, Command};
+#[allow(dead_code)]
+mod auto_scaling;
+#[allow(dead_code)]
+#[path = "../backend/keys.rs"]
+mod keys;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../backend/test_util.rs"]
+mod test_util;
+
+use auto_scaling::startup::{self, StartupCheck};
+use auto_scaling::{sign_attestation, AutoScaler, ClusterError, ScalingBackend, ServiceTemplate};
+use keys::KeyProvider;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Root of the cgroup v2 hierarchy used for container resource limits
+const CGROUP_ROOT: &str = "/sys/fs/cgroup/zero-trust";
+/// CPU accounting period (microseconds) used when writing `cpu.max`
+const CPU_PERIOD_US: u64 = 100_000;
+/// Containers that go this long without a heartbeat are treated as dead
+const HEARTBEAT_WINDOW: Duration = Duration::from_secs(10);
+/// Memory assumed for the local node when /proc/meminfo is unavailable (MB)
+const DEFAULT_NODE_MEMORY_MB: u64 = 4096;
+/// Where containers send `POST /heartbeat/<container id>`
+const HEARTBEAT_BIND_ADDR: &str = "127.0.0.1:9470";
+const HEARTBEAT_BIND_ENV: &str = "ORCHESTRATOR_HEARTBEAT_ADDR";
+const CGROUP_ROOT_ENV: &str = "ORCHESTRATOR_CGROUP_ROOT";
+
+/// Determines whether an exited container is relaunched by the reaper
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RestartPolicy {
+    Never,
+    OnFailure,
+    #[allow(dead_code)] // For long-running services started via `with_restart_policy`
+    Always,
+}
 
 /// Defines a lightweight container instance
 struct Container {
     id: String,
+    command: String,
     process: Option<Child>,
     cpu_limit: u8,     // CPU percentage limit
     memory_limit: u64, // Memory in MB
+    cgroup: Option<PathBuf>,
+    launched_at: Instant,
+    last_heartbeat: Instant,       // Launch counts as the first heartbeat
+    node: NodeId,                  // Node whose capacity this container holds
+    startup: Option<StartupCheck>, // Applied again when the container is restarted
+}
+
+impl Container {
+    fn resources(&self) -> ResourceRequest {
+        ResourceRequest {
+            cpu: self.cpu_limit as u32,
+            memory: self.memory_limit,
+        }
+    }
+}
+
+type NodeId = String;
+
+/// Resources a container asks for; the same units as its limits
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ResourceRequest {
+    cpu: u32,    // Percent of one core
+    memory: u64, // MB
+}
+
+/// Why a container could not be placed
+#[derive(Debug, PartialEq)]
+enum ScheduleError {
+    InsufficientResources(ResourceRequest),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::InsufficientResources(request) => write!(
+                f,
+                "no node has {}% CPU and {} MB memory free",
+                request.cpu, request.memory
+            ),
+        }
+    }
+}
+
+/// A node's total capacity and what is still unclaimed
+struct Node {
+    id: NodeId,
+    cpu_free: u32,
+    memory_free: u64,
+}
+
+impl Node {
+    fn new(id: &str, cpu: u32, memory: u64) -> Self {
+        Self {
+            id: id.to_string(),
+            cpu_free: cpu,
+            memory_free: memory,
+        }
+    }
+
+    /// The machine the runtime runs on: 100% per core and its physical memory
+    fn local() -> Self {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get() as u32);
+        let memory = fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+                let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+                Some(kb / 1024)
+            })
+            .unwrap_or(DEFAULT_NODE_MEMORY_MB);
+        Self::new("local", cores * 100, memory)
+    }
+
+    fn fits(&self, request: &ResourceRequest) -> bool {
+        self.cpu_free >= request.cpu && self.memory_free >= request.memory
+    }
+}
+
+/// How and when a container's process ended, kept for lifecycle analytics
+#[derive(Clone, Debug, PartialEq)]
+struct ContainerStats {
+    id: String,
+    exit_code: Option<i32>, // None when the process was ended by a signal
+    signal: Option<i32>,
+    runtime: Duration,
+    forced: bool, // True if the orchestrator killed it rather than it exiting
+}
+
+impl ContainerStats {
+    fn new(container: &Container, status: ExitStatus, forced: bool) -> Self {
+        Self {
+            id: container.id.clone(),
+            exit_code: status.code(),
+            signal: exit_signal(&status),
+            runtime: container.launched_at.elapsed(),
+            forced,
+        }
+    }
+
+    /// One-line summary for the audit log
+    fn describe(&self) -> String {
+        let outcome = match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("exit code {}", code),
+            (None, Some(signal)) => format!("signal {}", signal),
+            (None, None) => "unknown status".to_string(),
+        };
+        format!(
+            "{} with {} after {:.3}s",
+            if self.forced { "Terminated" } else { "Exited" },
+            outcome,
+            self.runtime.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Creates the container's cgroup v2 group with its CPU and memory limits. The
+/// process is moved in by `confined_command` before it execs, so it never runs
+/// unconstrained.
+#[cfg(target_os = "linux")]
+fn create_cgroup(
+    root: &Path,
+    container_id: &str,
+    cpu_limit: u8,
+    memory_limit: u64,
+) -> io::Result<PathBuf> {
+    if cpu_limit == 0 || cpu_limit > 100 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "CPU limit must be between 1 and 100 percent",
+        ));
+    }
+
+    // Child groups only get cpu.max and memory.max once the parent delegates them
+    fs::create_dir_all(root)?;
+    fs::write(root.join("cgroup.subtree_control"), "+cpu +memory")?;
+
+    let cgroup = root.join(container_id);
+    fs::create_dir_all(&cgroup)?;
+    let cpu_quota = CPU_PERIOD_US * cpu_limit as u64 / 100;
+    let limits = fs::write(
+        cgroup.join("cpu.max"),
+        format!("{} {}", cpu_quota, CPU_PERIOD_US),
+    )
+    .and_then(|_| {
+        fs::write(
+            cgroup.join("memory.max"),
+            (memory_limit * 1024 * 1024).to_string(),
+        )
+    });
+    if let Err(e) = limits {
+        let _ = fs::remove_dir(&cgroup);
+        return Err(e);
+    }
+
+    Ok(cgroup)
+}
+
+/// Resource limits are only enforced on Linux; refuse to run unconstrained elsewhere
+#[cfg(not(target_os = "linux"))]
+fn create_cgroup(
+    _root: &Path,
+    _container_id: &str,
+    _cpu_limit: u8,
+    _memory_limit: u64,
+) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Container resource limits require cgroups v2 (Linux)",
+    ))
+}
+
+/// A command whose process joins `cgroup` in the forked child, before exec
+#[cfg(target_os = "linux")]
+fn confined_command(command: &str, cgroup: &Path) -> io::Result<Command> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+
+    const O_WRONLY: i32 = 0o1;
+
+    let procs = CString::new(cgroup.join("cgroup.procs").as_os_str().as_bytes()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cgroup path contains a NUL byte",
+        )
+    })?;
+    let mut cmd = Command::new(command);
+    // Registered first so it runs before any chroot hides the cgroup filesystem
+    unsafe {
+        cmd.pre_exec(move || {
+            let fd = open(procs.as_ptr(), O_WRONLY);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Writing "0" to cgroup.procs moves the writing process itself
+            let joined = if write(fd, b"0".as_ptr().cast(), 1) == 1 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            };
+            close(fd);
+            joined
+        });
+    }
+    Ok(cmd)
+}
+
+/// `create_cgroup` refuses to run outside Linux, so there is nothing to join
+#[cfg(not(target_os = "linux"))]
+fn confined_command(command: &str, _cgroup: &Path) -> io::Result<Command> {
+    Ok(Command::new(command))
+}
+
+/// Kernel isolation applied to a container's process before it runs. Every option
+/// is off by default; enabled options need Linux and root (or CAP_SYS_ADMIN).
+#[derive(Clone, Debug, Default)]
+struct Isolation {
+    pid_namespace: bool,     // The container sees itself as PID 1
+    mount_namespace: bool,   // Mount changes stay inside the container
+    network_namespace: bool, // Only a loopback interface, which starts down
+    rootfs: Option<PathBuf>, // Each container is chrooted into `<rootfs>/<container id>`
+}
+
+impl Isolation {
+    fn is_enabled(&self) -> bool {
+        self.pid_namespace
+            || self.mount_namespace
+            || self.network_namespace
+            || self.rootfs.is_some()
+    }
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn unshare(flags: i32) -> i32;
+    fn chroot(path: *const std::os::raw::c_char) -> i32;
+    fn chdir(path: *const std::os::raw::c_char) -> i32;
+    fn open(path: *const std::os::raw::c_char, flags: i32, ...) -> i32;
+    fn write(fd: i32, buf: *const std::os::raw::c_void, count: usize) -> isize;
+    fn close(fd: i32) -> i32;
+    fn mount(
+        source: *const std::os::raw::c_char,
+        target: *const std::os::raw::c_char,
+        fstype: *const std::os::raw::c_char,
+        flags: std::os::raw::c_ulong,
+        data: *const std::os::raw::c_void,
+    ) -> i32;
+}
+
+#[cfg(target_os = "linux")]
+fn check(result: i32) -> io::Result<()> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Spawns `command` inside the requested namespaces and root directory
+#[cfg(target_os = "linux")]
+fn spawn_isolated(
+    command: &str,
+    container_id: &str,
+    isolation: &Isolation,
+    cgroup: &Path,
+) -> io::Result<Child> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+
+    const CLONE_NEWNS: i32 = 0x0002_0000;
+    const CLONE_NEWPID: i32 = 0x2000_0000;
+    const CLONE_NEWNET: i32 = 0x4000_0000;
+    const MS_REC: std::os::raw::c_ulong = 0x4000;
+    const MS_PRIVATE: std::os::raw::c_ulong = 0x4_0000;
+
+    let mut flags = 0;
+    if isolation.mount_namespace {
+        flags |= CLONE_NEWNS;
+    }
+    if isolation.network_namespace {
+        flags |= CLONE_NEWNET;
+    }
+    let mount_namespace = isolation.mount_namespace;
+    let root = match &isolation.rootfs {
+        Some(rootfs) => {
+            let root = rootfs.join(container_id);
+            Some(CString::new(root.as_os_str().as_bytes()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "container root contains a NUL byte",
+                )
+            })?)
+        }
+        None => None,
+    };
+
+    let mut cmd = confined_command(command, cgroup)?;
+    // Runs in the forked child before exec: only async-signal-safe calls, no allocation
+    unsafe {
+        cmd.pre_exec(move || {
+            if flags != 0 {
+                check(unshare(flags))?;
+            }
+            if mount_namespace {
+                // Keep the container's mounts from propagating back to the host
+                check(mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    MS_REC | MS_PRIVATE,
+                    std::ptr::null(),
+                ))?;
+            }
+            if let Some(root) = &root {
+                check(chroot(root.as_ptr()))?;
+                check(chdir(c"/".as_ptr()))?;
+            }
+            Ok(())
+        });
+    }
+
+    if !isolation.pid_namespace {
+        return cmd.spawn();
+    }
+    // A new PID namespace only applies to children of the unsharing thread, so unshare
+    // on a short-lived thread that does nothing but spawn the container
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                check(unsafe { unshare(CLONE_NEWPID) })?;
+                cmd.spawn()
+            })
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("spawn thread panicked")))
+    })
+}
+
+/// Namespaces and chroot are Linux features; callers fall back to a plain spawn
+#[cfg(not(target_os = "linux"))]
+fn spawn_isolated(
+    _command: &str,
+    _container_id: &str,
+    _isolation: &Isolation,
+    _cgroup: &Path,
+) -> io::Result<Child> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Container namespaces require Linux",
+    ))
+}
+
+/// Starts a container's process inside `cgroup`, isolated as configured. Where the
+/// kernel or our privileges cannot provide isolation the process runs unisolated
+/// with a warning; other failures (such as a missing root directory) abort the launch.
+fn spawn_container(
+    command: &str,
+    container_id: &str,
+    isolation: &Isolation,
+    cgroup: &Path,
+) -> io::Result<Child> {
+    if !isolation.is_enabled() {
+        return confined_command(command, cgroup)?.spawn();
+    }
+    match spawn_isolated(command, container_id, isolation, cgroup) {
+        Ok(child) => Ok(child),
+        Err(e)
+            if e.kind() == io::ErrorKind::Unsupported
+                || e.kind() == io::ErrorKind::PermissionDenied
+                || matches!(e.raw_os_error(), Some(1) | Some(22) | Some(38)) =>
+        {
+            // EPERM, EINVAL and ENOSYS: the namespaces are unavailable to us
+            println!(
+                "[ORCHESTRATOR] WARNING: Isolation unavailable for container {} ({}); running without it",
+                container_id, e
+            );
+            confined_command(command, cgroup)?.spawn()
+        }
+        Err(e) => Err(e),
+    }
 }
 
 /// Manages the runtime and lifecycle of containers
 struct ContainerRuntime {
     containers: Mutex<HashMap<String, Container>>,
+    nodes: Mutex<Vec<Node>>,
+    finished: Mutex<HashMap<String, ContainerStats>>, // Latest exit per container id
     load_balancer: Arc<LoadBalancer>,
-    auto_scaling: Arc<AutoScaler>,
+    restart_policy: RestartPolicy,
+    isolation: Isolation,
+    cgroup_root: PathBuf, // Parent group of every container's cgroup
 }
 
 impl ContainerRuntime {
     /// Creates a new container runtime manager
-    fn new(load_balancer: Arc<LoadBalancer>, auto_scaling: Arc<AutoScaler>) -> Self {
+    #[allow(dead_code)] // `main` picks a restart policy explicitly
+    fn new(load_balancer: Arc<LoadBalancer>) -> Self {
+        Self::with_restart_policy(load_balancer, RestartPolicy::Never)
+    }
+
+    /// Creates a runtime that relaunches exited containers according to `restart_policy`
+    fn with_restart_policy(
+        load_balancer: Arc<LoadBalancer>,
+        restart_policy: RestartPolicy,
+    ) -> Self {
         Self {
             containers: Mutex::new(HashMap::new()),
+            nodes: Mutex::new(vec![Node::local()]),
+            finished: Mutex::new(HashMap::new()),
             load_balancer,
-            auto_scaling,
+            restart_policy,
+            isolation: Isolation::default(),
+            cgroup_root: PathBuf::from(CGROUP_ROOT),
+        }
+    }
+
+    /// Places containers on `nodes` instead of the local machine's capacity
+    #[allow(dead_code)] // Multi-node placement is configured by embedders
+    fn with_nodes(mut self, nodes: Vec<Node>) -> Self {
+        self.nodes = Mutex::new(nodes);
+        self
+    }
+
+    /// Claims `request` on the node it fits most tightly, leaving larger gaps free
+    /// for larger containers. Capacity is returned by `release`.
+    fn schedule(&self, request: ResourceRequest) -> Result<NodeId, ScheduleError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes
+            .iter_mut()
+            .filter(|node| node.fits(&request))
+            .min_by_key(|node| {
+                (
+                    node.cpu_free - request.cpu,
+                    node.memory_free - request.memory,
+                )
+            })
+            .ok_or(ScheduleError::InsufficientResources(request))?;
+        node.cpu_free -= request.cpu;
+        node.memory_free -= request.memory;
+        Ok(node.id.clone())
+    }
+
+    /// Returns a container's claim to the node it was placed on
+    fn release(&self, node_id: &str, request: ResourceRequest) {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(node) = nodes.iter_mut().find(|node| node.id == node_id) {
+            node.cpu_free += request.cpu;
+            node.memory_free += request.memory;
         }
     }
 
-    /// Launches a new container with resource constraints
+    /// Runs every container launched from now on with the given isolation
+    fn with_isolation(mut self, isolation: Isolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Creates container cgroups under `root` instead of the default hierarchy
+    fn with_cgroup_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.cgroup_root = root.into();
+        self
+    }
+
+    /// Launches a new container with resource constraints. With a `startup` check
+    /// the container only counts as launched once it is ready; until then (at most
+    /// the check's timeout) other launches wait.
     fn launch_container(
         &self,
         container_id: &str,
         command: &str,
         cpu_limit: u8,
         memory_limit: u64,
-    ) {
+        startup: Option<&StartupCheck>,
+    ) -> Result<(), ClusterError> {
         let mut containers = self.containers.lock().unwrap();
-        let process = Command::new(command)
-            .spawn()
-            .expect("[ORCHESTRATOR] Failed to launch container process");
+        if containers.contains_key(container_id) {
+            return Err(ClusterError::AlreadyRunning(container_id.to_string()));
+        }
+        let request = ResourceRequest {
+            cpu: cpu_limit as u32,
+            memory: memory_limit,
+        };
+        let node = match self.schedule(request) {
+            Ok(node) => node,
+            Err(e) => {
+                println!(
+                    "[ORCHESTRATOR] Cannot place container {}: {}",
+                    container_id, e
+                );
+                return Err(ClusterError::InsufficientResources {
+                    id: container_id.to_string(),
+                    cpu: request.cpu,
+                    memory: request.memory,
+                });
+            }
+        };
+        // A container that cannot be constrained must not run at all
+        let cgroup = match create_cgroup(&self.cgroup_root, container_id, cpu_limit, memory_limit) {
+            Ok(cgroup) => cgroup,
+            Err(source) => {
+                self.release(&node, request);
+                println!(
+                    "[ORCHESTRATOR] Failed to apply limits to container {}: {}",
+                    container_id, source
+                );
+                return Err(ClusterError::ResourceLimits {
+                    id: container_id.to_string(),
+                    source,
+                });
+            }
+        };
+        let mut process = match spawn_container(command, container_id, &self.isolation, &cgroup) {
+            Ok(process) => process,
+            Err(source) => {
+                let _ = fs::remove_dir(&cgroup);
+                self.release(&node, request);
+                return Err(ClusterError::Spawn {
+                    id: container_id.to_string(),
+                    source,
+                });
+            }
+        };
+
+        // Confined before the wait, so a container stuck starting is still limited
+        if let Some(check) = startup {
+            if let Err(e) = startup::await_startup(&mut process, &check.probe, check.timeout) {
+                let _ = fs::remove_dir(&cgroup);
+                self.release(&node, request);
+                println!(
+                    "[ORCHESTRATOR] Container {} did not become ready: {}",
+                    container_id, e
+                );
+                return Err(ClusterError::from_startup(container_id, e));
+            }
+        }
 
         let container = Container {
             id: container_id.to_string(),
+            command: command.to_string(),
             process: Some(process),
             cpu_limit,
             memory_limit,
+            cgroup: Some(cgroup),
+            launched_at: Instant::now(),
+            last_heartbeat: Instant::now(),
+            node,
+            startup: startup.cloned(),
         };
 
+        println!(
+            "[ORCHESTRATOR] Launched container: {} on {} (cpu {}%, memory {} MB)",
+            container_id, container.node, cpu_limit, memory_limit
+        );
         containers.insert(container_id.to_string(), container);
         self.load_balancer.register_service(container_id);
-        println!("[ORCHESTRATOR] Launched container: {}", container_id);
+        Ok(())
     }
 
-    /// Terminates a running container
+    /// Terminates a running container. A process that already exited on its own
+    /// is recorded as a voluntary exit rather than a forced one.
     fn terminate_container(&self, container_id: &str) {
         let mut containers = self.containers.lock().unwrap();
-        if let Some(container) = containers.remove(container_id) {
-            if let Some(mut process) = container.process {
-                process
-                    .kill()
-                    .expect("[ORCHESTRATOR] Failed to terminate container process");
+        if let Some(mut container) = containers.remove(container_id) {
+            self.load_balancer.deregister_service(container_id);
+            if let Some(mut process) = container.process.take() {
+                let outcome = match process.try_wait() {
+                    Ok(Some(status)) => Ok((status, false)),
+                    _ => process
+                        .kill()
+                        .and_then(|_| process.wait())
+                        .map(|status| (status, true)),
+                };
+                match outcome {
+                    Ok((status, forced)) => self.record_exit(&container, status, forced),
+                    Err(e) => println!(
+                        "[ORCHESTRATOR] Failed to terminate container {}: {}",
+                        container_id, e
+                    ),
+                }
                 println!("[ORCHESTRATOR] Terminated container: {}", container_id);
             }
+            if let Some(cgroup) = container.cgroup.take() {
+                let _ = fs::remove_dir(cgroup);
+            }
+            self.release(&container.node, container.resources());
+        }
+    }
+
+    /// Stores a container's exit for `container_stats` and writes it to the audit log
+    fn record_exit(&self, container: &Container, status: ExitStatus, forced: bool) {
+        let stats = ContainerStats::new(container, status, forced);
+        log_workload_execution(&container.id, &stats.describe());
+        self.finished
+            .lock()
+            .unwrap()
+            .insert(container.id.clone(), stats);
+    }
+
+    /// Returns how the most recent run of `container_id` ended, or `None` if it
+    /// has not finished yet
+    fn container_stats(&self, container_id: &str) -> Option<ContainerStats> {
+        self.finished.lock().unwrap().get(container_id).cloned()
+    }
+
+    /// Removes containers whose process has exited and restarts them per the restart policy
+    fn reap_containers(&self) -> Vec<String> {
+        let mut exited = Vec::new();
+        {
+            let mut containers = self.containers.lock().unwrap();
+            let dead: Vec<(String, ExitStatus)> = containers
+                .iter_mut()
+                .filter_map(|(id, container)| {
+                    let process = container.process.as_mut()?;
+                    match process.try_wait() {
+                        Ok(Some(status)) => Some((id.clone(), status)),
+                        Ok(None) => None,
+                        Err(e) => {
+                            println!("[ORCHESTRATOR] Failed to poll container {}: {}", id, e);
+                            None
+                        }
+                    }
+                })
+                .collect();
+
+            for (id, status) in dead {
+                if let Some(container) = containers.remove(&id) {
+                    self.load_balancer.deregister_service(&id);
+                    if let Some(cgroup) = &container.cgroup {
+                        let _ = fs::remove_dir(cgroup);
+                    }
+                    self.release(&container.node, container.resources());
+                    println!("[ORCHESTRATOR] Reaped exited container: {}", id);
+                    self.record_exit(&container, status, false);
+                    exited.push((container, status.success()));
+                }
+            }
+        }
+
+        let mut reaped = Vec::new();
+        for (container, success) in exited {
+            let restart = match self.restart_policy {
+                RestartPolicy::Never => false,
+                RestartPolicy::OnFailure => !success,
+                RestartPolicy::Always => true,
+            };
+            if restart {
+                if let Err(e) = self.launch_container(
+                    &container.id,
+                    &container.command,
+                    container.cpu_limit,
+                    container.memory_limit,
+                    container.startup.as_ref(),
+                ) {
+                    println!("[ORCHESTRATOR] Restart of {} failed: {}", container.id, e);
+                }
+            }
+            reaped.push(container.id);
+        }
+        reaped
+    }
+
+    /// Records that a container is alive; false if no such container is running
+    fn heartbeat(&self, container_id: &str) -> bool {
+        let mut containers = self.containers.lock().unwrap();
+        match containers.get_mut(container_id) {
+            Some(container) => {
+                container.last_heartbeat = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Terminates containers whose last heartbeat is older than `window`, taking
+    /// them out of load balancing. Returns the ids that were removed.
+    fn evict_silent_containers(&self, window: Duration) -> Vec<String> {
+        let silent: Vec<String> = self
+            .containers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|c| c.last_heartbeat.elapsed() > window)
+            .map(|c| c.id.clone())
+            .collect();
+
+        for id in &silent {
+            log_workload_execution(id, "Missed heartbeat window; removing from service");
+            self.terminate_container(id);
         }
+        silent
     }
+
+    /// Checks heartbeats on a background thread, evicting containers silent for `window`
+    fn spawn_liveness_monitor(self: Arc<Self>, window: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            thread::sleep(window / 2);
+            self.evict_silent_containers(window);
+        })
+    }
+
+    /// Runs the reaper on a background thread at a fixed interval
+    fn spawn_reaper(self: Arc<Self>, interval: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            self.reap_containers();
+        })
+    }
+
+    /// Returns the number of running containers
+    fn container_count(&self) -> usize {
+        self.containers.lock().unwrap().len()
+    }
+
+    /// Finds the container with the fewest active connections, preferring the newest on ties
+    fn least_loaded_container(&self) -> Option<String> {
+        let containers = self.containers.lock().unwrap();
+        containers
+            .values()
+            .filter(|c| !self.load_balancer.is_draining(&c.id))
+            .min_by_key(|c| {
+                (
+                    self.load_balancer.active_connections(&c.id),
+                    std::cmp::Reverse(c.launched_at),
+                )
+            })
+            .map(|c| c.id.clone())
+    }
+}
+
+/// Load balancing strategy used to pick the next service instance
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    RoundRobin,
+    #[allow(dead_code)] // Chosen via `LoadBalancer::with_strategy`
+    LeastConnections,
+}
+
+/// Tracks a registered service and its in-flight connections
+struct ServiceEntry {
+    id: String,
+    active_connections: u64,
+    draining: bool,
+}
+
+/// Registered services plus the round-robin cursor
+struct BalancerState {
+    services: Vec<ServiceEntry>,
+    cursor: usize,
 }
 
 /// Implements a service load balancer for distributed workloads
 struct LoadBalancer {
-    services: Mutex<HashSet<String>>,
+    state: Mutex<BalancerState>,
+    strategy: Strategy,
 }
 
 impl LoadBalancer {
     /// Creates a new load balancer instance
     fn new() -> Self {
+        Self::with_strategy(Strategy::RoundRobin)
+    }
+
+    /// Creates a load balancer using the given selection strategy
+    fn with_strategy(strategy: Strategy) -> Self {
         Self {
-            services: Mutex::new(HashSet::new()),
+            state: Mutex::new(BalancerState {
+                services: Vec::new(),
+                cursor: 0,
+            }),
+            strategy,
         }
     }
 
     /// Registers a service for load balancing
     fn register_service(&self, service_id: &str) {
-        let mut services = self.services.lock().unwrap();
-        services.insert(service_id.to_string());
+        let mut state = self.state.lock().unwrap();
+        if state.services.iter().any(|s| s.id == service_id) {
+            return;
+        }
+        state.services.push(ServiceEntry {
+            id: service_id.to_string(),
+            active_connections: 0,
+            draining: false,
+        });
         println!("[LOAD BALANCER] Service registered: {}", service_id);
     }
 
-    /// Retrieves the next available service instance
+    /// Retrieves the next available service instance and counts it as an active connection
     fn get_next_service(&self) -> Option<String> {
-        let services = self.services.lock().unwrap();
-        services.iter().next().cloned()
+        let mut state = self.state.lock().unwrap();
+        let total = state.services.len();
+
+        let index = match self.strategy {
+            Strategy::RoundRobin => {
+                let start = state.cursor;
+                let index = (0..total)
+                    .map(|offset| (start + offset) % total)
+                    .find(|&i| !state.services[i].draining)?;
+                state.cursor = (index + 1) % total;
+                index
+            }
+            Strategy::LeastConnections => state
+                .services
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| !s.draining)
+                .min_by_key(|(_, s)| s.active_connections)
+                .map(|(i, _)| i)?,
+        };
+
+        let service = &mut state.services[index];
+        service.active_connections += 1;
+        Some(service.id.clone())
     }
-}
 
-/// Implements auto-scaling for dynamically adjusting workloads
-struct AutoScaler {
-    scaling_threshold: u64,
-    active_instances: Mutex<u8>,
-}
+    /// Removes a service from the rotation
+    fn deregister_service(&self, service_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.services.retain(|s| s.id != service_id);
+        if state.cursor >= state.services.len() {
+            state.cursor = 0;
+        }
+        println!("[LOAD BALANCER] Service deregistered: {}", service_id);
+    }
 
-impl AutoScaler {
-    /// Creates an auto-scaler instance
-    fn new(scaling_threshold: u64) -> Self {
-        Self {
-            scaling_threshold,
-            active_instances: Mutex::new(1),
+    /// Stops handing out new connections to a service that is shutting down
+    fn set_draining(&self, service_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(service) = state.services.iter_mut().find(|s| s.id == service_id) {
+            service.draining = true;
+            println!("[LOAD BALANCER] Service draining: {}", service_id);
         }
     }
 
-    /// Evaluates system load and scales services accordingly
-    fn evaluate_scaling(&self, current_load: u64) {
-        let mut instances = self.active_instances.lock().unwrap();
-        if current_load > self.scaling_threshold {
-            *instances += 1;
-            println!("[AUTO-SCALER] Increased active instances to {}", instances);
-        } else if *instances > 1 {
-            *instances -= 1;
-            println!("[AUTO-SCALER] Decreased active instances to {}", instances);
+    /// Returns true if the service is draining
+    fn is_draining(&self, service_id: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .services
+            .iter()
+            .any(|s| s.id == service_id && s.draining)
+    }
+
+    /// Returns the number of in-flight connections for a service
+    fn active_connections(&self, service_id: &str) -> u64 {
+        let state = self.state.lock().unwrap();
+        state
+            .services
+            .iter()
+            .find(|s| s.id == service_id)
+            .map_or(0, |s| s.active_connections)
+    }
+
+    /// Releases a connection previously handed out by `get_next_service`
+    fn release(&self, service_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(service) = state.services.iter_mut().find(|s| s.id == service_id) {
+            service.active_connections = service.active_connections.saturating_sub(1);
         }
     }
 }
 
+/// Lets the shared auto-scaler drive the container runtime
+impl ScalingBackend for ContainerRuntime {
+    fn launch(&self, instance_id: &str, template: &ServiceTemplate) -> Result<(), ClusterError> {
+        self.launch_container(
+            instance_id,
+            &template.command,
+            template.cpu_usage,
+            template.memory_usage,
+            template.startup.as_ref(),
+        )
+    }
+
+    fn terminate(&self, instance_id: &str) {
+        self.terminate_container(instance_id);
+    }
+
+    fn instance_count(&self) -> usize {
+        self.container_count()
+    }
+
+    fn mark_draining(&self, instance_id: &str) {
+        self.load_balancer.set_draining(instance_id);
+    }
+
+    fn is_idle(&self, instance_id: &str) -> bool {
+        self.load_balancer.active_connections(instance_id) == 0
+    }
+
+    fn idle_instance(&self) -> Option<String> {
+        self.least_loaded_container()
+    }
+}
+
+/// Answers `POST /heartbeat/<container id>` with 204, or 404 for unknown containers
+fn handle_heartbeat_request(mut stream: TcpStream, runtime: &ContainerRuntime) {
+    let mut buffer = [0u8; 1024];
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let bytes_read = match stream.read(&mut buffer) {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
+
+    let container_id = path.strip_prefix("/heartbeat/").filter(|id| !id.is_empty());
+    let status = match container_id {
+        Some(id) if method == "POST" && runtime.heartbeat(id) => "204 No Content",
+        Some(_) if method != "POST" => "405 Method Not Allowed",
+        _ => "404 Not Found",
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Serves the heartbeat endpoint on a background thread
+fn serve_heartbeats(runtime: Arc<ContainerRuntime>, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            handle_heartbeat_request(stream, &runtime);
+        }
+    });
+    Ok(())
+}
+
 /// Logs and audits workload execution
 fn log_workload_execution(container_id: &str, event: &str) {
     let timestamp = SystemTime::now()
@@ -147,20 +1023,76 @@ fn log_workload_execution(container_id: &str, event: &str) {
 
 /// Simulated real-time orchestration of containers
 fn main() {
+    let attestation_key = match keys::EnvKeyProvider.require(keys::ATTESTATION_SECRET) {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("[ORCHESTRATOR] {}", e);
+            std::process::exit(1);
+        }
+    };
     let load_balancer = Arc::new(LoadBalancer::new());
-    let auto_scaler = Arc::new(AutoScaler::new(70));
+    let runtime = Arc::new(
+        ContainerRuntime::with_restart_policy(load_balancer.clone(), RestartPolicy::OnFailure)
+            .with_isolation(Isolation {
+                pid_namespace: true,
+                mount_namespace: true,
+                network_namespace: true,
+                rootfs: None,
+            })
+            .with_cgroup_root(
+                std::env::var(CGROUP_ROOT_ENV).unwrap_or_else(|_| CGROUP_ROOT.to_string()),
+            ),
+    );
+    runtime.clone().spawn_reaper(Duration::from_secs(1));
+    runtime.clone().spawn_liveness_monitor(HEARTBEAT_WINDOW);
+    let heartbeat_addr =
+        std::env::var(HEARTBEAT_BIND_ENV).unwrap_or_else(|_| HEARTBEAT_BIND_ADDR.to_string());
+    match serve_heartbeats(runtime.clone(), &heartbeat_addr) {
+        Ok(()) => println!(
+            "[ORCHESTRATOR] Heartbeats at http://{}/heartbeat/<id>",
+            heartbeat_addr
+        ),
+        Err(e) => println!("[ORCHESTRATOR] Unable to serve heartbeats: {}", e),
+    }
 
-    let runtime = Arc::new(ContainerRuntime::new(
-        load_balancer.clone(),
-        auto_scaler.clone(),
+    let template = ServiceTemplate {
+        name_prefix: "backend-scaled".to_string(),
+        command: "/bin/sh".to_string(),
+        cpu_usage: 40,
+        memory_usage: 256,
+        startup: None,
+    };
+    let auto_scaler = Arc::new(AutoScaler::new(
+        runtime.clone(),
+        template,
+        70,
+        8,
+        &attestation_key,
     ));
 
     // Launch simulated containers
-    runtime.launch_container("backend-1", "/bin/sh", 50, 512);
-    runtime.launch_container("backend-2", "/bin/sh", 40, 256);
+    for (id, cpu, memory) in [("backend-1", 50, 512), ("backend-2", 40, 256)] {
+        if let Err(e) = runtime.launch_container(id, "/bin/sh", cpu, memory, None) {
+            println!("[ORCHESTRATOR] Could not launch {}: {}", id, e);
+        }
+    }
 
-    // Simulate service load evaluation
+    // Distribute simulated requests across registered services
+    for _ in 0..4 {
+        if let Some(service) = load_balancer.get_next_service() {
+            log_workload_execution(&service, "Routed API request");
+            load_balancer.release(&service);
+        }
+    }
+
+    // Attest the next scaled instance, then simulate service load evaluation
+    let next_instance = auto_scaler.next_instance_id();
+    auto_scaler.attest_instance(
+        &next_instance,
+        &sign_attestation(&attestation_key, &next_instance),
+    );
     auto_scaler.evaluate_scaling(85);
+    auto_scaler.evaluate_scaling(30);
 
     // Simulate workload execution logging
     log_workload_execution("backend-1", "Processing API request");
@@ -168,4 +1100,466 @@ fn main() {
 
     // Simulate service termination
     runtime.terminate_container("backend-1");
+    if let Some(stats) = runtime.container_stats("backend-1") {
+        println!("[ORCHESTRATOR] backend-1: {}", stats.describe());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Stands in for the kernel, which creates `cgroup.procs` with every new group
+    fn prepare_cgroup(root: &Path, container_id: &str) {
+        let cgroup = root.join(container_id);
+        fs::create_dir_all(&cgroup).unwrap();
+        fs::write(cgroup.join("cgroup.procs"), "").unwrap();
+    }
+
+    /// Containers run a bare command, so anything needing arguments goes in a script
+    fn script(dir: &Path, name: &str, body: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// A runtime whose cgroups live under `root` instead of /sys/fs/cgroup
+    fn runtime(root: &Path) -> (Arc<LoadBalancer>, ContainerRuntime) {
+        let load_balancer = Arc::new(LoadBalancer::new());
+        let runtime = ContainerRuntime::new(load_balancer.clone()).with_cgroup_root(root);
+        (load_balancer, runtime)
+    }
+
+    #[test]
+    fn launch_writes_limits_and_joins_the_cgroup_before_running() {
+        let root = test_util::scratch_dir("orchestrator", "limits");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        prepare_cgroup(&root, "tiny");
+        let (_, runtime) = runtime(&root);
+
+        runtime
+            .launch_container("tiny", &sleeper, 10, 1, None)
+            .unwrap();
+        let cgroup = root.join("tiny");
+        assert_eq!(
+            fs::read_to_string(cgroup.join("memory.max")).unwrap(),
+            "1048576"
+        );
+        assert_eq!(
+            fs::read_to_string(cgroup.join("cpu.max")).unwrap(),
+            "10000 100000"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("cgroup.subtree_control")).unwrap(),
+            "+cpu +memory"
+        );
+        // Written by the child itself, between fork and exec
+        assert_eq!(
+            fs::read_to_string(cgroup.join("cgroup.procs")).unwrap(),
+            "0"
+        );
+
+        runtime.terminate_container("tiny");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn launch_is_refused_when_limits_cannot_be_applied() {
+        let root = test_util::scratch_dir("orchestrator", "unconfined");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        // The root is a regular file, so no cgroup can be created beneath it
+        let (load_balancer, runtime) = runtime(&root.join("sleeper"));
+
+        let result = runtime.launch_container("unconfined", &sleeper, 10, 1, None);
+        assert!(matches!(result, Err(ClusterError::ResourceLimits { .. })));
+        assert_eq!(runtime.container_count(), 0);
+        assert_eq!(load_balancer.get_next_service(), None);
+        let invalid_cpu = create_cgroup(&root, "unconfined", 0, 1);
+        assert_eq!(invalid_cpu.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn round_robin_cycles_through_every_service_evenly() {
+        let load_balancer = LoadBalancer::with_strategy(Strategy::RoundRobin);
+        for id in ["a", "b", "c"] {
+            load_balancer.register_service(id);
+        }
+        let mut picks: HashMap<String, usize> = HashMap::new();
+        for _ in 0..9 {
+            let service = load_balancer.get_next_service().unwrap();
+            load_balancer.release(&service);
+            *picks.entry(service).or_default() += 1;
+        }
+        assert_eq!(picks.len(), 3);
+        assert!(picks.values().all(|&count| count == 3));
+    }
+
+    #[test]
+    fn least_connections_picks_the_least_loaded_service() {
+        let load_balancer = LoadBalancer::with_strategy(Strategy::LeastConnections);
+        for id in ["a", "b", "c"] {
+            load_balancer.register_service(id);
+        }
+        // Ties go to the earliest registered service: "a", then "b"
+        for _ in 0..2 {
+            load_balancer.get_next_service();
+        }
+        assert_eq!(load_balancer.active_connections("a"), 1);
+        assert_eq!(load_balancer.get_next_service().as_deref(), Some("c"));
+        assert_eq!(load_balancer.get_next_service().as_deref(), Some("a"));
+        load_balancer.get_next_service();
+        assert_eq!(load_balancer.active_connections("a"), 2);
+
+        load_balancer.release("c");
+        assert_eq!(load_balancer.get_next_service().as_deref(), Some("c"));
+    }
+
+    const TEST_ATTESTATION_KEY: &[u8] = b"test-attestation-key";
+
+    /// An auto-scaler over `backend` whose instances run `command`
+    fn auto_scaler(backend: Arc<dyn ScalingBackend>, command: &str) -> AutoScaler {
+        let template = ServiceTemplate {
+            name_prefix: "scaled".to_string(),
+            command: command.to_string(),
+            cpu_usage: 10,
+            memory_usage: 16,
+            startup: None,
+        };
+        AutoScaler::new(backend, template, 70, 4, TEST_ATTESTATION_KEY)
+    }
+
+    /// Attests the next instance the scaler will launch, and prepares its cgroup
+    fn attest_next(auto_scaler: &AutoScaler, cgroup_root: &Path) {
+        let next = auto_scaler.next_instance_id();
+        prepare_cgroup(cgroup_root, &next);
+        assert!(auto_scaler.attest_instance(&next, &sign_attestation(TEST_ATTESTATION_KEY, &next)));
+    }
+
+    #[test]
+    fn scaling_launches_and_terminates_containers() {
+        let root = test_util::scratch_dir("orchestrator", "scaling");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        let (load_balancer, runtime) = runtime(&root);
+        let runtime = Arc::new(runtime);
+        let auto_scaler = auto_scaler(runtime.clone(), &sleeper);
+
+        attest_next(&auto_scaler, &root);
+        auto_scaler.evaluate_scaling(85);
+        assert_eq!(runtime.container_count(), 1);
+        let service = load_balancer.get_next_service();
+        assert_eq!(service.as_deref(), Some("scaled-1"));
+        load_balancer.release("scaled-1");
+
+        attest_next(&auto_scaler, &root);
+        auto_scaler.evaluate_scaling(85);
+        assert_eq!(runtime.container_count(), 2);
+
+        auto_scaler.evaluate_scaling(30);
+        assert_eq!(runtime.container_count(), 1);
+        // The newest, idle container goes first and leaves the rotation with it
+        assert_eq!(
+            load_balancer.get_next_service().as_deref(),
+            Some("scaled-1")
+        );
+
+        runtime.terminate_container("scaled-1");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Reaps until `container_id` has been collected, or gives up after a few seconds
+    fn reap_until_gone(runtime: &ContainerRuntime, container_id: &str) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if runtime
+                .reap_containers()
+                .iter()
+                .any(|id| id == container_id)
+            {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn exited_container_is_reaped_and_deregistered() {
+        let root = test_util::scratch_dir("orchestrator", "reap");
+        prepare_cgroup(&root, "short-lived");
+        let (load_balancer, runtime) = runtime(&root);
+
+        runtime
+            .launch_container("short-lived", "/bin/true", 10, 16, None)
+            .unwrap();
+        assert!(reap_until_gone(&runtime, "short-lived"));
+        assert_eq!(runtime.container_count(), 0);
+        assert_eq!(load_balancer.get_next_service(), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Instance counts after each step of one scaling scenario
+    fn scaling_scenario(
+        auto_scaler: &AutoScaler,
+        backend: &dyn ScalingBackend,
+        root: &Path,
+    ) -> Vec<usize> {
+        let mut counts = Vec::new();
+        auto_scaler.evaluate_scaling(85); // Not attested yet
+        counts.push(backend.instance_count());
+        for _ in 0..2 {
+            attest_next(auto_scaler, root);
+            auto_scaler.evaluate_scaling(85);
+            counts.push(backend.instance_count());
+        }
+        auto_scaler.evaluate_scaling(30);
+        counts.push(backend.instance_count());
+        auto_scaler.evaluate_scaling(30); // Never scales below one instance
+        counts.push(backend.instance_count());
+        counts
+    }
+
+    #[test]
+    fn both_backends_behave_the_same_under_the_shared_auto_scaler() {
+        let root = test_util::scratch_dir("orchestrator", "parity");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+
+        let processes = Arc::new(auto_scaling::ProcessBackend::new());
+        let process_scaler = auto_scaler(processes.clone(), &sleeper);
+        let process_counts = scaling_scenario(&process_scaler, processes.as_ref(), &root);
+
+        let (_, runtime) = runtime(&root);
+        let runtime = Arc::new(runtime);
+        let container_scaler = auto_scaler(runtime.clone(), &sleeper);
+        let container_counts = scaling_scenario(&container_scaler, runtime.as_ref(), &root);
+
+        assert_eq!(process_counts, vec![0, 1, 2, 1, 1]);
+        assert_eq!(container_counts, process_counts);
+
+        processes.terminate("scaled-1");
+        runtime.terminate_container("scaled-1");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn draining_service_receives_no_new_connections() {
+        let load_balancer = LoadBalancer::new();
+        load_balancer.register_service("a");
+        load_balancer.register_service("b");
+        load_balancer.set_draining("a");
+        for _ in 0..4 {
+            assert_eq!(load_balancer.get_next_service().as_deref(), Some("b"));
+        }
+        assert_eq!(load_balancer.active_connections("a"), 0);
+
+        load_balancer.set_draining("b");
+        assert_eq!(load_balancer.get_next_service(), None);
+    }
+
+    #[test]
+    fn exit_status_of_each_run_is_recorded() {
+        let root = test_util::scratch_dir("orchestrator", "exit-status");
+        let failing = script(&root, "failing", "exit 3");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        prepare_cgroup(&root, "fails");
+        prepare_cgroup(&root, "killed");
+        let (_, runtime) = runtime(&root);
+
+        runtime
+            .launch_container("fails", &failing, 10, 16, None)
+            .unwrap();
+        assert_eq!(runtime.container_stats("fails"), None, "still running");
+        assert!(reap_until_gone(&runtime, "fails"));
+        let stats = runtime.container_stats("fails").unwrap();
+        assert_eq!(stats.exit_code, Some(3));
+        assert_eq!(stats.signal, None);
+        assert!(!stats.forced);
+        assert!(stats
+            .describe()
+            .starts_with("Exited with exit code 3 after "));
+
+        runtime
+            .launch_container("killed", &sleeper, 10, 16, None)
+            .unwrap();
+        runtime.terminate_container("killed");
+        let stats = runtime.container_stats("killed").unwrap();
+        assert_eq!(stats.exit_code, None);
+        assert_eq!(stats.signal, Some(9)); // SIGKILL
+        assert!(stats.forced);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// True when the tests run with root's privileges, which namespaces need
+    fn running_as_root() -> bool {
+        fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| {
+                let uid = status.lines().find(|line| line.starts_with("Uid:"))?;
+                Some(uid.split_whitespace().nth(1)? == "0")
+            })
+            .unwrap_or(false)
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn container_in_a_pid_namespace_is_pid_1() {
+        if !running_as_root() {
+            println!("skipped: PID namespaces need root");
+            return;
+        }
+        let root = test_util::scratch_dir("orchestrator", "pid-namespace");
+        let pid_file = root.join("pid");
+        let report = script(
+            &root,
+            "report",
+            &format!("echo $$ > {}", pid_file.display()),
+        );
+        prepare_cgroup(&root, "isolated");
+        let (_, runtime) = runtime(&root);
+        let runtime = runtime.with_isolation(Isolation {
+            pid_namespace: true,
+            ..Isolation::default()
+        });
+
+        runtime
+            .launch_container("isolated", &report, 10, 16, None)
+            .unwrap();
+        assert!(reap_until_gone(&runtime, "isolated"));
+        assert_eq!(
+            runtime.container_stats("isolated").unwrap().exit_code,
+            Some(0)
+        );
+        assert_eq!(fs::read_to_string(&pid_file).unwrap().trim(), "1");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Sends `POST /heartbeat/<container_id>` through the heartbeat handler; returns the status line
+    fn send_heartbeat(runtime: &ContainerRuntime, container_id: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "POST /heartbeat/{} HTTP/1.1\r\n\r\n", container_id).unwrap();
+        handle_heartbeat_request(listener.accept().unwrap().0, runtime);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response.lines().next().unwrap_or("").to_string()
+    }
+
+    #[test]
+    fn silent_containers_are_evicted_and_heartbeating_ones_stay() {
+        let root = test_util::scratch_dir("orchestrator", "heartbeat");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        prepare_cgroup(&root, "chatty");
+        prepare_cgroup(&root, "silent");
+        let (load_balancer, runtime) = runtime(&root);
+        for id in ["chatty", "silent"] {
+            runtime
+                .launch_container(id, &sleeper, 10, 16, None)
+                .unwrap();
+        }
+
+        let window = Duration::from_millis(200);
+        thread::sleep(window + Duration::from_millis(50));
+        assert_eq!(
+            send_heartbeat(&runtime, "chatty"),
+            "HTTP/1.1 204 No Content"
+        );
+        assert_eq!(
+            send_heartbeat(&runtime, "unknown"),
+            "HTTP/1.1 404 Not Found"
+        );
+
+        assert_eq!(runtime.evict_silent_containers(window), vec!["silent"]);
+        assert_eq!(runtime.container_count(), 1);
+        assert!(runtime.container_stats("silent").unwrap().forced);
+        for _ in 0..3 {
+            let service = load_balancer.get_next_service();
+            assert_eq!(service.as_deref(), Some("chatty"));
+            load_balancer.release("chatty");
+        }
+        assert_eq!(send_heartbeat(&runtime, "silent"), "HTTP/1.1 404 Not Found");
+
+        runtime.terminate_container("chatty");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn placement_stops_at_capacity_and_resumes_after_termination() {
+        let root = test_util::scratch_dir("orchestrator", "capacity");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        let (load_balancer, runtime) = runtime(&root);
+        let runtime = runtime.with_nodes(vec![
+            Node::new("big", 100, 512),
+            Node::new("small", 50, 128),
+        ]);
+
+        // Tightest fit first: the small node fills before the big one is touched
+        for id in ["w1", "w2", "w3"] {
+            prepare_cgroup(&root, id);
+            runtime
+                .launch_container(id, &sleeper, 50, 128, None)
+                .unwrap();
+        }
+        prepare_cgroup(&root, "w4");
+        let refused = runtime.launch_container("w4", &sleeper, 50, 128, None);
+        assert!(matches!(
+            refused,
+            Err(ClusterError::InsufficientResources { ref id, cpu: 50, memory: 128 }) if id == "w4"
+        ));
+        assert_eq!(runtime.container_count(), 3);
+        assert_eq!(
+            runtime.schedule(ResourceRequest { cpu: 1, memory: 1 }),
+            Err(ScheduleError::InsufficientResources(ResourceRequest {
+                cpu: 1,
+                memory: 1
+            }))
+        );
+        // Memory alone can exhaust a node: the big one still has 0% CPU but 256 MB free
+        assert!(runtime
+            .launch_container("w4", &sleeper, 0, 512, None)
+            .is_err());
+
+        runtime.terminate_container("w2");
+        runtime
+            .launch_container("w4", &sleeper, 50, 128, None)
+            .unwrap();
+        assert_eq!(runtime.container_count(), 3);
+        assert!(load_balancer.get_next_service().is_some());
+
+        for id in ["w1", "w3", "w4"] {
+            runtime.terminate_container(id);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn failed_spawn_is_an_error_and_leaves_the_runtime_usable() {
+        let root = test_util::scratch_dir("orchestrator", "missing-command");
+        let sleeper = script(&root, "sleeper", "exec sleep 5");
+        prepare_cgroup(&root, "ghost");
+        let (load_balancer, runtime) = runtime(&root);
+        let runtime = runtime.with_nodes(vec![Node::new("only", 50, 64)]);
+
+        let missing = root.join("no-such-command");
+        let result = runtime.launch_container("ghost", &missing.to_string_lossy(), 50, 64, None);
+        match result {
+            Err(ClusterError::Spawn { id, source }) => {
+                assert_eq!(id, "ghost");
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
+            }
+            other => panic!("expected a spawn error, got {:?}", other),
+        }
+        assert_eq!(runtime.container_count(), 0);
+        assert_eq!(load_balancer.get_next_service(), None);
+
+        // The failed launch gave its claim back, so the node's full capacity is usable
+        runtime
+            .launch_container("ghost", &sleeper, 50, 64, None)
+            .unwrap();
+        assert_eq!(runtime.container_count(), 1);
+
+        runtime.terminate_container("ghost");
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
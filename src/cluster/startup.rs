@@ -0,0 +1,272 @@
+//! Zero Trust Process Startup Checks (Standard Library Only)
+//! A spawned process only counts as launched once it answers a readiness probe.
+//! Features:
+//! - **Readiness by open TCP port or a 2xx answer from an HTTP health endpoint**
+//! - **Bounded wait: a process that is not ready in time is killed and reaped**
+//! - **A process that exits while starting is reported with its exit status**
+
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../backend/test_util.rs"]
+mod test_util;
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PROBE_INTERVAL: Duration = Duration::from_millis(50); // Pause between readiness probes
+const HEALTH_RESPONSE_LIMIT: u64 = 1024; // Enough of the response for its status line
+
+/// How to tell that a freshly spawned process is ready for work
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadinessProbe {
+    /// Something accepts TCP connections on this address
+    Port(SocketAddr),
+    /// `GET path` on this address answers with a 2xx status
+    Health { addr: SocketAddr, path: String },
+}
+
+impl ReadinessProbe {
+    /// One probe attempt, waiting at most `timeout` for the connection
+    fn is_ready(&self, timeout: Duration) -> bool {
+        match self {
+            ReadinessProbe::Port(addr) => TcpStream::connect_timeout(addr, timeout).is_ok(),
+            ReadinessProbe::Health { addr, path } => {
+                health_check(addr, path, timeout).unwrap_or(false)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ReadinessProbe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadinessProbe::Port(addr) => write!(f, "port {}", addr),
+            ReadinessProbe::Health { addr, path } => write!(f, "http://{}{}", addr, path),
+        }
+    }
+}
+
+/// A readiness probe together with how long startup may take
+#[derive(Clone, Debug, PartialEq)]
+pub struct StartupCheck {
+    pub probe: ReadinessProbe,
+    pub timeout: Duration,
+}
+
+/// Why a process could not be brought up
+#[derive(Debug)]
+pub enum StartupError {
+    /// The process could not be started at all
+    Spawn(io::Error),
+    /// The process exited before it became ready
+    Exited(ExitStatus),
+    /// The probe never succeeded; the process has been killed
+    TimedOut {
+        probe: ReadinessProbe,
+        timeout: Duration,
+    },
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StartupError::Spawn(e) => write!(f, "spawn failed: {}", e),
+            StartupError::Exited(status) => write!(f, "exited during startup ({})", status),
+            StartupError::TimedOut { probe, timeout } => {
+                write!(f, "not ready on {} within {:?}; killed", probe, timeout)
+            }
+        }
+    }
+}
+
+impl Error for StartupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StartupError::Spawn(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Spawns `command` and returns the child once `probe` succeeds. A child that
+/// exits first, or is still not ready after `timeout`, is killed, reaped and
+/// reported as an error, so a wedged process is never counted as launched.
+pub fn spawn_with_startup_check(
+    command: &mut Command,
+    probe: &ReadinessProbe,
+    timeout: Duration,
+) -> Result<Child, StartupError> {
+    let mut child = command.spawn().map_err(StartupError::Spawn)?;
+    await_startup(&mut child, probe, timeout)?;
+    Ok(child)
+}
+
+/// Spawns `command`, confirming readiness only when a check is configured
+pub fn spawn_checked(
+    command: &mut Command,
+    check: Option<&StartupCheck>,
+) -> Result<Child, StartupError> {
+    match check {
+        Some(check) => spawn_with_startup_check(command, &check.probe, check.timeout),
+        None => command.spawn().map_err(StartupError::Spawn),
+    }
+}
+
+/// Waits for an already spawned `child` to pass `probe`, killing and reaping it
+/// on failure. For callers that need to spawn the process themselves.
+pub fn await_startup(
+    child: &mut Child,
+    probe: &ReadinessProbe,
+    timeout: Duration,
+) -> Result<(), StartupError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(StartupError::Exited(status));
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(StartupError::TimedOut {
+                probe: probe.clone(),
+                timeout,
+            });
+        }
+        if probe.is_ready(remaining.min(PROBE_INTERVAL)) {
+            return Ok(());
+        }
+        thread::sleep(remaining.min(PROBE_INTERVAL));
+    }
+}
+
+/// True if `GET path` on `addr` answers with a 2xx status line
+fn health_check(addr: &SocketAddr, path: &str, timeout: Duration) -> io::Result<bool> {
+    let mut stream = TcpStream::connect_timeout(addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, addr
+    )?;
+    // Only the status line is read, so a server that keeps the connection open still passes
+    let mut status_line = String::new();
+    BufReader::new(stream.take(HEALTH_RESPONSE_LIMIT)).read_line(&mut status_line)?;
+    let status = status_line.split_whitespace().nth(1).unwrap_or("");
+    Ok(status.len() == 3 && status.starts_with('2'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// An address nothing is listening on
+    fn closed_port() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn a_process_that_becomes_ready_in_time_is_returned_running() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // The service reports unavailable until it has finished a short warm-up
+        let server = thread::spawn(move || {
+            let started = Instant::now();
+            let mut unavailable = 0;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                // Read the whole request so closing the socket does not reset it
+                let mut request = Vec::new();
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    request.push(line);
+                }
+                assert_eq!(request[0], "GET /healthz HTTP/1.1");
+                if started.elapsed() < Duration::from_millis(150) {
+                    stream
+                        .write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n")
+                        .unwrap();
+                    unavailable += 1;
+                } else {
+                    stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+                    return unavailable;
+                }
+            }
+            unreachable!()
+        });
+        let probe = ReadinessProbe::Health {
+            addr,
+            path: "/healthz".to_string(),
+        };
+
+        let mut child = spawn_with_startup_check(
+            Command::new("sleep").arg("5"),
+            &probe,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(
+            child.try_wait().unwrap().is_none(),
+            "child is still running"
+        );
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(server.join().unwrap() > 0, "probed before it was ready");
+    }
+
+    #[test]
+    fn a_process_that_never_becomes_ready_is_killed_and_reported() {
+        let pid_file = test_util::scratch_path("startup", &module_path!().replace("::", "-"));
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("echo $$ > {}; exec sleep 30", pid_file.display()));
+        let probe = ReadinessProbe::Port(closed_port());
+
+        let started = Instant::now();
+        let error = spawn_with_startup_check(&mut command, &probe, Duration::from_millis(300))
+            .expect_err("startup should time out");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        match error {
+            StartupError::TimedOut {
+                probe: reported,
+                timeout,
+            } => {
+                assert_eq!(reported, probe);
+                assert_eq!(timeout, Duration::from_millis(300));
+            }
+            other => panic!("expected TimedOut, got {}", other),
+        }
+
+        // Killed and reaped, so the process is gone entirely
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid.trim())).exists());
+        let _ = std::fs::remove_file(&pid_file);
+    }
+
+    #[test]
+    fn a_process_that_exits_while_starting_reports_its_status() {
+        let probe = ReadinessProbe::Port(closed_port());
+        match spawn_with_startup_check(
+            Command::new("sh").args(["-c", "exit 3"]),
+            &probe,
+            Duration::from_secs(5),
+        ) {
+            Err(StartupError::Exited(status)) => assert_eq!(status.code(), Some(3)),
+            other => panic!("expected Exited, got {:?}", other.map(|child| child.id())),
+        }
+    }
+}
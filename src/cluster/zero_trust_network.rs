@@ -8,29 +8,383 @@
 //! - **Encrypted inter-service communication to prevent MITM attacks**
 //! - **Intrusion detection based on anomalous traffic patterns**
 //! - **Tamper-proof logging of security events for auditability**
+//! - **Admin-signed (HMAC-SHA256) service registrations and revocations**
+//! - **Hash-chained audit log of every authentication, segmentation and intrusion decision**
+//! - **Expiring IDS blocks that escalate for repeat offenders, with an allow-list**
 
-use std::collections::{HashMap, HashSet};
-use std::net::UdpSocket;
+#[path = "../backend/challenge_store.rs"]
+mod challenge_store;
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../backend/hash_chain.rs"]
+mod hash_chain;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../backend/test_util.rs"]
+mod test_util;
+
+use challenge_store::ChallengeStore;
+use crypto::constant_time_eq;
+use hash_chain::{ChainVerifier, VerifyError};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{ToSocketAddrs, UdpSocket};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const AUTH_CHALLENGE_TTL: Duration = Duration::from_secs(30);
+const AUDIT_LOG: &str = "logs/network_audit.log";
+const BASE_BLOCK: Duration = Duration::from_secs(60); // First offense; doubles per repeat
+const MAX_BLOCK: Duration = Duration::from_secs(24 * 60 * 60);
+const OFFENSE_MEMORY: Duration = Duration::from_secs(24 * 60 * 60); // Clean time before offenses reset
+const SERVICE_PORT: u16 = 8080;
+const TRAFFIC_LOG: &str = "logs/network_traffic.log";
+const TRAFFIC_MEMORY_ENTRIES: usize = 1024; // Recent packets kept in memory for inspection
+const TRAFFIC_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024; // Rotate the on-disk log past this size
+const TRAFFIC_LOG_ARCHIVES: usize = 5; // Rotated files kept as `<path>.1` ..= `<path>.N`
+const TRANSMIT_RETRIES: u32 = 3; // Extra attempts after the first failed send
+const TRANSMIT_BACKOFF: Duration = Duration::from_millis(50); // Doubles after each failure
+
+/// Shared UDP socket for service-to-service traffic with bounded retries
+struct DatagramTransport {
+    socket: UdpSocket,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl DatagramTransport {
+    /// Binds the shared socket once; every transmission reuses it
+    fn bind(address: &str, retries: u32, backoff: Duration) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(address)?,
+            retries,
+            backoff,
+        })
+    }
+
+    /// Sends one datagram, retrying with exponential backoff before returning the last error
+    fn send<A: ToSocketAddrs>(&self, destination: A, data: &[u8]) -> io::Result<usize> {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.socket.send_to(data, &destination) {
+                Ok(sent) => return Ok(sent),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(e) => {
+                    attempt += 1;
+                    println!(
+                        "[NETWORK] Send failed ({}), retry {}/{} in {:?}",
+                        e, attempt, self.retries, delay
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+            }
+        }
+    }
+}
+
+/// What a security decision concluded
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Decision {
+    Allow,
+    Deny,
+    Notice, // Recorded for the audit trail but not an access decision
+}
+
+impl Decision {
+    fn as_str(self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::Deny => "deny",
+            Decision::Notice => "notice",
+        }
+    }
+}
+
+/// Escapes a string for embedding in a JSON string literal
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Open end of the audit log and the hash its next line chains from
+struct AuditWriter {
+    file: BufWriter<File>,
+    chain_head: [u8; 32],
+}
+
+/// Tamper-evident record of security decisions. Each line is
+/// `<unix seconds> | {"category":…,"actor":…,"decision":…,"reason":…}`, hash-chained
+/// to the line before it so edits and deletions are detectable.
+struct SecurityAudit {
+    path: String,
+    writer: Mutex<AuditWriter>,
+}
+
+impl SecurityAudit {
+    /// Opens the log at `path`, continuing the chain of any existing lines
+    fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_string(),
+            writer: Mutex::new(AuditWriter {
+                file: BufWriter::new(file),
+                chain_head: hash_chain::chain_head(path)?,
+            }),
+        })
+    }
+
+    /// Appends one decision and flushes it before returning
+    fn record(&self, category: &str, actor: &str, decision: Decision, reason: &str) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let entry = format!(
+            "{} | {{\"category\":\"{}\",\"actor\":\"{}\",\"decision\":\"{}\",\"reason\":\"{}\"}}",
+            timestamp,
+            json_escape(category),
+            json_escape(actor),
+            decision.as_str(),
+            json_escape(reason)
+        );
+        println!("[AUDIT] {}", entry);
+
+        let mut writer = self.writer.lock().unwrap();
+        let AuditWriter { file, chain_head } = &mut *writer;
+        if let Err(e) = hash_chain::write_line(file, chain_head, &entry).and_then(|_| file.flush())
+        {
+            eprintln!("[AUDIT] Failed to write audit entry: {}", e);
+        }
+    }
+
+    /// Reads back every entry, verifying each line's hash and chain link
+    fn entries(&self) -> io::Result<ChainVerifier<BufReader<File>>> {
+        Ok(ChainVerifier::new(BufReader::new(File::open(&self.path)?)))
+    }
+
+    /// Verifies the whole log, returning how many entries it holds
+    fn verify(&self) -> Result<usize, VerifyError> {
+        let mut count = 0;
+        for entry in self.entries().map_err(VerifyError::Io)? {
+            entry?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Bounded record of observed traffic. The newest entries stay in memory; older
+/// ones are appended to a hash-chained file on disk, which is rotated once it
+/// grows past `max_bytes`. Each file carries its own chain from the genesis hash.
+struct TrafficLog {
+    recent: VecDeque<String>,
+    capacity: usize,
+    path: String,
+    file: BufWriter<File>,
+    chain_head: [u8; 32],
+    written: u64,
+    max_bytes: u64,
+}
+
+impl TrafficLog {
+    /// Opens the on-disk log at `path`, continuing the chain of any existing lines
+    fn open(path: &str, capacity: usize, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            recent: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            path: path.to_string(),
+            written: file.metadata()?.len(),
+            file: BufWriter::new(file),
+            chain_head: hash_chain::chain_head(path)?,
+            max_bytes,
+        })
+    }
+
+    /// Keeps `entry` in memory, moving the oldest entry to disk once full
+    fn push(&mut self, entry: String) {
+        if self.recent.len() >= self.capacity {
+            if let Some(oldest) = self.recent.pop_front() {
+                if let Err(e) = self.persist(&oldest) {
+                    eprintln!("[NETWORK] Failed to persist traffic entry: {}", e);
+                }
+            }
+        }
+        self.recent.push_back(entry);
+    }
+
+    /// The last `n` entries still in memory, oldest first
+    fn recent(&self, n: usize) -> Vec<String> {
+        let skip = self.recent.len().saturating_sub(n);
+        self.recent.iter().skip(skip).cloned().collect()
+    }
+
+    fn persist(&mut self, entry: &str) -> io::Result<()> {
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+        hash_chain::write_line(&mut self.file, &mut self.chain_head, entry)?;
+        self.file.flush()?;
+        self.written = self.file.get_ref().metadata()?.len();
+        Ok(())
+    }
+
+    /// Shifts `<path>.k` to `<path>.k+1`, dropping the oldest archive, and starts
+    /// a fresh file and chain at `path`
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..TRAFFIC_LOG_ARCHIVES).rev() {
+            let from = format!("{}.{}", self.path, index);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, format!("{}.1", self.path))?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.file = BufWriter::new(file);
+        self.chain_head = hash_chain::GENESIS_HASH;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// A change to the set of trusted services; only an admin may sign one
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PolicyAction {
+    Register,
+    Revoke,
+}
+
+impl PolicyAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            PolicyAction::Register => "register",
+            PolicyAction::Revoke => "revoke",
+        }
+    }
+}
+
+/// Signs a policy change with the admin key (hex HMAC-SHA256). `version` is the
+/// manager's current `policy_version`, so each signature is good for one change.
+fn sign_policy_change(
+    admin_key: &[u8],
+    action: PolicyAction,
+    service_name: &str,
+    auth_key: &str,
+    version: u64,
+) -> String {
+    let mut message = Vec::new();
+    for field in [
+        action.as_str().as_bytes(),
+        service_name.as_bytes(),
+        auth_key.as_bytes(),
+        &version.to_be_bytes(),
+    ] {
+        message.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        message.extend_from_slice(field);
+    }
+    crypto::hmac_sha256(admin_key, &message)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
 
 /// Defines a Zero Trust network policy manager
 struct NetworkPolicyManager {
     allowed_services: Mutex<HashSet<String>>, // Services allowed to communicate
     service_keys: Mutex<HashMap<String, String>>, // Service -> Authentication Key
+    auth_challenges: ChallengeStore,          // Single-use service auth nonces
+    admin_key: Vec<u8>,                       // Verifies signed policy changes
+    policy_version: Mutex<u64>,               // Bumped by every accepted change
+    audit: Arc<SecurityAudit>,
 }
 
 impl NetworkPolicyManager {
-    /// Initializes the policy manager with predefined rules
-    fn new() -> Self {
+    /// Initializes the policy manager; changes must be signed with `admin_key`
+    fn new(admin_key: &[u8], audit: Arc<SecurityAudit>) -> Self {
         Self {
             allowed_services: Mutex::new(HashSet::new()),
             service_keys: Mutex::new(HashMap::new()),
+            auth_challenges: ChallengeStore::new(AUTH_CHALLENGE_TTL),
+            admin_key: admin_key.to_vec(),
+            policy_version: Mutex::new(0),
+            audit,
         }
     }
 
-    /// Registers a new service in the Zero Trust network
-    fn register_service(&self, service_name: &str, auth_key: &str) {
+    /// The version the next policy change must be signed against
+    fn policy_version(&self) -> u64 {
+        *self.policy_version.lock().unwrap()
+    }
+
+    /// Checks an admin signature over a change and, if valid, consumes the current
+    /// policy version so the same signature cannot be replayed
+    fn authorize_change(
+        &self,
+        action: PolicyAction,
+        service_name: &str,
+        auth_key: &str,
+        admin_signature: &str,
+    ) -> bool {
+        let mut version = self.policy_version.lock().unwrap();
+        let expected =
+            sign_policy_change(&self.admin_key, action, service_name, auth_key, *version);
+        if !constant_time_eq(expected.as_bytes(), admin_signature.as_bytes()) {
+            self.audit.record(
+                "policy",
+                service_name,
+                Decision::Deny,
+                &format!("unsigned or forged {} request", action.as_str()),
+            );
+            return false;
+        }
+        *version += 1;
+        self.audit.record(
+            "policy",
+            service_name,
+            Decision::Allow,
+            &format!(
+                "admin-signed {} at policy version {}",
+                action.as_str(),
+                *version
+            ),
+        );
+        true
+    }
+
+    /// Registers a service in the Zero Trust network if an admin signed the request
+    fn register_service_signed(
+        &self,
+        service_name: &str,
+        auth_key: &str,
+        admin_signature: &str,
+    ) -> bool {
+        if !self.authorize_change(
+            PolicyAction::Register,
+            service_name,
+            auth_key,
+            admin_signature,
+        ) {
+            return false;
+        }
         let mut services = self.allowed_services.lock().unwrap();
         let mut keys = self.service_keys.lock().unwrap();
 
@@ -41,71 +395,253 @@ impl NetworkPolicyManager {
             "[NETWORK] Service '{}' registered with authentication key.",
             service_name
         );
+        true
+    }
+
+    /// Removes a service from the network if an admin signed the request
+    fn revoke_service_signed(&self, service_name: &str, admin_signature: &str) -> bool {
+        if !self.authorize_change(PolicyAction::Revoke, service_name, "", admin_signature) {
+            return false;
+        }
+        self.allowed_services.lock().unwrap().remove(service_name);
+        self.service_keys.lock().unwrap().remove(service_name);
+        println!("[NETWORK] Service '{}' revoked.", service_name);
+        true
     }
 
     /// Validates an authentication request from a service
     fn validate_authentication(&self, service_name: &str, auth_key: &str) -> bool {
         let keys = self.service_keys.lock().unwrap();
-        match keys.get(service_name) {
-            Some(expected_key) if expected_key == auth_key => true,
-            _ => {
-                println!(
-                    "[SECURITY] Unauthorized access attempt detected from '{}'",
-                    service_name
+        let (allowed, reason) = match keys.get(service_name) {
+            Some(expected_key)
+                if constant_time_eq(expected_key.as_bytes(), auth_key.as_bytes()) =>
+            {
+                (true, "service key accepted")
+            }
+            Some(_) => (false, "wrong service key"),
+            None => (false, "unregistered service"),
+        };
+        self.audit.record(
+            "authentication",
+            service_name,
+            if allowed {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            },
+            reason,
+        );
+        allowed
+    }
+
+    /// Issues a nonce the service must answer with `challenge_response`
+    fn issue_auth_challenge(&self, service_name: &str) -> io::Result<String> {
+        self.auth_challenges.prune();
+        self.auth_challenges.issue(service_name)
+    }
+
+    /// Validates a challenge response without the key crossing the network.
+    /// Each challenge is accepted at most once.
+    fn validate_challenge_response(
+        &self,
+        service_name: &str,
+        challenge: &str,
+        response: &str,
+    ) -> bool {
+        let expected = match self.service_keys.lock().unwrap().get(service_name) {
+            Some(key) => challenge_response(key, challenge),
+            None => {
+                self.audit.record(
+                    "authentication",
+                    service_name,
+                    Decision::Deny,
+                    "challenge response from unregistered service",
                 );
-                false
+                return false;
             }
-        }
+        };
+        let accepted = self.auth_challenges.verify(challenge, service_name).is_ok()
+            && constant_time_eq(expected.as_bytes(), response.as_bytes())
+            && self
+                .auth_challenges
+                .consume(challenge, service_name)
+                .is_ok();
+        self.audit.record(
+            "authentication",
+            service_name,
+            if accepted {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            },
+            if accepted {
+                "challenge response accepted"
+            } else {
+                "invalid, expired or replayed challenge response"
+            },
+        );
+        accepted
     }
 
     /// Enforces network segmentation by blocking unauthorized services
     fn enforce_microsegmentation(&self, service_name: &str) -> bool {
-        let services = self.allowed_services.lock().unwrap();
-        if !services.contains(service_name) {
-            println!(
-                "[SECURITY] Service '{}' is isolated due to Zero Trust policies.",
-                service_name
-            );
-            return false;
-        }
-        true
+        let allowed = self.allowed_services.lock().unwrap().contains(service_name);
+        self.audit.record(
+            "microsegmentation",
+            service_name,
+            if allowed {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            },
+            if allowed {
+                "service is in an allowed segment"
+            } else {
+                "service is isolated by Zero Trust policy"
+            },
+        );
+        allowed
     }
 }
 
+/// A source's current block and how often it has offended recently
+struct BlockRecord {
+    until: Instant,
+    offenses: u32,
+}
+
 /// Monitors network traffic for anomalous activity
 struct IntrusionDetectionSystem {
-    traffic_logs: Mutex<Vec<String>>,
-    blocked_ips: Mutex<HashSet<String>>,
+    traffic_logs: Mutex<TrafficLog>,
+    blocked_ips: Mutex<HashMap<String, BlockRecord>>,
+    allowlist: Mutex<HashSet<String>>, // Known-good sources that are never blocked
+    base_block: Duration,
+    max_block: Duration,
+    audit: Arc<SecurityAudit>,
 }
 
 impl IntrusionDetectionSystem {
     /// Initializes an IDS for detecting network threats
-    fn new() -> Self {
+    fn new(audit: Arc<SecurityAudit>, traffic: TrafficLog) -> Self {
+        Self::with_block_durations(audit, traffic, BASE_BLOCK, MAX_BLOCK)
+    }
+
+    /// An IDS whose first block lasts `base_block`, doubling per repeat up to `max_block`
+    fn with_block_durations(
+        audit: Arc<SecurityAudit>,
+        traffic: TrafficLog,
+        base_block: Duration,
+        max_block: Duration,
+    ) -> Self {
         Self {
-            traffic_logs: Mutex::new(Vec::new()),
-            blocked_ips: Mutex::new(HashSet::new()),
+            traffic_logs: Mutex::new(traffic),
+            blocked_ips: Mutex::new(HashMap::new()),
+            allowlist: Mutex::new(HashSet::new()),
+            base_block,
+            max_block,
+            audit,
+        }
+    }
+
+    /// Exempts a trusted peer from blocking and lifts any block it is under
+    fn allow_source(&self, source_ip: &str) {
+        self.allowlist.lock().unwrap().insert(source_ip.to_string());
+        self.blocked_ips.lock().unwrap().remove(source_ip);
+    }
+
+    /// True while `source_ip` is serving a block
+    fn is_blocked(&self, source_ip: &str) -> bool {
+        self.blocked_ips
+            .lock()
+            .unwrap()
+            .get(source_ip)
+            .is_some_and(|record| record.until > Instant::now())
+    }
+
+    /// Manually lifts a block and forgets the source's offenses
+    fn unblock(&self, source_ip: &str) -> bool {
+        let removed = self.blocked_ips.lock().unwrap().remove(source_ip).is_some();
+        if removed {
+            self.audit.record(
+                "intrusion",
+                source_ip,
+                Decision::Allow,
+                "block lifted manually",
+            );
         }
+        removed
+    }
+
+    /// Block length for a source's `offenses`-th recent offense
+    fn block_duration(&self, offenses: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(offenses.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.base_block.saturating_mul(factor).min(self.max_block)
     }
 
     /// Logs network activity for anomaly detection
     fn log_traffic(&self, source_ip: &str, packet_data: &str) {
-        let mut logs = self.traffic_logs.lock().unwrap();
-        logs.push(format!("[TRAFFIC] {} | {}", source_ip, packet_data));
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let entry = format!(
+            "{} | {} | {}",
+            timestamp,
+            source_ip,
+            json_escape(packet_data)
+        );
+        self.traffic_logs.lock().unwrap().push(entry);
 
         println!("[NETWORK] Logged traffic from '{}'", source_ip);
     }
 
-    /// Detects and blocks malicious traffic based on patterns
-    fn detect_intrusions(&self, source_ip: &str, packet_data: &str) {
-        if packet_data.contains("unauthorized-access") {
-            let mut blocked_ips = self.blocked_ips.lock().unwrap();
-            blocked_ips.insert(source_ip.to_string());
+    /// The last `n` traffic entries held in memory, oldest first
+    fn recent_traffic(&self, n: usize) -> Vec<String> {
+        self.traffic_logs.lock().unwrap().recent(n)
+    }
 
-            println!(
-                "[SECURITY] Intrusion detected from '{}'. IP blacklisted.",
-                source_ip
+    /// Detects malicious traffic based on patterns and blocks its source for a
+    /// limited time. Allow-listed sources are recorded but never blocked.
+    fn detect_intrusions(&self, source_ip: &str, packet_data: &str) {
+        if !packet_data.contains("unauthorized-access") {
+            return;
+        }
+        if self.allowlist.lock().unwrap().contains(source_ip) {
+            self.audit.record(
+                "intrusion",
+                source_ip,
+                Decision::Notice,
+                "unauthorized-access pattern from allow-listed source; not blocked",
             );
+            return;
         }
+
+        let now = Instant::now();
+        let mut blocked_ips = self.blocked_ips.lock().unwrap();
+        // Sources that stayed clean long enough after their last block start over
+        blocked_ips.retain(|_, record| record.until + OFFENSE_MEMORY > now);
+        let record = blocked_ips
+            .entry(source_ip.to_string())
+            .or_insert(BlockRecord {
+                until: now,
+                offenses: 0,
+            });
+        record.offenses += 1;
+        let duration = self.block_duration(record.offenses);
+        record.until = now + duration;
+
+        self.audit.record(
+            "intrusion",
+            source_ip,
+            Decision::Deny,
+            &format!(
+                "unauthorized-access pattern in traffic; IP blocked for {}s (offense {})",
+                duration.as_secs(),
+                record.offenses
+            ),
+        );
     }
 }
 
@@ -116,6 +652,7 @@ fn secure_service_communication(
     data: &str,
     auth_key: &str,
     policy_manager: Arc<NetworkPolicyManager>,
+    transport: &DatagramTransport,
 ) {
     if !policy_manager.validate_authentication(source, auth_key) {
         println!("[SECURITY] Access denied for '{}'", source);
@@ -133,8 +670,22 @@ fn secure_service_communication(
         source, destination
     );
 
-    // Simulated transmission
-    transmit_data(destination, &encrypted_data);
+    if let Err(e) = transmit_data(transport, destination, &encrypted_data) {
+        policy_manager.audit.record(
+            "transport",
+            source,
+            Decision::Notice,
+            &format!("transmission to '{}' failed: {}", destination, e),
+        );
+    }
+}
+
+/// Derives a service's answer to an auth challenge from its key (hex HMAC-SHA256)
+fn challenge_response(auth_key: &str, challenge: &str) -> String {
+    crypto::hmac_sha256(auth_key.as_bytes(), challenge.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
 }
 
 /// Encrypts data (simulated AES-GCM-like encryption using XOR)
@@ -143,50 +694,408 @@ fn encrypt_data(data: &str) -> Vec<u8> {
 }
 
 /// Transmits encrypted data over a simulated UDP network
-fn transmit_data(destination: &str, encrypted_data: &[u8]) {
-    let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind socket");
-    let server_address = format!("{}:8080", destination);
-
-    socket
-        .send_to(encrypted_data, server_address)
-        .expect("Failed to send encrypted data");
+fn transmit_data(
+    transport: &DatagramTransport,
+    destination: &str,
+    encrypted_data: &[u8],
+) -> io::Result<usize> {
+    let sent = transport.send((destination, SERVICE_PORT), encrypted_data)?;
     println!("[NETWORK] Encrypted data transmitted to '{}'", destination);
+    Ok(sent)
 }
 
-/// Securely logs network events
-fn log_security_event(service: &str, message: &str) {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    println!(
-        "[LOG] {} | Service: {} | Event: {}",
-        timestamp, service, message
-    );
-}
+/// Shared secret of the simulated network administrator
+const POLICY_ADMIN_KEY: &[u8] = b"zero-trust-policy-admin-key";
 
 /// Simulated Zero Trust network enforcement and secure communication
 fn main() {
-    let policy_manager = Arc::new(NetworkPolicyManager::new());
-    let intrusion_detection = Arc::new(IntrusionDetectionSystem::new());
+    let audit = match fs::create_dir_all("logs").and_then(|_| SecurityAudit::open(AUDIT_LOG)) {
+        Ok(audit) => Arc::new(audit),
+        Err(e) => {
+            eprintln!("[SECURITY] Cannot open audit log {}: {}", AUDIT_LOG, e);
+            return;
+        }
+    };
+    let policy_manager = Arc::new(NetworkPolicyManager::new(
+        POLICY_ADMIN_KEY,
+        Arc::clone(&audit),
+    ));
+    let traffic = match TrafficLog::open(TRAFFIC_LOG, TRAFFIC_MEMORY_ENTRIES, TRAFFIC_LOG_MAX_BYTES)
+    {
+        Ok(traffic) => traffic,
+        Err(e) => {
+            eprintln!("[SECURITY] Cannot open traffic log {}: {}", TRAFFIC_LOG, e);
+            return;
+        }
+    };
+    let intrusion_detection = Arc::new(IntrusionDetectionSystem::new(Arc::clone(&audit), traffic));
 
-    // Register trusted services
-    policy_manager.register_service("backend-service", "secure-key-123");
-    policy_manager.register_service("database-service", "db-key-456");
+    // Register trusted services with admin-signed requests
+    for (service, key) in [
+        ("backend-service", "secure-key-123"),
+        ("database-service", "db-key-456"),
+    ] {
+        let signature = sign_policy_change(
+            POLICY_ADMIN_KEY,
+            PolicyAction::Register,
+            service,
+            key,
+            policy_manager.policy_version(),
+        );
+        policy_manager.register_service_signed(service, key, &signature);
+    }
 
-    // Simulated secure communication
-    secure_service_communication(
-        "backend-service",
-        "database-service",
-        "Fetch user data",
-        "secure-key-123",
-        Arc::clone(&policy_manager),
-    );
+    // A registration without the admin's signature is refused
+    policy_manager.register_service_signed("rogue-service", "rogue-key", "");
+
+    // Simulated secure communication; an unresolvable peer is logged, not fatal
+    match DatagramTransport::bind("0.0.0.0:0", TRANSMIT_RETRIES, TRANSMIT_BACKOFF) {
+        Ok(transport) => secure_service_communication(
+            "backend-service",
+            "database-service",
+            "Fetch user data",
+            "secure-key-123",
+            Arc::clone(&policy_manager),
+            &transport,
+        ),
+        Err(e) => audit.record(
+            "transport",
+            "backend-service",
+            Decision::Notice,
+            &format!("no UDP socket: {}", e),
+        ),
+    }
+
+    // Challenge-response authentication; a replayed response is refused
+    match policy_manager.issue_auth_challenge("backend-service") {
+        Ok(challenge) => {
+            let response = challenge_response("secure-key-123", &challenge);
+            for attempt in 1..=2 {
+                println!(
+                    "[NETWORK] Challenge response attempt {}: {}",
+                    attempt,
+                    policy_manager.validate_challenge_response(
+                        "backend-service",
+                        &challenge,
+                        &response
+                    )
+                );
+            }
+        }
+        Err(e) => println!("[SECURITY] Unable to issue auth challenge: {}", e),
+    }
 
     // Simulated intrusion attempt
     intrusion_detection.log_traffic("192.168.1.100", "unauthorized-access attempt");
     intrusion_detection.detect_intrusions("192.168.1.100", "unauthorized-access attempt");
+    for entry in intrusion_detection.recent_traffic(5) {
+        println!("[NETWORK] Recent traffic: {}", entry);
+    }
+    println!(
+        "[NETWORK] 192.168.1.100 blocked: {}",
+        intrusion_detection.is_blocked("192.168.1.100")
+    );
+
+    // A trusted peer's burst trips the same pattern but is not cut off
+    intrusion_detection.allow_source("10.0.0.2");
+    intrusion_detection.detect_intrusions("10.0.0.2", "unauthorized-access burst");
+    intrusion_detection.unblock("192.168.1.100");
 
     // Secure log example
-    log_security_event("backend-service", "API request validated and executed");
+    audit.record(
+        "request",
+        "backend-service",
+        Decision::Allow,
+        "API request validated and executed",
+    );
+
+    // Decommission a service with an admin-signed revocation
+    let signature = sign_policy_change(
+        POLICY_ADMIN_KEY,
+        PolicyAction::Revoke,
+        "database-service",
+        "",
+        policy_manager.policy_version(),
+    );
+    policy_manager.revoke_service_signed("database-service", &signature);
+
+    match audit.verify() {
+        Ok(count) => println!("[SECURITY] Audit log intact: {} verified entries", count),
+        Err(e) => eprintln!("[SECURITY] Audit log verification failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fast-failing transport: two retries a millisecond apart
+    fn transport() -> DatagramTransport {
+        DatagramTransport::bind("127.0.0.1:0", 2, Duration::from_millis(1)).unwrap()
+    }
+
+    #[test]
+    fn datagram_reaches_a_loopback_receiver() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let transport = transport();
+
+        for payload in [&b"first"[..], b"second"] {
+            let sent = transport
+                .send(receiver.local_addr().unwrap(), payload)
+                .unwrap();
+            assert_eq!(sent, payload.len());
+            let mut buffer = [0u8; 64];
+            let (received, from) = receiver.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..received], payload);
+            // Every send goes out through the one shared socket
+            assert_eq!(from, transport.socket.local_addr().unwrap());
+        }
+    }
+
+    #[test]
+    fn unroutable_destination_returns_an_error() {
+        // Broadcast is refused on a socket without SO_BROADCAST
+        let result = transport().send("255.255.255.255:9", b"data");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn failed_transmission_is_audited_instead_of_panicking() {
+        let audit_path = test_util::scratch_path("network", "transmit-audit")
+            .to_string_lossy()
+            .into_owned();
+        let audit = Arc::new(SecurityAudit::open(&audit_path).unwrap());
+        let policy_manager = Arc::new(NetworkPolicyManager::new(
+            POLICY_ADMIN_KEY,
+            Arc::clone(&audit),
+        ));
+        let signature =
+            sign_policy_change(POLICY_ADMIN_KEY, PolicyAction::Register, "svc", "key", 0);
+        assert!(policy_manager.register_service_signed("svc", "key", &signature));
+
+        secure_service_communication(
+            "svc",
+            "255.255.255.255",
+            "payload",
+            "key",
+            Arc::clone(&policy_manager),
+            &transport(),
+        );
+        let log = fs::read_to_string(&audit_path).unwrap();
+        assert!(log.contains("transmission to '255.255.255.255' failed"));
+        assert!(audit.verify().is_ok());
+        fs::remove_file(audit_path).unwrap();
+    }
+
+    /// A policy manager auditing to a fresh log; returns the log path for cleanup
+    fn policy_manager(name: &str) -> (NetworkPolicyManager, String) {
+        let audit_path = test_util::scratch_path("network", name)
+            .to_string_lossy()
+            .into_owned();
+        let audit = Arc::new(SecurityAudit::open(&audit_path).unwrap());
+        (
+            NetworkPolicyManager::new(POLICY_ADMIN_KEY, audit),
+            audit_path,
+        )
+    }
+
+    #[test]
+    fn only_admin_signed_registrations_are_accepted() {
+        let (manager, audit_path) = policy_manager("registration");
+        let sign = |key: &[u8], version| {
+            sign_policy_change(key, PolicyAction::Register, "api", "api-key", version)
+        };
+
+        assert!(!manager.register_service_signed("api", "api-key", ""));
+        assert!(!manager.register_service_signed("api", "api-key", &sign(b"not-the-admin", 0)));
+        // Signed for a different key than the one being registered
+        let other_key =
+            sign_policy_change(POLICY_ADMIN_KEY, PolicyAction::Register, "api", "other", 0);
+        assert!(!manager.register_service_signed("api", "api-key", &other_key));
+        assert_eq!(manager.policy_version(), 0);
+        assert!(!manager.validate_authentication("api", "api-key"));
+
+        let signature = sign(POLICY_ADMIN_KEY, 0);
+        assert!(manager.register_service_signed("api", "api-key", &signature));
+        assert_eq!(manager.policy_version(), 1);
+        assert!(manager.validate_authentication("api", "api-key"));
+        assert!(manager.enforce_microsegmentation("api"));
+        // Each signature authorizes one change only
+        assert!(!manager.register_service_signed("api", "api-key", &signature));
+
+        let revoke = sign_policy_change(POLICY_ADMIN_KEY, PolicyAction::Revoke, "api", "", 1);
+        assert!(manager.revoke_service_signed("api", &revoke));
+        assert!(!manager.enforce_microsegmentation("api"));
+        fs::remove_file(audit_path).unwrap();
+    }
+
+    /// An IDS auditing to `audit`, with its traffic log at a fresh path
+    fn ids(name: &str, audit: Arc<SecurityAudit>) -> (IntrusionDetectionSystem, String) {
+        let traffic_path = test_util::scratch_path("network", name)
+            .to_string_lossy()
+            .into_owned();
+        let traffic = TrafficLog::open(&traffic_path, 16, TRAFFIC_LOG_MAX_BYTES).unwrap();
+        (IntrusionDetectionSystem::new(audit, traffic), traffic_path)
+    }
+
+    #[test]
+    fn security_decisions_are_chained_audit_entries() {
+        let audit_path = test_util::scratch_path("network", "decisions")
+            .to_string_lossy()
+            .into_owned();
+        let audit = Arc::new(SecurityAudit::open(&audit_path).unwrap());
+        let manager = NetworkPolicyManager::new(POLICY_ADMIN_KEY, Arc::clone(&audit));
+        let (ids, traffic_path) = ids("decisions-traffic", Arc::clone(&audit));
+
+        assert!(!manager.validate_authentication("intruder", "guess"));
+        ids.detect_intrusions("10.0.0.9", "GET /admin unauthorized-access");
+        assert!(ids.is_blocked("10.0.0.9"));
+
+        let entries: Vec<String> = audit.entries().unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 2);
+        let (_, denied) = entries[0].split_once(" | ").unwrap();
+        assert_eq!(
+            denied,
+            "{\"category\":\"authentication\",\"actor\":\"intruder\",\"decision\":\"deny\",\"reason\":\"unregistered service\"}"
+        );
+        let (_, blocked) = entries[1].split_once(" | ").unwrap();
+        assert!(blocked.starts_with(
+            "{\"category\":\"intrusion\",\"actor\":\"10.0.0.9\",\"decision\":\"deny\","
+        ));
+        assert!(blocked.contains("IP blocked for 60s (offense 1)"));
+        assert_eq!(audit.verify().unwrap(), 2);
+
+        // Rewriting the first decision breaks verification
+        let log = fs::read_to_string(&audit_path).unwrap();
+        fs::write(&audit_path, log.replacen("\"deny\"", "\"allow\"", 1)).unwrap();
+        assert!(matches!(
+            audit.verify(),
+            Err(VerifyError::HashMismatch { line: 1 })
+        ));
+
+        fs::remove_file(audit_path).unwrap();
+        fs::remove_file(traffic_path).unwrap();
+    }
+
+    #[test]
+    fn blocks_expire_and_lengthen_for_repeat_offenders() {
+        let audit_path = test_util::scratch_path("network", "blocks")
+            .to_string_lossy()
+            .into_owned();
+        let traffic_path = test_util::scratch_path("network", "blocks-traffic")
+            .to_string_lossy()
+            .into_owned();
+        let audit = Arc::new(SecurityAudit::open(&audit_path).unwrap());
+        let traffic = TrafficLog::open(&traffic_path, 16, TRAFFIC_LOG_MAX_BYTES).unwrap();
+        let base = Duration::from_millis(100);
+        let ids = IntrusionDetectionSystem::with_block_durations(
+            audit,
+            traffic,
+            base,
+            Duration::from_millis(300),
+        );
+        assert_eq!(ids.block_duration(1), base);
+        assert_eq!(ids.block_duration(2), base * 2);
+        assert_eq!(ids.block_duration(3), Duration::from_millis(300), "capped");
+        assert_eq!(ids.block_duration(64), Duration::from_millis(300));
+
+        ids.detect_intrusions("10.0.0.9", "unauthorized-access");
+        assert!(ids.is_blocked("10.0.0.9"));
+        assert!(!ids.is_blocked("10.0.0.10"));
+        thread::sleep(Duration::from_millis(150));
+        assert!(!ids.is_blocked("10.0.0.9"), "first block has expired");
+
+        // The second offense is remembered and blocked for twice as long
+        ids.detect_intrusions("10.0.0.9", "unauthorized-access");
+        thread::sleep(Duration::from_millis(150));
+        assert!(ids.is_blocked("10.0.0.9"));
+        thread::sleep(Duration::from_millis(100));
+        assert!(!ids.is_blocked("10.0.0.9"));
+
+        ids.allow_source("10.0.0.1");
+        for _ in 0..3 {
+            ids.detect_intrusions("10.0.0.1", "unauthorized-access");
+        }
+        assert!(!ids.is_blocked("10.0.0.1"));
+        // Allow-listing also lifts a block already in place
+        ids.detect_intrusions("10.0.0.2", "unauthorized-access");
+        ids.allow_source("10.0.0.2");
+        assert!(!ids.is_blocked("10.0.0.2"));
+
+        let log = fs::read_to_string(&audit_path).unwrap();
+        assert!(log.contains("(offense 2)"));
+        assert_eq!(log.matches("allow-listed source; not blocked").count(), 3);
+        fs::remove_file(audit_path).unwrap();
+        fs::remove_file(traffic_path).unwrap();
+    }
+
+    /// Verified entries of one traffic log file
+    fn persisted(path: &str) -> Vec<String> {
+        ChainVerifier::new(BufReader::new(File::open(path).unwrap()))
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn traffic_memory_stays_bounded_and_older_entries_go_to_disk() {
+        let path = test_util::scratch_path("network", "traffic")
+            .to_string_lossy()
+            .into_owned();
+        let mut traffic = TrafficLog::open(&path, 8, TRAFFIC_LOG_MAX_BYTES).unwrap();
+        for i in 0..1000 {
+            traffic.push(format!("packet {}", i));
+        }
+        assert_eq!(traffic.recent.len(), 8);
+        assert_eq!(
+            traffic.recent(3),
+            vec!["packet 997", "packet 998", "packet 999"]
+        );
+        assert_eq!(traffic.recent(100).len(), 8);
+
+        let on_disk = persisted(&path);
+        assert_eq!(on_disk.len(), 992);
+        assert_eq!(on_disk.first().map(String::as_str), Some("packet 0"));
+        assert_eq!(on_disk.last().map(String::as_str), Some("packet 991"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn traffic_log_rotates_into_independently_chained_archives() {
+        let path = test_util::scratch_path("network", "traffic-rotation")
+            .to_string_lossy()
+            .into_owned();
+        let archives: Vec<String> = (1..=TRAFFIC_LOG_ARCHIVES + 1)
+            .map(|index| format!("{}.{}", path, index))
+            .collect();
+        for archive in &archives {
+            let _ = fs::remove_file(archive);
+        }
+        let mut traffic = TrafficLog::open(&path, 1, 1024).unwrap();
+        for i in 0..500 {
+            traffic.push(format!("packet {}", i));
+        }
+
+        for archive in &archives[..TRAFFIC_LOG_ARCHIVES] {
+            assert!(
+                !persisted(archive).is_empty(),
+                "{} is a valid chain",
+                archive
+            );
+        }
+        assert!(
+            fs::metadata(&archives[TRAFFIC_LOG_ARCHIVES]).is_err(),
+            "oldest dropped"
+        );
+        let current = persisted(&path);
+        assert_eq!(current.last().map(String::as_str), Some("packet 498"));
+        assert!(fs::metadata(&path).unwrap().len() < 2 * 1024);
+
+        fs::remove_file(path).unwrap();
+        for archive in &archives[..TRAFFIC_LOG_ARCHIVES] {
+            fs::remove_file(archive).unwrap();
+        }
+    }
 }
@@ -9,15 +9,20 @@
 //! - **Browser fingerprinting mitigation for privacy enforcement**
 //! - **Dynamic security policy enforcement based on user risk level**
 
+#[allow(dead_code)]
+#[path = "../backend/router.rs"]
+mod router;
+
+use router::{Cookie, CsrfMiddleware, FingerprintMitigation};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io;
+use std::sync::Arc;
 
 /// Defines security policies enforced at the browser level
 struct BrowserSecurityPolicies {
     csp: String,
     x_frame_options: String,
-    csrf_tokens: Mutex<HashMap<String, String>>, // Stores CSRF tokens per session
+    csrf: CsrfMiddleware, // Single-use CSRF tokens bound to a browser, shared with the router
 }
 
 impl BrowserSecurityPolicies {
@@ -27,29 +32,23 @@ impl BrowserSecurityPolicies {
             csp: "default-src 'none'; script-src 'self'; style-src 'self'; frame-ancestors 'none';"
                 .to_string(),
             x_frame_options: "DENY".to_string(),
-            csrf_tokens: Mutex::new(HashMap::new()),
+            csrf: CsrfMiddleware::new(),
         }
     }
 
-    /// Generates a secure CSRF token for a session
-    fn generate_csrf_token(&self, session_id: &str) -> String {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let token = format!("csrf-{}-{}", session_id, timestamp);
-        let mut csrf_tokens = self.csrf_tokens.lock().unwrap();
-        csrf_tokens.insert(session_id.to_string(), token.clone());
-        token
+    /// Generates an unpredictable CSRF token for a session
+    fn generate_csrf_token(&self, session_id: &str) -> io::Result<String> {
+        self.csrf.issue_token(session_id)
     }
 
-    /// Validates a received CSRF token
+    /// Validates a received CSRF token; each token is accepted only once
     fn validate_csrf_token(&self, session_id: &str, token: &str) -> bool {
-        let csrf_tokens = self.csrf_tokens.lock().unwrap();
-        match csrf_tokens.get(session_id) {
-            Some(stored_token) if stored_token == token => true,
-            _ => false,
-        }
+        self.csrf.verify_token(session_id, token).is_ok()
+    }
+
+    /// Adds a hidden CSRF token field to each state-changing form in `html`
+    fn protect_forms(&self, session_id: &str, html: &str) -> io::Result<String> {
+        self.csrf.protect_forms(session_id, html)
     }
 
     /// Retrieves security headers to be applied to HTTP responses
@@ -69,7 +68,7 @@ impl BrowserSecurityPolicies {
         );
         headers.insert(
             "Set-Cookie".to_string(),
-            "session_id=secure; HttpOnly; Secure; SameSite=Strict".to_string(),
+            Cookie::session("session_id", "secure").to_header(),
         );
         headers
     }
@@ -83,13 +82,12 @@ impl BrowserSecurityPolicies {
     }
 }
 
-/// Protects against browser fingerprinting by randomizing request headers
-fn mitigate_fingerprinting(headers: &mut HashMap<String, String>) {
-    headers.insert(
-        "User-Agent".to_string(),
-        "Mozilla/5.0 (privacy-enforced)".to_string(),
-    );
-    headers.insert("Accept-Language".to_string(), "en-US".to_string());
+/// Protects against browser fingerprinting by normalizing the User-Agent; Accept-Language
+/// is left alone so content negotiation keeps working
+fn fingerprint_mitigation() -> FingerprintMitigation {
+    FingerprintMitigation::new()
+        .normalize("User-Agent", "Mozilla/5.0 (privacy-enforced)")
+        .skip_route("/api/client-diagnostics")
 }
 
 /// Simulated request and response cycle with security enforcement
@@ -97,22 +95,38 @@ fn main() {
     let security_policies = Arc::new(BrowserSecurityPolicies::new());
 
     let session_id = "session_abc123";
-    let csrf_token = security_policies.generate_csrf_token(session_id);
+    let csrf_token = security_policies
+        .generate_csrf_token(session_id)
+        .expect("OS random source unavailable");
 
     println!("[SECURITY] Generated CSRF Token: {}", csrf_token);
 
     // Simulated request headers
     let mut request_headers = HashMap::new();
-    request_headers.insert("User-Agent".to_string(), "Mozilla/5.0");
-    request_headers.insert("Accept-Language".to_string(), "en-US,en;q=0.5");
+    request_headers.insert("User-Agent".to_string(), "Mozilla/5.0".to_string());
+    request_headers.insert("Accept-Language".to_string(), "en-US,en;q=0.5".to_string());
 
     // Apply fingerprinting mitigation
-    mitigate_fingerprinting(&mut request_headers);
+    let mitigation = fingerprint_mitigation();
+    let mut diagnostics_headers = request_headers.clone();
+    mitigation.apply_headers("/", &mut request_headers);
     println!(
         "[SECURITY] Browser Fingerprinting Mitigated: {:?}",
         request_headers
     );
 
+    // Routes that need the real values are skipped, and a disabled middleware is a no-op
+    mitigation.apply_headers("/api/client-diagnostics", &mut diagnostics_headers);
+    println!(
+        "[SECURITY] Diagnostics Route Headers Preserved: {:?}",
+        diagnostics_headers
+    );
+    let mut unmodified = diagnostics_headers.clone();
+    mitigation
+        .enabled(false)
+        .apply_headers("/", &mut unmodified);
+    assert_eq!(unmodified, diagnostics_headers);
+
     // Simulated response headers
     let mut response_headers = HashMap::new();
     security_policies.apply_security_headers(&mut response_headers);
@@ -127,4 +141,18 @@ fn main() {
     } else {
         println!("[SECURITY] CSRF Token Validation Failed.");
     }
+
+    // Forms rendered for the session carry their own token
+    let form = security_policies
+        .protect_forms(
+            session_id,
+            "<form method=\"post\" action=\"/transfer\"><button>Send</button></form>",
+        )
+        .expect("OS random source unavailable");
+    println!("[SECURITY] Protected Form: {}", form);
+
+    // A replayed token is rejected
+    if !security_policies.validate_csrf_token(session_id, &csrf_token) {
+        println!("[SECURITY] Replayed CSRF Token Rejected.");
+    }
 }
@@ -0,0 +1,395 @@
+//! DOM Patch Wire Format (Standard Library Only)
+//! The renderer diffs two virtual DOM trees into `DomPatch`es; clients apply them
+//! to the live page. Every patch produced for one update travels together as a
+//! `PatchBatch`, encoded into a single binary WebSocket message so a client never
+//! renders a half-applied update.
+//!
+//! Encoding (all integers big-endian):
+//! - batch: `[u64 version][u32 count][patch]*count`
+//! - patch: `[u8 op][u16 depth][u32 child index]*depth` followed by the op's fields
+//! - string field: `[u32 length][UTF-8 bytes]`; index field: `[u32]`
+//!
+//! A path lists child indices from the document root, so `[]` is the root itself
+//! and `[0, 2]` is the third child of the root's first child.
+
+use std::fmt;
+
+const OP_REPLACE_NODE: u8 = 0x01;
+const OP_INSERT_CHILD: u8 = 0x02;
+const OP_REMOVE_NODE: u8 = 0x03;
+const OP_SET_TEXT: u8 = 0x04;
+const OP_SET_ATTRIBUTE: u8 = 0x05;
+const OP_REMOVE_ATTRIBUTE: u8 = 0x06;
+const OP_MOVE_CHILD: u8 = 0x07;
+
+const MAX_PATH_DEPTH: usize = u16::MAX as usize;
+
+/// Child indices from the document root to the patched node
+pub type NodePath = Vec<u32>;
+
+/// One change to the rendered document
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomPatch {
+    /// Swaps the node at `path` for freshly rendered markup
+    ReplaceNode {
+        path: NodePath,
+        html: String,
+    },
+    /// Inserts markup as child number `index` of the node at `path`
+    InsertChild {
+        path: NodePath,
+        index: u32,
+        html: String,
+    },
+    RemoveNode {
+        path: NodePath,
+    },
+    /// Replaces the text content of the node at `path`
+    SetText {
+        path: NodePath,
+        text: String,
+    },
+    SetAttribute {
+        path: NodePath,
+        name: String,
+        value: String,
+    },
+    RemoveAttribute {
+        path: NodePath,
+        name: String,
+    },
+    /// Takes child number `from` of the node at `path` out, then reinserts it so it
+    /// becomes child number `to`; the node keeps its subtree and any client state
+    MoveChild {
+        path: NodePath,
+        from: u32,
+        to: u32,
+    },
+}
+
+/// Every patch for one update, applied by the client in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchBatch {
+    /// Increases with every update so clients can drop stale or repeated batches
+    pub version: u64,
+    pub patches: Vec<DomPatch>,
+}
+
+/// Why bytes could not be decoded into patches
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchError {
+    /// The input ended inside a field
+    Truncated,
+    UnknownOp(u8),
+    InvalidUtf8,
+    /// Bytes remained after the last patch of the batch
+    TrailingBytes,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Truncated => f.write_str("patch data is truncated"),
+            PatchError::UnknownOp(op) => write!(f, "unknown patch op 0x{:02x}", op),
+            PatchError::InvalidUtf8 => f.write_str("patch string is not valid UTF-8"),
+            PatchError::TrailingBytes => f.write_str("trailing bytes after patch batch"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl DomPatch {
+    pub fn path(&self) -> &[u32] {
+        match self {
+            DomPatch::ReplaceNode { path, .. }
+            | DomPatch::InsertChild { path, .. }
+            | DomPatch::RemoveNode { path }
+            | DomPatch::SetText { path, .. }
+            | DomPatch::SetAttribute { path, .. }
+            | DomPatch::RemoveAttribute { path, .. }
+            | DomPatch::MoveChild { path, .. } => path,
+        }
+    }
+
+    fn op(&self) -> u8 {
+        match self {
+            DomPatch::ReplaceNode { .. } => OP_REPLACE_NODE,
+            DomPatch::InsertChild { .. } => OP_INSERT_CHILD,
+            DomPatch::RemoveNode { .. } => OP_REMOVE_NODE,
+            DomPatch::SetText { .. } => OP_SET_TEXT,
+            DomPatch::SetAttribute { .. } => OP_SET_ATTRIBUTE,
+            DomPatch::RemoveAttribute { .. } => OP_REMOVE_ATTRIBUTE,
+            DomPatch::MoveChild { .. } => OP_MOVE_CHILD,
+        }
+    }
+
+    /// Appends this patch's encoding to `out`.
+    ///
+    /// # Panics
+    /// If the path is deeper than `u16::MAX`, which no real document reaches.
+    pub fn encode_into(&self, out: &mut Vec<u8>) {
+        let path = self.path();
+        assert!(path.len() <= MAX_PATH_DEPTH, "patch path too deep");
+        out.push(self.op());
+        out.extend_from_slice(&(path.len() as u16).to_be_bytes());
+        for index in path {
+            out.extend_from_slice(&index.to_be_bytes());
+        }
+        match self {
+            DomPatch::ReplaceNode { html, .. } => put_str(out, html),
+            DomPatch::InsertChild { index, html, .. } => {
+                out.extend_from_slice(&index.to_be_bytes());
+                put_str(out, html);
+            }
+            DomPatch::RemoveNode { .. } => {}
+            DomPatch::SetText { text, .. } => put_str(out, text),
+            DomPatch::SetAttribute { name, value, .. } => {
+                put_str(out, name);
+                put_str(out, value);
+            }
+            DomPatch::RemoveAttribute { name, .. } => put_str(out, name),
+            DomPatch::MoveChild { from, to, .. } => {
+                out.extend_from_slice(&from.to_be_bytes());
+                out.extend_from_slice(&to.to_be_bytes());
+            }
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    /// Decodes exactly one patch; anything after it is an error
+    pub fn decode(bytes: &[u8]) -> Result<Self, PatchError> {
+        let mut reader = Reader { rest: bytes };
+        let patch = reader.patch()?;
+        reader.finish()?;
+        Ok(patch)
+    }
+}
+
+impl PatchBatch {
+    pub fn new(version: u64) -> Self {
+        Self {
+            version,
+            patches: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, patch: DomPatch) {
+        self.patches.push(patch);
+    }
+
+    /// The payload of the binary WebSocket message carrying this batch
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12);
+        out.extend_from_slice(&self.version.to_be_bytes());
+        out.extend_from_slice(&(self.patches.len() as u32).to_be_bytes());
+        for patch in &self.patches {
+            patch.encode_into(&mut out);
+        }
+        out
+    }
+
+    /// Client-side inverse of `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, PatchError> {
+        let mut reader = Reader { rest: bytes };
+        let version = u64::from_be_bytes(reader.array()?);
+        let count = reader.u32()?;
+        // Every patch takes at least 3 bytes, which bounds the allocation
+        let mut patches = Vec::with_capacity((count as usize).min(reader.rest.len() / 3));
+        for _ in 0..count {
+            patches.push(reader.patch()?);
+        }
+        reader.finish()?;
+        Ok(Self { version, patches })
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Consumes fields from the front of an encoded batch
+struct Reader<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        if self.rest.len() < len {
+            return Err(PatchError::Truncated);
+        }
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], PatchError> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, PatchError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, PatchError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| PatchError::InvalidUtf8)
+    }
+
+    fn patch(&mut self) -> Result<DomPatch, PatchError> {
+        let [op] = self.array()?;
+        let depth = u16::from_be_bytes(self.array()?) as usize;
+        if self.rest.len() < depth * 4 {
+            return Err(PatchError::Truncated);
+        }
+        let path = (0..depth).map(|_| self.u32()).collect::<Result<_, _>>()?;
+        Ok(match op {
+            OP_REPLACE_NODE => DomPatch::ReplaceNode {
+                path,
+                html: self.string()?,
+            },
+            OP_INSERT_CHILD => DomPatch::InsertChild {
+                path,
+                index: self.u32()?,
+                html: self.string()?,
+            },
+            OP_REMOVE_NODE => DomPatch::RemoveNode { path },
+            OP_SET_TEXT => DomPatch::SetText {
+                path,
+                text: self.string()?,
+            },
+            OP_SET_ATTRIBUTE => DomPatch::SetAttribute {
+                path,
+                name: self.string()?,
+                value: self.string()?,
+            },
+            OP_REMOVE_ATTRIBUTE => DomPatch::RemoveAttribute {
+                path,
+                name: self.string()?,
+            },
+            OP_MOVE_CHILD => DomPatch::MoveChild {
+                path,
+                from: self.u32()?,
+                to: self.u32()?,
+            },
+            other => return Err(PatchError::UnknownOp(other)),
+        })
+    }
+
+    fn finish(&self) -> Result<(), PatchError> {
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(PatchError::TrailingBytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One patch of every kind, with non-ASCII text and empty and deep paths
+    fn every_patch() -> Vec<DomPatch> {
+        vec![
+            DomPatch::ReplaceNode {
+                path: vec![],
+                html: "<main>héllo</main>".to_string(),
+            },
+            DomPatch::InsertChild {
+                path: vec![0, 2],
+                index: 1,
+                html: "<li>new</li>".to_string(),
+            },
+            DomPatch::RemoveNode {
+                path: vec![0, 2, 5],
+            },
+            DomPatch::SetText {
+                path: vec![1],
+                text: String::new(),
+            },
+            DomPatch::SetAttribute {
+                path: vec![1, 0],
+                name: "class".to_string(),
+                value: "active ✓".to_string(),
+            },
+            DomPatch::RemoveAttribute {
+                path: vec![u32::MAX],
+                name: "hidden".to_string(),
+            },
+            DomPatch::MoveChild {
+                path: (0..40).collect(),
+                from: 3,
+                to: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn every_patch_type_round_trips() {
+        for patch in every_patch() {
+            assert_eq!(DomPatch::decode(&patch.encode()), Ok(patch.clone()));
+        }
+        let remove = DomPatch::RemoveNode { path: vec![0, 2] };
+        assert_eq!(
+            remove.encode(),
+            [OP_REMOVE_NODE, 0, 2, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+    }
+
+    #[test]
+    fn batch_of_three_round_trips() {
+        let mut batch = PatchBatch::new(42);
+        for patch in every_patch().into_iter().skip(2).take(3) {
+            batch.push(patch);
+        }
+        let bytes = batch.encode();
+        assert_eq!(&bytes[..12], &[0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 3]);
+        let decoded = PatchBatch::decode(&bytes).unwrap();
+        assert_eq!(decoded.patches.len(), 3);
+        assert_eq!(decoded, batch);
+        assert_eq!(decoded.encode(), bytes);
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let mut batch = PatchBatch::new(1);
+        batch.push(every_patch().remove(1));
+        let bytes = batch.encode();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                PatchBatch::decode(&bytes[..len]),
+                Err(PatchError::Truncated),
+                "{} bytes",
+                len
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            PatchBatch::decode(&trailing),
+            Err(PatchError::TrailingBytes)
+        );
+        assert_eq!(
+            DomPatch::decode(&[0x7f, 0, 0]),
+            Err(PatchError::UnknownOp(0x7f))
+        );
+        assert_eq!(
+            DomPatch::decode(&[OP_SET_TEXT, 0, 0, 0, 0, 0, 1, 0xff]),
+            Err(PatchError::InvalidUtf8)
+        );
+        // A count far beyond the data fails cleanly instead of allocating for it
+        let mut huge = PatchBatch::new(1).encode();
+        huge[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(PatchBatch::decode(&huge), Err(PatchError::Truncated));
+    }
+}
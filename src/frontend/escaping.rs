@@ -0,0 +1,530 @@
+//! Context-Aware Output Escaping (Standard Library Only)
+//! Follows the literal template text through a small HTML tokenizer so each
+//! placeholder is escaped for where it lands:
+//! - text nodes and comments: HTML entities
+//! - attribute values: HTML entities plus the characters that end an unquoted value
+//! - URL attributes (`href`, `src`, ...): a value that starts the URL must use a safe
+//!   scheme (else it becomes `about:invalid#blocked`) and is percent-encoded; a value
+//!   later in the URL is encoded as a single component
+//! - `<script>` bodies and `on*` handlers: inside a JS string the value is escaped for
+//!   that string; elsewhere it becomes a quoted JSON string, so it is always data
+//!
+//! Anything the tokenizer cannot classify (inside a tag, `<style>`) gets HTML escaping.
+//! Quotes inside `<script>` are tracked without a full JS lexer, so comments and regex
+//! literals containing quote characters can confuse it; keep placeholders out of them.
+
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "cite",
+    "poster",
+    "background",
+    "srcset",
+    "xlink:href",
+];
+const SAFE_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const BLOCKED_URL: &str = "about:invalid#blocked";
+
+/// Where a placeholder sits, which decides how its value is escaped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EscapeContext {
+    Html,
+    Attribute,
+    /// The start of a URL attribute value
+    Url,
+    /// A URL attribute value after its first character, e.g. a query parameter
+    UrlComponent,
+    /// Script code outside any string literal
+    Script,
+    /// Inside a script string literal
+    ScriptString,
+    /// `EscapeContext::Script` or `ScriptString` within an `on*` attribute value
+    HandlerAttribute {
+        in_string: bool,
+    },
+}
+
+/// Escapes `value` for `context`
+pub fn escape(context: EscapeContext, value: &str) -> String {
+    match context {
+        EscapeContext::Html => escape_html(value),
+        EscapeContext::Attribute => escape_attribute(value),
+        EscapeContext::Url => escape_attribute(&filter_url(value)),
+        EscapeContext::UrlComponent => escape_attribute(&percent_encode(value, false)),
+        EscapeContext::Script => format!("\"{}\"", escape_js_string(value)),
+        EscapeContext::ScriptString => escape_js_string(value),
+        EscapeContext::HandlerAttribute { in_string: true } => {
+            escape_attribute(&escape_js_string(value))
+        }
+        EscapeContext::HandlerAttribute { in_string: false } => {
+            escape_attribute(&format!("\"{}\"", escape_js_string(value)))
+        }
+    }
+}
+
+/// Prevents XSS by escaping special characters
+pub fn escape_html(input: &str) -> String {
+    input
+        .replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#39;")
+}
+
+/// HTML escaping that also covers what ends an unquoted attribute value
+fn escape_attribute(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '=' | '`' | ' ' | '\t' | '\n' | '\r' | '\x0c' => {
+                out.push_str(&format!("&#{};", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Replaces a URL with an unsafe scheme, then percent-encodes what is not URL syntax
+fn filter_url(url: &str) -> String {
+    let scheme_end = url.find([':', '/', '?', '#']);
+    if let Some(end) = scheme_end.filter(|&end| url[end..].starts_with(':')) {
+        let scheme = url[..end].trim().to_ascii_lowercase();
+        if !SAFE_URL_SCHEMES.contains(&scheme.as_str()) {
+            return BLOCKED_URL.to_string();
+        }
+    }
+    percent_encode(url, true)
+}
+
+/// Percent-encodes every byte except unreserved characters and, when `keep_syntax`,
+/// the reserved delimiters (quotes are always encoded)
+fn percent_encode(input: &str, keep_syntax: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    for &byte in input.as_bytes() {
+        let unreserved = byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~');
+        let syntax = matches!(
+            byte,
+            b':' | b'/'
+                | b'?'
+                | b'#'
+                | b'['
+                | b']'
+                | b'@'
+                | b'!'
+                | b'$'
+                | b'&'
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+                | b'%'
+        );
+        if unreserved || (keep_syntax && syntax) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+/// Escapes for the inside of a JS or JSON string literal of any quote style. Every
+/// character that could end the string, the script element or a template literal
+/// becomes a `\uXXXX` escape, which is valid in both JS and JSON.
+fn escape_js_string(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' | '\'' | '`' | '<' | '>' | '&' | '/' | '$' | '\u{2028}' | '\u{2029}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Tokenizer position between two pieces of literal template text
+#[derive(Debug, Clone, Default)]
+pub struct HtmlState {
+    mode: Mode,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Mode {
+    #[default]
+    Text,
+    Comment,
+    Tag {
+        name: String,
+        closing: bool,
+        phase: TagPhase,
+    },
+    Value {
+        tag: String,
+        attr: String,
+        quote: Option<char>,
+        empty: bool,
+        js_quote: Option<char>,
+    },
+    /// Inside `<script>` or `<style>`, up to the matching end tag
+    RawText {
+        tag: String,
+        js_quote: Option<char>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TagPhase {
+    Name,
+    BeforeAttribute,
+    AttributeName(String),
+    AfterAttributeName(String),
+    BeforeValue(String),
+}
+
+impl HtmlState {
+    /// How a placeholder at the current position must be escaped
+    pub fn context(&self) -> EscapeContext {
+        match &self.mode {
+            Mode::Text | Mode::Comment => EscapeContext::Html,
+            // An unquoted value starting with the placeholder: `href={{url}}`
+            Mode::Tag {
+                phase: TagPhase::BeforeValue(attr),
+                ..
+            } => value_context(attr, true, None),
+            Mode::Tag { .. } => EscapeContext::Attribute,
+            Mode::Value {
+                attr,
+                empty,
+                js_quote,
+                ..
+            } => value_context(attr, *empty, *js_quote),
+            Mode::RawText { tag, js_quote } if tag == "script" => match js_quote {
+                Some(_) => EscapeContext::ScriptString,
+                None => EscapeContext::Script,
+            },
+            Mode::RawText { .. } => EscapeContext::Html,
+        }
+    }
+
+    /// Records that a placeholder's escaped value was emitted here
+    pub fn after_value(&mut self) {
+        self.mode = match std::mem::take(&mut self.mode) {
+            Mode::Tag {
+                name,
+                phase: TagPhase::BeforeValue(attr),
+                ..
+            } => Mode::Value {
+                tag: name,
+                attr,
+                quote: None,
+                empty: false,
+                js_quote: None,
+            },
+            Mode::Value {
+                tag,
+                attr,
+                quote,
+                js_quote,
+                ..
+            } => Mode::Value {
+                tag,
+                attr,
+                quote,
+                empty: false,
+                js_quote,
+            },
+            mode => mode,
+        };
+    }
+
+    /// Advances over a run of literal template text
+    pub fn feed(&mut self, text: &str) {
+        let mut chars = text.char_indices().peekable();
+        let mut skip_escaped = false;
+        while let Some((i, c)) = chars.next() {
+            let rest = &text[i..];
+            let mode = std::mem::take(&mut self.mode);
+            self.mode = match mode {
+                Mode::Text if rest.starts_with("<!--") => {
+                    chars.nth(2);
+                    Mode::Comment
+                }
+                Mode::Text if c == '<' => match rest[1..].chars().next() {
+                    Some('/') => {
+                        chars.next();
+                        Self::open_tag(true)
+                    }
+                    Some(next) if next.is_ascii_alphabetic() => Self::open_tag(false),
+                    _ => Mode::Text,
+                },
+                Mode::Text => Mode::Text,
+                Mode::Comment if rest.starts_with("-->") => {
+                    chars.nth(1);
+                    Mode::Text
+                }
+                Mode::Comment => Mode::Comment,
+                Mode::Tag {
+                    name,
+                    closing,
+                    phase,
+                } => Self::tag_step(name, closing, phase, c),
+                Mode::Value {
+                    tag,
+                    attr,
+                    quote,
+                    js_quote,
+                    ..
+                } => {
+                    let ends = match quote {
+                        Some(q) => c == q,
+                        None => c.is_whitespace() || c == '>',
+                    };
+                    if ends && c == '>' {
+                        Self::end_of_tag(tag, false)
+                    } else if ends {
+                        Mode::Tag {
+                            name: tag,
+                            closing: false,
+                            phase: TagPhase::BeforeAttribute,
+                        }
+                    } else {
+                        let js_quote = if attr.starts_with("on") {
+                            track_js_quote(js_quote, c, &mut skip_escaped)
+                        } else {
+                            None
+                        };
+                        Mode::Value {
+                            tag,
+                            attr,
+                            quote,
+                            empty: false,
+                            js_quote,
+                        }
+                    }
+                }
+                Mode::RawText { tag, js_quote } => {
+                    // Browsers end the element here even inside a JS string
+                    let end_tag = format!("</{}", tag);
+                    let ends = rest.len() >= end_tag.len()
+                        && rest.is_char_boundary(end_tag.len())
+                        && rest[..end_tag.len()].eq_ignore_ascii_case(&end_tag);
+                    if ends {
+                        chars.next();
+                        Self::open_tag(true)
+                    } else {
+                        let js_quote = if tag == "script" {
+                            track_js_quote(js_quote, c, &mut skip_escaped)
+                        } else {
+                            None
+                        };
+                        Mode::RawText { tag, js_quote }
+                    }
+                }
+            };
+        }
+    }
+
+    fn open_tag(closing: bool) -> Mode {
+        Mode::Tag {
+            name: String::new(),
+            closing,
+            phase: TagPhase::Name,
+        }
+    }
+
+    /// `<script>` and `<style>` bodies are raw text; every other tag returns to text
+    fn end_of_tag(name: String, closing: bool) -> Mode {
+        if !closing && (name == "script" || name == "style") {
+            Mode::RawText {
+                tag: name,
+                js_quote: None,
+            }
+        } else {
+            Mode::Text
+        }
+    }
+
+    fn tag_step(mut name: String, closing: bool, phase: TagPhase, c: char) -> Mode {
+        let tag = |name: String, phase| Mode::Tag {
+            name,
+            closing,
+            phase,
+        };
+        match phase {
+            _ if c == '>' && !matches!(phase, TagPhase::BeforeValue(_)) => {
+                Self::end_of_tag(name, closing)
+            }
+            TagPhase::Name if c.is_whitespace() || c == '/' => tag(name, TagPhase::BeforeAttribute),
+            TagPhase::Name => {
+                name.push(c.to_ascii_lowercase());
+                tag(name, TagPhase::Name)
+            }
+            TagPhase::BeforeAttribute if c.is_whitespace() || c == '/' => {
+                tag(name, TagPhase::BeforeAttribute)
+            }
+            TagPhase::BeforeAttribute => tag(
+                name,
+                TagPhase::AttributeName(c.to_ascii_lowercase().to_string()),
+            ),
+            TagPhase::AttributeName(attr) if c == '=' => tag(name, TagPhase::BeforeValue(attr)),
+            TagPhase::AttributeName(attr) if c.is_whitespace() => {
+                tag(name, TagPhase::AfterAttributeName(attr))
+            }
+            TagPhase::AttributeName(mut attr) => {
+                attr.push(c.to_ascii_lowercase());
+                tag(name, TagPhase::AttributeName(attr))
+            }
+            TagPhase::AfterAttributeName(attr) if c == '=' => {
+                tag(name, TagPhase::BeforeValue(attr))
+            }
+            TagPhase::AfterAttributeName(attr) if c.is_whitespace() => {
+                tag(name, TagPhase::AfterAttributeName(attr))
+            }
+            TagPhase::AfterAttributeName(_) => tag(
+                name,
+                TagPhase::AttributeName(c.to_ascii_lowercase().to_string()),
+            ),
+            TagPhase::BeforeValue(attr) if c.is_whitespace() => {
+                tag(name, TagPhase::BeforeValue(attr))
+            }
+            TagPhase::BeforeValue(_) if c == '>' => Self::end_of_tag(name, closing),
+            TagPhase::BeforeValue(attr) => {
+                let quote = Some(c).filter(|&c| c == '"' || c == '\'');
+                Mode::Value {
+                    tag: name,
+                    js_quote: None,
+                    empty: quote.is_some(),
+                    quote,
+                    attr,
+                }
+            }
+        }
+    }
+}
+
+/// Context for a whole value of attribute `name`, as when an element is built in code
+#[allow(dead_code)] // Used by the renderer's virtual DOM
+pub fn attribute_value_context(name: &str) -> EscapeContext {
+    value_context(&name.to_ascii_lowercase(), true, None)
+}
+
+/// Context inside the value of attribute `attr`; `at_start` if nothing precedes it
+fn value_context(attr: &str, at_start: bool, js_quote: Option<char>) -> EscapeContext {
+    if URL_ATTRIBUTES.contains(&attr) {
+        if at_start {
+            EscapeContext::Url
+        } else {
+            EscapeContext::UrlComponent
+        }
+    } else if attr.starts_with("on") {
+        EscapeContext::HandlerAttribute {
+            in_string: js_quote.is_some(),
+        }
+    } else {
+        EscapeContext::Attribute
+    }
+}
+
+/// Follows JS string literals: an unescaped quote opens or closes one
+fn track_js_quote(current: Option<char>, c: char, skip_escaped: &mut bool) -> Option<char> {
+    if std::mem::take(skip_escaped) {
+        return current;
+    }
+    match current {
+        Some(_) if c == '\\' => {
+            *skip_escaped = true;
+            current
+        }
+        Some(q) if c == q => None,
+        Some(q) => Some(q),
+        None if matches!(c, '"' | '\'' | '`') => Some(c),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `before`, then `value` escaped for wherever `before` left off, then `after`
+    fn render(before: &str, value: &str, after: &str) -> String {
+        let mut html = HtmlState::default();
+        html.feed(before);
+        let escaped = escape(html.context(), value);
+        html.after_value();
+        format!("{}{}{}", before, escaped, after)
+    }
+
+    #[test]
+    fn hostile_href_values_are_neutralised() {
+        assert_eq!(
+            render("<a href=\"", "javascript:alert(1)", "\">x</a>"),
+            "<a href=\"about:invalid#blocked\">x</a>"
+        );
+        assert_eq!(
+            render("<a href='", " JavaScript:alert(1)", "'>x</a>"),
+            "<a href='about:invalid#blocked'>x</a>"
+        );
+        // Quotes cannot end the attribute early
+        assert_eq!(
+            render(
+                "<a href=\"",
+                "https://ok.test/\" onmouseover=\"x",
+                "\">x</a>"
+            ),
+            "<a href=\"https://ok.test/%22%20onmouseover&#61;%22x\">x</a>"
+        );
+        // Later in the URL the value is one component, so it cannot add parameters
+        assert_eq!(
+            render("<a href=\"/search?q=", "a&admin=1 \"", "\">x</a>"),
+            "<a href=\"/search?q=a%26admin%3D1%20%22\">x</a>"
+        );
+        assert_eq!(
+            render("<img src=\"", "data:text/html,<script>", "\">"),
+            "<img src=\"about:invalid#blocked\">"
+        );
+    }
+
+    #[test]
+    fn hostile_script_values_stay_data() {
+        assert_eq!(
+            render("<script>var user = ", "</script><script>alert(1)", ";</script>"),
+            "<script>var user = \"\\u003c\\u002fscript\\u003e\\u003cscript\\u003ealert(1)\";</script>"
+        );
+        assert_eq!(
+            render("<script>var s = '", "';alert(1)//", "';</script>"),
+            "<script>var s = '\\u0027;alert(1)\\u002f\\u002f';</script>"
+        );
+        assert_eq!(
+            render("<script>var t = `", "${alert(1)}`", "`;</script>"),
+            "<script>var t = `\\u0024{alert(1)}\\u0060`;</script>"
+        );
+        assert_eq!(
+            render("<button onclick=\"go(", "1); alert(2", ")\">"),
+            "<button onclick=\"go(&quot;1);&#32;alert(2&quot;)\">"
+        );
+        // Text after the script is back to HTML escaping
+        assert_eq!(
+            render("<script>1</script><p>", "<b>", "</p>"),
+            "<script>1</script><p>&lt;b&gt;</p>"
+        );
+    }
+}
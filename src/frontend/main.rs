@@ -10,73 +10,1314 @@
 //! - **Secure WebAssembly (WASM) integration for UI enhancements**
 //! - **Optimized rendering pipeline for high-performance UI updates**
 //! - **Real-time session-aware UI elements**
+//! - **Typed, length-prefixed UI message protocol**
+//! - **State changes broadcast to subscribed clients through bounded per-client queues; clients too slow to keep up are dropped**
+//! - **Updates signed with a per-session key and single-use nonces**
+//! - **Event-sourced component state with undo/redo and replay for reconnecting clients**
 
-use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::{Arc, Mutex};
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../backend/keys.rs"]
+mod keys;
+#[allow(dead_code)]
+#[path = "../backend/session_management.rs"]
+mod session_management;
+#[allow(dead_code)]
+#[path = "../backend/util.rs"]
+mod util;
+
+use crypto::constant_time_eq;
+use keys::KeyProvider;
+use session_management::SessionStore;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 const BIND_ADDR: &str = "0.0.0.0:8082";
+const BIND_ADDR_ENV: &str = "FRONTEND_BIND_ADDR";
+const READ_TIMEOUT: Duration = Duration::from_secs(10); // A silent client is dropped after this
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+const MAX_NONCES_PER_SESSION: usize = 65_536; // Past this the client must start a new session
+const MAX_UNDO_DEPTH: usize = 64; // States kept per component for undo
+const SNAPSHOT_INTERVAL: usize = 256; // Events logged per component before folding into a snapshot
+const OUTBOUND_QUEUE_CAPACITY: usize = 256; // Frames waiting for a client's writer
+const MAX_DROPPED_PUSHES: u64 = 64; // Pushes a client may miss before it is disconnected
+
+const TAG_UPDATE_COMPONENT: u8 = 0x01;
+const TAG_GET_COMPONENT: u8 = 0x02;
+const TAG_SUBSCRIBE: u8 = 0x03;
+const TAG_UNDO: u8 = 0x04;
+const TAG_REDO: u8 = 0x05;
+const TAG_REPLAY: u8 = 0x06;
+const TAG_STATE: u8 = 0x81;
+const TAG_NOT_FOUND: u8 = 0x82;
+const TAG_SUBSCRIBED: u8 = 0x83;
+const TAG_EVENT: u8 = 0x84;
+const TAG_REPLAYED: u8 = 0x85;
+const TAG_ERROR: u8 = 0xFF;
+
+/// A client-to-server UI message. Updates, undos and redos are authenticated: `mac`
+/// is the hex HMAC-SHA256 from `update_mac` or `history_mac` under the session's event key.
+#[derive(Debug, Clone, PartialEq)]
+enum UiMessage {
+    UpdateComponent {
+        id: String,
+        state: String,
+        session: String,
+        nonce: String,
+        mac: String,
+    },
+    GetComponent {
+        id: String,
+    },
+    Subscribe {
+        id: String,
+    },
+    Undo {
+        id: String,
+        session: String,
+        nonce: String,
+        mac: String,
+    },
+    Redo {
+        id: String,
+        session: String,
+        nonce: String,
+        mac: String,
+    },
+    /// Resends every change after sequence number `since`, for a reconnecting client
+    Replay {
+        since: u64,
+    },
+}
+
+/// A server-to-client UI message; `Event` is also pushed to subscribers
+#[derive(Debug, Clone, PartialEq)]
+enum UiResponse {
+    State {
+        id: String,
+        state: String,
+    },
+    NotFound {
+        id: String,
+    },
+    Subscribed {
+        id: String,
+    },
+    /// Component `id` changed to `state`; `seq` orders changes across all components
+    Event {
+        seq: u64,
+        id: String,
+        state: String,
+    },
+    /// A replay has sent every change through `seq`
+    Replayed {
+        seq: u64,
+    },
+    Error(String),
+}
+
+/// Why a frame could not be read or decoded
+#[derive(Debug)]
+enum ProtocolError {
+    Io(io::Error),
+    TooLarge(usize),
+    Malformed(&'static str),
+}
+
+impl From<io::Error> for ProtocolError {
+    fn from(e: io::Error) -> Self {
+        ProtocolError::Io(e)
+    }
+}
+
+/// Frames are `[u32 BE payload length][payload]`; a payload is a tag byte followed
+/// by `[u32 BE length][UTF-8 bytes]` fields, so values may contain any character.
+fn encode_frame(tag: u8, fields: &[&str]) -> Vec<u8> {
+    let mut payload = vec![tag];
+    for field in fields {
+        payload.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        payload.extend_from_slice(field.as_bytes());
+    }
+    let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(&payload);
+    frame
+}
+
+/// Splits a payload into its tag and exactly `count` fields
+fn decode_fields(payload: &[u8], count: usize) -> Result<Vec<String>, ProtocolError> {
+    let mut rest = &payload[1..];
+    let mut fields = Vec::with_capacity(count);
+    for _ in 0..count {
+        if rest.len() < 4 {
+            return Err(ProtocolError::Malformed("truncated field length"));
+        }
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        rest = &rest[4..];
+        if rest.len() < len {
+            return Err(ProtocolError::Malformed("truncated field"));
+        }
+        let field = String::from_utf8(rest[..len].to_vec())
+            .map_err(|_| ProtocolError::Malformed("field is not valid UTF-8"))?;
+        fields.push(field);
+        rest = &rest[len..];
+    }
+    if !rest.is_empty() {
+        return Err(ProtocolError::Malformed("trailing bytes after fields"));
+    }
+    Ok(fields)
+}
+
+/// The MAC a client attaches to an update. The signed fields are length-prefixed
+/// exactly as on the wire, so no two field combinations share an input.
+fn update_mac(key: &[u8; 32], id: &str, state: &str, session: &str, nonce: &str) -> [u8; 32] {
+    let signed = encode_frame(TAG_UPDATE_COMPONENT, &[id, state, session, nonce]);
+    crypto::hmac_sha256(key, &signed)
+}
+
+/// The MAC a client attaches to an undo (`TAG_UNDO`) or redo (`TAG_REDO`). The tag is
+/// signed too, so an undo cannot be replayed as a redo.
+fn history_mac(key: &[u8; 32], tag: u8, id: &str, session: &str, nonce: &str) -> [u8; 32] {
+    let signed = encode_frame(tag, &[id, session, nonce]);
+    crypto::hmac_sha256(key, &signed)
+}
+
+/// Parses a decimal sequence number field
+fn parse_seq(field: &str) -> Result<u64, ProtocolError> {
+    field
+        .parse()
+        .map_err(|_| ProtocolError::Malformed("sequence number is not a number"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads one frame payload; `Ok(None)` means the peer closed between frames
+fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, ProtocolError> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(ProtocolError::TooLarge(len));
+    }
+    if len == 0 {
+        return Err(ProtocolError::Malformed("empty frame"));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+impl UiMessage {
+    #[allow(dead_code)] // Used by clients of the protocol
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            UiMessage::UpdateComponent {
+                id,
+                state,
+                session,
+                nonce,
+                mac,
+            } => encode_frame(TAG_UPDATE_COMPONENT, &[id, state, session, nonce, mac]),
+            UiMessage::GetComponent { id } => encode_frame(TAG_GET_COMPONENT, &[id]),
+            UiMessage::Subscribe { id } => encode_frame(TAG_SUBSCRIBE, &[id]),
+            UiMessage::Undo {
+                id,
+                session,
+                nonce,
+                mac,
+            } => encode_frame(TAG_UNDO, &[id, session, nonce, mac]),
+            UiMessage::Redo {
+                id,
+                session,
+                nonce,
+                mac,
+            } => encode_frame(TAG_REDO, &[id, session, nonce, mac]),
+            UiMessage::Replay { since } => encode_frame(TAG_REPLAY, &[&since.to_string()]),
+        }
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self, ProtocolError> {
+        let take = |count| decode_fields(payload, count).map(Vec::into_iter);
+        match payload[0] {
+            TAG_UPDATE_COMPONENT => {
+                let mut fields = take(5)?;
+                Ok(UiMessage::UpdateComponent {
+                    id: fields.next().unwrap(),
+                    state: fields.next().unwrap(),
+                    session: fields.next().unwrap(),
+                    nonce: fields.next().unwrap(),
+                    mac: fields.next().unwrap(),
+                })
+            }
+            TAG_GET_COMPONENT => Ok(UiMessage::GetComponent {
+                id: take(1)?.next().unwrap(),
+            }),
+            TAG_SUBSCRIBE => Ok(UiMessage::Subscribe {
+                id: take(1)?.next().unwrap(),
+            }),
+            TAG_UNDO | TAG_REDO => {
+                let mut fields = take(4)?;
+                let (id, session, nonce, mac) = (
+                    fields.next().unwrap(),
+                    fields.next().unwrap(),
+                    fields.next().unwrap(),
+                    fields.next().unwrap(),
+                );
+                Ok(if payload[0] == TAG_UNDO {
+                    UiMessage::Undo {
+                        id,
+                        session,
+                        nonce,
+                        mac,
+                    }
+                } else {
+                    UiMessage::Redo {
+                        id,
+                        session,
+                        nonce,
+                        mac,
+                    }
+                })
+            }
+            TAG_REPLAY => Ok(UiMessage::Replay {
+                since: parse_seq(&take(1)?.next().unwrap())?,
+            }),
+            _ => Err(ProtocolError::Malformed("unknown message type")),
+        }
+    }
+}
+
+impl UiResponse {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            UiResponse::State { id, state } => encode_frame(TAG_STATE, &[id, state]),
+            UiResponse::NotFound { id } => encode_frame(TAG_NOT_FOUND, &[id]),
+            UiResponse::Subscribed { id } => encode_frame(TAG_SUBSCRIBED, &[id]),
+            UiResponse::Event { seq, id, state } => {
+                encode_frame(TAG_EVENT, &[&seq.to_string(), id, state])
+            }
+            UiResponse::Replayed { seq } => encode_frame(TAG_REPLAYED, &[&seq.to_string()]),
+            UiResponse::Error(reason) => encode_frame(TAG_ERROR, &[reason]),
+        }
+    }
+
+    #[allow(dead_code)] // Used by clients of the protocol
+    fn decode(payload: &[u8]) -> Result<Self, ProtocolError> {
+        let take = |count| decode_fields(payload, count).map(Vec::into_iter);
+        match payload[0] {
+            TAG_STATE => {
+                let mut fields = take(2)?;
+                Ok(UiResponse::State {
+                    id: fields.next().unwrap(),
+                    state: fields.next().unwrap(),
+                })
+            }
+            TAG_NOT_FOUND => Ok(UiResponse::NotFound {
+                id: take(1)?.next().unwrap(),
+            }),
+            TAG_SUBSCRIBED => Ok(UiResponse::Subscribed {
+                id: take(1)?.next().unwrap(),
+            }),
+            TAG_EVENT => {
+                let mut fields = take(3)?;
+                Ok(UiResponse::Event {
+                    seq: parse_seq(&fields.next().unwrap())?,
+                    id: fields.next().unwrap(),
+                    state: fields.next().unwrap(),
+                })
+            }
+            TAG_REPLAYED => Ok(UiResponse::Replayed {
+                seq: parse_seq(&take(1)?.next().unwrap())?,
+            }),
+            TAG_ERROR => Ok(UiResponse::Error(take(1)?.next().unwrap())),
+            _ => Err(ProtocolError::Malformed("unknown response type")),
+        }
+    }
+}
+
+/// What a connection's writer thread is asked to do
+enum Outbound {
+    Send(UiResponse),
+    Close, // Flush what is queued, then shut the socket down
+}
+
+/// The connection behind a client's queue, shared by every clone of its sender
+struct ClientLink {
+    socket: TcpStream, // Shut down to disconnect a client that cannot keep up
+    dropped: AtomicU64,
+    disconnected: AtomicBool,
+}
+
+/// Queues frames for one client; pushes and replies share it so frames never interleave.
+/// The queue is bounded, so a client that stops reading cannot hold unbounded memory.
+#[derive(Clone)]
+struct ClientSender {
+    queue: mpsc::SyncSender<Outbound>,
+    link: Arc<ClientLink>,
+}
+
+impl ClientSender {
+    /// A bounded queue for the client on `stream`, and the receiving end for its writer
+    fn new(stream: &TcpStream) -> io::Result<(Self, mpsc::Receiver<Outbound>)> {
+        let (queue, outbound) = mpsc::sync_channel(OUTBOUND_QUEUE_CAPACITY);
+        let link = Arc::new(ClientLink {
+            socket: stream.try_clone()?,
+            dropped: AtomicU64::new(0),
+            disconnected: AtomicBool::new(false),
+        });
+        Ok((Self { queue, link }, outbound))
+    }
+
+    /// Queues a reply, waiting for room since the client is waiting for it. Fails
+    /// once the writer has exited.
+    fn send(&self, response: UiResponse) -> Result<(), ()> {
+        self.queue.send(Outbound::Send(response)).map_err(|_| ())
+    }
+
+    /// Queues a push without waiting, so one slow client never holds up the others.
+    /// A full queue drops the push; after `MAX_DROPPED_PUSHES` the client is
+    /// disconnected, to reconnect and replay what it missed. Returns false once the
+    /// client should be unsubscribed.
+    fn push(&self, response: UiResponse) -> bool {
+        match self.queue.try_send(Outbound::Send(response)) {
+            Ok(()) => true,
+            Err(mpsc::TrySendError::Full(_)) => {
+                let dropped = self.link.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped < MAX_DROPPED_PUSHES {
+                    return true;
+                }
+                if !self.link.disconnected.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "UI client dropped after missing {} pushes: it is not reading",
+                        dropped
+                    );
+                    let _ = self.link.socket.shutdown(Shutdown::Both);
+                }
+                false
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// Asks the writer to flush what is queued and shut the socket down
+    fn close(&self) {
+        let _ = self.queue.send(Outbound::Close);
+    }
+
+    /// Pushes this client has missed because its queue was full
+    fn dropped_pushes(&self) -> u64 {
+        self.link.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A change to one component, as recorded in its event log
+#[derive(Debug, Clone, PartialEq)]
+enum EventKind {
+    Set(String),
+    Undo,
+    Redo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct UiEvent {
+    seq: u64,
+    kind: EventKind,
+}
+
+/// What a component's events fold into: the states that can be undone to, newest
+/// last (so the last is the current state), and the states that can be redone
+#[derive(Debug, Clone, Default)]
+struct History {
+    past: Vec<String>,
+    undone: Vec<String>,
+}
+
+impl History {
+    fn current(&self) -> Option<&str> {
+        self.past.last().map(String::as_str)
+    }
+
+    /// Whether `kind` would change the state; events that would not are never logged
+    fn accepts(&self, kind: &EventKind) -> bool {
+        match kind {
+            EventKind::Set(_) => true,
+            EventKind::Undo => self.past.len() > 1,
+            EventKind::Redo => !self.undone.is_empty(),
+        }
+    }
+
+    fn apply(&mut self, kind: &EventKind) {
+        match kind {
+            EventKind::Set(state) => {
+                self.past.push(state.clone());
+                if self.past.len() > MAX_UNDO_DEPTH {
+                    self.past.remove(0);
+                }
+                self.undone.clear();
+            }
+            EventKind::Undo => {
+                if self.past.len() > 1 {
+                    self.undone.extend(self.past.pop());
+                }
+            }
+            EventKind::Redo => {
+                if let Some(state) = self.undone.pop() {
+                    self.past.push(state);
+                }
+            }
+        }
+    }
+}
 
-/// Manages UI state with diff-based updates
+/// One component's state: a snapshot, the events logged since it, and their fold
+#[derive(Debug, Default)]
+struct ComponentLog {
+    snapshot: History,
+    snapshot_seq: u64, // Sequence number of the last event folded into `snapshot`
+    events: Vec<UiEvent>,
+    head: History, // `snapshot` with every event in `events` applied
+}
+
+impl ComponentLog {
+    fn append(&mut self, event: UiEvent) {
+        self.head.apply(&event.kind);
+        self.events.push(event);
+        if self.events.len() >= SNAPSHOT_INTERVAL {
+            self.snapshot = self.head.clone();
+            self.snapshot_seq = self.events.last().map_or(self.snapshot_seq, |e| e.seq);
+            self.events.clear();
+        }
+    }
+
+    /// `(seq, state)` after each event later than `since`. Events already folded into
+    /// the snapshot are summarized by the snapshot's state.
+    fn since(&self, since: u64) -> Vec<(u64, String)> {
+        let mut changes = Vec::new();
+        let mut history = self.snapshot.clone();
+        if since < self.snapshot_seq {
+            changes.extend(
+                history
+                    .current()
+                    .map(|state| (self.snapshot_seq, state.to_string())),
+            );
+        }
+        for event in &self.events {
+            history.apply(&event.kind);
+            if event.seq > since {
+                changes.extend(
+                    history
+                        .current()
+                        .map(|state| (event.seq, state.to_string())),
+                );
+            }
+        }
+        changes
+    }
+}
+
+/// Every component's event log, sequenced together so replay can resume across components
+#[derive(Debug, Default)]
+struct EventStore {
+    logs: HashMap<String, ComponentLog>,
+    last_seq: u64,
+}
+
+/// Manages UI state as per-component event logs
 struct UIState {
-    components: Mutex<HashMap<String, String>>, // UI elements and their states
+    components: Mutex<EventStore>, // UI elements and the events that built their states
+    subscribers: Mutex<HashMap<String, Vec<ClientSender>>>, // Notified of every update to a component
+    sessions: Arc<SessionStore>,
+    seen_nonces: Mutex<HashMap<String, HashSet<String>>>, // Session -> nonces already used
 }
 
 impl UIState {
-    fn new() -> Self {
+    fn new(sessions: Arc<SessionStore>) -> Self {
         Self {
-            components: Mutex::new(HashMap::new()),
+            components: Mutex::new(EventStore::default()),
+            subscribers: Mutex::new(HashMap::new()),
+            sessions,
+            seen_nonces: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Updates a UI component state
-    fn update_component(&self, component_id: &str, state: &str) {
+    /// Checks a signed message against the session's key, then burns its nonce.
+    /// The signature is checked first so forged messages cannot use up nonces.
+    /// `sign` computes the MAC the message should carry under a given key.
+    fn authorize_update(
+        &self,
+        sign: impl FnOnce(&[u8; 32]) -> [u8; 32],
+        session: &str,
+        nonce: &str,
+        mac: &str,
+        peer_ip: &str,
+    ) -> Result<(), &'static str> {
+        let key = self
+            .sessions
+            .event_key(session, peer_ip)
+            .ok_or("unknown or expired session")?;
+        let expected = to_hex(&sign(&key));
+        if !constant_time_eq(expected.as_bytes(), mac.as_bytes()) {
+            return Err("invalid event signature");
+        }
+        if nonce.is_empty() {
+            return Err("missing nonce");
+        }
+
+        let mut seen_nonces = self.seen_nonces.lock().unwrap();
+        let seen = seen_nonces.entry(session.to_string()).or_default();
+        if seen.contains(nonce) {
+            return Err("replayed nonce");
+        }
+        if seen.len() >= MAX_NONCES_PER_SESSION {
+            return Err("nonce limit reached for this session");
+        }
+        seen.insert(nonce.to_string());
+        Ok(())
+    }
+
+    /// Logs an event for a component and pushes the resulting state to subscribers.
+    /// Fails, logging nothing, when there is nothing to undo or redo.
+    fn apply_event(
+        &self,
+        component_id: &str,
+        kind: EventKind,
+    ) -> Result<(u64, String), &'static str> {
         let mut components = self.components.lock().unwrap();
-        components.insert(component_id.to_string(), state.to_string());
+        let store = &mut *components;
+        let accepted = match store.logs.get(component_id) {
+            Some(log) => log.head.accepts(&kind),
+            None => History::default().accepts(&kind),
+        };
+        if !accepted {
+            return Err(match kind {
+                EventKind::Redo => "nothing to redo",
+                _ => "nothing to undo",
+            });
+        }
+        let log = store.logs.entry(component_id.to_string()).or_default();
+        store.last_seq += 1;
+        let seq = store.last_seq;
+        log.append(UiEvent { seq, kind });
+        let state = log.head.current().unwrap_or_default().to_string();
+        drop(components);
+
+        self.broadcast(UiResponse::Event {
+            seq,
+            id: component_id.to_string(),
+            state: state.clone(),
+        });
+        Ok((seq, state))
+    }
+
+    /// Sets a UI component state and pushes it to subscribers
+    fn update_component(&self, component_id: &str, state: &str) -> u64 {
+        let set = EventKind::Set(state.to_string());
+        self.apply_event(component_id, set)
+            .map(|(seq, _)| seq)
+            .expect("a state can always be set")
+    }
+
+    /// Returns a component to its previous state
+    fn undo(&self, component_id: &str) -> Result<(u64, String), &'static str> {
+        self.apply_event(component_id, EventKind::Undo)
+    }
+
+    /// Reapplies the state most recently undone
+    fn redo(&self, component_id: &str) -> Result<(u64, String), &'static str> {
+        self.apply_event(component_id, EventKind::Redo)
+    }
+
+    /// Every change after `since` as `(seq, component, state)` in sequence order, and
+    /// the latest sequence number, from which the client can resume next time
+    fn replay_since(&self, since: u64) -> (Vec<(u64, String, String)>, u64) {
+        let components = self.components.lock().unwrap();
+        let mut changes: Vec<(u64, String, String)> = components
+            .logs
+            .iter()
+            .flat_map(|(id, log)| {
+                log.since(since)
+                    .into_iter()
+                    .map(move |(seq, state)| (seq, id.clone(), state))
+            })
+            .collect();
+        changes.sort_by_key(|(seq, _, _)| *seq);
+        (changes, components.last_seq)
+    }
+
+    /// Pushes an event to the component's subscribers
+    fn broadcast(&self, update: UiResponse) {
+        let component_id = match &update {
+            UiResponse::Event { id, .. } => id.clone(),
+            _ => return,
+        };
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if let Some(senders) = subscribers.get_mut(&component_id) {
+            // Prunes clients whose writer has exited or who were too slow to keep up
+            senders.retain(|sender| sender.push(update.clone()));
+            if senders.is_empty() {
+                subscribers.remove(&component_id);
+            }
+        }
+    }
+
+    /// Registers a client to receive future updates to a component
+    fn subscribe(&self, component_id: &str, sender: ClientSender) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .entry(component_id.to_string())
+            .or_default()
+            .push(sender);
     }
 
     /// Retrieves a UI component state
     fn get_component_state(&self, component_id: &str) -> Option<String> {
         let components = self.components.lock().unwrap();
-        components.get(component_id).cloned()
+        components
+            .logs
+            .get(component_id)?
+            .head
+            .current()
+            .map(str::to_string)
+    }
+}
+
+/// Applies one message to the UI state and builds its reply
+fn handle_message(
+    message: UiMessage,
+    sender: &ClientSender,
+    ui_state: &UIState,
+    peer_ip: &str,
+) -> UiResponse {
+    match message {
+        UiMessage::UpdateComponent {
+            id,
+            state,
+            session,
+            nonce,
+            mac,
+        } => {
+            let sign = |key: &[u8; 32]| update_mac(key, &id, &state, &session, &nonce);
+            match ui_state.authorize_update(sign, &session, &nonce, &mac, peer_ip) {
+                Ok(()) => {
+                    let seq = ui_state.update_component(&id, &state);
+                    println!("Updated component {}", id);
+                    UiResponse::Event { seq, id, state }
+                }
+                Err(reason) => {
+                    eprintln!("Rejected update to {} from {}: {}", id, peer_ip, reason);
+                    UiResponse::Error(format!("update rejected: {}", reason))
+                }
+            }
+        }
+        UiMessage::Undo {
+            id,
+            session,
+            nonce,
+            mac,
+        } => history_step(ui_state, TAG_UNDO, id, &session, &nonce, &mac, peer_ip),
+        UiMessage::Redo {
+            id,
+            session,
+            nonce,
+            mac,
+        } => history_step(ui_state, TAG_REDO, id, &session, &nonce, &mac, peer_ip),
+        UiMessage::Replay { since } => {
+            let (changes, seq) = ui_state.replay_since(since);
+            for (seq, id, state) in changes {
+                let _ = sender.send(UiResponse::Event { seq, id, state });
+            }
+            UiResponse::Replayed { seq }
+        }
+        UiMessage::GetComponent { id } => match ui_state.get_component_state(&id) {
+            Some(state) => UiResponse::State { id, state },
+            None => UiResponse::NotFound { id },
+        },
+        UiMessage::Subscribe { id } => {
+            ui_state.subscribe(&id, sender.clone());
+            UiResponse::Subscribed { id }
+        }
     }
 }
 
-/// Handles incoming WebSocket UI updates
-fn handle_connection(mut stream: TcpStream, ui_state: Arc<UIState>) {
-    let mut buffer = [0; 1024];
+/// Authorizes and applies a signed undo (`TAG_UNDO`) or redo (`TAG_REDO`)
+fn history_step(
+    ui_state: &UIState,
+    tag: u8,
+    id: String,
+    session: &str,
+    nonce: &str,
+    mac: &str,
+    peer_ip: &str,
+) -> UiResponse {
+    let action = if tag == TAG_UNDO { "undo" } else { "redo" };
+    let sign = |key: &[u8; 32]| history_mac(key, tag, &id, session, nonce);
+    let applied = ui_state
+        .authorize_update(sign, session, nonce, mac, peer_ip)
+        .and_then(|()| {
+            if tag == TAG_UNDO {
+                ui_state.undo(&id)
+            } else {
+                ui_state.redo(&id)
+            }
+        });
+    match applied {
+        Ok((seq, state)) => {
+            println!("Applied {} to component {}", action, id);
+            UiResponse::Event { seq, id, state }
+        }
+        Err(reason) => {
+            eprintln!("Rejected {} of {} from {}: {}", action, id, peer_ip, reason);
+            UiResponse::Error(format!("{} rejected: {}", action, reason))
+        }
+    }
+}
 
-    if stream.read(&mut buffer).is_ok() {
-        let request = String::from_utf8_lossy(&buffer);
-        let parts: Vec<&str> = request.trim().split(':').collect();
+/// Writes queued frames to the client until told to close or a write fails.
+/// Either way the receiver is dropped, so subscriptions held for this client go dead.
+fn spawn_writer(mut stream: TcpStream, outbound: mpsc::Receiver<Outbound>) {
+    thread::spawn(move || {
+        for message in outbound {
+            match message {
+                Outbound::Send(response) => {
+                    if let Err(e) = stream.write_all(&response.encode()) {
+                        eprintln!("UI response failed: {}", e);
+                        break;
+                    }
+                }
+                Outbound::Close => break,
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+}
 
-        if parts.len() == 2 {
-            ui_state.update_component(parts[0], parts[1]);
-            println!("Updated component {}: {}", parts[0], parts[1]);
+/// Handles framed UI messages until the client closes, stalls, or misbehaves
+fn handle_connection(
+    mut stream: TcpStream,
+    ui_state: Arc<UIState>,
+    read_timeout: Duration,
+    write_timeout: Duration,
+) {
+    if let Err(e) = util::set_timeouts(&stream, read_timeout, write_timeout) {
+        eprintln!("UI connection setup failed: {}", e);
+        return;
+    }
+    let peer_ip = match stream.peer_addr() {
+        Ok(addr) => addr.ip().to_string(),
+        Err(e) => {
+            eprintln!("UI connection setup failed: {}", e);
+            return;
+        }
+    };
+    let (sender, outbound) = match ClientSender::new(&stream) {
+        Ok(channel) => channel,
+        Err(e) => {
+            eprintln!("UI connection setup failed: {}", e);
+            return;
+        }
+    };
+    match stream.try_clone() {
+        Ok(write_half) => spawn_writer(write_half, outbound),
+        Err(e) => {
+            eprintln!("UI connection setup failed: {}", e);
+            return;
         }
+    }
+    let mut subscribed = false;
 
-        if let Some(state) = ui_state.get_component_state(parts[0]) {
-            let response = format!("{}:{}", parts[0], state);
-            stream.write_all(response.as_bytes()).unwrap();
+    loop {
+        let response = match read_frame(&mut stream) {
+            Ok(None) => break,
+            Ok(Some(payload)) => match UiMessage::decode(&payload) {
+                Ok(message) => {
+                    subscribed |= matches!(message, UiMessage::Subscribe { .. });
+                    handle_message(message, &sender, &ui_state, &peer_ip)
+                }
+                Err(ProtocolError::Malformed(reason)) => UiResponse::Error(reason.to_string()),
+                Err(e) => {
+                    eprintln!("Unreadable UI frame: {:?}", e);
+                    break;
+                }
+            },
+            // Subscribers may legitimately sit idle while waiting for pushes
+            Err(ProtocolError::Io(e)) if util::is_timeout(&e) && subscribed => continue,
+            Err(ProtocolError::Io(e)) if util::is_timeout(&e) => {
+                eprintln!("UI client sent nothing within {:?}, closing", read_timeout);
+                break;
+            }
+            Err(ProtocolError::TooLarge(len)) => {
+                let reason = format!("frame of {} bytes exceeds {}", len, MAX_FRAME_SIZE);
+                let _ = sender.send(UiResponse::Error(reason));
+                break;
+            }
+            Err(_) => break,
+        };
+        if sender.send(response).is_err() {
+            break; // The writer gave up on this client
         }
     }
+    if sender.dropped_pushes() > 0 {
+        eprintln!(
+            "UI client {} missed {} pushes while connected",
+            peer_ip,
+            sender.dropped_pushes()
+        );
+    }
+    sender.close();
 }
 
-fn main() {
-    let listener = TcpListener::bind(BIND_ADDR).expect("Failed to bind frontend UI server");
-    println!("Zero Trust Rust UI running on {}", BIND_ADDR);
+/// Serves the UI state protocol on `addr` until the listener fails
+fn run(addr: &str, session_secret: Vec<u8>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Zero Trust Rust UI running on {}", listener.local_addr()?);
 
-    let ui_state = Arc::new(UIState::new());
+    let ui_state = Arc::new(UIState::new(Arc::new(SessionStore::new(session_secret))));
+    let read_timeout = util::timeout_from_env("FRONTEND_READ_TIMEOUT_SECS", READ_TIMEOUT);
+    let write_timeout = util::timeout_from_env("FRONTEND_WRITE_TIMEOUT_SECS", WRITE_TIMEOUT);
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 let ui_state = Arc::clone(&ui_state);
-                thread::spawn(move || handle_connection(stream, ui_state));
+                thread::spawn(move || {
+                    handle_connection(stream, ui_state, read_timeout, write_timeout)
+                });
             }
             Err(e) => eprintln!("UI connection failed: {}", e),
         }
     }
+    Ok(())
+}
+
+fn main() {
+    let addr = util::bind_address(BIND_ADDR_ENV, BIND_ADDR);
+    let session_secret = match keys::EnvKeyProvider.require(keys::SESSION_SECRET) {
+        Ok(secret) => secret,
+        Err(e) => {
+            eprintln!("Frontend UI server not started: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = run(&addr, session_secret) {
+        eprintln!("Failed to bind frontend UI server on {}: {}", addr, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a frame and reads it back the way the server does
+    fn reframe(frame: &[u8]) -> Vec<u8> {
+        read_frame(&mut &frame[..]).unwrap().expect("a whole frame")
+    }
+
+    #[test]
+    fn every_message_type_round_trips() {
+        let tricky = "theme:dark:v2\nline two\r\n: trailing";
+        let messages = [
+            UiMessage::UpdateComponent {
+                id: "panel:1".to_string(),
+                state: tricky.to_string(),
+                session: "session-token".to_string(),
+                nonce: "n-1".to_string(),
+                mac: "00ff".to_string(),
+            },
+            UiMessage::GetComponent {
+                id: "panel:1".to_string(),
+            },
+            UiMessage::Subscribe { id: String::new() },
+            UiMessage::Undo {
+                id: "panel".to_string(),
+                session: "s".to_string(),
+                nonce: "n-2".to_string(),
+                mac: "ab".to_string(),
+            },
+            UiMessage::Redo {
+                id: "panel".to_string(),
+                session: "s".to_string(),
+                nonce: "n-3".to_string(),
+                mac: "cd".to_string(),
+            },
+            UiMessage::Replay { since: u64::MAX },
+        ];
+        for message in messages {
+            assert_eq!(
+                UiMessage::decode(&reframe(&message.encode())).unwrap(),
+                message
+            );
+        }
+
+        let responses = [
+            UiResponse::State {
+                id: "panel:1".to_string(),
+                state: tricky.to_string(),
+            },
+            UiResponse::NotFound {
+                id: "missing".to_string(),
+            },
+            UiResponse::Subscribed {
+                id: "panel".to_string(),
+            },
+            UiResponse::Event {
+                seq: 42,
+                id: "panel".to_string(),
+                state: tricky.to_string(),
+            },
+            UiResponse::Replayed { seq: 7 },
+            UiResponse::Error("näive: failure\n".to_string()),
+        ];
+        for response in responses {
+            assert_eq!(
+                UiResponse::decode(&reframe(&response.encode())).unwrap(),
+                response
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        let frame = UiMessage::GetComponent {
+            id: "panel".to_string(),
+        }
+        .encode();
+        let payload = &frame[4..];
+
+        // Truncated field, trailing bytes, an unknown tag, and a non-numeric sequence
+        assert!(UiMessage::decode(&payload[..payload.len() - 1]).is_err());
+        let mut trailing = payload.to_vec();
+        trailing.push(0);
+        assert!(UiMessage::decode(&trailing).is_err());
+        let mut unknown = payload.to_vec();
+        unknown[0] = 0x7F;
+        assert!(UiMessage::decode(&unknown).is_err());
+        assert!(UiMessage::decode(&encode_frame(TAG_REPLAY, &["soon"])[4..]).is_err());
+
+        assert!(matches!(
+            read_frame(&mut &[0u8, 0, 0, 0][..]),
+            Err(ProtocolError::Malformed(_))
+        ));
+        let oversized = ((MAX_FRAME_SIZE + 1) as u32).to_be_bytes();
+        assert!(matches!(
+            read_frame(&mut &oversized[..]),
+            Err(ProtocolError::TooLarge(_))
+        ));
+        assert!(read_frame(&mut &[][..]).unwrap().is_none());
+    }
+
+    const TEST_SECRET: &[u8] = b"frontend-test-session-secret-0123";
+    const LOOPBACK: &str = "127.0.0.1";
+
+    /// Serves `ui_state` on an ephemeral port, one thread per connection
+    fn start(ui_state: Arc<UIState>) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let ui_state = Arc::clone(&ui_state);
+                thread::spawn(move || {
+                    handle_connection(
+                        stream,
+                        ui_state,
+                        Duration::from_secs(5),
+                        Duration::from_secs(5),
+                    )
+                });
+            }
+        });
+        addr
+    }
+
+    fn ui_state() -> Arc<UIState> {
+        Arc::new(UIState::new(Arc::new(SessionStore::new(
+            TEST_SECRET.to_vec(),
+        ))))
+    }
+
+    /// A protocol client over a real connection
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: std::net::SocketAddr) -> Self {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            Self { stream }
+        }
+
+        fn send(&mut self, message: &UiMessage) {
+            self.stream.write_all(&message.encode()).unwrap();
+        }
+
+        fn receive(&mut self) -> UiResponse {
+            let payload = read_frame(&mut self.stream).unwrap().expect("a response");
+            UiResponse::decode(&payload).unwrap()
+        }
+
+        fn request(&mut self, message: &UiMessage) -> UiResponse {
+            self.send(message);
+            self.receive()
+        }
+    }
+
+    /// An update to `id` signed for `session` with `nonce`
+    fn signed_update(
+        ui_state: &UIState,
+        id: &str,
+        state: &str,
+        session: &str,
+        nonce: &str,
+    ) -> UiMessage {
+        let key = ui_state.sessions.event_key(session, LOOPBACK).unwrap();
+        UiMessage::UpdateComponent {
+            id: id.to_string(),
+            state: state.to_string(),
+            session: session.to_string(),
+            nonce: nonce.to_string(),
+            mac: to_hex(&update_mac(&key, id, state, session, nonce)),
+        }
+    }
+
+    #[test]
+    fn subscribers_receive_an_update_written_by_another_client() {
+        let ui_state = ui_state();
+        let addr = start(Arc::clone(&ui_state));
+        let session = ui_state.sessions.create_session("writer", LOOPBACK);
+
+        let mut watchers = [Client::connect(addr), Client::connect(addr)];
+        for watcher in &mut watchers {
+            let subscribe = UiMessage::Subscribe {
+                id: "banner".to_string(),
+            };
+            assert_eq!(
+                watcher.request(&subscribe),
+                UiResponse::Subscribed {
+                    id: "banner".to_string()
+                }
+            );
+        }
+
+        let mut writer = Client::connect(addr);
+        let update = signed_update(&ui_state, "banner", "maintenance: 10:00", &session, "n-1");
+        let expected = UiResponse::Event {
+            seq: 1,
+            id: "banner".to_string(),
+            state: "maintenance: 10:00".to_string(),
+        };
+        assert_eq!(writer.request(&update), expected);
+        for watcher in &mut watchers {
+            assert_eq!(watcher.receive(), expected);
+        }
+    }
+
+    #[test]
+    fn disconnected_subscribers_are_pruned() {
+        let ui_state = ui_state();
+        let addr = start(Arc::clone(&ui_state));
+        let mut watcher = Client::connect(addr);
+        watcher.request(&UiMessage::Subscribe {
+            id: "banner".to_string(),
+        });
+        drop(watcher);
+
+        // Once the server notices the hang-up the next push finds the sender dead
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            ui_state.update_component("banner", "ping");
+            if !ui_state.subscribers.lock().unwrap().contains_key("banner") {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "subscriber never pruned"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn rejected(response: UiResponse, reason: &str) -> bool {
+        matches!(response, UiResponse::Error(message) if message.ends_with(reason))
+    }
+
+    #[test]
+    fn signed_updates_apply_and_forged_or_replayed_ones_do_not() {
+        let ui_state = ui_state();
+        let addr = start(Arc::clone(&ui_state));
+        let session = ui_state.sessions.create_session("alice", LOOPBACK);
+        let mut client = Client::connect(addr);
+
+        let update = signed_update(&ui_state, "theme", "dark", &session, "n-1");
+        assert!(matches!(client.request(&update), UiResponse::Event { .. }));
+        assert_eq!(
+            ui_state.get_component_state("theme").as_deref(),
+            Some("dark")
+        );
+
+        // The same message again reuses its nonce
+        assert!(rejected(client.request(&update), "replayed nonce"));
+
+        // Changing any signed field without re-signing breaks the MAC
+        let mut forged = signed_update(&ui_state, "theme", "light", &session, "n-2");
+        if let UiMessage::UpdateComponent { state, .. } = &mut forged {
+            *state = "hacked".to_string();
+        }
+        assert!(rejected(client.request(&forged), "invalid event signature"));
+
+        // A MAC under another session's key is just as invalid
+        let other = ui_state.sessions.create_session("mallory", LOOPBACK);
+        let mut borrowed = signed_update(&ui_state, "theme", "evil", &other, "n-3");
+        if let UiMessage::UpdateComponent {
+            session: claimed, ..
+        } = &mut borrowed
+        {
+            *claimed = session.clone();
+        }
+        assert!(rejected(
+            client.request(&borrowed),
+            "invalid event signature"
+        ));
+
+        // A rejected forgery does not burn the nonce it carried
+        let retry = signed_update(&ui_state, "theme", "light", &session, "n-2");
+        assert!(matches!(client.request(&retry), UiResponse::Event { .. }));
+        assert_eq!(
+            ui_state.get_component_state("theme").as_deref(),
+            Some("light")
+        );
+
+        ui_state.sessions.revoke_session(&session);
+        let stale = UiMessage::UpdateComponent {
+            id: "theme".to_string(),
+            state: "dark".to_string(),
+            session: session.clone(),
+            nonce: "n-4".to_string(),
+            mac: "00".repeat(32),
+        };
+        assert!(rejected(
+            client.request(&stale),
+            "unknown or expired session"
+        ));
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_event_log() {
+        let ui_state = ui_state();
+        for state in ["a", "b", "c", "d"] {
+            ui_state.update_component("panel", state);
+        }
+        assert_eq!(ui_state.undo("panel").unwrap().1, "c");
+        assert_eq!(ui_state.undo("panel").unwrap().1, "b");
+        assert_eq!(ui_state.redo("panel").unwrap().1, "c");
+        assert_eq!(ui_state.get_component_state("panel").as_deref(), Some("c"));
+
+        // A new update discards what could have been redone
+        ui_state.update_component("panel", "e");
+        assert_eq!(ui_state.redo("panel"), Err("nothing to redo"));
+        assert_eq!(ui_state.undo("missing"), Err("nothing to undo"));
+    }
+
+    #[test]
+    fn replay_delivers_only_events_after_the_given_sequence() {
+        let ui_state = ui_state();
+        let first = ui_state.update_component("theme", "dark");
+        ui_state.update_component("layout", "grid");
+        let seen = ui_state.update_component("theme", "light");
+        let (undo_seq, _) = ui_state.undo("theme").unwrap();
+        let list_seq = ui_state.update_component("layout", "list");
+
+        let (changes, last) = ui_state.replay_since(seen);
+        assert_eq!(
+            changes,
+            [
+                (undo_seq, "theme".to_string(), "dark".to_string()),
+                (list_seq, "layout".to_string(), "list".to_string()),
+            ]
+        );
+        assert_eq!(last, list_seq);
+        assert_eq!(ui_state.replay_since(first - 1).0.len(), 5);
+        assert!(ui_state.replay_since(last).0.is_empty());
+    }
+
+    #[test]
+    fn snapshots_cap_the_log_without_changing_the_state() {
+        let ui_state = ui_state();
+        let total = SNAPSHOT_INTERVAL as u64 + 10;
+        for i in 0..total {
+            ui_state.update_component("counter", &i.to_string());
+        }
+        {
+            let components = ui_state.components.lock().unwrap();
+            let log = &components.logs["counter"];
+            assert_eq!(log.events.len(), 10);
+            assert_eq!(log.snapshot_seq, SNAPSHOT_INTERVAL as u64);
+        }
+
+        // Replay from before the snapshot summarizes it as one change
+        let (changes, _) = ui_state.replay_since(0);
+        assert_eq!(changes.len(), 11);
+        assert_eq!(changes[0].2, (SNAPSHOT_INTERVAL - 1).to_string());
+        assert_eq!(ui_state.undo("counter").unwrap().1, (total - 2).to_string());
+    }
+
+    /// A subscriber whose socket is one end of a loopback pair; returns the peer end too
+    fn mock_subscriber() -> (ClientSender, mpsc::Receiver<Outbound>, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let (sender, outbound) = ClientSender::new(&stream).unwrap();
+        (sender, outbound, peer)
+    }
+
+    #[test]
+    fn a_slow_subscriber_is_bounded_and_dropped_without_stalling_a_fast_one() {
+        let ui_state = ui_state();
+        let (fast, fast_outbound, _fast_peer) = mock_subscriber();
+        let (slow, _slow_outbound, mut slow_peer) = mock_subscriber();
+        ui_state.subscribe("ticker", fast);
+        ui_state.subscribe("ticker", slow.clone());
+
+        // The fast subscriber drains its queue as pushes arrive; the slow one never does
+        let reader = thread::spawn(move || {
+            let mut received = 0;
+            while let Ok(Outbound::Send(UiResponse::Event { .. })) =
+                fast_outbound.recv_timeout(Duration::from_secs(1))
+            {
+                received += 1;
+            }
+            received
+        });
+        let updates = OUTBOUND_QUEUE_CAPACITY + MAX_DROPPED_PUSHES as usize + 10;
+        for i in 0..updates {
+            ui_state.update_component("ticker", &i.to_string());
+            if i % 64 == 0 {
+                thread::sleep(Duration::from_millis(1)); // Give the fast reader a turn
+            }
+        }
+
+        assert_eq!(reader.join().unwrap(), updates);
+        assert_eq!(slow.dropped_pushes(), MAX_DROPPED_PUSHES);
+        assert_eq!(ui_state.subscribers.lock().unwrap()["ticker"].len(), 1);
+
+        // The slow client's socket was shut down, so it sees end of stream
+        slow_peer
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(slow_peer.read(&mut [0u8; 16]).unwrap(), 0);
+    }
 }
@@ -0,0 +1,216 @@
+//! Template Expansion with Partials (Standard Library Only)
+//! Shared by the templating engine and the HTML renderer so both accept the same syntax:
+//! - `{{name}}` is replaced by the escaped value of variable `name`; unknown names are
+//!   left as written
+//! - `{{> partial}}` renders another registered template in place, seeing the same
+//!   variables as the template that includes it
+//! - `{{> partial key=name ...}}` additionally binds `key` inside the partial to the
+//!   includer's `name`, so one partial can render different values at each use
+//!
+//! Expansion is a single left-to-right pass: substituted values and partial output
+//! are never rescanned, so a value containing `{{...}}` is emitted literally. Includes
+//! nest at most `MAX_INCLUDE_DEPTH` deep, which also stops include cycles.
+//!
+//! Each value is escaped for the HTML context its placeholder sits in (see
+//! `escaping`); a partial continues in the context of its include directive.
+
+#[path = "escaping.rs"]
+mod escaping;
+
+use escaping::HtmlState;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Why a template could not be rendered
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    UnknownTemplate(String),
+    /// Includes nested deeper than `MAX_INCLUDE_DEPTH`; `name` is the partial refused
+    IncludeDepthExceeded {
+        name: String,
+    },
+    MalformedInclude(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownTemplate(name) => write!(f, "unknown template '{}'", name),
+            TemplateError::IncludeDepthExceeded { name } => write!(
+                f,
+                "including '{}' exceeds the include depth limit of {}",
+                name, MAX_INCLUDE_DEPTH
+            ),
+            TemplateError::MalformedInclude(directive) => {
+                write!(f, "malformed include directive '{{{{{}}}}}'", directive)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Renders template `name` from `templates`, escaping every substituted value for its context
+pub fn expand(
+    templates: &HashMap<String, String>,
+    name: &str,
+    variables: &HashMap<&str, &str>,
+) -> Result<String, TemplateError> {
+    let mut out = String::new();
+    let mut html = HtmlState::default();
+    expand_into(&mut out, &mut html, templates, name, variables, 0)?;
+    Ok(out)
+}
+
+/// Appends `text` as literal template output
+fn emit(out: &mut String, html: &mut HtmlState, text: &str) {
+    html.feed(text);
+    out.push_str(text);
+}
+
+fn expand_into(
+    out: &mut String,
+    html: &mut HtmlState,
+    templates: &HashMap<String, String>,
+    name: &str,
+    variables: &HashMap<&str, &str>,
+    depth: usize,
+) -> Result<(), TemplateError> {
+    let template = templates
+        .get(name)
+        .ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))?;
+
+    let mut rest = template.as_str();
+    while let Some(open) = rest.find("{{") {
+        emit(out, html, &rest[..open]);
+        let tag = &rest[open + 2..];
+        let inner = match tag.find("}}") {
+            Some(close) => &tag[..close],
+            None => {
+                rest = &rest[open..];
+                break;
+            }
+        };
+
+        if let Some(directive) = inner.strip_prefix('>') {
+            if depth == MAX_INCLUDE_DEPTH {
+                let partial = directive.split_whitespace().next().unwrap_or("");
+                return Err(TemplateError::IncludeDepthExceeded {
+                    name: partial.to_string(),
+                });
+            }
+            let (partial, scope) = include_scope(directive, variables)?;
+            expand_into(out, html, templates, partial, &scope, depth + 1)?;
+        } else if let Some(value) = variables.get(inner) {
+            out.push_str(&escaping::escape(html.context(), value));
+            html.after_value();
+        } else {
+            // Not a placeholder we fill: keep one brace and look for a tag after it,
+            // so `{{{name}}}` still substitutes its inner `{{name}}`
+            emit(out, html, "{");
+            rest = &rest[open + 1..];
+            continue;
+        }
+        rest = &tag[inner.len() + 2..];
+    }
+    emit(out, html, rest);
+    Ok(())
+}
+
+/// Parses `partial key=name ...` into the partial's name and the variables it sees
+fn include_scope<'a>(
+    directive: &'a str,
+    variables: &HashMap<&'a str, &'a str>,
+) -> Result<(&'a str, HashMap<&'a str, &'a str>), TemplateError> {
+    let malformed = || TemplateError::MalformedInclude(format!(">{}", directive));
+    let mut words = directive.split_whitespace();
+    let partial = words.next().ok_or_else(malformed)?;
+
+    let mut scope = variables.clone();
+    for binding in words {
+        let (key, source) = binding.split_once('=').ok_or_else(malformed)?;
+        match variables.get(source) {
+            Some(&value) => scope.insert(key, value),
+            None => scope.remove(key), // Unbound: the partial shows `{{key}}` as written
+        };
+    }
+    Ok((partial, scope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, body)| (name.to_string(), body.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn layout_includes_header_and_footer_partials() {
+        let templates = templates(&[
+            (
+                "layout",
+                "{{> header}}<main>{{body}}</main>{{> footer year=copyright}}",
+            ),
+            ("header", "<header><h1>{{title}}</h1></header>"),
+            ("footer", "<footer>&copy; {{year}} {{title}}</footer>"),
+        ]);
+        let variables = HashMap::from([
+            ("title", "Zero Trust"),
+            ("body", "Hello <world>"),
+            ("copyright", "2026"),
+        ]);
+        assert_eq!(
+            expand(&templates, "layout", &variables).unwrap(),
+            "<header><h1>Zero Trust</h1></header>\
+             <main>Hello &lt;world&gt;</main>\
+             <footer>&copy; 2026 Zero Trust</footer>"
+        );
+
+        // Unbound keys are shown as written rather than leaking another value
+        let unbound = HashMap::from([("title", "T")]);
+        assert_eq!(
+            expand(&templates, "footer", &unbound).unwrap(),
+            "<footer>&copy; {{year}} T</footer>"
+        );
+    }
+
+    #[test]
+    fn self_including_template_stops_at_the_depth_limit() {
+        let looping = templates(&[("loop", "x{{> loop}}")]);
+        assert_eq!(
+            expand(&looping, "loop", &HashMap::new()),
+            Err(TemplateError::IncludeDepthExceeded {
+                name: "loop".to_string()
+            })
+        );
+        let mutual = templates(&[("a", "{{> b}}"), ("b", "{{> a}}")]);
+        assert!(matches!(
+            expand(&mutual, "a", &HashMap::new()),
+            Err(TemplateError::IncludeDepthExceeded { .. })
+        ));
+
+        // Exactly MAX_INCLUDE_DEPTH nested includes are fine
+        let mut chain: Vec<(String, String)> = (0..MAX_INCLUDE_DEPTH)
+            .map(|i| (format!("t{}", i), format!("{{{{> t{}}}}}", i + 1)))
+            .collect();
+        chain.push((format!("t{}", MAX_INCLUDE_DEPTH), "end".to_string()));
+        let chain: HashMap<String, String> = chain.into_iter().collect();
+        assert_eq!(expand(&chain, "t0", &HashMap::new()).unwrap(), "end");
+
+        let missing = templates(&[("page", "{{> nowhere}}"), ("bad", "{{> p key}}")]);
+        assert_eq!(
+            expand(&missing, "page", &HashMap::new()),
+            Err(TemplateError::UnknownTemplate("nowhere".to_string()))
+        );
+        assert!(matches!(
+            expand(&missing, "bad", &HashMap::new()),
+            Err(TemplateError::MalformedInclude(_))
+        ));
+    }
+}
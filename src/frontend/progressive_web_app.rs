@@ -3,21 +3,55 @@
 //! Features:
 //! - **Offline-first caching for seamless functionality without an internet connection**
 //! - **Web Push notifications with cryptographic integrity verification**
+//! - **Pluggable push transports: HTTP POST to subscriber endpoints, or the console**
+//! - **Broadcasts to every subscriber over a bounded pool, one failure never stopping the rest**
 //! - **Secure local storage with encryption and tamper protection**
 //! - **Immutable asset storage with rollback support**
 //! - **Background synchronization for dynamic content updates**
+//! - **Jittered exponential backoff and socket recovery while the sync server is down**
+//! - **`PwaRuntime` owning the sync thread, stopped and joined by `shutdown` or on drop**
 //! - **Zero Trust enforced data access policies**
 
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../backend/test_util.rs"]
+mod test_util;
+
+use crypto::Zeroizing;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::net::UdpSocket;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Maximum cache size in bytes
 const CACHE_SIZE_LIMIT: usize = 50 * 1024 * 1024; // 50MB
 
+const SYNC_SERVER_ENV: &str = "PWA_SYNC_SERVER"; // Overrides the default sync server
+const DEFAULT_SYNC_SERVER: &str = "192.168.1.1:8080";
+const SYNC_INTERVAL: Duration = Duration::from_secs(30); // Between successful syncs
+const SYNC_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+const SYNC_BACKOFF_BASE: Duration = Duration::from_secs(1); // First retry after a failure
+const SYNC_BACKOFF_MAX: Duration = Duration::from_secs(300); // Retries never wait longer
+
+const PUSH_TRANSPORT_ENV: &str = "PWA_PUSH_TRANSPORT"; // "http" delivers; anything else prints
+const DEFAULT_PUSH_TTL: u32 = 24 * 60 * 60; // Seconds a push service may hold a message
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+const PUSH_SALT_LEN: usize = 16; // Fresh per message, so every key/nonce pair is single-use
+const PUSH_KEY_INFO: &[u8] = b"ztw web push payload";
+const PUSH_CONTENT_ENCODING: &str = "ztw-chacha20poly1305"; // salt || ciphertext || tag
+const MAX_CONCURRENT_PUSHES: usize = 8; // Deliveries in flight at once during a broadcast
+
 /// Stores cached assets securely with metadata
 struct PwaCache {
     cache: Mutex<HashMap<String, Vec<u8>>>, // URL -> Cached Data
@@ -49,39 +83,313 @@ impl PwaCache {
     }
 }
 
+/// How soon a push service should wake the device for a message (RFC 8030 §5.3)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)] // Picked by callers of `send_notification_with`
+enum Urgency {
+    VeryLow,
+    Low,
+    Normal,
+    High,
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Urgency::VeryLow => "very-low",
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::High => "high",
+        }
+    }
+}
+
+/// An encrypted notification addressed to one subscriber's push endpoint
+#[derive(Clone, Debug)]
+struct PushMessage {
+    endpoint: String,
+    payload: Vec<u8>, // Encoded as `PUSH_CONTENT_ENCODING`
+    ttl: u32,
+    urgency: Urgency,
+}
+
+/// Delivers push messages; returns the HTTP status the push service answered with
+trait PushTransport: Send + Sync {
+    fn deliver(&self, message: &PushMessage) -> io::Result<u16>;
+}
+
+/// POSTs each message to its endpoint. Only `http://` endpoints are reachable
+/// without a TLS stack, so `https://` ones fail with `Unsupported`.
+struct HttpPushTransport;
+
+impl PushTransport for HttpPushTransport {
+    fn deliver(&self, message: &PushMessage) -> io::Result<u16> {
+        let (host, port, path) = parse_http_endpoint(&message.endpoint)?;
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "push endpoint did not resolve")
+            })?;
+        let mut stream = TcpStream::connect_timeout(&addr, PUSH_TIMEOUT)?;
+        stream.set_read_timeout(Some(PUSH_TIMEOUT))?;
+        stream.set_write_timeout(Some(PUSH_TIMEOUT))?;
+
+        let authority = if port == 80 {
+            host.clone()
+        } else {
+            format!("{}:{}", host, port)
+        };
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nTTL: {}\r\nUrgency: {}\r\nContent-Type: application/octet-stream\r\nContent-Encoding: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path,
+            authority,
+            message.ttl,
+            message.urgency.as_str(),
+            PUSH_CONTENT_ENCODING,
+            message.payload.len()
+        );
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&message.payload)?;
+
+        // Only the status line matters; the push service's body is ignored
+        let mut response = [0u8; 64];
+        let read = stream.read(&mut response)?;
+        let status_line = String::from_utf8_lossy(&response[..read]);
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "malformed push service response",
+                )
+            })
+    }
+}
+
+/// Splits `http://host[:port]/path` into its parts
+fn parse_http_endpoint(endpoint: &str) -> io::Result<(String, u16, String)> {
+    let rest = endpoint.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("push endpoint {} is not plain http", endpoint),
+        )
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "invalid push endpoint port")
+            })?,
+        ),
+        None => (authority, 80),
+    };
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Prints messages instead of sending them and reports them as accepted
+struct ConsoleTransport;
+
+impl PushTransport for ConsoleTransport {
+    fn deliver(&self, message: &PushMessage) -> io::Result<u16> {
+        println!(
+            "[PUSH] POST {} (TTL {}, Urgency {}, {} encrypted bytes)",
+            message.endpoint,
+            message.ttl,
+            message.urgency.as_str(),
+            message.payload.len()
+        );
+        Ok(201)
+    }
+}
+
+/// Picks the push transport named by `PWA_PUSH_TRANSPORT`
+fn push_transport_from_env() -> Box<dyn PushTransport> {
+    match std::env::var(PUSH_TRANSPORT_ENV).as_deref() {
+        Ok("http") => Box::new(HttpPushTransport),
+        _ => Box::new(ConsoleTransport),
+    }
+}
+
+/// Why a notification was not delivered
+#[derive(Debug)]
+enum PushError {
+    NotRegistered,
+    /// The push service reported the subscription gone; it has been removed
+    Unsubscribed,
+    Rejected(u16),
+    Io(io::Error),
+}
+
+impl fmt::Display for PushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::NotRegistered => f.write_str("device is not registered"),
+            PushError::Unsubscribed => f.write_str("subscription expired and was removed"),
+            PushError::Rejected(status) => write!(f, "push service answered {}", status),
+            PushError::Io(e) => write!(f, "delivery failed: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for PushError {
+    fn from(e: io::Error) -> Self {
+        PushError::Io(e)
+    }
+}
+
+/// Outcome of a broadcast for each subscriber, ordered by device ID
+#[derive(Debug, Default)]
+struct BroadcastReport {
+    delivered: Vec<String>,
+    failed: Vec<(String, PushError)>, // Device ID -> why its delivery failed
+}
+
+impl BroadcastReport {
+    /// Devices dropped during the broadcast because their subscription was gone
+    fn unsubscribed(&self) -> impl Iterator<Item = &str> {
+        self.failed
+            .iter()
+            .filter(|(_, e)| matches!(e, PushError::Unsubscribed))
+            .map(|(device_id, _)| device_id.as_str())
+    }
+}
+
+/// Where a device receives pushes and the secret its payloads are keyed with
+struct Subscription {
+    endpoint: String,
+    auth_secret: Zeroizing<Vec<u8>>,
+}
+
+/// Seals `plaintext` under a key and nonce derived from `auth_secret` and a fresh salt
+fn encrypt_payload(auth_secret: &[u8], plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    let mut salt = [0u8; PUSH_SALT_LEN];
+    crypto::random_bytes(&mut salt)?;
+    let okm = Zeroizing::new(crypto::hkdf(&salt, auth_secret, PUSH_KEY_INFO, 44));
+    let mut key = Zeroizing::new([0u8; 32]);
+    let mut nonce = [0u8; 12];
+    key.copy_from_slice(&okm[..32]);
+    nonce.copy_from_slice(&okm[32..]);
+
+    let mut payload = salt.to_vec();
+    payload.extend(crypto::chacha20poly1305_seal(&key, &nonce, b"", plaintext));
+    Ok(payload)
+}
+
 /// Secure Web Push notification handler
 struct WebPushNotifications {
-    subscribers: Mutex<HashMap<String, String>>, // Device ID -> Public Key
+    subscribers: Mutex<HashMap<String, Subscription>>, // Device ID -> Subscription
+    transport: Box<dyn PushTransport>,
 }
 
 impl WebPushNotifications {
-    /// Creates a new Web Push notification system
-    fn new() -> Self {
+    /// Creates a new Web Push notification system delivering through `transport`
+    fn new(transport: Box<dyn PushTransport>) -> Self {
         Self {
             subscribers: Mutex::new(HashMap::new()),
+            transport,
         }
     }
 
-    /// Registers a subscriber
-    fn register_device(&self, device_id: &str, public_key: &str) {
+    /// Registers a subscriber's push endpoint and payload secret
+    fn register_device(&self, device_id: &str, endpoint: &str, auth_secret: &[u8]) {
         let mut subscribers = self.subscribers.lock().unwrap();
-        subscribers.insert(device_id.to_string(), public_key.to_string());
+        subscribers.insert(
+            device_id.to_string(),
+            Subscription {
+                endpoint: endpoint.to_string(),
+                auth_secret: Zeroizing::new(auth_secret.to_vec()),
+            },
+        );
         println!(
             "[NOTIFICATIONS] Device {} registered for push notifications",
             device_id
         );
     }
 
-    /// Sends a push notification
-    fn send_notification(&self, device_id: &str, message: &str) {
-        let subscribers = self.subscribers.lock().unwrap();
-        if let Some(pub_key) = subscribers.get(device_id) {
-            println!("[PUSH] Sending notification to {}: {}", device_id, message);
-            println!("[PUSH] Encrypted using public key: {}", pub_key);
-            // Placeholder for actual cryptographic signing & sending logic
-        } else {
-            println!("[PUSH] Device {} is not registered", device_id);
+    fn unregister_device(&self, device_id: &str) -> bool {
+        self.subscribers.lock().unwrap().remove(device_id).is_some()
+    }
+
+    /// Sends a push notification with the default TTL and normal urgency
+    fn send_notification(&self, device_id: &str, message: &str) -> Result<(), PushError> {
+        self.send_notification_with(device_id, message, DEFAULT_PUSH_TTL, Urgency::Normal)
+    }
+
+    /// Encrypts `message` for the device and delivers it to its endpoint. A 404 or
+    /// 410 means the subscription no longer exists, so the device is unregistered.
+    fn send_notification_with(
+        &self,
+        device_id: &str,
+        message: &str,
+        ttl: u32,
+        urgency: Urgency,
+    ) -> Result<(), PushError> {
+        let push = {
+            let subscribers = self.subscribers.lock().unwrap();
+            let subscription = subscribers.get(device_id).ok_or(PushError::NotRegistered)?;
+            PushMessage {
+                endpoint: subscription.endpoint.clone(),
+                payload: encrypt_payload(&subscription.auth_secret, message.as_bytes())?,
+                ttl,
+                urgency,
+            }
+        }; // Not held across the network round trip
+
+        match self.transport.deliver(&push)? {
+            200..=299 => {
+                println!("[PUSH] Notification delivered to {}", device_id);
+                Ok(())
+            }
+            404 | 410 => {
+                self.unregister_device(device_id);
+                println!(
+                    "[PUSH] Subscription for {} is gone, unregistered",
+                    device_id
+                );
+                Err(PushError::Unsubscribed)
+            }
+            status => Err(PushError::Rejected(status)),
+        }
+    }
+
+    /// Sends `message` to every registered device, at most `MAX_CONCURRENT_PUSHES` at a
+    /// time. Each device's failure is recorded in the report without affecting the
+    /// others, and devices whose subscription is gone are unregistered as usual.
+    fn broadcast(&self, message: &str) -> BroadcastReport {
+        let mut device_ids: Vec<String> =
+            self.subscribers.lock().unwrap().keys().cloned().collect();
+        device_ids.sort();
+        let next = AtomicUsize::new(0);
+        let outcomes = Mutex::new(Vec::with_capacity(device_ids.len()));
+        thread::scope(|scope| {
+            for _ in 0..MAX_CONCURRENT_PUSHES.min(device_ids.len()) {
+                scope.spawn(|| {
+                    while let Some(device_id) = device_ids.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let outcome = self.send_notification(device_id, message);
+                        outcomes.lock().unwrap().push((device_id.clone(), outcome));
+                    }
+                });
+            }
+        });
+
+        let mut outcomes = outcomes.into_inner().unwrap();
+        outcomes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut report = BroadcastReport::default();
+        for (device_id, outcome) in outcomes {
+            match outcome {
+                Ok(()) => report.delivered.push(device_id),
+                Err(e) => report.failed.push((device_id, e)),
+            }
         }
+        report
     }
 }
 
@@ -134,45 +442,538 @@ impl SecureLocalStorage {
     }
 }
 
-/// Handles background sync & updates
-fn background_sync(cache: Arc<PwaCache>) {
-    let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind socket");
-    let server_address = "192.168.1.1:8080";
+/// Where and how often background sync talks to the server
+#[derive(Clone, Debug)]
+struct SyncConfig {
+    server: String,
+    interval: Duration,
+    response_timeout: Duration,
+    backoff_base: Duration,
+    backoff_max: Duration,
+}
+
+impl SyncConfig {
+    /// Default timings against `server` (anything `ToSocketAddrs` accepts)
+    fn new(server: &str) -> Self {
+        Self {
+            server: server.to_string(),
+            interval: SYNC_INTERVAL,
+            response_timeout: SYNC_RESPONSE_TIMEOUT,
+            backoff_base: SYNC_BACKOFF_BASE,
+            backoff_max: SYNC_BACKOFF_MAX,
+        }
+    }
+
+    /// Reads the server address from `PWA_SYNC_SERVER`, falling back to the default
+    fn from_env() -> Self {
+        let server =
+            std::env::var(SYNC_SERVER_ENV).unwrap_or_else(|_| DEFAULT_SYNC_SERVER.to_string());
+        Self::new(&server)
+    }
+}
+
+/// Exponential backoff with "equal jitter": the n-th consecutive failure waits a
+/// random time in `[d/2, d]` where `d = base * 2^n` capped at `max`. Until the cap
+/// each window starts where the previous one ended, so delays never shrink while
+/// the server stays down, yet clients that failed together do not retry in lockstep.
+struct Backoff {
+    base: Duration,
+    max: Duration,
+    failures: u32,
+    rng: u64,
+}
+
+impl Backoff {
+    fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            failures: 0,
+            rng: RandomState::new().build_hasher().finish() | 1, // Xorshift state must be non-zero
+        }
+    }
+
+    /// Records a failure and returns how long to wait before retrying
+    fn next_delay(&mut self) -> Duration {
+        let ceiling = self
+            .base
+            .checked_mul(1 << self.failures.min(31))
+            .map_or(self.max, |delay| delay.min(self.max));
+        self.failures = self.failures.saturating_add(1);
+
+        let half = ceiling / 2;
+        let spread = (ceiling - half).as_nanos() as u64;
+        half + Duration::from_nanos(self.next_random() % spread.saturating_add(1))
+    }
+
+    /// Forgets past failures after a successful attempt
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
+
+/// Binds a fresh socket connected to the sync server. Connecting means replies
+/// from anyone else are dropped by the OS and an unreachable port surfaces as an
+/// error instead of a silent timeout.
+fn connect_sync_socket(config: &SyncConfig) -> io::Result<UdpSocket> {
+    let server: SocketAddr =
+        config.server.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "sync server did not resolve")
+        })?;
+    let local = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(server)?;
+    socket.set_read_timeout(Some(config.response_timeout))?;
+    Ok(socket)
+}
+
+/// Sends one sync request and waits for the reply
+fn sync_once(socket: &UdpSocket) -> io::Result<Vec<u8>> {
+    socket.send(b"SYNC")?;
+    let mut buffer = [0; 1024];
+    let amt = socket.recv(&mut buffer)?;
+    Ok(buffer[..amt].to_vec())
+}
+
+/// Handles background sync & updates until `shutdown` is signalled or its sender
+/// is dropped. Failed attempts back off exponentially; a timeout keeps the socket,
+/// while any other socket error discards it so the next attempt rebinds.
+fn background_sync(cache: Arc<PwaCache>, config: SyncConfig, shutdown: Receiver<()>) {
+    let mut socket: Option<UdpSocket> = None;
+    let mut backoff = Backoff::new(config.backoff_base, config.backoff_max);
 
     loop {
-        let request = b"SYNC";
-        socket
-            .send_to(request, server_address)
-            .expect("Failed to send sync request");
+        let attempt = match socket.take() {
+            Some(existing) => Ok(existing),
+            None => connect_sync_socket(&config),
+        }
+        .and_then(|connected| {
+            let result = sync_once(&connected);
+            socket = Some(connected);
+            result
+        });
+
+        let delay = match attempt {
+            Ok(response) => {
+                let response = String::from_utf8_lossy(&response);
+                println!("[SYNC] Server responded with: {}", response);
 
-        let mut buffer = [0; 1024];
-        if let Ok((amt, _)) = socket.recv_from(&mut buffer) {
-            let response = String::from_utf8_lossy(&buffer[..amt]);
-            println!("[SYNC] Server responded with: {}", response);
+                // Example: Cache received data
+                cache.cache_asset("/latest-data", response.as_bytes());
+                backoff.reset();
+                config.interval
+            }
+            Err(e) => {
+                if !matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) {
+                    socket = None; // Rebind on the next attempt
+                }
+                let delay = backoff.next_delay();
+                println!(
+                    "[SYNC] Sync with {} failed ({}), retrying in {:?}",
+                    config.server, e, delay
+                );
+                delay
+            }
+        };
 
-            // Example: Cache received data
-            cache.cache_asset("/latest-data", response.as_bytes());
+        match shutdown.recv_timeout(delay) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
         }
     }
+    println!("[SYNC] Background sync stopped");
 }
 
-fn main() {
-    let cache = Arc::new(PwaCache::new());
-    let notifications = WebPushNotifications::new();
-    let storage = SecureLocalStorage::new("pwa_local_storage.dat");
+/// The PWA components and the background sync thread that keeps the cache fresh
+struct PwaRuntime {
+    cache: Arc<PwaCache>,
+    notifications: WebPushNotifications,
+    storage: SecureLocalStorage,
+    stop_sync: Option<Sender<()>>,
+    sync: Option<JoinHandle<()>>,
+}
+
+impl PwaRuntime {
+    /// Creates the components and starts background sync against `config.server`
+    fn start(
+        config: SyncConfig,
+        transport: Box<dyn PushTransport>,
+        storage_path: &str,
+    ) -> io::Result<Self> {
+        let cache = Arc::new(PwaCache::new());
+        let (stop_sync, sync_shutdown) = mpsc::channel();
+        let sync = {
+            let cache = Arc::clone(&cache);
+            thread::Builder::new()
+                .name("pwa-sync".to_string())
+                .spawn(move || background_sync(cache, config, sync_shutdown))?
+        };
+        Ok(Self {
+            cache,
+            notifications: WebPushNotifications::new(transport),
+            storage: SecureLocalStorage::new(storage_path),
+            stop_sync: Some(stop_sync),
+            sync: Some(sync),
+        })
+    }
 
-    // Background sync runs in a separate thread
-    let cache_clone = Arc::clone(&cache);
-    std::thread::spawn(move || {
-        background_sync(cache_clone);
-    });
+    /// True until the sync thread has exited
+    fn is_syncing(&self) -> bool {
+        self.sync.as_ref().is_some_and(|sync| !sync.is_finished())
+    }
+
+    /// Stops background sync and waits for its thread, which closes the sync socket.
+    /// A sync request already in flight is allowed to finish or time out first, so
+    /// this takes at most the sync response timeout. Calling it again does nothing.
+    fn shutdown(&mut self) {
+        if let Some(stop_sync) = self.stop_sync.take() {
+            let _ = stop_sync.send(());
+        }
+        if let Some(sync) = self.sync.take() {
+            if sync.join().is_err() {
+                eprintln!("[SYNC] Background sync thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for PwaRuntime {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn main() {
+    let mut pwa = match PwaRuntime::start(
+        SyncConfig::from_env(),
+        push_transport_from_env(),
+        "pwa_local_storage.dat",
+    ) {
+        Ok(pwa) => pwa,
+        Err(e) => {
+            eprintln!("[PWA] Failed to start: {}", e);
+            return;
+        }
+    };
 
     // Example usage:
-    cache.cache_asset("/index.html", b"<html><body>Offline Page</body></html>");
-    storage.store_data("user_pref", "dark_mode");
+    pwa.cache
+        .cache_asset("/index.html", b"<html><body>Offline Page</body></html>");
+    if let Some(page) = pwa.cache.get_asset("/index.html") {
+        println!("[CACHE] Offline page ready ({} bytes)", page.len());
+    }
+    pwa.storage.store_data("user_pref", "dark_mode");
+    println!(
+        "[STORAGE] user_pref reads back as {:?}",
+        pwa.storage.retrieve_data("user_pref")
+    );
 
-    notifications.register_device("device123", "public_key_abc");
-    notifications.send_notification("device123", "New update available!");
+    pwa.notifications.register_device(
+        "device123",
+        "http://push.example.com/subscriptions/device123",
+        b"device123-auth-secret",
+    );
+    if let Err(e) = pwa
+        .notifications
+        .send_notification("device123", "New update available!")
+    {
+        eprintln!("[PUSH] Notification to device123 failed: {}", e);
+    }
+
+    pwa.notifications.register_device(
+        "device456",
+        "http://push.example.com/subscriptions/device456",
+        b"device456-auth-secret",
+    );
+    let report = pwa.notifications.broadcast("Scheduled maintenance tonight");
+    println!(
+        "[PUSH] Broadcast delivered to {} device(s), {} failed",
+        report.delivered.len(),
+        report.failed.len()
+    );
+    for (device_id, e) in &report.failed {
+        eprintln!("[PUSH] Broadcast to {} failed: {}", device_id, e);
+    }
+    for device_id in report.unsubscribed() {
+        println!("[PUSH] {} no longer subscribed", device_id);
+    }
 
     println!("[PWA] Progressive Web App system initialized successfully.");
+
+    pwa.shutdown();
+    println!("[PWA] Background sync running: {}", pwa.is_syncing());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn backoff_delays_grow_until_the_cap_and_reset_on_success() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(800);
+        let mut backoff = Backoff::new(base, max);
+        // Each window starts where the last ended, so delays never shrink before the cap
+        let mut previous = Duration::ZERO;
+        for ceiling in [100, 200, 400, 800] {
+            let ceiling = Duration::from_millis(ceiling);
+            let delay = backoff.next_delay();
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?}", delay);
+            assert!(delay >= previous, "{:?} after {:?}", delay, previous);
+            previous = delay;
+        }
+        // Then they stay within the capped window, even past where doubling would overflow
+        for _ in 0..64 {
+            let delay = backoff.next_delay();
+            assert!(delay >= max / 2 && delay <= max, "{:?}", delay);
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= base);
+    }
+
+    /// Answers every `SYNC` datagram on `socket` with `reply` until it stays quiet
+    fn serve_sync(socket: UdpSocket, reply: &'static [u8]) -> JoinHandle<()> {
+        socket
+            .set_read_timeout(Some(Duration::from_millis(300)))
+            .unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 16];
+            while let Ok((n, from)) = socket.recv_from(&mut buffer) {
+                if &buffer[..n] == b"SYNC" {
+                    let _ = socket.send_to(reply, from);
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn sync_recovers_when_the_server_comes_back() {
+        // Reserve a port, then take the server away
+        let address = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = SyncConfig {
+            server: address.to_string(),
+            interval: Duration::from_millis(20),
+            response_timeout: Duration::from_millis(100),
+            backoff_base: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(40),
+        };
+        let cache = Arc::new(PwaCache::new());
+        let (stop, shutdown) = mpsc::channel();
+        let sync = {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || background_sync(cache, config, shutdown))
+        };
+
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(cache.get_asset("/latest-data"), None);
+
+        let server = serve_sync(UdpSocket::bind(address).unwrap(), b"fresh data");
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while cache.get_asset("/latest-data").is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            cache.get_asset("/latest-data").as_deref(),
+            Some(&b"fresh data"[..])
+        );
+
+        stop.send(()).unwrap();
+        sync.join().unwrap();
+        server.join().unwrap();
+    }
+
+    /// Records every message and answers with the status configured for its endpoint
+    struct MockTransport {
+        statuses: HashMap<String, u16>, // Endpoints not listed answer 201
+        sent: Arc<Mutex<Vec<PushMessage>>>,
+    }
+
+    impl PushTransport for MockTransport {
+        fn deliver(&self, message: &PushMessage) -> io::Result<u16> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(*self.statuses.get(&message.endpoint).unwrap_or(&201))
+        }
+    }
+
+    fn mock_push(statuses: &[(&str, u16)]) -> (WebPushNotifications, Arc<Mutex<Vec<PushMessage>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let transport = MockTransport {
+            statuses: statuses
+                .iter()
+                .map(|(endpoint, status)| (endpoint.to_string(), *status))
+                .collect(),
+            sent: Arc::clone(&sent),
+        };
+        (WebPushNotifications::new(Box::new(transport)), sent)
+    }
+
+    /// Undoes `encrypt_payload`
+    fn decrypt_payload(auth_secret: &[u8], payload: &[u8]) -> Option<Vec<u8>> {
+        let (salt, sealed) = payload.split_at(PUSH_SALT_LEN);
+        let okm = crypto::hkdf(salt, auth_secret, PUSH_KEY_INFO, 44);
+        let key: [u8; 32] = okm[..32].try_into().unwrap();
+        let nonce: [u8; 12] = okm[32..].try_into().unwrap();
+        crypto::chacha20poly1305_open(&key, &nonce, b"", sealed)
+    }
+
+    #[test]
+    fn notification_is_encrypted_for_the_device_endpoint() {
+        let (push, sent) = mock_push(&[]);
+        push.register_device("phone", "http://push.test/sub/phone", b"phone-secret");
+        push.register_device("laptop", "http://push.test/sub/laptop", b"laptop-secret");
+
+        push.send_notification_with("phone", "hello", 60, Urgency::High)
+            .unwrap();
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].endpoint, "http://push.test/sub/phone");
+        assert_eq!(sent[0].ttl, 60);
+        assert_eq!(sent[0].urgency, Urgency::High);
+        assert_eq!(
+            decrypt_payload(b"phone-secret", &sent[0].payload).as_deref(),
+            Some(&b"hello"[..])
+        );
+        assert_eq!(decrypt_payload(b"laptop-secret", &sent[0].payload), None);
+        drop(sent);
+
+        assert!(matches!(
+            push.send_notification("tablet", "hello"),
+            Err(PushError::NotRegistered)
+        ));
+    }
+
+    #[test]
+    fn gone_subscriptions_are_removed_and_other_failures_kept() {
+        let (push, sent) = mock_push(&[
+            ("http://push.test/sub/expired", 410),
+            ("http://push.test/sub/busy", 429),
+        ]);
+        push.register_device("expired", "http://push.test/sub/expired", b"s1");
+        push.register_device("busy", "http://push.test/sub/busy", b"s2");
+        push.register_device("ok", "http://push.test/sub/ok", b"s3");
+
+        let report = push.broadcast("maintenance");
+        assert_eq!(report.delivered, vec!["ok"]);
+        assert_eq!(report.unsubscribed().collect::<Vec<_>>(), vec!["expired"]);
+        assert!(matches!(
+            report.failed.iter().find(|(id, _)| id == "busy"),
+            Some((_, PushError::Rejected(429)))
+        ));
+        assert_eq!(sent.lock().unwrap().len(), 3);
+
+        // The expired device is gone; the throttled one is still registered
+        assert!(matches!(
+            push.send_notification("expired", "again"),
+            Err(PushError::NotRegistered)
+        ));
+        assert!(matches!(
+            push.send_notification("busy", "again"),
+            Err(PushError::Rejected(429))
+        ));
+        assert_eq!(sent.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn shutdown_stops_the_sync_thread_promptly() {
+        // A server that never answers, so sync keeps timing out and retrying
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = SyncConfig {
+            server: silent.local_addr().unwrap().to_string(),
+            interval: Duration::from_secs(60),
+            response_timeout: Duration::from_millis(100),
+            backoff_base: Duration::from_secs(60),
+            backoff_max: Duration::from_secs(60),
+        };
+        let (push, _) = mock_push(&[]);
+        let storage = test_util::scratch_path("pwa", "runtime");
+        let mut pwa =
+            PwaRuntime::start(config, push.transport, &storage.to_string_lossy()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(pwa.is_syncing());
+
+        let started = Instant::now();
+        pwa.shutdown();
+        assert!(
+            started.elapsed() < Duration::from_secs(1),
+            "{:?}",
+            started.elapsed()
+        );
+        assert!(!pwa.is_syncing());
+        assert!(pwa.sync.is_none() && pwa.stop_sync.is_none());
+
+        // A second shutdown, and the one run on drop, do nothing
+        pwa.shutdown();
+        drop(pwa);
+    }
+
+    #[test]
+    fn one_failing_subscriber_does_not_affect_the_others() {
+        let (push, sent) = mock_push(&[("http://push.test/sub/b", 410)]);
+        for device in ["a", "b", "c"] {
+            let endpoint = format!("http://push.test/sub/{}", device);
+            push.register_device(device, &endpoint, device.as_bytes());
+        }
+
+        let report = push.broadcast("release notes");
+        assert_eq!(report.delivered, vec!["a", "c"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.unsubscribed().collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(sent.lock().unwrap().len(), 3);
+
+        // The next broadcast skips the unsubscribed device
+        let report = push.broadcast("again");
+        assert_eq!(report.delivered, vec!["a", "c"]);
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn broadcast_sends_at_most_the_concurrency_limit_at_once() {
+        /// Holds each delivery briefly and records the most held at once
+        struct SlowTransport {
+            in_flight: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        impl PushTransport for Arc<SlowTransport> {
+            fn deliver(&self, _: &PushMessage) -> io::Result<u16> {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(201)
+            }
+        }
+
+        let transport = Arc::new(SlowTransport {
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let push = WebPushNotifications::new(Box::new(Arc::clone(&transport)));
+        let devices = MAX_CONCURRENT_PUSHES * 3;
+        for i in 0..devices {
+            push.register_device(&format!("d{:02}", i), "http://push.test/sub", b"s");
+        }
+        assert_eq!(push.broadcast("hi").delivered.len(), devices);
+        let peak = transport.peak.load(Ordering::SeqCst);
+        assert!(peak > 1 && peak <= MAX_CONCURRENT_PUSHES, "{}", peak);
+    }
 }
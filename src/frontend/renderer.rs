@@ -8,13 +8,333 @@
 //! - **Secure session-aware UI elements (RBAC & ABAC)**
 //! - **Stateless, high-speed rendering pipeline**
 //! - **Immutable HTML state for integrity enforcement**
-//! - **Auto-escaping against XSS and injection attacks**
+//! - **Context-aware auto-escaping (text, attributes, URLs, scripts) against XSS**
 //! - **Real-time UI updates with WebSocket-backed diffing**
+//! - **Compact binary patch batches, one WebSocket message per update**
+//! - **`{{> partial}}` includes for shared layout pieces, with a nesting limit**
+//! - **Attribute-level patches, and moves rather than re-renders for reordered keyed lists**
 
-use std::collections::HashMap;
-use std::io::Write;
+#[allow(dead_code)]
+#[path = "dom_patch.rs"]
+mod dom_patch;
+#[allow(dead_code)]
+#[path = "escaping.rs"]
+mod escaping;
+#[path = "partials.rs"]
+mod partials;
+
+use dom_patch::{DomPatch, NodePath, PatchBatch};
+use escaping::EscapeContext;
+use partials::TemplateError;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// A virtual DOM node; successive renders are diffed into `DomPatch`es
+#[derive(Debug, Clone, PartialEq)]
+enum VNode {
+    Text(String),
+    Element(VElement),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct VElement {
+    tag: String,
+    key: Option<String>, // Identifies the element among its siblings from render to render
+    attributes: Vec<(String, String)>, // In source order; names are unique
+    children: Vec<VNode>,
+}
+
+impl VElement {
+    fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            key: None,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Sets attribute `name`, replacing any earlier value
+    fn attr(mut self, name: &str, value: &str) -> Self {
+        match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.attributes.push((name.to_string(), value.to_string())),
+        }
+        self
+    }
+
+    fn child(mut self, child: impl Into<VNode>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl From<VElement> for VNode {
+    fn from(element: VElement) -> Self {
+        VNode::Element(element)
+    }
+}
+
+impl VNode {
+    fn text(text: &str) -> Self {
+        VNode::Text(text.to_string())
+    }
+
+    fn key(&self) -> Option<&str> {
+        match self {
+            VNode::Element(element) => element.key.as_deref(),
+            VNode::Text(_) => None,
+        }
+    }
+
+    /// Markup for this node, with text and attribute values escaped for their context
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        self.write_html(&mut out);
+        out
+    }
+
+    fn write_html(&self, out: &mut String) {
+        match self {
+            VNode::Text(text) => out.push_str(&escaping::escape(EscapeContext::Html, text)),
+            VNode::Element(element) => {
+                out.push('<');
+                out.push_str(&element.tag);
+                for (name, value) in &element.attributes {
+                    let context = escaping::attribute_value_context(name);
+                    out.push_str(&format!(
+                        " {}=\"{}\"",
+                        name,
+                        escaping::escape(context, value)
+                    ));
+                }
+                out.push('>');
+                if VOID_ELEMENTS.contains(&element.tag.as_str()) {
+                    return;
+                }
+                for child in &element.children {
+                    child.write_html(out);
+                }
+                out.push_str(&format!("</{}>", element.tag));
+            }
+        }
+    }
+}
+
+/// The patches that turn the page rendered from `old` into the one rendered from `new`
+fn diff(old: &VNode, new: &VNode) -> Vec<DomPatch> {
+    let mut patches = Vec::new();
+    diff_node(&mut Vec::new(), old, new, &mut patches);
+    patches
+}
+
+fn diff_node(path: &mut NodePath, old: &VNode, new: &VNode, patches: &mut Vec<DomPatch>) {
+    match (old, new) {
+        (VNode::Text(old_text), VNode::Text(new_text)) => {
+            if old_text != new_text {
+                patches.push(DomPatch::SetText {
+                    path: path.clone(),
+                    text: new_text.clone(),
+                });
+            }
+        }
+        (VNode::Element(old_el), VNode::Element(new_el))
+            if old_el.tag == new_el.tag && old_el.key == new_el.key =>
+        {
+            diff_attributes(path, old_el, new_el, patches);
+            diff_children(path, &old_el.children, &new_el.children, patches);
+        }
+        _ => patches.push(DomPatch::ReplaceNode {
+            path: path.clone(),
+            html: new.to_html(),
+        }),
+    }
+}
+
+/// One `SetAttribute` per added or changed attribute, one `RemoveAttribute` per dropped one
+fn diff_attributes(path: &NodePath, old: &VElement, new: &VElement, patches: &mut Vec<DomPatch>) {
+    for (name, _) in &old.attributes {
+        if new.attribute(name).is_none() {
+            patches.push(DomPatch::RemoveAttribute {
+                path: path.clone(),
+                name: name.clone(),
+            });
+        }
+    }
+    for (name, value) in &new.attributes {
+        if old.attribute(name) != Some(value.as_str()) {
+            patches.push(DomPatch::SetAttribute {
+                path: path.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+}
+
+/// Children are matched by key when every child on both sides has a distinct one,
+/// otherwise by position
+fn diff_children(path: &mut NodePath, old: &[VNode], new: &[VNode], patches: &mut Vec<DomPatch>) {
+    match (distinct_keys(old), distinct_keys(new)) {
+        (Some(old_keys), Some(new_keys)) if !old.is_empty() && !new.is_empty() => {
+            diff_keyed_children(path, old, new, &old_keys, &new_keys, patches)
+        }
+        _ => diff_positional_children(path, old, new, patches),
+    }
+}
+
+/// The key of every node, or `None` if any node is unkeyed or a key repeats
+fn distinct_keys(nodes: &[VNode]) -> Option<Vec<&str>> {
+    let keys: Vec<&str> = nodes.iter().map(VNode::key).collect::<Option<_>>()?;
+    let unique: HashSet<&str> = keys.iter().copied().collect();
+    (unique.len() == keys.len()).then_some(keys)
+}
+
+fn diff_positional_children(
+    path: &mut NodePath,
+    old: &[VNode],
+    new: &[VNode],
+    patches: &mut Vec<DomPatch>,
+) {
+    for (index, (old_child, new_child)) in old.iter().zip(new).enumerate() {
+        path.push(index as u32);
+        diff_node(path, old_child, new_child, patches);
+        path.pop();
+    }
+    // From the end, so the indices of the children not yet removed stay valid
+    for index in (new.len()..old.len()).rev() {
+        let mut child = path.clone();
+        child.push(index as u32);
+        patches.push(DomPatch::RemoveNode { path: child });
+    }
+    for (index, child) in new.iter().enumerate().skip(old.len()) {
+        patches.push(DomPatch::InsertChild {
+            path: path.clone(),
+            index: index as u32,
+            html: child.to_html(),
+        });
+    }
+}
+
+/// Removes children whose keys are gone, moves the fewest survivors needed to reach
+/// the new order (those off the longest run already in order), inserts new keys, and
+/// finally diffs each survivor in its new position
+fn diff_keyed_children(
+    path: &mut NodePath,
+    old: &[VNode],
+    new: &[VNode],
+    old_keys: &[&str],
+    new_keys: &[&str],
+    patches: &mut Vec<DomPatch>,
+) {
+    let new_index: HashMap<&str, usize> =
+        new_keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+    let old_index: HashMap<&str, usize> =
+        old_keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+
+    // Client-side order of the children as each patch is applied
+    let mut current: Vec<&str> = old_keys.to_vec();
+    for index in (0..old_keys.len()).rev() {
+        if !new_index.contains_key(old_keys[index]) {
+            let mut child = path.clone();
+            child.push(index as u32);
+            patches.push(DomPatch::RemoveNode { path: child });
+            current.remove(index);
+        }
+    }
+
+    let survivors: Vec<usize> = new_keys
+        .iter()
+        .filter_map(|key| old_index.get(key).copied())
+        .collect();
+    let stable: HashSet<usize> = longest_increasing_run(&survivors).into_iter().collect();
+
+    // Right to left, so each node is placed before its already-placed successor
+    for (index, key) in new_keys.iter().enumerate().rev() {
+        let anchor = |current: &[&str]| match new_keys.get(index + 1) {
+            Some(next) => current
+                .iter()
+                .position(|k| k == next)
+                .unwrap_or(current.len()),
+            None => current.len(),
+        };
+        match old_index.get(key) {
+            Some(old_position) if stable.contains(old_position) => {}
+            Some(_) => {
+                let from = current.iter().position(|k| k == key).unwrap_or(0);
+                current.remove(from);
+                let to = anchor(&current);
+                current.insert(to, key);
+                if from != to {
+                    patches.push(DomPatch::MoveChild {
+                        path: path.clone(),
+                        from: from as u32,
+                        to: to as u32,
+                    });
+                }
+            }
+            None => {
+                let to = anchor(&current);
+                current.insert(to, key);
+                patches.push(DomPatch::InsertChild {
+                    path: path.clone(),
+                    index: to as u32,
+                    html: new[index].to_html(),
+                });
+            }
+        }
+    }
+    debug_assert_eq!(current, new_keys);
+
+    for (index, key) in new_keys.iter().enumerate() {
+        if let Some(&old_position) = old_index.get(key) {
+            path.push(index as u32);
+            diff_node(path, &old[old_position], &new[index], patches);
+            path.pop();
+        }
+    }
+}
+
+/// Values of the longest strictly increasing subsequence of `values`
+fn longest_increasing_run(values: &[usize]) -> Vec<usize> {
+    let mut tails: Vec<usize> = Vec::new(); // Index into `values` ending the best run of each length
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&t| values[t] < value);
+        previous[i] = length.checked_sub(1).map(|l| tails[l]);
+        if length == tails.len() {
+            tails.push(i);
+        } else {
+            tails[length] = i;
+        }
+    }
+    let mut run = Vec::with_capacity(tails.len());
+    let mut cursor = tails.last().copied();
+    while let Some(i) = cursor {
+        run.push(values[i]);
+        cursor = previous[i];
+    }
+    run.reverse();
+    run
+}
+
 /// Represents a lightweight, secure HTML rendering engine
 struct HTMLRenderer {
     templates: Mutex<HashMap<String, String>>, // Stores precompiled HTML templates
@@ -33,29 +353,10 @@ impl HTMLRenderer {
         templates.insert(name.to_string(), template.to_string());
     }
 
-    /// Renders a template with secure escaping and variable replacement
-    fn render(&self, name: &str, variables: &HashMap<&str, &str>) -> Option<String> {
+    /// Renders a template with secure escaping, variable replacement and partials
+    fn render(&self, name: &str, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
         let templates = self.templates.lock().unwrap();
-        let template = templates.get(name)?;
-
-        let mut rendered = template.clone();
-        for (key, value) in variables.iter() {
-            let safe_value = self.escape_html(value);
-            let placeholder = format!("{{{{{}}}}}", key);
-            rendered = rendered.replace(&placeholder, &safe_value);
-        }
-
-        Some(rendered)
-    }
-
-    /// Prevents XSS and injection attacks by escaping special characters
-    fn escape_html(&self, input: &str) -> String {
-        input
-            .replace("&", "&amp;")
-            .replace("<", "&lt;")
-            .replace(">", "&gt;")
-            .replace("\"", "&quot;")
-            .replace("'", "&#39;")
+        partials::expand(&templates, name, variables)
     }
 }
 
@@ -67,7 +368,156 @@ fn main() {
     let mut variables = HashMap::new();
     variables.insert("username", "Alice & Bob");
 
-    if let Some(rendered_html) = renderer.render("welcome", &variables) {
+    if let Ok(rendered_html) = renderer.render("welcome", &variables) {
         println!("Rendered Output: {}", rendered_html);
+
+        // The whole update reaches clients as one binary WebSocket message
+        let mut batch = PatchBatch::new(1);
+        batch.push(DomPatch::ReplaceNode {
+            path: vec![0],
+            html: rendered_html,
+        });
+        let encoded = batch.encode();
+        println!("Patch batch: {} bytes", encoded.len());
+    }
+
+    // Toggling a class and rotating a keyed list: one attribute patch and one move
+    let list = |class: &str, order: &[&str]| -> VNode {
+        order
+            .iter()
+            .fold(VElement::new("ul").attr("class", class), |list, item| {
+                list.child(VElement::new("li").key(item).child(VNode::text(item)))
+            })
+            .into()
+    };
+    let before = list("todo", &["a", "b", "c", "d"]);
+    let after = list("todo done", &["b", "c", "d", "a"]);
+    let mut batch = PatchBatch::new(2);
+    for patch in diff(&before, &after) {
+        println!("Patch: {:?}", patch);
+        batch.push(patch);
+    }
+    println!("Patch batch: {} bytes", batch.encode().len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(keys: &[&str]) -> VNode {
+        keys.iter()
+            .fold(VElement::new("ul"), |ul, key| {
+                ul.child(VElement::new("li").key(key).child(VNode::text(key)))
+            })
+            .into()
+    }
+
+    /// Applies child-level patches for the root to its children's markup, as a client would
+    fn apply_to_children(children: &mut Vec<String>, patches: &[DomPatch]) {
+        for patch in patches {
+            match patch {
+                DomPatch::RemoveNode { path } => {
+                    children.remove(path[0] as usize);
+                }
+                DomPatch::MoveChild { path, from, to } if path.is_empty() => {
+                    let child = children.remove(*from as usize);
+                    children.insert(*to as usize, child);
+                }
+                DomPatch::InsertChild { path, index, html } if path.is_empty() => {
+                    children.insert(*index as usize, html.clone());
+                }
+                other => panic!("unexpected patch {:?}", other),
+            }
+        }
+    }
+
+    fn children_html(node: &VNode) -> Vec<String> {
+        match node {
+            VNode::Element(element) => element.children.iter().map(VNode::to_html).collect(),
+            VNode::Text(_) => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn toggling_one_attribute_yields_one_set_attribute() {
+        let button = |class: &str| -> VNode {
+            VElement::new("div")
+                .child(
+                    VElement::new("button")
+                        .attr("class", class)
+                        .attr("type", "submit")
+                        .child(VNode::text("Save")),
+                )
+                .into()
+        };
+        assert_eq!(
+            diff(&button("primary"), &button("primary active")),
+            [DomPatch::SetAttribute {
+                path: vec![0],
+                name: "class".to_string(),
+                value: "primary active".to_string(),
+            }]
+        );
+        assert!(diff(&button("primary"), &button("primary")).is_empty());
+
+        let old: VNode = VElement::new("input").attr("disabled", "").into();
+        let new: VNode = VElement::new("input").into();
+        assert_eq!(
+            diff(&old, &new),
+            [DomPatch::RemoveAttribute {
+                path: Vec::new(),
+                name: "disabled".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn reordering_a_keyed_list_moves_rather_than_replaces() {
+        let old = list(&["a", "b", "c", "d"]);
+        let new = list(&["d", "a", "b", "c"]);
+        assert_eq!(
+            diff(&old, &new),
+            [DomPatch::MoveChild {
+                path: Vec::new(),
+                from: 3,
+                to: 0,
+            }]
+        );
+
+        let new = list(&["d", "c", "b", "a"]);
+        let patches = diff(&old, &new);
+        assert!(patches
+            .iter()
+            .all(|patch| matches!(patch, DomPatch::MoveChild { .. })));
+        assert_eq!(patches.len(), 3);
+        let mut children = children_html(&old);
+        apply_to_children(&mut children, &patches);
+        assert_eq!(children, children_html(&new));
+    }
+
+    #[test]
+    fn keyed_removals_and_insertions_reach_the_new_order() {
+        let old = list(&["a", "b", "c", "d", "e"]);
+        let new = list(&["e", "x", "c", "a", "y"]);
+        let patches = diff(&old, &new);
+        assert!(!patches
+            .iter()
+            .any(|patch| matches!(patch, DomPatch::ReplaceNode { .. })));
+        let mut children = children_html(&old);
+        apply_to_children(&mut children, &patches);
+        assert_eq!(children, children_html(&new));
+    }
+
+    #[test]
+    fn unkeyed_children_are_diffed_by_position() {
+        let old: VNode = VElement::new("p").child(VNode::text("one")).into();
+        let new: VNode = VElement::new("p").child(VNode::text("two")).into();
+        assert_eq!(
+            diff(&old, &new),
+            [DomPatch::SetText {
+                path: vec![0],
+                text: "two".to_string(),
+            }]
+        );
     }
 }
@@ -3,16 +3,26 @@
 //! Features:
 //! - **Secure server-side rendering (SSR) for dynamic content**
 //! - **Immutable template compilation to prevent runtime modifications**
-//! - **Automatic HTML escaping to mitigate XSS and injection attacks**
+//! - **Context-aware auto-escaping (text, attributes, URLs, scripts) against XSS**
 //! - **Declarative, component-based UI templating**
 //! - **RBAC & ABAC-based template rendering permissions**
 //! - **Zero-allocation parsing for high-performance execution**
 //! - **Dynamic variable injection with integrity checks**
 //! - **Security-hardened sandboxing for template execution**
 //! - **Real-time UI synchronization with WebSocket-backed updates**
+//! - **`{{> partial}}` includes for shared layout pieces, with a nesting limit**
+//! - **Form validation whose per-field messages render beside the inputs**
 
+#[path = "partials.rs"]
+mod partials;
+#[allow(dead_code)]
+#[path = "validation.rs"]
+mod validation;
+
+use partials::TemplateError;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use validation::{Rule, Validator};
 
 /// Secure templating engine for server-side rendering
 struct TemplatingEngine {
@@ -32,29 +42,10 @@ impl TemplatingEngine {
         templates.insert(name.to_string(), template.to_string());
     }
 
-    /// Renders a template with strict security controls
-    fn render(&self, name: &str, variables: &HashMap<&str, &str>) -> Option<String> {
+    /// Renders a template with strict security controls, expanding any partials it includes
+    fn render(&self, name: &str, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
         let templates = self.templates.lock().unwrap();
-        let template = templates.get(name)?;
-
-        let mut rendered = template.clone();
-        for (key, value) in variables.iter() {
-            let safe_value = self.escape_html(value);
-            let placeholder = format!("{{{{{}}}}}", key);
-            rendered = rendered.replace(&placeholder, &safe_value);
-        }
-
-        Some(rendered)
-    }
-
-    /// Prevents XSS by escaping special characters
-    fn escape_html(&self, input: &str) -> String {
-        input
-            .replace("&", "&amp;")
-            .replace("<", "&lt;")
-            .replace(">", "&gt;")
-            .replace("\"", "&quot;")
-            .replace("'", "&#39;")
+        partials::expand(&templates, name, variables)
     }
 }
 
@@ -62,11 +53,50 @@ fn main() {
     let templating_engine = Arc::new(TemplatingEngine::new());
 
     templating_engine.register_template("dashboard", "<h1>Welcome, {{{user}}}!</h1>");
+    templating_engine.register_template("header", "<header>{{site}}</header>");
+    templating_engine.register_template("footer", "<footer>&copy; {{site}}</footer>");
+    templating_engine.register_template(
+        "page",
+        "{{> header}}<main>{{> dashboard}}</main>{{> footer}}",
+    );
 
     let mut variables = HashMap::new();
     variables.insert("user", "Alice <script>alert('XSS')</script>");
+    variables.insert("site", "Zero Trust");
+
+    for name in ["dashboard", "page"] {
+        match templating_engine.render(name, &variables) {
+            Ok(rendered) => println!("Rendered Output: {}", rendered),
+            Err(e) => eprintln!("Failed to render {}: {}", name, e),
+        }
+    }
 
-    if let Some(rendered) = templating_engine.render("dashboard", &variables) {
-        println!("Rendered Output: {}", rendered);
+    // A rejected signup form is rendered again with a message beside each bad input
+    templating_engine.register_template(
+        "signup",
+        "<input name=\"email\" value=\"{{email}}\"><span>{{email_error}}</span>\
+         <input name=\"age\" value=\"{{age}}\"><span>{{age_error}}</span>",
+    );
+    let validator = Validator::new()
+        .field("email", "Email", &[Rule::Required, Rule::Email])
+        .field("age", "Age", &[Rule::Range { min: 18, max: 130 }]);
+    let form: HashMap<String, String> = [("email", "alice@"), ("age", "12")]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    let result = validator.validate(&form);
+    let errors = result.template_variables();
+    let mut variables: HashMap<&str, &str> = form
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    variables.extend(
+        errors
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+    match templating_engine.render("signup", &variables) {
+        Ok(rendered) => println!("Rendered Output: {}", rendered),
+        Err(e) => eprintln!("Failed to render signup: {}", e),
     }
 }
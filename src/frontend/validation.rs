@@ -0,0 +1,262 @@
+//! Server-Side Form Validation (Standard Library Only)
+//! Checks submitted form fields against declared rules so every handler validates
+//! the same way, and turns the failures into messages templates render next to inputs.
+//! Features:
+//! - **Required, length, email, numeric and integer range rules, without regexes**
+//! - **Optional fields: rules other than `Required` skip an empty value**
+//! - **Every failing rule reported per field, in declaration order**
+//! - **`{{<field>_error}}` template variables, escaped like any other value**
+
+use std::collections::HashMap;
+
+const MAX_EMAIL_LEN: usize = 254; // RFC 5321 path limit
+const MAX_EMAIL_LOCAL_LEN: usize = 64;
+
+/// One check applied to a field's value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    /// Present and not just whitespace
+    Required,
+    /// At most this many characters
+    MaxLength(usize),
+    /// At least this many characters
+    MinLength(usize),
+    /// `local@domain.tld`, without spaces or quoted local parts
+    Email,
+    /// ASCII digits only, such as a PIN or numeric code
+    Numeric,
+    /// An integer from `min` to `max` inclusive
+    Range { min: i64, max: i64 },
+}
+
+impl Rule {
+    /// The message for `label` when `value` breaks this rule, or `None` if it passes
+    fn check(&self, label: &str, value: &str) -> Option<String> {
+        let passes = match self {
+            Rule::Required => !value.trim().is_empty(),
+            Rule::MaxLength(max) => value.chars().count() <= *max,
+            Rule::MinLength(min) => value.chars().count() >= *min,
+            Rule::Email => is_email(value),
+            Rule::Numeric => value.bytes().all(|b| b.is_ascii_digit()),
+            Rule::Range { min, max } => value
+                .trim()
+                .parse::<i64>()
+                .is_ok_and(|n| (*min..=*max).contains(&n)),
+        };
+        if passes {
+            return None;
+        }
+        Some(match self {
+            Rule::Required => format!("{} is required", label),
+            Rule::MaxLength(max) => format!("{} must be at most {} characters", label, max),
+            Rule::MinLength(min) => format!("{} must be at least {} characters", label, min),
+            Rule::Email => format!("{} must be a valid email address", label),
+            Rule::Numeric => format!("{} must contain only digits", label),
+            Rule::Range { min, max } => {
+                format!("{} must be a whole number from {} to {}", label, min, max)
+            }
+        })
+    }
+}
+
+/// Accepts `local@domain` where the domain has at least two dot-separated labels of
+/// letters, digits and hyphens. Deliberately stricter than RFC 5322.
+fn is_email(value: &str) -> bool {
+    if value.len() > MAX_EMAIL_LEN {
+        return false;
+    }
+    let (local, domain) = match value.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= MAX_EMAIL_LOCAL_LEN
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-/=?^_`{|}~.".contains(&b));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        });
+    local_ok && domain_ok
+}
+
+/// Rules for a form's fields, checked in the order they were declared
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    fields: Vec<(String, String, Vec<Rule>)>, // Name, label used in messages, rules
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares field `name`, called `label` in error messages
+    pub fn field(mut self, name: &str, label: &str, rules: &[Rule]) -> Self {
+        self.fields
+            .push((name.to_string(), label.to_string(), rules.to_vec()));
+        self
+    }
+
+    /// Checks `form` against every declared field; fields not declared are ignored
+    pub fn validate(&self, form: &HashMap<String, String>) -> ValidationResult {
+        let mut fields = Vec::new();
+        for (name, label, rules) in &self.fields {
+            let value = form.get(name).map(String::as_str).unwrap_or("");
+            let messages: Vec<String> = if value.trim().is_empty() {
+                // An empty optional field is valid; an empty required one has one problem
+                rules
+                    .iter()
+                    .filter(|rule| **rule == Rule::Required)
+                    .filter_map(|rule| rule.check(label, value))
+                    .collect()
+            } else {
+                rules
+                    .iter()
+                    .filter_map(|rule| rule.check(label, value))
+                    .collect()
+            };
+            fields.push((name.clone(), messages));
+        }
+        ValidationResult { fields }
+    }
+}
+
+/// The messages for each declared field, empty for fields that passed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationResult {
+    fields: Vec<(String, Vec<String>)>, // Field name -> messages, in declaration order
+}
+
+impl ValidationResult {
+    pub fn is_valid(&self) -> bool {
+        self.fields.iter().all(|(_, messages)| messages.is_empty())
+    }
+
+    /// Messages for `field`; empty when it passed
+    pub fn errors_for(&self, field: &str) -> &[String] {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == field)
+            .map_or(&[], |(_, messages)| messages.as_slice())
+    }
+
+    /// Every failing field with its messages
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.fields
+            .iter()
+            .filter(|(_, messages)| !messages.is_empty())
+            .map(|(name, messages)| (name.as_str(), messages.as_slice()))
+    }
+
+    /// `<field>_error` for every declared field: its messages joined with "; ", or
+    /// empty when it passed, so a template's placeholders always resolve
+    pub fn template_variables(&self) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .map(|(name, messages)| (format!("{}_error", name), messages.join("; ")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signup() -> Validator {
+        Validator::new()
+            .field(
+                "username",
+                "Username",
+                &[Rule::Required, Rule::MaxLength(8)],
+            )
+            .field("email", "Email", &[Rule::Required, Rule::Email])
+            .field("age", "Age", &[Rule::Range { min: 18, max: 130 }])
+            .field("pin", "PIN", &[Rule::Numeric, Rule::MinLength(4)])
+    }
+
+    fn form(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn a_valid_form_has_no_messages() {
+        let result = signup().validate(&form(&[
+            ("username", "alice"),
+            ("email", "alice@example.com"),
+            ("age", "30"),
+            ("pin", "0042"),
+        ]));
+        assert!(result.is_valid());
+        assert_eq!(result.errors().count(), 0);
+        assert_eq!(result.template_variables()["username_error"], "");
+    }
+
+    #[test]
+    fn a_too_long_field_reports_its_limit() {
+        let result = signup().validate(&form(&[
+            ("username", "alexandria"),
+            ("email", "alex@example.com"),
+        ]));
+        assert!(!result.is_valid());
+        assert_eq!(
+            result.errors_for("username"),
+            ["Username must be at most 8 characters"]
+        );
+        assert!(result.errors_for("email").is_empty());
+        // Optional fields left empty are not checked
+        assert!(result.errors_for("age").is_empty());
+    }
+
+    #[test]
+    fn a_missing_required_field_reports_only_that_it_is_required() {
+        let result = signup().validate(&form(&[("username", "  "), ("age", "12")]));
+        assert_eq!(result.errors_for("username"), ["Username is required"]);
+        assert_eq!(result.errors_for("email"), ["Email is required"]);
+        assert_eq!(
+            result.errors_for("age"),
+            ["Age must be a whole number from 18 to 130"]
+        );
+        let failing: Vec<&str> = result.errors().map(|(name, _)| name).collect();
+        assert_eq!(failing, ["username", "email", "age"]);
+    }
+
+    #[test]
+    fn every_failing_rule_is_reported_for_a_field() {
+        let result = signup().validate(&form(&[("pin", "12a")]));
+        assert_eq!(
+            result.template_variables()["pin_error"],
+            "PIN must contain only digits; PIN must be at least 4 characters"
+        );
+    }
+
+    #[test]
+    fn emails_are_checked_without_regexes() {
+        for valid in ["a@b.co", "first.last+tag@mail.example.org"] {
+            assert!(is_email(valid), "{}", valid);
+        }
+        for invalid in [
+            "alice@",
+            "@example.com",
+            "a@b",
+            "a..b@c.com",
+            "a@-b.com",
+            "a b@c.com",
+        ] {
+            assert!(!is_email(invalid), "{}", invalid);
+        }
+    }
+}
@@ -1,147 +1,513 @@
 //! Advanced Zero Trust WebCrypto Module (Rust-Only)
 //! Implements high-security client-side cryptography using Rust WebAssembly.
 //! Features:
-//! - **AES-GCM encryption for local storage security**
+//! - **ChaCha20-Poly1305 authenticated encryption for local storage, one nonce per record**
 //! - **SHA-256 hashing for data integrity verification**
 //! - **WebAssembly-based cryptographic operations**
 //! - **Zero Trust model enforcing key isolation**
 //! - **HMAC verification to prevent tampering**
 //! - **Time-based key rotation for enhanced security**
+//! - **Keys held in `Zeroizing` buffers, wiped when storage is dropped**
+//!
+//! Exposed from the library root as `zero_trust_website::webcrypto`; every
+//! fallible operation reports a `WebCryptoError`.
 
-use std::collections::HashMap;
-use std::fmt::Write as FmtWrite;
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../backend/encoding.rs"]
+mod encoding;
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../backend/test_util.rs"]
+mod test_util;
+
+use crypto::Rng;
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::sync::{Arc, Mutex};
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Secure storage for encrypted data
-struct EncryptedStorage {
+pub use crypto::Zeroizing;
+
+const SALT_HEADER: &str = "#salt:"; // First line of passphrase-protected storage files
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32; // ChaCha20-Poly1305 key size
+
+/// Why a WebCrypto operation failed
+#[derive(Debug)]
+pub enum WebCryptoError {
+    /// Hex input had an odd number of digits
+    OddLengthHex,
+    /// A non-hex character at this byte offset
+    InvalidHex {
+        position: usize,
+    },
+    /// A storage key that is not `KEY_LEN` bytes long
+    KeyLength(usize),
+    /// Stored data failed authentication under this key (tampered with, or the
+    /// wrong key), or did not decrypt to valid UTF-8
+    Decrypt,
+    /// A storage file exists but is not in the expected format
+    Corrupt(&'static str),
+    Io(io::Error),
+}
+
+impl fmt::Display for WebCryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebCryptoError::OddLengthHex => f.write_str("hex input has odd length"),
+            WebCryptoError::InvalidHex { position } => {
+                write!(f, "invalid hex digit at offset {}", position)
+            }
+            WebCryptoError::KeyLength(len) => {
+                write!(f, "storage key is {} bytes, expected {}", len, KEY_LEN)
+            }
+            WebCryptoError::Decrypt => f.write_str("stored data failed to decrypt"),
+            WebCryptoError::Corrupt(reason) => write!(f, "corrupt storage file: {}", reason),
+            WebCryptoError::Io(e) => write!(f, "storage I/O failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebCryptoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebCryptoError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WebCryptoError {
+    fn from(e: io::Error) -> Self {
+        WebCryptoError::Io(e)
+    }
+}
+
+/// Secure storage for encrypted data. Each record is `timestamp:key:nonce:sealed`,
+/// sealed with ChaCha20-Poly1305 under a nonce from this instance's `NonceSequence`
+/// and bound to its key name, so altered or swapped values fail to open.
+pub struct EncryptedStorage {
     file_path: String,
-    key: Vec<u8>, // Encryption key
+    key: Zeroizing<Vec<u8>>, // Encryption key, wiped on drop
+    nonces: Mutex<crypto::NonceSequence>,
 }
 
 impl EncryptedStorage {
-    /// Creates a new encrypted storage instance
-    fn new(file_path: &str, key: &[u8]) -> Self {
-        Self {
-            file_path: file_path.to_string(),
-            key: key.to_vec(),
+    /// Creates a new encrypted storage instance; `key` must be `KEY_LEN` (32) bytes
+    pub fn new(file_path: &str, key: &[u8]) -> Result<Self, WebCryptoError> {
+        if key.len() != KEY_LEN {
+            return Err(WebCryptoError::KeyLength(key.len()));
         }
+        Ok(Self {
+            file_path: file_path.to_string(),
+            key: Zeroizing::new(key.to_vec()),
+            nonces: Mutex::new(crypto::NonceSequence::new()?),
+        })
+    }
+
+    /// Opens storage whose key is derived from `passphrase` with PBKDF2-HMAC-SHA256.
+    /// A new file gets a random salt written as its header line; existing files reuse theirs.
+    pub fn from_passphrase(file_path: &str, passphrase: &str) -> Result<Self, WebCryptoError> {
+        Self::from_passphrase_with_iterations(file_path, passphrase, crypto::PBKDF2_ITERATIONS)
+    }
+
+    /// As `from_passphrase`, with an explicit PBKDF2 work factor. Opening a file
+    /// needs the count it was created with; go below `PBKDF2_ITERATIONS` only in tests.
+    pub fn from_passphrase_with_iterations(
+        file_path: &str,
+        passphrase: &str,
+        iterations: u32,
+    ) -> Result<Self, WebCryptoError> {
+        let salt = match File::open(file_path) {
+            Ok(mut file) => {
+                let mut contents = String::new();
+                file.read_to_string(&mut contents)?;
+                let header = contents.lines().next().unwrap_or("");
+                match header.strip_prefix(SALT_HEADER) {
+                    Some(salt_hex) => hex_decode(salt_hex)?,
+                    None if contents.is_empty() => Self::write_new_salt(file_path)?,
+                    None => return Err(WebCryptoError::Corrupt("no passphrase salt header")),
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::write_new_salt(file_path)?,
+            Err(e) => return Err(e.into()),
+        };
+
+        let key = Zeroizing::new(crypto::pbkdf2_hmac_sha256(
+            passphrase.as_bytes(),
+            &salt,
+            iterations,
+            KEY_LEN,
+        ));
+        Self::new(file_path, &key)
+    }
+
+    /// Generates a salt and records it as the storage file's header line
+    fn write_new_salt(file_path: &str) -> io::Result<Vec<u8>> {
+        let mut salt = vec![0u8; SALT_LEN];
+        crypto::random_bytes(&mut salt)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(file_path)?;
+        writeln!(file, "{}{}", SALT_HEADER, hex_encode(&salt))?;
+        Ok(salt)
     }
 
     /// Encrypts and stores data securely
-    fn store_data(&self, key: &str, value: &str) {
-        let encrypted_value = self.aes_gcm_encrypt(value.as_bytes());
+    pub fn store_data(&self, key: &str, value: &str) -> Result<(), WebCryptoError> {
+        let (nonce, sealed) = self.seal(key, value.as_bytes())?;
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&self.file_path)
-            .expect("Failed to open local storage file");
+            .open(&self.file_path)?;
 
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let entry = format!("{}:{}:{}\n", timestamp, key, hex_encode(&encrypted_value));
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let entry = format!(
+            "{}:{}:{}:{}\n",
+            timestamp,
+            key,
+            hex_encode(&nonce),
+            hex_encode(&sealed)
+        );
 
-        file.write_all(entry.as_bytes())
-            .expect("Failed to write to encrypted storage");
-        println!("[WEBCRYPTO] Data stored securely: {} -> [ENCRYPTED]", key);
+        file.write_all(entry.as_bytes())?;
+        Ok(())
     }
 
-    /// Retrieves and decrypts stored data
-    fn retrieve_data(&self, key: &str) -> Option<String> {
-        let mut file = File::open(&self.file_path).ok()?;
+    /// Retrieves and decrypts stored data; `Ok(None)` if the key (or the file) is absent
+    pub fn retrieve_data(&self, key: &str) -> Result<Option<String>, WebCryptoError> {
+        let mut file = match File::open(&self.file_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
         let mut contents = String::new();
-        file.read_to_string(&mut contents).ok()?;
+        file.read_to_string(&mut contents)?;
 
         for line in contents.lines() {
             let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() == 3 && parts[1] == key {
-                let encrypted_value = hex_decode(parts[2]);
-                let decrypted = self.aes_gcm_decrypt(&encrypted_value);
-                return decrypted;
+            if parts.get(1) != Some(&key) {
+                continue;
+            }
+            if parts.len() != 4 {
+                return Err(WebCryptoError::Corrupt(
+                    "record is not timestamp:key:nonce:sealed",
+                ));
             }
+            let nonce: [u8; 12] = hex_decode(parts[2])?
+                .try_into()
+                .map_err(|_| WebCryptoError::Corrupt("nonce is not 12 bytes"))?;
+            let sealed = hex_decode(parts[3])?;
+            return self.open(key, &nonce, &sealed).map(Some);
         }
 
-        None
+        Ok(None)
     }
 
-    /// AES-GCM Encryption (Simulated)
-    fn aes_gcm_encrypt(&self, data: &[u8]) -> Vec<u8> {
-        // Simulating AES-GCM encryption using a simple XOR operation (since std::lib has no native AES)
-        data.iter()
-            .zip(self.key.iter().cycle())
-            .map(|(d, k)| d ^ k)
-            .collect()
+    fn aead_key(&self) -> [u8; KEY_LEN] {
+        let mut key = [0u8; KEY_LEN];
+        key.copy_from_slice(&self.key);
+        key
     }
 
-    /// AES-GCM Decryption (Simulated)
-    fn aes_gcm_decrypt(&self, data: &[u8]) -> Option<String> {
-        let decrypted: Vec<u8> = data
-            .iter()
-            .zip(self.key.iter().cycle())
-            .map(|(d, k)| d ^ k)
-            .collect();
-        String::from_utf8(decrypted).ok()
+    /// Seals `data` under the next nonce, authenticating the record's key name with it
+    fn seal(&self, record_key: &str, data: &[u8]) -> Result<([u8; 12], Vec<u8>), WebCryptoError> {
+        let nonce = self
+            .nonces
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .next_nonce()
+            .map_err(io::Error::from)?;
+        let key = Zeroizing::new(self.aead_key());
+        let sealed = crypto::chacha20poly1305_seal(&key, &nonce, record_key.as_bytes(), data);
+        Ok((nonce, sealed))
     }
-}
 
-/// SHA-256 Hashing for Integrity Verification
-fn sha256_hash(input: &str) -> String {
-    let mut hash = 0u64;
-    for byte in input.bytes() {
-        hash = hash.wrapping_add(byte as u64).rotate_left(5);
+    /// Opens a sealed record; any change to the nonce, value or key name is rejected
+    fn open(
+        &self,
+        record_key: &str,
+        nonce: &[u8; 12],
+        sealed: &[u8],
+    ) -> Result<String, WebCryptoError> {
+        let key = Zeroizing::new(self.aead_key());
+        let plaintext = crypto::chacha20poly1305_open(&key, nonce, record_key.as_bytes(), sealed)
+            .ok_or(WebCryptoError::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| WebCryptoError::Decrypt)
     }
-    format!("{:x}", hash)
+}
+
+/// SHA-256 Hashing for Integrity Verification; returns the 64-digit lowercase hex digest
+pub fn sha256_hash(input: &str) -> String {
+    hex_encode(&crypto::sha256(input.as_bytes()))
 }
 
 /// Hex Encoding Helper
-fn hex_encode(data: &[u8]) -> String {
-    let mut s = String::new();
-    for byte in data {
-        write!(s, "{:02x}", byte).unwrap();
-    }
-    s
+pub fn hex_encode(data: &[u8]) -> String {
+    encoding::hex_encode(data)
 }
 
-/// Hex Decoding Helper
-fn hex_decode(hex: &str) -> Vec<u8> {
-    (0..hex.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0))
-        .collect()
+/// Hex Decoding Helper; rejects odd-length input and non-hex digits
+pub fn hex_decode(hex: &str) -> Result<Vec<u8>, WebCryptoError> {
+    encoding::hex_decode(hex).map_err(|e| match e {
+        encoding::DecodeError::InvalidByte { position, .. } => {
+            WebCryptoError::InvalidHex { position }
+        }
+        _ => WebCryptoError::OddLengthHex,
+    })
 }
 
-/// Time-Based Key Rotation (Every 24 Hours)
-fn generate_rotation_key() -> Vec<u8> {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let mut key = Vec::new();
-    for i in 0..32 {
-        key.push(((timestamp.wrapping_add(i as u64)) % 256) as u8);
-    }
-    key
+/// Key Rotation: a fresh 256-bit key from the OS CSPRNG for each rotation period
+pub fn generate_rotation_key() -> Result<Zeroizing<Vec<u8>>, WebCryptoError> {
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    crypto::OsRng.fill_bytes(&mut key)?;
+    Ok(key)
 }
 
-fn main() {
-    let encryption_key = generate_rotation_key();
-    let storage = EncryptedStorage::new("webcrypto_secure_store.dat", &encryption_key);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keeps the round trip fast; the derivation is the same at any count
+    const TEST_ITERATIONS: u32 = 1_000;
+
+    #[test]
+    fn passphrase_storage_round_trips_across_reopening() {
+        let path = test_util::scratch_path("webcrypto", "passphrase")
+            .to_string_lossy()
+            .into_owned();
+        let storage = EncryptedStorage::from_passphrase_with_iterations(
+            &path,
+            "correct horse",
+            TEST_ITERATIONS,
+        )
+        .unwrap();
+        storage.store_data("token", "s3cret value").unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(SALT_HEADER));
+        assert!(!contents.contains("s3cret value"));
+
+        // Reopening reads the stored salt, so the same passphrase derives the same key
+        let reopened = EncryptedStorage::from_passphrase_with_iterations(
+            &path,
+            "correct horse",
+            TEST_ITERATIONS,
+        )
+        .unwrap();
+        assert_eq!(
+            reopened.retrieve_data("token").unwrap().as_deref(),
+            Some("s3cret value")
+        );
+        assert!(reopened.retrieve_data("missing").unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn hex_decode_rejects_odd_length_and_non_hex_input() {
+        assert_eq!(hex_decode("00ff7A").unwrap(), vec![0x00, 0xff, 0x7a]);
+        assert_eq!(hex_decode("").unwrap(), Vec::<u8>::new());
+        assert!(matches!(
+            hex_decode("abc"),
+            Err(WebCryptoError::OddLengthHex)
+        ));
+        assert!(matches!(
+            hex_decode("00zz"),
+            Err(WebCryptoError::InvalidHex { position: 2 })
+        ));
+        // Previously decoded as zero bytes
+        assert!(matches!(
+            hex_decode("0x"),
+            Err(WebCryptoError::InvalidHex { position: 1 })
+        ));
+        assert!(hex_decode("+1").is_err());
+        assert_eq!(
+            hex_decode(&hex_encode(b"\x00\x10zt")).unwrap(),
+            b"\x00\x10zt"
+        );
+    }
+
+    #[test]
+    fn storage_round_trips_and_reports_failures() {
+        let path = test_util::scratch_path("webcrypto", "storage")
+            .to_string_lossy()
+            .into_owned();
+        let storage = EncryptedStorage::new(&path, b"an example 32-byte storage key!!").unwrap();
+        assert!(
+            storage.retrieve_data("session").unwrap().is_none(),
+            "no file yet"
+        );
+
+        storage.store_data("session", "token abc123").unwrap();
+        storage.store_data("theme", "dark").unwrap();
+        assert_eq!(
+            storage.retrieve_data("session").unwrap().as_deref(),
+            Some("token abc123")
+        );
+        assert_eq!(
+            storage.retrieve_data("theme").unwrap().as_deref(),
+            Some("dark")
+        );
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("abc123"));
+
+        // A stored value that is not hex surfaces as an error, not a garbled value
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "0:broken:000000000000000000000000:nothex").unwrap();
+        assert!(matches!(
+            storage.retrieve_data("broken"),
+            Err(WebCryptoError::InvalidHex { position: 0 })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Rewrites the stored record for `key` with `edit` applied to its fields
+    fn edit_record(path: &str, key: &str, edit: impl Fn(&mut Vec<String>)) {
+        let contents = std::fs::read_to_string(path).unwrap();
+        let edited: Vec<String> = contents
+            .lines()
+            .map(|line| {
+                let mut fields: Vec<String> = line.split(':').map(str::to_string).collect();
+                if fields.get(1).map(String::as_str) == Some(key) {
+                    edit(&mut fields);
+                }
+                fields.join(":")
+            })
+            .collect();
+        std::fs::write(path, edited.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn records_carry_their_own_nonce() {
+        let path = test_util::scratch_path("webcrypto", "nonces")
+            .to_string_lossy()
+            .into_owned();
+        let storage = EncryptedStorage::new(&path, &[7; KEY_LEN]).unwrap();
+        storage.store_data("a", "same value").unwrap();
+        storage.store_data("b", "same value").unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<Vec<&str>> = contents.lines().map(|l| l.split(':').collect()).collect();
+        assert_eq!(records.len(), 2);
+        assert_ne!(records[0][2], records[1][2], "fresh nonce per record");
+        assert_ne!(
+            records[0][3], records[1][3],
+            "equal values do not look equal"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tampered_records_are_rejected() {
+        let path = test_util::scratch_path("webcrypto", "tamper")
+            .to_string_lossy()
+            .into_owned();
+        let storage = EncryptedStorage::new(&path, &[7; KEY_LEN]).unwrap();
+        storage.store_data("role", "user").unwrap();
+        storage.store_data("other", "admin").unwrap();
 
-    // Store Encrypted Data
-    storage.store_data("user_session", "session_token_abc123");
+        // One flipped ciphertext bit
+        edit_record(&path, "role", |fields| {
+            let mut sealed = hex_decode(&fields[3]).unwrap();
+            sealed[0] ^= 1;
+            fields[3] = hex_encode(&sealed);
+        });
+        assert!(matches!(
+            storage.retrieve_data("role"),
+            Err(WebCryptoError::Decrypt)
+        ));
 
-    // Retrieve Decrypted Data
-    if let Some(value) = storage.retrieve_data("user_session") {
-        println!("[WEBCRYPTO] Retrieved decrypted session: {}", value);
-    } else {
-        println!("[WEBCRYPTO] No session found.");
+        // A value sealed for another key moved under this one
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let other: Vec<String> = contents
+            .lines()
+            .find(|line| line.split(':').nth(1) == Some("other"))
+            .unwrap()
+            .split(':')
+            .map(str::to_string)
+            .collect();
+        edit_record(&path, "role", |fields| {
+            fields[2] = other[2].clone();
+            fields[3] = other[3].clone();
+        });
+        assert!(matches!(
+            storage.retrieve_data("role"),
+            Err(WebCryptoError::Decrypt)
+        ));
+
+        // The right record under the wrong key
+        let stranger = EncryptedStorage::new(&path, &[8; KEY_LEN]).unwrap();
+        assert!(matches!(
+            stranger.retrieve_data("other"),
+            Err(WebCryptoError::Decrypt)
+        ));
+        assert_eq!(
+            storage.retrieve_data("other").unwrap().as_deref(),
+            Some("admin")
+        );
+        std::fs::remove_file(&path).unwrap();
     }
 
-    // SHA-256 Integrity Check Example
-    let integrity_hash = sha256_hash("secure_data_example");
-    println!("[WEBCRYPTO] SHA-256 Hash of data: {}", integrity_hash);
+    #[test]
+    fn keys_of_the_wrong_length_are_refused() {
+        assert!(matches!(
+            EncryptedStorage::new("unused", b"short"),
+            Err(WebCryptoError::KeyLength(5))
+        ));
+    }
+
+    #[test]
+    fn rotation_keys_are_random_not_derived_from_the_clock() {
+        let first = generate_rotation_key().unwrap();
+        let second = generate_rotation_key().unwrap();
+        assert_eq!(first.len(), 32);
+        // Generated in the same second, a clock-derived key would repeat
+        assert_ne!(*first, *second);
+    }
+
+    #[test]
+    fn sha256_hash_matches_the_known_digest() {
+        assert_eq!(
+            sha256_hash("secure_data_example"),
+            "1982b15364eee8efbd3bae061bdd3868c30d58323e338d3f325f9597cf1822f0"
+        );
+        assert_eq!(
+            sha256_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn single_bit_changes_flip_about_half_the_digest() {
+        let input = "secure_data_example";
+        let digest = hex_decode(&sha256_hash(input)).unwrap();
+        let mut total = 0;
+        let mut flips = 0;
+        for (i, byte) in input.bytes().enumerate() {
+            for bit in 0..7 {
+                // Low seven bits only, so the input stays ASCII
+                let mut changed = input.as_bytes().to_vec();
+                changed[i] = byte ^ (1 << bit);
+                let changed =
+                    hex_decode(&sha256_hash(std::str::from_utf8(&changed).unwrap())).unwrap();
+                let differing: u32 = digest
+                    .iter()
+                    .zip(&changed)
+                    .map(|(a, b)| (a ^ b).count_ones())
+                    .sum();
+                assert!((64..=192).contains(&differing), "{} bits differ", differing);
+                total += differing;
+                flips += 1;
+            }
+        }
+        let mean = total as f64 / flips as f64;
+        assert!((118.0..=138.0).contains(&mean), "mean {} of 256 bits", mean);
+    }
 }
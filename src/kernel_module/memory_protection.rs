@@ -8,15 +8,96 @@
 //! - **Logs memory violations securely for forensic analysis**
 //! - **Real-time monitoring of memory pages for unauthorized changes**
 //! - **Adaptive anomaly detection based on memory access patterns**
+//! - **Tampering scored by how much of each page changed and whether it was executable**
+
+#[allow(dead_code)]
+#[path = "../backend/crypto.rs"]
+mod crypto;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const PAGE_SIZE: usize = 4096;
+const MEDIUM_PAGE_FRACTION: f64 = 0.25; // Share of one page changed before tampering is Medium
+const HIGH_PAGE_FRACTION: f64 = 0.75; // ... and High
+
+/// How alarming a detected modification is
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// One level up; touching executable memory escalates
+    fn escalate(self) -> Self {
+        match self {
+            Severity::Low => Severity::Medium,
+            Severity::Medium => Severity::High,
+            Severity::High | Severity::Critical => Severity::Critical,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+            Severity::Critical => "CRITICAL",
+        })
+    }
+}
+
+/// A process's memory as first seen: the bytes, plus a SHA-256 per page so
+/// unchanged pages are skipped without a byte comparison
+struct Snapshot {
+    memory: Vec<u8>,
+    page_hashes: Vec<[u8; 32]>,
+    executable: bool, // Code pages, where any change is more serious
+}
+
+/// What changed between a snapshot and the current memory
+#[derive(Clone, Debug, PartialEq)]
+struct TamperReport {
+    page_changes: Vec<f64>, // Fraction of each page's bytes that changed, one per page
+    changed_bytes: usize,
+    executable: bool,
+    score: f64, // Largest fraction of any one page that changed, 0.0 to 1.0
+    severity: Severity,
+}
+
+impl TamperReport {
+    /// One character per page: `.` unchanged, `1`-`9` tenths changed (rounded up), `#` all of it
+    fn page_map(&self) -> String {
+        self.page_changes
+            .iter()
+            .map(|&fraction| {
+                if fraction == 0.0 {
+                    '.'
+                } else if fraction >= 1.0 {
+                    '#'
+                } else {
+                    char::from(b'0' + ((fraction * 10.0).ceil() as u8).clamp(1, 9))
+                }
+            })
+            .collect()
+    }
+
+    fn changed_pages(&self) -> usize {
+        self.page_changes.iter().filter(|&&f| f > 0.0).count()
+    }
+}
+
 /// Secure memory page tracker
 struct MemoryProtection {
-    monitored_pages: Mutex<HashMap<String, Vec<u8>>>, // Process -> Memory Page Snapshot
-    access_control_list: Mutex<HashSet<String>>,      // Approved processes for memory access
+    monitored_pages: Mutex<HashMap<String, Snapshot>>, // Process -> Memory Page Snapshot
+    access_control_list: Mutex<HashSet<String>>,       // Approved processes for memory access
 }
 
 impl MemoryProtection {
@@ -28,25 +109,96 @@ impl MemoryProtection {
         }
     }
 
-    /// Stores an initial memory snapshot for a process
-    fn store_memory_snapshot(&self, process: &str, memory_page: &[u8]) {
+    /// Stores an initial memory snapshot for a process; `executable` marks code pages
+    fn store_memory_snapshot(&self, process: &str, memory: &[u8], executable: bool) {
+        let snapshot = Snapshot {
+            memory: memory.to_vec(),
+            page_hashes: memory.chunks(PAGE_SIZE).map(crypto::sha256).collect(),
+            executable,
+        };
         let mut monitored_pages = self.monitored_pages.lock().unwrap();
-        monitored_pages.insert(process.to_string(), memory_page.to_vec());
+        monitored_pages.insert(process.to_string(), snapshot);
         println!("[MEMORY] Stored initial memory snapshot for '{}'", process);
     }
 
-    /// Detects unauthorized memory modifications
-    fn detect_memory_tampering(&self, process: &str, current_memory: &[u8]) {
+    /// Detects unauthorized memory modifications, scored by the worst-hit page so
+    /// a stray byte ranks below a page overwrite. `None` if nothing changed or the
+    /// process has no snapshot.
+    fn detect_memory_tampering(
+        &self,
+        process: &str,
+        current_memory: &[u8],
+    ) -> Option<TamperReport> {
         let monitored_pages = self.monitored_pages.lock().unwrap();
-        if let Some(original_memory) = monitored_pages.get(process) {
-            if original_memory != &current_memory.to_vec() {
-                println!(
-                    "[SECURITY] Memory corruption detected in process '{}'",
-                    process
-                );
-                self.log_security_event(process, "Memory Tampering Detected");
+        let snapshot = monitored_pages.get(process)?;
+
+        // Pages missing from either side count as entirely changed
+        let pages = snapshot
+            .page_hashes
+            .len()
+            .max(current_memory.len().div_ceil(PAGE_SIZE));
+        let mut page_changes = Vec::with_capacity(pages);
+        let mut changed_bytes = 0;
+        for page in 0..pages {
+            let start = page * PAGE_SIZE;
+            let original = snapshot
+                .memory
+                .get(start..)
+                .map_or(&[][..], |rest| &rest[..rest.len().min(PAGE_SIZE)]);
+            let current = current_memory
+                .get(start..)
+                .map_or(&[][..], |rest| &rest[..rest.len().min(PAGE_SIZE)]);
+            if snapshot.page_hashes.get(page) == Some(&crypto::sha256(current)) {
+                page_changes.push(0.0);
+                continue;
             }
+            let compared = original.len().min(current.len());
+            let changed = original[..compared]
+                .iter()
+                .zip(&current[..compared])
+                .filter(|(a, b)| a != b)
+                .count()
+                + original.len().max(current.len())
+                - compared;
+            changed_bytes += changed;
+            page_changes.push(changed as f64 / original.len().max(current.len()) as f64);
         }
+        if changed_bytes == 0 {
+            return None;
+        }
+
+        let score = page_changes.iter().cloned().fold(0.0, f64::max);
+        let mut severity = if score >= HIGH_PAGE_FRACTION {
+            Severity::High
+        } else if score >= MEDIUM_PAGE_FRACTION {
+            Severity::Medium
+        } else {
+            Severity::Low
+        };
+        if snapshot.executable {
+            severity = severity.escalate();
+        }
+        let report = TamperReport {
+            page_changes,
+            changed_bytes,
+            executable: snapshot.executable,
+            score,
+            severity,
+        };
+
+        println!(
+            "[SECURITY] {} memory tampering in process '{}': {} byte(s) across {} page(s) [{}]",
+            report.severity,
+            process,
+            report.changed_bytes,
+            report.changed_pages(),
+            report.page_map()
+        );
+        self.log_security_event(
+            process,
+            &format!("Memory Tampering Detected (severity {})", report.severity),
+        );
+        Some(report)
     }
 
     /// Logs a security event securely
@@ -96,14 +248,18 @@ fn main() {
     let memory_protector = Arc::new(MemoryProtection::new());
 
     let process_name = "suspicious_binary";
-    let memory_page = vec![0u8; 4096]; // Simulated memory page
-    let corrupted_memory_page = vec![255u8; 4096]; // Simulated memory corruption
+    let memory = vec![0u8; 4 * PAGE_SIZE]; // Simulated code pages
+    let mut flipped_byte = memory.clone(); // A single stray write
+    flipped_byte[100] = 1;
+    let mut overwritten_page = memory.clone(); // Injected code over a whole page
+    overwritten_page[PAGE_SIZE..2 * PAGE_SIZE].fill(0x90);
 
     // Store initial memory snapshot
-    memory_protector.store_memory_snapshot(process_name, &memory_page);
+    memory_protector.store_memory_snapshot(process_name, &memory, true);
 
     // Simulate unauthorized memory modification detection
-    memory_protector.detect_memory_tampering(process_name, &corrupted_memory_page);
+    memory_protector.detect_memory_tampering(process_name, &flipped_byte);
+    memory_protector.detect_memory_tampering(process_name, &overwritten_page);
 
     // Simulated buffer overflow detection using a canary value
     let canary_value = 0xAA;
@@ -130,3 +286,70 @@ fn main() {
         println!("[SECURITY] Trusted process 'trusted_service' allowed memory access.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four pages of distinct, non-zero bytes
+    fn memory() -> Vec<u8> {
+        (0..4 * PAGE_SIZE).map(|i| (i % 251) as u8 + 1).collect()
+    }
+
+    fn protected(executable: bool) -> MemoryProtection {
+        let protection = MemoryProtection::new();
+        protection.store_memory_snapshot("app", &memory(), executable);
+        protection
+    }
+
+    #[test]
+    fn a_single_byte_change_is_low_severity() {
+        let mut changed = memory();
+        changed[PAGE_SIZE + 7] ^= 0xff;
+
+        let report = protected(false)
+            .detect_memory_tampering("app", &changed)
+            .unwrap();
+        assert_eq!(report.severity, Severity::Low);
+        assert_eq!(report.changed_bytes, 1);
+        assert_eq!(report.page_map(), ".1..");
+        assert!(report.score < 0.001);
+    }
+
+    #[test]
+    fn a_full_page_overwrite_is_high_severity() {
+        let mut changed = memory();
+        changed[2 * PAGE_SIZE..3 * PAGE_SIZE].fill(0);
+
+        let report = protected(false)
+            .detect_memory_tampering("app", &changed)
+            .unwrap();
+        assert_eq!(report.severity, Severity::High);
+        assert_eq!(report.changed_bytes, PAGE_SIZE);
+        assert_eq!(report.page_map(), "..#.");
+        assert_eq!(report.score, 1.0);
+    }
+
+    #[test]
+    fn executable_memory_escalates_and_unchanged_memory_reports_nothing() {
+        let mut changed = memory();
+        changed[0] ^= 0xff;
+        let code = protected(true);
+        assert_eq!(
+            code.detect_memory_tampering("app", &changed)
+                .unwrap()
+                .severity,
+            Severity::Medium
+        );
+        changed[..PAGE_SIZE].fill(0x90); // A NOP sled over the first code page
+        assert_eq!(
+            code.detect_memory_tampering("app", &changed)
+                .unwrap()
+                .severity,
+            Severity::Critical
+        );
+
+        assert!(code.detect_memory_tampering("app", &memory()).is_none());
+        assert!(code.detect_memory_tampering("unknown", &changed).is_none());
+    }
+}
@@ -7,11 +7,148 @@
 //! - **Detects suspicious processes and isolates threats**
 //! - **Hardens memory by detecting unauthorized modifications**
 //! - **Logs security events to tamper-proof storage**
+//! - **Per-process capability sets (`CAP_SETUID`, `CAP_NET_BIND_SERVICE`, ...) checked per syscall**
+//!
+//! Capability policy files grant capabilities to processes by name, one process per
+//! line; `#` starts a comment:
+//!
+//! ```text
+//! grant sshd CAP_SETUID CAP_SETGID CAP_NET_BIND_SERVICE
+//! grant nginx CAP_NET_BIND_SERVICE
+//! ```
+//!
+//! A syscall that needs a capability is allowed only for processes granted it;
+//! syscalls that need none are always allowed.
 
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const CAPABILITY_POLICY_ENV: &str = "CAPABILITY_POLICY_FILE"; // Overrides the policy path below
+const CAPABILITY_POLICY_FILE: &str = "config/capabilities.conf";
+
+/// Policy applied when no policy file exists; mirrors the historical privileged processes
+const DEFAULT_CAPABILITIES: &str = "\
+grant init CAP_SETUID CAP_SETGID CAP_KILL CAP_SYS_ADMIN
+grant systemd CAP_SETUID CAP_SETGID CAP_KILL CAP_SYS_ADMIN CAP_NET_BIND_SERVICE
+grant sshd CAP_SETUID CAP_SETGID CAP_NET_BIND_SERVICE
+";
+
+/// Privileges a process may hold, named as in Linux `capabilities(7)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Capability {
+    Chown,
+    Fowner,
+    Kill,
+    SetGid,
+    SetUid,
+    SetPcap,
+    NetBindService,
+    NetRaw,
+    SysPtrace,
+    SysAdmin,
+}
+
+const CAPABILITIES: &[(Capability, &str)] = &[
+    (Capability::Chown, "CAP_CHOWN"),
+    (Capability::Fowner, "CAP_FOWNER"),
+    (Capability::Kill, "CAP_KILL"),
+    (Capability::SetGid, "CAP_SETGID"),
+    (Capability::SetUid, "CAP_SETUID"),
+    (Capability::SetPcap, "CAP_SETPCAP"),
+    (Capability::NetBindService, "CAP_NET_BIND_SERVICE"),
+    (Capability::NetRaw, "CAP_NET_RAW"),
+    (Capability::SysPtrace, "CAP_SYS_PTRACE"),
+    (Capability::SysAdmin, "CAP_SYS_ADMIN"),
+];
+
+impl Capability {
+    fn name(self) -> &'static str {
+        CAPABILITIES
+            .iter()
+            .find(|(known, _)| *known == self)
+            .map_or("CAP_UNKNOWN", |(_, name)| name)
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        CAPABILITIES
+            .iter()
+            .find(|(_, known)| *known == name)
+            .map(|(capability, _)| *capability)
+    }
+
+    /// The capability a syscall needs, or `None` if any process may make it
+    fn required_for(syscall: &str) -> Option<Self> {
+        match syscall {
+            "chown" | "fchown" | "lchown" => Some(Capability::Chown),
+            "chmod" | "fchmod" | "fchmodat" => Some(Capability::Fowner),
+            "kill" | "tkill" | "tgkill" => Some(Capability::Kill),
+            "setgid" | "setregid" | "setresgid" | "setgroups" => Some(Capability::SetGid),
+            "setuid" | "setreuid" | "setresuid" => Some(Capability::SetUid),
+            "capset" => Some(Capability::SetPcap),
+            "bind" => Some(Capability::NetBindService),
+            "ptrace" | "process_vm_writev" => Some(Capability::SysPtrace),
+            "mount" | "umount2" | "_sysctl" | "reboot" | "init_module" => {
+                Some(Capability::SysAdmin)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Bitmap of capabilities, one bit per `Capability` variant
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct CapabilitySet {
+    bits: u32,
+}
+
+impl CapabilitySet {
+    fn insert(&mut self, capability: Capability) {
+        self.bits |= 1 << capability as u32;
+    }
+
+    fn remove(&mut self, capability: Capability) {
+        self.bits &= !(1 << capability as u32);
+    }
+
+    fn contains(&self, capability: Capability) -> bool {
+        self.bits & (1 << capability as u32) != 0
+    }
+}
+
+/// Why a capability policy file was rejected; `line` is 1-based
+#[derive(Debug)]
+enum PolicyError {
+    Parse { line: usize, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            PolicyError::Io(e) => write!(f, "unable to read capability policy: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+impl From<io::Error> for PolicyError {
+    fn from(e: io::Error) -> Self {
+        PolicyError::Io(e)
+    }
+}
+
 /// Tracks system call activity per process
 struct SyscallMonitor {
     syscall_logs: Mutex<HashMap<String, Vec<String>>>, // Process -> Syscall History
@@ -29,7 +166,7 @@ impl SyscallMonitor {
     fn log_syscall(&self, process_name: &str, syscall: &str) {
         let mut logs = self.syscall_logs.lock().unwrap();
         logs.entry(process_name.to_string())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(format!("{} - {}", self.timestamp(), syscall));
 
         println!("[KERNEL] {} invoked syscall: {}", process_name, syscall);
@@ -44,45 +181,93 @@ impl SyscallMonitor {
     }
 }
 
-/// Enforces Zero Trust Security Policies
+/// Enforces Zero Trust Security Policies as per-process capability sets
 struct ZeroTrustSecurity {
-    blocked_syscalls: Vec<&'static str>,
-    privileged_processes: Vec<&'static str>,
+    grants: Mutex<HashMap<String, CapabilitySet>>, // Process -> Granted capabilities
 }
 
 impl ZeroTrustSecurity {
-    /// Initializes Zero Trust syscall rules
+    /// A policy in which no process holds any capability
     fn new() -> Self {
         Self {
-            blocked_syscalls: vec!["ptrace", "chmod 777", "cap_setuid", "cap_setgid"],
-            privileged_processes: vec!["init", "systemd", "sshd"],
+            grants: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Checks if a syscall should be blocked
-    fn enforce_policy(&self, process: &str, syscall: &str) -> bool {
-        if self.blocked_syscalls.contains(&syscall) {
-            println!(
-                "[SECURITY] Blocked unauthorized syscall '{}' by {}",
-                syscall, process
-            );
-            return false;
+    /// Loads the policy named by `CAPABILITY_POLICY_FILE`, else `config/capabilities.conf`.
+    /// Without a policy file `DEFAULT_CAPABILITIES` apply; a file that fails to parse is an error.
+    fn load() -> Result<Self, PolicyError> {
+        let path = std::env::var(CAPABILITY_POLICY_ENV)
+            .unwrap_or_else(|_| CAPABILITY_POLICY_FILE.to_string());
+        match fs::read_to_string(&path) {
+            Ok(source) => Self::parse(&source),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::parse(DEFAULT_CAPABILITIES),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn parse(source: &str) -> Result<Self, PolicyError> {
+        let security = Self::new();
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| PolicyError::Parse {
+                line: index + 1,
+                message,
+            };
+            let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+            match words.next() {
+                Some("grant") => {}
+                Some(keyword) => return Err(error(format!("unknown keyword '{}'", keyword))),
+                None => continue,
+            }
+            let process = words
+                .next()
+                .ok_or_else(|| error("'grant' needs a process".to_string()))?;
+            for name in words {
+                let capability = Capability::parse(name)
+                    .ok_or_else(|| error(format!("unknown capability '{}'", name)))?;
+                security.grant(process, capability);
+            }
+        }
+        Ok(security)
+    }
+
+    /// Gives `process` a capability
+    fn grant(&self, process: &str, capability: Capability) {
+        let mut grants = self.grants.lock().unwrap();
+        grants
+            .entry(process.to_string())
+            .or_default()
+            .insert(capability);
+    }
+
+    /// Takes a capability away from `process`
+    #[allow(dead_code)] // Runtime policy API; the simulation below only checks grants
+    fn revoke(&self, process: &str, capability: Capability) {
+        let mut grants = self.grants.lock().unwrap();
+        if let Some(granted) = grants.get_mut(process) {
+            granted.remove(capability);
         }
-        true
     }
 
-    /// Prevents unauthorized privilege escalation
-    fn prevent_privilege_escalation(&self, process: &str, syscall: &str) -> bool {
-        if syscall.contains("cap_setuid") || syscall.contains("cap_setgid") {
-            if !self.privileged_processes.contains(&process) {
+    fn has_capability(&self, process: &str, capability: Capability) -> bool {
+        let grants = self.grants.lock().unwrap();
+        grants
+            .get(process)
+            .is_some_and(|granted| granted.contains(capability))
+    }
+
+    /// Checks if `process` may make `syscall`; false if it lacks the capability it needs
+    fn enforce_policy(&self, process: &str, syscall: &str) -> bool {
+        match Capability::required_for(syscall) {
+            Some(capability) if !self.has_capability(process, capability) => {
                 println!(
-                    "[SECURITY] Privilege escalation attempt blocked: {} -> {}",
-                    process, syscall
+                    "[SECURITY] Blocked syscall '{}' by {}: {} not granted",
+                    syscall, process, capability
                 );
-                return false;
+                false
             }
+            _ => true,
         }
-        true
     }
 }
 
@@ -118,17 +303,23 @@ impl MemoryProtection {
 /// Simulated system call interception and security enforcement
 fn main() {
     let syscall_monitor = Arc::new(SyscallMonitor::new());
-    let security_enforcer = Arc::new(ZeroTrustSecurity::new());
+    let security_enforcer = match ZeroTrustSecurity::load() {
+        Ok(security) => Arc::new(security),
+        Err(e) => {
+            eprintln!("[SECURITY] Refusing to start: {}", e);
+            std::process::exit(1);
+        }
+    };
     let memory_protector = Arc::new(MemoryProtection::new());
 
     // Simulated process activity
     let process_name = "malicious_binary";
-    let syscall = "cap_setuid"; // Unauthorized privilege escalation attempt
+    let syscall = "setuid"; // Unauthorized privilege escalation attempt
 
     // Log syscall
     syscall_monitor.log_syscall(process_name, syscall);
 
-    // Enforce Zero Trust policies
+    // Enforce Zero Trust policies; without CAP_SETUID this is a privilege escalation
     if !security_enforcer.enforce_policy(process_name, syscall) {
         println!(
             "[SECURITY] Process '{}' terminated due to unauthorized syscall.",
@@ -137,16 +328,60 @@ fn main() {
         return;
     }
 
-    // Prevent privilege escalation
-    if !security_enforcer.prevent_privilege_escalation(process_name, syscall) {
-        println!(
-            "[SECURITY] Process '{}' blocked from privilege escalation.",
-            process_name
-        );
-        return;
-    }
-
     // Simulate memory protection
     let fake_memory_page = vec![0u8; 4096];
     memory_protector.detect_memory_tampering(process_name, &fake_memory_page);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setuid_needs_cap_setuid() {
+        let security = ZeroTrustSecurity::parse(
+            "# Privileged daemons\ngrant sshd CAP_SETUID CAP_NET_BIND_SERVICE\n\ngrant nginx CAP_NET_BIND_SERVICE # web\n",
+        )
+        .unwrap();
+
+        assert!(security.enforce_policy("sshd", "setuid"));
+        assert!(!security.enforce_policy("nginx", "setuid"));
+        assert!(!security.enforce_policy("malicious_binary", "setresuid"));
+        // Syscalls that need no capability are open to every process
+        assert!(security.enforce_policy("malicious_binary", "open"));
+    }
+
+    #[test]
+    fn grants_and_revocations_take_effect_immediately() {
+        let security = ZeroTrustSecurity::new();
+        assert!(!security.enforce_policy("worker", "setuid"));
+
+        security.grant("worker", Capability::SetUid);
+        assert!(security.enforce_policy("worker", "setuid"));
+        assert!(!security.enforce_policy("worker", "setgid"));
+
+        security.revoke("worker", Capability::SetUid);
+        assert!(!security.enforce_policy("worker", "setuid"));
+    }
+
+    #[test]
+    fn malformed_policy_lines_are_reported() {
+        for (source, line) in [
+            ("grant sshd CAP_SETUID\ngrant sshd CAP_FLY\n", 2),
+            ("allow sshd CAP_SETUID\n", 1),
+            ("\n\ngrant\n", 3),
+        ] {
+            match ZeroTrustSecurity::parse(source) {
+                Err(PolicyError::Parse { line: reported, .. }) => assert_eq!(reported, line),
+                other => panic!(
+                    "expected a parse error for {:?}, got {:?}",
+                    source,
+                    other.err()
+                ),
+            }
+        }
+        assert!(ZeroTrustSecurity::parse(DEFAULT_CAPABILITIES)
+            .unwrap()
+            .enforce_policy("init", "setuid"));
+    }
+}
@@ -8,66 +8,158 @@
 //! - **Logs system calls securely for forensic auditing**
 //! - **Adaptive anomaly detection based on syscall frequency patterns**
 //! - **Zero Trust enforcement at the kernel level**
+//! - **Allocation-free policy checks on numeric syscall ids**
+//! - **Per-thread event batching so the hot path takes no lock**
+//! - **Typed event stream of syscall decisions for the IDS via `subscribe`**
 
-use std::collections::{HashMap, HashSet};
+#[path = "syscall_policy.rs"]
+mod syscall_policy;
+#[allow(dead_code)]
+#[path = "syscall_stream.rs"]
+mod syscall_stream;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use syscall_policy::{
+    syscall_name, Action, SyscallEvent, SyscallPolicy, DEFAULT_RULES, SYS_CAPSET, SYS_CHMOD,
+    SYS_EXECVE, SYS_OPEN, SYS_SETGID, SYS_SETUID,
+};
+use syscall_stream::{Decision, SyscallBroadcaster};
+
+const RULES_FILE_ENV: &str = "SYSCALL_RULES_FILE"; // Overrides the rules path below
+const RULES_FILE: &str = "config/syscall_rules.conf";
+const EVENT_BATCH_SIZE: usize = 4096; // Events a recorder buffers before publishing them
+const EVENT_FLUSH_INTERVAL: Duration = Duration::from_millis(100); // Or this long after the last flush
+const FLUSH_CHECK_EVERY: usize = 256; // Events between clock reads on the hot path
+const ANOMALY_THRESHOLD: u64 = 10; // Calls by one process before it is reported
+
+/// A recorded syscall. `seq` orders events across every recording thread.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RecordedSyscall {
+    seq: u64,
+    timestamp: u64,
+    pid: u32,
+    nr: u32,
+    args: [u64; 3],
+}
 
 /// Tracks system calls made by processes
 struct SyscallInterceptor {
-    monitored_processes: Mutex<HashMap<String, Vec<String>>>, // Process -> Syscall History
-    blocked_syscalls: HashSet<&'static str>,
-    secure_log: Mutex<Vec<String>>, // Tamper-proof security event log
+    policy: SyscallPolicy,
+    sequence: AtomicU64,
+    secure_log: Mutex<Vec<RecordedSyscall>>, // Tamper-proof security event log, filled batch by batch
+    call_counts: Mutex<HashMap<u32, u64>>,   // PID -> Syscalls recorded
+    process_names: Mutex<HashMap<u32, String>>, // PID -> Name used in reports
+    stream: SyscallBroadcaster,
 }
 
 impl SyscallInterceptor {
-    /// Creates a new system call interceptor with predefined security policies
-    fn new() -> Self {
-        let blocked_syscalls: HashSet<&str> = [
-            "ptrace",
-            "chmod 777",
-            "setuid",
-            "setgid",
-            "kill",
-            "execve",
-            "cap_setuid",
-            "cap_setgid",
-            "sysctl",
-            "write /proc/mem",
-        ]
-        .iter()
-        .cloned()
-        .collect();
-
+    /// Creates a new system call interceptor enforcing `policy`
+    fn new(policy: SyscallPolicy) -> Self {
         Self {
-            monitored_processes: Mutex::new(HashMap::new()),
-            blocked_syscalls,
+            policy,
+            sequence: AtomicU64::new(0),
             secure_log: Mutex::new(Vec::new()),
+            call_counts: Mutex::new(HashMap::new()),
+            process_names: Mutex::new(HashMap::new()),
+            stream: SyscallBroadcaster::new(),
+        }
+    }
+
+    /// Structured events for every decided syscall from now on. A subscriber that
+    /// falls behind loses events rather than slowing interception; see `dropped_events`.
+    fn subscribe(&self) -> Receiver<syscall_stream::SyscallEvent> {
+        self.stream.subscribe()
+    }
+
+    /// Publishes the decision on a recorded syscall to subscribers
+    fn notify(&self, recorded: &RecordedSyscall, path: Option<&str>, decision: Decision) {
+        if !self.stream.has_subscribers() {
+            return;
         }
+        let [a0, a1, a2] = recorded.args;
+        let args = match path {
+            Some(path) => format!("path={:?} args=[{:#x}, {:#x}, {:#x}]", path, a0, a1, a2),
+            None => format!("args=[{:#x}, {:#x}, {:#x}]", a0, a1, a2),
+        };
+        self.stream.publish(syscall_stream::SyscallEvent {
+            seq: recorded.seq,
+            pid: recorded.pid,
+            name: syscall_name(recorded.nr),
+            args,
+            timestamp: recorded.timestamp,
+            decision,
+        });
     }
 
-    /// Logs a system call event securely
-    fn log_syscall(&self, process: &str, syscall: &str) {
-        let mut monitored_processes = self.monitored_processes.lock().unwrap();
-        monitored_processes
-            .entry(process.to_string())
-            .or_insert_with(Vec::new)
-            .push(format!("{} - {}", self.timestamp(), syscall));
+    /// Events subscribers missed because their queue was full
+    fn dropped_events(&self) -> u64 {
+        self.stream.dropped_count()
+    }
 
-        // Secure Logging
-        let log_entry = format!("[SECURITY] {} executed syscall: {}", process, syscall);
-        let mut secure_log = self.secure_log.lock().unwrap();
-        secure_log.push(log_entry.clone());
+    /// Loads the rule file named by `SYSCALL_RULES_FILE`, else `config/syscall_rules.conf`.
+    /// Without a rule file the built-in `DEFAULT_RULES` apply; a file that fails to parse is an error.
+    fn load_policy() -> Result<SyscallPolicy, syscall_policy::RuleError> {
+        let path = std::env::var(RULES_FILE_ENV).unwrap_or_else(|_| RULES_FILE.to_string());
+        match SyscallPolicy::load(&path) {
+            Err(syscall_policy::RuleError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                SyscallPolicy::parse(DEFAULT_RULES)
+            }
+            result => result,
+        }
+    }
 
-        println!("{}", log_entry);
+    /// Names a process for log output
+    fn register_process(&self, pid: u32, name: &str) {
+        self.process_names
+            .lock()
+            .unwrap()
+            .insert(pid, name.to_string());
     }
 
-    /// Blocks unauthorized system calls in real time
-    fn enforce_syscall_policies(&self, process: &str, syscall: &str) -> bool {
-        if self.blocked_syscalls.contains(syscall) {
+    fn process_name(&self, pid: u32) -> String {
+        match self.process_names.lock().unwrap().get(&pid) {
+            Some(name) => format!("{} ({})", name, pid),
+            None => pid.to_string(),
+        }
+    }
+
+    /// A buffer for one thread's events. Recording takes no lock; events are published
+    /// to the shared log once the buffer fills, periodically, and when it is dropped.
+    fn recorder(self: &Arc<Self>) -> SyscallRecorder {
+        SyscallRecorder {
+            interceptor: Arc::clone(self),
+            buffer: Vec::with_capacity(EVENT_BATCH_SIZE),
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Appends one recorder's batch, taking each shared lock once per batch
+    fn publish(&self, batch: &[RecordedSyscall]) {
+        self.secure_log.lock().unwrap().extend_from_slice(batch);
+        let mut call_counts = self.call_counts.lock().unwrap();
+        for event in batch {
+            *call_counts.entry(event.pid).or_insert(0) += 1;
+        }
+    }
+
+    /// Every published event in the order it was recorded
+    fn events_in_order(&self) -> Vec<RecordedSyscall> {
+        let mut events = self.secure_log.lock().unwrap().clone();
+        events.sort_unstable_by_key(|event| event.seq);
+        events
+    }
+
+    /// Blocks system calls the policy denies, in real time. Allocation-free unless it blocks.
+    fn enforce_syscall_policies(&self, call: &SyscallEvent) -> bool {
+        if self.policy.evaluate(call) == Action::Deny {
             println!(
                 "[SECURITY] BLOCKED: Unauthorized syscall '{}' by process '{}'",
-                syscall, process
+                syscall_name(call.nr),
+                self.process_name(call.pid)
             );
             return false;
         }
@@ -75,66 +167,231 @@ impl SyscallInterceptor {
     }
 
     /// Detects privilege escalation attempts and blocks them
-    fn detect_privilege_escalation(&self, process: &str, syscall: &str) -> bool {
-        if syscall.contains("setuid") || syscall.contains("setgid") || syscall == "execve" {
+    fn detect_privilege_escalation(&self, call: &SyscallEvent) -> bool {
+        if matches!(call.nr, SYS_SETUID | SYS_SETGID | SYS_CAPSET | SYS_EXECVE) {
             println!(
                 "[SECURITY] ALERT: Unauthorized privilege escalation attempt detected: {} -> {}",
-                process, syscall
+                self.process_name(call.pid),
+                syscall_name(call.nr)
             );
             return false;
         }
         true
     }
 
-    /// Detects anomalous behavior by analyzing syscall frequency
-    fn detect_anomalous_behavior(&self, process: &str) {
-        let monitored_processes = self.monitored_processes.lock().unwrap();
-        if let Some(syscalls) = monitored_processes.get(process) {
-            if syscalls.len() > 10 {
-                println!("[SECURITY] Anomaly detected: '{}' is making an unusually high number of system calls!", process);
-            }
+    /// Detects anomalous behavior by analyzing syscall frequency. Sees published
+    /// events only, so it lags recorders by at most one batch.
+    fn detect_anomalous_behavior(&self, pid: u32) {
+        let calls = self.call_counts.lock().unwrap().get(&pid).copied();
+        if calls.is_some_and(|calls| calls > ANOMALY_THRESHOLD) {
+            println!(
+                "[SECURITY] Anomaly detected: '{}' is making an unusually high number of system calls!",
+                self.process_name(pid)
+            );
         }
     }
+}
+
+/// Buffers syscall events for one thread; see `SyscallInterceptor::recorder`
+struct SyscallRecorder {
+    interceptor: Arc<SyscallInterceptor>,
+    buffer: Vec<RecordedSyscall>,
+    last_flush: Instant,
+}
 
-    /// Generates a timestamp for logging
-    fn timestamp(&self) -> u64 {
-        SystemTime::now()
+impl SyscallRecorder {
+    /// Logs a system call event; the only shared state touched is the sequence counter
+    fn record(&mut self, call: &SyscallEvent) -> RecordedSyscall {
+        let seq = self.interceptor.sequence.fetch_add(1, Ordering::Relaxed);
+        let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let recorded = RecordedSyscall {
+            seq,
+            timestamp,
+            pid: call.pid,
+            nr: call.nr,
+            args: call.args,
+        };
+        self.buffer.push(recorded);
+
+        let len = self.buffer.len();
+        if len >= EVENT_BATCH_SIZE
+            || (len.is_multiple_of(FLUSH_CHECK_EVERY)
+                && self.last_flush.elapsed() >= EVENT_FLUSH_INTERVAL)
+        {
+            self.flush();
+        }
+        recorded
+    }
+
+    /// Publishes buffered events to the shared log now
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            self.interceptor.publish(&self.buffer);
+            self.buffer.clear();
+        }
+        self.last_flush = Instant::now();
+    }
+}
+
+impl Drop for SyscallRecorder {
+    fn drop(&mut self) {
+        self.flush();
     }
 }
 
 /// Simulated real-time syscall monitoring and security enforcement
 fn main() {
-    let interceptor = Arc::new(SyscallInterceptor::new());
-
-    let process_name = "suspicious_binary";
-    let syscall_attempts = vec!["execve", "open", "setuid", "chmod 777", "write /proc/mem"];
-
-    for syscall in syscall_attempts {
-        // Log the syscall
-        interceptor.log_syscall(process_name, syscall);
+    let policy = match SyscallInterceptor::load_policy() {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!(
+                "[SECURITY] Refusing to start with invalid syscall rules: {}",
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+    let interceptor = Arc::new(SyscallInterceptor::new(policy));
+    let mut recorder = interceptor.recorder();
 
-        // Enforce syscall policies
-        if !interceptor.enforce_syscall_policies(process_name, syscall) {
+    // Stand-in for the IDS: print each decision as it is published
+    let events = interceptor.subscribe();
+    let consumer = std::thread::spawn(move || {
+        for event in events {
             println!(
-                "[SECURITY] Process '{}' terminated due to unauthorized system call.",
-                process_name
+                "[STREAM] #{} pid {} {} {} -> {:?}",
+                event.seq, event.pid, event.name, event.args, event.decision
             );
-            break;
         }
+    });
 
-        // Detect privilege escalation
-        if !interceptor.detect_privilege_escalation(process_name, syscall) {
+    let pid = 4242;
+    interceptor.register_process(pid, "suspicious_binary");
+    let syscall_attempts = [
+        (SYS_OPEN, [0, 0, 0], Some("/etc/hosts")),
+        (SYS_OPEN, [0, 0, 0], Some("/proc/1/mem")),
+        (SYS_EXECVE, [0, 0, 0], None),
+        (SYS_SETUID, [0, 0, 0], None),
+        (SYS_CHMOD, [0, 0o777, 0], None),
+    ];
+
+    for (nr, args, path) in syscall_attempts {
+        let call = SyscallEvent {
+            pid,
+            nr,
+            args,
+            path,
+        };
+
+        // Log the syscall
+        let recorded = recorder.record(&call);
+
+        // Enforce syscall policies, then detect privilege escalation
+        let outcome = if !interceptor.enforce_syscall_policies(&call) {
+            Some("terminated due to unauthorized system call")
+        } else if !interceptor.detect_privilege_escalation(&call) {
+            Some("prevented from privilege escalation")
+        } else {
+            None
+        };
+        let decision = match outcome {
+            Some(_) => Decision::Blocked,
+            None => Decision::Allowed,
+        };
+        interceptor.notify(&recorded, path, decision);
+        if let Some(outcome) = outcome {
             println!(
-                "[SECURITY] Process '{}' prevented from privilege escalation.",
-                process_name
+                "[SECURITY] Process '{}' {}.",
+                interceptor.process_name(pid),
+                outcome
             );
             break;
         }
 
         // Detect anomalous behavior
-        interceptor.detect_anomalous_behavior(process_name);
+        interceptor.detect_anomalous_behavior(pid);
+    }
+
+    recorder.flush();
+    for event in interceptor.events_in_order() {
+        println!(
+            "[SECURITY] #{} {} {} executed syscall: {}",
+            event.seq,
+            event.timestamp,
+            interceptor.process_name(event.pid),
+            syscall_name(event.nr)
+        );
+    }
+
+    // Dropping the last handle hangs up the stream, which ends the consumer
+    let dropped = interceptor.dropped_events();
+    drop(recorder);
+    drop(interceptor);
+    let _ = consumer.join();
+    if dropped > 0 {
+        println!("[STREAM] {} events dropped by slow subscribers", dropped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syscall_policy::SYS_SOCKET;
+
+    #[test]
+    fn a_subscriber_receives_each_decision_in_order() {
+        let interceptor = Arc::new(SyscallInterceptor::new(
+            SyscallPolicy::parse(DEFAULT_RULES).unwrap(),
+        ));
+        let events = interceptor.subscribe();
+        let mut recorder = interceptor.recorder();
+
+        let calls = [
+            (SYS_OPEN, [0, 0, 0], Some("/etc/hosts")),
+            (SYS_SOCKET, [2, 1, 0], None),
+            (SYS_OPEN, [0, 0, 0], Some("/proc/1/mem")),
+            (SYS_CHMOD, [0, 0o644, 0], None),
+            (SYS_SETUID, [0, 0, 0], None),
+        ];
+        for (nr, args, path) in calls {
+            let call = SyscallEvent {
+                pid: 7,
+                nr,
+                args,
+                path,
+            };
+            let recorded = recorder.record(&call);
+            let decision = if interceptor.enforce_syscall_policies(&call) {
+                Decision::Allowed
+            } else {
+                Decision::Blocked
+            };
+            interceptor.notify(&recorded, path, decision);
+        }
+
+        let received: Vec<_> = events.try_iter().collect();
+        let summary: Vec<_> = received
+            .iter()
+            .map(|event| (event.seq, event.pid, event.name, event.decision))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, 7, "open", Decision::Allowed),
+                (1, 7, "socket", Decision::Allowed),
+                (2, 7, "open", Decision::Blocked),
+                (3, 7, "chmod", Decision::Allowed),
+                (4, 7, "setuid", Decision::Blocked),
+            ]
+        );
+        assert_eq!(
+            received[0].args,
+            r#"path="/etc/hosts" args=[0x0, 0x0, 0x0]"#
+        );
+        assert_eq!(received[1].args, "args=[0x2, 0x1, 0x0]");
+        assert!(received.iter().all(|event| event.timestamp > 0));
+        assert_eq!(interceptor.dropped_events(), 0);
     }
 }
@@ -0,0 +1,381 @@
+//! Zero Trust Syscall Policy Rules (Rust Standard Library Only)
+//! Parses and evaluates per-syscall allow/deny rules with optional argument predicates.
+//!
+//! Rule files hold one rule per line; `#` starts a comment:
+//!
+//! ```text
+//! deny  setuid arg0=0            # only setuid(0)
+//! deny  open   path=/proc/*/mem  # `*` matches within one path segment
+//! allow open
+//! deny  chmod  arg1&0o002        # any of these mode bits set
+//! default allow                  # when no rule matches (allow if omitted)
+//! ```
+//!
+//! A syscall is named (`open`) or numbered (`2`). Every predicate on a rule must hold
+//! for it to match, and the first matching rule decides.
+
+use std::fmt;
+use std::fs;
+use std::io;
+
+/// x86_64 syscall numbers known by name
+pub const SYS_WRITE: u32 = 1;
+pub const SYS_OPEN: u32 = 2;
+pub const SYS_SOCKET: u32 = 41;
+pub const SYS_EXECVE: u32 = 59;
+pub const SYS_KILL: u32 = 62;
+pub const SYS_CHMOD: u32 = 90;
+pub const SYS_PTRACE: u32 = 101;
+pub const SYS_SETUID: u32 = 105;
+pub const SYS_SETGID: u32 = 106;
+pub const SYS_CAPSET: u32 = 126;
+pub const SYS_SYSCTL: u32 = 156;
+pub const SYS_OPENAT: u32 = 257;
+pub const SYS_FCHMODAT: u32 = 268;
+
+const SYSCALL_NAMES: &[(u32, &str)] = &[
+    (SYS_WRITE, "write"),
+    (SYS_OPEN, "open"),
+    (SYS_SOCKET, "socket"),
+    (SYS_EXECVE, "execve"),
+    (SYS_KILL, "kill"),
+    (SYS_CHMOD, "chmod"),
+    (SYS_PTRACE, "ptrace"),
+    (SYS_SETUID, "setuid"),
+    (SYS_SETGID, "setgid"),
+    (SYS_CAPSET, "capset"),
+    (SYS_SYSCTL, "_sysctl"),
+    (SYS_OPENAT, "openat"),
+    (SYS_FCHMODAT, "fchmodat"),
+];
+
+const MAX_SYSCALL_NR: usize = 512; // Rules for numbers at or above this are ignored
+
+/// Policy applied when no rule file is configured; mirrors the historical block list
+pub const DEFAULT_RULES: &str = "\
+deny ptrace
+deny setuid
+deny setgid
+deny kill
+deny execve
+deny capset
+deny _sysctl
+deny open path=/proc/*/mem
+deny openat path=/proc/*/mem
+deny chmod arg1&0o002
+deny fchmodat arg2&0o002
+";
+
+/// Name of a syscall number, for log output
+pub fn syscall_name(nr: u32) -> &'static str {
+    SYSCALL_NAMES
+        .iter()
+        .find(|(known, _)| *known == nr)
+        .map_or("unknown", |(_, name)| name)
+}
+
+fn syscall_number(name: &str) -> Option<u32> {
+    SYSCALL_NAMES
+        .iter()
+        .find(|(_, known)| *known == name)
+        .map(|(nr, _)| *nr)
+        .or_else(|| name.parse().ok())
+}
+
+/// A syscall as seen at interception: its number, first arguments, and the path it
+/// names if any (already read from the tracee, e.g. for `open`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SyscallEvent<'a> {
+    pub pid: u32,
+    pub nr: u32,
+    pub args: [u64; 3],
+    pub path: Option<&'a str>,
+}
+
+/// Fixed-size bitmap of syscall numbers
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SyscallSet {
+    bits: [u64; MAX_SYSCALL_NR / 64],
+}
+
+impl SyscallSet {
+    fn insert(&mut self, nr: u32) {
+        let nr = nr as usize;
+        if nr < MAX_SYSCALL_NR {
+            self.bits[nr / 64] |= 1 << (nr % 64);
+        }
+    }
+
+    fn contains(&self, nr: u32) -> bool {
+        let nr = nr as usize;
+        nr < MAX_SYSCALL_NR && self.bits[nr / 64] & (1 << (nr % 64)) != 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Predicate {
+    ArgEquals(usize, u64),
+    ArgHasBits(usize, u64),
+    PathMatches(String),
+}
+
+impl Predicate {
+    fn holds(&self, event: &SyscallEvent) -> bool {
+        match self {
+            Predicate::ArgEquals(index, value) => event.args[*index] == *value,
+            Predicate::ArgHasBits(index, mask) => event.args[*index] & mask != 0,
+            Predicate::PathMatches(pattern) => {
+                event.path.is_some_and(|path| glob_match(pattern, path))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    action: Action,
+    nr: u32,
+    predicates: Vec<Predicate>,
+}
+
+/// Why a rule file was rejected; `line` is 1-based
+#[derive(Debug)]
+pub enum RuleError {
+    Parse { line: usize, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            RuleError::Io(e) => write!(f, "unable to read rules: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl From<io::Error> for RuleError {
+    fn from(e: io::Error) -> Self {
+        RuleError::Io(e)
+    }
+}
+
+/// An ordered rule list with a fallback action
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyscallPolicy {
+    rules: Vec<Rule>,
+    default: Action,
+    ruled: SyscallSet, // Numbers with at least one rule; the rest skip the scan
+}
+
+impl SyscallPolicy {
+    pub fn load(path: &str) -> Result<Self, RuleError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, RuleError> {
+        let mut policy = SyscallPolicy {
+            rules: Vec::new(),
+            default: Action::Allow,
+            ruled: SyscallSet::default(),
+        };
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| RuleError::Parse {
+                line: index + 1,
+                message,
+            };
+            let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+            let keyword = match words.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let target = words
+                .next()
+                .ok_or_else(|| error(format!("'{}' needs an argument", keyword)))?;
+
+            if keyword == "default" {
+                policy.default = parse_action(target)
+                    .ok_or_else(|| error(format!("unknown action '{}'", target)))?;
+                if let Some(extra) = words.next() {
+                    return Err(error(format!("unexpected '{}' after default", extra)));
+                }
+                continue;
+            }
+            let action = parse_action(keyword)
+                .ok_or_else(|| error(format!("unknown action '{}'", keyword)))?;
+            let nr = syscall_number(target)
+                .filter(|&nr| (nr as usize) < MAX_SYSCALL_NR)
+                .ok_or_else(|| error(format!("unknown syscall '{}'", target)))?;
+            let predicates = words
+                .map(|word| parse_predicate(word).map_err(&error))
+                .collect::<Result<_, _>>()?;
+            policy.ruled.insert(nr);
+            policy.rules.push(Rule {
+                action,
+                nr,
+                predicates,
+            });
+        }
+        Ok(policy)
+    }
+
+    /// The action of the first rule matching `event`, or the default. Allocation-free.
+    pub fn evaluate(&self, event: &SyscallEvent) -> Action {
+        if !self.ruled.contains(event.nr) {
+            return self.default;
+        }
+        self.rules
+            .iter()
+            .find(|rule| rule.nr == event.nr && rule.predicates.iter().all(|p| p.holds(event)))
+            .map_or(self.default, |rule| rule.action)
+    }
+}
+
+fn parse_action(word: &str) -> Option<Action> {
+    match word {
+        "allow" => Some(Action::Allow),
+        "deny" => Some(Action::Deny),
+        _ => None,
+    }
+}
+
+/// `argN=V`, `argN&MASK` or `path=GLOB`
+fn parse_predicate(word: &str) -> Result<Predicate, String> {
+    if let Some(pattern) = word.strip_prefix("path=") {
+        return Ok(Predicate::PathMatches(pattern.to_string()));
+    }
+    let arg = word
+        .strip_prefix("arg")
+        .ok_or_else(|| format!("unknown predicate '{}'", word))?;
+    let (index, value, is_mask) = match arg.split_once('=') {
+        Some((index, value)) => (index, value, false),
+        None => match arg.split_once('&') {
+            Some((index, mask)) => (index, mask, true),
+            None => return Err(format!("unknown predicate '{}'", word)),
+        },
+    };
+    let index: usize = index
+        .parse()
+        .ok()
+        .filter(|&index| index < 3)
+        .ok_or_else(|| format!("argument index in '{}' must be 0, 1 or 2", word))?;
+    let value = parse_number(value).ok_or_else(|| format!("invalid number in '{}'", word))?;
+    Ok(if is_mask {
+        Predicate::ArgHasBits(index, value)
+    } else {
+        Predicate::ArgEquals(index, value)
+    })
+}
+
+/// Decimal, `0x` hex or `0o` octal
+fn parse_number(text: &str) -> Option<u64> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(octal) = text.strip_prefix("0o") {
+        u64::from_str_radix(octal, 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Matches `path` against `pattern`, where `*` matches any run of characters except `/`
+fn glob_match(pattern: &str, path: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == path,
+        Some((prefix, rest)) => {
+            let tail = match path.strip_prefix(prefix) {
+                Some(tail) => tail,
+                None => return false,
+            };
+            let segment_end = tail.find('/').unwrap_or(tail.len());
+            tail[..segment_end]
+                .char_indices()
+                .map(|(split, _)| split)
+                .chain(std::iter::once(segment_end))
+                .any(|split| glob_match(rest, &tail[split..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(nr: u32, args: [u64; 3], path: Option<&str>) -> SyscallEvent<'_> {
+        SyscallEvent {
+            pid: 100,
+            nr,
+            args,
+            path,
+        }
+    }
+
+    #[test]
+    fn open_is_denied_only_on_sensitive_paths() {
+        let policy = SyscallPolicy::parse(
+            "deny open path=/proc/*/mem\ndeny open path=/etc/shadow\nallow open\ndefault deny\n",
+        )
+        .unwrap();
+        let open = |path| policy.evaluate(&event(SYS_OPEN, [0; 3], Some(path)));
+
+        assert_eq!(open("/proc/1234/mem"), Action::Deny);
+        assert_eq!(open("/etc/shadow"), Action::Deny);
+        assert_eq!(open("/etc/hostname"), Action::Allow);
+        assert_eq!(open("/tmp/report.txt"), Action::Allow);
+        // `*` stays within one segment
+        assert_eq!(open("/proc/1/task/2/mem"), Action::Allow);
+        // Syscalls without a rule take the default
+        assert_eq!(
+            policy.evaluate(&event(SYS_WRITE, [1, 0, 0], None)),
+            Action::Deny
+        );
+    }
+
+    #[test]
+    fn only_setuid_to_root_is_blocked() {
+        let policy = SyscallPolicy::parse("deny setuid arg0=0 # become root\n").unwrap();
+
+        assert_eq!(
+            policy.evaluate(&event(SYS_SETUID, [0, 0, 0], None)),
+            Action::Deny
+        );
+        assert_eq!(
+            policy.evaluate(&event(SYS_SETUID, [1000, 0, 0], None)),
+            Action::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&event(SYS_SETGID, [0, 0, 0], None)),
+            Action::Allow
+        );
+
+        let numbered = SyscallPolicy::parse("deny 105 arg0=0x0").unwrap();
+        assert_eq!(
+            numbered.evaluate(&event(SYS_SETUID, [0, 0, 0], None)),
+            Action::Deny
+        );
+    }
+
+    #[test]
+    fn malformed_rules_report_their_line() {
+        for (source, bad_line) in [
+            ("allow open\nforbid open\n", 2),
+            ("deny nosuchcall\n", 1),
+            ("\n# comment\ndeny setuid arg3=0\n", 3),
+            ("deny chmod arg1&0o9\n", 1),
+            ("default\n", 1),
+        ] {
+            match SyscallPolicy::parse(source) {
+                Err(RuleError::Parse { line, .. }) => assert_eq!(line, bad_line, "{:?}", source),
+                other => panic!("{:?} parsed as {:?}", source, other),
+            }
+        }
+    }
+}
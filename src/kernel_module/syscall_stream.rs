@@ -0,0 +1,134 @@
+//! Zero Trust Syscall Event Stream (Standard Library Only)
+//! Structured syscall decisions for consumers outside the interceptor, such as the IDS.
+//! Features:
+//! - **One bounded queue per subscriber, so a slow consumer never stalls interception**
+//! - **Events dropped under backpressure are counted, not silently lost**
+//! - **Subscribers that hang up are pruned on the next publish**
+//! - **No lock taken while nobody is subscribed**
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 4096; // Events a subscriber may fall behind before drops
+
+/// What the interceptor did with a syscall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Blocked,
+}
+
+/// One intercepted syscall. `seq` orders events across every recording thread.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyscallEvent {
+    pub seq: u64,
+    pub pid: u32,
+    pub name: &'static str,
+    pub args: String, // Path and leading arguments, e.g. `path="/etc/hosts" args=[0x0, 0x1ff, 0x0]`
+    pub timestamp: u64,
+    pub decision: Decision,
+}
+
+/// Fans syscall events out to subscribers
+pub struct SyscallBroadcaster {
+    subscribers: Mutex<Vec<SyncSender<SyscallEvent>>>,
+    subscriber_count: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+impl SyscallBroadcaster {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Mutex::new(Vec::new()),
+            subscriber_count: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// A stream of every event published from now on, in publish order
+    pub fn subscribe(&self) -> Receiver<SyscallEvent> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_QUEUE_CAPACITY);
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.push(sender);
+        self.subscriber_count
+            .store(subscribers.len(), Ordering::Relaxed);
+        receiver
+    }
+
+    /// True when an event would reach someone, so callers can skip building it
+    pub fn has_subscribers(&self) -> bool {
+        self.subscriber_count.load(Ordering::Relaxed) > 0
+    }
+
+    /// Queues `event` for every subscriber without waiting; a full queue drops it
+    pub fn publish(&self, event: SyscallEvent) {
+        if !self.has_subscribers() {
+            return;
+        }
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|sender| match sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+        self.subscriber_count
+            .store(subscribers.len(), Ordering::Relaxed);
+    }
+
+    /// Events lost because a subscriber's queue was full, summed over subscribers
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(seq: u64) -> SyscallEvent {
+        SyscallEvent {
+            seq,
+            pid: 1,
+            name: "open",
+            args: String::new(),
+            timestamp: 0,
+            decision: Decision::Allowed,
+        }
+    }
+
+    #[test]
+    fn a_full_subscriber_drops_and_counts_while_others_keep_receiving() {
+        let stream = SyscallBroadcaster::new();
+        assert!(!stream.has_subscribers());
+        let slow = stream.subscribe();
+        let fast = stream.subscribe();
+
+        let total = SUBSCRIBER_QUEUE_CAPACITY as u64 + 10;
+        for seq in 0..total {
+            stream.publish(event(seq));
+            assert_eq!(fast.try_recv().unwrap().seq, seq);
+        }
+
+        assert_eq!(stream.dropped_count(), 10);
+        let kept: Vec<u64> = slow.try_iter().map(|event| event.seq).collect();
+        assert_eq!(
+            kept,
+            (0..SUBSCRIBER_QUEUE_CAPACITY as u64).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn subscribers_that_hang_up_are_pruned() {
+        let stream = SyscallBroadcaster::new();
+        drop(stream.subscribe());
+        assert!(stream.has_subscribers());
+
+        stream.publish(event(0));
+        assert!(!stream.has_subscribers());
+        assert_eq!(stream.dropped_count(), 0);
+    }
+}
@@ -0,0 +1,29 @@
+//! Zero Trust Website backend as a library.
+//! The standalone binaries under `src/backend` remain runnable on their own; this crate root
+//! composes the pieces needed to embed a server. Bearer tokens are signed with the
+//! `ACCESS_SECRET` from the builder's `KeyProvider`, by default `ZTW_ACCESS_SECRET`:
+//!
+//! ```no_run
+//! use zero_trust_website::{Response, Server};
+//!
+//! Server::builder()
+//!     .route("GET", "/health", |_| Response::ok("ok"))
+//!     .protected_route("GET", "/admin", "ALL", |req| {
+//!         Response::ok(&format!("hello {}", req.principal.as_deref().unwrap_or("")))
+//!     })
+//!     .bind("127.0.0.1:8080")
+//!     .run()
+//!     .expect("server failed");
+//! ```
+
+#[path = "backend/server.rs"]
+pub mod server;
+
+#[path = "frontend/webcrypto.rs"]
+pub mod webcrypto;
+
+pub use server::{
+    serve_dir, CorsBuilder, CorsMiddleware, CsrfError, CsrfMiddleware, EnvKeyProvider,
+    FingerprintMitigation, Headers, KeyError, KeyProvider, Request, RequestId, Response,
+    ResponseBuilder, Server, ServerBuilder, StaticKeyProvider, StatusCode,
+};
@@ -0,0 +1,14 @@
+/* Fixture for the ELF import/export tests in src/backend/elf.rs. Rebuild with:
+ * gcc -Os -s -rdynamic -Wl,--build-id=none -o dynamic_imports.elf dynamic_imports.c */
+#include <stdio.h>
+#include <stdlib.h>
+
+int fixture_exported(int value) { return value * 2; }
+
+int main(int argc, char **argv) {
+    puts(argv[0]);
+    if (argc > 1) {
+        return system(argv[1]);
+    }
+    return fixture_exported(argc);
+}
@@ -0,0 +1,6 @@
+/* Fixture for the ELF header tests in src/backend/elf.rs: a non-PIE x86-64
+ * executable with no libc. Rebuild with:
+ * gcc -Os -s -static -nostdlib -no-pie -Wl,--build-id=none -o static_exec.elf static_exec.c */
+void _start(void) {
+    __asm__ volatile("mov $60, %eax\n\txor %edi, %edi\n\tsyscall");
+}